@@ -0,0 +1,1334 @@
+//! しきい値アラームのルールエンジン — 任意の読み取り値に対して上限・下限・帯域ルールを評価する。
+//!
+//! - ルール: [`AlarmRule`] は読み取り型 `R` から `i32` を取り出す関数と [`AlarmCondition`] の組
+//! - ヒステリシス: 発報後は `hysteresis` だけ内側へ戻るまで解除しない
+//! - デバウンス: 条件が `debounce_ms` 続いてから発報する (瞬間的なスパイクを無視)
+//! - ラッチ: `latching` なルールは条件が消えても [`AlarmEngine::acknowledge`] まで保持する
+//! - 出力: [`AlarmOutput`] を実装したブザー / LED 点滅 / 表示バナー / イベントキューへ配る
+//!
+//! 1 つのエンジンで複数センサを扱う場合は [`SensorSample`] と [`metric`] の抽出関数を使います。
+//! 抽出関数が `None` を返したルールはその読み取りでは評価されません。
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::alarm::{metric, AlarmEngine, AlarmEventQueue, AlarmRule, AlarmTransition, SensorSample};
+//! use hal_api::gas::GasReading;
+//!
+//! let mut engine: AlarmEngine<SensorSample, 4> = AlarmEngine::new();
+//! engine
+//!     .add_rule(AlarmRule {
+//!         hysteresis: 100,
+//!         ..AlarmRule::rising(1, "co2-high", metric::co2_ppm, 1000)
+//!     })
+//!     .unwrap();
+//!
+//! let mut queue: AlarmEventQueue<8> = AlarmEventQueue::new();
+//! let report = engine.evaluate(&GasReading::new(1200, 0).into(), 0);
+//! report.dispatch(&mut queue, 0).unwrap();
+//!
+//! let event = queue.pop().unwrap();
+//! assert_eq!(event.transition, AlarmTransition::Raised);
+//! assert_eq!(event.value, 1200);
+//!
+//! // 950 ppm はしきい値未満だがヒステリシス (900 ppm) の内側なので解除されない
+//! assert!(engine.evaluate(&GasReading::new(950, 0).into(), 1000).events().is_empty());
+//! ```
+
+use core::convert::Infallible;
+use core::fmt::Write as _;
+
+use hal_api::display::{TextDisplay16x2, TextFrame16x2};
+use hal_api::distance::DistanceReading;
+use hal_api::gas::GasReading;
+use hal_api::gpio::OutputPin;
+use hal_api::imu::ImuReading;
+use hal_api::light::LightReading;
+use hal_api::sensor::EnvReading;
+use heapless::{Deque, String, Vec};
+
+#[cfg(test)]
+extern crate std;
+
+/// アラームの重要度。`Ord` は `Info < Warning < Critical`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlarmSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl AlarmSeverity {
+    /// ログやダッシュボード向けの短いラベル。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlarmSeverity::Info => "info",
+            AlarmSeverity::Warning => "warn",
+            AlarmSeverity::Critical => "critical",
+        }
+    }
+}
+
+/// 発報条件。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlarmCondition {
+    /// 値が `threshold` を上回ったら発報 (rising)
+    Above(i32),
+    /// 値が `threshold` を下回ったら発報 (falling)
+    Below(i32),
+    /// 値が `[low, high]` の外に出たら発報
+    OutsideBand { low: i32, high: i32 },
+}
+
+impl AlarmCondition {
+    /// 発報条件を満たしているか。
+    pub fn is_tripped(self, value: i32) -> bool {
+        match self {
+            AlarmCondition::Above(threshold) => value > threshold,
+            AlarmCondition::Below(threshold) => value < threshold,
+            AlarmCondition::OutsideBand { low, high } => value < low || value > high,
+        }
+    }
+
+    /// ヒステリシス分だけ内側へ戻ったか。
+    pub fn is_recovered(self, value: i32, hysteresis: i32) -> bool {
+        let hysteresis = hysteresis.max(0);
+        match self {
+            AlarmCondition::Above(threshold) => value <= threshold.saturating_sub(hysteresis),
+            AlarmCondition::Below(threshold) => value >= threshold.saturating_add(hysteresis),
+            AlarmCondition::OutsideBand { low, high } => {
+                value >= low.saturating_add(hysteresis) && value <= high.saturating_sub(hysteresis)
+            }
+        }
+    }
+}
+
+/// 読み取り型 `R` に対するアラームルール。
+///
+/// `metric` は `R` から評価対象の値を取り出す。`None` を返した読み取りではルールを評価しない。
+/// 単位は抽出関数が決める (例: CO₂ は ppm、温度は 1/100 °C)。
+pub struct AlarmRule<R> {
+    pub id: u16,
+    pub name: &'static str,
+    pub severity: AlarmSeverity,
+    pub metric: fn(&R) -> Option<i32>,
+    pub condition: AlarmCondition,
+    /// 解除に必要な戻り幅 (`metric` と同じ単位)
+    pub hysteresis: i32,
+    /// 条件がこの時間続いてから発報する。0 なら即時
+    pub debounce_ms: u32,
+    /// `true` なら条件が消えても確認 (acknowledge) まで発報状態を保持する
+    pub latching: bool,
+}
+
+impl<R> AlarmRule<R> {
+    /// 条件を指定してルールを作る。重要度は `Warning`、ヒステリシス・デバウンスなし、非ラッチ。
+    pub fn new(
+        id: u16,
+        name: &'static str,
+        metric: fn(&R) -> Option<i32>,
+        condition: AlarmCondition,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            severity: AlarmSeverity::Warning,
+            metric,
+            condition,
+            hysteresis: 0,
+            debounce_ms: 0,
+            latching: false,
+        }
+    }
+
+    /// 値が `threshold` を上回ったら発報するルール。
+    pub fn rising(
+        id: u16,
+        name: &'static str,
+        metric: fn(&R) -> Option<i32>,
+        threshold: i32,
+    ) -> Self {
+        Self::new(id, name, metric, AlarmCondition::Above(threshold))
+    }
+
+    /// 値が `threshold` を下回ったら発報するルール。
+    pub fn falling(
+        id: u16,
+        name: &'static str,
+        metric: fn(&R) -> Option<i32>,
+        threshold: i32,
+    ) -> Self {
+        Self::new(id, name, metric, AlarmCondition::Below(threshold))
+    }
+
+    /// 値が `[low, high]` の外に出たら発報するルール。
+    pub fn outside_band(
+        id: u16,
+        name: &'static str,
+        metric: fn(&R) -> Option<i32>,
+        low: i32,
+        high: i32,
+    ) -> Self {
+        Self::new(id, name, metric, AlarmCondition::OutsideBand { low, high })
+    }
+}
+
+// `derive` だと `R: Clone` を要求してしまうので手で実装する
+impl<R> Clone for AlarmRule<R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R> Copy for AlarmRule<R> {}
+
+impl<R> core::fmt::Debug for AlarmRule<R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AlarmRule")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("severity", &self.severity)
+            .field("condition", &self.condition)
+            .field("hysteresis", &self.hysteresis)
+            .field("debounce_ms", &self.debounce_ms)
+            .field("latching", &self.latching)
+            .finish()
+    }
+}
+
+/// ルールごとの状態。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlarmState {
+    /// 条件を満たしていない
+    Normal,
+    /// 条件を満たしているがデバウンス待ち
+    Pending,
+    /// 発報中
+    Active,
+    /// 条件は解消したが確認待ちで保持中 (ラッチ)
+    Latched,
+}
+
+impl AlarmState {
+    /// 利用者に見える「発報中」か (`Active` と `Latched`)。
+    pub fn is_raised(self) -> bool {
+        matches!(self, AlarmState::Active | AlarmState::Latched)
+    }
+}
+
+/// 状態遷移の種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlarmTransition {
+    Raised,
+    Cleared,
+    Acknowledged,
+}
+
+impl AlarmTransition {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlarmTransition::Raised => "raised",
+            AlarmTransition::Cleared => "cleared",
+            AlarmTransition::Acknowledged => "acknowledged",
+        }
+    }
+}
+
+/// 出力へ配られる遷移イベント。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlarmEvent {
+    pub rule_id: u16,
+    pub name: &'static str,
+    pub severity: AlarmSeverity,
+    pub transition: AlarmTransition,
+    /// 遷移時点の最新値
+    pub value: i32,
+    pub at_ms: u32,
+}
+
+/// 発報中のアラームのうち最も重要なもの。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveAlarm {
+    pub rule_id: u16,
+    pub name: &'static str,
+    pub severity: AlarmSeverity,
+    pub value: i32,
+    pub acknowledged: bool,
+}
+
+/// エンジン全体の集計。出力はこれを見てブザーや LED を駆動する。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AlarmStatus {
+    /// 発報中 (`Active` + `Latched`) のルール数
+    pub active: u8,
+    /// そのうち未確認のルール数
+    pub unacknowledged: u8,
+    /// 未確認アラームの最大重要度
+    pub highest_unacknowledged: Option<AlarmSeverity>,
+    /// 最も重要な発報中アラーム (同じ重要度なら先に登録されたルール)
+    pub top: Option<ActiveAlarm>,
+}
+
+impl AlarmStatus {
+    /// 発報中アラームの最大重要度。
+    pub fn highest(&self) -> Option<AlarmSeverity> {
+        self.top.map(|alarm| alarm.severity)
+    }
+}
+
+/// 1 回の評価 / 確認で起きた遷移と、その後の集計。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlarmReport<const N: usize> {
+    events: Vec<AlarmEvent, N>,
+    status: AlarmStatus,
+}
+
+impl<const N: usize> AlarmReport<N> {
+    pub fn events(&self) -> &[AlarmEvent] {
+        &self.events
+    }
+
+    pub fn status(&self) -> &AlarmStatus {
+        &self.status
+    }
+
+    /// 遷移と集計を出力へ渡す。
+    pub fn dispatch<O>(&self, output: &mut O, now_ms: u32) -> Result<(), O::Error>
+    where
+        O: AlarmOutput,
+    {
+        output.update(&self.events, &self.status, now_ms)
+    }
+}
+
+/// アラームの出力先。
+///
+/// `update` は評価のたびに呼ばれる。`events` が空でも `status` と `now_ms` で
+/// LED の点滅などを進められるよう、読み取りの合間にも
+/// `output.update(&[], &engine.status(), now_ms)` を呼んでよい。
+pub trait AlarmOutput {
+    type Error;
+
+    fn update(
+        &mut self,
+        events: &[AlarmEvent],
+        status: &AlarmStatus,
+        now_ms: u32,
+    ) -> Result<(), Self::Error>;
+}
+
+struct RuleSlot<R> {
+    rule: AlarmRule<R>,
+    state: AlarmState,
+    pending_since_ms: u32,
+    last_value: Option<i32>,
+    acknowledged: bool,
+}
+
+/// 最大 `N` 個のルールを保持して評価するエンジン。
+pub struct AlarmEngine<R, const N: usize> {
+    slots: Vec<RuleSlot<R>, N>,
+}
+
+impl<R, const N: usize> Default for AlarmEngine<R, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R, const N: usize> AlarmEngine<R, N> {
+    pub fn new() -> Self {
+        Self { slots: Vec::new() }
+    }
+
+    /// ルールを追加する。満杯なら渡したルールを `Err` で返す。
+    pub fn add_rule(&mut self, rule: AlarmRule<R>) -> Result<(), AlarmRule<R>> {
+        self.slots
+            .push(RuleSlot {
+                rule,
+                state: AlarmState::Normal,
+                pending_since_ms: 0,
+                last_value: None,
+                acknowledged: false,
+            })
+            .map_err(|slot| slot.rule)
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &AlarmRule<R>> {
+        self.slots.iter().map(|slot| &slot.rule)
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// ルールの状態。未登録の ID なら `None`。
+    pub fn state(&self, rule_id: u16) -> Option<AlarmState> {
+        self.slot(rule_id).map(|slot| slot.state)
+    }
+
+    /// ルールが最後に評価した値。
+    pub fn last_value(&self, rule_id: u16) -> Option<i32> {
+        self.slot(rule_id).and_then(|slot| slot.last_value)
+    }
+
+    /// 読み取り 1 件ですべてのルールを評価する。
+    ///
+    /// `now_ms` は単調増加するミリ秒カウンタ。桁あふれは `wrapping_sub` で扱う。
+    pub fn evaluate(&mut self, reading: &R, now_ms: u32) -> AlarmReport<N> {
+        let mut events = Vec::new();
+        for slot in self.slots.iter_mut() {
+            let Some(value) = (slot.rule.metric)(reading) else {
+                continue;
+            };
+            slot.last_value = Some(value);
+            if let Some(transition) = Self::step(slot, value, now_ms) {
+                // ルール 1 つにつき遷移は高々 1 件なので容量 N を超えない
+                let _ = events.push(Self::event(slot, transition, value, now_ms));
+            }
+        }
+        AlarmReport {
+            events,
+            status: self.status(),
+        }
+    }
+
+    /// アラームを確認する。
+    ///
+    /// 発報中なら `Acknowledged` を出す。ラッチ中 (条件解消済み) なら確認で解除され `Cleared` を出す。
+    /// 条件が続いている `Active` なアラームは、値が戻った時点で `Cleared` になる。
+    pub fn acknowledge(&mut self, rule_id: u16, now_ms: u32) -> AlarmReport<N> {
+        let mut events = Vec::new();
+        if let Some(slot) = self.slots.iter_mut().find(|slot| slot.rule.id == rule_id) {
+            Self::acknowledge_slot(slot, now_ms, &mut events);
+        }
+        AlarmReport {
+            events,
+            status: self.status(),
+        }
+    }
+
+    /// 発報中のすべてのアラームを確認する。
+    pub fn acknowledge_all(&mut self, now_ms: u32) -> AlarmReport<N> {
+        let mut events = Vec::new();
+        for slot in self.slots.iter_mut() {
+            Self::acknowledge_slot(slot, now_ms, &mut events);
+        }
+        AlarmReport {
+            events,
+            status: self.status(),
+        }
+    }
+
+    /// 現在の集計。
+    pub fn status(&self) -> AlarmStatus {
+        let mut status = AlarmStatus::default();
+        for slot in self.slots.iter().filter(|slot| slot.state.is_raised()) {
+            status.active = status.active.saturating_add(1);
+            if !slot.acknowledged {
+                status.unacknowledged = status.unacknowledged.saturating_add(1);
+                status.highest_unacknowledged =
+                    status.highest_unacknowledged.max(Some(slot.rule.severity));
+            }
+            let is_higher = status
+                .top
+                .map_or(true, |top| slot.rule.severity > top.severity);
+            if is_higher {
+                status.top = Some(ActiveAlarm {
+                    rule_id: slot.rule.id,
+                    name: slot.rule.name,
+                    severity: slot.rule.severity,
+                    value: slot.last_value.unwrap_or_default(),
+                    acknowledged: slot.acknowledged,
+                });
+            }
+        }
+        status
+    }
+
+    fn slot(&self, rule_id: u16) -> Option<&RuleSlot<R>> {
+        self.slots.iter().find(|slot| slot.rule.id == rule_id)
+    }
+
+    fn step(slot: &mut RuleSlot<R>, value: i32, now_ms: u32) -> Option<AlarmTransition> {
+        let rule = &slot.rule;
+        let tripped = rule.condition.is_tripped(value);
+        match slot.state {
+            AlarmState::Normal if tripped => {
+                if rule.debounce_ms == 0 {
+                    return Some(Self::raise(slot));
+                }
+                slot.state = AlarmState::Pending;
+                slot.pending_since_ms = now_ms;
+                None
+            }
+            AlarmState::Normal => None,
+            AlarmState::Pending if !tripped => {
+                slot.state = AlarmState::Normal;
+                None
+            }
+            AlarmState::Pending => {
+                if now_ms.wrapping_sub(slot.pending_since_ms) >= rule.debounce_ms {
+                    Some(Self::raise(slot))
+                } else {
+                    None
+                }
+            }
+            AlarmState::Active if rule.condition.is_recovered(value, rule.hysteresis) => {
+                if rule.latching && !slot.acknowledged {
+                    slot.state = AlarmState::Latched;
+                    None
+                } else {
+                    slot.state = AlarmState::Normal;
+                    Some(AlarmTransition::Cleared)
+                }
+            }
+            AlarmState::Active => None,
+            AlarmState::Latched => {
+                // 確認前に再び条件を満たしたら発報中へ戻す (イベントは出さない)
+                if tripped {
+                    slot.state = AlarmState::Active;
+                }
+                None
+            }
+        }
+    }
+
+    fn raise(slot: &mut RuleSlot<R>) -> AlarmTransition {
+        slot.state = AlarmState::Active;
+        slot.acknowledged = false;
+        AlarmTransition::Raised
+    }
+
+    fn acknowledge_slot(slot: &mut RuleSlot<R>, now_ms: u32, events: &mut Vec<AlarmEvent, N>) {
+        if !slot.state.is_raised() || slot.acknowledged {
+            return;
+        }
+        let value = slot.last_value.unwrap_or_default();
+        slot.acknowledged = true;
+        // ラッチ中なら確認と同時に解除する。イベントはルールごとに 1 件に収める
+        let transition = if slot.state == AlarmState::Latched {
+            slot.state = AlarmState::Normal;
+            AlarmTransition::Cleared
+        } else {
+            AlarmTransition::Acknowledged
+        };
+        let _ = events.push(Self::event(slot, transition, value, now_ms));
+    }
+
+    fn event(
+        slot: &RuleSlot<R>,
+        transition: AlarmTransition,
+        value: i32,
+        now_ms: u32,
+    ) -> AlarmEvent {
+        AlarmEvent {
+            rule_id: slot.rule.id,
+            name: slot.rule.name,
+            severity: slot.rule.severity,
+            transition,
+            value,
+            at_ms: now_ms,
+        }
+    }
+}
+
+/// 複数センサを 1 つのエンジンで扱うための読み取り値。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorSample {
+    Env(EnvReading),
+    Gas(GasReading),
+    Distance(DistanceReading),
+    Light(LightReading),
+    Imu(ImuReading),
+}
+
+impl From<EnvReading> for SensorSample {
+    fn from(reading: EnvReading) -> Self {
+        SensorSample::Env(reading)
+    }
+}
+
+impl From<GasReading> for SensorSample {
+    fn from(reading: GasReading) -> Self {
+        SensorSample::Gas(reading)
+    }
+}
+
+impl From<DistanceReading> for SensorSample {
+    fn from(reading: DistanceReading) -> Self {
+        SensorSample::Distance(reading)
+    }
+}
+
+impl From<LightReading> for SensorSample {
+    fn from(reading: LightReading) -> Self {
+        SensorSample::Light(reading)
+    }
+}
+
+impl From<ImuReading> for SensorSample {
+    fn from(reading: ImuReading) -> Self {
+        SensorSample::Imu(reading)
+    }
+}
+
+/// [`SensorSample`] 用の抽出関数。[`AlarmRule::metric`] にそのまま渡せる。
+pub mod metric {
+    use super::SensorSample;
+
+    /// 温度 (1/100 °C)。IMU の内蔵温度計は対象外。
+    pub fn temperature_centi_celsius(sample: &SensorSample) -> Option<i32> {
+        match sample {
+            SensorSample::Env(reading) => Some(reading.temperature_centi_celsius),
+            _ => None,
+        }
+    }
+
+    /// 相対湿度 (1/100 %RH)。
+    pub fn humidity_centi_percent(sample: &SensorSample) -> Option<i32> {
+        match sample {
+            SensorSample::Env(reading) => Some(saturate(reading.humidity_centi_percent)),
+            _ => None,
+        }
+    }
+
+    /// CO₂ 濃度 (ppm)。
+    pub fn co2_ppm(sample: &SensorSample) -> Option<i32> {
+        match sample {
+            SensorSample::Gas(reading) => Some(i32::from(reading.co2_ppm)),
+            _ => None,
+        }
+    }
+
+    /// VOC 濃度 (ppb)。
+    pub fn voc_ppb(sample: &SensorSample) -> Option<i32> {
+        match sample {
+            SensorSample::Gas(reading) => Some(i32::from(reading.voc_ppb)),
+            _ => None,
+        }
+    }
+
+    /// 距離 (mm)。
+    pub fn distance_mm(sample: &SensorSample) -> Option<i32> {
+        match sample {
+            SensorSample::Distance(reading) => Some(saturate(reading.distance_mm)),
+            _ => None,
+        }
+    }
+
+    /// 照度 (lux の 100 倍)。
+    pub fn lux_x100(sample: &SensorSample) -> Option<i32> {
+        match sample {
+            SensorSample::Light(reading) => Some(saturate(reading.lux_x100)),
+            _ => None,
+        }
+    }
+
+    /// 加速度の最大軸絶対値 (milli-g)。衝撃・転倒検知向け。
+    pub fn peak_accel_mg(sample: &SensorSample) -> Option<i32> {
+        match sample {
+            SensorSample::Imu(reading) => reading
+                .accel_mg
+                .iter()
+                .map(|axis| i32::from(*axis).abs())
+                .max(),
+            _ => None,
+        }
+    }
+
+    fn saturate(value: u32) -> i32 {
+        i32::try_from(value).unwrap_or(i32::MAX)
+    }
+}
+
+/// 未確認アラームがある間ブザーを鳴らす出力。
+///
+/// `min_severity` 未満のアラームでは鳴らさない。確認 (acknowledge) すると止まる。
+pub struct BuzzerOutput<P> {
+    pin: P,
+    min_severity: AlarmSeverity,
+    sounding: Option<bool>,
+}
+
+impl<P> BuzzerOutput<P>
+where
+    P: OutputPin,
+{
+    pub fn new(pin: P, min_severity: AlarmSeverity) -> Self {
+        Self {
+            pin,
+            min_severity,
+            sounding: None,
+        }
+    }
+
+    pub fn is_sounding(&self) -> bool {
+        self.sounding.unwrap_or(false)
+    }
+
+    pub fn into_inner(self) -> P {
+        self.pin
+    }
+}
+
+impl<P> AlarmOutput for BuzzerOutput<P>
+where
+    P: OutputPin,
+{
+    type Error = P::Error;
+
+    fn update(
+        &mut self,
+        _events: &[AlarmEvent],
+        status: &AlarmStatus,
+        _now_ms: u32,
+    ) -> Result<(), Self::Error> {
+        let sound = status
+            .highest_unacknowledged
+            .is_some_and(|severity| severity >= self.min_severity);
+        // 状態が変わったときだけピンへ書く
+        if self.sounding != Some(sound) {
+            self.pin.set(sound)?;
+            self.sounding = Some(sound);
+        }
+        Ok(())
+    }
+}
+
+/// LED 点滅パターン。`period_ms` 周期のうち先頭 `on_ms` だけ点灯する。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlinkPattern {
+    pub on_ms: u32,
+    pub period_ms: u32,
+}
+
+impl BlinkPattern {
+    pub const fn new(on_ms: u32, period_ms: u32) -> Self {
+        Self { on_ms, period_ms }
+    }
+
+    /// `now_ms` 時点で点灯しているか。
+    pub fn is_on(self, now_ms: u32) -> bool {
+        now_ms % self.period_ms.max(1) < self.on_ms
+    }
+}
+
+/// 最大重要度に応じたパターンで LED を点滅させる出力。
+///
+/// 既定パターン: Info は 2 秒ごとに短く点灯、Warning は 1 Hz、Critical は 5 Hz。
+pub struct BlinkLedOutput<P> {
+    pin: P,
+    patterns: [BlinkPattern; 3],
+    lit: Option<bool>,
+}
+
+impl<P> BlinkLedOutput<P>
+where
+    P: OutputPin,
+{
+    pub const DEFAULT_PATTERNS: [BlinkPattern; 3] = [
+        BlinkPattern::new(100, 2000),
+        BlinkPattern::new(500, 1000),
+        BlinkPattern::new(100, 200),
+    ];
+
+    pub fn new(pin: P) -> Self {
+        Self::new_with_patterns(pin, Self::DEFAULT_PATTERNS)
+    }
+
+    /// `patterns` は Info / Warning / Critical の順。
+    pub fn new_with_patterns(pin: P, patterns: [BlinkPattern; 3]) -> Self {
+        Self {
+            pin,
+            patterns,
+            lit: None,
+        }
+    }
+
+    pub fn is_lit(&self) -> bool {
+        self.lit.unwrap_or(false)
+    }
+
+    pub fn into_inner(self) -> P {
+        self.pin
+    }
+
+    fn pattern(&self, severity: AlarmSeverity) -> BlinkPattern {
+        match severity {
+            AlarmSeverity::Info => self.patterns[0],
+            AlarmSeverity::Warning => self.patterns[1],
+            AlarmSeverity::Critical => self.patterns[2],
+        }
+    }
+}
+
+impl<P> AlarmOutput for BlinkLedOutput<P>
+where
+    P: OutputPin,
+{
+    type Error = P::Error;
+
+    fn update(
+        &mut self,
+        _events: &[AlarmEvent],
+        status: &AlarmStatus,
+        now_ms: u32,
+    ) -> Result<(), Self::Error> {
+        // 確認済みでも発報中なら点滅を続ける (ブザーとは違い視覚表示は残す)
+        let lit = status
+            .highest()
+            .is_some_and(|severity| self.pattern(severity).is_on(now_ms));
+        if self.lit != Some(lit) {
+            self.pin.set(lit)?;
+            self.lit = Some(lit);
+        }
+        Ok(())
+    }
+}
+
+/// 最も重要な発報中アラームを 16x2 表示へバナーとして描く出力。
+///
+/// 表示内容が変わったときだけ描画する。全アラームが解除されたら "ALARMS CLEAR" を描く。
+pub struct AlarmBannerOutput<D> {
+    display: D,
+    last_frame: Option<TextFrame16x2>,
+}
+
+impl<D> AlarmBannerOutput<D>
+where
+    D: TextDisplay16x2,
+{
+    pub fn new(display: D) -> Self {
+        Self {
+            display,
+            last_frame: None,
+        }
+    }
+
+    pub fn last_frame(&self) -> Option<&TextFrame16x2> {
+        self.last_frame.as_ref()
+    }
+
+    pub fn into_inner(self) -> D {
+        self.display
+    }
+}
+
+impl<D> AlarmOutput for AlarmBannerOutput<D>
+where
+    D: TextDisplay16x2,
+{
+    type Error = D::Error;
+
+    fn update(
+        &mut self,
+        events: &[AlarmEvent],
+        status: &AlarmStatus,
+        _now_ms: u32,
+    ) -> Result<(), Self::Error> {
+        // まだ何も発報していなければ他のアプリの表示を上書きしない
+        if self.last_frame.is_none() && status.top.is_none() && events.is_empty() {
+            return Ok(());
+        }
+        let frame = alarm_banner(status);
+        if self.last_frame != Some(frame) {
+            self.display.render(&frame)?;
+            self.last_frame = Some(frame);
+        }
+        Ok(())
+    }
+}
+
+/// バナー表示用のフレームを作る。
+///
+/// - 1 行目: `"WARN co2-high"` (重要度 + ルール名)
+/// - 2 行目: `"= 1234 +1"` (最新値 + 他の発報中件数)、確認済みなら末尾に `ACK`
+pub fn alarm_banner(status: &AlarmStatus) -> TextFrame16x2 {
+    let Some(top) = status.top else {
+        return TextFrame16x2::from_lines("ALARMS CLEAR", "");
+    };
+    let label = match top.severity {
+        AlarmSeverity::Info => "INFO",
+        AlarmSeverity::Warning => "WARN",
+        AlarmSeverity::Critical => "CRIT",
+    };
+    let mut line1: String<17> = String::new();
+    let mut line2: String<17> = String::new();
+    let _ = write!(line1, "{} {}", label, top.name);
+    let _ = write!(line2, "= {}", top.value);
+    if status.active > 1 {
+        let _ = write!(line2, " +{}", status.active - 1);
+    }
+    if top.acknowledged {
+        let _ = write!(line2, " ACK");
+    }
+    TextFrame16x2::from_lines(&line1, &line2)
+}
+
+/// 遷移イベントを溜めておく出力。満杯なら最も古いイベントを捨てる。
+///
+/// ログ出力やダッシュボードなど、後段で `pop` して処理する用途向け。
+pub struct AlarmEventQueue<const N: usize> {
+    events: Deque<AlarmEvent, N>,
+    dropped: u32,
+}
+
+impl<const N: usize> Default for AlarmEventQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> AlarmEventQueue<N> {
+    pub fn new() -> Self {
+        Self {
+            events: Deque::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<AlarmEvent> {
+        self.events.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// 満杯のために捨てたイベント数。
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+impl<const N: usize> AlarmOutput for AlarmEventQueue<N> {
+    type Error = Infallible;
+
+    fn update(
+        &mut self,
+        events: &[AlarmEvent],
+        _status: &AlarmStatus,
+        _now_ms: u32,
+    ) -> Result<(), Self::Error> {
+        for event in events {
+            if self.events.is_full() {
+                self.events.pop_front();
+                self.dropped = self.dropped.wrapping_add(1);
+            }
+            let _ = self.events.push_back(*event);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hal_api::error::{DisplayError, GpioError};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec::Vec as StdVec;
+
+    #[derive(Clone, Default)]
+    struct MockPin {
+        history: Rc<RefCell<StdVec<bool>>>,
+    }
+
+    impl MockPin {
+        fn history(&self) -> StdVec<bool> {
+            self.history.borrow().clone()
+        }
+    }
+
+    impl OutputPin for MockPin {
+        type Error = GpioError;
+
+        fn set_high(&mut self) -> Result<(), GpioError> {
+            self.history.borrow_mut().push(true);
+            Ok(())
+        }
+
+        fn set_low(&mut self) -> Result<(), GpioError> {
+            self.history.borrow_mut().push(false);
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct MockDisplay {
+        frames: Rc<RefCell<StdVec<TextFrame16x2>>>,
+    }
+
+    impl TextDisplay16x2 for MockDisplay {
+        type Error = DisplayError;
+
+        fn render(&mut self, frame: &TextFrame16x2) -> Result<(), DisplayError> {
+            self.frames.borrow_mut().push(*frame);
+            Ok(())
+        }
+    }
+
+    fn gas(co2_ppm: u16) -> SensorSample {
+        GasReading::new(co2_ppm, 0).into()
+    }
+
+    fn distance(distance_mm: u32) -> SensorSample {
+        DistanceReading::new(distance_mm).into()
+    }
+
+    fn climate(temperature_centi_celsius: i32) -> SensorSample {
+        EnvReading::new(temperature_centi_celsius, 4000, None).into()
+    }
+
+    fn transitions<const N: usize>(report: &AlarmReport<N>) -> StdVec<(u16, AlarmTransition)> {
+        report
+            .events()
+            .iter()
+            .map(|event| (event.rule_id, event.transition))
+            .collect()
+    }
+
+    #[test]
+    fn rising_rule_uses_hysteresis_to_clear() {
+        let mut engine: AlarmEngine<SensorSample, 2> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule {
+                hysteresis: 100,
+                ..AlarmRule::rising(1, "co2-high", metric::co2_ppm, 1000)
+            })
+            .unwrap();
+
+        assert!(engine.evaluate(&gas(1000), 0).events().is_empty());
+        let report = engine.evaluate(&gas(1001), 10);
+        assert_eq!(transitions(&report), [(1, AlarmTransition::Raised)]);
+        assert_eq!(report.status().active, 1);
+
+        assert!(engine.evaluate(&gas(950), 20).events().is_empty());
+        assert_eq!(engine.state(1), Some(AlarmState::Active));
+        let report = engine.evaluate(&gas(900), 30);
+        assert_eq!(transitions(&report), [(1, AlarmTransition::Cleared)]);
+        assert_eq!(report.status().active, 0);
+    }
+
+    #[test]
+    fn falling_rule_and_band_rule() {
+        let mut engine: AlarmEngine<SensorSample, 2> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule::falling(1, "too-close", metric::distance_mm, 150))
+            .unwrap();
+        engine
+            .add_rule(AlarmRule {
+                hysteresis: 50,
+                ..AlarmRule::outside_band(
+                    2,
+                    "temp-band",
+                    metric::temperature_centi_celsius,
+                    1800,
+                    2800,
+                )
+            })
+            .unwrap();
+
+        let report = engine.evaluate(&distance(120), 0);
+        assert_eq!(transitions(&report), [(1, AlarmTransition::Raised)]);
+
+        let report = engine.evaluate(&climate(1790), 10);
+        assert_eq!(transitions(&report), [(2, AlarmTransition::Raised)]);
+        // 下限 + ヒステリシス (18.50 °C) まで戻るまでは解除しない
+        assert!(engine.evaluate(&climate(1820), 20).events().is_empty());
+        let report = engine.evaluate(&climate(1850), 30);
+        assert_eq!(transitions(&report), [(2, AlarmTransition::Cleared)]);
+
+        // 距離ルールは温度の読み取りでは評価されない
+        assert_eq!(engine.state(1), Some(AlarmState::Active));
+        assert_eq!(engine.last_value(1), Some(120));
+    }
+
+    #[test]
+    fn debounce_ignores_short_spikes() {
+        let mut engine: AlarmEngine<SensorSample, 1> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule {
+                debounce_ms: 500,
+                ..AlarmRule::rising(1, "co2-high", metric::co2_ppm, 1000)
+            })
+            .unwrap();
+
+        assert!(engine.evaluate(&gas(1500), 0).events().is_empty());
+        assert_eq!(engine.state(1), Some(AlarmState::Pending));
+        assert!(engine.evaluate(&gas(800), 300).events().is_empty());
+        assert_eq!(engine.state(1), Some(AlarmState::Normal));
+
+        assert!(engine.evaluate(&gas(1500), 1000).events().is_empty());
+        assert!(engine.evaluate(&gas(1500), 1499).events().is_empty());
+        let report = engine.evaluate(&gas(1500), 1500);
+        assert_eq!(transitions(&report), [(1, AlarmTransition::Raised)]);
+    }
+
+    #[test]
+    fn debounce_survives_clock_wrap() {
+        let mut engine: AlarmEngine<SensorSample, 1> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule {
+                debounce_ms: 100,
+                ..AlarmRule::rising(1, "co2-high", metric::co2_ppm, 1000)
+            })
+            .unwrap();
+
+        engine.evaluate(&gas(1500), u32::MAX - 49);
+        let report = engine.evaluate(&gas(1500), 50);
+        assert_eq!(transitions(&report), [(1, AlarmTransition::Raised)]);
+    }
+
+    #[test]
+    fn latching_rule_holds_until_acknowledged() {
+        let mut engine: AlarmEngine<SensorSample, 1> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule {
+                latching: true,
+                ..AlarmRule::falling(7, "too-close", metric::distance_mm, 100)
+            })
+            .unwrap();
+
+        engine.evaluate(&distance(50), 0);
+        assert!(engine.evaluate(&distance(400), 10).events().is_empty());
+        assert_eq!(engine.state(7), Some(AlarmState::Latched));
+        assert_eq!(engine.status().active, 1);
+
+        let report = engine.acknowledge(7, 20);
+        assert_eq!(transitions(&report), [(7, AlarmTransition::Cleared)]);
+        assert_eq!(engine.state(7), Some(AlarmState::Normal));
+    }
+
+    #[test]
+    fn acknowledged_active_alarm_clears_when_value_recovers() {
+        let mut engine: AlarmEngine<SensorSample, 1> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule {
+                latching: true,
+                ..AlarmRule::rising(1, "co2-high", metric::co2_ppm, 1000)
+            })
+            .unwrap();
+
+        engine.evaluate(&gas(1200), 0);
+        let report = engine.acknowledge_all(10);
+        assert_eq!(transitions(&report), [(1, AlarmTransition::Acknowledged)]);
+        assert_eq!(report.status().unacknowledged, 0);
+        assert!(engine.acknowledge(1, 20).events().is_empty());
+
+        let report = engine.evaluate(&gas(600), 30);
+        assert_eq!(transitions(&report), [(1, AlarmTransition::Cleared)]);
+    }
+
+    #[test]
+    fn status_reports_highest_severity() {
+        let mut engine: AlarmEngine<SensorSample, 2> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule::rising(1, "co2-high", metric::co2_ppm, 1000))
+            .unwrap();
+        engine
+            .add_rule(AlarmRule {
+                severity: AlarmSeverity::Critical,
+                ..AlarmRule::falling(2, "too-close", metric::distance_mm, 100)
+            })
+            .unwrap();
+
+        engine.evaluate(&gas(1200), 0);
+        engine.evaluate(&distance(40), 10);
+        let status = engine.status();
+        assert_eq!(status.active, 2);
+        assert_eq!(status.highest(), Some(AlarmSeverity::Critical));
+        assert_eq!(status.top.unwrap().rule_id, 2);
+        assert_eq!(status.top.unwrap().value, 40);
+    }
+
+    #[test]
+    fn rules_can_target_plain_reading_types() {
+        let mut engine: AlarmEngine<GasReading, 1> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule::rising(
+                1,
+                "voc-high",
+                |reading: &GasReading| Some(i32::from(reading.voc_ppb)),
+                500,
+            ))
+            .unwrap();
+
+        let report = engine.evaluate(&GasReading::new(400, 700), 0);
+        assert_eq!(transitions(&report), [(1, AlarmTransition::Raised)]);
+    }
+
+    #[test]
+    fn add_rule_returns_rule_when_full() {
+        let mut engine: AlarmEngine<SensorSample, 1> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule::rising(1, "a", metric::co2_ppm, 1))
+            .unwrap();
+        let rejected = engine
+            .add_rule(AlarmRule::rising(2, "b", metric::co2_ppm, 1))
+            .unwrap_err();
+        assert_eq!(rejected.id, 2);
+        assert_eq!(engine.rule_count(), 1);
+    }
+
+    #[test]
+    fn buzzer_sounds_until_acknowledged() {
+        let pin = MockPin::default();
+        let mut buzzer = BuzzerOutput::new(pin.clone(), AlarmSeverity::Warning);
+        let mut engine: AlarmEngine<SensorSample, 1> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule::rising(1, "co2-high", metric::co2_ppm, 1000))
+            .unwrap();
+
+        engine
+            .evaluate(&gas(400), 0)
+            .dispatch(&mut buzzer, 0)
+            .unwrap();
+        engine
+            .evaluate(&gas(1200), 10)
+            .dispatch(&mut buzzer, 10)
+            .unwrap();
+        engine
+            .evaluate(&gas(1300), 20)
+            .dispatch(&mut buzzer, 20)
+            .unwrap();
+        assert!(buzzer.is_sounding());
+        engine.acknowledge(1, 30).dispatch(&mut buzzer, 30).unwrap();
+        assert!(!buzzer.is_sounding());
+        assert_eq!(pin.history(), [false, true, false]);
+    }
+
+    #[test]
+    fn buzzer_ignores_low_severity() {
+        let pin = MockPin::default();
+        let mut buzzer = BuzzerOutput::new(pin.clone(), AlarmSeverity::Critical);
+        let mut engine: AlarmEngine<SensorSample, 1> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule::rising(1, "co2-high", metric::co2_ppm, 1000))
+            .unwrap();
+
+        engine
+            .evaluate(&gas(1200), 0)
+            .dispatch(&mut buzzer, 0)
+            .unwrap();
+        assert!(!buzzer.is_sounding());
+    }
+
+    #[test]
+    fn led_blinks_faster_for_critical_alarms() {
+        let pin = MockPin::default();
+        let mut led = BlinkLedOutput::new(pin.clone());
+        let mut engine: AlarmEngine<SensorSample, 1> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule {
+                severity: AlarmSeverity::Critical,
+                ..AlarmRule::falling(1, "too-close", metric::distance_mm, 100)
+            })
+            .unwrap();
+
+        engine
+            .evaluate(&distance(50), 0)
+            .dispatch(&mut led, 0)
+            .unwrap();
+        let status = engine.status();
+        for now_ms in (50..=400).step_by(50) {
+            led.update(&[], &status, now_ms).unwrap();
+        }
+        // 100 ms ON / 100 ms OFF
+        assert_eq!(pin.history(), [true, false, true, false, true]);
+    }
+
+    #[test]
+    fn banner_renders_only_on_change() {
+        let display = MockDisplay::default();
+        let mut banner = AlarmBannerOutput::new(display.clone());
+        let mut engine: AlarmEngine<SensorSample, 1> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule::rising(1, "co2-high", metric::co2_ppm, 1000))
+            .unwrap();
+
+        engine
+            .evaluate(&gas(400), 0)
+            .dispatch(&mut banner, 0)
+            .unwrap();
+        assert!(display.frames.borrow().is_empty());
+
+        engine
+            .evaluate(&gas(1234), 10)
+            .dispatch(&mut banner, 10)
+            .unwrap();
+        engine
+            .evaluate(&gas(1234), 20)
+            .dispatch(&mut banner, 20)
+            .unwrap();
+        engine
+            .evaluate(&gas(500), 30)
+            .dispatch(&mut banner, 30)
+            .unwrap();
+
+        let frames = display.frames.borrow();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(
+            frames[0],
+            TextFrame16x2::from_lines("WARN co2-high", "= 1234")
+        );
+        assert_eq!(frames[1], TextFrame16x2::from_lines("ALARMS CLEAR", ""));
+    }
+
+    #[test]
+    fn banner_shows_extra_count_and_ack() {
+        let status = AlarmStatus {
+            active: 3,
+            unacknowledged: 0,
+            highest_unacknowledged: None,
+            top: Some(ActiveAlarm {
+                rule_id: 1,
+                name: "temp-band",
+                severity: AlarmSeverity::Critical,
+                value: 3120,
+                acknowledged: true,
+            }),
+        };
+        assert_eq!(
+            alarm_banner(&status),
+            TextFrame16x2::from_lines("CRIT temp-band", "= 3120 +2 ACK")
+        );
+    }
+
+    #[test]
+    fn event_queue_drops_oldest_when_full() {
+        let mut queue: AlarmEventQueue<2> = AlarmEventQueue::new();
+        let mut engine: AlarmEngine<SensorSample, 1> = AlarmEngine::new();
+        engine
+            .add_rule(AlarmRule::rising(1, "co2-high", metric::co2_ppm, 1000))
+            .unwrap();
+
+        for (index, co2) in [1200, 400, 1200].into_iter().enumerate() {
+            let now_ms = index as u32 * 10;
+            engine
+                .evaluate(&gas(co2), now_ms)
+                .dispatch(&mut queue, now_ms)
+                .unwrap();
+        }
+
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(queue.pop().unwrap().transition, AlarmTransition::Cleared);
+        let last = queue.pop().unwrap();
+        assert_eq!(last.transition, AlarmTransition::Raised);
+        assert_eq!(last.at_ms, 20);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn peak_accel_uses_largest_axis() {
+        let sample: SensorSample = ImuReading::new([100, -2500, 900], [0; 3], None).into();
+        assert_eq!(metric::peak_accel_mg(&sample), Some(2500));
+        assert_eq!(metric::co2_ppm(&sample), None);
+    }
+}
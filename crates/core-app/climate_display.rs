@@ -114,6 +114,11 @@ where
     pub fn config(&self) -> ClimateDisplayConfig {
         self.config
     }
+
+    /// 設定を差し替える。tick カウンタはそのままなので、次の周期境界から新しい周期で更新する。
+    pub fn set_config(&mut self, config: ClimateDisplayConfig) {
+        self.config = config;
+    }
 }
 
 pub fn frame_from_reading(reading: EnvReading) -> Result<TextFrame16x2, DisplayError> {
@@ -0,0 +1,1202 @@
+//! データロガーアプリ — 任意のセンサ読み取りにタイムスタンプを付けて `BlockStorage` へ循環記録する。
+//!
+//! - 記録: 固定長 [`RECORD_SIZE`] バイトの [`LogRecord`] をブロックストレージへ順に書く
+//! - 循環: 末尾まで書いたら先頭ブロックを消去して上書きする (最古のブロック分を捨てる)
+//! - 時刻: [`RtcClock`] (UNIX 秒) または [`MonotonicClock`] (起動からのミリ秒)
+//! - 電源断復旧: [`DataLog::mount`] が全スロットを走査し、CRC が正しい最大シーケンス番号の
+//!   次から書き込みを再開する。書きかけのレコードは CRC 不一致として読み飛ばす
+//! - 出力: [`DataLog::export_csv`] で `core::fmt::Write` (シリアルなど) へ CSV を書き出す
+//!
+//! `ImuLoggerApp` と違い RAM には保持しないので、容量はストレージの大きさだけで決まります。
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::data_logger::{DataLog, DataLoggerApp, DataLoggerConfig, MonotonicClock, SensorSource};
+//! use hal_api::sensor::{EnvReading, EnvSensor};
+//! use hal_api::storage::BlockStorage;
+//!
+//! struct Room;
+//! impl EnvSensor for Room {
+//!     type Error = ();
+//!     fn read(&mut self) -> Result<EnvReading, ()> {
+//!         Ok(EnvReading::new(2150, 4500, None))
+//!     }
+//! }
+//!
+//! struct Eeprom([u8; 256]);
+//! impl BlockStorage for Eeprom {
+//!     type Error = ();
+//!     fn block_size(&self) -> u32 { 128 }
+//!     fn block_count(&self) -> u32 { 2 }
+//!     fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), ()> {
+//!         let start = offset as usize;
+//!         buffer.copy_from_slice(&self.0[start..start + buffer.len()]);
+//!         Ok(())
+//!     }
+//!     fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), ()> {
+//!         let start = offset as usize;
+//!         self.0[start..start + data.len()].copy_from_slice(data);
+//!         Ok(())
+//!     }
+//!     fn erase_block(&mut self, block: u32) -> Result<(), ()> {
+//!         let start = block as usize * 128;
+//!         self.0[start..start + 128].fill(0xFF);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let log = DataLog::mount(Eeprom([0xFF; 256])).unwrap();
+//! let mut app = DataLoggerApp::new_with_config(
+//!     SensorSource::new(Room, EnvSensor::read),
+//!     log,
+//!     MonotonicClock::new(10),
+//!     DataLoggerConfig { sample_period_ticks: 100 },
+//! );
+//! for _ in 0..300 {
+//!     app.tick().unwrap();
+//! }
+//!
+//! let mut csv = heapless::String::<256>::new();
+//! app.log_mut().export_csv(&mut csv).unwrap();
+//! assert!(csv.starts_with("seq,time,channel,v0,v1,v2\n0,1.000,env,2150,4500,\n"));
+//! ```
+
+use core::fmt::Write;
+
+use hal_api::distance::DistanceReading;
+use hal_api::gas::GasReading;
+use hal_api::imu::ImuReading;
+use hal_api::light::LightReading;
+use hal_api::rtc::{RtcDateTime, RtcSensor};
+use hal_api::sensor::EnvReading;
+use hal_api::storage::BlockStorage;
+use heapless::Vec;
+
+use crate::alarm::SensorSample;
+
+#[cfg(test)]
+extern crate std;
+
+/// 1 レコードのバイト数。
+pub const RECORD_SIZE: usize = 24;
+
+/// 1 レコードに入る値の最大個数。
+pub const LOG_VALUE_CAPACITY: usize = 3;
+
+/// 組み込みのチャネル番号。利用者定義のチャネルは [`CHANNEL_USER_BASE`] 以降を使う。
+pub const CHANNEL_ENV: u8 = 1;
+pub const CHANNEL_GAS: u8 = 2;
+pub const CHANNEL_DISTANCE: u8 = 3;
+pub const CHANNEL_LIGHT: u8 = 4;
+pub const CHANNEL_IMU: u8 = 5;
+pub const CHANNEL_USER_BASE: u8 = 0x80;
+
+/// 消去済みスロットの通し番号。`u32::MAX` はレコードに割り当てず
+/// ([`following_sequence`] が飛ばす)、新旧の比較は [`is_newer_sequence`] の
+/// 通し番号演算で行う。ラップ直後の 0 が直前の `u32::MAX - 1` より新しいと分かる。
+const ERASED_SEQUENCE: u32 = u32::MAX;
+const FLAG_UNIX_TIME: u8 = 0x80;
+const FLAG_COUNT_MASK: u8 = 0x03;
+
+/// `sequence` の次に割り当てる通し番号。[`ERASED_SEQUENCE`] は飛ばす。
+fn following_sequence(sequence: u32) -> u32 {
+    match sequence.wrapping_add(1) {
+        ERASED_SEQUENCE => 0,
+        next => next,
+    }
+}
+
+/// `a` が `b` より後に書かれた通し番号か (RFC 1982 の通し番号演算)。
+///
+/// ログに残るレコード数は 2^31 よりずっと少ないので、ラップをまたいでも正しく比べられる。
+fn is_newer_sequence(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
+
+/// チャネル番号の表示名。CSV の `channel` 列に使う。
+pub fn channel_name(channel: u8) -> &'static str {
+    match channel {
+        CHANNEL_ENV => "env",
+        CHANNEL_GAS => "gas",
+        CHANNEL_DISTANCE => "distance",
+        CHANNEL_LIGHT => "light",
+        CHANNEL_IMU => "imu",
+        _ => "user",
+    }
+}
+
+/// ロガーへ渡せる読み取り値。
+///
+/// `log_values` は最大 [`LOG_VALUE_CAPACITY`] 個の固定小数点値を返す。
+pub trait LogSample {
+    fn channel(&self) -> u8;
+
+    fn log_values(&self) -> Vec<i32, LOG_VALUE_CAPACITY>;
+}
+
+/// 温度 (1/100 °C)、湿度 (1/100 %RH)、気圧 (Pa、無ければ省略)。
+impl LogSample for EnvReading {
+    fn channel(&self) -> u8 {
+        CHANNEL_ENV
+    }
+
+    fn log_values(&self) -> Vec<i32, LOG_VALUE_CAPACITY> {
+        let mut values = Vec::new();
+        let _ = values.push(self.temperature_centi_celsius);
+        let _ = values.push(saturate(self.humidity_centi_percent));
+        if let Some(pressure) = self.pressure_pascal {
+            let _ = values.push(saturate(pressure));
+        }
+        values
+    }
+}
+
+/// CO₂ (ppm)、VOC (ppb)。
+impl LogSample for GasReading {
+    fn channel(&self) -> u8 {
+        CHANNEL_GAS
+    }
+
+    fn log_values(&self) -> Vec<i32, LOG_VALUE_CAPACITY> {
+        Vec::from_slice(&[i32::from(self.co2_ppm), i32::from(self.voc_ppb)]).unwrap_or_default()
+    }
+}
+
+/// 距離 (mm)。
+impl LogSample for DistanceReading {
+    fn channel(&self) -> u8 {
+        CHANNEL_DISTANCE
+    }
+
+    fn log_values(&self) -> Vec<i32, LOG_VALUE_CAPACITY> {
+        Vec::from_slice(&[saturate(self.distance_mm)]).unwrap_or_default()
+    }
+}
+
+/// 照度 (lux の 100 倍)。
+impl LogSample for LightReading {
+    fn channel(&self) -> u8 {
+        CHANNEL_LIGHT
+    }
+
+    fn log_values(&self) -> Vec<i32, LOG_VALUE_CAPACITY> {
+        Vec::from_slice(&[saturate(self.lux_x100)]).unwrap_or_default()
+    }
+}
+
+/// 加速度 3 軸 (mg)。角速度は記録しない。
+impl LogSample for ImuReading {
+    fn channel(&self) -> u8 {
+        CHANNEL_IMU
+    }
+
+    fn log_values(&self) -> Vec<i32, LOG_VALUE_CAPACITY> {
+        self.accel_mg.iter().map(|axis| i32::from(*axis)).collect()
+    }
+}
+
+impl LogSample for SensorSample {
+    fn channel(&self) -> u8 {
+        match self {
+            SensorSample::Env(reading) => reading.channel(),
+            SensorSample::Gas(reading) => reading.channel(),
+            SensorSample::Distance(reading) => reading.channel(),
+            SensorSample::Light(reading) => reading.channel(),
+            SensorSample::Imu(reading) => reading.channel(),
+        }
+    }
+
+    fn log_values(&self) -> Vec<i32, LOG_VALUE_CAPACITY> {
+        match self {
+            SensorSample::Env(reading) => reading.log_values(),
+            SensorSample::Gas(reading) => reading.log_values(),
+            SensorSample::Distance(reading) => reading.log_values(),
+            SensorSample::Light(reading) => reading.log_values(),
+            SensorSample::Imu(reading) => reading.log_values(),
+        }
+    }
+}
+
+fn saturate(value: u32) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
+
+/// レコードのタイムスタンプ。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogTimestamp {
+    /// 起動からのミリ秒
+    Millis(u32),
+    /// 1970-01-01 からの秒 (UTC として扱う)
+    UnixSeconds(u32),
+}
+
+/// タイムスタンプの供給源。`tick_count` はアプリの tick 数。
+pub trait LogClock {
+    type Error;
+
+    fn timestamp(&mut self, tick_count: u32) -> Result<LogTimestamp, Self::Error>;
+}
+
+/// tick 数 × tick 周期で起動からのミリ秒を返す時計。RTC の無いボード向け。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonotonicClock {
+    tick_period_ms: u32,
+}
+
+impl MonotonicClock {
+    pub const fn new(tick_period_ms: u32) -> Self {
+        Self { tick_period_ms }
+    }
+}
+
+impl LogClock for MonotonicClock {
+    type Error = core::convert::Infallible;
+
+    fn timestamp(&mut self, tick_count: u32) -> Result<LogTimestamp, Self::Error> {
+        Ok(LogTimestamp::Millis(
+            tick_count.wrapping_mul(self.tick_period_ms),
+        ))
+    }
+}
+
+/// `RtcSensor` の日時を UNIX 秒に変換する時計。
+pub struct RtcClock<R> {
+    rtc: R,
+}
+
+impl<R> RtcClock<R>
+where
+    R: RtcSensor,
+{
+    pub fn new(rtc: R) -> Self {
+        Self { rtc }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.rtc
+    }
+}
+
+impl<R> LogClock for RtcClock<R>
+where
+    R: RtcSensor,
+{
+    type Error = R::Error;
+
+    fn timestamp(&mut self, _tick_count: u32) -> Result<LogTimestamp, Self::Error> {
+        let now = self.rtc.read_datetime()?;
+        Ok(LogTimestamp::UnixSeconds(unix_seconds(&now)))
+    }
+}
+
+/// `RtcDateTime` を UNIX 秒へ変換する。
+pub fn unix_seconds(dt: &RtcDateTime) -> u32 {
+    let days = days_from_civil(i32::from(dt.year()), u32::from(dt.month), u32::from(dt.day));
+    let seconds = u32::from(dt.hour) * 3600 + u32::from(dt.minute) * 60 + u32::from(dt.second);
+    (days as u32) * 86_400 + seconds
+}
+
+/// UNIX 秒を `RtcDateTime` へ戻す。2000–2255 年の範囲外は年オフセットが飽和する。
+pub fn datetime_from_unix(seconds: u32) -> RtcDateTime {
+    let days = (seconds / 86_400) as i32;
+    let rem = seconds % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    RtcDateTime::new(
+        (year - 2000).clamp(0, i32::from(u8::MAX)) as u8,
+        month as u8,
+        day as u8,
+        (rem / 3600) as u8,
+        (rem / 60 % 60) as u8,
+        (rem % 60) as u8,
+    )
+}
+
+// Howard Hinnant の days_from_civil / civil_from_days
+fn days_from_civil(year: i32, month: u32, day: u32) -> i32 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year as i32;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i32) -> (i32, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i32::from(month <= 2);
+    (year, month, day)
+}
+
+/// ストレージ上の 1 レコード。
+///
+/// バイト配置 (リトルエンディアン):
+///
+/// | offset | size | 内容 |
+/// |---|---|---|
+/// | 0 | 4 | シーケンス番号 (`0xFFFF_FFFF` は空き) |
+/// | 4 | 4 | タイムスタンプ |
+/// | 8 | 1 | チャネル |
+/// | 9 | 1 | bit0-1: 値の個数、bit7: UNIX 秒 |
+/// | 10 | 2 | CRC-16/CCITT (この 2 バイトを除く全体) |
+/// | 12 | 12 | 値 × 3 (`i32`) |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogRecord {
+    pub sequence: u32,
+    pub timestamp: LogTimestamp,
+    pub channel: u8,
+    value_count: u8,
+    values: [i32; LOG_VALUE_CAPACITY],
+}
+
+impl LogRecord {
+    pub fn new(sequence: u32, timestamp: LogTimestamp, channel: u8, values: &[i32]) -> Self {
+        let value_count = values.len().min(LOG_VALUE_CAPACITY);
+        let mut stored = [0; LOG_VALUE_CAPACITY];
+        stored[..value_count].copy_from_slice(&values[..value_count]);
+        Self {
+            sequence,
+            timestamp,
+            channel,
+            value_count: value_count as u8,
+            values: stored,
+        }
+    }
+
+    pub fn values(&self) -> &[i32] {
+        &self.values[..usize::from(self.value_count)]
+    }
+
+    pub fn to_bytes(&self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0u8; RECORD_SIZE];
+        let (timestamp, unix_flag) = match self.timestamp {
+            LogTimestamp::Millis(ms) => (ms, 0),
+            LogTimestamp::UnixSeconds(seconds) => (seconds, FLAG_UNIX_TIME),
+        };
+        bytes[0..4].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes[4..8].copy_from_slice(&timestamp.to_le_bytes());
+        bytes[8] = self.channel;
+        bytes[9] = self.value_count | unix_flag;
+        for (chunk, value) in bytes[12..].chunks_exact_mut(4).zip(self.values) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        let crc = record_crc(&bytes);
+        bytes[10..12].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// バイト列からレコードを復元する。空きスロットや CRC 不一致なら `None`。
+    pub fn from_bytes(bytes: &[u8; RECORD_SIZE]) -> Option<Self> {
+        let sequence = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        if sequence == ERASED_SEQUENCE {
+            return None;
+        }
+        if u16::from_le_bytes([bytes[10], bytes[11]]) != record_crc(bytes) {
+            return None;
+        }
+        let raw_timestamp = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        let flags = bytes[9];
+        let timestamp = if flags & FLAG_UNIX_TIME != 0 {
+            LogTimestamp::UnixSeconds(raw_timestamp)
+        } else {
+            LogTimestamp::Millis(raw_timestamp)
+        };
+        let mut values = [0; LOG_VALUE_CAPACITY];
+        for (value, chunk) in values.iter_mut().zip(bytes[12..].chunks_exact(4)) {
+            *value = i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        Some(Self {
+            sequence,
+            timestamp,
+            channel: bytes[8],
+            value_count: (flags & FLAG_COUNT_MASK).min(LOG_VALUE_CAPACITY as u8),
+            values,
+        })
+    }
+
+    /// CSV の 1 行 (改行付き) を書く。
+    pub fn write_csv_row<W: Write>(&self, out: &mut W) -> core::fmt::Result {
+        write!(out, "{},", self.sequence)?;
+        match self.timestamp {
+            LogTimestamp::Millis(ms) => write!(out, "{}.{:03}", ms / 1000, ms % 1000)?,
+            LogTimestamp::UnixSeconds(seconds) => {
+                let dt = datetime_from_unix(seconds);
+                write!(
+                    out,
+                    "{}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                    dt.year(),
+                    dt.month,
+                    dt.day,
+                    dt.hour,
+                    dt.minute,
+                    dt.second
+                )?
+            }
+        }
+        write!(out, ",{}", channel_name(self.channel))?;
+        for index in 0..LOG_VALUE_CAPACITY {
+            match self.values().get(index) {
+                Some(value) => write!(out, ",{}", value)?,
+                None => out.write_char(',')?,
+            }
+        }
+        out.write_char('\n')
+    }
+}
+
+/// CSV のヘッダ行。
+pub const CSV_HEADER: &str = "seq,time,channel,v0,v1,v2\n";
+
+fn record_crc(bytes: &[u8; RECORD_SIZE]) -> u16 {
+    crc16_ccitt_update(crc16_ccitt_update(0xFFFF, &bytes[..10]), &bytes[12..])
+}
+
+/// CRC-16/CCITT-FALSE (初期値 `0xFFFF`) の途中計算。
+pub(crate) fn crc16_ccitt_update(mut crc: u16, bytes: &[u8]) -> u16 {
+    for byte in bytes {
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// `DataLog` が返すエラー型。
+#[derive(Debug, PartialEq, Eq)]
+pub enum DataLogError<E> {
+    Storage(E),
+    /// ブロックにレコードが 1 つも入らない、またはブロックが 2 つ未満
+    InvalidGeometry,
+    /// CSV 出力先への書き込みに失敗した
+    Format,
+}
+
+impl<E> From<E> for DataLogError<E> {
+    fn from(err: E) -> Self {
+        DataLogError::Storage(err)
+    }
+}
+
+/// `BlockStorage` 上の循環レコードログ。
+///
+/// 各ブロックの先頭スロットへ書く直前にそのブロックを消去するため、
+/// 保持できるのは最大 `(block_count - 1) * records_per_block` 件から
+/// `block_count * records_per_block` 件の間になる。
+pub struct DataLog<S> {
+    storage: S,
+    records_per_block: u32,
+    slot_count: u32,
+    next_slot: u32,
+    next_sequence: u32,
+    len: u32,
+}
+
+impl<S> DataLog<S>
+where
+    S: BlockStorage,
+{
+    /// ストレージを走査して書き込み位置を復元する。
+    ///
+    /// 空のストレージでも、電源断で書きかけのレコードが残ったストレージでも使える。
+    pub fn mount(mut storage: S) -> Result<Self, DataLogError<S::Error>> {
+        let records_per_block = storage.block_size() / RECORD_SIZE as u32;
+        let block_count = storage.block_count();
+        if records_per_block == 0 || block_count < 2 {
+            return Err(DataLogError::InvalidGeometry);
+        }
+        let slot_count = records_per_block * block_count;
+
+        let mut newest: Option<(u32, u32)> = None;
+        let mut len = 0;
+        let mut bytes = [0u8; RECORD_SIZE];
+        for slot in 0..slot_count {
+            storage.read(slot_offset(slot, records_per_block, &storage), &mut bytes)?;
+            if let Some(record) = LogRecord::from_bytes(&bytes) {
+                len += 1;
+                if newest.map_or(true, |(sequence, _)| {
+                    is_newer_sequence(record.sequence, sequence)
+                }) {
+                    newest = Some((record.sequence, slot));
+                }
+            }
+        }
+
+        let mut log = Self {
+            storage,
+            records_per_block,
+            slot_count,
+            next_slot: 0,
+            next_sequence: 0,
+            len,
+        };
+        if let Some((sequence, slot)) = newest {
+            log.next_sequence = following_sequence(sequence);
+            log.next_slot = (slot + 1) % slot_count;
+            // 書きかけのゴミが残っているスロットには上書きできないので次のブロックへ進む。
+            // ブロック先頭なら古いブロックが残っているだけで、append が消去してから書く
+            if log.next_slot % records_per_block != 0 && !log.slot_is_erased(log.next_slot)? {
+                log.next_slot = log.next_block_start(log.next_slot);
+            }
+        }
+        Ok(log)
+    }
+
+    /// レコードを 1 件追記する。
+    pub fn append<T>(
+        &mut self,
+        timestamp: LogTimestamp,
+        sample: &T,
+    ) -> Result<LogRecord, DataLogError<S::Error>>
+    where
+        T: LogSample + ?Sized,
+    {
+        let record = LogRecord::new(
+            self.next_sequence,
+            timestamp,
+            sample.channel(),
+            &sample.log_values(),
+        );
+        self.append_record(&record)?;
+        Ok(record)
+    }
+
+    fn append_record(&mut self, record: &LogRecord) -> Result<(), DataLogError<S::Error>> {
+        let slot = self.next_slot;
+        if slot % self.records_per_block == 0 {
+            let block = slot / self.records_per_block;
+            let discarded = self.count_valid_in_block(block)?;
+            self.storage.erase_block(block)?;
+            self.len -= discarded;
+        }
+        let offset = slot_offset(slot, self.records_per_block, &self.storage);
+        // 失敗しても次の追記は次のスロットから行う (書きかけのスロットは CRC で弾かれる)
+        self.next_slot = (slot + 1) % self.slot_count;
+        self.next_sequence = following_sequence(self.next_sequence);
+        self.storage.write(offset, &record.to_bytes())?;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// 古い順にレコードを渡す。
+    pub fn for_each<F>(&mut self, mut f: F) -> Result<(), DataLogError<S::Error>>
+    where
+        F: FnMut(&LogRecord),
+    {
+        let mut bytes = [0u8; RECORD_SIZE];
+        for index in 0..self.slot_count {
+            let slot = (self.next_slot + index) % self.slot_count;
+            self.storage.read(
+                slot_offset(slot, self.records_per_block, &self.storage),
+                &mut bytes,
+            )?;
+            if let Some(record) = LogRecord::from_bytes(&bytes) {
+                f(&record);
+            }
+        }
+        Ok(())
+    }
+
+    /// 最新のレコード。
+    pub fn latest(&mut self) -> Result<Option<LogRecord>, DataLogError<S::Error>> {
+        let mut latest = None;
+        self.for_each(|record| latest = Some(*record))?;
+        Ok(latest)
+    }
+
+    /// ヘッダ付き CSV を書き出し、書いたレコード数を返す。
+    pub fn export_csv<W: Write>(&mut self, out: &mut W) -> Result<u32, DataLogError<S::Error>> {
+        out.write_str(CSV_HEADER)
+            .map_err(|_| DataLogError::Format)?;
+        let mut written = 0;
+        let mut format_failed = false;
+        self.for_each(|record| {
+            if format_failed {
+                return;
+            }
+            if record.write_csv_row(out).is_err() {
+                format_failed = true;
+            } else {
+                written += 1;
+            }
+        })?;
+        if format_failed {
+            return Err(DataLogError::Format);
+        }
+        Ok(written)
+    }
+
+    /// 古い順に生のレコードバイト列を渡す (バイナリ出力用)。
+    pub fn export_binary<F>(&mut self, mut f: F) -> Result<u32, DataLogError<S::Error>>
+    where
+        F: FnMut(&[u8; RECORD_SIZE]),
+    {
+        let mut written = 0;
+        self.for_each(|record| {
+            f(&record.to_bytes());
+            written += 1;
+        })?;
+        Ok(written)
+    }
+
+    /// 保持しているレコード数。
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 格納できるスロット数の合計。
+    pub fn slot_count(&self) -> u32 {
+        self.slot_count
+    }
+
+    /// 次に書くレコードのシーケンス番号。
+    pub fn next_sequence(&self) -> u32 {
+        self.next_sequence
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    fn slot_is_erased(&mut self, slot: u32) -> Result<bool, S::Error> {
+        let mut bytes = [0u8; RECORD_SIZE];
+        self.storage.read(
+            slot_offset(slot, self.records_per_block, &self.storage),
+            &mut bytes,
+        )?;
+        Ok(bytes.iter().all(|byte| *byte == 0xFF))
+    }
+
+    fn next_block_start(&self, slot: u32) -> u32 {
+        let block = slot / self.records_per_block;
+        ((block + 1) * self.records_per_block) % self.slot_count
+    }
+
+    fn count_valid_in_block(&mut self, block: u32) -> Result<u32, S::Error> {
+        let mut bytes = [0u8; RECORD_SIZE];
+        let mut count = 0;
+        for slot in block * self.records_per_block..(block + 1) * self.records_per_block {
+            self.storage.read(
+                slot_offset(slot, self.records_per_block, &self.storage),
+                &mut bytes,
+            )?;
+            if LogRecord::from_bytes(&bytes).is_some() {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+fn slot_offset<S: BlockStorage>(slot: u32, records_per_block: u32, storage: &S) -> u32 {
+    let block = slot / records_per_block;
+    let index = slot % records_per_block;
+    block * storage.block_size() + index * RECORD_SIZE as u32
+}
+
+/// `DataLoggerApp` が読み取るセンサの抽象。
+pub trait LogSource {
+    type Sample: LogSample;
+    type Error;
+
+    fn read_sample(&mut self) -> Result<Self::Sample, Self::Error>;
+}
+
+/// 既存のセンサ trait を [`LogSource`] にするアダプタ。
+///
+/// `read` には `EnvSensor::read` や `GasSensor::read_gas` などをそのまま渡せる。
+pub struct SensorSource<S, F> {
+    sensor: S,
+    read: F,
+}
+
+impl<S, F> SensorSource<S, F> {
+    pub fn new(sensor: S, read: F) -> Self {
+        Self { sensor, read }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.sensor
+    }
+}
+
+impl<S, F, T, E> LogSource for SensorSource<S, F>
+where
+    F: FnMut(&mut S) -> Result<T, E>,
+    T: LogSample,
+{
+    type Sample = T;
+    type Error = E;
+
+    fn read_sample(&mut self) -> Result<T, E> {
+        (self.read)(&mut self.sensor)
+    }
+}
+
+/// `DataLoggerApp` の設定。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataLoggerConfig {
+    /// センサを読み取って記録する tick 間隔。
+    pub sample_period_ticks: u32,
+}
+
+impl Default for DataLoggerConfig {
+    fn default() -> Self {
+        Self {
+            sample_period_ticks: 6000,
+        }
+    }
+}
+
+/// `DataLoggerApp` が返すエラー型。
+#[derive(Debug, PartialEq, Eq)]
+pub enum DataLoggerError<SE, ST, CE> {
+    Sensor(SE),
+    Log(DataLogError<ST>),
+    Clock(CE),
+}
+
+/// 一定周期でセンサを読み、タイムスタンプ付きで [`DataLog`] に記録するアプリ。
+pub struct DataLoggerApp<SOURCE, STORAGE, CLOCK> {
+    source: SOURCE,
+    log: DataLog<STORAGE>,
+    clock: CLOCK,
+    config: DataLoggerConfig,
+    tick_count: u32,
+    last_record: Option<LogRecord>,
+}
+
+impl<SOURCE, STORAGE, CLOCK> DataLoggerApp<SOURCE, STORAGE, CLOCK>
+where
+    SOURCE: LogSource,
+    STORAGE: BlockStorage,
+    CLOCK: LogClock,
+{
+    pub fn new(source: SOURCE, log: DataLog<STORAGE>, clock: CLOCK) -> Self {
+        Self::new_with_config(source, log, clock, DataLoggerConfig::default())
+    }
+
+    pub fn new_with_config(
+        source: SOURCE,
+        log: DataLog<STORAGE>,
+        clock: CLOCK,
+        config: DataLoggerConfig,
+    ) -> Self {
+        Self {
+            source,
+            log,
+            clock,
+            config,
+            tick_count: 0,
+            last_record: None,
+        }
+    }
+
+    /// `sample_period_ticks` ごとにセンサを読み取り、1 レコード記録する。
+    #[allow(clippy::type_complexity)]
+    pub fn tick(
+        &mut self,
+    ) -> Result<(), DataLoggerError<SOURCE::Error, STORAGE::Error, CLOCK::Error>> {
+        self.tick_count = self.tick_count.wrapping_add(1);
+        if self.tick_count % self.config.sample_period_ticks.max(1) != 0 {
+            return Ok(());
+        }
+        let sample = self.source.read_sample().map_err(DataLoggerError::Sensor)?;
+        let timestamp = self
+            .clock
+            .timestamp(self.tick_count)
+            .map_err(DataLoggerError::Clock)?;
+        let record = self
+            .log
+            .append(timestamp, &sample)
+            .map_err(DataLoggerError::Log)?;
+        self.last_record = Some(record);
+        Ok(())
+    }
+
+    pub fn last_record(&self) -> Option<&LogRecord> {
+        self.last_record.as_ref()
+    }
+
+    pub fn log(&self) -> &DataLog<STORAGE> {
+        &self.log
+    }
+
+    pub fn log_mut(&mut self) -> &mut DataLog<STORAGE> {
+        &mut self.log
+    }
+
+    pub fn config(&self) -> &DataLoggerConfig {
+        &self.config
+    }
+
+    pub fn tick_count(&self) -> u32 {
+        self.tick_count
+    }
+
+    pub fn into_parts(self) -> (SOURCE, DataLog<STORAGE>, CLOCK) {
+        (self.source, self.log, self.clock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hal_api::error::{SensorError, StorageError};
+    use hal_api::gas::GasSensor;
+    use std::string::String;
+    use std::vec;
+    use std::vec::Vec as StdVec;
+
+    /// NOR フラッシュ相当 (書き込みは AND) の RAM ストレージ。
+    struct RamFlash {
+        bytes: StdVec<u8>,
+        block_size: u32,
+        tear_next_write_at: Option<usize>,
+    }
+
+    impl RamFlash {
+        fn new(block_size: u32, block_count: u32) -> Self {
+            Self {
+                bytes: vec![0xFF; (block_size * block_count) as usize],
+                block_size,
+                tear_next_write_at: None,
+            }
+        }
+    }
+
+    impl BlockStorage for RamFlash {
+        type Error = StorageError;
+
+        fn block_size(&self) -> u32 {
+            self.block_size
+        }
+
+        fn block_count(&self) -> u32 {
+            self.bytes.len() as u32 / self.block_size
+        }
+
+        fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), StorageError> {
+            let start = offset as usize;
+            let source = self
+                .bytes
+                .get(start..start + buffer.len())
+                .ok_or(StorageError::OutOfBounds)?;
+            buffer.copy_from_slice(source);
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), StorageError> {
+            let start = offset as usize;
+            let keep = self.tear_next_write_at.take();
+            let target = self
+                .bytes
+                .get_mut(start..start + data.len())
+                .ok_or(StorageError::OutOfBounds)?;
+            let count = keep.unwrap_or(data.len());
+            for (cell, byte) in target.iter_mut().zip(data).take(count) {
+                *cell &= *byte;
+            }
+            if keep.is_some() {
+                return Err(StorageError::WriteFailed);
+            }
+            Ok(())
+        }
+
+        fn erase_block(&mut self, block: u32) -> Result<(), StorageError> {
+            let start = (block * self.block_size) as usize;
+            self.bytes
+                .get_mut(start..start + self.block_size as usize)
+                .ok_or(StorageError::OutOfBounds)?
+                .fill(0xFF);
+            Ok(())
+        }
+    }
+
+    fn sequences(log: &mut DataLog<RamFlash>) -> StdVec<u32> {
+        let mut sequences = StdVec::new();
+        log.for_each(|record| sequences.push(record.sequence))
+            .unwrap();
+        sequences
+    }
+
+    fn append_distance(log: &mut DataLog<RamFlash>, mm: u32) {
+        log.append(LogTimestamp::Millis(mm), &DistanceReading::new(mm))
+            .unwrap();
+    }
+
+    #[test]
+    fn record_roundtrips_through_bytes() {
+        let record = LogRecord::new(
+            42,
+            LogTimestamp::UnixSeconds(1_735_716_600),
+            CHANNEL_ENV,
+            &[2150, 4500, 101_325],
+        );
+        let bytes = record.to_bytes();
+        assert_eq!(LogRecord::from_bytes(&bytes), Some(record));
+
+        let mut corrupted = bytes;
+        corrupted[14] ^= 0x01;
+        assert_eq!(LogRecord::from_bytes(&corrupted), None);
+        assert_eq!(LogRecord::from_bytes(&[0xFF; RECORD_SIZE]), None);
+    }
+
+    #[test]
+    fn mount_rejects_too_small_geometry() {
+        assert!(matches!(
+            DataLog::mount(RamFlash::new(16, 4)),
+            Err(DataLogError::InvalidGeometry)
+        ));
+        assert!(matches!(
+            DataLog::mount(RamFlash::new(64, 1)),
+            Err(DataLogError::InvalidGeometry)
+        ));
+    }
+
+    #[test]
+    fn log_wraps_and_discards_oldest_block() {
+        // 48 バイト / ブロック → 2 レコード × 3 ブロック = 6 スロット
+        let mut log = DataLog::mount(RamFlash::new(48, 3)).unwrap();
+        assert_eq!(log.slot_count(), 6);
+        for mm in 0..6 {
+            append_distance(&mut log, mm);
+        }
+        assert_eq!(log.len(), 6);
+        assert_eq!(sequences(&mut log), [0, 1, 2, 3, 4, 5]);
+
+        append_distance(&mut log, 6);
+        assert_eq!(log.len(), 5);
+        assert_eq!(sequences(&mut log), [2, 3, 4, 5, 6]);
+        append_distance(&mut log, 7);
+        append_distance(&mut log, 8);
+        assert_eq!(sequences(&mut log), [4, 5, 6, 7, 8]);
+        assert_eq!(log.latest().unwrap().unwrap().sequence, 8);
+    }
+
+    #[test]
+    fn mount_resumes_after_wrap() {
+        let mut log = DataLog::mount(RamFlash::new(48, 3)).unwrap();
+        for mm in 0..9 {
+            append_distance(&mut log, mm);
+        }
+        let mut log = DataLog::mount(log.into_inner()).unwrap();
+        assert_eq!(log.next_sequence(), 9);
+        assert_eq!(log.len(), 5);
+        append_distance(&mut log, 9);
+        assert_eq!(sequences(&mut log), [4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn sequence_wrap_skips_erased_marker_and_mount_finds_newest() {
+        let mut log = DataLog::mount(RamFlash::new(48, 3)).unwrap();
+        log.next_sequence = u32::MAX - 3;
+        for mm in 0..5 {
+            append_distance(&mut log, mm);
+        }
+        assert_eq!(
+            sequences(&mut log),
+            [u32::MAX - 3, u32::MAX - 2, u32::MAX - 1, 0, 1]
+        );
+
+        let mut log = DataLog::mount(log.into_inner()).unwrap();
+        assert_eq!(log.len(), 5);
+        assert_eq!(log.next_sequence(), 2);
+        append_distance(&mut log, 5);
+        assert_eq!(log.latest().unwrap().unwrap().sequence, 2);
+        assert_eq!(
+            sequences(&mut log),
+            [u32::MAX - 3, u32::MAX - 2, u32::MAX - 1, 0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn mount_at_block_boundary_keeps_newest_records() {
+        let mut log = DataLog::mount(RamFlash::new(48, 3)).unwrap();
+        for mm in 0..10 {
+            append_distance(&mut log, mm);
+        }
+        // 次のスロットは最古のレコード (4, 5) が残るブロックの先頭
+        let mut log = DataLog::mount(log.into_inner()).unwrap();
+        assert_eq!(sequences(&mut log), [4, 5, 6, 7, 8, 9]);
+        append_distance(&mut log, 10);
+        assert_eq!(sequences(&mut log), [6, 7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn torn_write_is_skipped_after_remount() {
+        let mut log = DataLog::mount(RamFlash::new(96, 2)).unwrap();
+        append_distance(&mut log, 100);
+        append_distance(&mut log, 200);
+
+        let mut flash = log.into_inner();
+        flash.tear_next_write_at = Some(10);
+        let mut log = DataLog::mount(flash).unwrap();
+        assert!(matches!(
+            log.append(LogTimestamp::Millis(0), &DistanceReading::new(300)),
+            Err(DataLogError::Storage(StorageError::WriteFailed))
+        ));
+
+        // 電源復帰: 書きかけのスロットを避けて次のブロックから再開する
+        let mut log = DataLog::mount(log.into_inner()).unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log.next_sequence(), 2);
+        append_distance(&mut log, 400);
+        assert_eq!(sequences(&mut log), [0, 1, 2]);
+        let values: StdVec<i32> = {
+            let mut values = StdVec::new();
+            log.for_each(|record| values.push(record.values()[0]))
+                .unwrap();
+            values
+        };
+        assert_eq!(values, [100, 200, 400]);
+    }
+
+    #[test]
+    fn csv_export_formats_both_clock_kinds() {
+        let mut log = DataLog::mount(RamFlash::new(96, 2)).unwrap();
+        log.append(
+            LogTimestamp::Millis(12_340),
+            &EnvReading::new(2150, 4500, None),
+        )
+        .unwrap();
+        log.append(
+            LogTimestamp::UnixSeconds(unix_seconds(&RtcDateTime::new(25, 1, 2, 7, 30, 5))),
+            &GasReading::new(1100, 220),
+        )
+        .unwrap();
+
+        let mut csv = String::new();
+        assert_eq!(log.export_csv(&mut csv).unwrap(), 2);
+        assert_eq!(
+            csv,
+            "seq,time,channel,v0,v1,v2\n\
+             0,12.340,env,2150,4500,\n\
+             1,2025-01-02T07:30:05,gas,1100,220,\n"
+        );
+
+        let mut binary = StdVec::new();
+        assert_eq!(
+            log.export_binary(|bytes| binary.extend_from_slice(bytes))
+                .unwrap(),
+            2
+        );
+        assert_eq!(binary.len(), 2 * RECORD_SIZE);
+    }
+
+    #[test]
+    fn unix_conversion_roundtrips() {
+        let dt = RtcDateTime::new(24, 2, 29, 23, 59, 58);
+        let seconds = unix_seconds(&dt);
+        assert_eq!(seconds, 1_709_251_198);
+        assert_eq!(datetime_from_unix(seconds), dt);
+        assert_eq!(
+            unix_seconds(&RtcDateTime::new(0, 1, 1, 0, 0, 0)),
+            946_684_800
+        );
+    }
+
+    struct StepRtc {
+        now: RtcDateTime,
+    }
+
+    impl RtcSensor for StepRtc {
+        type Error = SensorError;
+
+        fn read_datetime(&mut self) -> Result<RtcDateTime, SensorError> {
+            let now = self.now;
+            self.now.second += 1;
+            Ok(now)
+        }
+
+        fn set_datetime(&mut self, dt: &RtcDateTime) -> Result<(), SensorError> {
+            self.now = *dt;
+            Ok(())
+        }
+    }
+
+    struct FlakyGas {
+        reads: u32,
+    }
+
+    impl GasSensor for FlakyGas {
+        type Error = SensorError;
+
+        fn read_gas(&mut self) -> Result<GasReading, SensorError> {
+            self.reads += 1;
+            if self.reads == 2 {
+                return Err(SensorError::Busy);
+            }
+            Ok(GasReading::new(400 + self.reads as u16, 0))
+        }
+    }
+
+    #[test]
+    fn app_logs_every_period_with_rtc_timestamps() {
+        let mut app = DataLoggerApp::new_with_config(
+            SensorSource::new(FlakyGas { reads: 0 }, GasSensor::read_gas),
+            DataLog::mount(RamFlash::new(96, 2)).unwrap(),
+            RtcClock::new(StepRtc {
+                now: RtcDateTime::new(25, 6, 1, 12, 0, 0),
+            }),
+            DataLoggerConfig {
+                sample_period_ticks: 10,
+            },
+        );
+
+        for _ in 0..10 {
+            app.tick().unwrap();
+        }
+        let first = *app.last_record().unwrap();
+        assert_eq!(first.values(), [401, 0]);
+        assert_eq!(
+            first.timestamp,
+            LogTimestamp::UnixSeconds(unix_seconds(&RtcDateTime::new(25, 6, 1, 12, 0, 0)))
+        );
+
+        for _ in 0..9 {
+            app.tick().unwrap();
+        }
+        assert_eq!(app.tick(), Err(DataLoggerError::Sensor(SensorError::Busy)));
+        for _ in 0..10 {
+            app.tick().unwrap();
+        }
+        assert_eq!(app.log().len(), 2);
+        assert_eq!(app.last_record().unwrap().sequence, 1);
+        assert_eq!(app.last_record().unwrap().values(), [403, 0]);
+    }
+
+    #[test]
+    fn sensor_sample_delegates_channel() {
+        let sample: SensorSample = LightReading::new(12_345).into();
+        assert_eq!(sample.channel(), CHANNEL_LIGHT);
+        assert_eq!(sample.log_values().as_slice(), [12_345]);
+        let imu = ImuReading::new([1, -2, 1000], [0; 3], None);
+        assert_eq!(imu.log_values().as_slice(), [1, -2, 1000]);
+    }
+}
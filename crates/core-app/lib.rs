@@ -65,8 +65,19 @@ use hal_api::error::{GpioError, I2cError};
 use hal_api::gpio::OutputPin;
 use hal_api::i2c::I2cBus;
 
+pub mod alarm;
 pub mod climate_display;
+pub mod data_logger;
 pub mod imu_logger;
+pub mod menu;
+pub mod metrics;
+pub mod mqtt;
+pub mod pid;
+pub mod scheduler;
+pub mod settings;
+pub mod speed_control;
+pub mod telemetry;
+pub mod thermostat;
 
 #[cfg(test)]
 extern crate std;
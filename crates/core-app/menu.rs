@@ -0,0 +1,1325 @@
+//! 16x2 文字ディスプレイ向けのボタン操作メニュー。
+//!
+//! - メニュー構造は `&'static [MenuItem]` の木で静的に定義する (サブメニュー / 数値 / 選択肢 / アクション)
+//! - 入力は [`MenuInput`] (Up / Down / Select / Back) に正規化する。3 ボタン
+//!   ([`ButtonInput`]) とロータリーエンコーダ ([`RotaryInput`]) のアダプタを用意している
+//! - 設定値は [`SettingsStore`] 経由で読み書きし、確定 (Select) した時だけ保存する
+//! - 各サブメニューの末尾には `< Back` 行が自動で付くので、Back ボタンが無くても戻れる
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::menu::{
+//!     ChoiceSetting, MemorySettings, Menu, MenuEvent, MenuInput, MenuItem, NumberSetting,
+//! };
+//!
+//! static UNITS: [&str; 2] = ["C", "F"];
+//! static ITEMS: [MenuItem; 2] = [
+//!     MenuItem::Number(NumberSetting::new("Refresh", 1, 1, 60, 5).unit("s")),
+//!     MenuItem::Choice(ChoiceSetting::new("Unit", 2, &UNITS)),
+//! ];
+//!
+//! let mut store: MemorySettings<4> = MemorySettings::new();
+//! let mut menu: Menu<2> = Menu::new("Settings", &ITEMS);
+//! let frame = menu.render(&mut store).unwrap();
+//! assert_eq!(frame.line(0), b">Refresh      5s");
+//!
+//! menu.handle(MenuInput::Select, &mut store).unwrap();
+//! menu.handle(MenuInput::Up, &mut store).unwrap();
+//! let event = menu.handle(MenuInput::Select, &mut store).unwrap();
+//! assert_eq!(event, Some(MenuEvent::Changed { key: 1, value: 6 }));
+//! ```
+
+use hal_api::display::{TextDisplay16x2, TextFrame16x2, DISPLAY_COLUMNS};
+use hal_api::encoder::WheelEncoder;
+use hal_api::error::{DisplayError, GpioError, StorageError};
+use hal_api::gpio::InputPin;
+use heapless::{LinearMap, String, Vec};
+
+#[cfg(test)]
+extern crate std;
+
+/// 設定値のキー。
+pub type SettingKey = u16;
+
+/// メニューが編集する設定値の保存先。
+///
+/// 値はすべて `i32` で扱い、選択肢は選択中のインデックスとして保存する。
+pub trait SettingsStore {
+    type Error;
+
+    /// 未保存なら `Ok(None)` を返す。
+    fn load(&mut self, key: SettingKey) -> Result<Option<i32>, Self::Error>;
+
+    fn store(&mut self, key: SettingKey, value: i32) -> Result<(), Self::Error>;
+}
+
+/// RAM 上だけに保持する `SettingsStore`。最大 `N` 個のキーを持てる。
+#[derive(Debug, Clone, Default)]
+pub struct MemorySettings<const N: usize> {
+    values: LinearMap<SettingKey, i32, N>,
+}
+
+impl<const N: usize> MemorySettings<N> {
+    pub fn new() -> Self {
+        Self {
+            values: LinearMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: SettingKey) -> Option<i32> {
+        self.values.get(&key).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl<const N: usize> SettingsStore for MemorySettings<N> {
+    type Error = StorageError;
+
+    fn load(&mut self, key: SettingKey) -> Result<Option<i32>, Self::Error> {
+        Ok(self.get(key))
+    }
+
+    fn store(&mut self, key: SettingKey, value: i32) -> Result<(), Self::Error> {
+        self.values
+            .insert(key, value)
+            .map(|_| ())
+            .map_err(|_| StorageError::WriteFailed)
+    }
+}
+
+/// 数値設定。値は `decimals` 桁の固定小数点 (例: `decimals = 1` なら 235 → `23.5`)。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberSetting {
+    pub label: &'static str,
+    pub key: SettingKey,
+    pub min: i32,
+    pub max: i32,
+    pub step: i32,
+    pub default: i32,
+    pub decimals: u8,
+    pub unit: &'static str,
+}
+
+impl NumberSetting {
+    pub const fn new(
+        label: &'static str,
+        key: SettingKey,
+        min: i32,
+        max: i32,
+        default: i32,
+    ) -> Self {
+        Self {
+            label,
+            key,
+            min,
+            max,
+            step: 1,
+            default,
+            decimals: 0,
+            unit: "",
+        }
+    }
+
+    pub const fn step(mut self, step: i32) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub const fn decimals(mut self, decimals: u8) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    pub const fn unit(mut self, unit: &'static str) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// 保存値 (無ければ既定値) を範囲内に丸めて返す。
+    pub fn load<S: SettingsStore>(&self, store: &mut S) -> Result<i32, S::Error> {
+        Ok(self.clamp(store.load(self.key)?.unwrap_or(self.default)))
+    }
+
+    fn clamp(&self, value: i32) -> i32 {
+        value.clamp(self.min, self.max.max(self.min))
+    }
+
+    fn adjust(&self, value: i32, up: bool) -> i32 {
+        let step = self.step.max(1);
+        let next = if up {
+            value.saturating_add(step)
+        } else {
+            value.saturating_sub(step)
+        };
+        self.clamp(next)
+    }
+}
+
+/// 選択肢設定。保存値は `options` のインデックス。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChoiceSetting {
+    pub label: &'static str,
+    pub key: SettingKey,
+    pub options: &'static [&'static str],
+    pub default: u8,
+}
+
+impl ChoiceSetting {
+    pub const fn new(
+        label: &'static str,
+        key: SettingKey,
+        options: &'static [&'static str],
+    ) -> Self {
+        Self {
+            label,
+            key,
+            options,
+            default: 0,
+        }
+    }
+
+    pub const fn default_index(mut self, index: u8) -> Self {
+        self.default = index;
+        self
+    }
+
+    /// 保存値 (無ければ既定値) を有効なインデックスに丸めて返す。
+    pub fn load<S: SettingsStore>(&self, store: &mut S) -> Result<u8, S::Error> {
+        let value = store.load(self.key)?.unwrap_or(i32::from(self.default));
+        Ok(self.clamp(value) as u8)
+    }
+
+    /// 保存値に対応する選択肢の文字列。
+    pub fn option<S: SettingsStore>(&self, store: &mut S) -> Result<&'static str, S::Error> {
+        let index = self.load(store)?;
+        Ok(self.options.get(usize::from(index)).copied().unwrap_or(""))
+    }
+
+    fn clamp(&self, value: i32) -> i32 {
+        let last = self.options.len().saturating_sub(1) as i32;
+        value.clamp(0, last)
+    }
+
+    fn cycle(&self, value: i32, up: bool) -> i32 {
+        let count = self.options.len().max(1) as i32;
+        let step = if up { 1 } else { count - 1 };
+        (self.clamp(value) + step) % count
+    }
+}
+
+/// メニューの 1 行。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    Submenu {
+        label: &'static str,
+        items: &'static [MenuItem],
+    },
+    Number(NumberSetting),
+    Choice(ChoiceSetting),
+    /// 選択すると [`MenuEvent::Action`] を返す。`confirm` があれば確認画面を挟む
+    Action {
+        label: &'static str,
+        id: u16,
+        confirm: Option<&'static str>,
+    },
+}
+
+impl MenuItem {
+    pub const fn submenu(label: &'static str, items: &'static [MenuItem]) -> Self {
+        Self::Submenu { label, items }
+    }
+
+    pub const fn action(label: &'static str, id: u16) -> Self {
+        Self::Action {
+            label,
+            id,
+            confirm: None,
+        }
+    }
+
+    pub const fn confirmed_action(label: &'static str, id: u16, prompt: &'static str) -> Self {
+        Self::Action {
+            label,
+            id,
+            confirm: Some(prompt),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Submenu { label, .. } | Self::Action { label, .. } => label,
+            Self::Number(setting) => setting.label,
+            Self::Choice(setting) => setting.label,
+        }
+    }
+}
+
+/// メニュー操作の入力。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuInput {
+    /// 前の行へ / 値を増やす
+    Up,
+    /// 次の行へ / 値を減らす
+    Down,
+    Select,
+    /// 編集の取り消し / 親メニューへ戻る
+    Back,
+}
+
+/// メニュー操作の結果としてアプリに通知する出来事。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuEvent {
+    /// 設定値を確定して保存した
+    Changed { key: SettingKey, value: i32 },
+    /// アクションを実行する (確認画面があれば Yes を選んだ後)
+    Action(u16),
+    /// ルートで Back した
+    Exit,
+}
+
+/// `Menu` が返すエラー型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuError<E> {
+    Store(E),
+    /// サブメニューの入れ子が `DEPTH` を超えた
+    TooDeep,
+}
+
+/// 現在の画面。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuMode {
+    Browse,
+    /// 設定値を編集中 (未保存の値)
+    Edit {
+        value: i32,
+    },
+    /// 確認画面。`yes` が選択中の答え
+    Confirm {
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MenuLevel {
+    title: &'static str,
+    items: &'static [MenuItem],
+    cursor: usize,
+}
+
+/// 入れ子 `DEPTH` 段までのメニュー状態機械。
+///
+/// ディスプレイや入力デバイスは持たず、[`Menu::handle`] と [`Menu::render`] だけを提供する。
+#[derive(Debug, Clone)]
+pub struct Menu<const DEPTH: usize> {
+    current: MenuLevel,
+    parents: Vec<MenuLevel, DEPTH>,
+    mode: MenuMode,
+}
+
+impl<const DEPTH: usize> Menu<DEPTH> {
+    pub fn new(title: &'static str, items: &'static [MenuItem]) -> Self {
+        Self {
+            current: MenuLevel {
+                title,
+                items,
+                cursor: 0,
+            },
+            parents: Vec::new(),
+            mode: MenuMode::Browse,
+        }
+    }
+
+    pub fn mode(&self) -> MenuMode {
+        self.mode
+    }
+
+    /// 現在のメニューのタイトル。
+    pub fn title(&self) -> &'static str {
+        self.current.title
+    }
+
+    /// カーソル位置の項目。`< Back` 行なら `None`。
+    pub fn selected(&self) -> Option<&'static MenuItem> {
+        self.current.items.get(self.current.cursor)
+    }
+
+    /// ルートからの入れ子の深さ (ルートは 0)。
+    pub fn depth(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// ルートの先頭に戻り、編集中の値は捨てる。
+    pub fn reset(&mut self) {
+        if let Some(root) = self.parents.first().copied() {
+            self.current = root;
+        }
+        self.current.cursor = 0;
+        self.parents.clear();
+        self.mode = MenuMode::Browse;
+    }
+
+    pub fn handle<S: SettingsStore>(
+        &mut self,
+        input: MenuInput,
+        store: &mut S,
+    ) -> Result<Option<MenuEvent>, MenuError<S::Error>> {
+        match self.mode {
+            MenuMode::Browse => self.handle_browse(input, store),
+            MenuMode::Edit { value } => self.handle_edit(input, value, store),
+            MenuMode::Confirm { yes } => Ok(self.handle_confirm(input, yes)),
+        }
+    }
+
+    fn handle_browse<S: SettingsStore>(
+        &mut self,
+        input: MenuInput,
+        store: &mut S,
+    ) -> Result<Option<MenuEvent>, MenuError<S::Error>> {
+        // 末尾の `< Back` 行を含めた行数
+        let rows = self.current.items.len() + 1;
+        match input {
+            MenuInput::Up => {
+                self.current.cursor = (self.current.cursor + rows - 1) % rows;
+                Ok(None)
+            }
+            MenuInput::Down => {
+                self.current.cursor = (self.current.cursor + 1) % rows;
+                Ok(None)
+            }
+            MenuInput::Back => Ok(self.leave()),
+            MenuInput::Select => match self.selected().copied() {
+                None => Ok(self.leave()),
+                Some(MenuItem::Submenu { label, items }) => {
+                    self.parents
+                        .push(self.current)
+                        .map_err(|_| MenuError::TooDeep)?;
+                    self.current = MenuLevel {
+                        title: label,
+                        items,
+                        cursor: 0,
+                    };
+                    Ok(None)
+                }
+                Some(MenuItem::Number(setting)) => {
+                    let value = setting.load(store).map_err(MenuError::Store)?;
+                    self.mode = MenuMode::Edit { value };
+                    Ok(None)
+                }
+                Some(MenuItem::Choice(setting)) => {
+                    let value = i32::from(setting.load(store).map_err(MenuError::Store)?);
+                    self.mode = MenuMode::Edit { value };
+                    Ok(None)
+                }
+                Some(MenuItem::Action { id, confirm, .. }) => {
+                    if confirm.is_some() {
+                        self.mode = MenuMode::Confirm { yes: false };
+                        Ok(None)
+                    } else {
+                        Ok(Some(MenuEvent::Action(id)))
+                    }
+                }
+            },
+        }
+    }
+
+    fn handle_edit<S: SettingsStore>(
+        &mut self,
+        input: MenuInput,
+        value: i32,
+        store: &mut S,
+    ) -> Result<Option<MenuEvent>, MenuError<S::Error>> {
+        let Some(item) = self.selected().copied() else {
+            self.mode = MenuMode::Browse;
+            return Ok(None);
+        };
+        let next = match (item, input) {
+            (MenuItem::Number(setting), MenuInput::Up | MenuInput::Down) => {
+                setting.adjust(value, input == MenuInput::Up)
+            }
+            (MenuItem::Choice(setting), MenuInput::Up | MenuInput::Down) => {
+                setting.cycle(value, input == MenuInput::Up)
+            }
+            (MenuItem::Number(setting), MenuInput::Select) => {
+                return self.commit(setting.key, value, store);
+            }
+            (MenuItem::Choice(setting), MenuInput::Select) => {
+                return self.commit(setting.key, value, store);
+            }
+            _ => {
+                self.mode = MenuMode::Browse;
+                return Ok(None);
+            }
+        };
+        self.mode = MenuMode::Edit { value: next };
+        Ok(None)
+    }
+
+    fn commit<S: SettingsStore>(
+        &mut self,
+        key: SettingKey,
+        value: i32,
+        store: &mut S,
+    ) -> Result<Option<MenuEvent>, MenuError<S::Error>> {
+        store.store(key, value).map_err(MenuError::Store)?;
+        self.mode = MenuMode::Browse;
+        Ok(Some(MenuEvent::Changed { key, value }))
+    }
+
+    fn handle_confirm(&mut self, input: MenuInput, yes: bool) -> Option<MenuEvent> {
+        match input {
+            MenuInput::Up | MenuInput::Down => {
+                self.mode = MenuMode::Confirm { yes: !yes };
+                None
+            }
+            MenuInput::Back => {
+                self.mode = MenuMode::Browse;
+                None
+            }
+            MenuInput::Select => {
+                self.mode = MenuMode::Browse;
+                match (yes, self.selected()) {
+                    (true, Some(MenuItem::Action { id, .. })) => Some(MenuEvent::Action(*id)),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self) -> Option<MenuEvent> {
+        match self.parents.pop() {
+            Some(parent) => {
+                self.current = parent;
+                None
+            }
+            None => {
+                self.current.cursor = 0;
+                Some(MenuEvent::Exit)
+            }
+        }
+    }
+
+    /// 現在の画面を 16x2 フレームに描画する。
+    pub fn render<S: SettingsStore>(
+        &self,
+        store: &mut S,
+    ) -> Result<TextFrame16x2, MenuError<S::Error>> {
+        let mut frame = TextFrame16x2::blank();
+        match self.mode {
+            MenuMode::Browse => {
+                let rows = self.current.items.len() + 1;
+                let top = self.current.cursor.min(rows.saturating_sub(2));
+                for (row, index) in (top..rows).take(2).enumerate() {
+                    let line = self.browse_line(index, store)?;
+                    frame.set_line(row, &line);
+                }
+            }
+            MenuMode::Edit { value } => {
+                let item = self.selected();
+                frame.set_line(0, item.map_or("", MenuItem::label));
+                let mut line: String<DISPLAY_COLUMNS> = String::new();
+                let _ = line.push_str("= ");
+                if let Some(item) = item {
+                    push_value(&mut line, item, value);
+                }
+                frame.set_line(1, &line);
+            }
+            MenuMode::Confirm { yes } => {
+                let prompt = match self.selected() {
+                    Some(MenuItem::Action {
+                        confirm: Some(prompt),
+                        ..
+                    }) => prompt,
+                    _ => "",
+                };
+                frame.set_line(0, prompt);
+                frame.set_line(1, if yes { " No  >Yes" } else { ">No   Yes" });
+            }
+        }
+        Ok(frame)
+    }
+
+    fn browse_line<S: SettingsStore>(
+        &self,
+        index: usize,
+        store: &mut S,
+    ) -> Result<String<DISPLAY_COLUMNS>, MenuError<S::Error>> {
+        let mut line: String<DISPLAY_COLUMNS> = String::new();
+        let marker = if index == self.current.cursor {
+            '>'
+        } else {
+            ' '
+        };
+        let _ = line.push(marker);
+
+        let Some(item) = self.current.items.get(index) else {
+            let _ = line.push_str("< Back");
+            return Ok(line);
+        };
+
+        let mut value: String<DISPLAY_COLUMNS> = String::new();
+        match item {
+            MenuItem::Number(setting) => {
+                let current = setting.load(store).map_err(MenuError::Store)?;
+                push_value(&mut value, item, current);
+            }
+            MenuItem::Choice(setting) => {
+                let current = i32::from(setting.load(store).map_err(MenuError::Store)?);
+                push_value(&mut value, item, current);
+            }
+            MenuItem::Submenu { .. } => {
+                let _ = value.push('>');
+            }
+            MenuItem::Action { .. } => {}
+        }
+
+        // ラベルは値と 1 文字以上空けて切り詰め、値は右寄せにする
+        let label_room = DISPLAY_COLUMNS - 1 - value.len().min(DISPLAY_COLUMNS - 1);
+        let label_room = if value.is_empty() {
+            label_room
+        } else {
+            label_room.saturating_sub(1)
+        };
+        for ch in item.label().chars().take(label_room) {
+            let _ = line.push(ch);
+        }
+        while line.len() + value.len() < DISPLAY_COLUMNS {
+            let _ = line.push(' ');
+        }
+        let _ = line.push_str(&value);
+        Ok(line)
+    }
+}
+
+/// 設定値を表示用の文字列にして追加する (収まらない分は捨てる)。
+fn push_value<const N: usize>(out: &mut String<N>, item: &MenuItem, value: i32) {
+    match item {
+        MenuItem::Number(setting) => {
+            let mut text: String<24> = String::new();
+            let _ = write_fixed_point(&mut text, value, setting.decimals);
+            let _ = text.push_str(setting.unit);
+            for ch in text.chars() {
+                if out.push(ch).is_err() {
+                    break;
+                }
+            }
+        }
+        MenuItem::Choice(setting) => {
+            let option = usize::try_from(setting.clamp(value))
+                .ok()
+                .and_then(|index| setting.options.get(index))
+                .copied()
+                .unwrap_or("");
+            for ch in option.chars() {
+                if out.push(ch).is_err() {
+                    break;
+                }
+            }
+        }
+        MenuItem::Submenu { .. } | MenuItem::Action { .. } => {}
+    }
+}
+
+fn write_fixed_point<W: core::fmt::Write>(
+    out: &mut W,
+    value: i32,
+    decimals: u8,
+) -> core::fmt::Result {
+    if decimals == 0 {
+        return write!(out, "{value}");
+    }
+    let scale = 10i64.pow(u32::from(decimals.min(6)));
+    let value = i64::from(value);
+    let sign = if value < 0 { "-" } else { "" };
+    let magnitude = value.abs();
+    write!(
+        out,
+        "{sign}{}.{:0width$}",
+        magnitude / scale,
+        magnitude % scale,
+        width = usize::from(decimals.min(6))
+    )
+}
+
+/// メニュー入力の取得元。`poll` は tick ごとに呼ばれ、入力が無ければ `Ok(None)` を返す。
+pub trait MenuInputSource {
+    type Error;
+
+    fn poll(&mut self) -> Result<Option<MenuInput>, Self::Error>;
+}
+
+/// ボタン読み取りの設定。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ButtonConfig {
+    /// 押下で LOW になる配線 (プルアップ) なら true
+    pub active_low: bool,
+    /// 同じ値がこの回数続いたら状態を確定する
+    pub debounce_polls: u8,
+    /// Select をこの回数以上押し続けたら Back として扱う。0 なら長押し無効
+    pub long_press_polls: u16,
+}
+
+impl Default for ButtonConfig {
+    fn default() -> Self {
+        Self {
+            active_low: true,
+            debounce_polls: 2,
+            long_press_polls: 100,
+        }
+    }
+}
+
+/// チャタリング除去と押下時間を持つボタン 1 個分の状態。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct DebouncedButton {
+    pressed: bool,
+    candidate: bool,
+    stable_polls: u8,
+    held_polls: u16,
+    long_fired: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ButtonEdge {
+    Pressed,
+    /// 長押しにならずに離した
+    Released,
+    LongPress,
+}
+
+impl DebouncedButton {
+    fn update(&mut self, raw: bool, config: &ButtonConfig) -> Option<ButtonEdge> {
+        if raw == self.candidate {
+            self.stable_polls = self.stable_polls.saturating_add(1);
+        } else {
+            self.candidate = raw;
+            self.stable_polls = 1;
+        }
+
+        if self.pressed {
+            self.held_polls = self.held_polls.saturating_add(1);
+        }
+
+        if self.stable_polls >= config.debounce_polls.max(1) && self.candidate != self.pressed {
+            self.pressed = self.candidate;
+            if self.pressed {
+                self.held_polls = 0;
+                self.long_fired = false;
+                return Some(ButtonEdge::Pressed);
+            }
+            return if self.long_fired {
+                None
+            } else {
+                Some(ButtonEdge::Released)
+            };
+        }
+
+        if self.pressed
+            && !self.long_fired
+            && config.long_press_polls != 0
+            && self.held_polls >= config.long_press_polls
+        {
+            self.long_fired = true;
+            return Some(ButtonEdge::LongPress);
+        }
+        None
+    }
+}
+
+fn read_button<P>(pin: &P, active_low: bool) -> Result<bool, GpioError>
+where
+    P: InputPin<Error = GpioError>,
+{
+    if active_low {
+        pin.is_low()
+    } else {
+        pin.is_high()
+    }
+}
+
+/// Up / Down / Select の 3 ボタン入力。
+///
+/// Up / Down は押した瞬間、Select は離した瞬間に入力になる。Select の長押しは Back。
+pub struct ButtonInput<UP, DOWN, SELECT> {
+    up: UP,
+    down: DOWN,
+    select: SELECT,
+    config: ButtonConfig,
+    states: [DebouncedButton; 3],
+}
+
+impl<UP, DOWN, SELECT> ButtonInput<UP, DOWN, SELECT>
+where
+    UP: InputPin<Error = GpioError>,
+    DOWN: InputPin<Error = GpioError>,
+    SELECT: InputPin<Error = GpioError>,
+{
+    pub fn new(up: UP, down: DOWN, select: SELECT) -> Self {
+        Self::new_with_config(up, down, select, ButtonConfig::default())
+    }
+
+    pub fn new_with_config(up: UP, down: DOWN, select: SELECT, config: ButtonConfig) -> Self {
+        Self {
+            up,
+            down,
+            select,
+            config,
+            states: [DebouncedButton::default(); 3],
+        }
+    }
+
+    pub fn config(&self) -> ButtonConfig {
+        self.config
+    }
+}
+
+impl<UP, DOWN, SELECT> MenuInputSource for ButtonInput<UP, DOWN, SELECT>
+where
+    UP: InputPin<Error = GpioError>,
+    DOWN: InputPin<Error = GpioError>,
+    SELECT: InputPin<Error = GpioError>,
+{
+    type Error = GpioError;
+
+    fn poll(&mut self) -> Result<Option<MenuInput>, Self::Error> {
+        let raw = [
+            read_button(&self.up, self.config.active_low)?,
+            read_button(&self.down, self.config.active_low)?,
+            read_button(&self.select, self.config.active_low)?,
+        ];
+        let config = self.config;
+        let edges = [
+            self.states[0].update(raw[0], &config),
+            self.states[1].update(raw[1], &config),
+            self.states[2].update(raw[2], &config),
+        ];
+        Ok(match edges {
+            [Some(ButtonEdge::Pressed), _, _] => Some(MenuInput::Up),
+            [_, Some(ButtonEdge::Pressed), _] => Some(MenuInput::Down),
+            [_, _, Some(ButtonEdge::Released)] => Some(MenuInput::Select),
+            [_, _, Some(ButtonEdge::LongPress)] => Some(MenuInput::Back),
+            _ => None,
+        })
+    }
+}
+
+/// `RotaryInput` が返すエラー型。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RotaryInputError<E> {
+    Encoder(E),
+    Button(GpioError),
+}
+
+/// 押しボタン付きロータリーエンコーダ入力。
+///
+/// `counts_per_detent` カウントごとに 1 入力を出す。時計回り (カウント増加) が Down。
+/// 1 回の `poll` で返す入力は 1 つだけで、残りのクリックは次回以降に持ち越す。
+pub struct RotaryInput<E, P> {
+    encoder: E,
+    button: P,
+    counts_per_detent: i32,
+    config: ButtonConfig,
+    last_count: Option<i32>,
+    pending_counts: i32,
+    button_state: DebouncedButton,
+}
+
+impl<E, P> RotaryInput<E, P>
+where
+    E: WheelEncoder,
+    P: InputPin<Error = GpioError>,
+{
+    pub fn new(encoder: E, button: P, counts_per_detent: i32) -> Self {
+        Self::new_with_config(encoder, button, counts_per_detent, ButtonConfig::default())
+    }
+
+    pub fn new_with_config(
+        encoder: E,
+        button: P,
+        counts_per_detent: i32,
+        config: ButtonConfig,
+    ) -> Self {
+        Self {
+            encoder,
+            button,
+            counts_per_detent: counts_per_detent.max(1),
+            config,
+            last_count: None,
+            pending_counts: 0,
+            button_state: DebouncedButton::default(),
+        }
+    }
+}
+
+impl<E, P> MenuInputSource for RotaryInput<E, P>
+where
+    E: WheelEncoder,
+    P: InputPin<Error = GpioError>,
+{
+    type Error = RotaryInputError<E::Error>;
+
+    fn poll(&mut self) -> Result<Option<MenuInput>, Self::Error> {
+        let count = self
+            .encoder
+            .read_count()
+            .map_err(RotaryInputError::Encoder)?;
+        if let Some(last) = self.last_count {
+            self.pending_counts = self.pending_counts.saturating_add(count.wrapping_sub(last));
+        }
+        self.last_count = Some(count);
+
+        let pressed =
+            read_button(&self.button, self.config.active_low).map_err(RotaryInputError::Button)?;
+        match self.button_state.update(pressed, &self.config) {
+            Some(ButtonEdge::Released) => return Ok(Some(MenuInput::Select)),
+            Some(ButtonEdge::LongPress) => return Ok(Some(MenuInput::Back)),
+            _ => {}
+        }
+
+        if self.pending_counts >= self.counts_per_detent {
+            self.pending_counts -= self.counts_per_detent;
+            Ok(Some(MenuInput::Down))
+        } else if self.pending_counts <= -self.counts_per_detent {
+            self.pending_counts += self.counts_per_detent;
+            Ok(Some(MenuInput::Up))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// `MenuApp` が返すエラー型。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuAppError<IE, SE> {
+    Input(IE),
+    Menu(MenuError<SE>),
+    Display(DisplayError),
+}
+
+impl<IE, SE> From<MenuError<SE>> for MenuAppError<IE, SE> {
+    fn from(error: MenuError<SE>) -> Self {
+        Self::Menu(error)
+    }
+}
+
+/// 入力を読み、メニューを進め、変化があった時だけ再描画するアプリ。
+pub struct MenuApp<INPUT, DISPLAY, STORE, const DEPTH: usize> {
+    input: INPUT,
+    display: DISPLAY,
+    store: STORE,
+    menu: Menu<DEPTH>,
+    tick_count: u32,
+    last_frame: Option<TextFrame16x2>,
+}
+
+impl<INPUT, DISPLAY, STORE, const DEPTH: usize> MenuApp<INPUT, DISPLAY, STORE, DEPTH>
+where
+    INPUT: MenuInputSource,
+    DISPLAY: TextDisplay16x2<Error = DisplayError>,
+    STORE: SettingsStore,
+{
+    pub fn new(input: INPUT, display: DISPLAY, store: STORE, menu: Menu<DEPTH>) -> Self {
+        Self {
+            input,
+            display,
+            store,
+            menu,
+            tick_count: 0,
+            last_frame: None,
+        }
+    }
+
+    /// 入力を 1 回読み、必要なら再描画する。
+    pub fn tick(&mut self) -> Result<Option<MenuEvent>, MenuAppError<INPUT::Error, STORE::Error>> {
+        self.tick_count = self.tick_count.wrapping_add(1);
+        let event = match self.input.poll().map_err(MenuAppError::Input)? {
+            Some(input) => self.menu.handle(input, &mut self.store)?,
+            None => None,
+        };
+        self.refresh()?;
+        Ok(event)
+    }
+
+    /// 現在の画面を描画する。前回と同じフレームなら何もしない。
+    pub fn refresh(&mut self) -> Result<(), MenuAppError<INPUT::Error, STORE::Error>> {
+        let frame = self.menu.render(&mut self.store)?;
+        if self.last_frame != Some(frame) {
+            self.display.render(&frame).map_err(MenuAppError::Display)?;
+            self.last_frame = Some(frame);
+        }
+        Ok(())
+    }
+
+    pub fn menu(&self) -> &Menu<DEPTH> {
+        &self.menu
+    }
+
+    pub fn menu_mut(&mut self) -> &mut Menu<DEPTH> {
+        &mut self.menu
+    }
+
+    pub fn store(&self) -> &STORE {
+        &self.store
+    }
+
+    pub fn store_mut(&mut self) -> &mut STORE {
+        &mut self.store
+    }
+
+    pub fn last_frame(&self) -> Option<TextFrame16x2> {
+        self.last_frame
+    }
+
+    pub fn tick_count(&self) -> u32 {
+        self.tick_count
+    }
+
+    pub fn into_parts(self) -> (INPUT, DISPLAY, STORE) {
+        (self.input, self.display, self.store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    const KEY_REFRESH: SettingKey = 1;
+    const KEY_UNIT: SettingKey = 2;
+    const KEY_OFFSET: SettingKey = 3;
+    const ACTION_RESET: u16 = 7;
+
+    static UNITS: [&str; 2] = ["Celsius", "Fahrenheit"];
+    static CALIBRATION: [MenuItem; 1] = [MenuItem::Number(
+        NumberSetting::new("Offset", KEY_OFFSET, -50, 50, 0)
+            .step(5)
+            .decimals(1)
+            .unit("C"),
+    )];
+    static ROOT: [MenuItem; 4] = [
+        MenuItem::Number(NumberSetting::new("Refresh", KEY_REFRESH, 1, 10, 5).unit("s")),
+        MenuItem::Choice(ChoiceSetting::new("Unit", KEY_UNIT, &UNITS)),
+        MenuItem::submenu("Calibrate", &CALIBRATION),
+        MenuItem::confirmed_action("Reset", ACTION_RESET, "Reset all?"),
+    ];
+
+    fn lines(frame: TextFrame16x2) -> (std::string::String, std::string::String) {
+        let line = |row| {
+            std::string::String::from_utf8(frame.line(row).to_vec())
+                .unwrap()
+                .trim_end()
+                .into()
+        };
+        (line(0), line(1))
+    }
+
+    fn press<const D: usize>(
+        menu: &mut Menu<D>,
+        store: &mut MemorySettings<8>,
+        inputs: &[MenuInput],
+    ) -> Option<MenuEvent> {
+        let mut last = None;
+        for input in inputs {
+            last = menu.handle(*input, store).unwrap();
+        }
+        last
+    }
+
+    #[test]
+    fn browse_renders_cursor_window_with_values() {
+        let mut store = MemorySettings::<8>::new();
+        let mut menu: Menu<2> = Menu::new("Settings", &ROOT);
+        assert_eq!(
+            lines(menu.render(&mut store).unwrap()),
+            (">Refresh      5s".into(), " Unit    Celsius".into())
+        );
+
+        press(&mut menu, &mut store, &[MenuInput::Down, MenuInput::Down]);
+        assert_eq!(
+            lines(menu.render(&mut store).unwrap()),
+            (">Calibrate     >".into(), " Reset".into())
+        );
+
+        // 末尾の Back 行と、先頭からの巻き戻り
+        press(&mut menu, &mut store, &[MenuInput::Down, MenuInput::Down]);
+        assert_eq!(
+            lines(menu.render(&mut store).unwrap()),
+            (" Reset".into(), ">< Back".into())
+        );
+        press(&mut menu, &mut store, &[MenuInput::Down]);
+        assert_eq!(menu.selected().map(MenuItem::label), Some("Refresh"));
+    }
+
+    #[test]
+    fn number_edit_clamps_and_commits_on_select() {
+        let mut store = MemorySettings::<8>::new();
+        let mut menu: Menu<2> = Menu::new("Settings", &ROOT);
+        press(&mut menu, &mut store, &[MenuInput::Select]);
+        assert_eq!(menu.mode(), MenuMode::Edit { value: 5 });
+
+        let ups = [MenuInput::Up; 8];
+        press(&mut menu, &mut store, &ups);
+        assert_eq!(
+            lines(menu.render(&mut store).unwrap()),
+            ("Refresh".into(), "= 10s".into())
+        );
+        assert_eq!(store.get(KEY_REFRESH), None);
+
+        let event = press(&mut menu, &mut store, &[MenuInput::Select]);
+        assert_eq!(
+            event,
+            Some(MenuEvent::Changed {
+                key: KEY_REFRESH,
+                value: 10
+            })
+        );
+        assert_eq!(store.get(KEY_REFRESH), Some(10));
+        assert_eq!(menu.mode(), MenuMode::Browse);
+    }
+
+    #[test]
+    fn back_discards_edit() {
+        let mut store = MemorySettings::<8>::new();
+        let mut menu: Menu<2> = Menu::new("Settings", &ROOT);
+        press(
+            &mut menu,
+            &mut store,
+            &[
+                MenuInput::Select,
+                MenuInput::Down,
+                MenuInput::Down,
+                MenuInput::Back,
+            ],
+        );
+        assert_eq!(menu.mode(), MenuMode::Browse);
+        assert_eq!(store.get(KEY_REFRESH), None);
+        assert_eq!(
+            ROOT[0],
+            MenuItem::Number(NumberSetting::new("Refresh", KEY_REFRESH, 1, 10, 5).unit("s"))
+        );
+    }
+
+    #[test]
+    fn choice_picker_cycles_options() {
+        let mut store = MemorySettings::<8>::new();
+        let mut menu: Menu<2> = Menu::new("Settings", &ROOT);
+        let event = press(
+            &mut menu,
+            &mut store,
+            &[
+                MenuInput::Down,
+                MenuInput::Select,
+                MenuInput::Up,
+                MenuInput::Up,
+                MenuInput::Down,
+                MenuInput::Select,
+            ],
+        );
+        assert_eq!(
+            event,
+            Some(MenuEvent::Changed {
+                key: KEY_UNIT,
+                value: 1
+            })
+        );
+        if let MenuItem::Choice(setting) = ROOT[1] {
+            assert_eq!(setting.option(&mut store).unwrap(), "Fahrenheit");
+        }
+        assert_eq!(
+            lines(menu.render(&mut store).unwrap()).0,
+            ">Unit Fahrenheit"
+        );
+    }
+
+    #[test]
+    fn nested_menu_formats_fixed_point_and_returns_via_back_row() {
+        let mut store = MemorySettings::<8>::new();
+        let mut menu: Menu<2> = Menu::new("Settings", &ROOT);
+        press(
+            &mut menu,
+            &mut store,
+            &[MenuInput::Down, MenuInput::Down, MenuInput::Select],
+        );
+        assert_eq!(menu.depth(), 1);
+        assert_eq!(menu.title(), "Calibrate");
+        assert_eq!(
+            lines(menu.render(&mut store).unwrap()),
+            (">Offset     0.0C".into(), " < Back".into())
+        );
+
+        press(
+            &mut menu,
+            &mut store,
+            &[
+                MenuInput::Select,
+                MenuInput::Down,
+                MenuInput::Down,
+                MenuInput::Down,
+            ],
+        );
+        assert_eq!(
+            lines(menu.render(&mut store).unwrap()),
+            ("Offset".into(), "= -1.5C".into())
+        );
+        press(&mut menu, &mut store, &[MenuInput::Select]);
+        assert_eq!(store.get(KEY_OFFSET), Some(-15));
+
+        press(&mut menu, &mut store, &[MenuInput::Down, MenuInput::Select]);
+        assert_eq!(menu.depth(), 0);
+        assert_eq!(menu.selected().map(MenuItem::label), Some("Calibrate"));
+    }
+
+    #[test]
+    fn confirmation_defaults_to_no() {
+        let mut store = MemorySettings::<8>::new();
+        let mut menu: Menu<2> = Menu::new("Settings", &ROOT);
+        let to_reset = [MenuInput::Up, MenuInput::Up, MenuInput::Select];
+        assert_eq!(press(&mut menu, &mut store, &to_reset), None);
+        assert_eq!(
+            lines(menu.render(&mut store).unwrap()),
+            ("Reset all?".into(), ">No   Yes".into())
+        );
+        assert_eq!(press(&mut menu, &mut store, &[MenuInput::Select]), None);
+
+        let event = press(
+            &mut menu,
+            &mut store,
+            &[MenuInput::Select, MenuInput::Down, MenuInput::Select],
+        );
+        assert_eq!(event, Some(MenuEvent::Action(ACTION_RESET)));
+    }
+
+    #[test]
+    fn root_back_exits_and_depth_limit_is_reported() {
+        let mut store = MemorySettings::<8>::new();
+        let mut menu: Menu<2> = Menu::new("Settings", &ROOT);
+        assert_eq!(
+            press(&mut menu, &mut store, &[MenuInput::Back]),
+            Some(MenuEvent::Exit)
+        );
+
+        let mut shallow: Menu<0> = Menu::new("Settings", &ROOT);
+        let mut store = MemorySettings::<8>::new();
+        shallow.handle(MenuInput::Down, &mut store).unwrap();
+        shallow.handle(MenuInput::Down, &mut store).unwrap();
+        assert_eq!(
+            shallow.handle(MenuInput::Select, &mut store),
+            Err(MenuError::TooDeep)
+        );
+    }
+
+    #[test]
+    fn stored_values_are_clamped_on_load() {
+        let mut store = MemorySettings::<8>::new();
+        store.store(KEY_REFRESH, 99).unwrap();
+        store.store(KEY_UNIT, -3).unwrap();
+        if let (MenuItem::Number(refresh), MenuItem::Choice(unit)) = (ROOT[0], ROOT[1]) {
+            assert_eq!(refresh.load(&mut store).unwrap(), 10);
+            assert_eq!(unit.load(&mut store).unwrap(), 0);
+        }
+    }
+
+    #[derive(Clone)]
+    struct FakeButton(Rc<Cell<bool>>);
+
+    impl InputPin for FakeButton {
+        type Error = GpioError;
+
+        fn is_high(&self) -> Result<bool, Self::Error> {
+            Ok(self.0.get())
+        }
+
+        fn is_low(&self) -> Result<bool, Self::Error> {
+            Ok(!self.0.get())
+        }
+    }
+
+    fn fake_button() -> FakeButton {
+        FakeButton(Rc::new(Cell::new(false)))
+    }
+
+    #[test]
+    fn buttons_are_debounced_and_long_select_is_back() {
+        let (up, down, select) = (fake_button(), fake_button(), fake_button());
+        let mut input = ButtonInput::new_with_config(
+            up.clone(),
+            down.clone(),
+            select.clone(),
+            ButtonConfig {
+                active_low: false,
+                debounce_polls: 2,
+                long_press_polls: 5,
+            },
+        );
+
+        // 1 回だけの HIGH はチャタリングとして無視する
+        up.0.set(true);
+        assert_eq!(input.poll().unwrap(), None);
+        up.0.set(false);
+        assert_eq!(input.poll().unwrap(), None);
+
+        up.0.set(true);
+        assert_eq!(input.poll().unwrap(), None);
+        assert_eq!(input.poll().unwrap(), Some(MenuInput::Up));
+        assert_eq!(input.poll().unwrap(), None);
+        up.0.set(false);
+        input.poll().unwrap();
+        input.poll().unwrap();
+
+        select.0.set(true);
+        input.poll().unwrap();
+        input.poll().unwrap();
+        select.0.set(false);
+        input.poll().unwrap();
+        assert_eq!(input.poll().unwrap(), Some(MenuInput::Select));
+
+        select.0.set(true);
+        let polled: std::vec::Vec<_> = (0..10).map(|_| input.poll().unwrap()).collect();
+        assert_eq!(
+            polled.iter().flatten().collect::<std::vec::Vec<_>>(),
+            [&MenuInput::Back]
+        );
+        select.0.set(false);
+        input.poll().unwrap();
+        assert_eq!(input.poll().unwrap(), None);
+    }
+
+    struct FakeEncoder(Rc<Cell<i32>>);
+
+    impl WheelEncoder for FakeEncoder {
+        type Error = ();
+
+        fn read_count(&mut self) -> Result<i32, Self::Error> {
+            Ok(self.0.get())
+        }
+    }
+
+    #[test]
+    fn rotary_emits_one_input_per_detent() {
+        let count = Rc::new(Cell::new(100));
+        let mut input = RotaryInput::new(FakeEncoder(count.clone()), fake_button_high(), 4);
+        assert_eq!(input.poll().unwrap(), None);
+
+        count.set(108);
+        assert_eq!(input.poll().unwrap(), Some(MenuInput::Down));
+        assert_eq!(input.poll().unwrap(), Some(MenuInput::Down));
+        assert_eq!(input.poll().unwrap(), None);
+
+        count.set(101);
+        assert_eq!(input.poll().unwrap(), Some(MenuInput::Up));
+        assert_eq!(input.poll().unwrap(), None);
+    }
+
+    fn fake_button_high() -> FakeButton {
+        // active_low 既定なので HIGH = 離している
+        FakeButton(Rc::new(Cell::new(true)))
+    }
+}
@@ -0,0 +1,381 @@
+//! OpenMetrics テキスト形式 (Prometheus の text exposition と互換) の書き出し。
+//!
+//! [`MetricsWriter`] は呼び出し側のバッファへヒープ無しで書き出す。WiFi ファームウェアの
+//! `GET /metrics` と host 側 `device-dashboard-web` の `/metrics` で同じ書き出し方を使い、
+//! 実機と simulator を同じ Grafana ダッシュボードで scrape できるようにする。
+//!
+//! - メトリクス名は `<namespace>_<name>`。カウンタのサンプルには `_total` が付く
+//! - 値は [`FieldValue`] (整数か固定小数点) で、浮動小数点は使わない
+//! - サンプルに時刻は付けない (scrape 時刻を使う)
+//! - [`TelemetryFrame::write_metrics`] は各フィールドを `device_id` ラベル付きの gauge にする
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::metrics::{MetricType, MetricsWriter};
+//! use core_app::telemetry::{FieldValue, TelemetryFrame};
+//! use hal_api::sensor::EnvReading;
+//!
+//! let mut buffer = [0u8; 512];
+//! let mut metrics = MetricsWriter::new(&mut buffer, "mcu");
+//! TelemetryFrame::new("esp32-01")
+//!     .with_env(EnvReading::new(2485, 4320, None))
+//!     .write_metrics(&mut metrics)
+//!     .unwrap();
+//! metrics
+//!     .family("http_posts", MetricType::Counter, "Reading POSTs by result")
+//!     .unwrap();
+//! metrics
+//!     .sample("http_posts", &[("result", "ok")], FieldValue::Int(3))
+//!     .unwrap();
+//! let len = metrics.finish().unwrap();
+//!
+//! let text = core::str::from_utf8(&buffer[..len]).unwrap();
+//! assert!(text.contains("# TYPE mcu_temperature_celsius gauge\n"));
+//! assert!(text.contains("mcu_temperature_celsius{device_id=\"esp32-01\"} 24.85\n"));
+//! assert!(text.contains("mcu_http_posts_total{result=\"ok\"} 3\n"));
+//! assert!(text.ends_with("# EOF\n"));
+//! ```
+
+use core::fmt::{self, Write};
+
+use crate::telemetry::{write_decimal, ByteWriter, FieldValue, TelemetryFrame};
+
+/// 実機と simulator で揃える既定の namespace。
+pub const DEFAULT_NAMESPACE: &str = "mcu";
+
+/// HTTP の `Content-Type`。
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// メトリクスの種類。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricType {
+    Gauge,
+    /// 単調増加。サンプル名に `_total` が付く
+    Counter,
+}
+
+impl MetricType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MetricType::Gauge => "gauge",
+            MetricType::Counter => "counter",
+        }
+    }
+}
+
+/// [`MetricsWriter`] が返すエラー型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsError {
+    /// 出力バッファが足りない
+    BufferTooSmall,
+    /// メトリクス名・ラベル名に使えない文字がある
+    InvalidName,
+}
+
+impl From<fmt::Error> for MetricsError {
+    fn from(_: fmt::Error) -> Self {
+        MetricsError::BufferTooSmall
+    }
+}
+
+/// OpenMetrics テキストを固定長バッファへ書き出す。
+///
+/// [`family`](Self::family) でメタデータ (`# TYPE` / `# HELP`) を書き、続けて同じ名前で
+/// [`sample`](Self::sample) を必要なだけ書く。最後に [`finish`](Self::finish) で `# EOF` を付ける。
+pub struct MetricsWriter<'b, 'n> {
+    out: ByteWriter<'b>,
+    namespace: &'n str,
+    /// 直前の `family` がカウンタか
+    counter: bool,
+}
+
+impl<'b, 'n> MetricsWriter<'b, 'n> {
+    /// `namespace` は空でもよい (その場合は接頭辞なし)。
+    pub fn new(out: &'b mut [u8], namespace: &'n str) -> Self {
+        Self {
+            out: ByteWriter::new(out),
+            namespace,
+            counter: false,
+        }
+    }
+
+    /// メトリクスファミリーのメタデータを書く。
+    pub fn family(&mut self, name: &str, kind: MetricType, help: &str) -> Result<(), MetricsError> {
+        self.check_metric_name(name)?;
+        self.counter = kind == MetricType::Counter;
+        self.out.push(b"# TYPE ")?;
+        self.write_name(name)?;
+        write!(self.out, " {}\n# HELP ", kind.as_str())?;
+        self.write_name(name)?;
+        self.out.push(b" ")?;
+        write_escaped(&mut self.out, help)?;
+        self.out.push(b"\n")?;
+        Ok(())
+    }
+
+    /// 直前の [`family`](Self::family) に属するサンプルを 1 行書く。
+    pub fn sample(
+        &mut self,
+        name: &str,
+        labels: &[(&str, &str)],
+        value: FieldValue,
+    ) -> Result<(), MetricsError> {
+        self.check_metric_name(name)?;
+        if !labels.iter().all(|(label, _)| is_label_name(label)) {
+            return Err(MetricsError::InvalidName);
+        }
+        self.write_name(name)?;
+        if self.counter {
+            self.out.push(b"_total")?;
+        }
+        for (i, (label, value)) in labels.iter().enumerate() {
+            self.out.push(if i == 0 { b"{" } else { b"," })?;
+            write!(self.out, "{label}=\"")?;
+            write_escaped(&mut self.out, value)?;
+            self.out.push(b"\"")?;
+        }
+        if !labels.is_empty() {
+            self.out.push(b"}")?;
+        }
+        self.out.push(b" ")?;
+        write_decimal(&mut self.out, value)?;
+        self.out.push(b"\n")?;
+        Ok(())
+    }
+
+    /// 書き出し済みのバイト数。
+    pub fn len(&self) -> usize {
+        self.out.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.out.len() == 0
+    }
+
+    /// `# EOF` を付けて全体の長さを返す。
+    pub fn finish(mut self) -> Result<usize, MetricsError> {
+        self.out.push(b"# EOF\n")?;
+        Ok(self.out.len())
+    }
+
+    fn write_name(&mut self, name: &str) -> fmt::Result {
+        if !self.namespace.is_empty() {
+            self.out.push(self.namespace.as_bytes())?;
+            self.out.push(b"_")?;
+        }
+        self.out.push(name.as_bytes())
+    }
+
+    fn check_metric_name(&self, name: &str) -> Result<(), MetricsError> {
+        let first = if self.namespace.is_empty() {
+            name
+        } else {
+            self.namespace
+        };
+        let valid = first
+            .bytes()
+            .next()
+            .is_some_and(|b| b.is_ascii_alphabetic() || b == b'_' || b == b':')
+            && self
+                .namespace
+                .bytes()
+                .chain(name.bytes())
+                .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b':')
+            && !name.is_empty();
+        if valid {
+            Ok(())
+        } else {
+            Err(MetricsError::InvalidName)
+        }
+    }
+}
+
+impl TelemetryFrame<'_> {
+    /// 載っている読み取り値を `device_id` ラベル付きの gauge として書く。
+    ///
+    /// メトリクス名はフィールド名に単位を補ったもの (`temperature` → `temperature_celsius`)。
+    pub fn write_metrics(&self, metrics: &mut MetricsWriter<'_, '_>) -> Result<(), MetricsError> {
+        let labels = [("device_id", self.device_id)];
+        let mut result = Ok(());
+        self.for_each_field(|field, value| {
+            if result.is_ok() {
+                let (name, help) = field_metric(field);
+                result = metrics
+                    .family(name, MetricType::Gauge, help)
+                    .and_then(|_| metrics.sample(name, &labels, value));
+            }
+        });
+        result
+    }
+}
+
+/// テレメトリのフィールド名に対応するメトリクス名と説明。
+fn field_metric(field: &'static str) -> (&'static str, &'static str) {
+    match field {
+        "temperature" => ("temperature_celsius", "Air temperature"),
+        "humidity" => ("humidity_percent", "Relative humidity"),
+        "pressure_pa" => ("pressure_pascals", "Barometric pressure"),
+        "co2_ppm" => ("co2_ppm", "Equivalent CO2 concentration"),
+        "voc_ppb" => ("voc_ppb", "Total VOC concentration"),
+        "lux" => ("illuminance_lux", "Ambient light"),
+        "accel_x_mg" => ("accel_x_mg", "Acceleration X axis in milli-g"),
+        "accel_y_mg" => ("accel_y_mg", "Acceleration Y axis in milli-g"),
+        "accel_z_mg" => ("accel_z_mg", "Acceleration Z axis in milli-g"),
+        "gyro_x_mdps" => (
+            "gyro_x_mdps",
+            "Angular rate X axis in milli-degrees per second",
+        ),
+        "gyro_y_mdps" => (
+            "gyro_y_mdps",
+            "Angular rate Y axis in milli-degrees per second",
+        ),
+        "gyro_z_mdps" => (
+            "gyro_z_mdps",
+            "Angular rate Z axis in milli-degrees per second",
+        ),
+        "imu_temperature" => ("imu_temperature_celsius", "IMU die temperature"),
+        "distance_mm" => ("distance_mm", "Measured distance"),
+        other => (other, "Telemetry field"),
+    }
+}
+
+/// ラベル値と HELP のエスケープ (`\` / `"` / 改行)。
+fn write_escaped(out: &mut ByteWriter<'_>, value: &str) -> fmt::Result {
+    for ch in value.chars() {
+        match ch {
+            '\\' => out.push(b"\\\\")?,
+            '"' => out.push(b"\\\"")?,
+            '\n' => out.push(b"\\n")?,
+            ch => out.write_char(ch)?,
+        }
+    }
+    Ok(())
+}
+
+fn is_label_name(name: &str) -> bool {
+    name.bytes()
+        .next()
+        .is_some_and(|b| b.is_ascii_alphabetic() || b == b'_')
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hal_api::distance::DistanceReading;
+    use hal_api::gas::GasReading;
+    use hal_api::sensor::EnvReading;
+
+    extern crate std;
+
+    fn render(f: impl FnOnce(&mut MetricsWriter<'_, '_>)) -> std::string::String {
+        let mut buffer = [0u8; 1024];
+        let mut metrics = MetricsWriter::new(&mut buffer, DEFAULT_NAMESPACE);
+        f(&mut metrics);
+        let len = metrics.finish().unwrap();
+        std::string::String::from_utf8(buffer[..len].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn frame_fields_become_labelled_gauges() {
+        let frame = TelemetryFrame::new("lab \"1\"")
+            .with_env(EnvReading::new(-525, 4320, Some(101_325)))
+            .with_gas(GasReading::new(612, 35))
+            .with_distance(DistanceReading::new(742));
+        let text = render(|metrics| frame.write_metrics(metrics).unwrap());
+        assert_eq!(
+            text,
+            concat!(
+                "# TYPE mcu_temperature_celsius gauge\n",
+                "# HELP mcu_temperature_celsius Air temperature\n",
+                "mcu_temperature_celsius{device_id=\"lab \\\"1\\\"\"} -5.25\n",
+                "# TYPE mcu_humidity_percent gauge\n",
+                "# HELP mcu_humidity_percent Relative humidity\n",
+                "mcu_humidity_percent{device_id=\"lab \\\"1\\\"\"} 43.20\n",
+                "# TYPE mcu_pressure_pascals gauge\n",
+                "# HELP mcu_pressure_pascals Barometric pressure\n",
+                "mcu_pressure_pascals{device_id=\"lab \\\"1\\\"\"} 101325\n",
+                "# TYPE mcu_co2_ppm gauge\n",
+                "# HELP mcu_co2_ppm Equivalent CO2 concentration\n",
+                "mcu_co2_ppm{device_id=\"lab \\\"1\\\"\"} 612\n",
+                "# TYPE mcu_voc_ppb gauge\n",
+                "# HELP mcu_voc_ppb Total VOC concentration\n",
+                "mcu_voc_ppb{device_id=\"lab \\\"1\\\"\"} 35\n",
+                "# TYPE mcu_distance_mm gauge\n",
+                "# HELP mcu_distance_mm Measured distance\n",
+                "mcu_distance_mm{device_id=\"lab \\\"1\\\"\"} 742\n",
+                "# EOF\n",
+            )
+        );
+    }
+
+    #[test]
+    fn counters_get_total_suffix_and_multiple_samples() {
+        let text = render(|metrics| {
+            metrics
+                .family(
+                    "i2c_operations",
+                    MetricType::Counter,
+                    "I2C transfers\nper address",
+                )
+                .unwrap();
+            for (address, count) in [("0x27", 10), ("0x77", 4)] {
+                metrics
+                    .sample(
+                        "i2c_operations",
+                        &[("address", address), ("op", "write")],
+                        FieldValue::Int(count),
+                    )
+                    .unwrap();
+            }
+            metrics.family("up", MetricType::Gauge, "Always 1").unwrap();
+            metrics.sample("up", &[], FieldValue::Int(1)).unwrap();
+        });
+        assert_eq!(
+            text,
+            concat!(
+                "# TYPE mcu_i2c_operations counter\n",
+                "# HELP mcu_i2c_operations I2C transfers\\nper address\n",
+                "mcu_i2c_operations_total{address=\"0x27\",op=\"write\"} 10\n",
+                "mcu_i2c_operations_total{address=\"0x77\",op=\"write\"} 4\n",
+                "# TYPE mcu_up gauge\n",
+                "# HELP mcu_up Always 1\n",
+                "mcu_up 1\n",
+                "# EOF\n",
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names_and_small_buffers() {
+        let mut buffer = [0u8; 256];
+        let mut metrics = MetricsWriter::new(&mut buffer, "");
+        assert_eq!(
+            metrics.family("1st", MetricType::Gauge, ""),
+            Err(MetricsError::InvalidName)
+        );
+        assert_eq!(
+            metrics.family("tick-rate", MetricType::Gauge, ""),
+            Err(MetricsError::InvalidName)
+        );
+        assert_eq!(
+            metrics.sample("x", &[("bad-label", "v")], FieldValue::Int(0)),
+            Err(MetricsError::InvalidName)
+        );
+        let mut buffer = [0u8; 256];
+        let mut metrics = MetricsWriter::new(&mut buffer, "a/b");
+        assert_eq!(
+            metrics.family("x", MetricType::Gauge, ""),
+            Err(MetricsError::InvalidName)
+        );
+
+        let mut small = [0u8; 40];
+        let mut metrics = MetricsWriter::new(&mut small, DEFAULT_NAMESPACE);
+        let frame = TelemetryFrame::new("d").with_env(EnvReading::new(0, 0, None));
+        assert_eq!(
+            frame.write_metrics(&mut metrics),
+            Err(MetricsError::BufferTooSmall)
+        );
+    }
+}
@@ -0,0 +1,2135 @@
+//! MQTT 3.1.1 クライアント (QoS 0 / 1)。
+//!
+//! - [`Packet`] はヒープ無しのパケットエンコーダ / デコーダ。host 側のブローカー代役も
+//!   同じ実装でパケットを読む
+//! - [`MqttClient`] は [`TcpClientSocket`] の上で動く接続管理。[`MqttClient::poll`] を
+//!   メインループから呼ぶと、接続 / keepalive / QoS 1 の再送 / 切断後の再接続と
+//!   再購読を進め、受信したメッセージを [`MqttEvent`] で返す
+//! - [`TopicScheme`] は `<prefix>/<device_id>/...` 形式のトピック名を組み立てる。
+//!   テレメトリは [`MqttClient::publish_telemetry`] で [`TelemetryFrame`] をそのまま送る
+//! - [`CommandDispatcher`] は `<prefix>/<device_id>/cmd/<name>` 宛てのコマンドを
+//!   [`ServoMotor`] / [`DualMotorDriver`] へ振り分ける
+//!
+//! QoS 2、UNSUBSCRIBE、1 つの SUBSCRIBE で複数フィルタを送ることには対応しない
+//! (受信側のデコードは複数フィルタの SUBSCRIBE も読める)。
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::mqtt::{Packet, Publish, QoS};
+//!
+//! let publish = Packet::Publish(Publish {
+//!     topic: "lab/esp32-01/telemetry",
+//!     payload: b"{\"temperature\":23.50}",
+//!     qos: QoS::AtLeastOnce,
+//!     retain: false,
+//!     dup: false,
+//!     packet_id: Some(7),
+//! });
+//! let mut buffer = [0u8; 64];
+//! let len = publish.encode(&mut buffer).unwrap();
+//! assert_eq!(Packet::decode(&buffer[..len]).unwrap(), Some((publish, len)));
+//! // 途中までしか届いていなければ `None`
+//! assert_eq!(Packet::decode(&buffer[..len - 1]).unwrap(), None);
+//! ```
+
+use core::fmt;
+use core::str;
+
+use hal_api::actuator::{DualMotorDriver, MotorCommand, MotorDirection, ServoMotor};
+use hal_api::net::TcpClientSocket;
+use heapless::{String, Vec};
+
+use crate::telemetry::{TelemetryError, TelemetryFormat, TelemetryFrame};
+
+/// MQTT の標準ポート。
+pub const DEFAULT_PORT: u16 = 1883;
+
+/// 同時に PUBACK を待てる QoS 1 メッセージの数。
+pub const MAX_IN_FLIGHT: usize = 4;
+
+/// 再接続時に送り直す購読の数。
+pub const MAX_SUBSCRIPTIONS: usize = 8;
+
+/// 購読できるトピックフィルタの最大バイト数。
+pub const MAX_TOPIC_FILTER_LEN: usize = 64;
+
+/// [`TopicScheme`] で組み立てるトピック名の長さ。
+pub const MAX_TOPIC_LEN: usize = 96;
+
+const PROTOCOL_NAME: &str = "MQTT";
+const PROTOCOL_LEVEL: u8 = 4;
+const MAX_REMAINING_LENGTH: usize = 268_435_455;
+
+/// SUBACK で購読失敗を表す戻り値。
+pub const SUBACK_FAILURE: u8 = 0x80;
+
+// ─── パケット ──────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QoS {
+    AtMostOnce = 0,
+    AtLeastOnce = 1,
+}
+
+impl QoS {
+    fn from_bits(bits: u8) -> Result<Self, PacketError> {
+        match bits {
+            0 => Ok(Self::AtMostOnce),
+            1 => Ok(Self::AtLeastOnce),
+            2 => Err(PacketError::Unsupported),
+            _ => Err(PacketError::Malformed),
+        }
+    }
+}
+
+/// パケットのエンコード / デコードのエラー。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketError {
+    BufferTooSmall,
+    /// MQTT 3.1.1 として不正なバイト列
+    Malformed,
+    /// QoS 2 や UNSUBSCRIBE など、この実装が扱わないパケット
+    Unsupported,
+}
+
+/// CONNECT に載せる last-will。クライアントが DISCONNECT 無しに消えたとき
+/// ブローカーが代わりに publish する。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LastWill<'a> {
+    pub topic: &'a str,
+    pub payload: &'a [u8],
+    pub qos: QoS,
+    pub retain: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Connect<'a> {
+    pub client_id: &'a str,
+    pub keep_alive_secs: u16,
+    pub clean_session: bool,
+    pub will: Option<LastWill<'a>>,
+    pub username: Option<&'a str>,
+    pub password: Option<&'a [u8]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Publish<'a> {
+    pub topic: &'a str,
+    pub payload: &'a [u8],
+    pub qos: QoS,
+    pub retain: bool,
+    /// 再送であることを示す
+    pub dup: bool,
+    /// QoS 1 のときだけ `Some`
+    pub packet_id: Option<u16>,
+}
+
+/// SUBSCRIBE のトピックフィルタ一覧。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscribeFilters<'a> {
+    repr: FiltersRepr<'a>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FiltersRepr<'a> {
+    Single(&'a str, QoS),
+    /// デコード済みの SUBSCRIBE ペイロード (検証済み)
+    Encoded(&'a [u8]),
+}
+
+impl<'a> SubscribeFilters<'a> {
+    pub const fn single(filter: &'a str, qos: QoS) -> Self {
+        Self {
+            repr: FiltersRepr::Single(filter, qos),
+        }
+    }
+
+    /// `(フィルタ, 要求 QoS)` を順に返す。要求 QoS は 0〜2 の生の値。
+    pub fn iter(&self) -> SubscribeFilterIter<'a> {
+        match self.repr {
+            FiltersRepr::Single(filter, qos) => SubscribeFilterIter {
+                single: Some((filter, qos as u8)),
+                encoded: &[],
+            },
+            FiltersRepr::Encoded(encoded) => SubscribeFilterIter {
+                single: None,
+                encoded,
+            },
+        }
+    }
+}
+
+pub struct SubscribeFilterIter<'a> {
+    single: Option<(&'a str, u8)>,
+    encoded: &'a [u8],
+}
+
+impl<'a> Iterator for SubscribeFilterIter<'a> {
+    type Item = (&'a str, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(single) = self.single.take() {
+            return Some(single);
+        }
+        let mut reader = Reader::new(self.encoded);
+        let filter = reader.str().ok()?;
+        let qos = reader.u8().ok()?;
+        self.encoded = reader.rest();
+        Some((filter, qos))
+    }
+}
+
+/// この実装が読み書きする MQTT 3.1.1 パケット。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Packet<'a> {
+    Connect(Connect<'a>),
+    ConnAck {
+        session_present: bool,
+        /// 0 が接続許可。1〜5 は拒否理由
+        return_code: u8,
+    },
+    Publish(Publish<'a>),
+    PubAck {
+        packet_id: u16,
+    },
+    Subscribe {
+        packet_id: u16,
+        filters: SubscribeFilters<'a>,
+    },
+    SubAck {
+        packet_id: u16,
+        /// フィルタごとの許可 QoS (0 / 1) または [`SUBACK_FAILURE`]
+        return_codes: &'a [u8],
+    },
+    PingReq,
+    PingResp,
+    Disconnect,
+}
+
+trait Sink {
+    fn put(&mut self, bytes: &[u8]) -> Result<(), PacketError>;
+
+    fn u16(&mut self, value: u16) -> Result<(), PacketError> {
+        self.put(&value.to_be_bytes())
+    }
+
+    fn binary(&mut self, bytes: &[u8]) -> Result<(), PacketError> {
+        let len = u16::try_from(bytes.len()).map_err(|_| PacketError::Malformed)?;
+        self.u16(len)?;
+        self.put(bytes)
+    }
+
+    fn str(&mut self, value: &str) -> Result<(), PacketError> {
+        self.binary(value.as_bytes())
+    }
+}
+
+struct Counter(usize);
+
+impl Sink for Counter {
+    fn put(&mut self, bytes: &[u8]) -> Result<(), PacketError> {
+        self.0 += bytes.len();
+        Ok(())
+    }
+}
+
+struct Writer<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+}
+
+impl Sink for Writer<'_> {
+    fn put(&mut self, bytes: &[u8]) -> Result<(), PacketError> {
+        let end = self.pos + bytes.len();
+        self.buf
+            .get_mut(self.pos..end)
+            .ok_or(PacketError::BufferTooSmall)?
+            .copy_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], PacketError> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + len)
+            .ok_or(PacketError::Malformed)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, PacketError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, PacketError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn binary(&mut self) -> Result<&'a [u8], PacketError> {
+        let len = self.u16()?;
+        self.take(usize::from(len))
+    }
+
+    fn str(&mut self) -> Result<&'a str, PacketError> {
+        str::from_utf8(self.binary()?).map_err(|_| PacketError::Malformed)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.buf[self.pos..];
+        self.pos = self.buf.len();
+        rest
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos == self.buf.len()
+    }
+}
+
+impl<'a> Packet<'a> {
+    /// `out` へ書き出してバイト数を返す。
+    pub fn encode(&self, out: &mut [u8]) -> Result<usize, PacketError> {
+        let mut counter = Counter(0);
+        self.write_body(&mut counter)?;
+        let remaining = counter.0;
+        if remaining > MAX_REMAINING_LENGTH {
+            return Err(PacketError::Malformed);
+        }
+
+        let mut writer = Writer { buf: out, pos: 0 };
+        writer.put(&[self.header_byte()])?;
+        let mut len = remaining;
+        loop {
+            let mut byte = (len % 128) as u8;
+            len /= 128;
+            if len > 0 {
+                byte |= 0x80;
+            }
+            writer.put(&[byte])?;
+            if len == 0 {
+                break;
+            }
+        }
+        self.write_body(&mut writer)?;
+        Ok(writer.pos)
+    }
+
+    /// 先頭の 1 パケットを読む。揃っていなければ `Ok(None)`、揃っていれば
+    /// パケットと消費したバイト数を返す。
+    pub fn decode(buf: &'a [u8]) -> Result<Option<(Self, usize)>, PacketError> {
+        let Some(&header) = buf.first() else {
+            return Ok(None);
+        };
+        let mut remaining = 0usize;
+        let mut length_bytes = 0usize;
+        loop {
+            let Some(&byte) = buf.get(1 + length_bytes) else {
+                return Ok(None);
+            };
+            remaining |= usize::from(byte & 0x7F) << (7 * length_bytes);
+            length_bytes += 1;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            if length_bytes == 4 {
+                return Err(PacketError::Malformed);
+            }
+        }
+        let start = 1 + length_bytes;
+        let total = start + remaining;
+        if buf.len() < total {
+            return Ok(None);
+        }
+        let packet = Self::decode_body(header, &buf[start..total])?;
+        Ok(Some((packet, total)))
+    }
+
+    fn decode_body(header: u8, body: &'a [u8]) -> Result<Self, PacketError> {
+        let flags = header & 0x0F;
+        let mut reader = Reader::new(body);
+        let packet = match header >> 4 {
+            1 => {
+                if reader.str()? != PROTOCOL_NAME {
+                    return Err(PacketError::Malformed);
+                }
+                if reader.u8()? != PROTOCOL_LEVEL {
+                    return Err(PacketError::Unsupported);
+                }
+                let connect_flags = reader.u8()?;
+                if connect_flags & 0x01 != 0 {
+                    return Err(PacketError::Malformed);
+                }
+                let keep_alive_secs = reader.u16()?;
+                let client_id = reader.str()?;
+                let will = if connect_flags & 0x04 != 0 {
+                    Some(LastWill {
+                        topic: reader.str()?,
+                        payload: reader.binary()?,
+                        qos: QoS::from_bits((connect_flags >> 3) & 0x03)?,
+                        retain: connect_flags & 0x20 != 0,
+                    })
+                } else {
+                    None
+                };
+                let username = if connect_flags & 0x80 != 0 {
+                    Some(reader.str()?)
+                } else {
+                    None
+                };
+                let password = if connect_flags & 0x40 != 0 {
+                    Some(reader.binary()?)
+                } else {
+                    None
+                };
+                Packet::Connect(Connect {
+                    client_id,
+                    keep_alive_secs,
+                    clean_session: connect_flags & 0x02 != 0,
+                    will,
+                    username,
+                    password,
+                })
+            }
+            2 => Packet::ConnAck {
+                session_present: reader.u8()? & 0x01 != 0,
+                return_code: reader.u8()?,
+            },
+            3 => {
+                let qos = QoS::from_bits((flags >> 1) & 0x03)?;
+                let topic = reader.str()?;
+                let packet_id = match qos {
+                    QoS::AtMostOnce => None,
+                    QoS::AtLeastOnce => Some(reader.u16()?),
+                };
+                Packet::Publish(Publish {
+                    topic,
+                    payload: reader.rest(),
+                    qos,
+                    retain: flags & 0x01 != 0,
+                    dup: flags & 0x08 != 0,
+                    packet_id,
+                })
+            }
+            4 => Packet::PubAck {
+                packet_id: reader.u16()?,
+            },
+            8 => {
+                if flags != 0x02 {
+                    return Err(PacketError::Malformed);
+                }
+                let packet_id = reader.u16()?;
+                let encoded = reader.rest();
+                // 先に全フィルタを検証しておき、イテレータでは失敗しないようにする
+                let mut check = Reader::new(encoded);
+                while !check.is_empty() {
+                    check.str()?;
+                    if check.u8()? > 2 {
+                        return Err(PacketError::Malformed);
+                    }
+                }
+                if encoded.is_empty() {
+                    return Err(PacketError::Malformed);
+                }
+                Packet::Subscribe {
+                    packet_id,
+                    filters: SubscribeFilters {
+                        repr: FiltersRepr::Encoded(encoded),
+                    },
+                }
+            }
+            9 => Packet::SubAck {
+                packet_id: reader.u16()?,
+                return_codes: reader.rest(),
+            },
+            12 => Packet::PingReq,
+            13 => Packet::PingResp,
+            14 => Packet::Disconnect,
+            5..=7 | 10 | 11 => return Err(PacketError::Unsupported),
+            _ => return Err(PacketError::Malformed),
+        };
+        if !reader.is_empty() {
+            return Err(PacketError::Malformed);
+        }
+        Ok(packet)
+    }
+
+    fn header_byte(&self) -> u8 {
+        match self {
+            Packet::Connect(_) => 0x10,
+            Packet::ConnAck { .. } => 0x20,
+            Packet::Publish(publish) => {
+                0x30 | (u8::from(publish.dup) << 3)
+                    | ((publish.qos as u8) << 1)
+                    | u8::from(publish.retain)
+            }
+            Packet::PubAck { .. } => 0x40,
+            Packet::Subscribe { .. } => 0x82,
+            Packet::SubAck { .. } => 0x90,
+            Packet::PingReq => 0xC0,
+            Packet::PingResp => 0xD0,
+            Packet::Disconnect => 0xE0,
+        }
+    }
+
+    fn write_body(&self, sink: &mut impl Sink) -> Result<(), PacketError> {
+        match self {
+            Packet::Connect(connect) => {
+                let mut flags = 0u8;
+                if connect.clean_session {
+                    flags |= 0x02;
+                }
+                if let Some(will) = &connect.will {
+                    flags |= 0x04 | ((will.qos as u8) << 3);
+                    if will.retain {
+                        flags |= 0x20;
+                    }
+                }
+                if connect.password.is_some() {
+                    flags |= 0x40;
+                }
+                if connect.username.is_some() {
+                    flags |= 0x80;
+                }
+                sink.str(PROTOCOL_NAME)?;
+                sink.put(&[PROTOCOL_LEVEL, flags])?;
+                sink.u16(connect.keep_alive_secs)?;
+                sink.str(connect.client_id)?;
+                if let Some(will) = &connect.will {
+                    sink.str(will.topic)?;
+                    sink.binary(will.payload)?;
+                }
+                if let Some(username) = connect.username {
+                    sink.str(username)?;
+                }
+                if let Some(password) = connect.password {
+                    sink.binary(password)?;
+                }
+            }
+            Packet::ConnAck {
+                session_present,
+                return_code,
+            } => sink.put(&[u8::from(*session_present), *return_code])?,
+            Packet::Publish(publish) => {
+                sink.str(publish.topic)?;
+                match (publish.qos, publish.packet_id) {
+                    (QoS::AtMostOnce, None) => {}
+                    (QoS::AtLeastOnce, Some(packet_id)) => sink.u16(packet_id)?,
+                    _ => return Err(PacketError::Malformed),
+                }
+                sink.put(publish.payload)?;
+            }
+            Packet::PubAck { packet_id } => sink.u16(*packet_id)?,
+            Packet::Subscribe { packet_id, filters } => {
+                sink.u16(*packet_id)?;
+                for (filter, qos) in filters.iter() {
+                    sink.str(filter)?;
+                    sink.put(&[qos])?;
+                }
+            }
+            Packet::SubAck {
+                packet_id,
+                return_codes,
+            } => {
+                sink.u16(*packet_id)?;
+                sink.put(return_codes)?;
+            }
+            Packet::PingReq | Packet::PingResp | Packet::Disconnect => {}
+        }
+        Ok(())
+    }
+}
+
+/// トピック名 `topic` がフィルタ `filter` (`+` / `#` ワイルドカード付き) に一致するか。
+///
+/// `$` で始まるトピック (`$SYS/...`) は先頭レベルのワイルドカードには一致しない
+/// (MQTT 3.1.1 §4.7.2)。
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut filter_levels = filter.split('/');
+    if topic.starts_with('$') && matches!(filter_levels.clone().next(), Some("#" | "+")) {
+        return false;
+    }
+    let mut topic_levels = topic.split('/');
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => {}
+            (Some(f), Some(t)) if f == t => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+// ─── クライアント ──────────────────────────────────────────────────────────────
+
+/// [`MqttClient`] の接続設定。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MqttConfig<'a> {
+    pub host: &'a str,
+    pub port: u16,
+    pub client_id: &'a str,
+    /// 0 で keepalive 無効
+    pub keep_alive_secs: u16,
+    pub clean_session: bool,
+    pub username: Option<&'a str>,
+    pub password: Option<&'a [u8]>,
+    pub will: Option<LastWill<'a>>,
+    /// TCP 接続開始から CONNACK までの制限時間
+    pub connect_timeout_ms: u64,
+    /// 再接続までの待ち時間の初期値。失敗が続くと倍々に伸びる
+    pub reconnect_delay_ms: u64,
+    pub max_reconnect_delay_ms: u64,
+    /// QoS 1 の PUBACK を待つ時間。過ぎたら DUP 付きで送り直す
+    pub ack_timeout_ms: u64,
+}
+
+impl<'a> MqttConfig<'a> {
+    pub const fn new(host: &'a str, client_id: &'a str) -> Self {
+        Self {
+            host,
+            port: DEFAULT_PORT,
+            client_id,
+            keep_alive_secs: 60,
+            clean_session: true,
+            username: None,
+            password: None,
+            will: None,
+            connect_timeout_ms: 10_000,
+            reconnect_delay_ms: 1_000,
+            max_reconnect_delay_ms: 30_000,
+            ack_timeout_ms: 5_000,
+        }
+    }
+
+    pub const fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub const fn with_keep_alive(mut self, keep_alive_secs: u16) -> Self {
+        self.keep_alive_secs = keep_alive_secs;
+        self
+    }
+
+    pub const fn with_will(mut self, will: LastWill<'a>) -> Self {
+        self.will = Some(will);
+        self
+    }
+
+    pub const fn with_credentials(mut self, username: &'a str, password: &'a [u8]) -> Self {
+        self.username = Some(username);
+        self.password = Some(password);
+        self
+    }
+}
+
+/// [`MqttClient`] の API 呼び出しが失敗した理由。
+///
+/// 通信断はここには出ず、[`MqttEvent::Disconnected`] で通知して自動で再接続する。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MqttError {
+    /// 接続していないので QoS 0 のメッセージを送れない
+    NotConnected,
+    /// 送信バッファに空きが無い。`poll` で送り切った後に再試行する
+    BufferFull,
+    /// PUBACK 待ちのメッセージが [`MAX_IN_FLIGHT`] 件ある
+    InFlightFull,
+    /// 購読が [`MAX_SUBSCRIPTIONS`] 件ある
+    TooManySubscriptions,
+    /// トピックが空、長すぎる、または publish 先にワイルドカードを含む
+    InvalidTopic,
+    Packet(PacketError),
+    Telemetry(TelemetryError),
+}
+
+impl From<PacketError> for MqttError {
+    fn from(error: PacketError) -> Self {
+        match error {
+            PacketError::BufferTooSmall => MqttError::BufferFull,
+            other => MqttError::Packet(other),
+        }
+    }
+}
+
+impl From<TelemetryError> for MqttError {
+    fn from(error: TelemetryError) -> Self {
+        MqttError::Telemetry(error)
+    }
+}
+
+impl fmt::Display for MqttError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MqttError::NotConnected => write!(f, "MQTT client not connected"),
+            MqttError::BufferFull => write!(f, "MQTT send buffer full"),
+            MqttError::InFlightFull => write!(f, "too many unacknowledged MQTT messages"),
+            MqttError::TooManySubscriptions => write!(f, "too many MQTT subscriptions"),
+            MqttError::InvalidTopic => write!(f, "invalid MQTT topic"),
+            MqttError::Packet(e) => write!(f, "MQTT packet error: {e:?}"),
+            MqttError::Telemetry(e) => write!(f, "telemetry encode error: {e:?}"),
+        }
+    }
+}
+
+/// 接続が切れた理由。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// ソケットの接続・送受信に失敗した
+    Transport,
+    /// 制限時間内に CONNACK が来なかった
+    ConnectTimeout,
+    /// CONNACK で拒否された (戻り値 1〜5)
+    Refused(u8),
+    /// PINGRESP が返ってこなかった
+    KeepAliveTimeout,
+    /// ブローカーから不正なパケットを受け取った
+    Protocol(PacketError),
+}
+
+/// 受信したメッセージ。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message<'m> {
+    pub topic: &'m str,
+    pub payload: &'m [u8],
+    pub qos: QoS,
+    pub retain: bool,
+}
+
+/// [`MqttClient::poll`] で通知するイベント。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MqttEvent<'m> {
+    /// CONNACK で接続が許可された。購読は自動で送り直す
+    Connected {
+        session_present: bool,
+    },
+    /// 接続が切れた。待ち時間の後に自動で再接続する
+    Disconnected(DisconnectReason),
+    Message(Message<'m>),
+    /// QoS 1 の publish に PUBACK が返った
+    Published {
+        packet_id: u16,
+    },
+    /// SUBACK を受け取った。`granted` が `None` なら拒否された
+    Subscribed {
+        packet_id: u16,
+        granted: Option<QoS>,
+    },
+}
+
+/// クライアントの接続状態。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MqttState {
+    /// 再接続待ち
+    Disconnected,
+    /// TCP 接続中または CONNACK 待ち
+    Connecting,
+    Connected,
+    /// [`MqttClient::disconnect`] 済み。[`MqttClient::reconnect`] まで接続しない
+    Stopped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Link {
+    Waiting { retry_at_ms: u64 },
+    TcpConnecting { since_ms: u64 },
+    AwaitingConnAck { since_ms: u64 },
+    Connected,
+    Stopped,
+}
+
+struct InFlight<const BUF: usize> {
+    packet_id: u16,
+    /// 最後に送った時刻。接続し直した後はまだ送っていないので `None`
+    sent_at_ms: Option<u64>,
+    /// DUP=0 でエンコード済みの PUBLISH
+    packet: Vec<u8, BUF>,
+}
+
+struct Subscription {
+    filter: String<MAX_TOPIC_FILTER_LEN>,
+    qos: QoS,
+}
+
+/// 受信パケットを処理した後に行うこと (受信バッファの借用を切るため)。
+enum Action {
+    None,
+    ConnAck {
+        session_present: bool,
+        return_code: u8,
+    },
+    PubAck(u16),
+    SendPubAck(u16),
+    SubAck {
+        packet_id: u16,
+        granted: Option<QoS>,
+    },
+    PingResp,
+}
+
+/// [`TcpClientSocket`] 上の MQTT 3.1.1 クライアント。
+///
+/// `BUF` は受信バッファ・送信バッファ、および PUBACK 待ち 1 件あたりの保存領域の
+/// バイト数。1 パケットがこれを超える publish / 受信はできない。
+///
+/// # Examples
+///
+/// ```
+/// use core_app::mqtt::{MqttClient, MqttConfig, MqttState, QoS};
+/// use hal_api::net::TcpClientSocket;
+///
+/// /// 接続できないソケット
+/// struct Offline;
+/// impl TcpClientSocket for Offline {
+///     type Error = ();
+///     fn connect(&mut self, _: &str, _: u16) -> Result<(), ()> { Err(()) }
+///     fn is_connected(&self) -> bool { false }
+///     fn send(&mut self, _: &[u8]) -> Result<usize, ()> { Err(()) }
+///     fn receive(&mut self, _: &mut [u8]) -> Result<usize, ()> { Err(()) }
+///     fn close(&mut self) {}
+/// }
+///
+/// let config = MqttConfig::new("192.168.1.20", "esp32-01");
+/// let mut client: MqttClient<'_, Offline, 256> = MqttClient::new(Offline, config);
+/// // QoS 1 は接続前でも受け付け、接続後に送る
+/// assert!(client.publish("lab/esp32-01/status", b"boot", QoS::AtLeastOnce, false, 0).is_ok());
+/// client.poll(0, |_event| {});
+/// assert_eq!(client.state(), MqttState::Disconnected);
+/// ```
+pub struct MqttClient<'a, S, const BUF: usize> {
+    socket: S,
+    config: MqttConfig<'a>,
+    link: Link,
+    reconnect_delay_ms: u64,
+    rx: Vec<u8, BUF>,
+    tx: Vec<u8, BUF>,
+    in_flight: Vec<InFlight<BUF>, MAX_IN_FLIGHT>,
+    subscriptions: Vec<Subscription, MAX_SUBSCRIPTIONS>,
+    next_packet_id: u16,
+    last_sent_ms: u64,
+    ping_sent_at_ms: Option<u64>,
+    pending_disconnect: Option<DisconnectReason>,
+}
+
+impl<'a, S, const BUF: usize> MqttClient<'a, S, BUF>
+where
+    S: TcpClientSocket,
+{
+    /// 最初の [`MqttClient::poll`] ですぐに接続を始める。
+    pub fn new(socket: S, config: MqttConfig<'a>) -> Self {
+        Self {
+            socket,
+            reconnect_delay_ms: config.reconnect_delay_ms,
+            config,
+            link: Link::Waiting { retry_at_ms: 0 },
+            rx: Vec::new(),
+            tx: Vec::new(),
+            in_flight: Vec::new(),
+            subscriptions: Vec::new(),
+            next_packet_id: 1,
+            last_sent_ms: 0,
+            ping_sent_at_ms: None,
+            pending_disconnect: None,
+        }
+    }
+
+    pub fn state(&self) -> MqttState {
+        match self.link {
+            Link::Waiting { .. } => MqttState::Disconnected,
+            Link::TcpConnecting { .. } | Link::AwaitingConnAck { .. } => MqttState::Connecting,
+            Link::Connected => MqttState::Connected,
+            Link::Stopped => MqttState::Stopped,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.link == Link::Connected
+    }
+
+    /// PUBACK 待ちの QoS 1 メッセージ数。
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    pub fn socket(&self) -> &S {
+        &self.socket
+    }
+
+    pub fn socket_mut(&mut self) -> &mut S {
+        &mut self.socket
+    }
+
+    /// メッセージを送る。QoS 1 ならパケット ID を返す。
+    ///
+    /// QoS 1 は PUBACK を受け取るまで保持し、タイムアウトや再接続の後に送り直す。
+    /// そのため接続前や切断中でも受け付ける。QoS 0 は接続中でなければ
+    /// [`MqttError::NotConnected`]。
+    pub fn publish(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        qos: QoS,
+        retain: bool,
+        now_ms: u64,
+    ) -> Result<Option<u16>, MqttError> {
+        if topic.is_empty() || topic.contains(['+', '#']) {
+            return Err(MqttError::InvalidTopic);
+        }
+        match qos {
+            QoS::AtMostOnce => {
+                if !self.is_connected() {
+                    return Err(MqttError::NotConnected);
+                }
+                self.send_packet(
+                    &Packet::Publish(Publish {
+                        topic,
+                        payload,
+                        qos,
+                        retain,
+                        dup: false,
+                        packet_id: None,
+                    }),
+                    now_ms,
+                )?;
+                Ok(None)
+            }
+            QoS::AtLeastOnce => {
+                if self.in_flight.is_full() {
+                    return Err(MqttError::InFlightFull);
+                }
+                let packet_id = self.allocate_packet_id();
+                let mut packet = Vec::new();
+                packet.resize(BUF, 0).map_err(|_| MqttError::BufferFull)?;
+                let len = Packet::Publish(Publish {
+                    topic,
+                    payload,
+                    qos,
+                    retain,
+                    dup: false,
+                    packet_id: Some(packet_id),
+                })
+                .encode(&mut packet)?;
+                packet.truncate(len);
+                let _ = self.in_flight.push(InFlight {
+                    packet_id,
+                    sent_at_ms: None,
+                    packet,
+                });
+                if self.is_connected() {
+                    self.send_in_flight(now_ms);
+                }
+                Ok(Some(packet_id))
+            }
+        }
+    }
+
+    /// テレメトリを `format` でエンコードして `topic` へ送る。
+    pub fn publish_telemetry(
+        &mut self,
+        topic: &str,
+        frame: &TelemetryFrame<'_>,
+        format: TelemetryFormat,
+        qos: QoS,
+        now_ms: u64,
+    ) -> Result<Option<u16>, MqttError> {
+        let mut payload = [0u8; BUF];
+        let len = frame.encode(format, topic, &mut payload)?;
+        self.publish(topic, &payload[..len], qos, false, now_ms)
+    }
+
+    /// 購読を登録する。接続中ならすぐに SUBSCRIBE を送り、以後は再接続のたびに送り直す。
+    pub fn subscribe(&mut self, filter: &str, qos: QoS, now_ms: u64) -> Result<(), MqttError> {
+        if filter.is_empty() {
+            return Err(MqttError::InvalidTopic);
+        }
+        if let Some(existing) = self
+            .subscriptions
+            .iter_mut()
+            .find(|s| s.filter.as_str() == filter)
+        {
+            existing.qos = qos;
+        } else {
+            let mut owned = String::new();
+            owned
+                .push_str(filter)
+                .map_err(|_| MqttError::InvalidTopic)?;
+            self.subscriptions
+                .push(Subscription { filter: owned, qos })
+                .map_err(|_| MqttError::TooManySubscriptions)?;
+        }
+        if self.is_connected() {
+            let packet_id = self.allocate_packet_id();
+            self.send_packet(
+                &Packet::Subscribe {
+                    packet_id,
+                    filters: SubscribeFilters::single(filter, qos),
+                },
+                now_ms,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// DISCONNECT を送って接続を閉じる。last-will は publish されない。
+    /// [`MqttClient::reconnect`] を呼ぶまで再接続しない。
+    pub fn disconnect(&mut self, now_ms: u64) {
+        if self.is_connected() {
+            let _ = self.send_packet(&Packet::Disconnect, now_ms);
+        }
+        self.socket.close();
+        self.reset_link();
+        self.link = Link::Stopped;
+    }
+
+    /// 次の [`MqttClient::poll`] で接続し直す。
+    pub fn reconnect(&mut self) {
+        if self.link == Link::Stopped {
+            self.reconnect_delay_ms = self.config.reconnect_delay_ms;
+            self.link = Link::Waiting { retry_at_ms: 0 };
+        }
+    }
+
+    /// 接続管理・送受信を進める。メインループから定期的に呼ぶ。
+    pub fn poll<F>(&mut self, now_ms: u64, mut on_event: F)
+    where
+        F: FnMut(MqttEvent<'_>),
+    {
+        if let Some(reason) = self.pending_disconnect.take() {
+            on_event(MqttEvent::Disconnected(reason));
+        }
+        match self.link {
+            Link::Stopped => return,
+            Link::Waiting { retry_at_ms } => {
+                if now_ms < retry_at_ms {
+                    return;
+                }
+                self.link = Link::TcpConnecting { since_ms: now_ms };
+                if self
+                    .socket
+                    .connect(self.config.host, self.config.port)
+                    .is_err()
+                {
+                    self.lose_connection(DisconnectReason::Transport, now_ms);
+                    self.flush_pending(&mut on_event);
+                    return;
+                }
+            }
+            _ => {}
+        }
+
+        if let Link::TcpConnecting { since_ms } = self.link {
+            if self.socket.is_connected() {
+                self.link = Link::AwaitingConnAck { since_ms };
+                let connect = Packet::Connect(Connect {
+                    client_id: self.config.client_id,
+                    keep_alive_secs: self.config.keep_alive_secs,
+                    clean_session: self.config.clean_session,
+                    will: self.config.will,
+                    username: self.config.username,
+                    password: self.config.password,
+                });
+                if self.send_packet(&connect, now_ms).is_err() {
+                    self.lose_connection(DisconnectReason::Transport, now_ms);
+                }
+            }
+        }
+        if let Link::TcpConnecting { since_ms } | Link::AwaitingConnAck { since_ms } = self.link {
+            if now_ms.saturating_sub(since_ms) >= self.config.connect_timeout_ms {
+                self.lose_connection(DisconnectReason::ConnectTimeout, now_ms);
+            }
+        }
+
+        if matches!(self.link, Link::AwaitingConnAck { .. } | Link::Connected) {
+            self.receive(now_ms, &mut on_event);
+        }
+        if self.link == Link::Connected {
+            self.maintain(now_ms);
+        }
+        if matches!(self.link, Link::AwaitingConnAck { .. } | Link::Connected) {
+            self.flush(now_ms);
+        }
+        self.flush_pending(&mut on_event);
+    }
+
+    fn flush_pending<F: FnMut(MqttEvent<'_>)>(&mut self, on_event: &mut F) {
+        if let Some(reason) = self.pending_disconnect.take() {
+            on_event(MqttEvent::Disconnected(reason));
+        }
+    }
+
+    fn receive<F: FnMut(MqttEvent<'_>)>(&mut self, now_ms: u64, on_event: &mut F) {
+        loop {
+            let filled = self.rx.len();
+            if filled == BUF {
+                break;
+            }
+            let _ = self.rx.resize(BUF, 0);
+            let received = self.socket.receive(&mut self.rx[filled..]);
+            match received {
+                Ok(n) => {
+                    self.rx.truncate(filled + n);
+                    if n == 0 {
+                        break;
+                    }
+                }
+                Err(_) => {
+                    self.rx.truncate(filled);
+                    self.lose_connection(DisconnectReason::Transport, now_ms);
+                    return;
+                }
+            }
+        }
+
+        while matches!(self.link, Link::AwaitingConnAck { .. } | Link::Connected) {
+            let (action, used) = match Packet::decode(&self.rx) {
+                Ok(Some((packet, used))) => (self.classify(packet, on_event), used),
+                Ok(None) if self.rx.is_full() => {
+                    // 1 パケットが受信バッファに収まらない
+                    self.lose_connection(
+                        DisconnectReason::Protocol(PacketError::BufferTooSmall),
+                        now_ms,
+                    );
+                    return;
+                }
+                Ok(None) => return,
+                Err(e) => {
+                    self.lose_connection(DisconnectReason::Protocol(e), now_ms);
+                    return;
+                }
+            };
+            let len = self.rx.len();
+            self.rx.copy_within(used..len, 0);
+            self.rx.truncate(len - used);
+            self.apply(action, now_ms, on_event);
+        }
+    }
+
+    /// 受信したパケットを分類する。メッセージはここでコールバックへ渡す。
+    fn classify<F: FnMut(MqttEvent<'_>)>(&self, packet: Packet<'_>, on_event: &mut F) -> Action {
+        match (self.link, packet) {
+            (
+                Link::AwaitingConnAck { .. },
+                Packet::ConnAck {
+                    session_present,
+                    return_code,
+                },
+            ) => Action::ConnAck {
+                session_present,
+                return_code,
+            },
+            (Link::Connected, Packet::Publish(publish)) => {
+                on_event(MqttEvent::Message(Message {
+                    topic: publish.topic,
+                    payload: publish.payload,
+                    qos: publish.qos,
+                    retain: publish.retain,
+                }));
+                publish.packet_id.map_or(Action::None, Action::SendPubAck)
+            }
+            (Link::Connected, Packet::PubAck { packet_id }) => Action::PubAck(packet_id),
+            (
+                Link::Connected,
+                Packet::SubAck {
+                    packet_id,
+                    return_codes,
+                },
+            ) => Action::SubAck {
+                packet_id,
+                granted: match return_codes.first() {
+                    Some(0) => Some(QoS::AtMostOnce),
+                    Some(1) => Some(QoS::AtLeastOnce),
+                    _ => None,
+                },
+            },
+            (Link::Connected, Packet::PingResp) => Action::PingResp,
+            _ => Action::None,
+        }
+    }
+
+    fn apply<F: FnMut(MqttEvent<'_>)>(&mut self, action: Action, now_ms: u64, on_event: &mut F) {
+        match action {
+            Action::None => {}
+            Action::ConnAck {
+                session_present,
+                return_code: 0,
+            } => {
+                self.link = Link::Connected;
+                self.reconnect_delay_ms = self.config.reconnect_delay_ms;
+                self.ping_sent_at_ms = None;
+                on_event(MqttEvent::Connected { session_present });
+                self.resubscribe(now_ms);
+                for entry in self.in_flight.iter_mut() {
+                    entry.sent_at_ms = None;
+                }
+                self.send_in_flight(now_ms);
+            }
+            Action::ConnAck { return_code, .. } => {
+                self.lose_connection(DisconnectReason::Refused(return_code), now_ms);
+            }
+            Action::PubAck(packet_id) => {
+                if let Some(index) = self
+                    .in_flight
+                    .iter()
+                    .position(|entry| entry.packet_id == packet_id)
+                {
+                    self.in_flight.remove(index);
+                    on_event(MqttEvent::Published { packet_id });
+                }
+            }
+            Action::SendPubAck(packet_id) => {
+                let _ = self.send_packet(&Packet::PubAck { packet_id }, now_ms);
+            }
+            Action::SubAck { packet_id, granted } => {
+                on_event(MqttEvent::Subscribed { packet_id, granted });
+            }
+            Action::PingResp => self.ping_sent_at_ms = None,
+        }
+    }
+
+    /// keepalive と QoS 1 の再送。
+    fn maintain(&mut self, now_ms: u64) {
+        let keep_alive_ms = u64::from(self.config.keep_alive_secs) * 1_000;
+        if keep_alive_ms > 0 {
+            if let Some(sent_at) = self.ping_sent_at_ms {
+                if now_ms.saturating_sub(sent_at) >= keep_alive_ms {
+                    self.lose_connection(DisconnectReason::KeepAliveTimeout, now_ms);
+                    return;
+                }
+            } else if now_ms.saturating_sub(self.last_sent_ms) >= keep_alive_ms
+                && self.send_packet(&Packet::PingReq, now_ms).is_ok()
+            {
+                self.ping_sent_at_ms = Some(now_ms);
+            }
+        }
+        self.send_in_flight(now_ms);
+    }
+
+    fn resubscribe(&mut self, now_ms: u64) {
+        for index in 0..self.subscriptions.len() {
+            let packet_id = self.allocate_packet_id();
+            let subscription = &self.subscriptions[index];
+            let mut encoded = [0u8; MAX_TOPIC_FILTER_LEN + 8];
+            let Ok(len) = (Packet::Subscribe {
+                packet_id,
+                filters: SubscribeFilters::single(&subscription.filter, subscription.qos),
+            })
+            .encode(&mut encoded) else {
+                continue;
+            };
+            let _ = self.send_raw(&encoded[..len], now_ms);
+        }
+    }
+
+    /// 未送信またはタイムアウトした QoS 1 メッセージを送る。
+    fn send_in_flight(&mut self, now_ms: u64) {
+        for index in 0..self.in_flight.len() {
+            let entry = &self.in_flight[index];
+            let due = match entry.sent_at_ms {
+                None => true,
+                Some(sent_at) => now_ms.saturating_sub(sent_at) >= self.config.ack_timeout_ms,
+            };
+            if !due {
+                continue;
+            }
+            let len = entry.packet.len();
+            if len > BUF - self.tx.len() {
+                self.flush(now_ms);
+                if len > BUF - self.tx.len() {
+                    return;
+                }
+            }
+            let entry = &mut self.in_flight[index];
+            let mut header = entry.packet[0];
+            if entry.sent_at_ms.is_some() {
+                header |= 0x08;
+            }
+            entry.sent_at_ms = Some(now_ms);
+            let _ = self.tx.push(header);
+            let _ = self.tx.extend_from_slice(&entry.packet[1..]);
+            self.last_sent_ms = now_ms;
+        }
+        self.flush(now_ms);
+    }
+
+    fn send_packet(&mut self, packet: &Packet<'_>, now_ms: u64) -> Result<(), MqttError> {
+        let mut encoded = [0u8; BUF];
+        let len = packet.encode(&mut encoded)?;
+        self.send_raw(&encoded[..len], now_ms)
+    }
+
+    fn send_raw(&mut self, bytes: &[u8], now_ms: u64) -> Result<(), MqttError> {
+        if bytes.len() > BUF - self.tx.len() {
+            self.flush(now_ms);
+        }
+        self.tx
+            .extend_from_slice(bytes)
+            .map_err(|_| MqttError::BufferFull)?;
+        self.last_sent_ms = now_ms;
+        self.flush(now_ms);
+        Ok(())
+    }
+
+    fn flush(&mut self, now_ms: u64) {
+        while !self.tx.is_empty() {
+            match self.socket.send(&self.tx) {
+                Ok(0) => return,
+                Ok(n) => {
+                    let len = self.tx.len();
+                    self.tx.copy_within(n..len, 0);
+                    self.tx.truncate(len - n);
+                }
+                Err(_) => {
+                    self.lose_connection(DisconnectReason::Transport, now_ms);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn lose_connection(&mut self, reason: DisconnectReason, now_ms: u64) {
+        // 同じ poll 内の 2 回目以降のエラーでバックオフと切断理由を上書きしない
+        if matches!(self.link, Link::Stopped | Link::Waiting { .. }) {
+            return;
+        }
+        self.socket.close();
+        self.reset_link();
+        self.link = Link::Waiting {
+            retry_at_ms: now_ms.saturating_add(self.reconnect_delay_ms),
+        };
+        self.reconnect_delay_ms = self
+            .reconnect_delay_ms
+            .saturating_mul(2)
+            .min(self.config.max_reconnect_delay_ms);
+        self.pending_disconnect = Some(reason);
+    }
+
+    fn reset_link(&mut self) {
+        self.rx.clear();
+        self.tx.clear();
+        self.ping_sent_at_ms = None;
+    }
+
+    fn allocate_packet_id(&mut self) -> u16 {
+        loop {
+            let id = self.next_packet_id;
+            self.next_packet_id = self.next_packet_id.checked_add(1).unwrap_or(1);
+            if !self.in_flight.iter().any(|entry| entry.packet_id == id) {
+                return id;
+            }
+        }
+    }
+}
+
+// ─── トピックとコマンド ────────────────────────────────────────────────────────
+
+/// `<prefix>/<device_id>/...` 形式のトピック名。
+///
+/// - `<prefix>/<device_id>/telemetry`: [`TelemetryFrame`] を publish する
+/// - `<prefix>/<device_id>/status`: `online` / last-will の `offline` (retain)
+/// - `<prefix>/<device_id>/cmd/<name>`: [`CommandDispatcher`] が受けるコマンド
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopicScheme<'a> {
+    pub prefix: &'a str,
+    pub device_id: &'a str,
+}
+
+impl<'a> TopicScheme<'a> {
+    pub const fn new(prefix: &'a str, device_id: &'a str) -> Self {
+        Self { prefix, device_id }
+    }
+
+    pub fn telemetry(&self) -> Result<String<MAX_TOPIC_LEN>, MqttError> {
+        self.topic(&["telemetry"])
+    }
+
+    pub fn status(&self) -> Result<String<MAX_TOPIC_LEN>, MqttError> {
+        self.topic(&["status"])
+    }
+
+    /// 全コマンドを受けるフィルタ (`<prefix>/<device_id>/cmd/#`)。
+    pub fn command_filter(&self) -> Result<String<MAX_TOPIC_LEN>, MqttError> {
+        self.topic(&["cmd", "#"])
+    }
+
+    pub fn command(&self, name: &str) -> Result<String<MAX_TOPIC_LEN>, MqttError> {
+        self.topic(&["cmd", name])
+    }
+
+    /// コマンドトピックならコマンド名 (`cmd/` 以降) を返す。
+    pub fn command_name<'t>(&self, topic: &'t str) -> Option<&'t str> {
+        let rest = topic.strip_prefix(self.prefix)?.strip_prefix('/')?;
+        let rest = rest.strip_prefix(self.device_id)?.strip_prefix("/cmd/")?;
+        (!rest.is_empty()).then_some(rest)
+    }
+
+    fn topic(&self, levels: &[&str]) -> Result<String<MAX_TOPIC_LEN>, MqttError> {
+        let mut topic = String::new();
+        let mut push = |part: &str| topic.push_str(part).map_err(|_| MqttError::InvalidTopic);
+        push(self.prefix)?;
+        push("/")?;
+        push(self.device_id)?;
+        for level in levels {
+            push("/")?;
+            push(level)?;
+        }
+        Ok(topic)
+    }
+}
+
+/// アクチュエータへのコマンド。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// `cmd/servo`: ペイロードは角度 (例 `90`)
+    Servo { angle_degrees: u16 },
+    /// `cmd/motors`: ペイロードは左右の `<方向>[:<duty>]` (例 `forward:60 reverse:40`)。
+    /// 方向は `forward` / `reverse` / `brake` / `coast`
+    Motors {
+        left: MotorCommand,
+        right: MotorCommand,
+    },
+    /// `cmd/stop`: 両モータをブレーキ。ペイロードは無視する
+    Stop,
+}
+
+/// コマンドとして解釈できなかった理由。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandParseError {
+    UnknownCommand,
+    InvalidPayload,
+}
+
+impl Command {
+    pub fn parse(name: &str, payload: &[u8]) -> Result<Self, CommandParseError> {
+        let text = str::from_utf8(payload)
+            .map_err(|_| CommandParseError::InvalidPayload)?
+            .trim();
+        match name {
+            "servo" => text
+                .parse()
+                .map(|angle_degrees| Command::Servo { angle_degrees })
+                .map_err(|_| CommandParseError::InvalidPayload),
+            "motors" => {
+                let mut channels = text
+                    .split(|c: char| c == ',' || c.is_ascii_whitespace())
+                    .filter(|part| !part.is_empty())
+                    .map(parse_motor_command);
+                let (Some(left), Some(right), None) =
+                    (channels.next(), channels.next(), channels.next())
+                else {
+                    return Err(CommandParseError::InvalidPayload);
+                };
+                Ok(Command::Motors {
+                    left: left?,
+                    right: right?,
+                })
+            }
+            "stop" => Ok(Command::Stop),
+            _ => Err(CommandParseError::UnknownCommand),
+        }
+    }
+}
+
+fn parse_motor_command(text: &str) -> Result<MotorCommand, CommandParseError> {
+    let (direction, duty) = match text.split_once(':') {
+        Some((direction, duty)) => (direction, Some(duty)),
+        None => (text, None),
+    };
+    let direction = match direction {
+        "forward" => MotorDirection::Forward,
+        "reverse" => MotorDirection::Reverse,
+        "brake" => MotorDirection::Brake,
+        "coast" => MotorDirection::Coast,
+        _ => return Err(CommandParseError::InvalidPayload),
+    };
+    let duty_percent = match (direction, duty) {
+        (MotorDirection::Brake | MotorDirection::Coast, None) => 0,
+        (_, None) => return Err(CommandParseError::InvalidPayload),
+        (_, Some(duty)) => duty
+            .parse::<u8>()
+            .ok()
+            .filter(|duty| *duty <= 100)
+            .ok_or(CommandParseError::InvalidPayload)?,
+    };
+    Ok(MotorCommand::new(direction, duty_percent))
+}
+
+/// コマンドを実行できなかった理由。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError<SE, ME> {
+    Parse(CommandParseError),
+    Servo(SE),
+    Motors(ME),
+}
+
+/// [`CommandDispatcher`] の実行結果。
+pub type CommandResult<SE, ME> = Result<Command, CommandError<SE, ME>>;
+
+/// コマンドトピックのメッセージをアクチュエータへ振り分ける。
+///
+/// # Examples
+///
+/// ```
+/// use core_app::mqtt::{Command, CommandDispatcher, Message, QoS, TopicScheme};
+/// use hal_api::actuator::{DualMotorDriver, MotorCommand, ServoMotor};
+///
+/// struct Servo(u16);
+/// impl ServoMotor for Servo {
+///     type Error = ();
+///     fn set_angle_degrees(&mut self, angle: u16) -> Result<(), ()> {
+///         self.0 = angle;
+///         Ok(())
+///     }
+/// }
+/// struct Motors;
+/// impl DualMotorDriver for Motors {
+///     type Error = ();
+///     fn apply_channels(&mut self, _: MotorCommand, _: MotorCommand) -> Result<(), ()> {
+///         Ok(())
+///     }
+/// }
+///
+/// let topics = TopicScheme::new("lab", "rover-1");
+/// let mut dispatcher = CommandDispatcher::new(Servo(0), Motors);
+/// let message = Message {
+///     topic: "lab/rover-1/cmd/servo",
+///     payload: b"135",
+///     qos: QoS::AtLeastOnce,
+///     retain: false,
+/// };
+/// let result = dispatcher.handle(&topics, &message);
+/// assert_eq!(result, Some(Ok(Command::Servo { angle_degrees: 135 })));
+/// assert_eq!(dispatcher.servo().0, 135);
+/// ```
+pub struct CommandDispatcher<SV, DM> {
+    servo: SV,
+    motors: DM,
+}
+
+impl<SV, DM> CommandDispatcher<SV, DM>
+where
+    SV: ServoMotor,
+    DM: DualMotorDriver,
+{
+    pub fn new(servo: SV, motors: DM) -> Self {
+        Self { servo, motors }
+    }
+
+    pub fn servo(&self) -> &SV {
+        &self.servo
+    }
+
+    pub fn motors(&self) -> &DM {
+        &self.motors
+    }
+
+    pub fn into_parts(self) -> (SV, DM) {
+        (self.servo, self.motors)
+    }
+
+    /// `message` がこのデバイス宛てのコマンドなら実行して結果を返す。
+    /// コマンドトピックでなければ `None`。
+    pub fn handle(
+        &mut self,
+        topics: &TopicScheme<'_>,
+        message: &Message<'_>,
+    ) -> Option<CommandResult<SV::Error, DM::Error>> {
+        let name = topics.command_name(message.topic)?;
+        Some(self.dispatch(name, message.payload))
+    }
+
+    pub fn dispatch(&mut self, name: &str, payload: &[u8]) -> CommandResult<SV::Error, DM::Error> {
+        let command = Command::parse(name, payload).map_err(CommandError::Parse)?;
+        match command {
+            Command::Servo { angle_degrees } => self
+                .servo
+                .set_angle_degrees(angle_degrees)
+                .map_err(CommandError::Servo)?,
+            Command::Motors { left, right } => self
+                .motors
+                .apply_channels(left, right)
+                .map_err(CommandError::Motors)?,
+            Command::Stop => {
+                let brake = MotorCommand::new(MotorDirection::Brake, 0);
+                self.motors
+                    .apply_channels(brake, brake)
+                    .map_err(CommandError::Motors)?
+            }
+        }
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec;
+    use std::vec::Vec as StdVec;
+
+    fn encode(packet: &Packet<'_>) -> StdVec<u8> {
+        let mut buffer = [0u8; 256];
+        let len = packet.encode(&mut buffer).unwrap();
+        buffer[..len].to_vec()
+    }
+
+    #[test]
+    fn connect_with_will_and_credentials_matches_spec_layout() {
+        let connect = Packet::Connect(Connect {
+            client_id: "dev",
+            keep_alive_secs: 30,
+            clean_session: true,
+            will: Some(LastWill {
+                topic: "s",
+                payload: b"off",
+                qos: QoS::AtLeastOnce,
+                retain: true,
+            }),
+            username: Some("u"),
+            password: Some(b"pw"),
+        });
+        let bytes = encode(&connect);
+        assert_eq!(
+            bytes,
+            [
+                0x10, 30, // fixed header
+                0, 4, b'M', b'Q', b'T', b'T', 4, 0xEE, 0, 30, // variable header
+                0, 3, b'd', b'e', b'v', // client id
+                0, 1, b's', 0, 3, b'o', b'f', b'f', // will
+                0, 1, b'u', 0, 2, b'p', b'w', // credentials
+            ]
+        );
+        assert_eq!(Packet::decode(&bytes), Ok(Some((connect, bytes.len()))));
+    }
+
+    #[test]
+    fn remaining_length_uses_multiple_bytes_and_waits_for_the_rest() {
+        let payload = [0xAB; 200];
+        let publish = Packet::Publish(Publish {
+            topic: "t",
+            payload: &payload,
+            qos: QoS::AtMostOnce,
+            retain: true,
+            dup: false,
+            packet_id: None,
+        });
+        let bytes = encode(&publish);
+        // 3 + 200 = 203 = 0xCB 0x01
+        assert_eq!(&bytes[..3], &[0x31, 0xCB, 0x01]);
+        assert_eq!(Packet::decode(&bytes[..2]), Ok(None));
+        assert_eq!(Packet::decode(&bytes[..bytes.len() - 1]), Ok(None));
+        assert_eq!(Packet::decode(&bytes), Ok(Some((publish, bytes.len()))));
+    }
+
+    #[test]
+    fn decode_rejects_malformed_and_unsupported_packets() {
+        assert_eq!(
+            Packet::decode(&[0x36, 0x03, 0, 1, b't']),
+            Err(PacketError::Malformed)
+        );
+        assert_eq!(
+            Packet::decode(&[0x34, 0x05, 0, 1, b't', 0, 1]),
+            Err(PacketError::Unsupported)
+        );
+        assert_eq!(
+            Packet::decode(&[0x80, 0x03, 0, 1, 0]),
+            Err(PacketError::Malformed)
+        );
+        assert_eq!(
+            Packet::decode(&[0x30, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]),
+            Err(PacketError::Malformed)
+        );
+        assert_eq!(
+            Packet::decode(&[0xC0, 0x01, 0x00]),
+            Err(PacketError::Malformed)
+        );
+    }
+
+    #[test]
+    fn subscribe_with_several_filters_decodes_each() {
+        let bytes = [
+            0x82, 12, 0, 9, // packet id
+            0, 3, b'a', b'/', b'#', 1, // filter 1
+            0, 1, b'+', 0, // filter 2
+        ];
+        let Ok(Some((Packet::Subscribe { packet_id, filters }, 14))) = Packet::decode(&bytes)
+        else {
+            panic!("subscribe should decode");
+        };
+        assert_eq!(packet_id, 9);
+        assert_eq!(
+            filters.iter().collect::<StdVec<_>>(),
+            vec![("a/#", 1), ("+", 0)]
+        );
+    }
+
+    #[test]
+    fn topic_filters_follow_wildcard_rules() {
+        assert!(topic_matches("lab/+/cmd/#", "lab/rover/cmd/servo"));
+        assert!(topic_matches("lab/#", "lab"));
+        assert!(topic_matches("#", "a/b"));
+        assert!(!topic_matches("#", "$SYS/uptime"));
+        assert!(!topic_matches("+/uptime", "$SYS/uptime"));
+        assert!(topic_matches("$SYS/#", "$SYS/uptime"));
+        assert!(topic_matches("$SYS/+", "$SYS/uptime"));
+        assert!(!topic_matches("lab/+", "lab/a/b"));
+        assert!(!topic_matches("lab/a", "lab/ab"));
+    }
+
+    // ─── スクリプト化したソケット ───
+
+    #[derive(Default)]
+    struct SocketState {
+        connect_calls: usize,
+        refuse_connect: bool,
+        connected: bool,
+        inbox: StdVec<u8>,
+        sent: StdVec<u8>,
+        fail_next_receive: bool,
+        send_limit: Option<usize>,
+    }
+
+    #[derive(Clone, Default)]
+    struct ScriptedSocket(Rc<RefCell<SocketState>>);
+
+    impl ScriptedSocket {
+        fn deliver(&self, packet: &Packet<'_>) {
+            self.0.borrow_mut().inbox.extend(encode(packet));
+        }
+
+        /// 送られたパケットを取り出す。
+        fn take_sent(&self) -> StdVec<StdVec<u8>> {
+            let sent = core::mem::take(&mut self.0.borrow_mut().sent);
+            let mut packets = StdVec::new();
+            let mut rest = &sent[..];
+            while let Some((_, used)) = Packet::decode(rest).unwrap() {
+                packets.push(rest[..used].to_vec());
+                rest = &rest[used..];
+            }
+            assert!(rest.is_empty(), "partial packet left: {rest:?}");
+            packets
+        }
+    }
+
+    impl TcpClientSocket for ScriptedSocket {
+        type Error = ();
+
+        fn connect(&mut self, _host: &str, port: u16) -> Result<(), ()> {
+            assert_eq!(port, DEFAULT_PORT);
+            let mut state = self.0.borrow_mut();
+            state.connect_calls += 1;
+            if state.refuse_connect {
+                return Err(());
+            }
+            state.connected = true;
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            self.0.borrow().connected
+        }
+
+        fn send(&mut self, data: &[u8]) -> Result<usize, ()> {
+            let mut state = self.0.borrow_mut();
+            if !state.connected {
+                return Err(());
+            }
+            let n = state
+                .send_limit
+                .map_or(data.len(), |limit| limit.min(data.len()));
+            state.sent.extend_from_slice(&data[..n]);
+            Ok(n)
+        }
+
+        fn receive(&mut self, buffer: &mut [u8]) -> Result<usize, ()> {
+            let mut state = self.0.borrow_mut();
+            if core::mem::take(&mut state.fail_next_receive) {
+                return Err(());
+            }
+            let n = state.inbox.len().min(buffer.len());
+            buffer[..n].copy_from_slice(&state.inbox[..n]);
+            state.inbox.drain(..n);
+            Ok(n)
+        }
+
+        fn close(&mut self) {
+            let mut state = self.0.borrow_mut();
+            state.connected = false;
+            state.inbox.clear();
+        }
+    }
+
+    type TestClient = MqttClient<'static, ScriptedSocket, 128>;
+
+    fn collect(client: &mut TestClient, now_ms: u64) -> StdVec<std::string::String> {
+        let mut events = StdVec::new();
+        client.poll(now_ms, |event| {
+            events.push(match event {
+                MqttEvent::Message(message) => std::format!(
+                    "message {} {}",
+                    message.topic,
+                    std::string::String::from_utf8_lossy(message.payload)
+                ),
+                other => std::format!("{other:?}"),
+            })
+        });
+        events
+    }
+
+    fn connected_client(config: MqttConfig<'static>) -> (TestClient, ScriptedSocket) {
+        let socket = ScriptedSocket::default();
+        let mut client = MqttClient::new(socket.clone(), config);
+        assert!(collect(&mut client, 0).is_empty());
+        let sent = socket.take_sent();
+        assert_eq!(sent.len(), 1);
+        assert!(matches!(
+            Packet::decode(&sent[0]),
+            Ok(Some((Packet::Connect(_), _)))
+        ));
+        socket.deliver(&Packet::ConnAck {
+            session_present: false,
+            return_code: 0,
+        });
+        assert_eq!(
+            collect(&mut client, 10),
+            vec!["Connected { session_present: false }"]
+        );
+        (client, socket)
+    }
+
+    #[test]
+    fn qos1_publish_is_retransmitted_with_dup_until_acked() {
+        let (mut client, socket) = connected_client(MqttConfig::new("broker", "dev"));
+        let id = client
+            .publish("t", b"x", QoS::AtLeastOnce, false, 100)
+            .unwrap()
+            .unwrap();
+        let first = socket.take_sent();
+        assert_eq!(first, vec![vec![0x32, 6, 0, 1, b't', 0, id as u8, b'x']]);
+
+        collect(&mut client, 5_099);
+        assert!(socket.take_sent().is_empty());
+        collect(&mut client, 5_100);
+        assert_eq!(
+            socket.take_sent(),
+            vec![vec![0x3A, 6, 0, 1, b't', 0, id as u8, b'x']]
+        );
+
+        socket.deliver(&Packet::PubAck { packet_id: id });
+        assert_eq!(
+            collect(&mut client, 5_200),
+            vec![std::format!("Published {{ packet_id: {id} }}")]
+        );
+        assert_eq!(client.in_flight(), 0);
+    }
+
+    #[test]
+    fn keepalive_pings_and_reconnects_with_backoff_when_unanswered() {
+        let config = MqttConfig::new("broker", "dev").with_keep_alive(10);
+        let (mut client, socket) = connected_client(config);
+        client.subscribe("cmd/#", QoS::AtLeastOnce, 20).unwrap();
+        socket.take_sent();
+
+        collect(&mut client, 10_019);
+        assert!(socket.take_sent().is_empty());
+        collect(&mut client, 10_020);
+        assert_eq!(socket.take_sent(), vec![vec![0xC0, 0]]);
+        socket.deliver(&Packet::PingResp);
+        collect(&mut client, 10_030);
+
+        collect(&mut client, 20_020);
+        assert_eq!(socket.take_sent(), vec![vec![0xC0, 0]]);
+        assert_eq!(
+            collect(&mut client, 30_020),
+            vec!["Disconnected(KeepAliveTimeout)"]
+        );
+        assert_eq!(client.state(), MqttState::Disconnected);
+
+        // 1 回目の再接続は 1 秒後。拒否されると次は 2 秒後
+        socket.0.borrow_mut().refuse_connect = true;
+        collect(&mut client, 31_019);
+        assert_eq!(socket.0.borrow().connect_calls, 1);
+        assert_eq!(
+            collect(&mut client, 31_020),
+            vec!["Disconnected(Transport)"]
+        );
+        collect(&mut client, 33_019);
+        assert_eq!(socket.0.borrow().connect_calls, 2);
+        socket.0.borrow_mut().refuse_connect = false;
+        collect(&mut client, 33_020);
+        assert_eq!(socket.0.borrow().connect_calls, 3);
+
+        socket.take_sent();
+        socket.deliver(&Packet::ConnAck {
+            session_present: false,
+            return_code: 0,
+        });
+        collect(&mut client, 33_030);
+        let sent = socket.take_sent();
+        assert_eq!(sent.len(), 1, "subscription should be renewed");
+        let Ok(Some((Packet::Subscribe { filters, .. }, _))) = Packet::decode(&sent[0]) else {
+            panic!("expected SUBSCRIBE, got {sent:?}");
+        };
+        assert_eq!(filters.iter().collect::<StdVec<_>>(), vec![("cmd/#", 1)]);
+    }
+
+    #[test]
+    fn refused_connack_and_connect_timeout_drop_the_connection() {
+        let socket = ScriptedSocket::default();
+        let mut client: TestClient = MqttClient::new(socket.clone(), MqttConfig::new("b", "d"));
+        collect(&mut client, 0);
+        socket.deliver(&Packet::ConnAck {
+            session_present: false,
+            return_code: 5,
+        });
+        assert_eq!(collect(&mut client, 1), vec!["Disconnected(Refused(5))"]);
+
+        collect(&mut client, 1_001);
+        assert_eq!(client.state(), MqttState::Connecting);
+        assert_eq!(
+            collect(&mut client, 11_001),
+            vec!["Disconnected(ConnectTimeout)"]
+        );
+    }
+
+    #[test]
+    fn incoming_qos1_message_is_delivered_and_acked() {
+        let (mut client, socket) = connected_client(MqttConfig::new("broker", "dev"));
+        socket.deliver(&Packet::Publish(Publish {
+            topic: "lab/dev/cmd/servo",
+            payload: b"90",
+            qos: QoS::AtLeastOnce,
+            retain: false,
+            dup: false,
+            packet_id: Some(42),
+        }));
+        socket.deliver(&Packet::Publish(Publish {
+            topic: "lab/dev/cmd/stop",
+            payload: b"",
+            qos: QoS::AtMostOnce,
+            retain: false,
+            dup: false,
+            packet_id: None,
+        }));
+        assert_eq!(
+            collect(&mut client, 20),
+            vec!["message lab/dev/cmd/servo 90", "message lab/dev/cmd/stop "]
+        );
+        assert_eq!(socket.take_sent(), vec![vec![0x40, 2, 0, 42]]);
+    }
+
+    #[test]
+    fn qos1_publishes_wait_while_offline_and_partial_sends_resume() {
+        let socket = ScriptedSocket::default();
+        socket.0.borrow_mut().refuse_connect = true;
+        let mut client: TestClient = MqttClient::new(socket.clone(), MqttConfig::new("b", "d"));
+        assert_eq!(
+            client.publish("t", b"1", QoS::AtMostOnce, false, 0),
+            Err(MqttError::NotConnected)
+        );
+        for _ in 0..MAX_IN_FLIGHT {
+            client
+                .publish("t", b"1", QoS::AtLeastOnce, false, 0)
+                .unwrap();
+        }
+        assert_eq!(
+            client.publish("t", b"1", QoS::AtLeastOnce, false, 0),
+            Err(MqttError::InFlightFull)
+        );
+        assert_eq!(
+            client.publish("a/+", b"1", QoS::AtLeastOnce, false, 0),
+            Err(MqttError::InvalidTopic)
+        );
+
+        collect(&mut client, 0);
+        socket.0.borrow_mut().refuse_connect = false;
+        socket.0.borrow_mut().send_limit = Some(3);
+        collect(&mut client, 1_000);
+        socket.deliver(&Packet::ConnAck {
+            session_present: false,
+            return_code: 0,
+        });
+        for now in 1_001..1_010 {
+            collect(&mut client, now);
+        }
+        let sent = socket.take_sent();
+        assert_eq!(sent.len(), 1 + MAX_IN_FLIGHT, "{sent:?}");
+        assert!(sent[1..].iter().all(|packet| packet[0] == 0x32));
+    }
+
+    #[test]
+    fn disconnect_sends_disconnect_and_stays_stopped() {
+        let (mut client, socket) = connected_client(MqttConfig::new("broker", "dev"));
+        client.disconnect(50);
+        assert_eq!(socket.take_sent(), vec![vec![0xE0, 0]]);
+        assert_eq!(client.state(), MqttState::Stopped);
+        collect(&mut client, 100_000);
+        assert_eq!(socket.0.borrow().connect_calls, 1);
+        client.reconnect();
+        collect(&mut client, 100_001);
+        assert_eq!(socket.0.borrow().connect_calls, 2);
+    }
+
+    #[test]
+    fn receive_error_is_reported_once() {
+        let (mut client, socket) = connected_client(MqttConfig::new("broker", "dev"));
+        socket.0.borrow_mut().fail_next_receive = true;
+        assert_eq!(collect(&mut client, 20), vec!["Disconnected(Transport)"]);
+        assert!(collect(&mut client, 21).is_empty());
+    }
+
+    #[test]
+    fn second_transport_error_in_one_poll_keeps_backoff_and_reason() {
+        let (mut client, _socket) = connected_client(MqttConfig::new("broker", "dev"));
+        client.lose_connection(DisconnectReason::KeepAliveTimeout, 100);
+        client.lose_connection(DisconnectReason::Transport, 100);
+        assert_eq!(client.link, Link::Waiting { retry_at_ms: 1_100 });
+        assert_eq!(client.reconnect_delay_ms, 2_000);
+        assert_eq!(
+            collect(&mut client, 101),
+            vec!["Disconnected(KeepAliveTimeout)"]
+        );
+    }
+
+    #[test]
+    fn publish_telemetry_encodes_frame() {
+        use hal_api::sensor::EnvReading;
+
+        let (mut client, socket) = connected_client(MqttConfig::new("broker", "dev"));
+        let topics = TopicScheme::new("lab", "dev");
+        let frame = TelemetryFrame::new("dev").with_env(EnvReading::new(2150, 4000, None));
+        client
+            .publish_telemetry(
+                &topics.telemetry().unwrap(),
+                &frame,
+                TelemetryFormat::Json,
+                QoS::AtMostOnce,
+                20,
+            )
+            .unwrap();
+        let sent = socket.take_sent();
+        let Ok(Some((Packet::Publish(publish), _))) = Packet::decode(&sent[0]) else {
+            panic!("expected PUBLISH");
+        };
+        assert_eq!(publish.topic, "lab/dev/telemetry");
+        assert_eq!(
+            publish.payload,
+            br#"{"device_id":"dev","temperature":21.50,"humidity":40.00}"#
+        );
+    }
+
+    // ─── コマンド ───
+
+    #[test]
+    fn topic_scheme_builds_and_matches_command_topics() {
+        let topics = TopicScheme::new("lab", "rover-1");
+        assert_eq!(topics.command_filter().unwrap(), "lab/rover-1/cmd/#");
+        assert_eq!(topics.status().unwrap(), "lab/rover-1/status");
+        assert_eq!(
+            topics.command_name("lab/rover-1/cmd/motors"),
+            Some("motors")
+        );
+        assert_eq!(topics.command_name("lab/rover-10/cmd/motors"), None);
+        assert_eq!(topics.command_name("lab/rover-1/cmd/"), None);
+        assert_eq!(topics.command_name("lab/rover-1/telemetry"), None);
+    }
+
+    #[test]
+    fn command_parsing_accepts_documented_payloads() {
+        assert_eq!(
+            Command::parse("motors", b"forward:60, reverse:40"),
+            Ok(Command::Motors {
+                left: MotorCommand::new(MotorDirection::Forward, 60),
+                right: MotorCommand::new(MotorDirection::Reverse, 40),
+            })
+        );
+        assert_eq!(
+            Command::parse("motors", b"brake coast"),
+            Ok(Command::Motors {
+                left: MotorCommand::new(MotorDirection::Brake, 0),
+                right: MotorCommand::new(MotorDirection::Coast, 0),
+            })
+        );
+        assert_eq!(
+            Command::parse("servo", b" 45\n"),
+            Ok(Command::Servo { angle_degrees: 45 })
+        );
+        for (name, payload) in [
+            ("motors", &b"forward:60"[..]),
+            ("motors", b"forward reverse:1"),
+            ("motors", b"forward:101 brake"),
+            ("motors", b"up:1 brake"),
+            ("servo", b"left"),
+        ] {
+            assert_eq!(
+                Command::parse(name, payload),
+                Err(CommandParseError::InvalidPayload),
+                "{name} {payload:?}"
+            );
+        }
+        assert_eq!(
+            Command::parse("lights", b"on"),
+            Err(CommandParseError::UnknownCommand)
+        );
+    }
+
+    #[derive(Default)]
+    struct RecordingServo(StdVec<u16>);
+
+    impl ServoMotor for RecordingServo {
+        type Error = &'static str;
+
+        fn set_angle_degrees(&mut self, angle: u16) -> Result<(), Self::Error> {
+            if angle > 180 {
+                return Err("out of range");
+            }
+            self.0.push(angle);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingMotors(StdVec<(MotorCommand, MotorCommand)>);
+
+    impl DualMotorDriver for RecordingMotors {
+        type Error = ();
+
+        fn apply_channels(&mut self, left: MotorCommand, right: MotorCommand) -> Result<(), ()> {
+            self.0.push((left, right));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn dispatcher_drives_actuators_and_surfaces_their_errors() {
+        let mut dispatcher =
+            CommandDispatcher::new(RecordingServo::default(), RecordingMotors::default());
+        assert_eq!(
+            dispatcher.dispatch("servo", b"200"),
+            Err(CommandError::Servo("out of range"))
+        );
+        dispatcher.dispatch("servo", b"30").unwrap();
+        dispatcher.dispatch("stop", b"").unwrap();
+        assert_eq!(
+            dispatcher.dispatch("fly", b""),
+            Err(CommandError::Parse(CommandParseError::UnknownCommand))
+        );
+
+        let (servo, motors) = dispatcher.into_parts();
+        assert_eq!(servo.0, vec![30]);
+        let brake = MotorCommand::new(MotorDirection::Brake, 0);
+        assert_eq!(motors.0, vec![(brake, brake)]);
+    }
+}
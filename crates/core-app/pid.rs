@@ -0,0 +1,238 @@
+//! 固定小数点 PID 制御器。
+//!
+//! ゲインは 1/1000 単位の整数 (`kp_milli = 1500` は Kp = 1.5) で持ち、内部演算は
+//! `i64` で行うため FPU のない MCU でもそのまま使えます。`update()` は 1 制御周期に
+//! 1 回呼ぶ前提で、積分・微分の時間刻みは周期に含めてゲインを決めます。
+//!
+//! ワインドアップ対策として次の 2 つを併用します。
+//!
+//! - 積分項を出力レンジ内にクランプする
+//! - 出力が飽和している方向へは積分を進めない (conditional integration)
+//!
+//! 微分項は測定値の変化から求める (derivative on measurement) ため、
+//! 目標値を急に変えても出力が跳ねません。
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::pid::{PidConfig, PidController};
+//!
+//! let mut pid = PidController::new(PidConfig {
+//!     kp_milli: 500,
+//!     ki_milli: 0,
+//!     kd_milli: 0,
+//!     output_min: -100,
+//!     output_max: 100,
+//! });
+//! // 誤差 40 × Kp 0.5 = 20
+//! assert_eq!(pid.update(100, 60), 20);
+//! ```
+
+/// ゲインの固定小数点スケール。`*_milli` フィールドはこの値で割って使われる。
+pub const PID_GAIN_SCALE: i64 = 1000;
+
+/// `PidController` の設定。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PidConfig {
+    /// 比例ゲイン (1/1000 単位)。
+    pub kp_milli: i32,
+    /// 積分ゲイン (1/1000 単位、1 周期あたり)。
+    pub ki_milli: i32,
+    /// 微分ゲイン (1/1000 単位、1 周期あたり)。
+    pub kd_milli: i32,
+    /// 出力の下限。
+    pub output_min: i32,
+    /// 出力の上限。
+    pub output_max: i32,
+}
+
+impl Default for PidConfig {
+    fn default() -> Self {
+        Self {
+            kp_milli: 1000,
+            ki_milli: 0,
+            kd_milli: 0,
+            output_min: -100,
+            output_max: 100,
+        }
+    }
+}
+
+/// アンチワインドアップ付きの固定小数点 PID 制御器。
+#[derive(Debug, Clone)]
+pub struct PidController {
+    config: PidConfig,
+    /// 積分項 (出力 × [`PID_GAIN_SCALE`] 単位)。
+    integral: i64,
+    prev_measurement: Option<i32>,
+    last_output: i32,
+}
+
+impl PidController {
+    pub fn new(config: PidConfig) -> Self {
+        Self {
+            config,
+            integral: 0,
+            prev_measurement: None,
+            last_output: 0,
+        }
+    }
+
+    /// 積分項と微分用の前回測定値を捨てる。
+    pub fn reset(&mut self) {
+        self.integral = 0;
+        self.prev_measurement = None;
+        self.last_output = 0;
+    }
+
+    /// 1 制御周期分の計算を行い、出力レンジにクランプした操作量を返す。
+    pub fn update(&mut self, setpoint: i32, measurement: i32) -> i32 {
+        let (min, max) = self.output_range_scaled();
+        let error = setpoint as i64 - measurement as i64;
+
+        let proportional = self.config.kp_milli as i64 * error;
+        let derivative = match self.prev_measurement {
+            Some(prev) => -(self.config.kd_milli as i64 * (measurement as i64 - prev as i64)),
+            None => 0,
+        };
+        let candidate_integral =
+            (self.integral + self.config.ki_milli as i64 * error).clamp(min, max);
+
+        let unclamped = proportional + candidate_integral + derivative;
+        let saturated_high = unclamped > max && error > 0;
+        let saturated_low = unclamped < min && error < 0;
+        if !(saturated_high || saturated_low) {
+            self.integral = candidate_integral;
+        }
+
+        self.prev_measurement = Some(measurement);
+        self.last_output = (unclamped.clamp(min, max) / PID_GAIN_SCALE) as i32;
+        self.last_output
+    }
+
+    /// 直前の `update()` が返した操作量。
+    pub fn last_output(&self) -> i32 {
+        self.last_output
+    }
+
+    /// 現在の積分項 (出力単位に換算、切り捨て)。
+    pub fn integral(&self) -> i32 {
+        (self.integral / PID_GAIN_SCALE) as i32
+    }
+
+    pub fn config(&self) -> PidConfig {
+        self.config
+    }
+
+    /// ゲインやレンジを差し替える。積分項は新しいレンジに収まるよう再クランプする。
+    pub fn set_config(&mut self, config: PidConfig) {
+        self.config = config;
+        let (min, max) = self.output_range_scaled();
+        self.integral = self.integral.clamp(min, max);
+    }
+
+    fn output_range_scaled(&self) -> (i64, i64) {
+        let min = self.config.output_min.min(self.config.output_max) as i64;
+        let max = self.config.output_max.max(self.config.output_min) as i64;
+        (min * PID_GAIN_SCALE, max * PID_GAIN_SCALE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(kp_milli: i32, ki_milli: i32, kd_milli: i32) -> PidConfig {
+        PidConfig {
+            kp_milli,
+            ki_milli,
+            kd_milli,
+            output_min: -100,
+            output_max: 100,
+        }
+    }
+
+    #[test]
+    fn proportional_only_scales_error() {
+        let mut pid = PidController::new(config(1500, 0, 0));
+        assert_eq!(pid.update(10, 0), 15);
+        assert_eq!(pid.update(0, 10), -15);
+    }
+
+    #[test]
+    fn output_is_clamped_to_range() {
+        let mut pid = PidController::new(config(10_000, 0, 0));
+        assert_eq!(pid.update(1000, 0), 100);
+        assert_eq!(pid.update(-1000, 0), -100);
+    }
+
+    #[test]
+    fn integral_accumulates_steady_error() {
+        let mut pid = PidController::new(config(0, 500, 0));
+        assert_eq!(pid.update(10, 0), 5);
+        assert_eq!(pid.update(10, 0), 10);
+        assert_eq!(pid.update(10, 0), 15);
+        assert_eq!(pid.integral(), 15);
+    }
+
+    #[test]
+    fn integral_does_not_wind_up_while_saturated() {
+        let mut pid = PidController::new(config(2000, 500, 0));
+        // 大きな誤差で長時間飽和させても積分は出力レンジを超えない
+        for _ in 0..1000 {
+            assert_eq!(pid.update(1000, 0), 100);
+        }
+        assert!(pid.integral() <= 100);
+
+        // 誤差の符号が変われば即座に出力が下がる
+        let output = pid.update(0, 10);
+        assert!(
+            output < 100,
+            "output should unwind immediately, got {output}"
+        );
+    }
+
+    #[test]
+    fn conditional_integration_freezes_integral_when_p_saturates() {
+        let mut pid = PidController::new(config(20_000, 100, 0));
+        pid.update(100, 0);
+        // P だけで飽和しているので積分は進まない
+        assert_eq!(pid.integral(), 0);
+    }
+
+    #[test]
+    fn derivative_uses_measurement_not_setpoint() {
+        let mut pid = PidController::new(config(0, 0, 1000));
+        assert_eq!(pid.update(0, 0), 0);
+        // 目標値の急変では出力は跳ねない
+        assert_eq!(pid.update(50, 0), 0);
+        // 測定値が増えると逆向きの出力が出る
+        assert_eq!(pid.update(50, 20), -20);
+    }
+
+    #[test]
+    fn reset_clears_state() {
+        let mut pid = PidController::new(config(0, 1000, 1000));
+        pid.update(10, 0);
+        pid.update(10, 5);
+        pid.reset();
+        assert_eq!(pid.integral(), 0);
+        assert_eq!(pid.last_output(), 0);
+        // 前回測定値が捨てられているので微分項は 0
+        assert_eq!(pid.update(0, 50), -50);
+    }
+
+    #[test]
+    fn set_config_reclamps_integral() {
+        let mut pid = PidController::new(config(0, 1000, 0));
+        for _ in 0..10 {
+            pid.update(10, 0);
+        }
+        assert_eq!(pid.integral(), 100);
+        pid.set_config(PidConfig {
+            output_max: 40,
+            ..pid.config()
+        });
+        assert_eq!(pid.integral(), 40);
+    }
+}
@@ -0,0 +1,610 @@
+//! 協調型タスクスケジューラ — 複数のアプリの `tick()` を周期・位相・デッドライン付きで回す。
+//!
+//! - 周期: [`TaskConfig::period_ticks`] ごとに実行、`offset_ticks` で位相をずらして負荷を分散
+//! - デッドライン: tick 開始から `deadline_us` 以内に終わらなければオーバーランとして数える
+//! - エラー分離: 1 つのタスクが失敗しても同じ tick の他のタスクは実行される。
+//!   `max_consecutive_errors` 回連続で失敗したタスクは停止 (`Suspended`) し、[`Scheduler::resume`] まで呼ばない
+//!
+//! タスクはスケジューラに借用 (`&mut dyn Task`) で登録するので、アプリの所有者は呼び出し側のまま。
+//! 既存のアプリ (`ClimateDisplayApp` / `ImuLoggerApp` / `ThermostatApp` など) は [`Task`] を実装済みで、
+//! クロージャ (`FnMut() -> Result<(), TaskError>`) もそのままタスクにできる。
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::scheduler::{NoClock, Scheduler, TaskConfig, TaskError};
+//!
+//! let mut fast = 0u32;
+//! let mut slow = 0u32;
+//! let mut fast_task = || -> Result<(), TaskError> { fast += 1; Ok(()) };
+//! let mut slow_task = || -> Result<(), TaskError> { slow += 1; Ok(()) };
+//!
+//! let mut scheduler: Scheduler<'_, NoClock, 4> = Scheduler::new(NoClock);
+//! scheduler.add_task(&mut fast_task, TaskConfig::every("fast", 2)).unwrap();
+//! scheduler.add_task(&mut slow_task, TaskConfig::every("slow", 5)).unwrap();
+//! for _ in 0..10 {
+//!     scheduler.tick();
+//! }
+//! drop(scheduler);
+//! assert_eq!((fast, slow), (5, 2));
+//! ```
+
+use hal_api::error::{ActuatorError, DisplayError, GpioError, I2cError, SensorError, StorageError};
+use hal_api::storage::BlockStorage;
+use heapless::Vec;
+
+use crate::climate_display::{ClimateDisplayApp, ClimateDisplayError};
+use crate::data_logger::{DataLogError, DataLoggerApp, DataLoggerError, LogClock, LogSource};
+use crate::imu_logger::{ImuLoggerApp, ImuLoggerError};
+use crate::speed_control::{SpeedControlError, SpeedControlledMotor};
+use crate::thermostat::{ThermostatApp, ThermostatError, ThermostatOutput};
+use crate::{App, AppError};
+
+#[cfg(test)]
+extern crate std;
+
+/// タスクが返すエラーの分類。
+///
+/// アプリごとのエラー型を 1 つにまとめるため、原因の種類だけを残す。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskError {
+    Gpio,
+    I2c,
+    Sensor,
+    Display,
+    Actuator,
+    Storage,
+    Clock,
+    /// 上記に当てはまらないタスク固有の失敗
+    Other(&'static str),
+}
+
+impl From<GpioError> for TaskError {
+    fn from(_: GpioError) -> Self {
+        TaskError::Gpio
+    }
+}
+
+impl From<I2cError> for TaskError {
+    fn from(_: I2cError) -> Self {
+        TaskError::I2c
+    }
+}
+
+impl From<SensorError> for TaskError {
+    fn from(_: SensorError) -> Self {
+        TaskError::Sensor
+    }
+}
+
+impl From<DisplayError> for TaskError {
+    fn from(_: DisplayError) -> Self {
+        TaskError::Display
+    }
+}
+
+impl From<ActuatorError> for TaskError {
+    fn from(_: ActuatorError) -> Self {
+        TaskError::Actuator
+    }
+}
+
+impl From<StorageError> for TaskError {
+    fn from(_: StorageError) -> Self {
+        TaskError::Storage
+    }
+}
+
+/// スケジューラから呼ばれる処理の単位。
+pub trait Task {
+    fn run(&mut self) -> Result<(), TaskError>;
+}
+
+impl<F> Task for F
+where
+    F: FnMut() -> Result<(), TaskError>,
+{
+    fn run(&mut self) -> Result<(), TaskError> {
+        self()
+    }
+}
+
+impl<PIN, I2C> Task for App<PIN, I2C>
+where
+    PIN: hal_api::gpio::OutputPin<Error = GpioError>,
+    I2C: hal_api::i2c::I2cBus<Error = I2cError>,
+{
+    fn run(&mut self) -> Result<(), TaskError> {
+        self.tick().map_err(|err| match err {
+            AppError::Gpio(err) => err.into(),
+            AppError::I2c(err) => err.into(),
+        })
+    }
+}
+
+impl<SENSOR, DISPLAY> Task for ClimateDisplayApp<SENSOR, DISPLAY>
+where
+    SENSOR: hal_api::sensor::EnvSensor<Error = SensorError>,
+    DISPLAY: hal_api::display::TextDisplay16x2<Error = DisplayError>,
+{
+    fn run(&mut self) -> Result<(), TaskError> {
+        self.tick().map_err(|err| match err {
+            ClimateDisplayError::Sensor(err) => err.into(),
+            ClimateDisplayError::Display(err) => err.into(),
+        })
+    }
+}
+
+impl<IMU> Task for ImuLoggerApp<IMU>
+where
+    IMU: hal_api::imu::ImuSensor,
+{
+    fn run(&mut self) -> Result<(), TaskError> {
+        self.tick()
+            .map_err(|ImuLoggerError::Sensor(_)| TaskError::Sensor)
+    }
+}
+
+impl<SENSOR, RTC, OUT, DISPLAY> Task for ThermostatApp<SENSOR, RTC, OUT, DISPLAY>
+where
+    SENSOR: hal_api::sensor::EnvSensor<Error = SensorError>,
+    RTC: hal_api::rtc::RtcSensor<Error = SensorError>,
+    OUT: ThermostatOutput,
+    DISPLAY: hal_api::display::TextDisplay16x2<Error = DisplayError>,
+{
+    fn run(&mut self) -> Result<(), TaskError> {
+        self.tick().map_err(|err| match err {
+            ThermostatError::Sensor(err) => err.into(),
+            ThermostatError::Output(_) => TaskError::Actuator,
+            ThermostatError::Display(err) => err.into(),
+        })
+    }
+}
+
+impl<M, S> Task for SpeedControlledMotor<M, S>
+where
+    M: hal_api::actuator::DriveMotor,
+    S: hal_api::encoder::SpeedFeedback,
+{
+    fn run(&mut self) -> Result<(), TaskError> {
+        self.tick().map_err(|err| match err {
+            SpeedControlError::InvalidCommand | SpeedControlError::Motor(_) => TaskError::Actuator,
+            SpeedControlError::Sensor(_) => TaskError::Sensor,
+        })
+    }
+}
+
+impl<SOURCE, STORAGE, CLOCK> Task for DataLoggerApp<SOURCE, STORAGE, CLOCK>
+where
+    SOURCE: LogSource,
+    STORAGE: BlockStorage,
+    CLOCK: LogClock,
+{
+    fn run(&mut self) -> Result<(), TaskError> {
+        self.tick().map_err(|err| match err {
+            DataLoggerError::Sensor(_) => TaskError::Sensor,
+            DataLoggerError::Log(DataLogError::Format) => TaskError::Other("log format"),
+            DataLoggerError::Log(_) => TaskError::Storage,
+            DataLoggerError::Clock(_) => TaskError::Clock,
+        })
+    }
+}
+
+/// デッドライン計測用のマイクロ秒カウンタ。`wrapping_sub` で差分を取る。
+pub trait TaskClock {
+    fn now_us(&mut self) -> u32;
+}
+
+/// 時間を測らないクロック。デッドライン・実行時間の計測は常に 0 になる。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoClock;
+
+impl TaskClock for NoClock {
+    fn now_us(&mut self) -> u32 {
+        0
+    }
+}
+
+/// タスクごとの実行条件。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskConfig {
+    pub name: &'static str,
+    /// 実行する tick 間隔。0 は 1 として扱う
+    pub period_ticks: u32,
+    /// 位相。`tick % period == offset % period` の tick で実行する
+    pub offset_ticks: u32,
+    /// tick 開始からこの時間内に終わらなければオーバーラン。`None` なら計測しない
+    pub deadline_us: Option<u32>,
+    /// この回数連続で失敗したら停止する。0 なら停止しない
+    pub max_consecutive_errors: u32,
+}
+
+impl TaskConfig {
+    /// `period_ticks` ごとに実行し、デッドラインなし・停止なしの設定。
+    pub const fn every(name: &'static str, period_ticks: u32) -> Self {
+        Self {
+            name,
+            period_ticks,
+            offset_ticks: 0,
+            deadline_us: None,
+            max_consecutive_errors: 0,
+        }
+    }
+
+    fn is_due(&self, tick: u32) -> bool {
+        let period = self.period_ticks.max(1);
+        tick % period == self.offset_ticks % period
+    }
+}
+
+/// タスクの状態。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    Ready,
+    /// 連続失敗で停止中
+    Suspended,
+}
+
+/// タスクごとの実行統計。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskStats {
+    pub state: TaskState,
+    pub runs: u32,
+    pub errors: u32,
+    pub consecutive_errors: u32,
+    pub overruns: u32,
+    pub last_error: Option<TaskError>,
+    pub last_duration_us: u32,
+    pub max_duration_us: u32,
+}
+
+impl TaskStats {
+    const fn new() -> Self {
+        Self {
+            state: TaskState::Ready,
+            runs: 0,
+            errors: 0,
+            consecutive_errors: 0,
+            overruns: 0,
+            last_error: None,
+            last_duration_us: 0,
+            max_duration_us: 0,
+        }
+    }
+}
+
+/// 登録済みタスクの番号 (登録順)。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TaskId(usize);
+
+impl TaskId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// `Scheduler` が返すエラー型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedulerError {
+    /// 登録数が容量 `N` に達している
+    Full,
+}
+
+/// 1 tick の実行結果。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TickReport {
+    pub ran: u8,
+    pub failed: u8,
+    pub overruns: u8,
+    /// このtickで新たに停止したタスク数
+    pub suspended: u8,
+    /// tick 全体の実行時間
+    pub elapsed_us: u32,
+}
+
+struct TaskSlot<'a> {
+    task: &'a mut dyn Task,
+    config: TaskConfig,
+    stats: TaskStats,
+}
+
+/// 最大 `N` 個のタスクを登録できる協調型スケジューラ。
+pub struct Scheduler<'a, C, const N: usize> {
+    clock: C,
+    slots: Vec<TaskSlot<'a>, N>,
+    tick_count: u32,
+}
+
+impl<'a, C, const N: usize> Scheduler<'a, C, N>
+where
+    C: TaskClock,
+{
+    pub fn new(clock: C) -> Self {
+        Self {
+            clock,
+            slots: Vec::new(),
+            tick_count: 0,
+        }
+    }
+
+    pub fn add_task(
+        &mut self,
+        task: &'a mut dyn Task,
+        config: TaskConfig,
+    ) -> Result<TaskId, SchedulerError> {
+        let id = TaskId(self.slots.len());
+        self.slots
+            .push(TaskSlot {
+                task,
+                config,
+                stats: TaskStats::new(),
+            })
+            .map_err(|_| SchedulerError::Full)?;
+        Ok(id)
+    }
+
+    /// tick を 1 つ進め、期限の来たタスクを登録順に実行する。
+    pub fn tick(&mut self) -> TickReport {
+        self.tick_count = self.tick_count.wrapping_add(1);
+        let tick = self.tick_count;
+        let tick_start = self.clock.now_us();
+        let mut report = TickReport::default();
+
+        for slot in self.slots.iter_mut() {
+            if slot.stats.state == TaskState::Suspended || !slot.config.is_due(tick) {
+                continue;
+            }
+            let start = self.clock.now_us();
+            let result = slot.task.run();
+            let end = self.clock.now_us();
+
+            let stats = &mut slot.stats;
+            stats.runs = stats.runs.wrapping_add(1);
+            stats.last_duration_us = end.wrapping_sub(start);
+            stats.max_duration_us = stats.max_duration_us.max(stats.last_duration_us);
+            report.ran = report.ran.saturating_add(1);
+
+            if let Some(deadline) = slot.config.deadline_us {
+                if end.wrapping_sub(tick_start) > deadline {
+                    stats.overruns = stats.overruns.wrapping_add(1);
+                    report.overruns = report.overruns.saturating_add(1);
+                }
+            }
+
+            match result {
+                Ok(()) => stats.consecutive_errors = 0,
+                Err(err) => {
+                    stats.errors = stats.errors.wrapping_add(1);
+                    stats.consecutive_errors = stats.consecutive_errors.saturating_add(1);
+                    stats.last_error = Some(err);
+                    report.failed = report.failed.saturating_add(1);
+                    let limit = slot.config.max_consecutive_errors;
+                    if limit != 0 && stats.consecutive_errors >= limit {
+                        stats.state = TaskState::Suspended;
+                        report.suspended = report.suspended.saturating_add(1);
+                    }
+                }
+            }
+        }
+
+        report.elapsed_us = self.clock.now_us().wrapping_sub(tick_start);
+        report
+    }
+
+    /// 停止したタスクを再開する。連続失敗回数はリセットされる。
+    pub fn resume(&mut self, id: TaskId) {
+        if let Some(slot) = self.slots.get_mut(id.0) {
+            slot.stats.state = TaskState::Ready;
+            slot.stats.consecutive_errors = 0;
+        }
+    }
+
+    /// タスクを手動で停止する。
+    pub fn suspend(&mut self, id: TaskId) {
+        if let Some(slot) = self.slots.get_mut(id.0) {
+            slot.stats.state = TaskState::Suspended;
+        }
+    }
+
+    pub fn stats(&self, id: TaskId) -> Option<&TaskStats> {
+        self.slots.get(id.0).map(|slot| &slot.stats)
+    }
+
+    pub fn config(&self, id: TaskId) -> Option<&TaskConfig> {
+        self.slots.get(id.0).map(|slot| &slot.config)
+    }
+
+    /// 登録済みタスクの ID を登録順に返す。
+    pub fn task_ids(&self) -> impl Iterator<Item = TaskId> {
+        (0..self.slots.len()).map(TaskId)
+    }
+
+    /// 名前でタスクを探す。
+    pub fn find(&self, name: &str) -> Option<TaskId> {
+        self.slots
+            .iter()
+            .position(|slot| slot.config.name == name)
+            .map(TaskId)
+    }
+
+    pub fn task_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn tick_count(&self) -> u32 {
+        self.tick_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::vec::Vec as StdVec;
+
+    /// タスク側が進める共有の時計。
+    struct SteppingClock {
+        now: Rc<Cell<u32>>,
+    }
+
+    impl TaskClock for SteppingClock {
+        fn now_us(&mut self) -> u32 {
+            self.now.get()
+        }
+    }
+
+    #[test]
+    fn tasks_run_at_their_period_and_offset() {
+        let log = RefCell::new(StdVec::new());
+        let mut a = || -> Result<(), TaskError> {
+            log.borrow_mut().push('a');
+            Ok(())
+        };
+        let mut b = || -> Result<(), TaskError> {
+            log.borrow_mut().push('b');
+            Ok(())
+        };
+        let mut scheduler: Scheduler<'_, NoClock, 2> = Scheduler::new(NoClock);
+        scheduler
+            .add_task(&mut a, TaskConfig::every("a", 2))
+            .unwrap();
+        scheduler
+            .add_task(
+                &mut b,
+                TaskConfig {
+                    offset_ticks: 1,
+                    ..TaskConfig::every("b", 3)
+                },
+            )
+            .unwrap();
+
+        let ran: StdVec<u8> = (0..6).map(|_| scheduler.tick().ran).collect();
+        drop(scheduler);
+        // tick 1: b, 2: a, 3: -, 4: a+b, 5: -, 6: a
+        assert_eq!(ran, [1, 1, 0, 2, 0, 1]);
+        assert_eq!(*log.borrow(), ['b', 'a', 'a', 'b', 'a']);
+    }
+
+    #[test]
+    fn failing_task_does_not_block_others_and_gets_suspended() {
+        let healthy_runs = Cell::new(0);
+        let mut failing = || -> Result<(), TaskError> { Err(SensorError::BusError.into()) };
+        let mut healthy = || -> Result<(), TaskError> {
+            healthy_runs.set(healthy_runs.get() + 1);
+            Ok(())
+        };
+        let mut scheduler: Scheduler<'_, NoClock, 2> = Scheduler::new(NoClock);
+        let failing_id = scheduler
+            .add_task(
+                &mut failing,
+                TaskConfig {
+                    max_consecutive_errors: 3,
+                    ..TaskConfig::every("failing", 1)
+                },
+            )
+            .unwrap();
+        scheduler
+            .add_task(&mut healthy, TaskConfig::every("healthy", 1))
+            .unwrap();
+
+        assert_eq!(scheduler.tick().failed, 1);
+        scheduler.tick();
+        let report = scheduler.tick();
+        assert_eq!(report.suspended, 1);
+        assert_eq!(
+            scheduler.stats(failing_id).unwrap().state,
+            TaskState::Suspended
+        );
+
+        let report = scheduler.tick();
+        assert_eq!((report.ran, report.failed), (1, 0));
+        let stats = *scheduler.stats(failing_id).unwrap();
+        assert_eq!(stats.errors, 3);
+        assert_eq!(stats.last_error, Some(TaskError::Sensor));
+
+        scheduler.resume(failing_id);
+        assert_eq!(scheduler.tick().failed, 1);
+        assert_eq!(scheduler.stats(failing_id).unwrap().state, TaskState::Ready);
+        drop(scheduler);
+        assert_eq!(healthy_runs.get(), 5);
+    }
+
+    #[test]
+    fn deadline_overrun_is_counted_from_tick_start() {
+        let now = Rc::new(Cell::new(0u32));
+        let first_now = now.clone();
+        let second_now = now.clone();
+        let mut first = move || -> Result<(), TaskError> {
+            first_now.set(first_now.get() + 600);
+            Ok(())
+        };
+        let mut second = move || -> Result<(), TaskError> {
+            second_now.set(second_now.get() + 300);
+            Ok(())
+        };
+        let mut scheduler: Scheduler<'_, SteppingClock, 2> =
+            Scheduler::new(SteppingClock { now: now.clone() });
+        let first_id = scheduler
+            .add_task(
+                &mut first,
+                TaskConfig {
+                    deadline_us: Some(1_000),
+                    ..TaskConfig::every("first", 1)
+                },
+            )
+            .unwrap();
+        let second_id = scheduler
+            .add_task(
+                &mut second,
+                TaskConfig {
+                    deadline_us: Some(800),
+                    ..TaskConfig::every("second", 1)
+                },
+            )
+            .unwrap();
+
+        let report = scheduler.tick();
+        assert_eq!(report.overruns, 1);
+        assert_eq!(report.elapsed_us, 900);
+        assert_eq!(scheduler.stats(first_id).unwrap().overruns, 0);
+        let second_stats = scheduler.stats(second_id).unwrap();
+        assert_eq!(second_stats.overruns, 1);
+        assert_eq!(second_stats.last_duration_us, 300);
+        assert_eq!(scheduler.stats(first_id).unwrap().max_duration_us, 600);
+    }
+
+    #[test]
+    fn add_task_reports_full() {
+        let mut a = || -> Result<(), TaskError> { Ok(()) };
+        let mut b = || -> Result<(), TaskError> { Ok(()) };
+        let mut scheduler: Scheduler<'_, NoClock, 1> = Scheduler::new(NoClock);
+        scheduler
+            .add_task(&mut a, TaskConfig::every("a", 1))
+            .unwrap();
+        assert_eq!(
+            scheduler.add_task(&mut b, TaskConfig::every("b", 1)),
+            Err(SchedulerError::Full)
+        );
+        assert_eq!(scheduler.find("a"), Some(TaskId(0)));
+        assert_eq!(scheduler.find("b"), None);
+    }
+
+    #[test]
+    fn zero_period_runs_every_tick() {
+        let mut runs = 0;
+        let mut task = || -> Result<(), TaskError> {
+            runs += 1;
+            Ok(())
+        };
+        let mut scheduler: Scheduler<'_, NoClock, 1> = Scheduler::new(NoClock);
+        scheduler
+            .add_task(&mut task, TaskConfig::every("zero", 0))
+            .unwrap();
+        for _ in 0..3 {
+            scheduler.tick();
+        }
+        drop(scheduler);
+        assert_eq!(runs, 3);
+    }
+}
@@ -0,0 +1,889 @@
+//! フラッシュ上の永続設定 — バージョン付きレコードとキーバリューストア。
+//!
+//! - [`PersistentSettings`] は 1 つの設定レコードを CRC 付きで保存する。保存のたびに次のスロットへ
+//!   書き進め、全ページを順に使い回すので消去回数がページ間で均等になる (ウェアレベリング)。
+//!   起動時は CRC が正しい最新シーケンスのレコードを読むので、書き込み中の電源断では直前の値に戻る
+//! - [`SettingsSchema`] でレコードの形式とスキーマバージョンを決める。古いバージョンのレコードは
+//!   [`SettingsSchema::migrate`] で変換し、変換結果をその場で保存し直す
+//! - [`FlashKvStore`] は上記の上に載せた `hal_api::kv::KeyValueStore` 実装 (NVS 相当)。
+//!   メニューの `SettingsStore` としても使える
+//!
+//! # レコード形式 (リトルエンディアン)
+//!
+//! | offset | size | 内容 |
+//! |---|---|---|
+//! | 0 | 2 | マジック `0x5347` |
+//! | 2 | 2 | スキーマバージョン |
+//! | 4 | 4 | シーケンス番号 |
+//! | 8 | 2 | ペイロード長 |
+//! | 10 | 2 | CRC-16/CCITT (0..10 とペイロード) |
+//! | 12 | n | ペイロード |
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::settings::{FlashKvStore, SettingsSchema};
+//! use hal_api::kv::KeyValueStore;
+//! use hal_api::storage::BlockStorage;
+//!
+//! struct Ram([u8; 512]);
+//!
+//! impl BlockStorage for Ram {
+//!     type Error = ();
+//!     fn block_size(&self) -> u32 { 256 }
+//!     fn block_count(&self) -> u32 { 2 }
+//!     fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), ()> {
+//!         let start = offset as usize;
+//!         buffer.copy_from_slice(&self.0[start..start + buffer.len()]);
+//!         Ok(())
+//!     }
+//!     fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), ()> {
+//!         let start = offset as usize;
+//!         for (cell, byte) in self.0[start..start + data.len()].iter_mut().zip(data) {
+//!             *cell &= *byte;
+//!         }
+//!         Ok(())
+//!     }
+//!     fn erase_block(&mut self, block: u32) -> Result<(), ()> {
+//!         let start = block as usize * 256;
+//!         self.0[start..start + 256].fill(0xFF);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut store: FlashKvStore<Ram, 4, 128> = FlashKvStore::mount(Ram([0xFF; 512])).unwrap();
+//! store.set("wifi_ssid", b"home").unwrap();
+//!
+//! // 再マウントしても値が残る
+//! let mut store: FlashKvStore<Ram, 4, 128> = FlashKvStore::mount(store.into_inner()).unwrap();
+//! let mut buffer = [0u8; 16];
+//! let len = store.get("wifi_ssid", &mut buffer).unwrap().unwrap();
+//! assert_eq!(&buffer[..len], b"home");
+//! ```
+
+use hal_api::error::KvError;
+use hal_api::kv::{is_valid_key, KeyValueStore, KV_MAX_KEY_LEN};
+use hal_api::storage::BlockStorage;
+use heapless::{String, Vec};
+
+use crate::data_logger::crc16_ccitt_update;
+use crate::menu::{SettingKey, SettingsStore};
+
+#[cfg(test)]
+extern crate std;
+
+/// レコードヘッダのバイト数。
+pub const SETTINGS_HEADER_SIZE: usize = 12;
+
+const SETTINGS_MAGIC: u16 = 0x5347;
+
+/// 永続化する設定の形式。
+pub trait SettingsSchema: Sized {
+    /// 現在のスキーマバージョン。形式を変えたら増やす
+    const VERSION: u16;
+
+    /// ペイロードを書き出してバイト数を返す。収まらなければ `None`。
+    fn encode(&self, out: &mut [u8]) -> Option<usize>;
+
+    /// 現在のバージョンのペイロードを読む。
+    fn decode(bytes: &[u8]) -> Option<Self>;
+
+    /// 古いバージョン `from_version` のペイロードを変換する。既定では変換できない。
+    fn migrate(from_version: u16, bytes: &[u8]) -> Option<Self> {
+        let _ = (from_version, bytes);
+        None
+    }
+}
+
+/// 読み込んだ設定の出どころ。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsSource {
+    /// 現在のバージョンで保存されていた
+    Stored,
+    /// 古いバージョンから変換して保存し直した
+    Migrated { from: u16 },
+    /// 保存されていなかったので既定値を使った
+    Default,
+}
+
+/// `PersistentSettings` が返すエラー型。
+#[derive(Debug, PartialEq, Eq)]
+pub enum SettingsError<E> {
+    Storage(E),
+    /// スロットにヘッダが収まらない、スロットがブロックを割り切らない、またはブロックが 2 つ未満
+    InvalidGeometry,
+    /// エンコード結果がスロットに収まらない
+    TooLarge,
+    /// 新しいバージョンのレコード、または変換できない古いバージョン
+    UnsupportedVersion(u16),
+    /// CRC は正しいがペイロードを解釈できない
+    Corrupted,
+}
+
+impl<E> From<E> for SettingsError<E> {
+    fn from(err: E) -> Self {
+        SettingsError::Storage(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RecordInfo {
+    slot: u32,
+    sequence: u32,
+    version: u16,
+    len: u16,
+}
+
+/// `BlockStorage` 上にバージョン付き設定レコードを 1 つ保持する。
+///
+/// `SLOT` はレコード 1 件分のバイト数 (ヘッダ込み) で、ブロックサイズを割り切る必要がある。
+pub struct PersistentSettings<S, const SLOT: usize> {
+    storage: S,
+    slots_per_block: u32,
+    slot_count: u32,
+    latest: Option<RecordInfo>,
+    next_slot: u32,
+}
+
+impl<S, const SLOT: usize> PersistentSettings<S, SLOT>
+where
+    S: BlockStorage,
+{
+    /// 全スロットを走査して最新のレコードを探す。
+    pub fn mount(storage: S) -> Result<Self, SettingsError<S::Error>> {
+        let block_size = storage.block_size();
+        let block_count = storage.block_count();
+        if SLOT <= SETTINGS_HEADER_SIZE
+            || block_count < 2
+            || block_size < SLOT as u32
+            || block_size % SLOT as u32 != 0
+        {
+            return Err(SettingsError::InvalidGeometry);
+        }
+        let slots_per_block = block_size / SLOT as u32;
+        let slot_count = slots_per_block * block_count;
+
+        let mut settings = Self {
+            storage,
+            slots_per_block,
+            slot_count,
+            latest: None,
+            next_slot: 0,
+        };
+        let mut buffer = [0u8; SLOT];
+        for slot in 0..slot_count {
+            settings
+                .storage
+                .read(settings.slot_offset(slot), &mut buffer)?;
+            if let Some(info) = parse_record(slot, &buffer) {
+                if settings
+                    .latest
+                    .map_or(true, |latest| info.sequence > latest.sequence)
+                {
+                    settings.latest = Some(info);
+                }
+            }
+        }
+
+        if let Some(latest) = settings.latest {
+            settings.next_slot = (latest.slot + 1) % slot_count;
+            // 書きかけのゴミが残っているスロットには上書きできないので次のブロックへ進む。
+            // ブロック先頭なら古いブロックが残っているだけで、save が消去してから書く
+            if settings.next_slot % slots_per_block != 0
+                && !settings.slot_is_erased(settings.next_slot)?
+            {
+                settings.next_slot = settings.next_block_start(settings.next_slot);
+            }
+        }
+        Ok(settings)
+    }
+
+    /// 最新の設定を読む。保存されていなければ `T::default()`。
+    ///
+    /// 古いバージョンのレコードは変換して保存し直す。
+    pub fn load<T>(&mut self) -> Result<(T, SettingsSource), SettingsError<S::Error>>
+    where
+        T: SettingsSchema + Default,
+    {
+        let Some(latest) = self.latest else {
+            return Ok((T::default(), SettingsSource::Default));
+        };
+        let mut buffer = [0u8; SLOT];
+        self.storage
+            .read(self.slot_offset(latest.slot), &mut buffer)?;
+        let payload = &buffer[SETTINGS_HEADER_SIZE..SETTINGS_HEADER_SIZE + usize::from(latest.len)];
+
+        if latest.version == T::VERSION {
+            let value = T::decode(payload).ok_or(SettingsError::Corrupted)?;
+            return Ok((value, SettingsSource::Stored));
+        }
+        if latest.version > T::VERSION {
+            return Err(SettingsError::UnsupportedVersion(latest.version));
+        }
+        let value = T::migrate(latest.version, payload)
+            .ok_or(SettingsError::UnsupportedVersion(latest.version))?;
+        self.save(&value)?;
+        Ok((
+            value,
+            SettingsSource::Migrated {
+                from: latest.version,
+            },
+        ))
+    }
+
+    /// 設定を次のスロットへ保存する。
+    pub fn save<T>(&mut self, value: &T) -> Result<(), SettingsError<S::Error>>
+    where
+        T: SettingsSchema,
+    {
+        let mut buffer = [0xFFu8; SLOT];
+        let len = value
+            .encode(&mut buffer[SETTINGS_HEADER_SIZE..])
+            .filter(|len| *len <= SLOT - SETTINGS_HEADER_SIZE)
+            .ok_or(SettingsError::TooLarge)?;
+        let sequence = self
+            .latest
+            .map_or(0, |latest| latest.sequence.wrapping_add(1));
+        buffer[0..2].copy_from_slice(&SETTINGS_MAGIC.to_le_bytes());
+        buffer[2..4].copy_from_slice(&T::VERSION.to_le_bytes());
+        buffer[4..8].copy_from_slice(&sequence.to_le_bytes());
+        buffer[8..10].copy_from_slice(&(len as u16).to_le_bytes());
+        let crc = record_crc(&buffer[..SETTINGS_HEADER_SIZE + len]);
+        buffer[10..12].copy_from_slice(&crc.to_le_bytes());
+
+        let slot = self.next_slot;
+        if slot % self.slots_per_block == 0 {
+            self.storage.erase_block(slot / self.slots_per_block)?;
+        }
+        // 失敗しても次の保存は次のスロットから行う (書きかけのスロットは CRC で弾かれる)
+        self.next_slot = (slot + 1) % self.slot_count;
+        self.storage.write(
+            self.slot_offset(slot),
+            &buffer[..SETTINGS_HEADER_SIZE + len],
+        )?;
+        self.latest = Some(RecordInfo {
+            slot,
+            sequence,
+            version: T::VERSION,
+            len: len as u16,
+        });
+        Ok(())
+    }
+
+    /// 全ブロックを消去する (工場出荷状態)。
+    pub fn erase_all(&mut self) -> Result<(), SettingsError<S::Error>> {
+        for block in 0..self.storage.block_count() {
+            self.storage.erase_block(block)?;
+        }
+        self.latest = None;
+        self.next_slot = 0;
+        Ok(())
+    }
+
+    /// 保存済みレコードのスキーマバージョン。
+    pub fn stored_version(&self) -> Option<u16> {
+        self.latest.map(|latest| latest.version)
+    }
+
+    /// 最新レコードのシーケンス番号 (保存回数 - 1)。
+    pub fn sequence(&self) -> Option<u32> {
+        self.latest.map(|latest| latest.sequence)
+    }
+
+    pub fn slot_count(&self) -> u32 {
+        self.slot_count
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+
+    fn slot_offset(&self, slot: u32) -> u32 {
+        let block = slot / self.slots_per_block;
+        let index = slot % self.slots_per_block;
+        block * self.storage.block_size() + index * SLOT as u32
+    }
+
+    fn slot_is_erased(&mut self, slot: u32) -> Result<bool, S::Error> {
+        let mut header = [0u8; SETTINGS_HEADER_SIZE];
+        self.storage.read(self.slot_offset(slot), &mut header)?;
+        Ok(header.iter().all(|byte| *byte == 0xFF))
+    }
+
+    fn next_block_start(&self, slot: u32) -> u32 {
+        let block = slot / self.slots_per_block;
+        ((block + 1) % (self.slot_count / self.slots_per_block)) * self.slots_per_block
+    }
+}
+
+fn record_crc(record: &[u8]) -> u16 {
+    crc16_ccitt_update(crc16_ccitt_update(0xFFFF, &record[..10]), &record[12..])
+}
+
+fn parse_record(slot: u32, bytes: &[u8]) -> Option<RecordInfo> {
+    if u16::from_le_bytes([bytes[0], bytes[1]]) != SETTINGS_MAGIC {
+        return None;
+    }
+    let len = u16::from_le_bytes([bytes[8], bytes[9]]);
+    let end = SETTINGS_HEADER_SIZE + usize::from(len);
+    if end > bytes.len() || u16::from_le_bytes([bytes[10], bytes[11]]) != record_crc(&bytes[..end])
+    {
+        return None;
+    }
+    Some(RecordInfo {
+        slot,
+        sequence: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        version: u16::from_le_bytes([bytes[2], bytes[3]]),
+        len,
+    })
+}
+
+/// [`FlashKvStore`] が保持できる値の最大バイト数 (WPA2 の PSK が収まる長さ)。
+pub const KV_MAX_VALUE_LEN: usize = 64;
+
+type KvKey = String<KV_MAX_KEY_LEN>;
+type KvValue = Vec<u8, KV_MAX_VALUE_LEN>;
+
+/// 最大 `N` 個のキーバリューを 1 レコードにまとめた設定。
+///
+/// ペイロードは `件数 (1)` に続いて `キー長 (1) / キー / 値長 (1) / 値` の繰り返し。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KvRecord<const N: usize> {
+    entries: Vec<(KvKey, KvValue), N>,
+}
+
+impl<const N: usize> KvRecord<N> {
+    pub fn get(&self, key: &str) -> Option<&[u8]> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| entry.as_str() == key)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(key, _)| key.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn insert(&mut self, key: &str, value: &[u8]) -> Result<(), KvError> {
+        if !is_valid_key(key) {
+            return Err(KvError::InvalidKey);
+        }
+        let value = KvValue::from_slice(value).map_err(|_| KvError::ValueTooLarge)?;
+        if let Some((_, existing)) = self
+            .entries
+            .iter_mut()
+            .find(|(entry, _)| entry.as_str() == key)
+        {
+            *existing = value;
+            return Ok(());
+        }
+        let mut owned = KvKey::new();
+        owned.push_str(key).map_err(|_| KvError::InvalidKey)?;
+        self.entries.push((owned, value)).map_err(|_| KvError::Full)
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        match self
+            .entries
+            .iter()
+            .position(|(entry, _)| entry.as_str() == key)
+        {
+            Some(index) => {
+                self.entries.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<const N: usize> SettingsSchema for KvRecord<N> {
+    const VERSION: u16 = 1;
+
+    fn encode(&self, out: &mut [u8]) -> Option<usize> {
+        let mut cursor = 0;
+        let mut put = |bytes: &[u8]| -> Option<()> {
+            out.get_mut(cursor..cursor + bytes.len())?
+                .copy_from_slice(bytes);
+            cursor += bytes.len();
+            Some(())
+        };
+        put(&[self.entries.len() as u8])?;
+        for (key, value) in &self.entries {
+            put(&[key.len() as u8])?;
+            put(key.as_bytes())?;
+            put(&[value.len() as u8])?;
+            put(value)?;
+        }
+        Some(cursor)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let (&count, mut rest) = bytes.split_first()?;
+        let mut record = Self::default();
+        for _ in 0..count {
+            let (&key_len, tail) = rest.split_first()?;
+            let (key, tail) = split_prefix(tail, key_len)?;
+            let (&value_len, tail) = tail.split_first()?;
+            let (value, tail) = split_prefix(tail, value_len)?;
+            record.insert(core::str::from_utf8(key).ok()?, value).ok()?;
+            rest = tail;
+        }
+        Some(record)
+    }
+}
+
+fn split_prefix(bytes: &[u8], len: u8) -> Option<(&[u8], &[u8])> {
+    let len = usize::from(len);
+    (bytes.len() >= len).then(|| bytes.split_at(len))
+}
+
+/// `BlockStorage` 上のキーバリューストア。最大 `N` キー、レコード 1 件 `SLOT` バイト。
+///
+/// 変更のたびに全キーを 1 レコードとして書き直すので、数十バイト程度の設定値向け。
+pub struct FlashKvStore<S, const N: usize, const SLOT: usize> {
+    settings: PersistentSettings<S, SLOT>,
+    record: KvRecord<N>,
+}
+
+impl<S, const N: usize, const SLOT: usize> FlashKvStore<S, N, SLOT>
+where
+    S: BlockStorage,
+{
+    pub fn mount(storage: S) -> Result<Self, SettingsError<S::Error>> {
+        let mut settings = PersistentSettings::mount(storage)?;
+        let (record, _) = settings.load::<KvRecord<N>>()?;
+        Ok(Self { settings, record })
+    }
+
+    pub fn record(&self) -> &KvRecord<N> {
+        &self.record
+    }
+
+    /// 全キーを削除してストレージを消去する。
+    pub fn clear(&mut self) -> Result<(), KvError> {
+        self.settings.erase_all().map_err(kv_error)?;
+        self.record = KvRecord::default();
+        Ok(())
+    }
+
+    pub fn settings(&self) -> &PersistentSettings<S, SLOT> {
+        &self.settings
+    }
+
+    pub fn into_inner(self) -> S {
+        self.settings.into_inner()
+    }
+
+    fn commit(&mut self, record: KvRecord<N>) -> Result<(), KvError> {
+        self.settings.save(&record).map_err(kv_error)?;
+        self.record = record;
+        Ok(())
+    }
+}
+
+fn kv_error<E>(error: SettingsError<E>) -> KvError {
+    match error {
+        SettingsError::TooLarge => KvError::Full,
+        _ => KvError::Storage,
+    }
+}
+
+impl<S, const N: usize, const SLOT: usize> KeyValueStore for FlashKvStore<S, N, SLOT>
+where
+    S: BlockStorage,
+{
+    type Error = KvError;
+
+    fn get(&mut self, key: &str, buffer: &mut [u8]) -> Result<Option<usize>, Self::Error> {
+        let Some(value) = self.record.get(key) else {
+            return Ok(None);
+        };
+        let target = buffer
+            .get_mut(..value.len())
+            .ok_or(KvError::BufferTooSmall)?;
+        target.copy_from_slice(value);
+        Ok(Some(value.len()))
+    }
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Self::Error> {
+        if self.record.get(key) == Some(value) {
+            return Ok(());
+        }
+        let mut record = self.record.clone();
+        record.insert(key, value)?;
+        self.commit(record)
+    }
+
+    fn remove(&mut self, key: &str) -> Result<bool, Self::Error> {
+        let mut record = self.record.clone();
+        if !record.remove(key) {
+            return Ok(false);
+        }
+        self.commit(record)?;
+        Ok(true)
+    }
+}
+
+/// メニューの設定値は `menu.<key>` というキーで保存する。
+impl<S, const N: usize, const SLOT: usize> SettingsStore for FlashKvStore<S, N, SLOT>
+where
+    S: BlockStorage,
+{
+    type Error = KvError;
+
+    fn load(&mut self, key: SettingKey) -> Result<Option<i32>, Self::Error> {
+        self.get_i32(&menu_key(key))
+    }
+
+    fn store(&mut self, key: SettingKey, value: i32) -> Result<(), Self::Error> {
+        self.set_i32(&menu_key(key), value)
+    }
+}
+
+fn menu_key(key: SettingKey) -> String<KV_MAX_KEY_LEN> {
+    use core::fmt::Write as _;
+
+    let mut text = String::new();
+    let _ = write!(text, "menu.{key}");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hal_api::error::StorageError;
+    use std::vec;
+    use std::vec::Vec as StdVec;
+
+    struct RamFlash {
+        bytes: StdVec<u8>,
+        block_size: u32,
+        erase_counts: StdVec<u32>,
+        tear_next_write: Option<usize>,
+    }
+
+    impl RamFlash {
+        fn new(block_size: u32, block_count: u32) -> Self {
+            Self {
+                bytes: vec![0xFF; (block_size * block_count) as usize],
+                block_size,
+                erase_counts: vec![0; block_count as usize],
+                tear_next_write: None,
+            }
+        }
+    }
+
+    impl BlockStorage for RamFlash {
+        type Error = StorageError;
+
+        fn block_size(&self) -> u32 {
+            self.block_size
+        }
+
+        fn block_count(&self) -> u32 {
+            self.erase_counts.len() as u32
+        }
+
+        fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), Self::Error> {
+            let start = offset as usize;
+            let source = self
+                .bytes
+                .get(start..start + buffer.len())
+                .ok_or(StorageError::OutOfBounds)?;
+            buffer.copy_from_slice(source);
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
+            let start = offset as usize;
+            let torn = self.tear_next_write.take();
+            let len = torn.unwrap_or(data.len()).min(data.len());
+            for (cell, byte) in self.bytes[start..start + len].iter_mut().zip(data) {
+                *cell &= *byte;
+            }
+            if torn.is_some() {
+                return Err(StorageError::WriteFailed);
+            }
+            Ok(())
+        }
+
+        fn erase_block(&mut self, block: u32) -> Result<(), Self::Error> {
+            let start = (block * self.block_size) as usize;
+            self.bytes[start..start + self.block_size as usize].fill(0xFF);
+            self.erase_counts[block as usize] += 1;
+            Ok(())
+        }
+    }
+
+    /// v1: 更新周期のみ。v2 で LCD の I2C アドレスを追加した想定。
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct DeviceConfigV2 {
+        refresh_ticks: u32,
+        lcd_address: u8,
+    }
+
+    impl Default for DeviceConfigV2 {
+        fn default() -> Self {
+            Self {
+                refresh_ticks: 100,
+                lcd_address: 0x27,
+            }
+        }
+    }
+
+    impl SettingsSchema for DeviceConfigV2 {
+        const VERSION: u16 = 2;
+
+        fn encode(&self, out: &mut [u8]) -> Option<usize> {
+            out.get_mut(..5)?[..4].copy_from_slice(&self.refresh_ticks.to_le_bytes());
+            out[4] = self.lcd_address;
+            Some(5)
+        }
+
+        fn decode(bytes: &[u8]) -> Option<Self> {
+            let bytes: [u8; 5] = bytes.try_into().ok()?;
+            Some(Self {
+                refresh_ticks: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                lcd_address: bytes[4],
+            })
+        }
+
+        fn migrate(from_version: u16, bytes: &[u8]) -> Option<Self> {
+            match from_version {
+                1 => Some(Self {
+                    refresh_ticks: V1(u32::from_le_bytes(bytes.try_into().ok()?)).0,
+                    ..Self::default()
+                }),
+                _ => None,
+            }
+        }
+    }
+
+    struct V1(u32);
+
+    impl SettingsSchema for V1 {
+        const VERSION: u16 = 1;
+
+        fn encode(&self, out: &mut [u8]) -> Option<usize> {
+            out.get_mut(..4)?.copy_from_slice(&self.0.to_le_bytes());
+            Some(4)
+        }
+
+        fn decode(bytes: &[u8]) -> Option<Self> {
+            Some(Self(u32::from_le_bytes(bytes.try_into().ok()?)))
+        }
+    }
+
+    type Settings = PersistentSettings<RamFlash, 32>;
+
+    #[test]
+    fn empty_storage_loads_default() {
+        let mut settings = Settings::mount(RamFlash::new(128, 2)).unwrap();
+        assert_eq!(settings.slot_count(), 8);
+        assert_eq!(
+            settings.load::<DeviceConfigV2>().unwrap(),
+            (DeviceConfigV2::default(), SettingsSource::Default)
+        );
+    }
+
+    #[test]
+    fn saved_value_survives_remount() {
+        let mut settings = Settings::mount(RamFlash::new(128, 2)).unwrap();
+        let config = DeviceConfigV2 {
+            refresh_ticks: 500,
+            lcd_address: 0x3F,
+        };
+        settings.save(&config).unwrap();
+
+        let mut settings = Settings::mount(settings.into_inner()).unwrap();
+        assert_eq!(
+            settings.load::<DeviceConfigV2>().unwrap(),
+            (config, SettingsSource::Stored)
+        );
+        assert_eq!(settings.stored_version(), Some(2));
+    }
+
+    #[test]
+    fn old_schema_is_migrated_and_rewritten() {
+        let mut settings = Settings::mount(RamFlash::new(128, 2)).unwrap();
+        settings.save(&V1(250)).unwrap();
+
+        let mut settings = Settings::mount(settings.into_inner()).unwrap();
+        let (config, source) = settings.load::<DeviceConfigV2>().unwrap();
+        assert_eq!(source, SettingsSource::Migrated { from: 1 });
+        assert_eq!(config.refresh_ticks, 250);
+        assert_eq!(config.lcd_address, 0x27);
+        assert_eq!(settings.stored_version(), Some(2));
+
+        let mut settings = Settings::mount(settings.into_inner()).unwrap();
+        assert_eq!(
+            settings.load::<DeviceConfigV2>().unwrap().1,
+            SettingsSource::Stored
+        );
+        // 新しいバージョンのレコードは古いスキーマでは読めない
+        assert_eq!(
+            settings.load::<V1Default>().map(|_| ()),
+            Err(SettingsError::UnsupportedVersion(2))
+        );
+    }
+
+    #[derive(Default)]
+    struct V1Default;
+
+    impl SettingsSchema for V1Default {
+        const VERSION: u16 = 1;
+
+        fn encode(&self, _out: &mut [u8]) -> Option<usize> {
+            Some(0)
+        }
+
+        fn decode(_bytes: &[u8]) -> Option<Self> {
+            Some(Self)
+        }
+    }
+
+    #[test]
+    fn torn_write_falls_back_to_previous_record() {
+        let mut settings = Settings::mount(RamFlash::new(128, 2)).unwrap();
+        settings.save(&V1(1)).unwrap();
+        settings.save(&V1(2)).unwrap();
+
+        let mut flash = settings.into_inner();
+        flash.tear_next_write = Some(6);
+        let mut settings = Settings::mount(flash).unwrap();
+        assert!(settings.save(&V1(3)).is_err());
+
+        let mut settings = Settings::mount(settings.into_inner()).unwrap();
+        assert_eq!(settings.sequence(), Some(1));
+        assert_eq!(
+            settings.load::<DeviceConfigV2>().unwrap().0.refresh_ticks,
+            2
+        );
+
+        // 書きかけのスロットの次のブロックから再開する
+        settings.save(&V1(4)).unwrap();
+        let mut settings = Settings::mount(settings.into_inner()).unwrap();
+        assert_eq!(
+            settings.load::<DeviceConfigV2>().unwrap().0.refresh_ticks,
+            4
+        );
+    }
+
+    #[test]
+    fn torn_write_after_remount_at_block_start_keeps_latest_record() {
+        // 2 スロット × 2 ブロック。0..6 を保存すると次は 2, 3 が残るブロック 1 の先頭
+        let mut settings = Settings::mount(RamFlash::new(64, 2)).unwrap();
+        for value in 0..6 {
+            settings.save(&V1(value)).unwrap();
+        }
+        let mut flash = settings.into_inner();
+        flash.tear_next_write = Some(6);
+        let mut settings = Settings::mount(flash).unwrap();
+        assert!(settings.save(&V1(100)).is_err());
+
+        let mut settings = Settings::mount(settings.into_inner()).unwrap();
+        assert_eq!(settings.sequence(), Some(5));
+        assert_eq!(
+            settings.load::<DeviceConfigV2>().unwrap().0.refresh_ticks,
+            5
+        );
+    }
+
+    #[test]
+    fn writes_rotate_through_all_blocks() {
+        let mut settings: PersistentSettings<RamFlash, 32> =
+            PersistentSettings::mount(RamFlash::new(64, 4)).unwrap();
+        for value in 0..80 {
+            settings.save(&V1(value)).unwrap();
+        }
+        // 8 スロット × 10 周
+        assert_eq!(settings.storage().erase_counts, [10, 10, 10, 10]);
+        let mut settings: PersistentSettings<RamFlash, 32> =
+            PersistentSettings::mount(settings.into_inner()).unwrap();
+        assert_eq!(settings.sequence(), Some(79));
+        assert_eq!(
+            settings.load::<DeviceConfigV2>().unwrap().0.refresh_ticks,
+            79
+        );
+    }
+
+    #[test]
+    fn mount_rejects_bad_geometry() {
+        assert!(matches!(
+            PersistentSettings::<_, 32>::mount(RamFlash::new(128, 1)),
+            Err(SettingsError::InvalidGeometry)
+        ));
+        assert!(matches!(
+            PersistentSettings::<_, 48>::mount(RamFlash::new(128, 2)),
+            Err(SettingsError::InvalidGeometry)
+        ));
+        assert!(matches!(
+            PersistentSettings::<_, 8>::mount(RamFlash::new(128, 2)),
+            Err(SettingsError::InvalidGeometry)
+        ));
+    }
+
+    #[test]
+    fn kv_store_sets_removes_and_reports_limits() {
+        let mut store: FlashKvStore<RamFlash, 2, 128> =
+            FlashKvStore::mount(RamFlash::new(256, 2)).unwrap();
+        store.set("wifi_ssid", b"lab").unwrap();
+        store.set_i32("interval", 30).unwrap();
+        assert_eq!(store.set("third", b"x"), Err(KvError::Full));
+        assert_eq!(
+            store.set("a_very_long_key_", b"x"),
+            Err(KvError::InvalidKey)
+        );
+        assert_eq!(
+            store.set("wifi_ssid", &[0u8; KV_MAX_VALUE_LEN + 1]),
+            Err(KvError::ValueTooLarge)
+        );
+
+        let mut small = [0u8; 2];
+        assert_eq!(
+            store.get("wifi_ssid", &mut small),
+            Err(KvError::BufferTooSmall)
+        );
+
+        assert!(store.remove("wifi_ssid").unwrap());
+        assert!(!store.remove("wifi_ssid").unwrap());
+
+        let mut store: FlashKvStore<RamFlash, 2, 128> =
+            FlashKvStore::mount(store.into_inner()).unwrap();
+        assert_eq!(store.get_i32("interval").unwrap(), Some(30));
+        assert_eq!(store.get("wifi_ssid", &mut small).unwrap(), None);
+        assert_eq!(store.record().keys().collect::<StdVec<_>>(), ["interval"]);
+    }
+
+    #[test]
+    fn kv_store_rejects_record_larger_than_slot() {
+        let mut store: FlashKvStore<RamFlash, 4, 64> =
+            FlashKvStore::mount(RamFlash::new(128, 2)).unwrap();
+        store.set("psk", &[b'p'; 40]).unwrap();
+        assert_eq!(store.set("psk2", &[b'q'; 40]), Err(KvError::Full));
+        assert_eq!(store.record().len(), 1);
+    }
+
+    #[test]
+    fn kv_store_backs_menu_settings() {
+        let mut store: FlashKvStore<RamFlash, 4, 128> =
+            FlashKvStore::mount(RamFlash::new(256, 2)).unwrap();
+        SettingsStore::store(&mut store, 7, -12).unwrap();
+        assert_eq!(SettingsStore::load(&mut store, 7).unwrap(), Some(-12));
+        assert_eq!(store.get_i32("menu.7").unwrap(), Some(-12));
+        assert_eq!(SettingsStore::load(&mut store, 8).unwrap(), None);
+    }
+}
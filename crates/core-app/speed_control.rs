@@ -0,0 +1,403 @@
+//! 速度フィードバック付き DC モータ制御。
+//!
+//! [`SpeedControlledMotor`] は開ループの [`DriveMotor`] と [`SpeedFeedback`] を束ね、
+//! [`PidController`] で duty を調整して目標回転数 (rpm) を保ちます。
+//! 自身も [`DriveMotor`] を実装するため、既存アプリの `MotorCommand` をそのまま渡せます。
+//! このとき `duty_percent` は「`max_rpm` に対する目標速度の割合」として解釈されます。
+//!
+//! `Brake` / `Coast` は制御ループを止めて下位モータへそのまま渡します。
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::speed_control::SpeedControlledMotor;
+//! use hal_api::actuator::{DriveMotor, MotorCommand, MotorDirection};
+//! use hal_api::encoder::{SpeedFeedback, SpeedReading};
+//!
+//! struct Motor;
+//! impl DriveMotor for Motor {
+//!     type Error = ();
+//!     fn apply(&mut self, _command: MotorCommand) -> Result<(), ()> { Ok(()) }
+//! }
+//!
+//! struct Tach;
+//! impl SpeedFeedback for Tach {
+//!     type Error = ();
+//!     fn read_speed(&mut self) -> Result<SpeedReading, ()> { Ok(SpeedReading::new(0)) }
+//! }
+//!
+//! let mut motor = SpeedControlledMotor::new(Motor, Tach);
+//! motor.set_target_rpm(120);
+//! motor.tick().unwrap();
+//! assert_eq!(motor.last_command().unwrap().direction, MotorDirection::Forward);
+//! ```
+
+use crate::pid::{PidConfig, PidController};
+use hal_api::actuator::{DriveMotor, MotorCommand, MotorDirection};
+use hal_api::encoder::SpeedFeedback;
+
+#[cfg(test)]
+extern crate std;
+
+/// `SpeedControlledMotor` の設定。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeedControlConfig {
+    /// `duty_percent = 100` の `MotorCommand` に対応する目標回転数。
+    pub max_rpm: u32,
+    /// 制御ループを回す tick 間隔。
+    pub control_period_ticks: u32,
+    /// 速度誤差 (rpm) → duty (%) の PID 設定。出力レンジは符号付き duty として扱う。
+    pub pid: PidConfig,
+}
+
+impl Default for SpeedControlConfig {
+    fn default() -> Self {
+        Self {
+            max_rpm: 300,
+            control_period_ticks: 1,
+            pid: PidConfig {
+                kp_milli: 150,
+                ki_milli: 40,
+                kd_milli: 0,
+                output_min: -100,
+                output_max: 100,
+            },
+        }
+    }
+}
+
+/// `SpeedControlledMotor` が返すエラー型。
+#[derive(Debug, PartialEq, Eq)]
+pub enum SpeedControlError<M, S> {
+    /// `duty_percent` が 100 を超える指令。
+    InvalidCommand,
+    Motor(M),
+    Sensor(S),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlMode {
+    /// 目標回転数へ閉ループ制御中。
+    ClosedLoop(i32),
+    /// `Brake` / `Coast` を下位モータへそのまま渡している。
+    OpenLoop,
+}
+
+/// 目標回転数で動く閉ループ速度制御モータ。
+pub struct SpeedControlledMotor<M, S> {
+    motor: M,
+    sensor: S,
+    config: SpeedControlConfig,
+    pid: PidController,
+    mode: ControlMode,
+    tick_count: u32,
+    last_speed_rpm: Option<i32>,
+    last_command: Option<MotorCommand>,
+}
+
+impl<M, S> SpeedControlledMotor<M, S>
+where
+    M: DriveMotor,
+    S: SpeedFeedback,
+{
+    pub fn new(motor: M, sensor: S) -> Self {
+        Self::new_with_config(motor, sensor, SpeedControlConfig::default())
+    }
+
+    pub fn new_with_config(motor: M, sensor: S, config: SpeedControlConfig) -> Self {
+        Self {
+            motor,
+            sensor,
+            config,
+            pid: PidController::new(config.pid),
+            mode: ControlMode::OpenLoop,
+            tick_count: 0,
+            last_speed_rpm: None,
+            last_command: None,
+        }
+    }
+
+    /// 目標回転数を設定して閉ループ制御を開始する。負の値は逆転。
+    pub fn set_target_rpm(&mut self, rpm: i32) {
+        if self.mode == ControlMode::OpenLoop {
+            self.pid.reset();
+        }
+        self.mode = ControlMode::ClosedLoop(rpm);
+    }
+
+    /// 閉ループ制御中の目標回転数。`Brake` / `Coast` 中は `None`。
+    pub fn target_rpm(&self) -> Option<i32> {
+        match self.mode {
+            ControlMode::ClosedLoop(rpm) => Some(rpm),
+            ControlMode::OpenLoop => None,
+        }
+    }
+
+    /// 1 tick 進める。`control_period_ticks` ごとに速度を読み、PID で duty を更新する。
+    pub fn tick(&mut self) -> Result<(), SpeedControlError<M::Error, S::Error>> {
+        self.tick_count = self.tick_count.wrapping_add(1);
+        let period = self.config.control_period_ticks.max(1);
+        if self.tick_count % period != 0 {
+            return Ok(());
+        }
+
+        let speed = self
+            .sensor
+            .read_speed()
+            .map_err(SpeedControlError::Sensor)?;
+        self.last_speed_rpm = Some(speed.rpm);
+
+        if let ControlMode::ClosedLoop(target) = self.mode {
+            let output = self.pid.update(target, speed.rpm);
+            let command = command_from_signed_duty(output);
+            self.motor
+                .apply(command)
+                .map_err(SpeedControlError::Motor)?;
+            self.last_command = Some(command);
+        }
+        Ok(())
+    }
+
+    /// 最後に読み取った回転数。
+    pub fn last_speed_rpm(&self) -> Option<i32> {
+        self.last_speed_rpm
+    }
+
+    /// 最後に下位モータへ渡した指令。
+    pub fn last_command(&self) -> Option<MotorCommand> {
+        self.last_command
+    }
+
+    pub fn pid(&self) -> &PidController {
+        &self.pid
+    }
+
+    /// 実行中に PID ゲインを差し替える (host 上でのチューニング用)。
+    pub fn set_pid_config(&mut self, pid: PidConfig) {
+        self.config.pid = pid;
+        self.pid.set_config(pid);
+    }
+
+    pub fn config(&self) -> SpeedControlConfig {
+        self.config
+    }
+
+    pub fn tick_count(&self) -> u32 {
+        self.tick_count
+    }
+
+    pub fn into_inner(self) -> (M, S) {
+        (self.motor, self.sensor)
+    }
+}
+
+impl<M, S> DriveMotor for SpeedControlledMotor<M, S>
+where
+    M: DriveMotor,
+    S: SpeedFeedback,
+{
+    type Error = SpeedControlError<M::Error, S::Error>;
+
+    fn apply(&mut self, command: MotorCommand) -> Result<(), Self::Error> {
+        if command.duty_percent > 100 {
+            return Err(SpeedControlError::InvalidCommand);
+        }
+        let rpm = (self.config.max_rpm as i64 * command.duty_percent as i64 / 100) as i32;
+        match command.direction {
+            MotorDirection::Forward => self.set_target_rpm(rpm),
+            MotorDirection::Reverse => self.set_target_rpm(-rpm),
+            MotorDirection::Brake | MotorDirection::Coast => {
+                self.mode = ControlMode::OpenLoop;
+                self.pid.reset();
+                self.motor
+                    .apply(command)
+                    .map_err(SpeedControlError::Motor)?;
+                self.last_command = Some(command);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// PID 出力 (符号付き duty %) を `MotorCommand` へ変換する。
+pub fn command_from_signed_duty(duty: i32) -> MotorCommand {
+    let magnitude = duty.unsigned_abs().min(100) as u8;
+    if duty >= 0 {
+        MotorCommand::new(MotorDirection::Forward, magnitude)
+    } else {
+        MotorCommand::new(MotorDirection::Reverse, magnitude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hal_api::encoder::SpeedReading;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec::Vec;
+
+    #[derive(Clone, Default)]
+    struct MockMotor {
+        history: Rc<RefCell<Vec<MotorCommand>>>,
+    }
+
+    impl DriveMotor for MockMotor {
+        type Error = ();
+
+        fn apply(&mut self, command: MotorCommand) -> Result<(), ()> {
+            self.history.borrow_mut().push(command);
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct MockTach {
+        rpm: Rc<RefCell<i32>>,
+        fail: bool,
+    }
+
+    impl SpeedFeedback for MockTach {
+        type Error = &'static str;
+
+        fn read_speed(&mut self) -> Result<SpeedReading, &'static str> {
+            if self.fail {
+                return Err("tach");
+            }
+            Ok(SpeedReading::new(*self.rpm.borrow()))
+        }
+    }
+
+    fn p_only_config() -> SpeedControlConfig {
+        SpeedControlConfig {
+            max_rpm: 200,
+            control_period_ticks: 1,
+            pid: PidConfig {
+                kp_milli: 500,
+                ki_milli: 0,
+                kd_milli: 0,
+                output_min: -100,
+                output_max: 100,
+            },
+        }
+    }
+
+    #[test]
+    fn apply_forward_maps_duty_to_target_rpm() {
+        let mut motor = SpeedControlledMotor::new_with_config(
+            MockMotor::default(),
+            MockTach::default(),
+            p_only_config(),
+        );
+        motor
+            .apply(MotorCommand::new(MotorDirection::Forward, 50))
+            .unwrap();
+        assert_eq!(motor.target_rpm(), Some(100));
+        motor
+            .apply(MotorCommand::new(MotorDirection::Reverse, 25))
+            .unwrap();
+        assert_eq!(motor.target_rpm(), Some(-50));
+    }
+
+    #[test]
+    fn tick_drives_motor_toward_target() {
+        let inner = MockMotor::default();
+        let tach = MockTach::default();
+        let mut motor =
+            SpeedControlledMotor::new_with_config(inner.clone(), tach.clone(), p_only_config());
+        motor.set_target_rpm(100);
+        motor.tick().unwrap();
+        assert_eq!(
+            inner.history.borrow().last().copied(),
+            Some(MotorCommand::new(MotorDirection::Forward, 50))
+        );
+
+        // 目標を超えたら逆向きの指令になる
+        *tach.rpm.borrow_mut() = 140;
+        motor.tick().unwrap();
+        assert_eq!(
+            motor.last_command(),
+            Some(MotorCommand::new(MotorDirection::Reverse, 20))
+        );
+        assert_eq!(motor.last_speed_rpm(), Some(140));
+    }
+
+    #[test]
+    fn brake_and_coast_bypass_the_loop() {
+        let inner = MockMotor::default();
+        let mut motor = SpeedControlledMotor::new_with_config(
+            inner.clone(),
+            MockTach::default(),
+            p_only_config(),
+        );
+        motor.set_target_rpm(100);
+        motor
+            .apply(MotorCommand::new(MotorDirection::Brake, 0))
+            .unwrap();
+        assert_eq!(motor.target_rpm(), None);
+
+        motor.tick().unwrap();
+        assert_eq!(
+            inner.history.borrow().as_slice(),
+            &[MotorCommand::new(MotorDirection::Brake, 0)]
+        );
+    }
+
+    #[test]
+    fn control_period_is_respected() {
+        let inner = MockMotor::default();
+        let mut motor = SpeedControlledMotor::new_with_config(
+            inner.clone(),
+            MockTach::default(),
+            SpeedControlConfig {
+                control_period_ticks: 5,
+                ..p_only_config()
+            },
+        );
+        motor.set_target_rpm(100);
+        for _ in 0..4 {
+            motor.tick().unwrap();
+        }
+        assert!(inner.history.borrow().is_empty());
+        motor.tick().unwrap();
+        assert_eq!(inner.history.borrow().len(), 1);
+    }
+
+    #[test]
+    fn rejects_duty_over_100() {
+        let mut motor = SpeedControlledMotor::new(MockMotor::default(), MockTach::default());
+        assert_eq!(
+            motor.apply(MotorCommand::new(MotorDirection::Forward, 101)),
+            Err(SpeedControlError::InvalidCommand)
+        );
+    }
+
+    #[test]
+    fn sensor_error_is_propagated() {
+        let mut motor = SpeedControlledMotor::new(
+            MockMotor::default(),
+            MockTach {
+                fail: true,
+                ..Default::default()
+            },
+        );
+        motor.set_target_rpm(10);
+        assert_eq!(motor.tick(), Err(SpeedControlError::Sensor("tach")));
+    }
+
+    #[test]
+    fn command_from_signed_duty_clamps_magnitude() {
+        assert_eq!(
+            command_from_signed_duty(150),
+            MotorCommand::new(MotorDirection::Forward, 100)
+        );
+        assert_eq!(
+            command_from_signed_duty(-30),
+            MotorCommand::new(MotorDirection::Reverse, 30)
+        );
+        assert_eq!(
+            command_from_signed_duty(0),
+            MotorCommand::new(MotorDirection::Forward, 0)
+        );
+    }
+}
@@ -0,0 +1,597 @@
+//! テレメトリのシリアライズ — JSON / CBOR / InfluxDB line protocol / OpenMetrics。
+//!
+//! [`TelemetryFrame`] に載せた読み取り値だけを、呼び出し側のバッファへヒープ無しで書き出す。
+//! 固定小数点の値 (温度・湿度・照度) は小数 2 桁の 10 進数として出力し、浮動小数点は使わない。
+//!
+//! | 読み取り | フィールド |
+//! |---|---|
+//! | `EnvReading` | `temperature` / `humidity` / `pressure_pa` (あれば) |
+//! | `GasReading` | `co2_ppm` / `voc_ppb` |
+//! | `LightReading` | `lux` |
+//! | `ImuReading` | `accel_{x,y,z}_mg` / `gyro_{x,y,z}_mdps` / `imu_temperature` (あれば) |
+//! | `DistanceReading` | `distance_mm` |
+//!
+//! - JSON: `{"device_id":"..","timestamp":<UNIX 秒>,<フィールド>...}`
+//! - CBOR: 同じキーのマップ。小数は decimal fraction (tag 4)、時刻は epoch (tag 1)
+//! - line protocol: `<measurement>,device_id=<id> <フィールド> <UNIX ナノ秒>`。整数は `i` 接尾辞
+//! - OpenMetrics: フィールドごとの gauge (`<namespace>_temperature_celsius{device_id="..."}` など)。
+//!   詳細は [`crate::metrics`]
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::telemetry::TelemetryFrame;
+//! use hal_api::gas::GasReading;
+//! use hal_api::sensor::EnvReading;
+//!
+//! let frame = TelemetryFrame::new("esp32-01")
+//!     .with_env(EnvReading::new(2485, 4320, None))
+//!     .with_gas(GasReading::new(612, 35));
+//!
+//! let mut buffer = [0u8; 128];
+//! let len = frame.encode_json(&mut buffer).unwrap();
+//! assert_eq!(
+//!     &buffer[..len],
+//!     br#"{"device_id":"esp32-01","temperature":24.85,"humidity":43.20,"co2_ppm":612,"voc_ppb":35}"#
+//! );
+//!
+//! let len = frame.encode_line_protocol("climate", &mut buffer).unwrap();
+//! assert_eq!(
+//!     &buffer[..len],
+//!     b"climate,device_id=esp32-01 temperature=24.85,humidity=43.20,co2_ppm=612i,voc_ppb=35i"
+//! );
+//! ```
+
+use core::fmt::{self, Write};
+
+use hal_api::distance::DistanceReading;
+use hal_api::gas::GasReading;
+use hal_api::imu::ImuReading;
+use hal_api::light::LightReading;
+use hal_api::rtc::RtcDateTime;
+use hal_api::sensor::EnvReading;
+
+use crate::data_logger::unix_seconds;
+use crate::metrics::{MetricsError, MetricsWriter};
+
+#[cfg(test)]
+extern crate std;
+
+/// 出力形式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TelemetryFormat {
+    Json,
+    Cbor,
+    /// InfluxDB line protocol
+    LineProtocol,
+    /// OpenMetrics テキスト ([`crate::metrics`])
+    OpenMetrics,
+}
+
+impl TelemetryFormat {
+    /// HTTP の `Content-Type`。
+    pub fn content_type(self) -> &'static str {
+        match self {
+            TelemetryFormat::Json => "application/json",
+            TelemetryFormat::Cbor => "application/cbor",
+            TelemetryFormat::LineProtocol => "text/plain; charset=utf-8",
+            TelemetryFormat::OpenMetrics => crate::metrics::CONTENT_TYPE,
+        }
+    }
+}
+
+/// `TelemetryFrame` のエンコードが返すエラー型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TelemetryError {
+    /// 出力バッファが足りない
+    BufferTooSmall,
+    /// line protocol の measurement 名が空 / OpenMetrics の namespace に使えない文字がある
+    InvalidMeasurement,
+}
+
+/// 1 つのフィールド値。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldValue {
+    Int(i64),
+    /// `value / 10^decimals` を表す固定小数点
+    Fixed {
+        value: i64,
+        decimals: u8,
+    },
+}
+
+/// 1 回分の送信内容。載せた読み取り値だけが出力される。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TelemetryFrame<'a> {
+    pub device_id: &'a str,
+    /// UNIX 秒 (UTC)
+    pub timestamp: Option<u32>,
+    pub env: Option<EnvReading>,
+    pub gas: Option<GasReading>,
+    pub light: Option<LightReading>,
+    pub imu: Option<ImuReading>,
+    pub distance: Option<DistanceReading>,
+}
+
+impl<'a> TelemetryFrame<'a> {
+    pub const fn new(device_id: &'a str) -> Self {
+        Self {
+            device_id,
+            timestamp: None,
+            env: None,
+            gas: None,
+            light: None,
+            imu: None,
+            distance: None,
+        }
+    }
+
+    pub const fn with_env(mut self, reading: EnvReading) -> Self {
+        self.env = Some(reading);
+        self
+    }
+
+    pub const fn with_gas(mut self, reading: GasReading) -> Self {
+        self.gas = Some(reading);
+        self
+    }
+
+    pub const fn with_light(mut self, reading: LightReading) -> Self {
+        self.light = Some(reading);
+        self
+    }
+
+    pub const fn with_imu(mut self, reading: ImuReading) -> Self {
+        self.imu = Some(reading);
+        self
+    }
+
+    pub const fn with_distance(mut self, reading: DistanceReading) -> Self {
+        self.distance = Some(reading);
+        self
+    }
+
+    pub const fn with_unix_timestamp(mut self, seconds: u32) -> Self {
+        self.timestamp = Some(seconds);
+        self
+    }
+
+    /// RTC の日時を UTC とみなして UNIX 秒に変換して載せる。
+    pub fn with_rtc_timestamp(self, datetime: &RtcDateTime) -> Self {
+        self.with_unix_timestamp(unix_seconds(datetime))
+    }
+
+    /// 出力するフィールドを順に渡す (デバイス ID と時刻は含まない)。
+    pub fn for_each_field<F>(&self, mut f: F)
+    where
+        F: FnMut(&'static str, FieldValue),
+    {
+        let fixed2 = |value: i64| FieldValue::Fixed { value, decimals: 2 };
+        if let Some(env) = self.env {
+            f(
+                "temperature",
+                fixed2(i64::from(env.temperature_centi_celsius)),
+            );
+            f("humidity", fixed2(i64::from(env.humidity_centi_percent)));
+            if let Some(pressure) = env.pressure_pascal {
+                f("pressure_pa", FieldValue::Int(i64::from(pressure)));
+            }
+        }
+        if let Some(gas) = self.gas {
+            f("co2_ppm", FieldValue::Int(i64::from(gas.co2_ppm)));
+            f("voc_ppb", FieldValue::Int(i64::from(gas.voc_ppb)));
+        }
+        if let Some(light) = self.light {
+            f("lux", fixed2(i64::from(light.lux_x100)));
+        }
+        if let Some(imu) = self.imu {
+            const ACCEL: [&str; 3] = ["accel_x_mg", "accel_y_mg", "accel_z_mg"];
+            const GYRO: [&str; 3] = ["gyro_x_mdps", "gyro_y_mdps", "gyro_z_mdps"];
+            for (name, value) in ACCEL.iter().zip(imu.accel_mg) {
+                f(name, FieldValue::Int(i64::from(value)));
+            }
+            for (name, value) in GYRO.iter().zip(imu.gyro_mdps) {
+                f(name, FieldValue::Int(i64::from(value)));
+            }
+            if let Some(temperature) = imu.temperature_centi_celsius {
+                f("imu_temperature", fixed2(i64::from(temperature)));
+            }
+        }
+        if let Some(distance) = self.distance {
+            f(
+                "distance_mm",
+                FieldValue::Int(i64::from(distance.distance_mm)),
+            );
+        }
+    }
+
+    /// フィールド数 (デバイス ID と時刻は含まない)。
+    pub fn field_count(&self) -> usize {
+        let mut count = 0;
+        self.for_each_field(|_, _| count += 1);
+        count
+    }
+
+    /// 指定した形式で書き出す。`measurement` は line protocol の measurement 名、
+    /// OpenMetrics では namespace として使う。
+    pub fn encode(
+        &self,
+        format: TelemetryFormat,
+        measurement: &str,
+        out: &mut [u8],
+    ) -> Result<usize, TelemetryError> {
+        match format {
+            TelemetryFormat::Json => self.encode_json(out),
+            TelemetryFormat::Cbor => self.encode_cbor(out),
+            TelemetryFormat::LineProtocol => self.encode_line_protocol(measurement, out),
+            TelemetryFormat::OpenMetrics => self.encode_openmetrics(measurement, out),
+        }
+    }
+
+    /// 読み取り値だけの OpenMetrics 文書 (`# EOF` まで) を書き出す。
+    pub fn encode_openmetrics(
+        &self,
+        namespace: &str,
+        out: &mut [u8],
+    ) -> Result<usize, TelemetryError> {
+        let mut metrics = MetricsWriter::new(out, namespace);
+        self.write_metrics(&mut metrics)?;
+        Ok(metrics.finish()?)
+    }
+
+    pub fn encode_json(&self, out: &mut [u8]) -> Result<usize, TelemetryError> {
+        let mut writer = ByteWriter::new(out);
+        writer.push(b"{\"device_id\":")?;
+        write_json_string(&mut writer, self.device_id)?;
+        if let Some(timestamp) = self.timestamp {
+            write!(writer, ",\"timestamp\":{timestamp}")?;
+        }
+        let mut result = Ok(());
+        self.for_each_field(|name, value| {
+            if result.is_ok() {
+                result =
+                    write!(writer, ",\"{name}\":").and_then(|_| write_decimal(&mut writer, value));
+            }
+        });
+        result?;
+        writer.push(b"}")?;
+        Ok(writer.len())
+    }
+
+    pub fn encode_cbor(&self, out: &mut [u8]) -> Result<usize, TelemetryError> {
+        let mut writer = ByteWriter::new(out);
+        let entries = 1 + usize::from(self.timestamp.is_some()) + self.field_count();
+        cbor_head(&mut writer, CBOR_MAP, entries as u64)?;
+        cbor_text(&mut writer, "device_id")?;
+        cbor_text(&mut writer, self.device_id)?;
+        if let Some(timestamp) = self.timestamp {
+            cbor_text(&mut writer, "timestamp")?;
+            cbor_head(&mut writer, CBOR_TAG, 1)?;
+            cbor_int(&mut writer, i64::from(timestamp))?;
+        }
+        let mut result = Ok(());
+        self.for_each_field(|name, value| {
+            if result.is_ok() {
+                result = cbor_text(&mut writer, name).and_then(|_| cbor_value(&mut writer, value));
+            }
+        });
+        result?;
+        Ok(writer.len())
+    }
+
+    pub fn encode_line_protocol(
+        &self,
+        measurement: &str,
+        out: &mut [u8],
+    ) -> Result<usize, TelemetryError> {
+        if measurement.is_empty() {
+            return Err(TelemetryError::InvalidMeasurement);
+        }
+        let mut writer = ByteWriter::new(out);
+        write_escaped(&mut writer, measurement, b", ")?;
+        writer.push(b",device_id=")?;
+        write_escaped(&mut writer, self.device_id, b", =")?;
+
+        let mut first = true;
+        let mut result = Ok(());
+        self.for_each_field(|name, value| {
+            if result.is_err() {
+                return;
+            }
+            let separator = if first { " " } else { "," };
+            first = false;
+            result = write!(writer, "{separator}{name}=")
+                .and_then(|_| write_decimal(&mut writer, value))
+                .and_then(|_| match value {
+                    FieldValue::Int(_) => writer.write_str("i"),
+                    FieldValue::Fixed { .. } => Ok(()),
+                });
+        });
+        result?;
+        if let Some(timestamp) = self.timestamp {
+            write!(writer, " {timestamp}000000000")?;
+        }
+        Ok(writer.len())
+    }
+}
+
+/// 固定長バッファへの書き込み。溢れたら `fmt::Error` を返す。
+pub(crate) struct ByteWriter<'b> {
+    buffer: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> ByteWriter<'b> {
+    pub(crate) fn new(buffer: &'b mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    pub(crate) fn push(&mut self, bytes: &[u8]) -> fmt::Result {
+        let target = self
+            .buffer
+            .get_mut(self.len..self.len + bytes.len())
+            .ok_or(fmt::Error)?;
+        target.copy_from_slice(bytes);
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Write for ByteWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s.as_bytes())
+    }
+}
+
+impl From<MetricsError> for TelemetryError {
+    fn from(error: MetricsError) -> Self {
+        match error {
+            MetricsError::BufferTooSmall => TelemetryError::BufferTooSmall,
+            MetricsError::InvalidName => TelemetryError::InvalidMeasurement,
+        }
+    }
+}
+
+impl From<fmt::Error> for TelemetryError {
+    fn from(_: fmt::Error) -> Self {
+        TelemetryError::BufferTooSmall
+    }
+}
+
+pub(crate) fn write_decimal<W: Write>(out: &mut W, value: FieldValue) -> fmt::Result {
+    match value {
+        FieldValue::Int(value) => write!(out, "{value}"),
+        FieldValue::Fixed { value, decimals } => {
+            if decimals == 0 {
+                return write!(out, "{value}");
+            }
+            let sign = if value < 0 { "-" } else { "" };
+            let magnitude = value.unsigned_abs();
+            let width = usize::from(decimals);
+            // 10^20 以上は u64 に収まらないが、i64 の絶対値は必ずそれより小さい
+            let Some(scale) = 10u64.checked_pow(u32::from(decimals)) else {
+                return write!(out, "{sign}0.{magnitude:0width$}");
+            };
+            write!(
+                out,
+                "{sign}{}.{:0width$}",
+                magnitude / scale,
+                magnitude % scale,
+            )
+        }
+    }
+}
+
+fn write_json_string(out: &mut ByteWriter<'_>, value: &str) -> fmt::Result {
+    out.push(b"\"")?;
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push(b"\\\"")?,
+            '\\' => out.push(b"\\\\")?,
+            '\n' => out.push(b"\\n")?,
+            '\r' => out.push(b"\\r")?,
+            '\t' => out.push(b"\\t")?,
+            ch if (ch as u32) < 0x20 => write!(out, "\\u{:04x}", ch as u32)?,
+            ch => out.write_char(ch)?,
+        }
+    }
+    out.push(b"\"")
+}
+
+/// line protocol のエスケープ。`special` に含まれる文字の前に `\` を入れる。
+fn write_escaped(out: &mut ByteWriter<'_>, value: &str, special: &[u8]) -> fmt::Result {
+    for ch in value.chars() {
+        if ch.is_ascii() && special.contains(&(ch as u8)) {
+            out.push(b"\\")?;
+        }
+        out.write_char(ch)?;
+    }
+    Ok(())
+}
+
+const CBOR_UNSIGNED: u8 = 0;
+const CBOR_NEGATIVE: u8 = 1;
+const CBOR_TEXT: u8 = 3;
+const CBOR_ARRAY: u8 = 4;
+const CBOR_MAP: u8 = 5;
+const CBOR_TAG: u8 = 6;
+/// decimal fraction `[exponent, mantissa]`
+const CBOR_TAG_DECIMAL_FRACTION: u64 = 4;
+
+fn cbor_head(out: &mut ByteWriter<'_>, major: u8, argument: u64) -> fmt::Result {
+    let major = major << 5;
+    if argument < 24 {
+        out.push(&[major | argument as u8])
+    } else if argument <= u64::from(u8::MAX) {
+        out.push(&[major | 24, argument as u8])
+    } else if argument <= u64::from(u16::MAX) {
+        out.push(&[major | 25])?;
+        out.push(&(argument as u16).to_be_bytes())
+    } else if argument <= u64::from(u32::MAX) {
+        out.push(&[major | 26])?;
+        out.push(&(argument as u32).to_be_bytes())
+    } else {
+        out.push(&[major | 27])?;
+        out.push(&argument.to_be_bytes())
+    }
+}
+
+fn cbor_int(out: &mut ByteWriter<'_>, value: i64) -> fmt::Result {
+    if value >= 0 {
+        cbor_head(out, CBOR_UNSIGNED, value as u64)
+    } else {
+        cbor_head(out, CBOR_NEGATIVE, (-1 - value) as u64)
+    }
+}
+
+fn cbor_text(out: &mut ByteWriter<'_>, value: &str) -> fmt::Result {
+    cbor_head(out, CBOR_TEXT, value.len() as u64)?;
+    out.push(value.as_bytes())
+}
+
+fn cbor_value(out: &mut ByteWriter<'_>, value: FieldValue) -> fmt::Result {
+    match value {
+        FieldValue::Int(value) => cbor_int(out, value),
+        FieldValue::Fixed { value, decimals } => {
+            cbor_head(out, CBOR_TAG, CBOR_TAG_DECIMAL_FRACTION)?;
+            cbor_head(out, CBOR_ARRAY, 2)?;
+            cbor_int(out, -i64::from(decimals))?;
+            cbor_int(out, value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_json(frame: &TelemetryFrame<'_>) -> std::string::String {
+        let mut buffer = [0u8; 512];
+        let len = frame.encode_json(&mut buffer).unwrap();
+        std::string::String::from_utf8(buffer[..len].to_vec()).unwrap()
+    }
+
+    fn full_frame() -> TelemetryFrame<'static> {
+        TelemetryFrame::new("rig-1")
+            .with_rtc_timestamp(&RtcDateTime::new(25, 1, 6, 12, 0, 0))
+            .with_env(EnvReading::new(-525, 8000, Some(101_325)))
+            .with_gas(GasReading::new(1200, 90))
+            .with_light(LightReading::new(32_005))
+            .with_imu(ImuReading::new(
+                [12, -8, 1000],
+                [150, -2_000, 0],
+                Some(3_012),
+            ))
+            .with_distance(DistanceReading::new(742))
+    }
+
+    #[test]
+    fn json_matches_legacy_firmware_payload() {
+        // 旧 `build_json` と同じバイト列
+        let frame = TelemetryFrame::new("esp32-01").with_env(EnvReading::new(-305, 4005, None));
+        assert_eq!(
+            encode_json(&frame),
+            r#"{"device_id":"esp32-01","temperature":-3.05,"humidity":40.05}"#
+        );
+    }
+
+    #[test]
+    fn json_includes_every_reading_and_timestamp() {
+        assert_eq!(
+            encode_json(&full_frame()),
+            concat!(
+                r#"{"device_id":"rig-1","timestamp":1736164800,"#,
+                r#""temperature":-5.25,"humidity":80.00,"pressure_pa":101325,"#,
+                r#""co2_ppm":1200,"voc_ppb":90,"lux":320.05,"#,
+                r#""accel_x_mg":12,"accel_y_mg":-8,"accel_z_mg":1000,"#,
+                r#""gyro_x_mdps":150,"gyro_y_mdps":-2000,"gyro_z_mdps":0,"#,
+                r#""imu_temperature":30.12,"distance_mm":742}"#
+            )
+        );
+    }
+
+    #[test]
+    fn json_escapes_device_id() {
+        let frame = TelemetryFrame::new("a\"b\\c\n");
+        assert_eq!(encode_json(&frame), r#"{"device_id":"a\"b\\c\n"}"#);
+    }
+
+    #[test]
+    fn line_protocol_escapes_tags_and_appends_nanoseconds() {
+        let frame = TelemetryFrame::new("lab bench,1")
+            .with_unix_timestamp(1_700_000_000)
+            .with_distance(DistanceReading::new(100))
+            .with_light(LightReading::new(5));
+        let mut buffer = [0u8; 128];
+        let len = frame.encode_line_protocol("room", &mut buffer).unwrap();
+        assert_eq!(
+            &buffer[..len],
+            b"room,device_id=lab\\ bench\\,1 lux=0.05,distance_mm=100i 1700000000000000000"
+        );
+        assert_eq!(
+            frame.encode_line_protocol("", &mut buffer),
+            Err(TelemetryError::InvalidMeasurement)
+        );
+    }
+
+    #[test]
+    fn cbor_uses_decimal_fraction_and_epoch_tags() {
+        let frame = TelemetryFrame::new("d1")
+            .with_unix_timestamp(1_700_000_000)
+            .with_env(EnvReading::new(-525, 4320, None))
+            .with_gas(GasReading::new(612, 0));
+        let mut buffer = [0u8; 128];
+        let len = frame.encode_cbor(&mut buffer).unwrap();
+        let mut expected = std::vec::Vec::new();
+        expected.push(0xA6); // map(6)
+        expected.extend_from_slice(b"\x69device_id\x62d1");
+        expected.extend_from_slice(b"\x69timestamp\xC1\x1A\x65\x53\xF1\x00");
+        // 4([-2, -525])
+        expected.extend_from_slice(b"\x6Btemperature\xC4\x82\x21\x39\x02\x0C");
+        // 4([-2, 4320])
+        expected.extend_from_slice(b"\x68humidity\xC4\x82\x21\x19\x10\xE0");
+        expected.extend_from_slice(b"\x67co2_ppm\x19\x02\x64");
+        expected.extend_from_slice(b"\x67voc_ppb\x00");
+        assert_eq!(&buffer[..len], expected.as_slice());
+    }
+
+    #[test]
+    fn small_buffer_is_reported() {
+        let frame = full_frame();
+        let mut buffer = [0u8; 32];
+        for format in [
+            TelemetryFormat::Json,
+            TelemetryFormat::Cbor,
+            TelemetryFormat::LineProtocol,
+            TelemetryFormat::OpenMetrics,
+        ] {
+            assert_eq!(
+                frame.encode(format, "climate", &mut buffer),
+                Err(TelemetryError::BufferTooSmall)
+            );
+        }
+    }
+
+    #[test]
+    fn decimal_with_many_fraction_digits_does_not_overflow() {
+        let write = |value: i64, decimals: u8| {
+            let mut out = std::string::String::new();
+            write_decimal(&mut out, FieldValue::Fixed { value, decimals }).unwrap();
+            out
+        };
+        assert_eq!(write(-1234, 2), "-12.34");
+        assert_eq!(write(i64::MAX, 19), "0.9223372036854775807");
+        assert_eq!(write(-5, 20), "-0.00000000000000000005");
+        assert_eq!(write(0, 255).len(), 257);
+    }
+
+    #[test]
+    fn field_count_matches_emitted_fields() {
+        assert_eq!(full_frame().field_count(), 14);
+        assert_eq!(TelemetryFrame::new("x").field_count(), 0);
+    }
+}
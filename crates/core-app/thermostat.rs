@@ -0,0 +1,975 @@
+//! サーモスタット / リレー制御アプリ — `EnvSensor` の温度で加熱・冷却出力を ON/OFF する。
+//!
+//! - ヒステリシス: 設定温度 ± `hysteresis_centi_celsius / 2` の不感帯で ON/OFF を切り替える
+//! - 最小 ON / OFF 時間: 一度切り替えたら `min_on_ticks` / `min_off_ticks` は状態を保持する
+//! - 短周期運転防止: 起動から次の起動まで `min_cycle_ticks` 以上空ける
+//! - 日課スケジュール: [`SetpointSchedule`] と `RtcSensor` の時刻から設定温度を決める
+//! - 状態表示: 毎サンプルで `TextDisplay16x2` に現在温度・設定温度・出力状態を描く
+//!
+//! センサ読み取りに失敗した場合は最小 ON 時間を無視して出力を OFF にし (fail-safe)、
+//! エラーを返します。RTC の失敗は致命的とせず、直前の設定温度で制御を続けます。
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::thermostat::{RelayOutput, SetpointSchedule, ThermostatApp, ThermostatConfig};
+//! use hal_api::display::{TextDisplay16x2, TextFrame16x2};
+//! use hal_api::error::{DisplayError, GpioError, SensorError};
+//! use hal_api::gpio::OutputPin;
+//! use hal_api::rtc::{RtcDateTime, RtcSensor};
+//! use hal_api::sensor::{EnvReading, EnvSensor};
+//!
+//! struct Cold;
+//! impl EnvSensor for Cold {
+//!     type Error = SensorError;
+//!     fn read(&mut self) -> Result<EnvReading, SensorError> {
+//!         Ok(EnvReading::new(1800, 4000, None))
+//!     }
+//! }
+//! struct Clock;
+//! impl RtcSensor for Clock {
+//!     type Error = SensorError;
+//!     fn read_datetime(&mut self) -> Result<RtcDateTime, SensorError> {
+//!         Ok(RtcDateTime::new(25, 1, 1, 7, 30, 0))
+//!     }
+//!     fn set_datetime(&mut self, _dt: &RtcDateTime) -> Result<(), SensorError> { Ok(()) }
+//! }
+//! struct Relay(bool);
+//! impl OutputPin for Relay {
+//!     type Error = GpioError;
+//!     fn set_high(&mut self) -> Result<(), GpioError> { self.0 = true; Ok(()) }
+//!     fn set_low(&mut self) -> Result<(), GpioError> { self.0 = false; Ok(()) }
+//! }
+//! struct Lcd;
+//! impl TextDisplay16x2 for Lcd {
+//!     type Error = DisplayError;
+//!     fn render(&mut self, _frame: &TextFrame16x2) -> Result<(), DisplayError> { Ok(()) }
+//! }
+//!
+//! let mut app = ThermostatApp::new_with_config(
+//!     Cold,
+//!     Clock,
+//!     RelayOutput::new(Relay(false)),
+//!     Lcd,
+//!     ThermostatConfig { sample_period_ticks: 1, ..ThermostatConfig::default() },
+//!     SetpointSchedule::new(),
+//! );
+//! app.tick().unwrap();
+//! assert!(app.output_active());
+//! ```
+
+use core::fmt::Write as _;
+
+use hal_api::display::{TextDisplay16x2, TextFrame16x2};
+use hal_api::error::{DisplayError, SensorError};
+use hal_api::gpio::OutputPin;
+use hal_api::pwm::PwmOutput;
+use hal_api::rtc::{RtcDateTime, RtcSensor};
+use hal_api::sensor::{EnvReading, EnvSensor};
+use heapless::{String, Vec};
+
+#[cfg(test)]
+extern crate std;
+
+/// 出力が温度をどちらへ動かすか。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermostatMode {
+    /// 温度が低いときに ON (ヒーター)。
+    Heat,
+    /// 温度が高いときに ON (ファン / 冷房)。
+    Cool,
+}
+
+impl ThermostatMode {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Heat => "HEAT",
+            Self::Cool => "COOL",
+        }
+    }
+}
+
+/// `ThermostatApp` の設定。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThermostatConfig {
+    pub mode: ThermostatMode,
+    /// スケジュールが空、または時刻が取れないときの設定温度。
+    pub default_setpoint_centi_celsius: i32,
+    /// ヒステリシス幅 (設定温度を中心とした全幅)。
+    pub hysteresis_centi_celsius: u16,
+    /// センサを読んで制御判断を行う tick 間隔。
+    pub sample_period_ticks: u32,
+    /// ON にしてから OFF にできるまでの最小 tick 数。
+    pub min_on_ticks: u32,
+    /// OFF にしてから ON にできるまでの最小 tick 数。
+    pub min_off_ticks: u32,
+    /// 起動から次の起動までの最小 tick 数 (短周期運転防止)。
+    pub min_cycle_ticks: u32,
+}
+
+impl Default for ThermostatConfig {
+    fn default() -> Self {
+        // 10 ms tick 想定: 1 秒ごとに判断、最小 ON/OFF 1 分、起動間隔 5 分
+        Self {
+            mode: ThermostatMode::Heat,
+            default_setpoint_centi_celsius: 2000,
+            hysteresis_centi_celsius: 50,
+            sample_period_ticks: 100,
+            min_on_ticks: 6_000,
+            min_off_ticks: 6_000,
+            min_cycle_ticks: 30_000,
+        }
+    }
+}
+
+/// 日課スケジュールの 1 エントリ。`hour:minute` 以降は `setpoint_centi_celsius` を使う。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleEntry {
+    pub hour: u8,
+    pub minute: u8,
+    pub setpoint_centi_celsius: i32,
+}
+
+impl ScheduleEntry {
+    pub const fn new(hour: u8, minute: u8, setpoint_centi_celsius: i32) -> Self {
+        Self {
+            hour,
+            minute,
+            setpoint_centi_celsius,
+        }
+    }
+
+    fn minute_of_day(&self) -> u16 {
+        self.hour as u16 * 60 + self.minute as u16
+    }
+}
+
+/// [`SetpointSchedule`] に登録できるエントリ数。
+pub const SCHEDULE_CAPACITY: usize = 8;
+
+/// 1 日周期の設定温度スケジュール。エントリは時刻順に保持される。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetpointSchedule {
+    entries: Vec<ScheduleEntry, SCHEDULE_CAPACITY>,
+}
+
+impl SetpointSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// エントリを追加する。同じ時刻のエントリは置き換える。
+    ///
+    /// 時刻が範囲外、または容量超過の場合は渡したエントリを `Err` で返す。
+    pub fn insert(&mut self, entry: ScheduleEntry) -> Result<(), ScheduleEntry> {
+        if entry.hour > 23 || entry.minute > 59 {
+            return Err(entry);
+        }
+        let key = entry.minute_of_day();
+        match self
+            .entries
+            .iter()
+            .position(|existing| existing.minute_of_day() >= key)
+        {
+            Some(index) if self.entries[index].minute_of_day() == key => {
+                self.entries[index] = entry;
+                Ok(())
+            }
+            Some(index) => self.entries.insert(index, entry),
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// `now` 時点で有効な設定温度。最初のエントリより前は前日最後のエントリが続く。
+    pub fn setpoint_at(&self, now: &RtcDateTime) -> Option<i32> {
+        let minute = now.hour as u16 * 60 + now.minute as u16;
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.minute_of_day() <= minute)
+            .or_else(|| self.entries.last())
+            .map(|entry| entry.setpoint_centi_celsius)
+    }
+
+    pub fn entries(&self) -> &[ScheduleEntry] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// サーモスタットが駆動する出力の抽象。
+pub trait ThermostatOutput {
+    type Error;
+
+    fn set_active(&mut self, active: bool) -> Result<(), Self::Error>;
+}
+
+/// `OutputPin` をリレーとして使う出力。LOW アクティブのリレー基板は [`RelayOutput::active_low`] を使う。
+pub struct RelayOutput<P> {
+    pin: P,
+    active_high: bool,
+}
+
+impl<P: OutputPin> RelayOutput<P> {
+    pub fn new(pin: P) -> Self {
+        Self {
+            pin,
+            active_high: true,
+        }
+    }
+
+    pub fn active_low(pin: P) -> Self {
+        Self {
+            pin,
+            active_high: false,
+        }
+    }
+
+    pub fn into_inner(self) -> P {
+        self.pin
+    }
+}
+
+impl<P: OutputPin> ThermostatOutput for RelayOutput<P> {
+    type Error = P::Error;
+
+    fn set_active(&mut self, active: bool) -> Result<(), Self::Error> {
+        self.pin.set(active == self.active_high)
+    }
+}
+
+/// `PwmOutput` をヒーター / ファンとして使う出力。ON のときは `on_duty_percent` を出す。
+pub struct PwmDemandOutput<P> {
+    pwm: P,
+    on_duty_percent: u8,
+}
+
+impl<P: PwmOutput> PwmDemandOutput<P> {
+    pub fn new(pwm: P, on_duty_percent: u8) -> Self {
+        Self {
+            pwm,
+            on_duty_percent: on_duty_percent.min(100),
+        }
+    }
+
+    pub fn into_inner(self) -> P {
+        self.pwm
+    }
+}
+
+impl<P: PwmOutput> ThermostatOutput for PwmDemandOutput<P> {
+    type Error = P::Error;
+
+    fn set_active(&mut self, active: bool) -> Result<(), Self::Error> {
+        self.pwm
+            .set_duty_percent(if active { self.on_duty_percent } else { 0 })
+    }
+}
+
+/// サーモスタットの運転状態 (表示用)。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermostatStatus {
+    /// 要求なし、出力 OFF。
+    Off,
+    /// 要求あり、出力 ON。
+    On,
+    /// 要求はあるが最小 OFF 時間 / 起動間隔のため待機中。
+    Waiting,
+    /// 要求はないが最小 ON 時間のため出力を保持中。
+    Holding,
+    /// センサ異常のため出力を止めている。
+    Fault,
+}
+
+impl ThermostatStatus {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Off => "OFF",
+            Self::On => "ON",
+            Self::Waiting => "WAIT",
+            Self::Holding => "HOLD",
+            Self::Fault => "FAULT",
+        }
+    }
+}
+
+/// `ThermostatApp` が返すエラー型。
+#[derive(Debug, PartialEq, Eq)]
+pub enum ThermostatError<E> {
+    Sensor(SensorError),
+    Output(E),
+    Display(DisplayError),
+}
+
+/// ヒステリシス・最小 ON/OFF 時間・スケジュール付きのサーモスタット。
+pub struct ThermostatApp<SENSOR, RTC, OUT, DISPLAY> {
+    sensor: SENSOR,
+    rtc: RTC,
+    output: OUT,
+    display: DISPLAY,
+    config: ThermostatConfig,
+    schedule: SetpointSchedule,
+    tick_count: u32,
+    setpoint_centi_celsius: i32,
+    demand: bool,
+    output_active: bool,
+    output_initialized: bool,
+    last_switch_tick: Option<u32>,
+    last_start_tick: Option<u32>,
+    status: ThermostatStatus,
+    last_reading: Option<EnvReading>,
+    last_time: Option<RtcDateTime>,
+    last_frame: Option<TextFrame16x2>,
+}
+
+impl<SENSOR, RTC, OUT, DISPLAY> ThermostatApp<SENSOR, RTC, OUT, DISPLAY>
+where
+    SENSOR: EnvSensor<Error = SensorError>,
+    RTC: RtcSensor<Error = SensorError>,
+    OUT: ThermostatOutput,
+    DISPLAY: TextDisplay16x2<Error = DisplayError>,
+{
+    pub fn new(sensor: SENSOR, rtc: RTC, output: OUT, display: DISPLAY) -> Self {
+        Self::new_with_config(
+            sensor,
+            rtc,
+            output,
+            display,
+            ThermostatConfig::default(),
+            SetpointSchedule::new(),
+        )
+    }
+
+    pub fn new_with_config(
+        sensor: SENSOR,
+        rtc: RTC,
+        output: OUT,
+        display: DISPLAY,
+        config: ThermostatConfig,
+        schedule: SetpointSchedule,
+    ) -> Self {
+        Self {
+            sensor,
+            rtc,
+            output,
+            display,
+            config,
+            schedule,
+            tick_count: 0,
+            setpoint_centi_celsius: config.default_setpoint_centi_celsius,
+            demand: false,
+            output_active: false,
+            output_initialized: false,
+            last_switch_tick: None,
+            last_start_tick: None,
+            status: ThermostatStatus::Off,
+            last_reading: None,
+            last_time: None,
+            last_frame: None,
+        }
+    }
+
+    /// 1 tick 進める。`sample_period_ticks` ごとに読み取り・制御・表示を行う。
+    pub fn tick(&mut self) -> Result<(), ThermostatError<OUT::Error>> {
+        self.tick_count = self.tick_count.wrapping_add(1);
+        let period = self.config.sample_period_ticks.max(1);
+        if self.tick_count % period == 0 {
+            self.sample()?;
+        }
+        Ok(())
+    }
+
+    fn sample(&mut self) -> Result<(), ThermostatError<OUT::Error>> {
+        // RTC は失敗しても直前の設定温度で制御を続ける
+        if let Ok(now) = self.rtc.read_datetime() {
+            self.last_time = Some(now);
+            self.setpoint_centi_celsius = self
+                .schedule
+                .setpoint_at(&now)
+                .unwrap_or(self.config.default_setpoint_centi_celsius);
+        }
+
+        let reading = match self.sensor.read() {
+            Ok(reading) => reading,
+            Err(error) => {
+                self.demand = false;
+                self.status = ThermostatStatus::Fault;
+                if self.output_active || !self.output_initialized {
+                    self.switch_output(false)?;
+                }
+                self.render()?;
+                return Err(ThermostatError::Sensor(error));
+            }
+        };
+        self.last_reading = Some(reading);
+        self.demand = self.evaluate_demand(reading.temperature_centi_celsius);
+
+        if self.demand != self.output_active {
+            if self.switch_allowed(self.demand) {
+                self.switch_output(self.demand)?;
+            }
+        } else if !self.output_initialized {
+            self.switch_output(self.output_active)?;
+        }
+
+        self.status = match (self.demand, self.output_active) {
+            (true, true) => ThermostatStatus::On,
+            (false, false) => ThermostatStatus::Off,
+            (true, false) => ThermostatStatus::Waiting,
+            (false, true) => ThermostatStatus::Holding,
+        };
+        self.render()
+    }
+
+    fn evaluate_demand(&self, temperature: i32) -> bool {
+        let half_band = (self.config.hysteresis_centi_celsius / 2) as i32;
+        let low = self.setpoint_centi_celsius - half_band;
+        let high = self.setpoint_centi_celsius + half_band;
+        let (turn_on, turn_off) = match self.config.mode {
+            ThermostatMode::Heat => (temperature <= low, temperature >= high),
+            ThermostatMode::Cool => (temperature >= high, temperature <= low),
+        };
+        if turn_on {
+            true
+        } else if turn_off {
+            false
+        } else {
+            self.demand
+        }
+    }
+
+    fn switch_allowed(&self, activate: bool) -> bool {
+        let since_switch = self.ticks_since(self.last_switch_tick);
+        if activate {
+            since_switch >= self.config.min_off_ticks
+                && self.ticks_since(self.last_start_tick) >= self.config.min_cycle_ticks
+        } else {
+            since_switch >= self.config.min_on_ticks
+        }
+    }
+
+    fn ticks_since(&self, tick: Option<u32>) -> u32 {
+        match tick {
+            Some(tick) => self.tick_count.wrapping_sub(tick),
+            None => u32::MAX,
+        }
+    }
+
+    fn switch_output(&mut self, active: bool) -> Result<(), ThermostatError<OUT::Error>> {
+        self.output
+            .set_active(active)
+            .map_err(ThermostatError::Output)?;
+        if active != self.output_active {
+            self.last_switch_tick = Some(self.tick_count);
+            if active {
+                self.last_start_tick = Some(self.tick_count);
+            }
+        }
+        self.output_active = active;
+        self.output_initialized = true;
+        Ok(())
+    }
+
+    fn render(&mut self) -> Result<(), ThermostatError<OUT::Error>> {
+        let frame = status_frame(
+            self.last_reading.map(|r| r.temperature_centi_celsius),
+            self.setpoint_centi_celsius,
+            self.config.mode,
+            self.status,
+            self.last_time,
+        )
+        .map_err(ThermostatError::Display)?;
+        self.display
+            .render(&frame)
+            .map_err(ThermostatError::Display)?;
+        self.last_frame = Some(frame);
+        Ok(())
+    }
+
+    /// 出力が現在 ON か。
+    pub fn output_active(&self) -> bool {
+        self.output_active
+    }
+
+    /// ヒステリシス判定上の要求 (最小時間制約で出力と一致しないことがある)。
+    pub fn demand(&self) -> bool {
+        self.demand
+    }
+
+    pub fn status(&self) -> ThermostatStatus {
+        self.status
+    }
+
+    /// 現在有効な設定温度。
+    pub fn setpoint_centi_celsius(&self) -> i32 {
+        self.setpoint_centi_celsius
+    }
+
+    pub fn last_reading(&self) -> Option<EnvReading> {
+        self.last_reading
+    }
+
+    pub fn last_frame(&self) -> Option<TextFrame16x2> {
+        self.last_frame
+    }
+
+    pub fn schedule(&self) -> &SetpointSchedule {
+        &self.schedule
+    }
+
+    pub fn schedule_mut(&mut self) -> &mut SetpointSchedule {
+        &mut self.schedule
+    }
+
+    pub fn config(&self) -> ThermostatConfig {
+        self.config
+    }
+
+    pub fn tick_count(&self) -> u32 {
+        self.tick_count
+    }
+}
+
+/// サーモスタットの状態表示フレームを作る。
+///
+/// ```text
+/// T 21.4C SP22.0C
+/// HEAT ON    07:30
+/// ```
+pub fn status_frame(
+    temperature_centi_celsius: Option<i32>,
+    setpoint_centi_celsius: i32,
+    mode: ThermostatMode,
+    status: ThermostatStatus,
+    time: Option<RtcDateTime>,
+) -> Result<TextFrame16x2, DisplayError> {
+    let mut line1: String<17> = String::new();
+    let mut line2: String<17> = String::new();
+
+    write_setpoint_line(
+        &mut line1,
+        temperature_centi_celsius,
+        setpoint_centi_celsius,
+    )
+    .map_err(|_| DisplayError::InvalidContent)?;
+    write_status_line(&mut line2, mode, status, time).map_err(|_| DisplayError::InvalidContent)?;
+
+    Ok(TextFrame16x2::from_lines(&line1, &line2))
+}
+
+fn write_setpoint_line(
+    line: &mut String<17>,
+    temperature_centi_celsius: Option<i32>,
+    setpoint_centi_celsius: i32,
+) -> core::fmt::Result {
+    write!(line, "T")?;
+    match temperature_centi_celsius {
+        Some(temperature) => write_tenths(line, temperature)?,
+        None => write!(line, " --.-")?,
+    }
+    write!(line, "C SP")?;
+    write_tenths(line, setpoint_centi_celsius)?;
+    write!(line, "C")
+}
+
+fn write_status_line(
+    line: &mut String<17>,
+    mode: ThermostatMode,
+    status: ThermostatStatus,
+    time: Option<RtcDateTime>,
+) -> core::fmt::Result {
+    write!(line, "{} {:<5} ", mode.label(), status.label())?;
+    match time {
+        Some(time) => write!(line, "{:02}:{:02}", time.hour, time.minute),
+        None => write!(line, "--:--"),
+    }
+}
+
+/// 1/100 ℃ を小数 1 桁 (四捨五入) で幅 5 に右寄せして書く。
+fn write_tenths(line: &mut String<17>, centi: i32) -> core::fmt::Result {
+    let tenths = if centi >= 0 {
+        (centi + 5) / 10
+    } else {
+        (centi - 5) / 10
+    };
+    let sign = if tenths < 0 { "-" } else { "" };
+    let magnitude = tenths.unsigned_abs();
+    let mut number: String<8> = String::new();
+    write!(number, "{}{}.{}", sign, magnitude / 10, magnitude % 10)?;
+    write!(line, "{:>5}", number.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec::Vec as StdVec;
+
+    #[derive(Clone, Default)]
+    struct MockSensor {
+        temperature: Rc<RefCell<Option<i32>>>,
+    }
+
+    impl MockSensor {
+        fn set(&self, temperature: Option<i32>) {
+            *self.temperature.borrow_mut() = temperature;
+        }
+    }
+
+    impl EnvSensor for MockSensor {
+        type Error = SensorError;
+
+        fn read(&mut self) -> Result<EnvReading, SensorError> {
+            self.temperature
+                .borrow()
+                .map(|t| EnvReading::new(t, 4000, None))
+                .ok_or(SensorError::BusError)
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockRtc {
+        now: Rc<RefCell<Option<RtcDateTime>>>,
+    }
+
+    impl MockRtc {
+        fn at(hour: u8, minute: u8) -> Self {
+            Self {
+                now: Rc::new(RefCell::new(Some(RtcDateTime::new(
+                    25, 1, 1, hour, minute, 0,
+                )))),
+            }
+        }
+
+        fn set(&self, hour: u8, minute: u8) {
+            *self.now.borrow_mut() = Some(RtcDateTime::new(25, 1, 1, hour, minute, 0));
+        }
+    }
+
+    impl RtcSensor for MockRtc {
+        type Error = SensorError;
+
+        fn read_datetime(&mut self) -> Result<RtcDateTime, SensorError> {
+            self.now.borrow().ok_or(SensorError::NotInitialized)
+        }
+
+        fn set_datetime(&mut self, dt: &RtcDateTime) -> Result<(), SensorError> {
+            *self.now.borrow_mut() = Some(*dt);
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct MockOutput {
+        history: Rc<RefCell<StdVec<bool>>>,
+    }
+
+    impl ThermostatOutput for MockOutput {
+        type Error = ();
+
+        fn set_active(&mut self, active: bool) -> Result<(), ()> {
+            self.history.borrow_mut().push(active);
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct MockDisplay {
+        frames: Rc<RefCell<StdVec<TextFrame16x2>>>,
+    }
+
+    impl TextDisplay16x2 for MockDisplay {
+        type Error = DisplayError;
+
+        fn render(&mut self, frame: &TextFrame16x2) -> Result<(), DisplayError> {
+            self.frames.borrow_mut().push(*frame);
+            Ok(())
+        }
+    }
+
+    type TestApp = ThermostatApp<MockSensor, MockRtc, MockOutput, MockDisplay>;
+
+    fn fast_config() -> ThermostatConfig {
+        ThermostatConfig {
+            mode: ThermostatMode::Heat,
+            default_setpoint_centi_celsius: 2000,
+            hysteresis_centi_celsius: 100,
+            sample_period_ticks: 1,
+            min_on_ticks: 0,
+            min_off_ticks: 0,
+            min_cycle_ticks: 0,
+        }
+    }
+
+    fn app_with(config: ThermostatConfig) -> (TestApp, MockSensor, MockRtc, MockOutput) {
+        let sensor = MockSensor::default();
+        let rtc = MockRtc::at(12, 0);
+        let output = MockOutput::default();
+        let app = ThermostatApp::new_with_config(
+            sensor.clone(),
+            rtc.clone(),
+            output.clone(),
+            MockDisplay::default(),
+            config,
+            SetpointSchedule::new(),
+        );
+        (app, sensor, rtc, output)
+    }
+
+    #[test]
+    fn hysteresis_band_keeps_previous_state() {
+        let (mut app, sensor, _, _) = app_with(fast_config());
+        sensor.set(Some(1960));
+        app.tick().unwrap();
+        assert!(
+            !app.output_active(),
+            "inside band from cold start stays off"
+        );
+
+        sensor.set(Some(1950));
+        app.tick().unwrap();
+        assert!(app.output_active());
+
+        sensor.set(Some(2040));
+        app.tick().unwrap();
+        assert!(app.output_active(), "still inside band");
+
+        sensor.set(Some(2050));
+        app.tick().unwrap();
+        assert!(!app.output_active());
+    }
+
+    #[test]
+    fn cool_mode_inverts_demand() {
+        let (mut app, sensor, _, _) = app_with(ThermostatConfig {
+            mode: ThermostatMode::Cool,
+            ..fast_config()
+        });
+        sensor.set(Some(2100));
+        app.tick().unwrap();
+        assert!(app.output_active());
+        sensor.set(Some(1900));
+        app.tick().unwrap();
+        assert!(!app.output_active());
+    }
+
+    #[test]
+    fn min_on_time_holds_output() {
+        let (mut app, sensor, _, _) = app_with(ThermostatConfig {
+            min_on_ticks: 5,
+            ..fast_config()
+        });
+        sensor.set(Some(1800));
+        app.tick().unwrap();
+        assert!(app.output_active());
+
+        sensor.set(Some(2200));
+        for _ in 0..4 {
+            app.tick().unwrap();
+            assert!(app.output_active());
+            assert_eq!(app.status(), ThermostatStatus::Holding);
+        }
+        app.tick().unwrap();
+        assert!(!app.output_active());
+        assert_eq!(app.status(), ThermostatStatus::Off);
+    }
+
+    #[test]
+    fn min_off_time_delays_restart() {
+        let (mut app, sensor, _, _) = app_with(ThermostatConfig {
+            min_off_ticks: 3,
+            ..fast_config()
+        });
+        sensor.set(Some(1800));
+        app.tick().unwrap();
+        sensor.set(Some(2200));
+        app.tick().unwrap();
+        assert!(!app.output_active());
+
+        sensor.set(Some(1800));
+        for _ in 0..2 {
+            app.tick().unwrap();
+            assert_eq!(app.status(), ThermostatStatus::Waiting);
+        }
+        app.tick().unwrap();
+        assert!(app.output_active());
+    }
+
+    #[test]
+    fn anti_short_cycle_spaces_starts() {
+        let (mut app, sensor, _, output) = app_with(ThermostatConfig {
+            min_cycle_ticks: 10,
+            ..fast_config()
+        });
+        // tick 1 で起動
+        sensor.set(Some(1800));
+        app.tick().unwrap();
+        sensor.set(Some(2200));
+        app.tick().unwrap();
+        sensor.set(Some(1800));
+        // tick 11 までは再起動できない
+        for _ in 3..11 {
+            app.tick().unwrap();
+            assert!(!app.output_active(), "tick {}", app.tick_count());
+        }
+        app.tick().unwrap();
+        assert!(app.output_active());
+        assert_eq!(output.history.borrow().as_slice(), &[true, false, true]);
+    }
+
+    #[test]
+    fn sensor_fault_forces_output_off() {
+        let (mut app, sensor, _, _) = app_with(ThermostatConfig {
+            min_on_ticks: 100,
+            ..fast_config()
+        });
+        sensor.set(Some(1800));
+        app.tick().unwrap();
+        assert!(app.output_active());
+
+        sensor.set(None);
+        assert_eq!(
+            app.tick(),
+            Err(ThermostatError::Sensor(SensorError::BusError))
+        );
+        assert!(!app.output_active());
+        assert_eq!(app.status(), ThermostatStatus::Fault);
+    }
+
+    #[test]
+    fn schedule_changes_setpoint_with_time() {
+        let (mut app, sensor, rtc, _) = app_with(fast_config());
+        app.schedule_mut()
+            .insert(ScheduleEntry::new(7, 0, 2100))
+            .unwrap();
+        app.schedule_mut()
+            .insert(ScheduleEntry::new(22, 30, 1700))
+            .unwrap();
+        sensor.set(Some(1900));
+
+        rtc.set(6, 59);
+        app.tick().unwrap();
+        assert_eq!(app.setpoint_centi_celsius(), 1700);
+        assert!(!app.output_active());
+
+        rtc.set(7, 0);
+        app.tick().unwrap();
+        assert_eq!(app.setpoint_centi_celsius(), 2100);
+        assert!(app.output_active());
+    }
+
+    #[test]
+    fn rtc_failure_keeps_last_setpoint() {
+        let (mut app, sensor, rtc, _) = app_with(fast_config());
+        app.schedule_mut()
+            .insert(ScheduleEntry::new(6, 0, 2300))
+            .unwrap();
+        sensor.set(Some(2000));
+        app.tick().unwrap();
+        assert_eq!(app.setpoint_centi_celsius(), 2300);
+
+        *rtc.now.borrow_mut() = None;
+        app.tick().unwrap();
+        assert_eq!(app.setpoint_centi_celsius(), 2300);
+    }
+
+    #[test]
+    fn schedule_insert_sorts_and_replaces() {
+        let mut schedule = SetpointSchedule::new();
+        schedule.insert(ScheduleEntry::new(18, 0, 2100)).unwrap();
+        schedule.insert(ScheduleEntry::new(6, 0, 2000)).unwrap();
+        schedule.insert(ScheduleEntry::new(18, 0, 2200)).unwrap();
+        assert_eq!(
+            schedule.entries(),
+            &[
+                ScheduleEntry::new(6, 0, 2000),
+                ScheduleEntry::new(18, 0, 2200)
+            ]
+        );
+        assert!(schedule.insert(ScheduleEntry::new(24, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn schedule_rejects_entries_beyond_capacity() {
+        let mut schedule = SetpointSchedule::new();
+        for hour in 0..SCHEDULE_CAPACITY as u8 {
+            schedule.insert(ScheduleEntry::new(hour, 0, 2000)).unwrap();
+        }
+        let extra = ScheduleEntry::new(23, 0, 2000);
+        assert_eq!(schedule.insert(extra), Err(extra));
+    }
+
+    #[test]
+    fn status_frame_layout() {
+        let frame = status_frame(
+            Some(2137),
+            2200,
+            ThermostatMode::Heat,
+            ThermostatStatus::On,
+            Some(RtcDateTime::new(25, 1, 1, 7, 30, 0)),
+        )
+        .unwrap();
+        assert_eq!(frame.line(0), b"T 21.4C SP 22.0C");
+        assert_eq!(frame.line(1), b"HEAT ON    07:30");
+
+        let frame = status_frame(
+            None,
+            -550,
+            ThermostatMode::Cool,
+            ThermostatStatus::Fault,
+            None,
+        )
+        .unwrap();
+        assert_eq!(frame.line(0), b"T --.-C SP -5.5C");
+        assert_eq!(frame.line(1), b"COOL FAULT --:--");
+    }
+
+    #[test]
+    fn display_updates_every_sample() {
+        let sensor = MockSensor::default();
+        sensor.set(Some(2000));
+        let display = MockDisplay::default();
+        let mut app = ThermostatApp::new_with_config(
+            sensor,
+            MockRtc::at(8, 5),
+            MockOutput::default(),
+            display.clone(),
+            ThermostatConfig {
+                sample_period_ticks: 2,
+                ..fast_config()
+            },
+            SetpointSchedule::new(),
+        );
+        for _ in 0..4 {
+            app.tick().unwrap();
+        }
+        assert_eq!(display.frames.borrow().len(), 2);
+        assert_eq!(app.last_frame().unwrap().line(1), b"HEAT OFF   08:05");
+    }
+
+    #[test]
+    fn relay_output_supports_active_low() {
+        #[derive(Default)]
+        struct Pin(StdVec<bool>);
+        impl OutputPin for Pin {
+            type Error = ();
+            fn set_high(&mut self) -> Result<(), ()> {
+                self.0.push(true);
+                Ok(())
+            }
+            fn set_low(&mut self) -> Result<(), ()> {
+                self.0.push(false);
+                Ok(())
+            }
+        }
+
+        let mut relay = RelayOutput::active_low(Pin::default());
+        relay.set_active(true).unwrap();
+        relay.set_active(false).unwrap();
+        assert_eq!(relay.into_inner().0, std::vec![false, true]);
+    }
+}
@@ -0,0 +1,165 @@
+//! Wheel encoder and speed feedback abstractions.
+
+/// 回転速度の読み取り結果。
+///
+/// `rpm` は符号付きで、正が `MotorDirection::Forward` 方向、負が `Reverse` 方向。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeedReading {
+    pub rpm: i32,
+}
+
+impl SpeedReading {
+    pub const fn new(rpm: i32) -> Self {
+        Self { rpm }
+    }
+
+    /// エンコーダカウントの差分から回転速度を求める。
+    ///
+    /// `counts_per_revolution` または `interval_ms` が 0 の場合は 0 rpm を返す。
+    ///
+    /// ```
+    /// use hal_api::encoder::SpeedReading;
+    ///
+    /// // 20 カウント/回転のエンコーダで 100 ms に 10 カウント → 300 rpm
+    /// assert_eq!(SpeedReading::from_count_delta(10, 20, 100).rpm, 300);
+    /// assert_eq!(SpeedReading::from_count_delta(-10, 20, 100).rpm, -300);
+    /// ```
+    pub fn from_count_delta(
+        delta_counts: i32,
+        counts_per_revolution: u32,
+        interval_ms: u32,
+    ) -> Self {
+        if counts_per_revolution == 0 || interval_ms == 0 {
+            return Self::new(0);
+        }
+        let numerator = delta_counts as i64 * 60_000;
+        let denominator = counts_per_revolution as i64 * interval_ms as i64;
+        let rpm = (numerator / denominator).clamp(i32::MIN as i64, i32::MAX as i64);
+        Self::new(rpm as i32)
+    }
+}
+
+/// ホイールエンコーダのようなパルスカウンタの抽象。
+///
+/// `read_count` は起動時からの累積カウントを返す。逆転時は減算される。
+/// カウンタの桁あふれは `wrapping_sub` で差分を取る前提とする。
+pub trait WheelEncoder {
+    type Error;
+
+    fn read_count(&mut self) -> Result<i32, Self::Error>;
+}
+
+/// 回転速度を直接返すフィードバック源の抽象。
+///
+/// 閉ループ速度制御はこの trait だけに依存する。
+pub trait SpeedFeedback {
+    type Error;
+
+    fn read_speed(&mut self) -> Result<SpeedReading, Self::Error>;
+}
+
+/// [`WheelEncoder`] を一定周期でサンプリングして [`SpeedFeedback`] に変換するアダプタ。
+///
+/// `read_speed` は前回呼び出しからのカウント差分を `sample_interval_ms` で割って rpm を求める。
+/// 呼び出し側が `sample_interval_ms` 周期で `read_speed` を呼ぶことを前提とする。
+pub struct EncoderSpeedSensor<E> {
+    encoder: E,
+    counts_per_revolution: u32,
+    sample_interval_ms: u32,
+    last_count: Option<i32>,
+}
+
+impl<E> EncoderSpeedSensor<E>
+where
+    E: WheelEncoder,
+{
+    pub fn new(encoder: E, counts_per_revolution: u32, sample_interval_ms: u32) -> Self {
+        Self {
+            encoder,
+            counts_per_revolution,
+            sample_interval_ms,
+            last_count: None,
+        }
+    }
+
+    pub fn into_inner(self) -> E {
+        self.encoder
+    }
+}
+
+impl<E> SpeedFeedback for EncoderSpeedSensor<E>
+where
+    E: WheelEncoder,
+{
+    type Error = E::Error;
+
+    fn read_speed(&mut self) -> Result<SpeedReading, Self::Error> {
+        let count = self.encoder.read_count()?;
+        // 初回は基準カウントを取るだけで 0 rpm を返す
+        let delta = match self.last_count {
+            Some(last) => count.wrapping_sub(last),
+            None => 0,
+        };
+        self.last_count = Some(count);
+        Ok(SpeedReading::from_count_delta(
+            delta,
+            self.counts_per_revolution,
+            self.sample_interval_ms,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountSequence {
+        counts: [i32; 3],
+        index: usize,
+    }
+
+    impl WheelEncoder for CountSequence {
+        type Error = ();
+
+        fn read_count(&mut self) -> Result<i32, ()> {
+            let count = self.counts[self.index.min(self.counts.len() - 1)];
+            self.index += 1;
+            Ok(count)
+        }
+    }
+
+    #[test]
+    fn from_count_delta_handles_zero_divisors() {
+        assert_eq!(SpeedReading::from_count_delta(10, 0, 100).rpm, 0);
+        assert_eq!(SpeedReading::from_count_delta(10, 20, 0).rpm, 0);
+    }
+
+    #[test]
+    fn encoder_speed_sensor_first_read_is_zero() {
+        let mut sensor = EncoderSpeedSensor::new(
+            CountSequence {
+                counts: [100, 110, 100],
+                index: 0,
+            },
+            20,
+            100,
+        );
+        assert_eq!(sensor.read_speed(), Ok(SpeedReading::new(0)));
+        assert_eq!(sensor.read_speed(), Ok(SpeedReading::new(300)));
+        assert_eq!(sensor.read_speed(), Ok(SpeedReading::new(-300)));
+    }
+
+    #[test]
+    fn encoder_speed_sensor_survives_counter_wrap() {
+        let mut sensor = EncoderSpeedSensor::new(
+            CountSequence {
+                counts: [i32::MAX - 4, i32::MIN + 5, i32::MIN + 5],
+                index: 0,
+            },
+            20,
+            100,
+        );
+        sensor.read_speed().unwrap();
+        assert_eq!(sensor.read_speed(), Ok(SpeedReading::new(300)));
+    }
+}
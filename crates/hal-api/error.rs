@@ -121,6 +121,49 @@ pub enum ActuatorError {
     HardwareError,
 }
 
+/// ブロックストレージ (フラッシュ / EEPROM) に関連するエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageError {
+    /// 範囲外のアドレスまたはブロック番号
+    OutOfBounds,
+    /// 書き込みに失敗した (途中で電源が落ちた場合を含む)
+    WriteFailed,
+    /// 消去に失敗した
+    EraseFailed,
+    /// 読み出しに失敗した
+    ReadFailed,
+}
+
+/// キーバリューストア (NVS) に関連するエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KvError {
+    /// キーが空、または最大長を超えている
+    InvalidKey,
+    /// 値が最大長を超えている
+    ValueTooLarge,
+    /// 読み出し先バッファが値より小さい
+    BufferTooSmall,
+    /// キー数または保存領域の上限に達した
+    Full,
+    /// 下位のストレージで読み書きに失敗した
+    Storage,
+}
+
+/// ネットワークソケットに関連するエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetError {
+    /// 接続していない状態で送受信しようとした
+    NotConnected,
+    /// 接続を拒否された、または名前解決に失敗した
+    ConnectionRefused,
+    /// 相手が切断した、または接続がリセットされた
+    ConnectionClosed,
+    /// 接続や送受信がタイムアウトした
+    Timeout,
+    /// 下位のネットワークスタックのエラー
+    Io,
+}
+
 impl From<GpioError> for ActuatorError {
     fn from(_: GpioError) -> Self {
         ActuatorError::HardwareError
@@ -196,6 +239,53 @@ impl std::fmt::Display for ActuatorError {
 #[cfg(feature = "std")]
 impl std::error::Error for ActuatorError {}
 
+#[cfg(feature = "std")]
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::OutOfBounds => write!(f, "storage address out of bounds"),
+            StorageError::WriteFailed => write!(f, "storage write failed"),
+            StorageError::EraseFailed => write!(f, "storage erase failed"),
+            StorageError::ReadFailed => write!(f, "storage read failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StorageError {}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for KvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KvError::InvalidKey => write!(f, "invalid key-value key"),
+            KvError::ValueTooLarge => write!(f, "key-value value too large"),
+            KvError::BufferTooSmall => write!(f, "key-value buffer too small"),
+            KvError::Full => write!(f, "key-value store full"),
+            KvError::Storage => write!(f, "key-value storage failure"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KvError {}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for NetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetError::NotConnected => write!(f, "socket not connected"),
+            NetError::ConnectionRefused => write!(f, "connection refused"),
+            NetError::ConnectionClosed => write!(f, "connection closed"),
+            NetError::Timeout => write!(f, "network timeout"),
+            NetError::Io => write!(f, "network I/O error"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NetError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -279,6 +369,35 @@ mod tests {
         assert_error::<SensorError>();
         assert_error::<DisplayError>();
         assert_error::<ActuatorError>();
+        assert_error::<StorageError>();
+        assert_error::<KvError>();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn storage_error_display() {
+        assert_eq!(
+            StorageError::OutOfBounds.to_string(),
+            "storage address out of bounds"
+        );
+        assert_eq!(
+            StorageError::WriteFailed.to_string(),
+            "storage write failed"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn kv_error_display() {
+        assert_eq!(KvError::InvalidKey.to_string(), "invalid key-value key");
+        assert_eq!(KvError::Full.to_string(), "key-value store full");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn net_error_display() {
+        assert_eq!(NetError::NotConnected.to_string(), "socket not connected");
+        assert_eq!(NetError::ConnectionClosed.to_string(), "connection closed");
     }
 
     #[test]
@@ -0,0 +1,87 @@
+//! Key-value (NVS) storage abstractions.
+
+/// キーの最大バイト数 (ESP-IDF NVS と同じ 15 バイト)。
+pub const KV_MAX_KEY_LEN: usize = 15;
+
+/// キーが空でなく、[`KV_MAX_KEY_LEN`] バイト以内の ASCII 印字可能文字だけでできているか。
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= KV_MAX_KEY_LEN
+        && key.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+/// WiFi 認証情報やデバイス設定のような小さな値を、キー付きで永続化するストアの抽象。
+///
+/// - キーは [`is_valid_key`] を満たす文字列
+/// - 値は任意のバイト列。数値は [`KeyValueStore::get_i32`] などのヘルパで扱う
+/// - `set` / `remove` が `Ok` を返した時点で永続化されていること
+///
+/// # Examples
+///
+/// ```
+/// use hal_api::kv::KeyValueStore;
+///
+/// struct OneSlot(Option<([u8; 4], usize)>);
+///
+/// impl KeyValueStore for OneSlot {
+///     type Error = ();
+///     fn get(&mut self, _key: &str, buffer: &mut [u8]) -> Result<Option<usize>, ()> {
+///         Ok(self.0.map(|(value, len)| {
+///             buffer[..len].copy_from_slice(&value[..len]);
+///             len
+///         }))
+///     }
+///     fn set(&mut self, _key: &str, value: &[u8]) -> Result<(), ()> {
+///         let mut slot = [0u8; 4];
+///         slot[..value.len()].copy_from_slice(value);
+///         self.0 = Some((slot, value.len()));
+///         Ok(())
+///     }
+///     fn remove(&mut self, _key: &str) -> Result<bool, ()> {
+///         Ok(self.0.take().is_some())
+///     }
+/// }
+///
+/// let mut store = OneSlot(None);
+/// store.set_i32("interval", 30).unwrap();
+/// assert_eq!(store.get_i32("interval").unwrap(), Some(30));
+/// assert!(store.remove("interval").unwrap());
+/// ```
+pub trait KeyValueStore {
+    type Error;
+
+    /// 値を `buffer` へ読み出してバイト数を返す。キーが無ければ `Ok(None)`。
+    fn get(&mut self, key: &str, buffer: &mut [u8]) -> Result<Option<usize>, Self::Error>;
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Self::Error>;
+
+    /// キーを削除する。存在しなかった場合は `Ok(false)`。
+    fn remove(&mut self, key: &str) -> Result<bool, Self::Error>;
+
+    /// 4 バイトのリトルエンディアン値として読み出す。長さが違う値は `None`。
+    fn get_i32(&mut self, key: &str) -> Result<Option<i32>, Self::Error> {
+        let mut bytes = [0u8; 4];
+        Ok(match self.get(key, &mut bytes)? {
+            Some(4) => Some(i32::from_le_bytes(bytes)),
+            _ => None,
+        })
+    }
+
+    fn set_i32(&mut self, key: &str, value: i32) -> Result<(), Self::Error> {
+        self.set(key, &value.to_le_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_validation_follows_nvs_limits() {
+        assert!(is_valid_key("wifi_ssid"));
+        assert!(is_valid_key("abcdefghijklmno"));
+        assert!(!is_valid_key(""));
+        assert!(!is_valid_key("abcdefghijklmnop"));
+        assert!(!is_valid_key("has space"));
+    }
+}
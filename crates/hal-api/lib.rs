@@ -37,13 +37,17 @@ pub mod adapter;
 pub mod camera;
 pub mod display;
 pub mod distance;
+pub mod encoder;
 pub mod error;
 pub mod gas;
 pub mod gpio;
 pub mod i2c;
 pub mod imu;
+pub mod kv;
 pub mod light;
+pub mod net;
 pub mod pwm;
 pub mod rtc;
 pub mod sensor;
 pub mod shared_i2c;
+pub mod storage;
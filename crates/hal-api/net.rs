@@ -0,0 +1,75 @@
+//! Network socket abstractions.
+
+/// ノンブロッキングな TCP クライアントソケットの抽象。
+///
+/// smoltcp (ESP32 / RP2040) のようにポーリングで進むスタックと、host の
+/// `std::net::TcpStream` の両方に載せられるよう、どの操作も待たずに戻ること。
+///
+/// - [`TcpClientSocket::connect`] は接続を開始するだけでよい。確立は
+///   [`TcpClientSocket::is_connected`] で確認する
+/// - [`TcpClientSocket::send`] は送信バッファに積めたバイト数を返す (0 もあり得る)
+/// - [`TcpClientSocket::receive`] は受信済みデータが無ければ `Ok(0)` を返す。
+///   相手からの切断やリセットは `Err` で知らせる
+///
+/// # Examples
+///
+/// ```
+/// use hal_api::net::TcpClientSocket;
+///
+/// /// 送ったものをそのまま返すループバック
+/// struct Echo {
+///     connected: bool,
+///     buffer: [u8; 16],
+///     len: usize,
+/// }
+///
+/// impl TcpClientSocket for Echo {
+///     type Error = ();
+///     fn connect(&mut self, _host: &str, _port: u16) -> Result<(), ()> {
+///         self.connected = true;
+///         Ok(())
+///     }
+///     fn is_connected(&self) -> bool {
+///         self.connected
+///     }
+///     fn send(&mut self, data: &[u8]) -> Result<usize, ()> {
+///         let n = data.len().min(self.buffer.len() - self.len);
+///         self.buffer[self.len..self.len + n].copy_from_slice(&data[..n]);
+///         self.len += n;
+///         Ok(n)
+///     }
+///     fn receive(&mut self, buffer: &mut [u8]) -> Result<usize, ()> {
+///         let n = self.len.min(buffer.len());
+///         buffer[..n].copy_from_slice(&self.buffer[..n]);
+///         self.buffer.copy_within(n..self.len, 0);
+///         self.len -= n;
+///         Ok(n)
+///     }
+///     fn close(&mut self) {
+///         self.connected = false;
+///     }
+/// }
+///
+/// let mut socket = Echo { connected: false, buffer: [0; 16], len: 0 };
+/// socket.connect("broker.local", 1883).unwrap();
+/// assert_eq!(socket.send(b"ping").unwrap(), 4);
+/// let mut reply = [0u8; 8];
+/// assert_eq!(socket.receive(&mut reply).unwrap(), 4);
+/// assert_eq!(&reply[..4], b"ping");
+/// ```
+pub trait TcpClientSocket {
+    type Error;
+
+    /// `host` (IPv4 表記またはホスト名) の `port` へ接続を開始する。
+    fn connect(&mut self, host: &str, port: u16) -> Result<(), Self::Error>;
+
+    /// 接続が確立していて送受信できる状態か。
+    fn is_connected(&self) -> bool;
+
+    fn send(&mut self, data: &[u8]) -> Result<usize, Self::Error>;
+
+    fn receive(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+
+    /// 接続を閉じる。既に閉じていれば何もしない。
+    fn close(&mut self);
+}
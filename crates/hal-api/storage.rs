@@ -0,0 +1,67 @@
+//! Block storage (flash / EEPROM) abstractions.
+
+/// フラッシュや EEPROM のようなブロック単位で消去するストレージの抽象。
+///
+/// - アドレスはストレージ先頭からのバイトオフセット
+/// - 消去後のバイトは `0xFF`
+/// - NOR フラッシュと同様に、書き込みはビットを 1 → 0 にしか変えられない前提で扱う。
+///   上書きする前に該当ブロックを `erase_block` すること
+///
+/// # Examples
+///
+/// ```
+/// use hal_api::storage::BlockStorage;
+///
+/// struct RamStorage([u8; 64]);
+///
+/// impl BlockStorage for RamStorage {
+///     type Error = ();
+///     fn block_size(&self) -> u32 { 32 }
+///     fn block_count(&self) -> u32 { 2 }
+///     fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), ()> {
+///         let start = offset as usize;
+///         buffer.copy_from_slice(&self.0[start..start + buffer.len()]);
+///         Ok(())
+///     }
+///     fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), ()> {
+///         let start = offset as usize;
+///         for (cell, byte) in self.0[start..start + data.len()].iter_mut().zip(data) {
+///             *cell &= *byte;
+///         }
+///         Ok(())
+///     }
+///     fn erase_block(&mut self, block: u32) -> Result<(), ()> {
+///         let start = (block * 32) as usize;
+///         self.0[start..start + 32].fill(0xFF);
+///         Ok(())
+///     }
+/// }
+///
+/// let mut storage = RamStorage([0xFF; 64]);
+/// storage.write(40, &[1, 2, 3]).unwrap();
+/// let mut buffer = [0u8; 3];
+/// storage.read(40, &mut buffer).unwrap();
+/// assert_eq!(buffer, [1, 2, 3]);
+/// assert_eq!(storage.capacity(), 64);
+/// ```
+pub trait BlockStorage {
+    type Error;
+
+    /// 消去単位のバイト数。
+    fn block_size(&self) -> u32;
+
+    /// ブロック数。
+    fn block_count(&self) -> u32;
+
+    fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), Self::Error>;
+
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// `block` 番目のブロックを `0xFF` で埋める。
+    fn erase_block(&mut self, block: u32) -> Result<(), Self::Error>;
+
+    /// 全体のバイト数。
+    fn capacity(&self) -> u32 {
+        self.block_size().saturating_mul(self.block_count())
+    }
+}
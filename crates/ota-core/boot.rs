@@ -0,0 +1,568 @@
+//! 起動スロットの選択と巻き戻し。
+//!
+//! [`BootRecord`] は ota_0 / ota_1 それぞれの状態と次に起動するスロットを持つ。
+//!
+//! - 新しいイメージは [`SlotState::Pending`] として起動先になる
+//! - 起動のたびに [`BootRecord::on_boot`] が試行回数を数え、アプリが
+//!   [`BootRecord::mark_valid`] で動作確認を済ませる前に上限を超えたら
+//!   [`SlotState::Invalid`] にして前の検証済みスロットへ戻す
+//!
+//! [`BootControl`] はこれを otadata パーティションの 2 ブロックへ交互に書く。
+//! 書き込み中に電源が落ちても、もう片方のブロックに 1 つ前のレコードが残る
+//! (ESP-IDF の otadata と同じ考え方)。
+
+use hal_api::storage::BlockStorage;
+
+use crate::crc32;
+use crate::error::{BootError, UploadError};
+use crate::manifest::{ImageManifest, PublicKey};
+use crate::partition::PartitionTable;
+use crate::upload::{UploadProgress, UploadSession, VerifiedImage};
+
+/// otadata に書くレコードのバイト数。
+pub const RECORD_LEN: usize = 25;
+
+const RECORD_MAGIC: [u8; 4] = *b"OTAB";
+
+/// アプリスロット。`A` が ota_0、`B` が ota_1。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    A,
+    B,
+}
+
+impl Slot {
+    pub const fn index(self) -> usize {
+        match self {
+            Slot::A => 0,
+            Slot::B => 1,
+        }
+    }
+
+    pub const fn from_index(index: u8) -> Option<Self> {
+        match index {
+            0 => Some(Slot::A),
+            1 => Some(Slot::B),
+            _ => None,
+        }
+    }
+
+    pub const fn other(self) -> Self {
+        match self {
+            Slot::A => Slot::B,
+            Slot::B => Slot::A,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotState {
+    /// イメージが無い
+    Empty,
+    /// 書き込み・検証済みで、アプリの動作確認待ち
+    Pending { boot_attempts: u8 },
+    /// 動作確認済み
+    Valid,
+    /// 動作確認前に起動失敗を繰り返した
+    Invalid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotInfo {
+    pub state: SlotState,
+    pub version: u32,
+}
+
+impl SlotInfo {
+    const EMPTY: Self = Self {
+        state: SlotState::Empty,
+        version: 0,
+    };
+}
+
+/// [`BootRecord::on_boot`] の結果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootDecision {
+    /// `slot` から起動する。`trial` なら動作確認後に [`BootRecord::mark_valid`] を呼ぶこと
+    Boot { slot: Slot, trial: bool },
+    /// `from` を諦めて検証済みの `to` から起動する
+    RolledBack { from: Slot, to: Slot },
+    /// 起動できるスロットが無い
+    NoBootableSlot,
+}
+
+/// 起動先と各スロットの状態。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootRecord {
+    /// 書き込むたびに増える。読み出し時は大きい方を採用する
+    pub seq: u32,
+    pub active: Slot,
+    pub slots: [SlotInfo; 2],
+}
+
+impl BootRecord {
+    /// 工場出荷時 (USB 書き込み直後) のレコード。`slot` の `version` を検証済みとみなす。
+    pub const fn new(slot: Slot, version: u32) -> Self {
+        let mut slots = [SlotInfo::EMPTY; 2];
+        slots[slot.index()] = SlotInfo {
+            state: SlotState::Valid,
+            version,
+        };
+        Self {
+            seq: 0,
+            active: slot,
+            slots,
+        }
+    }
+
+    pub fn slot(&self, slot: Slot) -> SlotInfo {
+        self.slots[slot.index()]
+    }
+
+    /// 起動中 (次に起動する) スロットのバージョン。
+    pub fn active_version(&self) -> u32 {
+        self.slot(self.active).version
+    }
+
+    /// 新しいイメージの書き込み先 (起動中でない方)。
+    pub fn update_target(&self) -> Slot {
+        self.active.other()
+    }
+
+    /// 起動時に 1 回呼び、どのスロットから起動するかを決める。
+    ///
+    /// 確認待ちのスロットは `max_trial_boots` 回まで試行起動する。
+    pub fn on_boot(&mut self, max_trial_boots: u8) -> BootDecision {
+        let active = self.active;
+        match self.slot(active).state {
+            SlotState::Valid => BootDecision::Boot {
+                slot: active,
+                trial: false,
+            },
+            SlotState::Pending { boot_attempts } if boot_attempts < max_trial_boots => {
+                self.slots[active.index()].state = SlotState::Pending {
+                    boot_attempts: boot_attempts + 1,
+                };
+                BootDecision::Boot {
+                    slot: active,
+                    trial: true,
+                }
+            }
+            SlotState::Pending { .. } => {
+                self.slots[active.index()].state = SlotState::Invalid;
+                self.fall_back()
+            }
+            SlotState::Empty | SlotState::Invalid => self.fall_back(),
+        }
+    }
+
+    fn fall_back(&mut self) -> BootDecision {
+        let from = self.active;
+        let to = from.other();
+        if self.slot(to).state == SlotState::Valid {
+            self.active = to;
+            BootDecision::RolledBack { from, to }
+        } else {
+            BootDecision::NoBootableSlot
+        }
+    }
+
+    /// 起動中のスロットを動作確認済みにする。状態が変わったら `true`。
+    pub fn mark_valid(&mut self) -> bool {
+        let info = &mut self.slots[self.active.index()];
+        if matches!(info.state, SlotState::Pending { .. }) {
+            info.state = SlotState::Valid;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 検証済みのイメージを次回の起動先にする。
+    pub fn stage(&mut self, image: &VerifiedImage) {
+        self.slots[image.slot.index()] = SlotInfo {
+            state: SlotState::Pending { boot_attempts: 0 },
+            version: image.version,
+        };
+        self.active = image.slot;
+    }
+
+    /// 検証済みの別スロットへ切り替える (updater への退避など)。
+    pub fn switch_to(&mut self, slot: Slot) -> bool {
+        if self.slot(slot).state != SlotState::Valid {
+            return false;
+        }
+        self.active = slot;
+        true
+    }
+
+    pub fn to_bytes(&self) -> [u8; RECORD_LEN] {
+        let mut bytes = [0u8; RECORD_LEN];
+        bytes[..4].copy_from_slice(&RECORD_MAGIC);
+        bytes[4..8].copy_from_slice(&self.seq.to_le_bytes());
+        bytes[8] = self.active.index() as u8;
+        for (index, info) in self.slots.iter().enumerate() {
+            let at = 9 + index * 6;
+            let (state, attempts) = match info.state {
+                SlotState::Empty => (0, 0),
+                SlotState::Pending { boot_attempts } => (1, boot_attempts),
+                SlotState::Valid => (2, 0),
+                SlotState::Invalid => (3, 0),
+            };
+            bytes[at] = state;
+            bytes[at + 1] = attempts;
+            bytes[at + 2..at + 6].copy_from_slice(&info.version.to_le_bytes());
+        }
+        let crc = crc32(&bytes[..RECORD_LEN - 4]);
+        bytes[RECORD_LEN - 4..].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// マジックか CRC が合わなければ `None` (未書き込み・書きかけのブロック)。
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < RECORD_LEN || bytes[..4] != RECORD_MAGIC {
+            return None;
+        }
+        let word = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+        if word(RECORD_LEN - 4) != crc32(&bytes[..RECORD_LEN - 4]) {
+            return None;
+        }
+        let mut slots = [SlotInfo::EMPTY; 2];
+        for (index, info) in slots.iter_mut().enumerate() {
+            let at = 9 + index * 6;
+            info.state = match (bytes[at], bytes[at + 1]) {
+                (0, _) => SlotState::Empty,
+                (1, boot_attempts) => SlotState::Pending { boot_attempts },
+                (2, _) => SlotState::Valid,
+                (3, _) => SlotState::Invalid,
+                _ => return None,
+            };
+            info.version = word(at + 2);
+        }
+        Some(Self {
+            seq: word(4),
+            active: Slot::from_index(bytes[8])?,
+            slots,
+        })
+    }
+}
+
+/// otadata に永続化した [`BootRecord`] とアプリスロットへの書き込みをまとめたもの。
+pub struct BootControl<S> {
+    storage: S,
+    table: PartitionTable,
+    record: BootRecord,
+}
+
+impl<S: BlockStorage> BootControl<S> {
+    /// otadata を読む。有効なレコードが無ければ `factory_slot` の `factory_version` を
+    /// 検証済みとして初期化する。
+    pub fn open(
+        mut storage: S,
+        table: PartitionTable,
+        factory_slot: Slot,
+        factory_version: u32,
+    ) -> Result<Self, BootError<S::Error>> {
+        table.validate(storage.block_size(), storage.capacity())?;
+        let loaded = Self::read_record(&mut storage, &table).map_err(BootError::Storage)?;
+        let mut control = Self {
+            storage,
+            table,
+            record: BootRecord::new(factory_slot, factory_version),
+        };
+        match loaded {
+            Some(record) => control.record = record,
+            None => control.commit(control.record)?,
+        }
+        Ok(control)
+    }
+
+    /// otadata の 2 ブロックから有効で `seq` の大きい方を読む。
+    pub fn read_record(
+        storage: &mut S,
+        table: &PartitionTable,
+    ) -> Result<Option<BootRecord>, S::Error> {
+        let mut best: Option<BootRecord> = None;
+        for copy in 0..2 {
+            let mut bytes = [0u8; RECORD_LEN];
+            storage.read(
+                table.otadata.offset + copy * storage.block_size(),
+                &mut bytes,
+            )?;
+            if let Some(record) = BootRecord::from_bytes(&bytes) {
+                if best.map_or(true, |b| record.seq > b.seq) {
+                    best = Some(record);
+                }
+            }
+        }
+        Ok(best)
+    }
+
+    pub fn record(&self) -> &BootRecord {
+        &self.record
+    }
+
+    pub fn table(&self) -> &PartitionTable {
+        &self.table
+    }
+
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    pub fn storage_mut(&mut self) -> &mut S {
+        &mut self.storage
+    }
+
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    /// 起動時に 1 回呼ぶ ([`BootRecord::on_boot`] を永続化する)。
+    pub fn boot(&mut self, max_trial_boots: u8) -> Result<BootDecision, BootError<S::Error>> {
+        let mut record = self.record;
+        let decision = record.on_boot(max_trial_boots);
+        if record != self.record {
+            self.commit(record)?;
+        }
+        Ok(decision)
+    }
+
+    /// 起動中のイメージの動作確認が済んだことを記録する。
+    pub fn mark_valid(&mut self) -> Result<bool, BootError<S::Error>> {
+        let mut record = self.record;
+        if !record.mark_valid() {
+            return Ok(false);
+        }
+        self.commit(record)?;
+        Ok(true)
+    }
+
+    /// 検証済みの `slot` を次回の起動先にする。
+    pub fn switch_to(&mut self, slot: Slot) -> Result<(), BootError<S::Error>> {
+        let mut record = self.record;
+        if !record.switch_to(slot) {
+            return Err(BootError::SlotNotBootable);
+        }
+        if record != self.record {
+            self.commit(record)?;
+        }
+        Ok(())
+    }
+
+    /// 起動中でない方のスロットへの書き込みを始める。
+    pub fn begin_update(
+        &self,
+        manifest: ImageManifest,
+        key: &PublicKey,
+    ) -> Result<UploadSession, UploadError<S::Error>> {
+        let target = self.record.update_target();
+        UploadSession::begin(
+            target,
+            self.table.slot(target),
+            manifest,
+            key,
+            self.record.active_version(),
+        )
+    }
+
+    /// 中断したアップロードを再開する。その後に起動先が変わっていれば最初からやり直す。
+    pub fn resume_update(
+        &self,
+        progress: UploadProgress,
+        key: &PublicKey,
+    ) -> Result<UploadSession, UploadError<S::Error>> {
+        let target = self.record.update_target();
+        if progress.slot != target {
+            return self.begin_update(progress.manifest, key);
+        }
+        UploadSession::resume(
+            progress,
+            self.table.slot(target),
+            self.storage.block_size(),
+            key,
+            self.record.active_version(),
+        )
+    }
+
+    /// 書き込み終えたイメージを読み戻して検証する。
+    pub fn finish_update(
+        &mut self,
+        session: &UploadSession,
+    ) -> Result<VerifiedImage, UploadError<S::Error>> {
+        session.finish(&mut self.storage)
+    }
+
+    /// 検証済みのイメージを次回の起動先にする。
+    pub fn stage(&mut self, image: &VerifiedImage) -> Result<(), BootError<S::Error>> {
+        let mut record = self.record;
+        record.stage(image);
+        self.commit(record)
+    }
+
+    /// `seq` を進めて、古い方のブロックへ書いて読み戻す。
+    fn commit(&mut self, mut record: BootRecord) -> Result<(), BootError<S::Error>> {
+        record.seq = self.record.seq.wrapping_add(1);
+        let block_size = self.storage.block_size();
+        let copy = record.seq % 2;
+        let offset = self.table.otadata.offset + copy * block_size;
+        let bytes = record.to_bytes();
+        self.storage
+            .erase_block(offset / block_size)
+            .map_err(BootError::Storage)?;
+        self.storage
+            .write(offset, &bytes)
+            .map_err(BootError::Storage)?;
+        let mut check = [0u8; RECORD_LEN];
+        self.storage
+            .read(offset, &mut check)
+            .map_err(BootError::Storage)?;
+        if check != bytes {
+            return Err(BootError::VerifyFailed);
+        }
+        self.record = record;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::SimFlash;
+    use hal_api::error::StorageError;
+
+    fn image(slot: Slot, version: u32) -> VerifiedImage {
+        VerifiedImage {
+            slot,
+            version,
+            size: 1,
+        }
+    }
+
+    #[test]
+    fn unconfirmed_image_rolls_back_after_trial_boots() {
+        let mut record = BootRecord::new(Slot::A, 1);
+        record.stage(&image(Slot::B, 2));
+        for _ in 0..3 {
+            assert_eq!(
+                record.on_boot(3),
+                BootDecision::Boot {
+                    slot: Slot::B,
+                    trial: true
+                }
+            );
+        }
+        assert_eq!(
+            record.on_boot(3),
+            BootDecision::RolledBack {
+                from: Slot::B,
+                to: Slot::A
+            }
+        );
+        assert_eq!(record.slot(Slot::B).state, SlotState::Invalid);
+        assert_eq!(
+            record.on_boot(3),
+            BootDecision::Boot {
+                slot: Slot::A,
+                trial: false
+            }
+        );
+        // 巻き戻した後は動作確認しても A のまま
+        assert!(!record.mark_valid());
+        assert_eq!(record.update_target(), Slot::B);
+    }
+
+    #[test]
+    fn confirmed_image_becomes_permanent() {
+        let mut record = BootRecord::new(Slot::A, 1);
+        record.stage(&image(Slot::B, 2));
+        record.on_boot(1);
+        assert!(record.mark_valid());
+        assert!(!record.mark_valid());
+        for _ in 0..5 {
+            assert_eq!(
+                record.on_boot(1),
+                BootDecision::Boot {
+                    slot: Slot::B,
+                    trial: false
+                }
+            );
+        }
+        assert_eq!(record.active_version(), 2);
+        assert!(record.switch_to(Slot::A));
+    }
+
+    #[test]
+    fn no_fallback_is_reported() {
+        let mut record = BootRecord::new(Slot::A, 1);
+        record.slots[0].state = SlotState::Invalid;
+        assert_eq!(record.on_boot(3), BootDecision::NoBootableSlot);
+        assert!(!record.switch_to(Slot::B));
+    }
+
+    #[test]
+    fn record_encoding_round_trips_and_detects_corruption() {
+        let mut record = BootRecord::new(Slot::B, 7);
+        record.seq = 42;
+        record.stage(&image(Slot::A, 8));
+        record.on_boot(3);
+        let bytes = record.to_bytes();
+        assert_eq!(BootRecord::from_bytes(&bytes), Some(record));
+        for index in 0..RECORD_LEN {
+            let mut corrupted = bytes;
+            corrupted[index] ^= 0x10;
+            assert_eq!(BootRecord::from_bytes(&corrupted), None, "byte {index}");
+        }
+        assert_eq!(BootRecord::from_bytes(&[0xFF; RECORD_LEN]), None);
+    }
+
+    #[test]
+    fn control_alternates_otadata_blocks_and_survives_torn_writes() {
+        let table = PartitionTable::ESP32_4MB_OTA;
+        let flash = SimFlash::new(4096, 1024);
+        let mut control = BootControl::open(flash, table, Slot::A, 1).unwrap();
+        assert_eq!(control.record().seq, 1);
+        control.stage(&image(Slot::B, 2)).unwrap();
+        assert_eq!(control.record().seq, 2);
+
+        // 3 回目の書き込みが途中で止まっても、直前のレコードが読める
+        control.storage_mut().tear_next_write(10);
+        assert_eq!(
+            control.boot(3),
+            Err(BootError::Storage(StorageError::WriteFailed))
+        );
+        let mut flash = control.into_storage();
+        let record = BootControl::read_record(&mut flash, &table)
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.seq, 2);
+        assert_eq!(record.active, Slot::B);
+        assert_eq!(
+            record.slot(Slot::B).state,
+            SlotState::Pending { boot_attempts: 0 }
+        );
+
+        let mut control = BootControl::open(flash, table, Slot::A, 1).unwrap();
+        assert_eq!(
+            control.boot(3).unwrap(),
+            BootDecision::Boot {
+                slot: Slot::B,
+                trial: true
+            }
+        );
+        assert_eq!(control.switch_to(Slot::B), Err(BootError::SlotNotBootable));
+        control.switch_to(Slot::A).unwrap();
+        assert_eq!(control.record().active, Slot::A);
+    }
+
+    #[test]
+    fn open_rejects_a_table_that_does_not_fit_the_flash() {
+        let flash = SimFlash::new(4096, 16);
+        assert!(matches!(
+            BootControl::open(flash, PartitionTable::ESP32_4MB_OTA, Slot::A, 1),
+            Err(BootError::Partition(_))
+        ));
+    }
+}
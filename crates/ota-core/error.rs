@@ -0,0 +1,146 @@
+//! OTA 処理のエラー型。
+
+/// マニフェストの解析・署名検証のエラー。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestError {
+    /// 長さが違う、またはマジックが一致しない
+    Malformed,
+    /// 公開鍵が Ed25519 の点として不正
+    InvalidKey,
+    /// 署名が一致しない (改ざん、または別の鍵で署名された)
+    BadSignature,
+}
+
+/// パーティションテーブルのエラー。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionError {
+    /// CSV の行を解釈できない
+    Malformed,
+    /// otadata / ota_0 / ota_1 のいずれかが無い
+    Missing,
+    /// 領域がブロック境界に揃っていない、重なっている、またはフラッシュに収まらない
+    Layout,
+}
+
+/// イメージのアップロードのエラー。`E` はフラッシュのエラー型。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadError<E> {
+    Storage(E),
+    Manifest(ManifestError),
+    /// イメージが書き込み先スロットに収まらない
+    TooLarge {
+        size: u32,
+        capacity: u32,
+    },
+    /// 稼働中のバージョン以下のイメージ (anti-rollback)
+    Downgrade {
+        current: u32,
+        offered: u32,
+    },
+    /// チャンクの位置が受信済みバイト数と一致しない。`expected` から送り直す
+    OffsetMismatch {
+        expected: u32,
+    },
+    /// チャンクがマニフェストのサイズを超える
+    ChunkOutOfBounds,
+    /// まだ全バイト受信していない
+    Incomplete {
+        received: u32,
+        size: u32,
+    },
+    /// 書き込んだ内容の SHA-256 がマニフェストと一致しない
+    HashMismatch,
+}
+
+impl<E> From<ManifestError> for UploadError<E> {
+    fn from(error: ManifestError) -> Self {
+        UploadError::Manifest(error)
+    }
+}
+
+/// 起動スロット管理のエラー。`E` はフラッシュのエラー型。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BootError<E> {
+    Storage(E),
+    Partition(PartitionError),
+    /// 切り替え先のスロットが検証済み (Valid) ではない
+    SlotNotBootable,
+    /// otadata を書いたが読み戻した内容が一致しない
+    VerifyFailed,
+}
+
+impl<E> From<PartitionError> for BootError<E> {
+    fn from(error: PartitionError) -> Self {
+        BootError::Partition(error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::Malformed => write!(f, "malformed image manifest"),
+            ManifestError::InvalidKey => write!(f, "invalid Ed25519 public key"),
+            ManifestError::BadSignature => write!(f, "image signature does not verify"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ManifestError {}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for PartitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PartitionError::Malformed => write!(f, "malformed partition table"),
+            PartitionError::Missing => write!(f, "partition table lacks otadata/ota_0/ota_1"),
+            PartitionError::Layout => write!(f, "partitions are misaligned or overlap"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PartitionError {}
+
+#[cfg(feature = "std")]
+impl<E: std::fmt::Debug> std::fmt::Display for UploadError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UploadError::Storage(e) => write!(f, "flash error: {e:?}"),
+            UploadError::Manifest(e) => write!(f, "{e}"),
+            UploadError::TooLarge { size, capacity } => {
+                write!(f, "image of {size} bytes exceeds slot capacity {capacity}")
+            }
+            UploadError::Downgrade { current, offered } => {
+                write!(f, "image version {offered} is not newer than {current}")
+            }
+            UploadError::OffsetMismatch { expected } => {
+                write!(f, "chunk offset mismatch, resume at {expected}")
+            }
+            UploadError::ChunkOutOfBounds => write!(f, "chunk extends past image size"),
+            UploadError::Incomplete { received, size } => {
+                write!(f, "image incomplete: {received}/{size} bytes")
+            }
+            UploadError::HashMismatch => write!(f, "image SHA-256 mismatch"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::fmt::Debug> std::error::Error for UploadError<E> {}
+
+#[cfg(feature = "std")]
+impl<E: std::fmt::Debug> std::fmt::Display for BootError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BootError::Storage(e) => write!(f, "flash error: {e:?}"),
+            BootError::Partition(e) => write!(f, "{e}"),
+            BootError::SlotNotBootable => write!(f, "target slot holds no verified image"),
+            BootError::VerifyFailed => write!(f, "otadata read-back mismatch"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::fmt::Debug> std::error::Error for BootError<E> {}
@@ -0,0 +1,83 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! # OTA Core
+//!
+//! ファームウェア更新 (OTA) の、ハードウェアに依存しない部分。
+//!
+//! - [`manifest::ImageManifest`] はイメージのサイズ・SHA-256・バージョンに対する Ed25519 署名
+//! - [`upload::UploadSession`] はオフセットを追跡するチャンク単位の書き込み。接続が切れても
+//!   [`upload::UploadProgress`] から途中再開でき、最後にフラッシュから読み戻して SHA-256 を照合する
+//! - [`boot::BootRecord`] / [`boot::BootControl`] は 2 スロット (ota_0 / ota_1) の起動先と
+//!   試行起動の回数を otadata に二重化して保存し、確認されないまま起動に失敗し続けた
+//!   イメージを前のスロットへ巻き戻す状態機械
+//! - [`partition::PartitionTable`] は ESP-IDF の `partitions.csv` から OTA 関連の領域を読む
+//!
+//! フラッシュは `hal_api::storage::BlockStorage` で抽象化しているので、`std` feature の
+//! [`sim::SimFlash`] (NOR フラッシュの書き込み規則と電源断を再現するメモリ上のフラッシュ)
+//! を使えば、更新から巻き戻しまでを host 上でテストできる。
+//!
+//! # Examples
+//!
+//! ```
+//! use ota_core::boot::{BootControl, BootDecision, Slot};
+//! use ota_core::manifest::ImageManifest;
+//! use ota_core::partition::PartitionTable;
+//! use ota_core::sim::SimFlash;
+//!
+//! let signing_key = [7u8; 32];
+//! let public_key = ota_core::manifest::public_key(&signing_key);
+//!
+//! let table = PartitionTable::ESP32_4MB_OTA;
+//! let flash = SimFlash::new(4096, 1024);
+//! let mut control = BootControl::open(flash, table, Slot::A, 1).unwrap();
+//!
+//! // 新しいイメージを受け取って検証し、次回起動先にする
+//! let image = [0xA5u8; 10_000];
+//! let manifest = ImageManifest::sign(&image, 2, &signing_key);
+//! let mut session = control.begin_update(manifest, &public_key).unwrap();
+//! for (index, chunk) in image.chunks(4096).enumerate() {
+//!     let offset = (index * 4096) as u32;
+//!     session.write_chunk(control.storage_mut(), offset, chunk).unwrap();
+//! }
+//! let verified = control.finish_update(&session).unwrap();
+//! control.stage(&verified).unwrap();
+//!
+//! // 再起動後: 新しいスロットで試行起動し、動作確認できたら確定する
+//! assert_eq!(control.boot(3).unwrap(), BootDecision::Boot { slot: Slot::B, trial: true });
+//! control.mark_valid().unwrap();
+//! assert_eq!(control.boot(3).unwrap(), BootDecision::Boot { slot: Slot::B, trial: false });
+//! ```
+
+pub mod boot;
+pub mod error;
+pub mod manifest;
+pub mod partition;
+#[cfg(feature = "std")]
+pub mod sim;
+pub mod upload;
+
+pub use error::{BootError, ManifestError, PartitionError, UploadError};
+
+/// IEEE 802.3 の CRC-32 (otadata レコードの破損検出用)。
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_reference_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+}
@@ -0,0 +1,189 @@
+//! 署名付きイメージマニフェスト。
+//!
+//! アップロードの最初に送る固定長 [`MANIFEST_LEN`] バイトのレコード:
+//!
+//! | オフセット | 長さ | 内容 |
+//! |-----------|------|------|
+//! | 0 | 4 | マジック `OTA1` |
+//! | 4 | 4 | バージョン (LE) |
+//! | 8 | 4 | イメージサイズ (LE) |
+//! | 12 | 32 | イメージ全体の SHA-256 |
+//! | 44 | 64 | 先頭 44 バイトに対する Ed25519 署名 |
+//!
+//! 署名を検証してから書き込みを始め、書き込み後に SHA-256 を照合するので、
+//! 秘密鍵を持たない相手が送ったイメージは起動先にならない。
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+use crate::error::ManifestError;
+
+pub const MANIFEST_MAGIC: [u8; 4] = *b"OTA1";
+
+/// 署名対象のバイト数 (マジック・バージョン・サイズ・SHA-256)。
+pub const SIGNED_LEN: usize = 44;
+
+/// エンコード済みマニフェストのバイト数。
+pub const MANIFEST_LEN: usize = SIGNED_LEN + 64;
+
+/// Ed25519 公開鍵 (32 バイト)。ファームウェアに埋め込む。
+pub type PublicKey = [u8; 32];
+
+/// Ed25519 秘密鍵のシード (32 バイト)。ビルドマシン側だけで持つ。
+pub type SecretKey = [u8; 32];
+
+/// `secret` に対応する公開鍵。
+pub fn public_key(secret: &SecretKey) -> PublicKey {
+    SigningKey::from_bytes(secret).verifying_key().to_bytes()
+}
+
+/// イメージの SHA-256。
+pub fn sha256(image: &[u8]) -> [u8; 32] {
+    Sha256::digest(image).into()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageManifest {
+    /// 単調増加するファームウェアのバージョン。古いイメージへの差し戻しを拒否するのに使う
+    pub version: u32,
+    pub size: u32,
+    pub sha256: [u8; 32],
+    pub signature: [u8; 64],
+}
+
+impl ImageManifest {
+    /// `image` のマニフェストを作って `secret` で署名する。
+    ///
+    /// # Panics
+    ///
+    /// `image` が 4 GiB 以上のとき。
+    pub fn sign(image: &[u8], version: u32, secret: &SecretKey) -> Self {
+        let size = u32::try_from(image.len()).expect("image larger than 4 GiB");
+        Self::sign_digest(sha256(image), size, version, secret)
+    }
+
+    /// SHA-256 を計算済みのイメージに署名する (大きなファイルを分割してハッシュする場合)。
+    pub fn sign_digest(sha256: [u8; 32], size: u32, version: u32, secret: &SecretKey) -> Self {
+        let mut manifest = Self {
+            version,
+            size,
+            sha256,
+            signature: [0; 64],
+        };
+        manifest.signature = SigningKey::from_bytes(secret)
+            .sign(&manifest.signed_bytes())
+            .to_bytes();
+        manifest
+    }
+
+    /// 署名対象のバイト列。
+    pub fn signed_bytes(&self) -> [u8; SIGNED_LEN] {
+        let mut bytes = [0u8; SIGNED_LEN];
+        bytes[..4].copy_from_slice(&MANIFEST_MAGIC);
+        bytes[4..8].copy_from_slice(&self.version.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.size.to_le_bytes());
+        bytes[12..].copy_from_slice(&self.sha256);
+        bytes
+    }
+
+    pub fn to_bytes(&self) -> [u8; MANIFEST_LEN] {
+        let mut bytes = [0u8; MANIFEST_LEN];
+        bytes[..SIGNED_LEN].copy_from_slice(&self.signed_bytes());
+        bytes[SIGNED_LEN..].copy_from_slice(&self.signature);
+        bytes
+    }
+
+    /// エンコード済みのマニフェストを読む。署名は検証しない ([`ImageManifest::verify`])。
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ManifestError> {
+        if bytes.len() != MANIFEST_LEN || bytes[..4] != MANIFEST_MAGIC {
+            return Err(ManifestError::Malformed);
+        }
+        let word = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+        let mut sha256 = [0u8; 32];
+        sha256.copy_from_slice(&bytes[12..SIGNED_LEN]);
+        let mut signature = [0u8; 64];
+        signature.copy_from_slice(&bytes[SIGNED_LEN..]);
+        Ok(Self {
+            version: word(4),
+            size: word(8),
+            sha256,
+            signature,
+        })
+    }
+
+    /// 署名が `key` の秘密鍵によるものか検証する。
+    pub fn verify(&self, key: &PublicKey) -> Result<(), ManifestError> {
+        let key = VerifyingKey::from_bytes(key).map_err(|_| ManifestError::InvalidKey)?;
+        key.verify_strict(
+            &self.signed_bytes(),
+            &Signature::from_bytes(&self.signature),
+        )
+        .map_err(|_| ManifestError::BadSignature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: SecretKey = [0x42; 32];
+
+    #[test]
+    fn signed_manifest_round_trips_and_verifies() {
+        let manifest = ImageManifest::sign(b"firmware", 3, &SECRET);
+        assert_eq!(manifest.size, 8);
+        assert_eq!(manifest.sha256, sha256(b"firmware"));
+
+        let bytes = manifest.to_bytes();
+        assert_eq!(&bytes[..4], b"OTA1");
+        let decoded = ImageManifest::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, manifest);
+        decoded.verify(&public_key(&SECRET)).unwrap();
+    }
+
+    #[test]
+    fn tampered_fields_or_wrong_key_fail_verification() {
+        let manifest = ImageManifest::sign(b"firmware", 3, &SECRET);
+        let key = public_key(&SECRET);
+
+        let mut newer = manifest;
+        newer.version = 4;
+        assert_eq!(newer.verify(&key), Err(ManifestError::BadSignature));
+
+        let mut other_image = manifest;
+        other_image.sha256[0] ^= 1;
+        assert_eq!(other_image.verify(&key), Err(ManifestError::BadSignature));
+
+        let other_key = public_key(&[0x43; 32]);
+        assert_eq!(
+            manifest.verify(&other_key),
+            Err(ManifestError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn malformed_bytes_are_rejected() {
+        let bytes = ImageManifest::sign(b"x", 1, &SECRET).to_bytes();
+        assert_eq!(
+            ImageManifest::from_bytes(&bytes[..MANIFEST_LEN - 1]),
+            Err(ManifestError::Malformed)
+        );
+        let mut bad_magic = bytes;
+        bad_magic[0] = b'X';
+        assert_eq!(
+            ImageManifest::from_bytes(&bad_magic),
+            Err(ManifestError::Malformed)
+        );
+    }
+
+    #[test]
+    fn sha256_matches_known_vector() {
+        assert_eq!(
+            sha256(b"abc")[..4],
+            [0xBA, 0x78, 0x16, 0xBF],
+            "FIPS 180-2 example"
+        );
+    }
+}
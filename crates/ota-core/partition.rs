@@ -0,0 +1,199 @@
+//! フラッシュ上の OTA 関連パーティション。
+
+use crate::boot::Slot;
+use crate::error::PartitionError;
+
+/// フラッシュ上の連続した領域。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Partition {
+    pub offset: u32,
+    pub size: u32,
+}
+
+impl Partition {
+    pub const fn new(offset: u32, size: u32) -> Self {
+        Self { offset, size }
+    }
+
+    pub const fn end(&self) -> u32 {
+        self.offset + self.size
+    }
+
+    fn overlaps(&self, other: &Partition) -> bool {
+        self.offset < other.end() && other.offset < self.end()
+    }
+}
+
+/// otadata と 2 つのアプリスロット。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionTable {
+    /// 起動先レコード。2 ブロック以上 (二重化のため) 必要
+    pub otadata: Partition,
+    /// `[ota_0, ota_1]`
+    pub slots: [Partition; 2],
+}
+
+impl PartitionTable {
+    /// `firmware/*/partitions.csv` と同じ 4 MB フラッシュの OTA レイアウト。
+    pub const ESP32_4MB_OTA: Self = Self {
+        otadata: Partition::new(0xE000, 0x2000),
+        slots: [
+            Partition::new(0x1_0000, 0x1E_0000),
+            Partition::new(0x1F_0000, 0x1E_0000),
+        ],
+    };
+
+    pub fn slot(&self, slot: Slot) -> Partition {
+        self.slots[slot.index()]
+    }
+
+    /// ESP-IDF 形式の `partitions.csv` から otadata / ota_0 / ota_1 を取り出す。
+    ///
+    /// `#` で始まる行と空行は読み飛ばす。オフセットとサイズは 10 進 / `0x` 16 進 /
+    /// `K` `M` 接尾辞に対応する (オフセット省略の自動配置には対応しない)。
+    pub fn from_csv(csv: &str) -> Result<Self, PartitionError> {
+        let mut otadata = None;
+        let mut slots = [None, None];
+        for line in csv.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split(',').map(str::trim);
+            let (Some(_name), Some(kind), Some(subtype), Some(offset), Some(size)) = (
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+            ) else {
+                return Err(PartitionError::Malformed);
+            };
+            let target = match (kind, subtype) {
+                ("data", "ota") => &mut otadata,
+                ("app", "ota_0") => &mut slots[0],
+                ("app", "ota_1") => &mut slots[1],
+                _ => continue,
+            };
+            *target = Some(Partition::new(parse_size(offset)?, parse_size(size)?));
+        }
+        match (otadata, slots) {
+            (Some(otadata), [Some(ota_0), Some(ota_1)]) => Ok(Self {
+                otadata,
+                slots: [ota_0, ota_1],
+            }),
+            _ => Err(PartitionError::Missing),
+        }
+    }
+
+    /// ブロック境界に揃っていて重ならず、`capacity` に収まるか。
+    pub fn validate(&self, block_size: u32, capacity: u32) -> Result<(), PartitionError> {
+        let all = [self.otadata, self.slots[0], self.slots[1]];
+        for (index, partition) in all.iter().enumerate() {
+            let aligned = partition.offset % block_size == 0 && partition.size % block_size == 0;
+            let fits =
+                u64::from(partition.offset) + u64::from(partition.size) <= u64::from(capacity);
+            if !aligned || !fits || partition.size == 0 {
+                return Err(PartitionError::Layout);
+            }
+            if all[index + 1..]
+                .iter()
+                .any(|other| partition.overlaps(other))
+            {
+                return Err(PartitionError::Layout);
+            }
+        }
+        if self.otadata.size < 2 * block_size {
+            return Err(PartitionError::Layout);
+        }
+        Ok(())
+    }
+}
+
+fn parse_size(text: &str) -> Result<u32, PartitionError> {
+    let (digits, multiplier) = match text.as_bytes().last() {
+        Some(b'K' | b'k') => (&text[..text.len() - 1], 1024),
+        Some(b'M' | b'm') => (&text[..text.len() - 1], 1024 * 1024),
+        _ => (text, 1),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => digits.parse(),
+    }
+    .map_err(|_| PartitionError::Malformed)?;
+    value
+        .checked_mul(multiplier)
+        .ok_or(PartitionError::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn firmware_partition_tables_match_the_builtin_layout() {
+        for csv in [
+            include_str!("../../firmware/original-esp32-wifi-climate/partitions.csv"),
+            include_str!("../../firmware/original-esp32-ota-bringup/partitions.csv"),
+        ] {
+            let table = PartitionTable::from_csv(csv).unwrap();
+            assert_eq!(table, PartitionTable::ESP32_4MB_OTA);
+            table.validate(4096, 4 * 1024 * 1024).unwrap();
+        }
+    }
+
+    #[test]
+    fn csv_sizes_accept_suffixes_and_missing_slots_are_reported() {
+        let table = PartitionTable::from_csv(
+            "otadata, data, ota, 0x1000, 8K,\n\
+             a, app, ota_0, 0x10000, 1M,\n\
+             b, app, ota_1, 1114112, 0x100000,\n",
+        )
+        .unwrap();
+        assert_eq!(table.otadata, Partition::new(0x1000, 0x2000));
+        assert_eq!(table.slots[0].size, 0x10_0000);
+        assert_eq!(table.slots[1].offset, 0x11_0000);
+
+        assert_eq!(
+            PartitionTable::from_csv("otadata, data, ota, 0x1000, 8K\n"),
+            Err(PartitionError::Missing)
+        );
+        assert_eq!(
+            PartitionTable::from_csv("otadata, data, ota, 0x1000, 8Q\n"),
+            Err(PartitionError::Malformed)
+        );
+        assert_eq!(
+            PartitionTable::from_csv("otadata, data\n"),
+            Err(PartitionError::Malformed)
+        );
+    }
+
+    #[test]
+    fn validate_rejects_overlap_misalignment_and_overflow() {
+        let mut table = PartitionTable::ESP32_4MB_OTA;
+        assert_eq!(
+            table.validate(4096, 2 * 1024 * 1024),
+            Err(PartitionError::Layout)
+        );
+        table.slots[1].offset -= 0x1000;
+        assert_eq!(
+            table.validate(4096, 4 * 1024 * 1024),
+            Err(PartitionError::Layout)
+        );
+        let mut table = PartitionTable::ESP32_4MB_OTA;
+        table.otadata.offset += 1;
+        assert_eq!(
+            table.validate(4096, 4 * 1024 * 1024),
+            Err(PartitionError::Layout)
+        );
+        let mut table = PartitionTable::ESP32_4MB_OTA;
+        table.otadata.size = 0x1000;
+        assert_eq!(
+            table.validate(4096, 4 * 1024 * 1024),
+            Err(PartitionError::Layout)
+        );
+    }
+}
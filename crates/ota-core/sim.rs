@@ -0,0 +1,123 @@
+//! host テスト用のメモリ上のフラッシュ。
+
+use hal_api::error::StorageError;
+use hal_api::storage::BlockStorage;
+
+/// NOR フラッシュの規則を再現する `BlockStorage`。
+///
+/// - 初期状態と消去後は `0xFF`
+/// - 書き込みは既存値との AND (1 → 0 のみ)。消去せずに上書きすると内容が壊れる
+/// - [`SimFlash::tear_next_write`] で次の書き込みを途中で打ち切り、電源断を再現する
+#[derive(Debug, Clone)]
+pub struct SimFlash {
+    data: Vec<u8>,
+    block_size: u32,
+    tear_next_write: Option<usize>,
+    erase_count: u32,
+    write_count: u32,
+}
+
+impl SimFlash {
+    pub fn new(block_size: u32, block_count: u32) -> Self {
+        Self {
+            data: vec![0xFF; block_size as usize * block_count as usize],
+            block_size,
+            tear_next_write: None,
+            erase_count: 0,
+            write_count: 0,
+        }
+    }
+
+    /// 次の書き込みを先頭 `bytes` バイトだけ反映して `WriteFailed` で失敗させる。
+    pub fn tear_next_write(&mut self, bytes: usize) {
+        self.tear_next_write = Some(bytes);
+    }
+
+    /// `offset` の 1 バイトを書き換える (ビット化けの再現)。
+    pub fn corrupt(&mut self, offset: u32, value: u8) {
+        self.data[offset as usize] = value;
+    }
+
+    pub fn contents(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn erase_count(&self) -> u32 {
+        self.erase_count
+    }
+
+    pub fn write_count(&self) -> u32 {
+        self.write_count
+    }
+
+    fn range(&self, offset: u32, len: usize) -> Result<core::ops::Range<usize>, StorageError> {
+        let start = offset as usize;
+        let end = start.checked_add(len).ok_or(StorageError::OutOfBounds)?;
+        if end > self.data.len() {
+            return Err(StorageError::OutOfBounds);
+        }
+        Ok(start..end)
+    }
+}
+
+impl BlockStorage for SimFlash {
+    type Error = StorageError;
+
+    fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u32 {
+        (self.data.len() / self.block_size as usize) as u32
+    }
+
+    fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        let range = self.range(offset, buffer.len())?;
+        buffer.copy_from_slice(&self.data[range]);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
+        let range = self.range(offset, data.len())?;
+        let torn = self.tear_next_write.take();
+        let written = torn.unwrap_or(data.len()).min(data.len());
+        for (cell, byte) in self.data[range].iter_mut().zip(&data[..written]) {
+            *cell &= *byte;
+        }
+        if torn.is_some() {
+            return Err(StorageError::WriteFailed);
+        }
+        self.write_count += 1;
+        Ok(())
+    }
+
+    fn erase_block(&mut self, block: u32) -> Result<(), Self::Error> {
+        if block >= self.block_count() {
+            return Err(StorageError::OutOfBounds);
+        }
+        let start = block as usize * self.block_size as usize;
+        self.data[start..start + self.block_size as usize].fill(0xFF);
+        self.erase_count += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_only_clear_bits_until_erased() {
+        let mut flash = SimFlash::new(16, 2);
+        flash.write(0, &[0x0F]).unwrap();
+        flash.write(0, &[0xF1]).unwrap();
+        let mut byte = [0u8];
+        flash.read(0, &mut byte).unwrap();
+        assert_eq!(byte, [0x01]);
+        flash.erase_block(0).unwrap();
+        flash.read(0, &mut byte).unwrap();
+        assert_eq!(byte, [0xFF]);
+        assert_eq!(flash.erase_block(2), Err(StorageError::OutOfBounds));
+        assert_eq!(flash.read(31, &mut [0; 2]), Err(StorageError::OutOfBounds));
+    }
+}
@@ -0,0 +1,347 @@
+//! 途中再開できるチャンク単位のイメージ書き込み。
+//!
+//! 1. [`UploadSession::begin`] でマニフェストの署名・サイズ・バージョンを検証する
+//! 2. [`UploadSession::write_chunk`] で受信済みバイト数と同じオフセットのチャンクだけを
+//!    受け付け、必要なブロックを消去しながら書き込む。位置がずれていれば
+//!    [`UploadError::OffsetMismatch`] で送り直すべきオフセットを返す
+//! 3. 接続が切れたら [`UploadSession::progress`] を保存しておき、
+//!    [`UploadSession::resume`] で続きから受ける (書きかけだった可能性のあるブロックは
+//!    捨てて、そのブロックの先頭から受け直す)
+//! 4. [`UploadSession::finish`] でスロットから読み戻した SHA-256 をマニフェストと照合する
+
+use hal_api::storage::BlockStorage;
+use sha2::{Digest, Sha256};
+
+use crate::boot::Slot;
+use crate::error::UploadError;
+use crate::manifest::{ImageManifest, PublicKey, MANIFEST_LEN};
+use crate::partition::Partition;
+
+/// [`UploadProgress::to_bytes`] のバイト数。
+pub const PROGRESS_LEN: usize = 1 + 4 + MANIFEST_LEN;
+
+/// 読み戻し検証で一度に読むバイト数。
+const VERIFY_CHUNK: usize = 256;
+
+/// 途中再開に必要な情報。NVS などに保存しておく。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadProgress {
+    pub slot: Slot,
+    pub received: u32,
+    pub manifest: ImageManifest,
+}
+
+impl UploadProgress {
+    pub fn to_bytes(&self) -> [u8; PROGRESS_LEN] {
+        let mut bytes = [0u8; PROGRESS_LEN];
+        bytes[0] = self.slot.index() as u8;
+        bytes[1..5].copy_from_slice(&self.received.to_le_bytes());
+        bytes[5..].copy_from_slice(&self.manifest.to_bytes());
+        bytes
+    }
+
+    /// 壊れていれば `None`。
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != PROGRESS_LEN {
+            return None;
+        }
+        Some(Self {
+            slot: Slot::from_index(bytes[0])?,
+            received: u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]),
+            manifest: ImageManifest::from_bytes(&bytes[5..]).ok()?,
+        })
+    }
+}
+
+/// 検証済みで起動先にできるイメージ。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifiedImage {
+    pub slot: Slot,
+    pub version: u32,
+    pub size: u32,
+}
+
+/// 1 つのスロットへのイメージ書き込み。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadSession {
+    manifest: ImageManifest,
+    slot: Slot,
+    partition: Partition,
+    received: u32,
+    /// スロット先頭からここまでは消去済み (ブロック境界)
+    erased_until: u32,
+}
+
+impl UploadSession {
+    /// マニフェストを検証して書き込みを始める。
+    ///
+    /// `current_version` は稼働中のファームウェアのバージョンで、これ以下のイメージは
+    /// [`UploadError::Downgrade`] で拒否する。
+    pub fn begin<E>(
+        slot: Slot,
+        partition: Partition,
+        manifest: ImageManifest,
+        key: &PublicKey,
+        current_version: u32,
+    ) -> Result<Self, UploadError<E>> {
+        manifest.verify(key)?;
+        if manifest.size > partition.size {
+            return Err(UploadError::TooLarge {
+                size: manifest.size,
+                capacity: partition.size,
+            });
+        }
+        if manifest.version <= current_version {
+            return Err(UploadError::Downgrade {
+                current: current_version,
+                offered: manifest.version,
+            });
+        }
+        Ok(Self {
+            manifest,
+            slot,
+            partition,
+            received: 0,
+            erased_until: 0,
+        })
+    }
+
+    /// 保存しておいた進捗から再開する。受信済みバイト数はブロック境界まで切り下げる。
+    pub fn resume<E>(
+        progress: UploadProgress,
+        partition: Partition,
+        block_size: u32,
+        key: &PublicKey,
+        current_version: u32,
+    ) -> Result<Self, UploadError<E>> {
+        let mut session = Self::begin(
+            progress.slot,
+            partition,
+            progress.manifest,
+            key,
+            current_version,
+        )?;
+        let received = progress.received.min(progress.manifest.size);
+        session.received = received - received % block_size;
+        session.erased_until = session.received;
+        Ok(session)
+    }
+
+    pub fn manifest(&self) -> &ImageManifest {
+        &self.manifest
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    /// 受信済みバイト数 (次に受け付けるチャンクのオフセット)。
+    pub fn received(&self) -> u32 {
+        self.received
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.received == self.manifest.size
+    }
+
+    pub fn progress(&self) -> UploadProgress {
+        UploadProgress {
+            slot: self.slot,
+            received: self.received,
+            manifest: self.manifest,
+        }
+    }
+
+    /// イメージ先頭から `offset` バイト目の `data` を書き込み、受信済みバイト数を返す。
+    pub fn write_chunk<S: BlockStorage>(
+        &mut self,
+        storage: &mut S,
+        offset: u32,
+        data: &[u8],
+    ) -> Result<u32, UploadError<S::Error>> {
+        if offset != self.received {
+            return Err(UploadError::OffsetMismatch {
+                expected: self.received,
+            });
+        }
+        let end = u32::try_from(data.len())
+            .ok()
+            .and_then(|len| offset.checked_add(len))
+            .filter(|end| *end <= self.manifest.size)
+            .ok_or(UploadError::ChunkOutOfBounds)?;
+
+        let block_size = storage.block_size();
+        while self.erased_until < end {
+            let block = (self.partition.offset + self.erased_until) / block_size;
+            storage.erase_block(block).map_err(UploadError::Storage)?;
+            self.erased_until += block_size;
+        }
+        storage
+            .write(self.partition.offset + offset, data)
+            .map_err(UploadError::Storage)?;
+        self.received = end;
+        Ok(end)
+    }
+
+    /// スロットから読み戻して SHA-256 を照合する。
+    pub fn finish<S: BlockStorage>(
+        &self,
+        storage: &mut S,
+    ) -> Result<VerifiedImage, UploadError<S::Error>> {
+        if !self.is_complete() {
+            return Err(UploadError::Incomplete {
+                received: self.received,
+                size: self.manifest.size,
+            });
+        }
+        let mut hasher = Sha256::new();
+        let mut buffer = [0u8; VERIFY_CHUNK];
+        let mut offset = 0u32;
+        while offset < self.manifest.size {
+            let len = (self.manifest.size - offset).min(VERIFY_CHUNK as u32) as usize;
+            storage
+                .read(self.partition.offset + offset, &mut buffer[..len])
+                .map_err(UploadError::Storage)?;
+            hasher.update(&buffer[..len]);
+            offset += len as u32;
+        }
+        if hasher.finalize()[..] != self.manifest.sha256 {
+            return Err(UploadError::HashMismatch);
+        }
+        Ok(VerifiedImage {
+            slot: self.slot,
+            version: self.manifest.version,
+            size: self.manifest.size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::{public_key, SecretKey};
+    use crate::sim::SimFlash;
+    use hal_api::error::StorageError;
+
+    const SECRET: SecretKey = [9; 32];
+    const SLOT: Partition = Partition::new(0x2000, 0x4000);
+
+    fn image(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    fn begin(manifest: ImageManifest) -> Result<UploadSession, UploadError<StorageError>> {
+        UploadSession::begin(Slot::B, SLOT, manifest, &public_key(&SECRET), 1)
+    }
+
+    #[test]
+    fn chunks_must_arrive_in_order_and_image_is_verified_from_flash() {
+        let mut flash = SimFlash::new(0x1000, 8);
+        let data = image(0x2800);
+        let mut session = begin(ImageManifest::sign(&data, 2, &SECRET)).unwrap();
+
+        assert_eq!(session.write_chunk(&mut flash, 0, &data[..1000]), Ok(1000));
+        assert_eq!(
+            session.write_chunk(&mut flash, 0, &data[..1000]),
+            Err(UploadError::OffsetMismatch { expected: 1000 })
+        );
+        assert_eq!(
+            session.finish(&mut flash),
+            Err(UploadError::Incomplete {
+                received: 1000,
+                size: 0x2800
+            })
+        );
+        for chunk in data[1000..].chunks(1500) {
+            let offset = session.received();
+            session.write_chunk(&mut flash, offset, chunk).unwrap();
+        }
+        assert_eq!(
+            session.write_chunk(&mut flash, 0x2800, &[0]),
+            Err(UploadError::ChunkOutOfBounds)
+        );
+        let verified = session.finish(&mut flash).unwrap();
+        assert_eq!(
+            verified,
+            VerifiedImage {
+                slot: Slot::B,
+                version: 2,
+                size: 0x2800
+            }
+        );
+        // 3 ブロック分だけ消去し、スロット外には触れない
+        assert_eq!(flash.erase_count(), 3);
+        assert!(flash.contents()[..0x2000].iter().all(|b| *b == 0xFF));
+    }
+
+    #[test]
+    fn corrupted_flash_contents_fail_the_hash_check() {
+        let mut flash = SimFlash::new(0x1000, 8);
+        let data = image(100);
+        let mut session = begin(ImageManifest::sign(&data, 2, &SECRET)).unwrap();
+        session.write_chunk(&mut flash, 0, &data).unwrap();
+        flash.corrupt(SLOT.offset + 10, 0x00);
+        assert_eq!(session.finish(&mut flash), Err(UploadError::HashMismatch));
+    }
+
+    #[test]
+    fn begin_rejects_bad_signature_oversize_and_downgrade() {
+        let mut forged = ImageManifest::sign(&image(10), 2, &[1; 32]);
+        assert_eq!(
+            begin(forged),
+            Err(UploadError::Manifest(
+                crate::error::ManifestError::BadSignature
+            ))
+        );
+        forged = ImageManifest::sign(&image(0x4001), 2, &SECRET);
+        assert_eq!(
+            begin(forged),
+            Err(UploadError::TooLarge {
+                size: 0x4001,
+                capacity: 0x4000
+            })
+        );
+        assert_eq!(
+            begin(ImageManifest::sign(&image(10), 1, &SECRET)),
+            Err(UploadError::Downgrade {
+                current: 1,
+                offered: 1
+            })
+        );
+    }
+
+    #[test]
+    fn interrupted_upload_resumes_from_the_last_whole_block() {
+        let mut flash = SimFlash::new(0x1000, 8);
+        let data = image(0x3000);
+        let manifest = ImageManifest::sign(&data, 5, &SECRET);
+        let mut session = begin(manifest).unwrap();
+        session.write_chunk(&mut flash, 0, &data[..0x1800]).unwrap();
+        // 次のチャンクの途中で電源断
+        flash.tear_next_write(0x100);
+        assert_eq!(
+            session.write_chunk(&mut flash, 0x1800, &data[0x1800..0x2000]),
+            Err(UploadError::Storage(StorageError::WriteFailed))
+        );
+
+        let saved = UploadProgress::from_bytes(&session.progress().to_bytes()).unwrap();
+        assert_eq!(saved.received, 0x1800);
+        let mut resumed =
+            UploadSession::resume::<StorageError>(saved, SLOT, 0x1000, &public_key(&SECRET), 1)
+                .unwrap();
+        assert_eq!(resumed.received(), 0x1000);
+        for chunk in data[0x1000..].chunks(0x700) {
+            let offset = resumed.received();
+            resumed.write_chunk(&mut flash, offset, chunk).unwrap();
+        }
+        assert_eq!(resumed.finish(&mut flash).unwrap().version, 5);
+    }
+
+    #[test]
+    fn progress_decoding_rejects_garbage() {
+        assert_eq!(UploadProgress::from_bytes(&[0; 3]), None);
+        let mut bytes = [0u8; PROGRESS_LEN];
+        bytes[0] = 7;
+        assert_eq!(UploadProgress::from_bytes(&bytes), None);
+    }
+}
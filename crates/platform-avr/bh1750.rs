@@ -0,0 +1,3 @@
+//! AVR re-export of the board-agnostic BH1750 light sensor driver.
+
+pub use reference_drivers::bh1750::*;
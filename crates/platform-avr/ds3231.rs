@@ -0,0 +1,3 @@
+//! AVR re-export of the board-agnostic DS3231 RTC driver.
+
+pub use reference_drivers::ds3231::*;
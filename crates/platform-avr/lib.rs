@@ -11,8 +11,14 @@
 //! これにより、classic Arduino Nano のような AVR board を追加するときも、
 //! `core-app` 側を変えずに platform 層だけで吸収できます。
 
+pub mod bh1750;
 pub mod bme280;
+pub mod ds3231;
 pub mod gpio;
 pub mod i2c;
 pub mod lcd1602;
+pub mod mpu6050;
+pub mod sgp30;
 pub mod shared_i2c;
+pub mod ssd1306;
+pub mod vl53l0x;
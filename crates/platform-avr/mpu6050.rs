@@ -0,0 +1,3 @@
+//! AVR re-export of the board-agnostic MPU6050 IMU driver.
+
+pub use reference_drivers::mpu6050::*;
@@ -0,0 +1,3 @@
+//! AVR re-export of the board-agnostic SGP30 gas sensor driver.
+
+pub use reference_drivers::sgp30::*;
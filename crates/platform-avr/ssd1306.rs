@@ -0,0 +1,3 @@
+//! AVR re-export of the board-agnostic SSD1306 OLED display driver.
+
+pub use reference_drivers::ssd1306::*;
@@ -0,0 +1,3 @@
+//! AVR re-export of the board-agnostic VL53L0X ToF distance sensor driver.
+
+pub use reference_drivers::vl53l0x::*;
@@ -1,7 +1,9 @@
 //! Host-side BME280 mock device.
 
-use crate::virtual_i2c::VirtualI2cDevice;
+use crate::virtual_i2c::{VirtualI2cBus, VirtualI2cDevice};
 use hal_api::error::I2cError;
+use hal_api::sensor::{EnvReading, EnvSensor};
+use reference_drivers::bme280::{Bme280Sensor, BME280_ADDRESS_PRIMARY};
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::vec::Vec;
@@ -133,6 +135,143 @@ pub fn demo_raw_samples() -> Vec<[u8; 8]> {
     ]
 }
 
+/// Decodes `raw_sample` with the mock's default calibration, exactly as
+/// `Bme280Sensor` would read it over the bus.
+pub fn reading_for_raw_sample(raw_sample: [u8; 8]) -> Option<EnvReading> {
+    let mut probe = RawSampleProbe::new();
+    probe.device.set_raw_sample(raw_sample);
+    probe.sensor.read().ok()
+}
+
+/// Encodes `reading` as the raw ADC burst that decodes closest to it under
+/// the mock's default calibration.
+///
+/// Lets host tools inject a temperature / humidity / pressure without
+/// knowing the compensation formulas: the driver reads the result like any
+/// other sample. `pressure_pascal: None` keeps the default ADC pressure.
+pub fn raw_sample_for_reading(reading: EnvReading) -> [u8; 8] {
+    let mut probe = RawSampleProbe::new();
+    let (_, default_pressure, default_humidity) = unpack_raw_sample(DEFAULT_RAW_SAMPLE);
+
+    let adc_temperature = closest_adc(
+        0,
+        0xF_FFFF,
+        i64::from(reading.temperature_centi_celsius),
+        |adc| {
+            probe
+                .decode(adc, default_pressure, default_humidity)
+                .map(|r| i64::from(r.temperature_centi_celsius))
+        },
+    );
+    let adc_pressure = match reading.pressure_pascal {
+        // Outside this window the integer compensation wraps around, so the
+        // decoded pressure stops being monotonic (~4 kPa .. ~145 kPa).
+        Some(target) => closest_adc(0x1_0000, 0xF_0000, i64::from(target), |adc| {
+            probe
+                .decode(adc_temperature, adc, default_humidity)
+                .and_then(|r| r.pressure_pascal)
+                .map(i64::from)
+        }),
+        None => default_pressure,
+    };
+    let adc_humidity = closest_adc(
+        0,
+        0xFFFF,
+        i64::from(reading.humidity_centi_percent),
+        |adc| {
+            probe
+                .decode(adc_temperature, adc_pressure, adc)
+                .map(|r| i64::from(r.humidity_centi_percent))
+        },
+    );
+
+    pack_raw_sample(adc_temperature, adc_pressure, adc_humidity)
+}
+
+/// Scratch bus + driver used to run the real compensation maths.
+struct RawSampleProbe {
+    device: MockBme280Device,
+    sensor: Bme280Sensor<VirtualI2cBus>,
+}
+
+impl RawSampleProbe {
+    fn new() -> Self {
+        let bus = VirtualI2cBus::new();
+        let device = MockBme280Device::new();
+        bus.attach_device(BME280_ADDRESS_PRIMARY, device.clone());
+        Self {
+            device,
+            sensor: Bme280Sensor::new(bus),
+        }
+    }
+
+    fn decode(&mut self, temperature: u32, pressure: u32, humidity: u32) -> Option<EnvReading> {
+        self.device
+            .set_raw_sample(pack_raw_sample(temperature, pressure, humidity));
+        self.sensor.read().ok()
+    }
+}
+
+/// Binary-searches `lo..=hi` for the ADC value whose decoded value is
+/// closest to `target`. `decode` must be monotonic (either direction); ADC
+/// codes the driver rejects as "no measurement" are skipped.
+fn closest_adc(
+    mut lo: u32,
+    mut hi: u32,
+    target: i64,
+    mut decode: impl FnMut(u32) -> Option<i64>,
+) -> u32 {
+    let mut value_at = |adc: u32| decode(adc).or_else(|| decode(adc + 1));
+    let increasing = value_at(lo) <= value_at(hi);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let below = value_at(mid).map_or(true, |value| {
+            if increasing {
+                value < target
+            } else {
+                value > target
+            }
+        });
+        if below {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    let distance = |adc: u32, value_at: &mut dyn FnMut(u32) -> Option<i64>| {
+        value_at(adc).map_or(i64::MAX, |value| (value - target).abs())
+    };
+    if lo > 0 && distance(lo - 1, &mut value_at) < distance(lo, &mut value_at) {
+        lo - 1
+    } else {
+        lo
+    }
+}
+
+fn unpack_raw_sample(raw: [u8; 8]) -> (u32, u32, u32) {
+    let adc20 = |msb: u8, lsb: u8, xlsb: u8| {
+        (u32::from(msb) << 12) | (u32::from(lsb) << 4) | (u32::from(xlsb) >> 4)
+    };
+    (
+        adc20(raw[3], raw[4], raw[5]),
+        adc20(raw[0], raw[1], raw[2]),
+        (u32::from(raw[6]) << 8) | u32::from(raw[7]),
+    )
+}
+
+fn pack_raw_sample(temperature: u32, pressure: u32, humidity: u32) -> [u8; 8] {
+    [
+        (pressure >> 12) as u8,
+        (pressure >> 4) as u8,
+        ((pressure & 0x0F) << 4) as u8,
+        (temperature >> 12) as u8,
+        (temperature >> 4) as u8,
+        ((temperature & 0x0F) << 4) as u8,
+        (humidity >> 8) as u8,
+        humidity as u8,
+    ]
+}
+
 impl Default for MockBme280Device {
     fn default() -> Self {
         Self::new()
@@ -214,4 +353,27 @@ mod tests {
         assert_eq!(status, [0x08]);
         assert_eq!(sample, [1, 2, 3, 4, 5, 6, 7, 8]);
     }
+
+    #[test]
+    fn raw_sample_for_reading_round_trips_through_the_driver() {
+        for (temperature, humidity, pressure) in [
+            (-4000, 0, 30_000),
+            (2150, 4500, 101_325),
+            (3550, 8000, 99_000),
+            (8500, 10_000, 110_000),
+        ] {
+            let raw =
+                raw_sample_for_reading(EnvReading::new(temperature, humidity, Some(pressure)));
+            let reading = reading_for_raw_sample(raw).unwrap();
+
+            assert!((reading.temperature_centi_celsius - temperature).abs() <= 1);
+            assert!(reading.humidity_centi_percent.abs_diff(humidity) <= 1);
+            assert!(reading.pressure_pascal.unwrap().abs_diff(pressure) <= 1);
+        }
+
+        assert_eq!(
+            unpack_raw_sample(pack_raw_sample(0x7EED0, 0x655AC, 0x8998)),
+            unpack_raw_sample(DEFAULT_RAW_SAMPLE)
+        );
+    }
 }
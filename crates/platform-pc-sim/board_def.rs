@@ -0,0 +1,796 @@
+//! Board definitions loaded from JSON.
+//!
+//! Everything the simulator knows about a board is data: pin names and what
+//! each pin can do, the default wiring, voltage levels and supply rails,
+//! parts soldered onto the board, how firmware is flashed and how the board
+//! is drawn in the wiring diagram. The boards that ship with the simulator
+//! live in `boards/*.json` and are compiled in; other projects add their own
+//! at runtime with [`load_file`], [`load_dir`] or `BOARD_DEFS_DIR=<dir>`
+//! ([`load_from_env`]) without forking this crate.
+//!
+//! ```json
+//! {
+//!   "slug": "xiao-rp2040",
+//!   "aliases": ["seeed-xiao-rp2040"],
+//!   "name": "XIAO RP2040",
+//!   "mcu": "RP2040",
+//!   "mcu_specs": "dual Cortex-M0+ @ 133 MHz, 264 KB SRAM, 2 MB flash",
+//!   "family": "rp2040",
+//!   "electrical": {
+//!     "logic_mv": 3300,
+//!     "io_max_mv": 3600,
+//!     "main_rail": {"name": "3V3", "millivolts": 3300, "max_ma": 500},
+//!     "alt_rail": {"name": "5V", "millivolts": 5000, "max_ma": 500},
+//!     "peripheral_budget_ma": 400
+//!   },
+//!   "defaults": {"sda": "D4", "scl": "D5", "power": "3V3", "servo": "D6"},
+//!   "pins": [
+//!     {"name": "D4", "number": 6, "caps": ["pwm", "i2c"]},
+//!     {"name": "D5", "number": 7, "caps": ["pwm", "i2c"]},
+//!     {"name": "D6", "number": 0, "caps": ["pwm"]}
+//!   ],
+//!   "flash": {"method": "uf2", "target_triple": "thumbv6m-none-eabi"},
+//!   "footprint": {"fill": "#1d3557", "parts": [{"kind": "usb", "x": 40, "y": -5, "w": 24, "h": 10}]}
+//! }
+//! ```
+//!
+//! - `defaults` is the wiring a fresh config starts from. A device whose
+//!   signals have no default pin (`trig`/`echo`, `servo`, `cam`, `motor`) is
+//!   not offered on the board.
+//! - `pins` lists the header pins. `number` is the HAL pin number and defaults
+//!   to the digits the name ends with. `strapping`, `reserved` and
+//!   `input_only` feed the wiring check; a pin that is not listed is reported
+//!   with `off_header` (default "is not on the <name> header").
+//! - `onboard` parts keep their pins out of reach; parts with an `address`
+//!   share the I2C bus on their pins with external devices.
+//! - `footprint` coordinates are relative to the top-left corner of the
+//!   104 × 420 board outline in the wiring diagram.
+//!
+//! Registered definitions live for the rest of the process, so a
+//! [`BoardProfile`] is a `Copy` handle and its accessors return `&'static str`.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::wiring_rules::Severity;
+
+/// Directory of extra board definitions read by [`load_from_env`].
+pub const BOARD_DEFS_ENV: &str = "BOARD_DEFS_DIR";
+
+/// Built-in boards, in the order of the [`BoardProfile`] constants.
+const BUILTIN: [&str; 4] = [
+    include_str!("boards/esp32.json"),
+    include_str!("boards/nano.json"),
+    include_str!("boards/pico.json"),
+    include_str!("boards/m5stickc.json"),
+];
+
+/// Error from reading or registering a board definition.
+#[derive(Debug)]
+pub enum BoardDefError {
+    Io(io::Error),
+    /// Not JSON, or a field is missing or has the wrong type.
+    Json(String),
+    /// Well-formed but inconsistent, or the slug is already taken.
+    Invalid(String),
+}
+
+impl BoardDefError {
+    fn in_file(self, path: &Path) -> Self {
+        match self {
+            BoardDefError::Io(e) => {
+                BoardDefError::Io(io::Error::new(e.kind(), format!("{}: {e}", path.display())))
+            }
+            BoardDefError::Json(e) => BoardDefError::Json(format!("{}: {e}", path.display())),
+            BoardDefError::Invalid(e) => BoardDefError::Invalid(format!("{}: {e}", path.display())),
+        }
+    }
+}
+
+impl fmt::Display for BoardDefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardDefError::Io(e) => write!(f, "{e}"),
+            BoardDefError::Json(e) => write!(f, "invalid board definition JSON: {e}"),
+            BoardDefError::Invalid(e) => write!(f, "invalid board definition: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for BoardDefError {}
+
+impl From<io::Error> for BoardDefError {
+    fn from(e: io::Error) -> Self {
+        BoardDefError::Io(e)
+    }
+}
+
+/// MCU family; selects the HAL the board-setup generator writes for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum McuFamily {
+    Esp32,
+    Avr,
+    Rp2040,
+}
+
+/// Something a pin can do besides plain digital I/O.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PinCap {
+    Pwm,
+    Adc,
+    Dac,
+    I2c,
+    Touch,
+}
+
+impl PinCap {
+    pub fn slug(self) -> &'static str {
+        match self {
+            PinCap::Pwm => "pwm",
+            PinCap::Adc => "adc",
+            PinCap::Dac => "dac",
+            PinCap::I2c => "i2c",
+            PinCap::Touch => "touch",
+        }
+    }
+}
+
+/// Why a pin needs care, as shown by the wiring check.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PinNote {
+    /// Fixed severity. Without it a strapping pin is a warning when a device
+    /// drives it and info when the MCU does; a reserved pin is an error.
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    /// Sentence fragment after the pin name, e.g. "is connected to the SPI flash".
+    pub why: String,
+}
+
+/// One header pin.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PinDef {
+    /// Silkscreen name, e.g. `GPIO21`, `A4` or `GP15`.
+    pub name: String,
+    /// HAL pin number when it differs from the digits the name ends with.
+    #[serde(default)]
+    pub number: Option<u8>,
+    #[serde(default)]
+    pub caps: Vec<PinCap>,
+    /// No output driver (ESP32 GPIO34–39).
+    #[serde(default)]
+    pub input_only: bool,
+    /// Sampled at reset to pick the boot mode.
+    #[serde(default)]
+    pub strapping: Option<PinNote>,
+    /// Taken by something on the board (flash, USB serial, ...).
+    #[serde(default)]
+    pub reserved: Option<PinNote>,
+}
+
+impl PinDef {
+    /// HAL pin number: `number`, or the digits the name ends with.
+    pub fn pin_number(&self) -> Option<u8> {
+        if self.number.is_some() {
+            return self.number;
+        }
+        let digits = self.name.trim_start_matches(|c: char| !c.is_ascii_digit());
+        digits.parse().ok()
+    }
+
+    pub fn has(&self, cap: PinCap) -> bool {
+        self.caps.contains(&cap)
+    }
+}
+
+/// A supply rail on the header.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RailDef {
+    pub name: String,
+    pub millivolts: u32,
+    pub max_ma: u32,
+}
+
+/// Voltage levels and supply limits.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ElectricalDef {
+    pub logic_mv: u32,
+    pub io_max_mv: u32,
+    /// Rail the default `power` pin belongs to.
+    pub main_rail: RailDef,
+    pub alt_rail: RailDef,
+    /// USB current left for peripherals after the MCU's own draw.
+    pub peripheral_budget_ma: u32,
+}
+
+/// L298N control pins.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MotorPins {
+    pub ena: String,
+    pub in1: String,
+    pub in2: String,
+    pub enb: String,
+    pub in3: String,
+    pub in4: String,
+}
+
+/// Default wiring of a fresh config.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DefaultPins {
+    pub sda: String,
+    pub scl: String,
+    pub power: String,
+    #[serde(default)]
+    pub trig: Option<String>,
+    #[serde(default)]
+    pub echo: Option<String>,
+    /// ESP32-CAM boot pin.
+    #[serde(default)]
+    pub cam: Option<String>,
+    #[serde(default)]
+    pub servo: Option<String>,
+    #[serde(default)]
+    pub motor: Option<MotorPins>,
+}
+
+/// A peripheral soldered onto the board itself.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OnboardDevice {
+    pub name: String,
+    pub function: String,
+    /// Address on the board's internal I2C bus.
+    #[serde(default, deserialize_with = "i2c_address")]
+    pub address: Option<u8>,
+    /// Board pins it is wired to.
+    pub pins: Vec<String>,
+}
+
+/// Tool chain that puts firmware on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlashMethod {
+    /// `cargo build` + `espflash flash` over a serial port.
+    Espflash,
+    /// `cargo run` through ravedude; prebuilt ELFs go through avrdude.
+    Ravedude,
+    /// `elf2uf2-rs -d` onto the USB mass-storage bootloader.
+    Uf2,
+}
+
+impl FlashMethod {
+    pub fn slug(self) -> &'static str {
+        match self {
+            FlashMethod::Espflash => "espflash",
+            FlashMethod::Ravedude => "ravedude",
+            FlashMethod::Uf2 => "uf2",
+        }
+    }
+
+    /// UF2 boards show up as a drive instead of a serial port.
+    pub fn needs_port(self) -> bool {
+        self != FlashMethod::Uf2
+    }
+}
+
+/// A firmware crate in this repository that the dashboard can build and flash.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FirmwareDef {
+    pub id: String,
+    pub label: String,
+    /// Crate directory relative to the workspace root.
+    pub dir: String,
+    /// Binary name when it differs from the directory name.
+    #[serde(default)]
+    pub binary: Option<String>,
+}
+
+impl FirmwareDef {
+    pub fn binary(&self) -> &str {
+        self.binary
+            .as_deref()
+            .unwrap_or_else(|| self.dir.rsplit('/').next().unwrap_or(&self.dir))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlashDef {
+    pub method: FlashMethod,
+    pub target_triple: String,
+    /// avrdude `-p` part for flashing prebuilt ELFs (`ravedude` boards).
+    #[serde(default)]
+    pub avrdude_part: Option<String>,
+    #[serde(default)]
+    pub firmware: Vec<FirmwareDef>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartKind {
+    Usb,
+    Chip,
+    Lcd,
+}
+
+/// A decoration drawn on the board outline.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FootprintPart {
+    pub kind: PartKind,
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// How the board is drawn in the wiring diagram.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Footprint {
+    /// PCB colours as `#rgb` / `#rrggbb`; the default is the green DevKit look.
+    #[serde(default)]
+    pub fill: Option<String>,
+    #[serde(default)]
+    pub stroke: Option<String>,
+    #[serde(default)]
+    pub parts: Vec<FootprintPart>,
+}
+
+/// One board description file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardDef {
+    /// Name in project files, `/api/wiring` and on the command line.
+    pub slug: String,
+    /// Other names accepted wherever the slug is.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub name: String,
+    /// Part value in netlists and the BOM; defaults to `name`.
+    #[serde(default)]
+    pub part: Option<String>,
+    pub mcu: String,
+    /// Core, clock and memory of the MCU.
+    pub mcu_specs: String,
+    pub family: McuFamily,
+    pub electrical: ElectricalDef,
+    pub defaults: DefaultPins,
+    pub pins: Vec<PinDef>,
+    /// Why a pin that is not in `pins` cannot be used.
+    #[serde(default)]
+    pub off_header: Option<String>,
+    #[serde(default)]
+    pub onboard: Vec<OnboardDevice>,
+    #[serde(default)]
+    pub flash: Option<FlashDef>,
+    #[serde(default)]
+    pub footprint: Footprint,
+}
+
+impl BoardDef {
+    /// Parse and validate a definition.
+    pub fn parse(text: &str) -> Result<Self, BoardDefError> {
+        let def: Self =
+            serde_json::from_str(text).map_err(|e| BoardDefError::Json(e.to_string()))?;
+        def.validate()?;
+        Ok(def)
+    }
+
+    pub fn pin(&self, name: &str) -> Option<&PinDef> {
+        self.pins.iter().find(|pin| pin.name == name)
+    }
+
+    /// Slug and aliases.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.slug.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+
+    fn validate(&self) -> Result<(), BoardDefError> {
+        let invalid = |message: String| Err(BoardDefError::Invalid(message));
+        for name in self.names() {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            {
+                return invalid(format!(
+                    "slug {name:?} must be lowercase letters, digits and '-'"
+                ));
+            }
+        }
+        for (i, pin) in self.pins.iter().enumerate() {
+            if self.pins[..i].iter().any(|other| other.name == pin.name) {
+                return invalid(format!("pin {} is listed twice", pin.name));
+            }
+        }
+        let defaults = &self.defaults;
+        let mut required = vec![
+            (defaults.sda.as_str(), Some(PinCap::I2c)),
+            (defaults.scl.as_str(), Some(PinCap::I2c)),
+        ];
+        for pin in [&defaults.trig, &defaults.echo, &defaults.cam]
+            .into_iter()
+            .flatten()
+        {
+            required.push((pin, None));
+        }
+        if let Some(servo) = &defaults.servo {
+            required.push((servo, Some(PinCap::Pwm)));
+        }
+        if let Some(motor) = &defaults.motor {
+            required.push((&motor.ena, Some(PinCap::Pwm)));
+            required.push((&motor.enb, Some(PinCap::Pwm)));
+            for pin in [&motor.in1, &motor.in2, &motor.in3, &motor.in4] {
+                required.push((pin, None));
+            }
+        }
+        for (name, cap) in required {
+            let Some(pin) = self.pin(name) else {
+                return invalid(format!("default pin {name} is not in \"pins\""));
+            };
+            if let Some(cap) = cap.filter(|&cap| !pin.has(cap)) {
+                return invalid(format!("default pin {name} has no {} cap", cap.slug()));
+            }
+        }
+        if defaults.trig.is_some() != defaults.echo.is_some() {
+            return invalid("\"trig\" and \"echo\" go together".to_string());
+        }
+        for colour in [&self.footprint.fill, &self.footprint.stroke]
+            .into_iter()
+            .flatten()
+        {
+            let hex = colour.strip_prefix('#').unwrap_or("");
+            if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return invalid(format!(
+                    "footprint colour {colour:?} is not #rgb or #rrggbb"
+                ));
+            }
+        }
+        if let Some(flash) = &self.flash {
+            if flash.method == FlashMethod::Ravedude && flash.avrdude_part.is_none() {
+                return invalid("ravedude boards need \"avrdude_part\"".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn i2c_address<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u8>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    let address = match text.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    };
+    address
+        .filter(|address| (0x08..=0x77).contains(address))
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("bad I2C address {text:?}")))
+}
+
+fn registry() -> &'static RwLock<Vec<&'static BoardDef>> {
+    static REGISTRY: OnceLock<RwLock<Vec<&'static BoardDef>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let builtin = BUILTIN
+            .iter()
+            .map(|text| {
+                let def = BoardDef::parse(text).expect("built-in board definitions are valid");
+                &*Box::leak(Box::new(def))
+            })
+            .collect();
+        RwLock::new(builtin)
+    })
+}
+
+/// Add a board. Fails when its slug or an alias names a known board.
+pub fn register(def: BoardDef) -> Result<BoardProfile, BoardDefError> {
+    let mut boards = registry().write().unwrap_or_else(PoisonError::into_inner);
+    if let Some(name) = def.names().find(|name| {
+        boards
+            .iter()
+            .any(|board| board.names().any(|known| known == *name))
+    }) {
+        return Err(BoardDefError::Invalid(format!(
+            "board {name:?} is already defined"
+        )));
+    }
+    boards.push(Box::leak(Box::new(def)));
+    Ok(BoardProfile(boards.len() - 1))
+}
+
+/// Register the board described by a JSON file.
+pub fn load_file(path: &Path) -> Result<BoardProfile, BoardDefError> {
+    let text = fs::read_to_string(path).map_err(|e| BoardDefError::from(e).in_file(path))?;
+    BoardDef::parse(&text)
+        .and_then(register)
+        .map_err(|e| e.in_file(path))
+}
+
+/// Register every `*.json` file in `dir`, in file name order.
+pub fn load_dir(dir: &Path) -> Result<Vec<BoardProfile>, BoardDefError> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| BoardDefError::from(e).in_file(dir))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    paths.iter().map(|path| load_file(path)).collect()
+}
+
+/// Register the boards in `$BOARD_DEFS_DIR`, if it is set.
+pub fn load_from_env() -> Result<Vec<BoardProfile>, BoardDefError> {
+    match std::env::var_os(BOARD_DEFS_ENV) {
+        Some(dir) if !dir.is_empty() => load_dir(Path::new(&dir)),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// A registered board: a `Copy` handle onto its [`BoardDef`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BoardProfile(usize);
+
+impl BoardProfile {
+    pub const ORIGINAL_ESP32: Self = Self(0);
+    pub const ARDUINO_NANO: Self = Self(1);
+    /// Raspberry Pi Pico; pins are named `GP0`..`GP28` as on the silkscreen.
+    pub const RASPBERRY_PI_PICO: Self = Self(2);
+    /// M5StickC; external devices go on the Grove port (G32/G33) and the
+    /// HAT header (G0/G26/G36).
+    pub const M5STICKC: Self = Self(3);
+
+    pub fn def(self) -> &'static BoardDef {
+        registry().read().unwrap_or_else(PoisonError::into_inner)[self.0]
+    }
+
+    /// Board whose slug or alias is `name`.
+    pub fn from_slug(name: &str) -> Option<Self> {
+        let boards = registry().read().unwrap_or_else(PoisonError::into_inner);
+        boards
+            .iter()
+            .position(|board| board.names().any(|known| known == name))
+            .map(Self)
+    }
+
+    /// Board named on the command line; unknown or missing names give the
+    /// original ESP32.
+    pub fn from_arg(value: Option<&str>) -> Self {
+        value
+            .and_then(Self::from_slug)
+            .unwrap_or(Self::ORIGINAL_ESP32)
+    }
+
+    /// Every registered board, built-in ones first.
+    pub fn all() -> Vec<BoardProfile> {
+        let count = registry()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len();
+        (0..count).map(Self).collect()
+    }
+
+    pub fn slug(self) -> &'static str {
+        &self.def().slug
+    }
+
+    pub fn name(self) -> &'static str {
+        &self.def().name
+    }
+
+    pub fn mcu(self) -> &'static str {
+        &self.def().mcu
+    }
+
+    /// Core, clock and memory of the MCU.
+    pub fn mcu_specs(self) -> &'static str {
+        &self.def().mcu_specs
+    }
+
+    pub fn family(self) -> McuFamily {
+        self.def().family
+    }
+
+    /// Peripherals on the board itself, which the wiring rules keep clear of.
+    pub fn onboard_devices(self) -> &'static [OnboardDevice] {
+        &self.def().onboard
+    }
+
+    /// Header pin named `name`.
+    pub fn pin(self, name: &str) -> Option<&'static PinDef> {
+        self.def().pin(name)
+    }
+
+    pub fn flash(self) -> Option<&'static FlashDef> {
+        self.def().flash.as_ref()
+    }
+
+    pub fn sda_pin(self) -> &'static str {
+        &self.def().defaults.sda
+    }
+
+    pub fn scl_pin(self) -> &'static str {
+        &self.def().defaults.scl
+    }
+
+    pub fn power_pin(self) -> &'static str {
+        &self.def().defaults.power
+    }
+
+    pub fn trig_pin(self) -> &'static str {
+        or_na(&self.def().defaults.trig)
+    }
+
+    pub fn echo_pin(self) -> &'static str {
+        or_na(&self.def().defaults.echo)
+    }
+
+    pub fn servo_pwm_pin(self) -> &'static str {
+        or_na(&self.def().defaults.servo)
+    }
+
+    pub fn motor_ena_pin(self) -> &'static str {
+        self.motor_pin(|motor| &motor.ena)
+    }
+
+    pub fn motor_in1_pin(self) -> &'static str {
+        self.motor_pin(|motor| &motor.in1)
+    }
+
+    pub fn motor_in2_pin(self) -> &'static str {
+        self.motor_pin(|motor| &motor.in2)
+    }
+
+    pub fn motor_enb_pin(self) -> &'static str {
+        self.motor_pin(|motor| &motor.enb)
+    }
+
+    pub fn motor_in3_pin(self) -> &'static str {
+        self.motor_pin(|motor| &motor.in3)
+    }
+
+    pub fn motor_in4_pin(self) -> &'static str {
+        self.motor_pin(|motor| &motor.in4)
+    }
+
+    /// GPIO pin used as boot/programming pin for the camera module.
+    pub fn cam_pin(self) -> &'static str {
+        or_na(&self.def().defaults.cam)
+    }
+
+    fn motor_pin(self, pick: fn(&MotorPins) -> &String) -> &'static str {
+        self.def()
+            .defaults
+            .motor
+            .as_ref()
+            .map_or("N/A", |motor| pick(motor).as_str())
+    }
+}
+
+impl fmt::Debug for BoardProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BoardProfile({})", self.slug())
+    }
+}
+
+fn or_na(pin: &'static Option<String>) -> &'static str {
+    pin.as_deref().unwrap_or("N/A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wiring_config::WiringConfig;
+    use crate::wiring_rules::{self, Rule};
+
+    #[test]
+    fn builtin_boards_match_their_constants() {
+        let slugs: Vec<&str> = [
+            BoardProfile::ORIGINAL_ESP32,
+            BoardProfile::ARDUINO_NANO,
+            BoardProfile::RASPBERRY_PI_PICO,
+            BoardProfile::M5STICKC,
+        ]
+        .iter()
+        .map(|board| board.slug())
+        .collect();
+        assert_eq!(slugs, ["esp32", "nano", "pico", "m5stickc"]);
+        assert_eq!(
+            BoardProfile::from_slug("arduino-nano"),
+            Some(BoardProfile::ARDUINO_NANO)
+        );
+        assert_eq!(
+            BoardProfile::from_arg(Some("uno")),
+            BoardProfile::ORIGINAL_ESP32
+        );
+        assert_eq!(
+            format!("{:?}", BoardProfile::M5STICKC),
+            "BoardProfile(m5stickc)"
+        );
+    }
+
+    #[test]
+    fn pins_carry_numbers_and_capabilities() {
+        let nano = BoardProfile::ARDUINO_NANO;
+        let a4 = nano.pin("A4").expect("A4");
+        assert_eq!(a4.pin_number(), Some(18));
+        assert!(a4.has(PinCap::I2c));
+        assert!(!nano.pin("D2").expect("D2").has(PinCap::Pwm));
+        assert!(nano.pin("GPIO2").is_none());
+
+        let esp = BoardProfile::ORIGINAL_ESP32;
+        assert_eq!(esp.pin("GPIO13").and_then(PinDef::pin_number), Some(13));
+        assert!(esp.pin("GPIO34").expect("GPIO34").input_only);
+        assert_eq!(
+            esp.flash().map(|flash| flash.method),
+            Some(FlashMethod::Espflash)
+        );
+        assert_eq!(
+            esp.flash().expect("flash").firmware[0].binary(),
+            "original-esp32-climate-display"
+        );
+    }
+
+    #[test]
+    fn example_board_loads_from_a_file() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/boards/xiao-rp2040.json");
+        let board = load_file(&path).expect("example board");
+        assert_eq!(BoardProfile::from_slug("seeed-xiao-rp2040"), Some(board));
+        assert!(BoardProfile::all().contains(&board));
+        assert_eq!(board.sda_pin(), "D4");
+        assert_eq!(board.pin("D4").and_then(PinDef::pin_number), Some(6));
+
+        let mut config = WiringConfig::from_board(board);
+        config.sda_pin = "GP6".to_string();
+        let report = wiring_rules::check(&config);
+        let off_header = report.by_rule(Rule::ReservedPin).next().expect("GP6");
+        assert_eq!(
+            off_header.message,
+            "GP6 (I2C SDA) is not on the XIAO RP2040 header (D0-D10)"
+        );
+
+        let again = load_file(&path).unwrap_err().to_string();
+        assert!(again.contains("already defined"), "{again}");
+        assert!(again.contains("xiao-rp2040.json"), "{again}");
+    }
+
+    #[test]
+    fn rejects_inconsistent_definitions() {
+        let base = r#"{"slug":"test","name":"T","mcu":"M","mcu_specs":"S","family":"esp32",
+            "electrical":{"logic_mv":3300,"io_max_mv":3600,
+                "main_rail":{"name":"3V3","millivolts":3300,"max_ma":100},
+                "alt_rail":{"name":"5V","millivolts":5000,"max_ma":100},
+                "peripheral_budget_ma":100},
+            "defaults":{"sda":"IO1","scl":"IO2","power":"3V3"EXTRA},
+            "pins":[{"name":"IO1","caps":["i2c"]},{"name":"IO2","caps":["i2c"]},{"name":"IO3"}]}"#;
+        let parse = |extra: &str| BoardDef::parse(&base.replace("EXTRA", extra));
+        assert!(parse("").is_ok());
+        let error = |extra: &str| parse(extra).unwrap_err().to_string();
+        assert!(error(r#","servo":"IO9""#).contains("IO9 is not in"));
+        assert!(error(r#","servo":"IO3""#).contains("no pwm cap"));
+        assert!(error(r#","trig":"IO3""#).contains("go together"));
+        assert!(error(r#","speed":1"#).contains("unknown field"));
+        assert!(BoardDef::parse(
+            &base
+                .replace("\"test\"", "\"Test Board\"")
+                .replace("EXTRA", "")
+        )
+        .unwrap_err()
+        .to_string()
+        .contains("lowercase"));
+    }
+}
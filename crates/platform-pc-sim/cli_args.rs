@@ -0,0 +1,117 @@
+//! host 側 CLI (`wiring-check` / `ota-upload`) の引数パーサ。
+//!
+//! 位置引数と `--name value` 形式のオプションだけを扱う。呼び出し側が知っている
+//! オプション名を渡し、それ以外 (綴り間違いを含む) はエラーにする。CI で
+//! `--fail_on` のような誤記が黙って既定値に落ちるのを防ぐため。
+
+/// 位置引数と `--name value` オプション。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    /// `raw` を分解する。`known` に無いオプション名や値の無いオプションは
+    /// `usage` を添えたメッセージで失敗する。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use platform_pc_sim::cli_args::Args;
+    ///
+    /// let raw = ["esp32", "--format", "json"].map(String::from);
+    /// let args = Args::parse(raw.into_iter(), &["format"], "usage: ...").unwrap();
+    /// assert_eq!(args.positional(0), Some("esp32"));
+    /// assert_eq!(args.option("format"), Some("json"));
+    ///
+    /// let raw = ["--board", "m5stickc"].map(String::from);
+    /// let error = Args::parse(raw.into_iter(), &["format"], "usage: ...").unwrap_err();
+    /// assert_eq!(error, "unknown option --board\nusage: ...");
+    /// ```
+    pub fn parse(
+        raw: impl Iterator<Item = String>,
+        known: &[&str],
+        usage: &str,
+    ) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        let mut raw = raw.peekable();
+        while let Some(arg) = raw.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    if !known.contains(&name) {
+                        return Err(format!("unknown option --{name}\n{usage}"));
+                    }
+                    let value = raw.next().ok_or(format!("--{name} needs a value"))?;
+                    options.push((name.to_string(), value));
+                }
+                None => positional.push(arg),
+            }
+        }
+        Ok(Self {
+            positional,
+            options,
+        })
+    }
+
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+
+    /// 最初に指定された値。
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// 繰り返し指定できるオプション (`--pin a --pin b`) の値を指定順に返す。
+    pub fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.options
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(raw: &[&str]) -> Result<Args, String> {
+        Args::parse(
+            raw.iter().map(|arg| arg.to_string()),
+            &["pin", "fail-on"],
+            "usage",
+        )
+    }
+
+    #[test]
+    fn splits_positionals_and_repeated_options() {
+        let args = parse(&["nano", "--pin", "servo=D1", "x", "--pin", "trig=D2"]).unwrap();
+        assert_eq!(args.positional(0), Some("nano"));
+        assert_eq!(args.positional(1), Some("x"));
+        assert_eq!(args.positional(2), None);
+        assert_eq!(args.option("pin"), Some("servo=D1"));
+        assert_eq!(args.all("pin").collect::<Vec<_>>(), ["servo=D1", "trig=D2"]);
+        assert_eq!(args.option("fail-on"), None);
+    }
+
+    #[test]
+    fn rejects_unknown_and_valueless_options() {
+        assert_eq!(
+            parse(&["--fail_on", "warning"]).unwrap_err(),
+            "unknown option --fail_on\nusage"
+        );
+        assert_eq!(
+            parse(&["esp32", "--board", "m5stickc"]).unwrap_err(),
+            "unknown option --board\nusage"
+        );
+        assert_eq!(
+            parse(&["--fail-on"]).unwrap_err(),
+            "--fail-on needs a value"
+        );
+    }
+}
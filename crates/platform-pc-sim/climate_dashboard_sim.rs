@@ -1,14 +1,23 @@
 //! Rich terminal dashboard for the reference climate path.
+//!
+//! `climate-dashboard-sim [esp32|nano]` or
+//! `climate-dashboard-sim --wiring <project.json>`; with a wiring project the
+//! board and the BME280 / LCD1602 addresses come from the project file.
 
 use core_app::climate_display::{ClimateDisplayApp, ClimateDisplayConfig};
 use embedded_hal::delay::DelayNs;
 use platform_pc_sim::bme280_mock::{demo_raw_samples, MockBme280Device};
+use platform_pc_sim::board_def;
 use platform_pc_sim::dashboard::{render_dashboard, BoardProfile, DashboardSnapshot};
 use platform_pc_sim::lcd1602_mock::MockLcd1602Device;
 use platform_pc_sim::virtual_i2c::VirtualI2cBus;
+use platform_pc_sim::wiring_config::DeviceKind;
+use platform_pc_sim::wiring_project::WiringProject;
 use reference_drivers::bme280::{Bme280Sensor, BME280_ADDRESS_PRIMARY};
 use reference_drivers::lcd1602::{Lcd1602Display, LCD1602_ADDRESS_PRIMARY};
 use std::env;
+use std::path::Path;
+use std::process;
 use std::thread;
 use std::time::Duration;
 
@@ -24,16 +33,49 @@ impl DelayNs for NoopDelay {
     fn delay_ms(&mut self, _ms: u32) {}
 }
 
+/// Board plus BME280 / LCD1602 addresses, from the arguments or a wiring project.
+fn board_and_addresses() -> Result<(BoardProfile, u8, u8), String> {
+    board_def::load_from_env().map_err(|e| format!("{}: {e}", board_def::BOARD_DEFS_ENV))?;
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(index) = args.iter().position(|arg| arg == "--wiring") else {
+        let board = BoardProfile::from_arg(args.first().map(String::as_str));
+        return Ok((board, BME280_ADDRESS_PRIMARY, LCD1602_ADDRESS_PRIMARY));
+    };
+    let path = args.get(index + 1).ok_or("--wiring needs a project file")?;
+    let config = WiringProject::load(Path::new(path))
+        .and_then(|project| project.to_config())
+        .map_err(|e| format!("{path}: {e}"))?;
+    let address = |kind: DeviceKind| {
+        config
+            .devices
+            .iter()
+            .find(|device| device.kind == kind)
+            .and_then(|device| device.address)
+            .ok_or(format!(
+                "{path}: the climate dashboard needs a {}",
+                kind.label()
+            ))
+    };
+    Ok((
+        config.board,
+        address(DeviceKind::Bme280)?,
+        address(DeviceKind::Lcd1602)?,
+    ))
+}
+
 fn main() {
-    let board = BoardProfile::from_arg(env::args().nth(1).as_deref());
+    let (board, bme280_address, lcd_address) = board_and_addresses().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(2);
+    });
     let bus = VirtualI2cBus::new();
     let bme280 = MockBme280Device::new();
     let lcd = MockLcd1602Device::new();
-    bus.attach_device(BME280_ADDRESS_PRIMARY, bme280.clone());
-    bus.attach_device(LCD1602_ADDRESS_PRIMARY, lcd.clone());
+    bus.attach_device(bme280_address, bme280.clone());
+    bus.attach_device(lcd_address, lcd.clone());
 
-    let sensor = Bme280Sensor::new(bus.clone());
-    let display = Lcd1602Display::new(bus.clone(), NoopDelay);
+    let sensor = Bme280Sensor::new_with_address(bus.clone(), bme280_address);
+    let display = Lcd1602Display::new_with_address(bus.clone(), NoopDelay, lcd_address);
     let app_config = ClimateDisplayConfig {
         refresh_period_ticks: REFRESH_PERIOD_TICKS,
         refresh_on_first_tick: true,
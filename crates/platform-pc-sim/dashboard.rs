@@ -9,126 +9,7 @@ use std::fmt::Write as _;
 use std::string::String;
 use std::vec::Vec;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum BoardProfile {
-    OriginalEsp32,
-    ArduinoNano,
-}
-
-impl BoardProfile {
-    pub fn from_arg(value: Option<&str>) -> Self {
-        match value {
-            Some("nano") | Some("arduino-nano") => Self::ArduinoNano,
-            _ => Self::OriginalEsp32,
-        }
-    }
-
-    pub fn name(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "original ESP32",
-            Self::ArduinoNano => "Arduino Nano",
-        }
-    }
-
-    pub fn mcu(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "ESP32",
-            Self::ArduinoNano => "ATmega328P",
-        }
-    }
-
-    pub fn sda_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO21",
-            Self::ArduinoNano => "A4",
-        }
-    }
-
-    pub fn scl_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO22",
-            Self::ArduinoNano => "A5",
-        }
-    }
-
-    pub fn power_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "3V3",
-            Self::ArduinoNano => "5V",
-        }
-    }
-
-    pub fn trig_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO5",
-            Self::ArduinoNano => "D2",
-        }
-    }
-
-    pub fn echo_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO18",
-            Self::ArduinoNano => "D3",
-        }
-    }
-
-    pub fn servo_pwm_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO13",
-            Self::ArduinoNano => "D9",
-        }
-    }
-
-    pub fn motor_ena_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO25",
-            Self::ArduinoNano => "D5",
-        }
-    }
-
-    pub fn motor_in1_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO26",
-            Self::ArduinoNano => "D6",
-        }
-    }
-
-    pub fn motor_in2_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO27",
-            Self::ArduinoNano => "D7",
-        }
-    }
-
-    pub fn motor_enb_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO32",
-            Self::ArduinoNano => "D10",
-        }
-    }
-
-    pub fn motor_in3_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO33",
-            Self::ArduinoNano => "D8",
-        }
-    }
-
-    pub fn motor_in4_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO14",
-            Self::ArduinoNano => "D11",
-        }
-    }
-
-    /// GPIO pin used as boot/programming pin for the camera module.
-    pub fn cam_pin(self) -> &'static str {
-        match self {
-            Self::OriginalEsp32 => "GPIO0",
-            Self::ArduinoNano => "N/A",
-        }
-    }
-}
+pub use crate::board_def::{BoardProfile, OnboardDevice};
 
 pub struct DashboardSnapshot<'a> {
     pub board: BoardProfile,
@@ -157,6 +38,17 @@ pub fn render_dashboard(snapshot: &DashboardSnapshot<'_>) -> String {
         snapshot.tick,
         snapshot.refresh_period_ticks
     );
+    let onboard = snapshot.board.onboard_devices();
+    if !onboard.is_empty() {
+        let parts: Vec<String> = onboard
+            .iter()
+            .map(|part| match part.address {
+                Some(address) => format!("{} (0x{address:02X})", part.name),
+                None => part.name.to_string(),
+            })
+            .collect();
+        let _ = writeln!(output, "Onboard: {}", parts.join(", "));
+    }
     let _ = writeln!(output);
     let _ = writeln!(output, "Wiring");
     let _ = writeln!(
@@ -290,7 +182,7 @@ mod tests {
     #[test]
     fn dashboard_renders_board_profile_and_devices() {
         let snapshot = DashboardSnapshot {
-            board: BoardProfile::ArduinoNano,
+            board: BoardProfile::ARDUINO_NANO,
             tick: 5,
             refresh_period_ticks: 5,
             reading: Some(EnvReading::new(2480, 4310, Some(101_325))),
@@ -318,5 +210,46 @@ mod tests {
         assert!(output.contains("A4"));
         assert!(output.contains("0x27, 0x77"));
         assert!(output.contains("WRITE_READ addr=0x77"));
+        assert!(!output.contains("Onboard:"));
+    }
+
+    #[test]
+    fn new_board_profiles_have_pinouts_and_onboard_parts() {
+        assert_eq!(
+            BoardProfile::from_arg(Some("raspi-pico")),
+            BoardProfile::RASPBERRY_PI_PICO
+        );
+        assert_eq!(
+            BoardProfile::from_arg(Some("m5stickc")),
+            BoardProfile::M5STICKC
+        );
+        // Boards registered at runtime come after the built-in four.
+        assert_eq!(
+            BoardProfile::all()[..4],
+            [
+                BoardProfile::ORIGINAL_ESP32,
+                BoardProfile::ARDUINO_NANO,
+                BoardProfile::RASPBERRY_PI_PICO,
+                BoardProfile::M5STICKC,
+            ]
+        );
+
+        let stick = BoardProfile::M5STICKC;
+        assert_eq!(stick.mcu(), "ESP32-PICO-D4");
+        assert_eq!(stick.sda_pin(), "GPIO32");
+        let i2c: Vec<(&str, u8)> = stick
+            .onboard_devices()
+            .iter()
+            .filter_map(|part| Some((part.name.as_str(), part.address?)))
+            .collect();
+        assert_eq!(i2c, [("MPU6886", 0x68), ("AXP192", 0x34), ("BM8563", 0x51)]);
+
+        let pico = BoardProfile::RASPBERRY_PI_PICO;
+        assert_eq!(pico.mcu(), "RP2040");
+        assert!(pico.mcu_specs().contains("Cortex-M0+"));
+        assert!(pico
+            .onboard_devices()
+            .iter()
+            .any(|part| part.name == "LED" && part.pins == ["GP25"]));
     }
 }
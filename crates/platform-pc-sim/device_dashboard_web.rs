@@ -1,43 +1,105 @@
+#[path = "device_dashboard_web/control_api.rs"]
+mod control_api;
 #[path = "device_dashboard_web/flash.rs"]
 mod flash;
+#[path = "device_dashboard_web/history.rs"]
+mod history;
 #[path = "device_dashboard_web/http_util.rs"]
 mod http_util;
+#[path = "device_dashboard_web/metrics.rs"]
+mod metrics;
+#[path = "device_dashboard_web/session.rs"]
+mod session;
 #[path = "device_dashboard_web/sim_rig.rs"]
 mod sim_rig;
+#[path = "device_dashboard_web/ws.rs"]
+mod ws;
 
 use std::collections::VecDeque;
 use std::env;
+use std::fs;
 use std::io::{Read as _, Write as _};
 use std::net::{TcpListener, TcpStream};
-use std::sync::{mpsc, Arc, Mutex};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc};
 use std::thread;
 use std::time::Duration;
 
+use core_app::metrics::{CONTENT_TYPE as METRICS_CONTENT_TYPE, DEFAULT_NAMESPACE};
+use core_app::telemetry::TelemetryFormat;
+use platform_pc_sim::board_def;
 use platform_pc_sim::dashboard::BoardProfile;
-use platform_pc_sim::web_dashboard::{dashboard_html, state_to_json};
+use platform_pc_sim::history_store::{
+    parse_duration_ms, HistoryRecord, HistoryStore, RetentionPolicy,
+};
+use platform_pc_sim::ingest_store::CsvReadingStore;
+use platform_pc_sim::sim_control::Scenario;
+use platform_pc_sim::web_dashboard::dashboard_html;
 use platform_pc_sim::wiring_config::{
     normalize_supported_device_selection, DeviceKind, SensorProfile, WiringConfig,
 };
+use platform_pc_sim::wiring_export::{self, ExportFormat};
+use platform_pc_sim::wiring_project::WiringProject;
+use platform_pc_sim::wiring_rules::{self, Severity};
 use platform_pc_sim::wiring_svg::wiring_svg;
 
-use flash::{flash_targets, handle_flash_stream, list_serial_ports};
+use control_api::handle_control;
+use flash::{flash_targets_json, handle_flash_stream, list_serial_ports};
+use history::{handle_history_export, handle_history_query, is_store_query};
 use http_util::{
     parse_board_from_json, parse_json_bool_field, parse_json_string_array_field,
-    parse_sensor_profile_from_json, respond,
+    parse_sensor_profile_from_json, read_remaining_body, respond, respond_attachment,
+    respond_bytes,
+};
+use session::{
+    handle_sessions, parse_session_specs, respond_error as respond_session_error,
+    split_session_path, Session, SessionError, SessionRegistry, DEFAULT_SESSION,
 };
-use sim_rig::DeviceSimulationRig;
+use ws::handle_websocket;
 
 // Items only needed in the test module — pulled into test scope via `use super::*`.
 #[cfg(test)]
-use flash::{board_kind_from_str, detect_binary_name, detect_build_target, BoardKind};
+use core_app::telemetry::TelemetryFrame;
 #[cfg(test)]
-use hal_api::actuator::MotorDirection;
+use flash::{detect_binary_name, detect_build_target, flash_targets};
+#[cfg(test)]
+use hal_api::actuator::{MotorCommand, MotorDirection};
 #[cfg(test)]
 use http_util::parse_json_string_field;
 #[cfg(test)]
-use sim_rig::{blank_lines, distance_to_servo_angle, motor_commands_from_state};
+use metrics::{MetricsSnapshot, TickRate};
+#[cfg(test)]
+use platform_pc_sim::board_def::FlashMethod;
+#[cfg(test)]
+use platform_pc_sim::web_dashboard::{state_to_json, PanelTracker, PANELS};
+#[cfg(test)]
+use sim_rig::{
+    blank_lines, distance_to_servo_angle, motor_commands_from_state, DeviceSimulationRig,
+};
+#[cfg(test)]
+use std::time::Instant;
 
 const DEFAULT_PORT: u16 = 7878;
+/// InfluxDB measurement name used by `/api/telemetry?format=line`.
+const TELEMETRY_MEASUREMENT: &str = "pc_sim";
+/// Environment variable pointing at the `ingest-server` CSV store served by
+/// `/api/history?source=ingest`.
+const INGEST_STORE_ENV: &str = "INGEST_STORE";
+/// Readings returned by `/api/history?source=ingest` (same as the ring buffer).
+const HISTORY_CAPACITY: usize = 300;
+/// Environment variable naming the directory of the persistent history store.
+const HISTORY_DIR_ENV: &str = "HISTORY_DIR";
+const DEFAULT_HISTORY_DIR: &str = "dashboard-history";
+/// Environment variable overriding the store's retention age (`24h`, `7d`, ...).
+const HISTORY_RETENTION_ENV: &str = "HISTORY_RETENTION";
+/// Interval between persisted history records (every push is ~100 ms).
+const HISTORY_RECORD_INTERVAL_MS: u64 = 1_000;
+/// Environment variable naming a scenario file replayed from startup.
+const SCENARIO_ENV: &str = "DASHBOARD_SCENARIO";
+/// Extra sessions started with the server: `nano=arduino-nano,node-2=esp32`.
+const SESSIONS_ENV: &str = "DASHBOARD_SESSIONS";
+/// Upper bound for a request (headers + body), e.g. an uploaded scenario.
+const MAX_REQUEST_LEN: usize = 256 * 1024;
 
 /// Combined board + sensor profile state read/written as a unit.
 #[derive(Clone)]
@@ -46,16 +108,22 @@ struct WiringState {
     sensor_profile: SensorProfile,
     selected_devices: Vec<DeviceKind>,
     show_bus_labels: bool,
+    /// Pins, addresses and labels from a loaded wiring project; dropped when
+    /// the board changes.
+    layout: Option<WiringConfig>,
 }
 
-fn dashboard_wiring_config(
-    board: BoardProfile,
-    sensor_profile: SensorProfile,
-    selected_devices: &[DeviceKind],
-    show_bus_labels: bool,
-) -> WiringConfig {
-    WiringConfig::from_board_with_selected_devices(board, sensor_profile, selected_devices)
-        .with_bus_labels(show_bus_labels)
+fn dashboard_wiring_config(wiring: &WiringState) -> WiringConfig {
+    let config = WiringConfig::from_board_with_selected_devices(
+        wiring.board,
+        wiring.sensor_profile,
+        &wiring.selected_devices,
+    )
+    .with_bus_labels(wiring.show_bus_labels);
+    match &wiring.layout {
+        Some(layout) => config.with_layout(layout),
+        None => config,
+    }
 }
 
 /// Ring buffer holding the most recent N sensor readings for history charts.
@@ -90,6 +158,21 @@ impl SensorHistoryBuffer {
         self.distance.push_back(distance_mm);
     }
 
+    /// Refills the sparklines from persisted records after a restart.
+    fn seed(&mut self, records: &[HistoryRecord]) {
+        let centi = |value: f64| (value * 100.0).round();
+        for record in records {
+            if let (Some(temp), Some(hum)) = (
+                record.value("temperature_c"),
+                record.value("humidity_percent"),
+            ) {
+                let pressure = record.value("pressure_pa").map(|p| p as u32);
+                self.push_climate(centi(temp) as i32, centi(hum) as u32, pressure);
+            }
+            self.push_distance(record.value("distance_mm").map(|d| d as u32));
+        }
+    }
+
     fn climate_json(&self) -> String {
         #[derive(serde::Serialize)]
         struct ClimateHistory {
@@ -127,50 +210,33 @@ impl SensorHistoryBuffer {
 
 /// Shared server state passed to every connection-handler thread.
 struct ServerContext {
-    latest_json: Mutex<Arc<str>>,
-    sse_clients: Mutex<Vec<mpsc::SyncSender<Arc<str>>>>,
-    current_board: Mutex<BoardProfile>,
-    /// Atomic board + sensor profile state for wiring API reads.
-    wiring_state: Mutex<WiringState>,
-    /// Last wiring-editor JSON submitted via POST /api/wiring/editor.
-    editor_json: Mutex<String>,
-    /// Snapshot of diagnostics ring from the last sim tick (for /api/diagnostics).
-    latest_diagnostics: Mutex<String>,
-    /// Ring buffer of sensor readings for /api/history.
-    history: Mutex<SensorHistoryBuffer>,
+    /// Session behind the unprefixed routes (`/api/state`, `/metrics`, ...).
+    default: Arc<Session>,
+    /// Every session, `default` included (see `session`).
+    sessions: SessionRegistry,
+    /// CSV store written by `ingest-server`, if configured via `INGEST_STORE`.
+    ingest_store: Option<PathBuf>,
 }
 
 impl ServerContext {
+    #[cfg(test)]
     fn new(board: BoardProfile) -> Arc<Self> {
-        Arc::new(Self {
-            latest_json: Mutex::new(Arc::from("{}")),
-            sse_clients: Mutex::new(vec![]),
-            current_board: Mutex::new(board),
-            wiring_state: Mutex::new(WiringState {
-                board,
-                sensor_profile: SensorProfile::Full,
-                selected_devices: normalize_supported_device_selection(
-                    board,
-                    SensorProfile::Full.device_kinds(),
-                ),
-                show_bus_labels: false,
-            }),
-            editor_json: Mutex::new("{}".into()),
-            latest_diagnostics: Mutex::new("[]".into()),
-            history: Mutex::new(SensorHistoryBuffer::new(300)),
-        })
+        Self::with_stores(board, None, None)
     }
 
-    fn push_state(&self, json: String, diag_json: String) {
-        // `Arc<str>` so fan-out to every SSE client bumps a refcount instead of
-        // allocating a fresh String copy per client (see #226).
-        let json: Arc<str> = Arc::from(json);
-        *self.latest_json.lock().unwrap() = Arc::clone(&json);
-        *self.latest_diagnostics.lock().unwrap() = diag_json;
-        self.sse_clients
-            .lock()
-            .unwrap()
-            .retain(|tx| tx.try_send(Arc::clone(&json)).is_ok());
+    /// Builds the context around a `default` session that is not running
+    /// yet; `main` starts it with `Session::spawn`.
+    fn with_stores(
+        board: BoardProfile,
+        ingest_store: Option<PathBuf>,
+        history: Option<(PathBuf, RetentionPolicy)>,
+    ) -> Arc<Self> {
+        let default = Arc::new(Session::new(DEFAULT_SESSION, board, SensorProfile::Full));
+        Arc::new(Self {
+            sessions: SessionRegistry::new(Arc::clone(&default), history),
+            default,
+            ingest_store,
+        })
     }
 }
 
@@ -254,7 +320,30 @@ fn handle_test_stream(stream: &mut TcpStream) {
 }
 
 fn main() {
-    let mut args = env::args().skip(1);
+    if let Err(e) = board_def::load_from_env() {
+        eprintln!("{}: {e}", board_def::BOARD_DEFS_ENV);
+        std::process::exit(2);
+    }
+    let mut positional = Vec::new();
+    let mut project_path = None;
+    let mut raw = env::args().skip(1);
+    while let Some(arg) = raw.next() {
+        if arg == "--wiring" {
+            project_path = Some(PathBuf::from(raw.next().unwrap_or_else(|| {
+                eprintln!("--wiring needs a project file");
+                std::process::exit(2);
+            })));
+        } else {
+            positional.push(arg);
+        }
+    }
+    let project = project_path.as_ref().map(|path| {
+        WiringProject::load(path).unwrap_or_else(|e| {
+            eprintln!("{}: {e}", path.display());
+            std::process::exit(2);
+        })
+    });
+    let mut args = positional.into_iter();
     let first = args.next();
     let second = args.next();
     let board = BoardProfile::from_arg(first.as_deref());
@@ -266,89 +355,132 @@ fn main() {
 
     let listener = TcpListener::bind(("127.0.0.1", port)).expect("server should bind");
 
-    let ctx = ServerContext::new(board);
-    let (board_tx, board_rx) = mpsc::channel::<BoardProfile>();
-    let (stream_tx, stream_rx) = mpsc::channel::<TcpStream>();
-    let mut rig = DeviceSimulationRig::new(board);
-    let mut push_ticker: u32 = 0;
+    let ingest_store = env::var_os(INGEST_STORE_ENV).map(PathBuf::from);
+    let history_dir = env::var_os(HISTORY_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_HISTORY_DIR));
+    let mut retention = RetentionPolicy::default();
+    if let Ok(age) = env::var(HISTORY_RETENTION_ENV) {
+        match parse_duration_ms(&age) {
+            Some(ms) => retention.max_age_ms = Some(ms),
+            None => {
+                eprintln!("{HISTORY_RETENTION_ENV}: expected e.g. 90m, 24h or 7d, got {age:?}");
+                std::process::exit(2);
+            }
+        }
+    }
+    let extra_sessions = match env::var(SESSIONS_ENV) {
+        Ok(specs) => parse_session_specs(&specs).unwrap_or_else(|e| {
+            eprintln!("{SESSIONS_ENV}: {e} (expected e.g. nano=arduino-nano,node-2=esp32)");
+            std::process::exit(2);
+        }),
+        Err(_) => Vec::new(),
+    };
+    let history_store = HistoryStore::open(&history_dir, retention)
+        .map_err(|e| {
+            eprintln!(
+                "history: cannot open {}: {e} (persistence disabled)",
+                history_dir.display()
+            )
+        })
+        .ok();
+    let ctx = ServerContext::with_stores(
+        board,
+        ingest_store,
+        history_store
+            .is_some()
+            .then(|| (history_dir.clone(), retention)),
+    );
+    if let Some(store) = history_store {
+        ctx.default.attach_history_store(store);
+    }
+    if let (Some(project), Some(path)) = (&project, &project_path) {
+        if let Err(e) = ctx.default.load_project(project) {
+            eprintln!("{}: {e}", path.display());
+            std::process::exit(2);
+        }
+    }
 
     println!("device dashboard server started");
     println!("open http://127.0.0.1:{port}");
-    println!("board profile: {}", board.name());
+    let wiring = ctx.default.wiring_state.lock().unwrap().clone();
+    println!("board profile: {}", wiring.board.name());
+    if let Some(path) = &project_path {
+        let report = wiring_rules::check(&dashboard_wiring_config(&wiring));
+        println!(
+            "wiring project: {} ({} devices; {} error(s), {} warning(s) from /api/wiring/check)",
+            path.display(),
+            wiring.selected_devices.len(),
+            report.count(Severity::Error),
+            report.count(Severity::Warning)
+        );
+    }
     println!("SSE endpoint: http://127.0.0.1:{port}/api/events");
-
-    thread::spawn(move || {
-        for stream in listener.incoming().flatten() {
-            if stream_tx.send(stream).is_err() {
-                break;
+    println!("WebSocket endpoint: ws://127.0.0.1:{port}/api/ws");
+    println!("metrics endpoint: http://127.0.0.1:{port}/metrics");
+    println!("sessions: http://127.0.0.1:{port}/api/sessions");
+    if let Some(store) = ctx.default.history_store.lock().unwrap().as_ref() {
+        println!(
+            "history store: {} (/api/history?panel=climate&last=1h, /api/history/export?format=parquet)",
+            store.dir().display()
+        );
+    }
+    if let Some(path) = &ctx.ingest_store {
+        println!(
+            "ingest history: {} (/api/history?source=ingest)",
+            path.display()
+        );
+    }
+    if let Some(path) = env::var_os(SCENARIO_ENV).map(PathBuf::from) {
+        let scenario = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Scenario::parse(&text).map_err(|e| e.to_string()));
+        match scenario {
+            Ok(scenario) => {
+                println!(
+                    "replaying scenario: {} ({} entries)",
+                    path.display(),
+                    scenario.entries.len()
+                );
+                ctx.default.control.lock().unwrap().play(scenario);
             }
-        }
-    });
-
-    loop {
-        // Apply pending board change from a handler thread.
-        if let Ok(new_board) = board_rx.try_recv() {
-            rig = DeviceSimulationRig::new(new_board);
-            *ctx.current_board.lock().unwrap() = new_board;
-            println!("board changed to: {}", new_board.name());
-        }
-
-        // Tick the simulation. `advance()` is the cheap phase (sensor mocks /
-        // internal counters only) and always runs; the expensive `snapshot()`
-        // formatting phase (wiring diagram, recent I2C ops, ...) only runs
-        // when the result will actually be pushed to SSE clients, since 9 of
-        // every 10 ticks previously built and discarded a full snapshot (#225).
-        let wiring_state = ctx.wiring_state.lock().unwrap().clone();
-        rig.advance(&wiring_state);
-        push_ticker = push_ticker.wrapping_add(1);
-
-        // Push JSON to SSE clients every 10 ticks (~100 ms).
-        if push_ticker % 10 == 0 {
-            let state = rig.snapshot(&wiring_state);
-            let diag = &state.diagnostics;
-            let diag_json = serde_json::to_string(diag).unwrap_or_default();
-            // Append to history ring buffers when sensors are active.
-            {
-                let mut hist = ctx.history.lock().unwrap();
-                let cli = &state.climate;
-                if cli.temperature_c.is_some() || cli.humidity_percent.is_some() {
-                    let temp_cc = cli.temperature_c.map(|v| (v * 100.0) as i32).unwrap_or(0);
-                    let hum_cp = cli
-                        .humidity_percent
-                        .map(|v| (v * 100.0) as u32)
-                        .unwrap_or(0);
-                    hist.push_climate(temp_cc, hum_cp, cli.pressure_pa);
-                }
-                hist.push_distance(state.distance.distance_mm);
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                std::process::exit(2);
             }
-            ctx.push_state(state_to_json(&state), diag_json);
         }
+    }
 
-        // Dispatch any connections accepted by the accept thread.
-        while let Ok(stream) = stream_rx.try_recv() {
-            let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
-            let ctx_clone = Arc::clone(&ctx);
-            let board_tx_clone = board_tx.clone();
-            thread::spawn(move || {
-                handle_connection(stream, ctx_clone, board_tx_clone);
-            });
+    ctx.default.spawn();
+    for (name, board) in extra_sessions {
+        if let Err(e) = ctx.sessions.create(&name, board, SensorProfile::Full) {
+            eprintln!("{SESSIONS_ENV}: {e}");
+            std::process::exit(2);
         }
+    }
 
-        thread::sleep(Duration::from_millis(10));
+    for stream in listener.incoming().flatten() {
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        let ctx = Arc::clone(&ctx);
+        thread::spawn(move || handle_connection(stream, ctx));
     }
 }
 
-fn handle_connection(
-    mut stream: TcpStream,
-    ctx: Arc<ServerContext>,
-    board_tx: mpsc::Sender<BoardProfile>,
-) {
+fn query_param<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query.split('&').find_map(|part| {
+        let (k, v) = part.split_once('=')?;
+        (k == key).then_some(v)
+    })
+}
+
+fn handle_connection(mut stream: TcpStream, ctx: Arc<ServerContext>) {
     let mut request_buf = [0u8; 4096];
     let Ok(read_len) = stream.read(&mut request_buf) else {
         return;
     };
-    let raw = &request_buf[..read_len];
-    let request = String::from_utf8_lossy(raw);
+    let mut raw = request_buf[..read_len].to_vec();
+    read_remaining_body(&mut stream, &mut raw, MAX_REQUEST_LEN);
+    let request = String::from_utf8_lossy(&raw);
 
     let first_line = request.lines().next().unwrap_or("GET / HTTP/1.1");
     let mut parts = first_line.split_whitespace();
@@ -361,7 +493,19 @@ fn handle_connection(
         .map(|pos| &request[pos + 4..])
         .unwrap_or("");
 
-    match (method, path_only) {
+    // `/api/sessions/<name>/state` is `/api/state` of session `<name>`.
+    let (session, path_only) = match split_session_path(path_only) {
+        Some((name, scoped_path)) => match ctx.sessions.get(name) {
+            Some(session) => (session, scoped_path),
+            None => {
+                respond_session_error(&mut stream, SessionError::NotFound(name.to_string()));
+                return;
+            }
+        },
+        None => (Arc::clone(&ctx.default), path_only.to_string()),
+    };
+
+    match (method, path_only.as_str()) {
         (_, "/") => respond(
             &mut stream,
             "200 OK",
@@ -369,11 +513,12 @@ fn handle_connection(
             dashboard_html(),
         ),
         (_, "/api/events") => {
-            handle_sse_events(&mut stream, ctx);
+            handle_sse_events(&mut stream, &session);
         }
+        (_, "/api/ws") => handle_websocket(stream, &request, session),
         (_, "/api/state") => {
             // `Arc<str>`; `.clone()` only bumps a refcount, not a full copy.
-            let json = ctx.latest_json.lock().unwrap().clone();
+            let json = session.latest_json.lock().unwrap().clone();
             respond(
                 &mut stream,
                 "200 OK",
@@ -382,17 +527,19 @@ fn handle_connection(
             );
         }
         ("POST", "/api/wiring") => {
-            if let Some(board_name) = parse_board_from_json(body) {
-                let new_board = BoardProfile::from_arg(Some(board_name));
-                let _ = board_tx.send(new_board);
-                // Give the main thread time to apply the change.
-                thread::sleep(Duration::from_millis(50));
-            }
-            // Update wiring_state atomically as a single unit.
+            // Update wiring_state atomically as a single unit; the session
+            // thread rebuilds its rig on the next tick if the board changed.
             let wiring = {
-                let mut ws = ctx.wiring_state.lock().unwrap();
+                let mut ws = session.wiring_state.lock().unwrap();
                 if let Some(board_name) = parse_board_from_json(body) {
                     ws.board = BoardProfile::from_arg(Some(board_name));
+                    if ws
+                        .layout
+                        .as_ref()
+                        .is_some_and(|layout| layout.board != ws.board)
+                    {
+                        ws.layout = None;
+                    }
                 }
                 if let Some(profile_slug) = parse_sensor_profile_from_json(body) {
                     if let Some(profile) = SensorProfile::from_slug(profile_slug) {
@@ -415,13 +562,7 @@ fn handle_connection(
                     normalize_supported_device_selection(ws.board, &ws.selected_devices);
                 ws.clone()
             };
-            let payload = dashboard_wiring_config(
-                wiring.board,
-                wiring.sensor_profile,
-                &wiring.selected_devices,
-                wiring.show_bus_labels,
-            )
-            .to_json();
+            let payload = dashboard_wiring_config(&wiring).to_json();
             respond(
                 &mut stream,
                 "200 OK",
@@ -455,15 +596,17 @@ fn handle_connection(
                 &payload,
             );
         }
+        (_, "/api/wiring/devices") => {
+            respond(
+                &mut stream,
+                "200 OK",
+                "application/json; charset=utf-8",
+                &devices_json(),
+            );
+        }
         (_, "/api/wiring") => {
-            let wiring = ctx.wiring_state.lock().unwrap().clone();
-            let payload = dashboard_wiring_config(
-                wiring.board,
-                wiring.sensor_profile,
-                &wiring.selected_devices,
-                wiring.show_bus_labels,
-            )
-            .to_json();
+            let wiring = session.wiring_state.lock().unwrap().clone();
+            let payload = dashboard_wiring_config(&wiring).to_json();
             respond(
                 &mut stream,
                 "200 OK",
@@ -471,28 +614,100 @@ fn handle_connection(
                 &payload,
             );
         }
-        (_, "/api/wiring/svg") => {
-            let wiring = ctx.wiring_state.lock().unwrap().clone();
-            let cfg = dashboard_wiring_config(
-                wiring.board,
-                wiring.sensor_profile,
-                &wiring.selected_devices,
-                wiring.show_bus_labels,
+        ("POST", "/api/wiring/project") => {
+            match WiringProject::parse(body).and_then(|project| session.load_project(&project)) {
+                Ok(()) => {
+                    let wiring = session.wiring_state.lock().unwrap().clone();
+                    respond(
+                        &mut stream,
+                        "200 OK",
+                        "application/json; charset=utf-8",
+                        &dashboard_wiring_config(&wiring).to_json(),
+                    );
+                }
+                Err(e) => respond(
+                    &mut stream,
+                    "400 Bad Request",
+                    "text/plain; charset=utf-8",
+                    &e.to_string(),
+                ),
+            }
+        }
+        (_, "/api/wiring/project") => {
+            respond(
+                &mut stream,
+                "200 OK",
+                "application/json; charset=utf-8",
+                &session.project().to_json_pretty(),
             );
-            let svg = wiring_svg(&cfg);
-            respond(&mut stream, "200 OK", "image/svg+xml; charset=utf-8", &svg);
         }
-        ("POST", "/api/wiring/editor") => {
-            *ctx.editor_json.lock().unwrap() = body.to_string();
+        (_, "/api/wiring/check") => {
+            let wiring = session.wiring_state.lock().unwrap().clone();
+            let cfg = dashboard_wiring_config(&wiring);
             respond(
                 &mut stream,
                 "200 OK",
                 "application/json; charset=utf-8",
-                r#"{"ok":true}"#,
+                &wiring_rules::check(&cfg).to_json(),
             );
         }
+        (_, "/api/wiring/export") => {
+            let slug = query_param(query_str, "format").unwrap_or("kicad");
+            let Some(format) = ExportFormat::from_slug(slug) else {
+                respond(
+                    &mut stream,
+                    "400 Bad Request",
+                    "text/plain; charset=utf-8",
+                    &format!("unknown format `{slug}` (kicad, breadboard, bom, board-setup)"),
+                );
+                return;
+            };
+            let wiring = session.wiring_state.lock().unwrap().clone();
+            let cfg = dashboard_wiring_config(&wiring);
+            match wiring_export::export(&cfg, format) {
+                Ok(body) => respond_attachment(
+                    &mut stream,
+                    format.content_type(),
+                    &format!("wiring-{}.{}", session.name, format.extension()),
+                    body.as_bytes(),
+                ),
+                Err(e) => respond(
+                    &mut stream,
+                    "400 Bad Request",
+                    "text/plain; charset=utf-8",
+                    &e.to_string(),
+                ),
+            }
+        }
+        (_, "/api/wiring/svg") => {
+            let wiring = session.wiring_state.lock().unwrap().clone();
+            let cfg = dashboard_wiring_config(&wiring);
+            let svg = wiring_svg(&cfg);
+            respond(&mut stream, "200 OK", "image/svg+xml; charset=utf-8", &svg);
+        }
+        ("POST", "/api/wiring/editor") => {
+            // Stored verbatim, but it has to be a JSON object so it can be
+            // embedded in the wiring project file.
+            match serde_json::from_str::<serde_json::Value>(body) {
+                Ok(serde_json::Value::Object(_)) => {
+                    *session.editor_json.lock().unwrap() = body.to_string();
+                    respond(
+                        &mut stream,
+                        "200 OK",
+                        "application/json; charset=utf-8",
+                        r#"{"ok":true}"#,
+                    );
+                }
+                _ => respond(
+                    &mut stream,
+                    "400 Bad Request",
+                    "text/plain; charset=utf-8",
+                    "editor layout must be a JSON object",
+                ),
+            }
+        }
         (_, "/api/wiring/editor") => {
-            let json = ctx.editor_json.lock().unwrap().clone();
+            let json = session.editor_json.lock().unwrap().clone();
             respond(
                 &mut stream,
                 "200 OK",
@@ -500,8 +715,31 @@ fn handle_connection(
                 &json,
             );
         }
+        (_, "/metrics") => {
+            let rendered = session
+                .latest_metrics
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|metrics| metrics.render(&session.name));
+            match rendered {
+                Some(Ok(body)) => respond_bytes(&mut stream, "200 OK", METRICS_CONTENT_TYPE, &body),
+                Some(Err(e)) => respond(
+                    &mut stream,
+                    "500 Internal Server Error",
+                    "text/plain; charset=utf-8",
+                    &format!("metrics encode failed: {e:?}"),
+                ),
+                None => respond(
+                    &mut stream,
+                    "503 Service Unavailable",
+                    "text/plain; charset=utf-8",
+                    "no reading yet",
+                ),
+            }
+        }
         (_, "/api/diagnostics") => {
-            let json = ctx.latest_diagnostics.lock().unwrap().clone();
+            let json = session.latest_diagnostics.lock().unwrap().clone();
             respond(
                 &mut stream,
                 "200 OK",
@@ -509,20 +747,74 @@ fn handle_connection(
                 &json,
             );
         }
+        (_, "/api/telemetry") => {
+            let format = match query_param(query_str, "format").unwrap_or("json") {
+                "cbor" => TelemetryFormat::Cbor,
+                "line" => TelemetryFormat::LineProtocol,
+                "openmetrics" => TelemetryFormat::OpenMetrics,
+                _ => TelemetryFormat::Json,
+            };
+            let frame = *session.latest_telemetry.lock().unwrap();
+            let Some(frame) = frame else {
+                respond(
+                    &mut stream,
+                    "503 Service Unavailable",
+                    "text/plain; charset=utf-8",
+                    "no reading yet",
+                );
+                return;
+            };
+            // OpenMetrics uses the firmware's namespace so both scrape alike.
+            let measurement = match format {
+                TelemetryFormat::OpenMetrics => DEFAULT_NAMESPACE,
+                _ => TELEMETRY_MEASUREMENT,
+            };
+            let mut buf = [0u8; 4096];
+            match frame.encode(format, measurement, &mut buf) {
+                Ok(len) => respond_bytes(&mut stream, "200 OK", format.content_type(), &buf[..len]),
+                Err(e) => respond(
+                    &mut stream,
+                    "500 Internal Server Error",
+                    "text/plain; charset=utf-8",
+                    &format!("telemetry encode failed: {e:?}"),
+                ),
+            }
+        }
+        (_, "/api/history") if query_param(query_str, "source") == Some("ingest") => {
+            let Some(path) = &ctx.ingest_store else {
+                respond(
+                    &mut stream,
+                    "503 Service Unavailable",
+                    "text/plain; charset=utf-8",
+                    "ingest store not configured (set INGEST_STORE)",
+                );
+                return;
+            };
+            match CsvReadingStore::open(path) {
+                Ok(store) => respond(
+                    &mut stream,
+                    "200 OK",
+                    "application/json; charset=utf-8",
+                    &store.history_json(query_param(query_str, "device_id"), HISTORY_CAPACITY),
+                ),
+                Err(e) => respond(
+                    &mut stream,
+                    "500 Internal Server Error",
+                    "text/plain; charset=utf-8",
+                    &format!("ingest store unreadable: {e}"),
+                ),
+            }
+        }
+        (_, "/api/history") if is_store_query(query_str) => {
+            handle_history_query(&mut stream, query_str, &session);
+        }
+        (_, "/api/history/export") => {
+            handle_history_export(&mut stream, query_str, &session);
+        }
         (_, "/api/history") => {
-            let sensor = query_str
-                .split('&')
-                .find_map(|part| {
-                    let (k, v) = part.split_once('=')?;
-                    if k == "sensor" {
-                        Some(v)
-                    } else {
-                        None
-                    }
-                })
-                .unwrap_or("bme280");
+            let sensor = query_param(query_str, "sensor").unwrap_or("bme280");
             let json = {
-                let hist = ctx.history.lock().unwrap();
+                let hist = session.history.lock().unwrap();
                 match sensor {
                     "distance" => hist.distance_json(),
                     _ => hist.climate_json(),
@@ -535,6 +827,12 @@ fn handle_connection(
                 &json,
             );
         }
+        (_, path) if path == "/api/control" || path.starts_with("/api/control/") => {
+            handle_control(&mut stream, method, path, body, &session);
+        }
+        (_, path) if path == "/api/sessions" || path.starts_with("/api/sessions/") => {
+            handle_sessions(&mut stream, method, path, body, &ctx);
+        }
         (_, "/api/test/stream") => {
             handle_test_stream(&mut stream);
         }
@@ -555,24 +853,20 @@ fn handle_connection(
                 &json,
             );
         }
+        (_, "/api/boards") => {
+            respond(
+                &mut stream,
+                "200 OK",
+                "application/json; charset=utf-8",
+                &boards_json(),
+            );
+        }
         (_, "/api/flash/targets") => {
-            let entries: Vec<String> = flash_targets()
-                .iter()
-                .map(|t| {
-                    format!(
-                        "{{\"id\":\"{}\",\"label\":\"{}\",\"board\":\"{}\"}}",
-                        t.id,
-                        t.label,
-                        t.board.label()
-                    )
-                })
-                .collect();
-            let json = format!("[{}]", entries.join(","));
             respond(
                 &mut stream,
                 "200 OK",
                 "application/json; charset=utf-8",
-                &json,
+                &flash_targets_json(),
             );
         }
         (_, "/api/flash/stream") => {
@@ -587,7 +881,94 @@ fn handle_connection(
     }
 }
 
-fn handle_sse_events(stream: &mut TcpStream, ctx: Arc<ServerContext>) {
+/// Registered boards for the board selectors: `[{slug,name,mcu,flash,needs_port}]`.
+fn boards_json() -> String {
+    #[derive(serde::Serialize)]
+    struct PinView {
+        name: &'static str,
+        caps: Vec<&'static str>,
+        input_only: bool,
+        reserved: bool,
+    }
+    #[derive(serde::Serialize)]
+    struct BoardView {
+        slug: &'static str,
+        name: &'static str,
+        mcu: &'static str,
+        /// Flash method slug, `null` when the board cannot be flashed.
+        flash: Option<&'static str>,
+        needs_port: bool,
+        /// Supply pins the wiring editor offers for `power`, main rail first.
+        rails: [&'static str; 2],
+        /// Header pins in definition order.
+        pins: Vec<PinView>,
+    }
+    let boards: Vec<BoardView> = BoardProfile::all()
+        .into_iter()
+        .map(|board| {
+            let def = board.def();
+            let method = board.flash().map(|flash| flash.method);
+            BoardView {
+                slug: board.slug(),
+                name: board.name(),
+                mcu: board.mcu(),
+                flash: method.map(|method| method.slug()),
+                needs_port: method.map_or(true, |method| method.needs_port()),
+                rails: [
+                    def.electrical.main_rail.name.as_str(),
+                    def.electrical.alt_rail.name.as_str(),
+                ],
+                pins: def
+                    .pins
+                    .iter()
+                    .map(|pin| PinView {
+                        name: pin.name.as_str(),
+                        caps: pin.caps.iter().map(|cap| cap.slug()).collect(),
+                        input_only: pin.input_only,
+                        reserved: pin.reserved.is_some(),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    serde_json::to_string(&boards).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Every device kind with the pins the wiring editor wires up.
+fn devices_json() -> String {
+    #[derive(serde::Serialize)]
+    struct SignalView {
+        signal: &'static str,
+        /// Project pin (`sda`, `power`, ...) the wire sets.
+        pin: &'static str,
+    }
+    #[derive(serde::Serialize)]
+    struct DeviceView {
+        kind: &'static str,
+        label: &'static str,
+        /// Default I2C address, `null` off the bus.
+        address: Option<String>,
+        signals: Vec<SignalView>,
+    }
+    let devices: Vec<DeviceView> = DeviceKind::all()
+        .iter()
+        .map(|&kind| DeviceView {
+            kind: kind.slug(),
+            label: kind.label(),
+            address: kind
+                .default_address()
+                .map(|address| format!("0x{address:02X}")),
+            signals: kind
+                .signals()
+                .iter()
+                .map(|&(signal, pin)| SignalView { signal, pin })
+                .collect(),
+        })
+        .collect();
+    serde_json::to_string(&devices).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn handle_sse_events(stream: &mut TcpStream, session: &Session) {
     let header = "HTTP/1.1 200 OK\r\n\
         Content-Type: text/event-stream\r\n\
         Cache-Control: no-cache\r\n\
@@ -598,9 +979,9 @@ fn handle_sse_events(stream: &mut TcpStream, ctx: Arc<ServerContext>) {
         return;
     }
 
-    let initial = ctx.latest_json.lock().unwrap().clone();
+    let initial = session.latest_json.lock().unwrap().clone();
     let (tx, rx) = mpsc::sync_channel::<Arc<str>>(32);
-    ctx.sse_clients.lock().unwrap().push(tx);
+    session.sse_clients.lock().unwrap().push(tx);
 
     if write_sse_frame(stream, &initial).is_err() {
         return;
@@ -776,16 +1157,13 @@ mod tests {
         let addr = listener
             .local_addr()
             .expect("listener should have local addr");
-        let ctx = ServerContext::new(BoardProfile::OriginalEsp32);
-        *ctx.latest_json.lock().unwrap() = Arc::from(r#"{"tick":1}"#);
-
-        let (board_tx, board_rx) = mpsc::channel::<BoardProfile>();
-        drop(board_rx);
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+        *ctx.default.latest_json.lock().unwrap() = Arc::from(r#"{"tick":1}"#);
 
         let ctx_for_thread = Arc::clone(&ctx);
         let server = thread::spawn(move || {
             let (stream, _) = listener.accept().expect("test client should connect");
-            handle_connection(stream, ctx_for_thread, board_tx);
+            handle_connection(stream, ctx_for_thread);
         });
 
         let mut client = TcpStream::connect(addr).expect("client should connect");
@@ -804,7 +1182,7 @@ mod tests {
         client
             .shutdown(Shutdown::Both)
             .expect("client should shut down cleanly");
-        ctx.sse_clients.lock().unwrap().clear();
+        ctx.default.sse_clients.lock().unwrap().clear();
 
         server.join().expect("server thread should exit");
     }
@@ -814,13 +1192,14 @@ mod tests {
         // Regression test for #226: push_state() must fan out the same
         // `Arc<str>` allocation to every subscribed SSE client (refcount
         // bump only) instead of cloning a fresh `String` per client.
-        let ctx = ServerContext::new(BoardProfile::OriginalEsp32);
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
         let (tx1, rx1) = mpsc::sync_channel::<Arc<str>>(4);
         let (tx2, rx2) = mpsc::sync_channel::<Arc<str>>(4);
-        ctx.sse_clients.lock().unwrap().push(tx1);
-        ctx.sse_clients.lock().unwrap().push(tx2);
+        ctx.default.sse_clients.lock().unwrap().push(tx1);
+        ctx.default.sse_clients.lock().unwrap().push(tx2);
 
-        ctx.push_state(r#"{"tick":1}"#.to_string(), "[]".to_string());
+        ctx.default
+            .push_state(r#"{"tick":1}"#.to_string(), "[]".to_string());
 
         let received1 = rx1.try_recv().expect("client 1 should receive state");
         let received2 = rx2.try_recv().expect("client 2 should receive state");
@@ -830,7 +1209,7 @@ mod tests {
         );
         assert_eq!(&*received1, r#"{"tick":1}"#);
 
-        let stored = ctx.latest_json.lock().unwrap().clone();
+        let stored = ctx.default.latest_json.lock().unwrap().clone();
         assert!(
             Arc::ptr_eq(&received1, &stored),
             "latest_json snapshot should also share the same allocation"
@@ -843,15 +1222,12 @@ mod tests {
         let addr = listener
             .local_addr()
             .expect("listener should have local addr");
-        let ctx = ServerContext::new(BoardProfile::OriginalEsp32);
-
-        let (board_tx, board_rx) = mpsc::channel::<BoardProfile>();
-        drop(board_rx);
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
 
         let ctx_for_thread = Arc::clone(&ctx);
         let server = thread::spawn(move || {
             let (stream, _) = listener.accept().expect("test client should connect");
-            handle_connection(stream, ctx_for_thread, board_tx);
+            handle_connection(stream, ctx_for_thread);
         });
 
         let body = r#"{"sensor_profile":"minimal","selected_devices":["bme280","servo"]}"#;
@@ -883,16 +1259,13 @@ mod tests {
         let addr = listener
             .local_addr()
             .expect("listener should have local addr");
-        let ctx = ServerContext::new(BoardProfile::OriginalEsp32);
-
-        let (board_tx, board_rx) = mpsc::channel::<BoardProfile>();
-        drop(board_rx);
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
 
         let ctx_for_thread = Arc::clone(&ctx);
         let server = thread::spawn(move || {
             for _ in 0..3 {
                 let (stream, _) = listener.accept().expect("test client should connect");
-                handle_connection(stream, Arc::clone(&ctx_for_thread), board_tx.clone());
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
             }
         });
 
@@ -920,12 +1293,13 @@ mod tests {
 
     #[test]
     fn device_simulation_rig_only_polls_selected_devices() {
-        let mut rig = DeviceSimulationRig::new(BoardProfile::OriginalEsp32);
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
         let wiring_state = WiringState {
-            board: BoardProfile::OriginalEsp32,
+            board: BoardProfile::ORIGINAL_ESP32,
             sensor_profile: SensorProfile::Minimal,
             selected_devices: vec![DeviceKind::Bme280, DeviceKind::Lcd1602],
             show_bus_labels: false,
+            layout: None,
         };
 
         let state = rig.step(&wiring_state);
@@ -951,6 +1325,83 @@ mod tests {
         assert_eq!(state.tof.distance_mm, None);
     }
 
+    #[test]
+    fn device_simulation_rig_follows_project_addresses() {
+        let project = WiringProject::parse(
+            r#"{"format":"mcu-hal-sim-wiring","version":1,"board":"esp32",
+                "devices":[{"kind":"bme280","address":"0x76"},{"kind":"lcd1602","address":"0x3F"},
+                           {"kind":"mpu6050","address":"0x69"},{"kind":"ds3231"}]}"#,
+        )
+        .unwrap();
+        let layout = project.to_config().unwrap();
+        let mut wiring_state = WiringState {
+            board: BoardProfile::ORIGINAL_ESP32,
+            sensor_profile: SensorProfile::Full,
+            selected_devices: layout.devices.iter().map(|device| device.kind).collect(),
+            show_bus_labels: false,
+            layout: Some(layout),
+        };
+
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
+        let state = rig.step(&wiring_state);
+        let mut attached = rig.bus.attached_addresses();
+        attached.sort_unstable();
+        assert_eq!(attached, vec![0x3F, 0x68, 0x69, 0x76]);
+        assert!(state.climate.temperature_c.is_some());
+        assert!(!state.climate.physical_lcd_frame[0].trim().is_empty());
+        assert_ne!(state.imu.accel_mg, [0, 0, 0]);
+        assert!(!state.rtc.datetime_str.is_empty());
+        assert!(state
+            .i2c
+            .recent_operations
+            .iter()
+            .all(|line| ["0x76", "0x3F", "0x69", "0x68"]
+                .iter()
+                .any(|addr| line.contains(addr))));
+
+        // Dropping the project moves the mocks back to their defaults.
+        wiring_state.layout = None;
+        let state = rig.step(&wiring_state);
+        let mut attached = rig.bus.attached_addresses();
+        attached.sort_unstable();
+        assert_eq!(attached, vec![0x27, 0x68, 0x69, 0x77]);
+        assert!(state.climate.temperature_c.is_some());
+        assert!(!state.rtc.datetime_str.is_empty());
+    }
+
+    #[test]
+    fn device_simulation_rig_leaves_the_later_of_two_clashing_devices_detached() {
+        let project = WiringProject::parse(
+            r#"{"format":"mcu-hal-sim-wiring","version":1,"board":"esp32",
+                "devices":[{"kind":"bh1750","address":"0x23"},{"kind":"sgp30","address":"0x23"}]}"#,
+        )
+        .unwrap();
+        let layout = project.to_config().unwrap();
+        let wiring_state = WiringState {
+            board: BoardProfile::ORIGINAL_ESP32,
+            sensor_profile: SensorProfile::Full,
+            selected_devices: layout.devices.iter().map(|device| device.kind).collect(),
+            show_bus_labels: false,
+            layout: Some(layout),
+        };
+
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
+        let state = rig.step(&wiring_state);
+        assert_eq!(rig.bus.attached_addresses(), vec![0x23]);
+        assert_eq!(rig.shadowed_devices, vec![DeviceKind::Sgp30]);
+        assert!(state.light.lux_x100 > 0);
+        assert_eq!(state.gas.co2_ppm, None);
+        assert!(state.diagnostics.recent_events.iter().any(|event| {
+            event.severity == "error"
+                && event.message == "[i2c] SGP30 shares 0x23 with BH1750; left detached"
+        }));
+
+        // The clash is reported once, not on every tick.
+        let errors = rig.diag_severity_counts[2];
+        rig.step(&wiring_state);
+        assert_eq!(rig.diag_severity_counts[2], errors);
+    }
+
     #[test]
     fn device_simulation_rig_snapshot_is_read_only_and_repeatable() {
         // Regression test for #225: advance() (cheap) and snapshot()
@@ -958,12 +1409,13 @@ mod tests {
         // pure read that can be skipped or called multiple times per tick
         // without re-triggering I2C reads or double-counting recorded bus
         // operations.
-        let mut rig = DeviceSimulationRig::new(BoardProfile::OriginalEsp32);
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
         let wiring_state = WiringState {
-            board: BoardProfile::OriginalEsp32,
+            board: BoardProfile::ORIGINAL_ESP32,
             sensor_profile: SensorProfile::Minimal,
             selected_devices: vec![DeviceKind::Bme280, DeviceKind::Lcd1602],
             show_bus_labels: false,
+            layout: None,
         };
 
         rig.advance(&wiring_state);
@@ -991,12 +1443,13 @@ mod tests {
         // fix avoiding needless bus re-attachment churn), but sensors must keep
         // reading correctly and no spurious enable/disable diagnostics should
         // fire across repeated identical ticks.
-        let mut rig = DeviceSimulationRig::new(BoardProfile::OriginalEsp32);
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
         let wiring_state = WiringState {
-            board: BoardProfile::OriginalEsp32,
+            board: BoardProfile::ORIGINAL_ESP32,
             sensor_profile: SensorProfile::Minimal,
             selected_devices: vec![DeviceKind::Bme280, DeviceKind::Lcd1602],
             show_bus_labels: false,
+            layout: None,
         };
 
         let state1 = rig.step(&wiring_state);
@@ -1027,13 +1480,14 @@ mod tests {
         // keyed by WiringConfig so unchanged wiring reuses the cached lines,
         // but a real wiring change (device selection here) must still
         // produce updated diagram content rather than stale cached lines.
-        let mut rig = DeviceSimulationRig::new(BoardProfile::OriginalEsp32);
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
 
         let minimal_state = WiringState {
-            board: BoardProfile::OriginalEsp32,
+            board: BoardProfile::ORIGINAL_ESP32,
             sensor_profile: SensorProfile::Minimal,
             selected_devices: vec![DeviceKind::Bme280, DeviceKind::Lcd1602],
             show_bus_labels: false,
+            layout: None,
         };
         rig.advance(&minimal_state);
         let snap1 = rig.snapshot(&minimal_state);
@@ -1044,7 +1498,7 @@ mod tests {
         );
 
         let full_state = WiringState {
-            board: BoardProfile::OriginalEsp32,
+            board: BoardProfile::ORIGINAL_ESP32,
             sensor_profile: SensorProfile::Full,
             selected_devices: vec![
                 DeviceKind::Bme280,
@@ -1053,6 +1507,7 @@ mod tests {
                 DeviceKind::Servo,
             ],
             show_bus_labels: false,
+            layout: None,
         };
         rig.advance(&full_state);
         let snap3 = rig.snapshot(&full_state);
@@ -1067,16 +1522,17 @@ mod tests {
         // step() must remain equivalent to advance() followed by snapshot()
         // so existing direct callers of step() keep working unchanged (#225).
         let wiring_state = WiringState {
-            board: BoardProfile::OriginalEsp32,
+            board: BoardProfile::ORIGINAL_ESP32,
             sensor_profile: SensorProfile::Minimal,
             selected_devices: vec![DeviceKind::Bme280, DeviceKind::Lcd1602],
             show_bus_labels: false,
+            layout: None,
         };
 
-        let mut rig_a = DeviceSimulationRig::new(BoardProfile::OriginalEsp32);
+        let mut rig_a = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
         let state_a = rig_a.step(&wiring_state);
 
-        let mut rig_b = DeviceSimulationRig::new(BoardProfile::OriginalEsp32);
+        let mut rig_b = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
         rig_b.advance(&wiring_state);
         let state_b = rig_b.snapshot(&wiring_state);
 
@@ -1095,12 +1551,13 @@ mod tests {
 
     #[test]
     fn device_simulation_rig_keeps_bme280_data_when_lcd_is_disabled() {
-        let mut rig = DeviceSimulationRig::new(BoardProfile::OriginalEsp32);
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
         let wiring_state = WiringState {
-            board: BoardProfile::OriginalEsp32,
+            board: BoardProfile::ORIGINAL_ESP32,
             sensor_profile: SensorProfile::Minimal,
             selected_devices: vec![DeviceKind::Bme280],
             show_bus_labels: false,
+            layout: None,
         };
 
         let state = rig.step(&wiring_state);
@@ -1119,14 +1576,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn device_simulation_rig_surfaces_co2_alarm_transitions_in_diagnostics() {
+        // The demo SGP30 sequence peaks at 1100 ppm on the sixth poll (tick 55)
+        // and drops to 850 ppm on the seventh (tick 66), which is below the
+        // 1000 ppm threshold minus the 100 ppm hysteresis.
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
+        let wiring_state = WiringState {
+            board: BoardProfile::ORIGINAL_ESP32,
+            sensor_profile: SensorProfile::Minimal,
+            selected_devices: vec![DeviceKind::Sgp30],
+            show_bus_labels: false,
+            layout: None,
+        };
+
+        for _ in 0..54 {
+            rig.advance(&wiring_state);
+        }
+        assert!(rig
+            .snapshot(&wiring_state)
+            .diagnostics
+            .active_alarms
+            .is_empty());
+
+        let state = rig.step(&wiring_state);
+        let latest = &state.diagnostics.recent_events[0];
+        assert_eq!(latest.severity, "warn");
+        assert_eq!(latest.message, "[alarm] co2-high raised (1100)");
+        assert_eq!(
+            state.diagnostics.active_alarms,
+            vec!["warn co2-high = 1100".to_string()]
+        );
+
+        for _ in 55..66 {
+            rig.advance(&wiring_state);
+        }
+        let state = rig.snapshot(&wiring_state);
+        let latest = &state.diagnostics.recent_events[0];
+        assert_eq!(latest.severity, "info");
+        assert_eq!(latest.message, "[alarm] co2-high cleared (850)");
+        assert!(state.diagnostics.active_alarms.is_empty());
+    }
+
     #[test]
     fn device_simulation_rig_does_not_fabricate_climate_without_bme280() {
-        let mut rig = DeviceSimulationRig::new(BoardProfile::OriginalEsp32);
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
         let wiring_state = WiringState {
-            board: BoardProfile::OriginalEsp32,
+            board: BoardProfile::ORIGINAL_ESP32,
             sensor_profile: SensorProfile::Minimal,
             selected_devices: vec![DeviceKind::Lcd1602],
             show_bus_labels: false,
+            layout: None,
         };
 
         let state = rig.step(&wiring_state);
@@ -1146,12 +1646,13 @@ mod tests {
 
     #[test]
     fn device_simulation_rig_reports_consistent_ds3231_address() {
-        let mut rig = DeviceSimulationRig::new(BoardProfile::OriginalEsp32);
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
         let wiring_state = WiringState {
-            board: BoardProfile::OriginalEsp32,
+            board: BoardProfile::ORIGINAL_ESP32,
             sensor_profile: SensorProfile::ClimateStation,
             selected_devices: vec![DeviceKind::Ds3231],
             show_bus_labels: false,
+            layout: None,
         };
 
         let state = rig.step(&wiring_state);
@@ -1181,9 +1682,9 @@ mod tests {
 
     #[test]
     fn device_simulation_rig_resets_disabled_actuators() {
-        let mut rig = DeviceSimulationRig::new(BoardProfile::OriginalEsp32);
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
         let active_wiring_state = WiringState {
-            board: BoardProfile::OriginalEsp32,
+            board: BoardProfile::ORIGINAL_ESP32,
             sensor_profile: SensorProfile::RobotBase,
             selected_devices: vec![
                 DeviceKind::HcSr04,
@@ -1192,6 +1693,7 @@ mod tests {
                 DeviceKind::L298n,
             ],
             show_bus_labels: false,
+            layout: None,
         };
 
         let active_state = rig.step(&active_wiring_state);
@@ -1202,10 +1704,11 @@ mod tests {
         assert_eq!(active_state.motor_driver.right.duty_percent, 42);
 
         let disabled_wiring_state = WiringState {
-            board: BoardProfile::OriginalEsp32,
+            board: BoardProfile::ORIGINAL_ESP32,
             sensor_profile: SensorProfile::ClimateStation,
             selected_devices: vec![DeviceKind::Ds3231],
             show_bus_labels: false,
+            layout: None,
         };
 
         let disabled_state = rig.step(&disabled_wiring_state);
@@ -1222,15 +1725,12 @@ mod tests {
         let addr = listener
             .local_addr()
             .expect("listener should have local addr");
-        let ctx = ServerContext::new(BoardProfile::OriginalEsp32);
-
-        let (board_tx, board_rx) = mpsc::channel::<BoardProfile>();
-        drop(board_rx);
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
 
         let ctx_for_thread = Arc::clone(&ctx);
         let server = thread::spawn(move || {
             let (stream, _) = listener.accept().expect("test client should connect");
-            handle_connection(stream, ctx_for_thread, board_tx);
+            handle_connection(stream, ctx_for_thread);
         });
 
         let response = send_request(
@@ -1248,121 +1748,490 @@ mod tests {
     }
 
     #[test]
-    fn wiring_state_and_svg_reflect_explicit_selection_over_profile() {
+    fn wiring_check_endpoint_reports_findings_for_current_selection() {
         let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
         let addr = listener
             .local_addr()
             .expect("listener should have local addr");
-        let ctx = ServerContext::new(BoardProfile::OriginalEsp32);
-
-        let (board_tx, board_rx) = mpsc::channel::<BoardProfile>();
-        drop(board_rx);
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
 
         let ctx_for_thread = Arc::clone(&ctx);
         let server = thread::spawn(move || {
             for _ in 0..3 {
                 let (stream, _) = listener.accept().expect("test client should connect");
-                handle_connection(stream, Arc::clone(&ctx_for_thread), board_tx.clone());
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
             }
         });
 
-        let body = r#"{"sensor_profile":"robot","selected_devices":["bme280","servo"]}"#;
+        let response = send_request(
+            addr,
+            "GET /api/wiring/check HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.contains(r#""rule":"i2c-address""#), "{response}");
+        assert!(response.contains("0x68"), "{response}");
+
+        let body = r#"{"selected_devices":["bme280","lcd1602"]}"#;
         let post_request = format!(
             "POST /api/wiring HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
             body.len(),
             body
         );
-        let post_response = send_request(addr, &post_request);
-        assert!(post_response.contains("\"sensor_profile\":\"robot\""));
-        assert!(post_response.contains("\"selected_devices\":[\"bme280\",\"servo\"]"));
-        assert!(post_response.contains("\"devices\":["));
-        assert!(post_response.contains("\"kind\":\"bme280\""));
-        assert!(post_response.contains("\"kind\":\"servo\""));
-
-        let wiring_response =
-            send_request(addr, "GET /api/wiring HTTP/1.1\r\nHost: localhost\r\n\r\n");
-        assert!(wiring_response.contains("\"selected_devices\":[\"bme280\",\"servo\"]"));
-        assert!(wiring_response.contains("\"devices\":["));
-        assert!(wiring_response.contains("\"kind\":\"bme280\""));
-        assert!(wiring_response.contains("\"kind\":\"servo\""));
-
-        let svg_response = send_request(
+        send_request(addr, &post_request);
+        let response = send_request(
             addr,
-            "GET /api/wiring/svg HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            "GET /api/wiring/check HTTP/1.1\r\nHost: localhost\r\n\r\n",
         );
-        assert!(svg_response.contains("BME280"));
-        assert!(svg_response.contains("Servo"));
-        assert!(!svg_response.contains("MPU6050"));
+        assert!(!response.contains(r#""rule":"i2c-address""#), "{response}");
+        assert!(response.contains(r#""rule":"logic-level""#), "{response}");
 
         server.join().expect("server thread should exit");
     }
 
     #[test]
-    fn wiring_endpoint_filters_unsupported_camera_from_arduino_nano() {
+    fn wiring_export_endpoint_downloads_netlist_breadboard_bom_and_board_setup() {
         let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
         let addr = listener
             .local_addr()
             .expect("listener should have local addr");
-        let ctx = ServerContext::new(BoardProfile::OriginalEsp32);
-
-        let (board_tx, board_rx) = mpsc::channel::<BoardProfile>();
-        drop(board_rx);
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
 
         let ctx_for_thread = Arc::clone(&ctx);
         let server = thread::spawn(move || {
-            for _ in 0..3 {
+            for _ in 0..6 {
                 let (stream, _) = listener.accept().expect("test client should connect");
-                handle_connection(stream, Arc::clone(&ctx_for_thread), board_tx.clone());
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
             }
         });
+        let get = |path: &str| {
+            send_request(
+                addr,
+                &format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            )
+        };
 
-        let body = r#"{"board":"arduino-nano","sensor_profile":"full"}"#;
-        let post_request = format!(
-            "POST /api/wiring HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
-            body.len(),
-            body
+        let body = r#"{"selected_devices":["bme280","hc_sr04"]}"#;
+        send_request(
+            addr,
+            &format!(
+                "POST /api/wiring HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            ),
         );
-        let post_response = send_request(addr, &post_request);
-        assert!(post_response.contains("\"board\":\"nano\""));
-        assert!(!post_response.contains("\"esp32_cam\""));
-        assert!(!post_response.contains("ESP32-CAM"));
 
-        let wiring_response =
-            send_request(addr, "GET /api/wiring HTTP/1.1\r\nHost: localhost\r\n\r\n");
-        assert!(!wiring_response.contains("\"esp32_cam\""));
+        let response = get("/api/wiring/export?format=kicad");
+        assert!(
+            response.contains("Content-Disposition: attachment; filename=\"wiring-default.net\""),
+            "{response}"
+        );
+        assert!(response.contains("(export (version \"E\")"), "{response}");
+        assert!(response.contains("(value \"GY-BME280\")"), "{response}");
 
-        let svg_response = send_request(
-            addr,
-            "GET /api/wiring/svg HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        let response = get("/api/wiring/export?format=breadboard");
+        assert!(response.contains("orange VIN"), "{response}");
+
+        let response = get("/api/wiring/export?format=bom");
+        assert!(response.contains("Content-Type: text/csv"), "{response}");
+        assert!(
+            response.contains("\r\n\r\npart,description,address,quantity,references\n"),
+            "{response}"
+        );
+        assert!(
+            response.contains("HC-SR04,ultrasonic distance sensor,,1,M2"),
+            "{response}"
         );
-        assert!(!svg_response.contains("CAM/N/A"));
-        assert!(!svg_response.contains("GPIO:N/A"));
 
-        server.join().expect("server thread should exit");
-    }
+        let response = get("/api/wiring/export?format=board-setup");
+        assert!(
+            response.contains("filename=\"wiring-default.rs\""),
+            "{response}"
+        );
+        assert!(
+            response.contains("pub const HC_SR04_TRIG_GPIO: u8 = 5;"),
+            "{response}"
+        );
+        assert!(response.contains("pub fn run(peripherals"), "{response}");
 
-    // ── board_kind_from_str ───────────────────────────────────────────────────
-    #[test]
-    fn board_kind_from_str_known_values() {
-        assert!(matches!(board_kind_from_str("esp32"), BoardKind::Esp32));
-        assert!(matches!(
-            board_kind_from_str("m5stickc"),
-            BoardKind::M5StickC
-        ));
-        assert!(matches!(
-            board_kind_from_str("arduino-nano"),
-            BoardKind::ArduinoNano
-        ));
-        assert!(matches!(
-            board_kind_from_str("raspi-pico"),
-            BoardKind::RaspberryPiPico
-        ));
+        let response = get("/api/wiring/export?format=fzz");
+        assert!(response.starts_with("HTTP/1.1 400"), "{response}");
+
+        server.join().expect("server thread should exit");
     }
 
     #[test]
-    fn board_kind_from_str_defaults_to_esp32() {
-        assert!(matches!(board_kind_from_str(""), BoardKind::Esp32));
-        assert!(matches!(board_kind_from_str("unknown"), BoardKind::Esp32));
+    fn wiring_project_endpoint_loads_and_saves_layout_and_editor() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should have local addr");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            for _ in 0..6 {
+                let (stream, _) = listener.accept().expect("test client should connect");
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
+            }
+        });
+        let post = |path: &str, body: &str| {
+            send_request(
+                addr,
+                &format!(
+                    "POST {path} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                    body.len()
+                ),
+            )
+        };
+
+        let response = post(
+            "/api/wiring/editor",
+            r#"{"nodes":[{"id":"n1"}],"edges":[]}"#,
+        );
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        let response = post("/api/wiring/editor", "not json");
+        assert!(response.starts_with("HTTP/1.1 400"), "{response}");
+
+        let project = r#"{"format":"mcu-hal-sim-wiring","version":1,"name":"bench","board":"nano",
+            "sensor_profile":"minimal","pins":{"sda":"A4"},
+            "devices":[{"kind":"bme280","address":"0x76","label":"Indoor"},{"kind":"lcd1602"}]}"#;
+        let response = post("/api/wiring/project", project);
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.contains(r#""board":"nano""#), "{response}");
+        assert!(
+            response.contains(r#"{"kind":"bme280","address":"0x76","label":"Indoor"}"#),
+            "{response}"
+        );
+
+        let response = send_request(
+            addr,
+            "GET /api/wiring/project HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        );
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or_default();
+        let saved = WiringProject::parse(body).expect("saved project should parse");
+        assert_eq!(saved.name.as_deref(), Some("bench"));
+        assert_eq!(saved.board, "nano");
+        assert_eq!(saved.devices[0].address.as_deref(), Some("0x76"));
+        assert_eq!(saved.editor.unwrap()["nodes"][0]["id"], "n1");
+
+        let response = post(
+            "/api/wiring/project",
+            r#"{"format":"mcu-hal-sim-wiring","version":9,"board":"nano","devices":[]}"#,
+        );
+        assert!(response.starts_with("HTTP/1.1 400"), "{response}");
+        assert!(response.contains("newer"), "{response}");
+
+        // Switching boards drops the project's pin layout.
+        let response = post("/api/wiring", r#"{"board":"original-esp32"}"#);
+        assert!(response.contains(r#""sda_pin":"GPIO21""#), "{response}");
+        assert!(response.contains(r#""address":"0x77""#), "{response}");
+
+        server.join().expect("server thread should exit");
+    }
+
+    #[test]
+    fn wiring_editor_edits_apply_to_the_session_and_the_check() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should have local addr");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            for _ in 0..5 {
+                let (stream, _) = listener.accept().expect("test client should connect");
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
+            }
+        });
+        let get = |path: &str| {
+            let response = send_request(
+                addr,
+                &format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            );
+            let body = response.split("\r\n\r\n").nth(1).unwrap_or_default();
+            serde_json::from_str::<serde_json::Value>(body).expect("response should be JSON")
+        };
+
+        let devices = get("/api/wiring/devices");
+        let hc_sr04 = devices
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|device| device["kind"] == "hc_sr04")
+            .expect("HC-SR04 should be listed");
+        assert_eq!(hc_sr04["address"], serde_json::Value::Null);
+        assert_eq!(
+            hc_sr04["signals"][2],
+            serde_json::json!({"signal": "TRIG", "pin": "trig"})
+        );
+        assert_eq!(devices[0]["address"], "0x77");
+
+        // What the editor does: edit the saved project and post it back.
+        let mut project = get("/api/wiring/project");
+        project["pins"]["sda"] = "GPIO34".into();
+        project["devices"] = serde_json::json!([{"kind": "mpu6050", "address": "0x69"}]);
+        project["editor"] = serde_json::json!({"nodes": [{"kind": "mpu6050", "x": 400, "y": 30}]});
+        let body = project.to_string();
+        let response = send_request(
+            addr,
+            &format!(
+                "POST /api/wiring/project HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        {
+            let wiring = ctx.default.wiring_state.lock().unwrap().clone();
+            assert_eq!(wiring.selected_devices, vec![DeviceKind::Mpu6050]);
+        }
+
+        let report = get("/api/wiring/check").to_string();
+        assert!(
+            report.contains("GPIO34 (I2C SDA) has no I2C function on the original ESP32"),
+            "{report}"
+        );
+        let saved = get("/api/wiring/project");
+        assert_eq!(saved["devices"][0]["label"], "MPU6050 (0x69)");
+        assert_eq!(saved["editor"]["nodes"][0]["kind"], "mpu6050");
+
+        server.join().expect("server thread should exit");
+    }
+
+    #[test]
+    fn wiring_state_and_svg_reflect_explicit_selection_over_profile() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should have local addr");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            for _ in 0..3 {
+                let (stream, _) = listener.accept().expect("test client should connect");
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
+            }
+        });
+
+        let body = r#"{"sensor_profile":"robot","selected_devices":["bme280","servo"]}"#;
+        let post_request = format!(
+            "POST /api/wiring HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let post_response = send_request(addr, &post_request);
+        assert!(post_response.contains("\"sensor_profile\":\"robot\""));
+        assert!(post_response.contains("\"selected_devices\":[\"bme280\",\"servo\"]"));
+        assert!(post_response.contains("\"devices\":["));
+        assert!(post_response.contains("\"kind\":\"bme280\""));
+        assert!(post_response.contains("\"kind\":\"servo\""));
+
+        let wiring_response =
+            send_request(addr, "GET /api/wiring HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(wiring_response.contains("\"selected_devices\":[\"bme280\",\"servo\"]"));
+        assert!(wiring_response.contains("\"devices\":["));
+        assert!(wiring_response.contains("\"kind\":\"bme280\""));
+        assert!(wiring_response.contains("\"kind\":\"servo\""));
+
+        let svg_response = send_request(
+            addr,
+            "GET /api/wiring/svg HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        );
+        assert!(svg_response.contains("BME280"));
+        assert!(svg_response.contains("Servo"));
+        assert!(!svg_response.contains("MPU6050"));
+
+        server.join().expect("server thread should exit");
+    }
+
+    #[test]
+    fn wiring_endpoint_switches_to_m5stickc_with_onboard_parts() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should have local addr");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().expect("test client should connect");
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
+            }
+        });
+
+        let body = r#"{"board":"m5stickc","sensor_profile":"robot"}"#;
+        let post_request = format!(
+            "POST /api/wiring HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let post_response = send_request(addr, &post_request);
+        assert!(
+            post_response.contains(r#""board":"m5stickc""#),
+            "{post_response}"
+        );
+        assert!(post_response.contains(r#""sda_pin":"GPIO32""#));
+        assert!(post_response.contains(r#""name":"AXP192""#));
+        assert!(!post_response.contains(r#""l298n""#));
+
+        let svg_response = send_request(
+            addr,
+            "GET /api/wiring/svg HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        );
+        assert!(svg_response.contains(r#"style="fill:#3a2414;stroke:#e07a2e""#));
+        assert!(svg_response.contains("Onboard: MPU6886 0x68"));
+
+        server.join().expect("server thread should exit");
+    }
+
+    #[test]
+    fn wiring_endpoint_filters_unsupported_camera_from_arduino_nano() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should have local addr");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            for _ in 0..3 {
+                let (stream, _) = listener.accept().expect("test client should connect");
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
+            }
+        });
+
+        let body = r#"{"board":"arduino-nano","sensor_profile":"full"}"#;
+        let post_request = format!(
+            "POST /api/wiring HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let post_response = send_request(addr, &post_request);
+        assert!(post_response.contains("\"board\":\"nano\""));
+        assert!(!post_response.contains("\"esp32_cam\""));
+        assert!(!post_response.contains("ESP32-CAM"));
+
+        let wiring_response =
+            send_request(addr, "GET /api/wiring HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(!wiring_response.contains("\"esp32_cam\""));
+
+        let svg_response = send_request(
+            addr,
+            "GET /api/wiring/svg HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        );
+        assert!(!svg_response.contains("CAM/N/A"));
+        assert!(!svg_response.contains("GPIO:N/A"));
+
+        server.join().expect("server thread should exit");
+    }
+
+    // ── flash board selection ─────────────────────────────────────────────────
+    #[test]
+    fn flash_board_from_query_known_values() {
+        let method = |value: &str| {
+            BoardProfile::from_arg(Some(value))
+                .flash()
+                .map(|flash| flash.method)
+        };
+        assert_eq!(method("esp32"), Some(FlashMethod::Espflash));
+        assert_eq!(method("m5stickc"), Some(FlashMethod::Espflash));
+        assert_eq!(method("arduino-nano"), Some(FlashMethod::Ravedude));
+        assert_eq!(method("raspi-pico"), Some(FlashMethod::Uf2));
+        assert_eq!(
+            BoardProfile::ARDUINO_NANO
+                .flash()
+                .and_then(|flash| flash.avrdude_part.as_deref()),
+            Some("m328p")
+        );
+    }
+
+    #[test]
+    fn flash_board_from_query_defaults_to_esp32() {
+        assert_eq!(
+            BoardProfile::from_arg(Some("")),
+            BoardProfile::ORIGINAL_ESP32
+        );
+        assert_eq!(
+            BoardProfile::from_arg(Some("unknown")),
+            BoardProfile::ORIGINAL_ESP32
+        );
+    }
+
+    #[test]
+    fn flash_targets_come_from_board_definitions() {
+        let targets = flash_targets();
+        let ids: Vec<&str> = targets.iter().map(|t| t.id).collect();
+        assert_eq!(
+            ids[..8],
+            [
+                "esp32-climate-display",
+                "esp32-robot-base",
+                "esp32-bringup",
+                "arduino-nano-climate-display",
+                "arduino-nano-bringup",
+                "raspi-pico-climate-display",
+                "raspi-pico-bringup",
+                "m5stickc-bringup",
+            ]
+        );
+        let pico = &targets[6];
+        assert_eq!(pico.board, BoardProfile::RASPBERRY_PI_PICO);
+        assert_eq!(pico.binary_name, "raspi-pico-bringup");
+        assert_eq!(pico.flash.target_triple, "thumbv6m-none-eabi");
+        assert!(!pico.flash.method.needs_port());
+    }
+
+    #[test]
+    fn boards_endpoint_lists_registered_boards() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener
+            .local_addr()
+            .expect("listener should have local addr");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().expect("test client should connect");
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
+            }
+        });
+
+        let boards = send_request(addr, "GET /api/boards HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        let boards: serde_json::Value =
+            serde_json::from_str(boards.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        let pico = &boards[2];
+        assert_eq!(pico["slug"], "pico");
+        assert_eq!(pico["name"], "Raspberry Pi Pico");
+        assert_eq!(pico["mcu"], "RP2040");
+        assert_eq!(pico["flash"], "uf2");
+        assert_eq!(pico["needs_port"], false);
+        assert_eq!(pico["rails"], serde_json::json!(["3V3", "VBUS (5V)"]));
+        assert_eq!(
+            pico["pins"][0],
+            serde_json::json!({"name": "GP0", "caps": ["pwm", "i2c"], "input_only": false, "reserved": false})
+        );
+        assert_eq!(boards[0]["slug"], "esp32");
+        assert_eq!(boards[0]["needs_port"], true);
+        let gpio34 = boards[0]["pins"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|pin| pin["name"] == "GPIO34")
+            .expect("GPIO34 is on the ESP32 header");
+        assert_eq!(gpio34["input_only"], true);
+
+        let targets = send_request(
+            addr,
+            "GET /api/flash/targets HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        );
+        assert!(
+            targets.contains(r#"{"id":"m5stickc-bringup","label":"Bringup (GPIO / I2C check)","board":"M5StickC","slug":"m5stickc","needs_port":true}"#),
+            "{targets}"
+        );
+
+        server.join().expect("server thread should exit");
     }
 
     // ── detect_build_target / detect_binary_name ─────────────────────────────
@@ -1464,18 +2333,16 @@ mod tests {
     fn api_history_endpoint_returns_json() {
         let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
         let addr = listener.local_addr().expect("addr");
-        let ctx = ServerContext::new(BoardProfile::OriginalEsp32);
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
         {
-            let mut hist = ctx.history.lock().unwrap();
+            let mut hist = ctx.default.history.lock().unwrap();
             hist.push_climate(2500, 6000, Some(101325));
         }
 
-        let (board_tx, board_rx) = mpsc::channel::<BoardProfile>();
-        drop(board_rx);
         let ctx_for_thread = Arc::clone(&ctx);
         let server = thread::spawn(move || {
             let (stream, _) = listener.accept().expect("test client should connect");
-            handle_connection(stream, ctx_for_thread, board_tx);
+            handle_connection(stream, ctx_for_thread);
         });
 
         let resp = send_request(
@@ -1499,18 +2366,16 @@ mod tests {
     fn api_history_endpoint_distance_returns_json() {
         let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
         let addr = listener.local_addr().expect("addr");
-        let ctx = ServerContext::new(BoardProfile::OriginalEsp32);
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
         {
-            let mut hist = ctx.history.lock().unwrap();
+            let mut hist = ctx.default.history.lock().unwrap();
             hist.push_distance(Some(400));
         }
 
-        let (board_tx, board_rx) = mpsc::channel::<BoardProfile>();
-        drop(board_rx);
         let ctx_for_thread = Arc::clone(&ctx);
         let server = thread::spawn(move || {
             let (stream, _) = listener.accept().expect("test client should connect");
-            handle_connection(stream, ctx_for_thread, board_tx);
+            handle_connection(stream, ctx_for_thread);
         });
 
         let resp = send_request(
@@ -1525,4 +2390,752 @@ mod tests {
         assert!(resp.contains("400"), "body missing value: {resp}");
         server.join().expect("server thread should exit");
     }
+
+    #[test]
+    fn api_history_endpoint_reads_ingest_store() {
+        use platform_pc_sim::ingest_store::parse_reading;
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("readings.csv");
+        {
+            let mut store = CsvReadingStore::open(&path).expect("store should open");
+            for body in [
+                r#"{"device_id":"esp32-01","temperature":21.50,"humidity":40.00}"#,
+                r#"{"device_id":"other","temperature":5.00,"humidity":90.00}"#,
+            ] {
+                store
+                    .append(parse_reading(body.as_bytes(), 0).unwrap())
+                    .unwrap();
+            }
+        }
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener.local_addr().expect("addr");
+        let configured = ServerContext::with_stores(BoardProfile::ORIGINAL_ESP32, Some(path), None);
+        let unconfigured = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+        let server = thread::spawn(move || {
+            for ctx in [configured, unconfigured] {
+                let (stream, _) = listener.accept().expect("test client should connect");
+                handle_connection(stream, ctx);
+            }
+        });
+
+        let request = "GET /api/history?source=ingest&device_id=esp32-01 HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+        let resp = send_request(addr, request);
+        assert!(resp.contains("200 OK"), "expected 200, got: {resp}");
+        assert!(
+            resp.ends_with(r#"{"temperature":[21.5],"humidity":[40.0],"pressure":[null]}"#),
+            "unexpected body: {resp}"
+        );
+        let resp = send_request(addr, request);
+        assert!(resp.contains("503"), "expected 503, got: {resp}");
+        server.join().expect("server thread should exit");
+    }
+
+    #[test]
+    fn history_store_endpoints_query_downsample_and_export() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
+        let wiring_state = test_wiring_state();
+        {
+            let mut store = HistoryStore::open(dir.path(), RetentionPolicy::default()).unwrap();
+            for i in 0..10u64 {
+                let state = rig.step(&wiring_state);
+                store
+                    .append(&HistoryRecord::from_state(&state, 1_000_000 + i * 1000))
+                    .unwrap();
+            }
+            *ctx.default.history_store.lock().unwrap() = Some(store);
+        }
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener.local_addr().expect("addr");
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            for _ in 0..5 {
+                let (stream, _) = listener.accept().expect("test client should connect");
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
+            }
+        });
+        let get = |path: &str| {
+            let mut client = TcpStream::connect(addr).expect("client should connect");
+            client
+                .write_all(
+                    format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                        .as_bytes(),
+                )
+                .expect("request should be written");
+            let mut response = Vec::new();
+            client.read_to_end(&mut response).expect("response");
+            response
+        };
+        let body_of = |response: &[u8]| {
+            let start = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+            response[start..].to_vec()
+        };
+
+        let response = get("/api/history?panel=imu&from=1002000&points=4");
+        let json: serde_json::Value = serde_json::from_slice(&body_of(&response)).unwrap();
+        assert_eq!(json["panel"], "imu");
+        assert_eq!(json["timestamp_ms"].as_array().unwrap().len(), 4);
+        assert_eq!(json["accel_z_mg"].as_array().unwrap().len(), 4);
+        assert!(json["accel_z_mg"][0].is_number(), "{json}");
+        assert!(json.get("temperature_c").is_none());
+
+        let response = get("/api/history/export?format=csv&panel=climate&to=1001000");
+        let text = String::from_utf8(response).unwrap();
+        assert!(
+            text.contains(
+                "Content-Disposition: attachment; filename=\"dashboard-history-climate.csv\""
+            ),
+            "{text}"
+        );
+        let csv = text.split("\r\n\r\n").nth(1).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "timestamp_ms,tick,temperature_c,humidity_percent,pressure_pa"
+        );
+        assert_eq!(lines.len(), 3, "{csv}");
+        assert!(lines[1].starts_with("1000000,1,"), "{csv}");
+
+        let response = get("/api/history/export?format=parquet");
+        let parquet = body_of(&response);
+        assert!(String::from_utf8_lossy(&response).contains("application/vnd.apache.parquet"));
+        assert!(parquet.starts_with(b"PAR1") && parquet.ends_with(b"PAR1"));
+
+        let response = String::from_utf8(get("/api/history?panel=board")).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400"), "{response}");
+
+        *ctx.default.history_store.lock().unwrap() = None;
+        let response = String::from_utf8(get("/api/history/export")).unwrap();
+        assert!(response.starts_with("HTTP/1.1 503"), "{response}");
+        server.join().expect("server thread should exit");
+    }
+
+    #[test]
+    fn sessions_endpoint_creates_scopes_and_deletes_sessions() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener.local_addr().expect("addr");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            for _ in 0..10 {
+                let (stream, _) = listener.accept().expect("test client should connect");
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
+            }
+        });
+        let request = |method: &str, path: &str, body: &str| {
+            send_request(
+                addr,
+                &format!(
+                    "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+                    body.len()
+                ),
+            )
+        };
+
+        let body = r#"{"name":"nano","board":"arduino-nano","sensor_profile":"minimal"}"#;
+        let response = request("POST", "/api/sessions", body);
+        assert!(response.starts_with("HTTP/1.1 201"), "{response}");
+        assert!(response.contains(r#""board":"Arduino Nano""#), "{response}");
+        assert!(request("POST", "/api/sessions", body).starts_with("HTTP/1.1 409"));
+        let response = request("POST", "/api/sessions", r#"{"name":"Node 1"}"#);
+        assert!(response.starts_with("HTTP/1.1 400"), "{response}");
+
+        let response = request("GET", "/api/sessions", "");
+        assert!(response.contains(r#""name":"default""#), "{response}");
+        assert!(response.contains(r#""name":"nano""#), "{response}");
+
+        // Scoped routes act on their own session only.
+        let response = request("POST", "/api/sessions/nano/control/pause", "");
+        assert!(response.contains(r#""paused":true"#), "{response}");
+        assert!(!ctx.default.control.lock().unwrap().is_paused());
+        let response = request("GET", "/api/sessions/nano/wiring", "");
+        assert!(
+            response.contains(r#""sensor_profile":"minimal""#),
+            "{response}"
+        );
+        let response = request("GET", "/api/wiring", "");
+        assert!(
+            response.contains(r#""sensor_profile":"full""#),
+            "{response}"
+        );
+        assert!(request("GET", "/api/sessions/ghost/state", "").starts_with("HTTP/1.1 404"));
+
+        // The created session runs its own simulation thread.
+        let nano = ctx.sessions.get("nano").expect("nano session");
+        let started = Instant::now();
+        while !nano.latest_json.lock().unwrap().contains("Arduino Nano") {
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "nano never pushed"
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        assert!(request("DELETE", "/api/sessions/default", "").starts_with("HTTP/1.1 409"));
+        assert!(request("DELETE", "/api/sessions/nano", "").starts_with("HTTP/1.1 200"));
+        assert!(ctx.sessions.get("nano").is_none());
+        server.join().expect("server thread should exit");
+    }
+
+    #[test]
+    fn session_paths_and_specs_parse() {
+        assert_eq!(
+            split_session_path("/api/sessions/nano/control/pause"),
+            Some(("nano", "/api/control/pause".to_string()))
+        );
+        assert_eq!(
+            split_session_path("/api/sessions/nano/metrics"),
+            Some(("nano", "/metrics".to_string()))
+        );
+        assert_eq!(split_session_path("/api/sessions/nano"), None);
+        assert_eq!(split_session_path("/api/sessions/nano/"), None);
+        assert_eq!(split_session_path("/api/state"), None);
+
+        let specs = parse_session_specs("nano=arduino-nano, node-2").unwrap();
+        assert_eq!(
+            specs,
+            vec![
+                ("nano".to_string(), BoardProfile::ARDUINO_NANO),
+                ("node-2".to_string(), BoardProfile::ORIGINAL_ESP32),
+            ]
+        );
+        assert!(parse_session_specs("Nano=esp32").is_err());
+    }
+
+    #[test]
+    fn sensor_history_buffer_seeds_from_persisted_records() {
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
+        let wiring_state = test_wiring_state();
+        let records: Vec<_> = (0..3)
+            .map(|i| HistoryRecord::from_state(&rig.step(&wiring_state), i))
+            .collect();
+        let mut buf = SensorHistoryBuffer::new(2);
+        buf.seed(&records);
+        assert_eq!(buf.climate.len(), 2);
+        assert_eq!(buf.distance.len(), 2);
+        let expected = records[2].value("temperature_c").unwrap();
+        assert_eq!(f64::from(buf.climate[1].0) / 100.0, expected);
+    }
+
+    #[test]
+    fn api_telemetry_endpoint_uses_firmware_encoder() {
+        use hal_api::sensor::EnvReading;
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener.local_addr().expect("addr");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            for _ in 0..3 {
+                let (stream, _) = listener.accept().expect("test client should connect");
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
+            }
+        });
+
+        let request = |query: &str| {
+            send_request(
+                addr,
+                &format!(
+                    "GET /api/telemetry{query} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+                ),
+            )
+        };
+
+        let resp = request("");
+        assert!(
+            resp.contains("503"),
+            "expected 503 before first tick: {resp}"
+        );
+
+        *ctx.default.latest_telemetry.lock().unwrap() =
+            Some(TelemetryFrame::new("sim").with_env(EnvReading::new(2485, 4320, None)));
+
+        let resp = request("?format=json");
+        assert!(resp.contains("Content-Type: application/json"), "{resp}");
+        assert!(
+            resp.ends_with(r#"{"device_id":"sim","temperature":24.85,"humidity":43.20}"#),
+            "unexpected body: {resp}"
+        );
+
+        let resp = request("?format=line");
+        assert!(
+            resp.ends_with("pc_sim,device_id=sim temperature=24.85,humidity=43.20"),
+            "unexpected body: {resp}"
+        );
+        server.join().expect("server thread should exit");
+    }
+
+    #[test]
+    fn metrics_endpoint_exports_panels_i2c_and_diag_counters() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener.local_addr().expect("addr");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().expect("test client should connect");
+                handle_connection(stream, Arc::clone(&ctx_for_thread));
+            }
+        });
+        let request = "GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+
+        let resp = send_request(addr, request);
+        assert!(
+            resp.contains("503"),
+            "expected 503 before first push: {resp}"
+        );
+
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
+        let wiring_state = test_wiring_state();
+        let state = (0..5).map(|_| rig.step(&wiring_state)).last().unwrap();
+        rig.push_diag("warn", "test warning".into());
+        let mut tick_rate = TickRate::default();
+        let start = Instant::now();
+        tick_rate.sample(start, 0);
+        *ctx.default.latest_metrics.lock().unwrap() = Some(MetricsSnapshot {
+            i2c: rig.bus.address_stats(),
+            diag_counts: rig.diag_severity_counts,
+            tick_rate_x100: tick_rate.sample(start + Duration::from_millis(100), rig.tick),
+            paused: false,
+            state,
+        });
+
+        let resp = send_request(addr, request);
+        server.join().expect("server thread should exit");
+        assert!(resp.contains("200 OK"), "{resp}");
+        assert!(
+            resp.contains("Content-Type: application/openmetrics-text; version=1.0.0"),
+            "{resp}"
+        );
+        for line in [
+            "# TYPE mcu_temperature_celsius gauge\n",
+            "mcu_board_info{device_id=\"original ESP32\",mcu=\"ESP32\",session=\"default\"} 1\n",
+            "mcu_sim_ticks_total 5\n",
+            "mcu_sim_tick_rate_hz 50.00\n",
+            "mcu_sim_paused 0\n",
+            "mcu_i2c_operations_total{address=\"0x77\",kind=\"write_read\"} ",
+            "mcu_i2c_errors_total{address=\"0x77\"} 0\n",
+            "mcu_diag_events_total{severity=\"warn\"} 1\n",
+            "mcu_wiring_device_selected{device=\"bme280\"} 1\n",
+            "mcu_motor_duty_percent{side=\"left\",direction=",
+        ] {
+            assert!(resp.contains(line), "missing {line:?} in {resp}");
+        }
+        assert!(resp.ends_with("# EOF\n"), "{resp}");
+    }
+
+    #[test]
+    fn device_simulation_rig_applies_sensor_overrides_and_manual_actuators() {
+        use platform_pc_sim::sim_control::{ControlCommand, SensorChannel, SimControl};
+
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
+        let wiring_state = WiringState {
+            board: BoardProfile::ORIGINAL_ESP32,
+            sensor_profile: SensorProfile::Full,
+            selected_devices: SensorProfile::Full.device_kinds().to_vec(),
+            show_bus_labels: false,
+            layout: None,
+        };
+        let mut control = SimControl::new();
+        for command in [
+            ControlCommand::Set(SensorChannel::TemperatureC, 31.5),
+            ControlCommand::Set(SensorChannel::DistanceMm, 360.0),
+            ControlCommand::Set(SensorChannel::Co2Ppm, 1500.0),
+        ] {
+            control.apply(command).unwrap();
+        }
+        rig.controls = *control.active();
+
+        let state = rig.step(&wiring_state);
+        assert_eq!(state.climate.temperature_c, Some(31.5));
+        // Injected through the BME280 registers, so the LCD app sees it too
+        assert_eq!(state.climate.app_frame[0].trim(), "Temp    31.5C");
+        assert_eq!(state.distance.distance_mm, Some(360));
+        assert_eq!(state.servo.angle_degrees, 180);
+        assert_eq!(state.gas.co2_ppm, Some(1500));
+        assert!(state
+            .diagnostics
+            .active_alarms
+            .iter()
+            .any(|alarm| alarm.contains("co2")));
+
+        control
+            .apply(ControlCommand::Servo(Some(45)))
+            .and_then(|()| {
+                control.apply(ControlCommand::Motors(Some((
+                    MotorCommand::new(MotorDirection::Reverse, 70),
+                    MotorCommand::new(MotorDirection::Brake, 0),
+                ))))
+            })
+            .unwrap();
+        rig.controls = *control.active();
+        let state = rig.step(&wiring_state);
+        assert_eq!(state.servo.angle_degrees, 45);
+        assert_eq!(state.motor_driver.left.direction, "reverse");
+        assert_eq!(state.motor_driver.left.duty_percent, 70);
+        assert_eq!(state.motor_driver.right.direction, "brake");
+
+        control.apply(ControlCommand::Reset).unwrap();
+        rig.controls = *control.active();
+        // The climate app only re-reads the sensor every 5 ticks
+        let state = (0..5).map(|_| rig.step(&wiring_state)).last().unwrap();
+        assert_ne!(state.climate.temperature_c, Some(31.5));
+        assert_ne!(state.servo.angle_degrees, 45);
+    }
+
+    #[test]
+    fn device_simulation_rig_applies_imu_and_rtc_overrides() {
+        use platform_pc_sim::sim_control::{ControlCommand, SensorChannel, SimControl};
+
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
+        let wiring_state = WiringState {
+            board: BoardProfile::ORIGINAL_ESP32,
+            sensor_profile: SensorProfile::Full,
+            selected_devices: SensorProfile::Full.device_kinds().to_vec(),
+            show_bus_labels: false,
+            layout: None,
+        };
+        let mut control = SimControl::new();
+        for command in [
+            ControlCommand::Set(SensorChannel::AccelZMg, -1000.0),
+            ControlCommand::Set(SensorChannel::GyroXDps, 12.5),
+            ControlCommand::Set(SensorChannel::RtcUnixS, 1_760_875_200.0),
+        ] {
+            control.apply(command).unwrap();
+        }
+        rig.controls = *control.active();
+
+        let state = rig.step(&wiring_state);
+        assert_eq!(state.imu.accel_mg[2], -1000);
+        assert_eq!(state.imu.gyro_mdps[0], 12_500);
+        assert_eq!(state.rtc.datetime_str, "2025-10-19 12:00:00");
+        let demo = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32).step(&wiring_state);
+        assert_eq!(state.imu.accel_mg[..2], demo.imu.accel_mg[..2]);
+
+        control.apply(ControlCommand::Reset).unwrap();
+        rig.controls = *control.active();
+        let state = (0..3).map(|_| rig.step(&wiring_state)).last().unwrap();
+        assert_ne!(state.imu.accel_mg[2], -1000);
+        assert!(state.rtc.datetime_str.starts_with("2025-05-04"));
+    }
+
+    #[test]
+    fn control_endpoints_apply_validate_and_record() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener.local_addr().expect("addr");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            for stream in listener.incoming().take(6) {
+                handle_connection(stream.unwrap(), Arc::clone(&ctx_for_thread));
+            }
+        });
+        let post = |path: &str, body: &str| {
+            send_request(
+                addr,
+                &format!(
+                    "POST {path} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+                    body.len()
+                ),
+            )
+        };
+
+        let resp = post("/api/control/record/start", "");
+        assert!(resp.contains(r#""recording":true"#), "{resp}");
+        let resp = post(
+            "/api/control/sensor",
+            r#"{"sensor":"temperature_c","value":30}"#,
+        );
+        assert!(resp.contains(r#""temperature_c":30.0"#), "{resp}");
+        let resp = post(
+            "/api/control/motors",
+            r#"{"left":{"direction":"forward","duty_percent":55},"right":{"direction":"coast","duty_percent":0}}"#,
+        );
+        assert!(
+            resp.contains(r#""motors":[{"direction":"forward""#),
+            "{resp}"
+        );
+        let resp = post("/api/control/servo", r#"{"angle":200}"#);
+        assert!(resp.contains("400 Bad Request"), "{resp}");
+        assert!(
+            resp.contains("servo angle 200 is outside 0..=180"),
+            "{resp}"
+        );
+        let resp = post("/api/control/step", r#"{"ticks":3}"#);
+        assert!(
+            resp.contains(r#""paused":true,"tick":0,"pending_steps":3"#),
+            "{resp}"
+        );
+        let resp = post("/api/control/record/stop", "");
+        assert!(
+            resp.ends_with(
+                "# device-dashboard-web scenario\n0 set temperature_c 30\n0 motors forward 55 coast 0\n"
+            ),
+            "{resp}"
+        );
+
+        server.join().expect("server thread should exit");
+        let control = ctx.default.control.lock().unwrap();
+        assert!(control.is_paused());
+        assert_eq!(control.active().servo_angle, None);
+    }
+
+    #[test]
+    fn scenario_upload_reads_bodies_split_across_segments() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener.local_addr().expect("addr");
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+
+        let ctx_for_thread = Arc::clone(&ctx);
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("test client should connect");
+            handle_connection(stream, ctx_for_thread);
+        });
+
+        let scenario: String = (0..400)
+            .map(|tick| format!("{tick} servo {}\n", tick % 180))
+            .collect();
+        let mut client = TcpStream::connect(addr).expect("client should connect");
+        client
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+        write!(
+            client,
+            "POST /api/control/scenario HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+            scenario.len()
+        )
+        .unwrap();
+        client.flush().unwrap();
+        thread::sleep(Duration::from_millis(50));
+        client.write_all(scenario.as_bytes()).unwrap();
+        let resp = read_response(&mut client);
+
+        assert!(resp.contains(r#""playback_remaining":400"#), "{resp}");
+        server.join().expect("server thread should exit");
+    }
+
+    fn test_wiring_state() -> WiringState {
+        WiringState {
+            board: BoardProfile::ORIGINAL_ESP32,
+            sensor_profile: SensorProfile::Full,
+            selected_devices: SensorProfile::Full.device_kinds().to_vec(),
+            show_bus_labels: false,
+            layout: None,
+        }
+    }
+
+    /// Connects a WebSocket client to a `handle_connection` thread.
+    fn connect_ws(
+        ctx: &Arc<ServerContext>,
+    ) -> (
+        platform_pc_sim::websocket::WebSocket<TcpStream>,
+        thread::JoinHandle<()>,
+    ) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("listener should bind");
+        let addr = listener.local_addr().expect("addr");
+        let ctx_for_thread = Arc::clone(ctx);
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("test client should connect");
+            handle_connection(stream, ctx_for_thread);
+        });
+
+        let mut client = TcpStream::connect(addr).expect("client should connect");
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        platform_pc_sim::websocket::client_handshake(&mut client, "localhost", "/api/ws")
+            .expect("upgrade should succeed");
+        (
+            platform_pc_sim::websocket::WebSocket::client(client),
+            server,
+        )
+    }
+
+    fn read_ws_json(
+        socket: &mut platform_pc_sim::websocket::WebSocket<TcpStream>,
+    ) -> serde_json::Value {
+        match socket.read_message().expect("server should send a message") {
+            platform_pc_sim::websocket::Message::Text(text) => {
+                serde_json::from_str(&text).expect("server messages are JSON")
+            }
+            other => panic!("unexpected message {other:?}"),
+        }
+    }
+
+    fn send_ws_json(socket: &mut platform_pc_sim::websocket::WebSocket<TcpStream>, json: &str) {
+        socket
+            .send(&platform_pc_sim::websocket::Message::Text(json.to_string()))
+            .expect("client message should be sent");
+    }
+
+    #[test]
+    fn panel_tracker_reports_changed_panels_and_rebuilds_full_state() {
+        let wiring_state = test_wiring_state();
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
+        let mut tracker = PanelTracker::new();
+
+        let state = rig.step(&wiring_state);
+        let delta = tracker.update(&state);
+        assert_eq!(delta.seq, 1);
+        assert_eq!(delta.changed.len(), PANELS.len());
+        let full: serde_json::Value = serde_json::from_str(&tracker.full_json()).unwrap();
+        let expected: serde_json::Value = serde_json::from_str(&state_to_json(&state)).unwrap();
+        assert_eq!(full, expected);
+
+        assert!(tracker.update(&state).changed.is_empty());
+
+        rig.controls.servo_angle = Some(170);
+        for _ in 0..5 {
+            rig.advance(&wiring_state);
+        }
+        let state = rig.snapshot(&wiring_state);
+        let delta = tracker.update(&state);
+        let changed: Vec<&str> = delta.changed.iter().map(|(name, _)| *name).collect();
+        assert!(changed.contains(&"servo"), "{changed:?}");
+        assert!(!changed.contains(&"board"), "{changed:?}");
+        assert!(!changed.contains(&"wiring"), "{changed:?}");
+        assert_eq!(
+            &**tracker.panel("servo").unwrap(),
+            r#"{"angle_degrees":170}"#
+        );
+        let full: serde_json::Value = serde_json::from_str(&tracker.full_json()).unwrap();
+        let expected: serde_json::Value = serde_json::from_str(&state_to_json(&state)).unwrap();
+        assert_eq!(full, expected);
+    }
+
+    #[test]
+    fn websocket_sends_subscribed_deltas_and_runs_commands() {
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
+        let mut tracker = PanelTracker::new();
+        let mut state = rig.step(&test_wiring_state());
+        let delta = tracker.update(&state);
+        ctx.default.push_panels(&tracker, delta);
+
+        let (mut socket, server) = connect_ws(&ctx);
+        let snapshot = read_ws_json(&mut socket);
+        assert_eq!(snapshot["type"], "snapshot");
+        assert_eq!(snapshot["seq"], 1);
+        assert_eq!(snapshot["resync"], false);
+        assert_eq!(snapshot["panels"].as_object().unwrap().len(), PANELS.len());
+        assert_eq!(snapshot["panels"]["board"]["board_name"], state.board_name);
+
+        send_ws_json(
+            &mut socket,
+            r#"{"type":"subscribe","panels":["servo","gas"]}"#,
+        );
+        let subscribed = read_ws_json(&mut socket);
+        assert_eq!(subscribed["type"], "subscribed");
+        assert_eq!(subscribed["panels"], serde_json::json!(["servo", "gas"]));
+        let snapshot = read_ws_json(&mut socket);
+        assert_eq!(snapshot["type"], "snapshot");
+        let names: Vec<&String> = snapshot["panels"].as_object().unwrap().keys().collect();
+        assert_eq!(names.len(), 2, "{names:?}");
+
+        // Climate and servo change; only servo is subscribed
+        state.tick += 10;
+        state.servo.angle_degrees = 120;
+        state.climate.temperature_c = Some(35.5);
+        let delta = tracker.update(&state);
+        assert_eq!(delta.changed.len(), 2);
+        ctx.default.push_panels(&tracker, delta);
+        let delta = read_ws_json(&mut socket);
+        assert_eq!(
+            delta,
+            serde_json::json!({
+                "type": "delta",
+                "seq": 2,
+                "tick": state.tick,
+                "panels": {"servo": {"angle_degrees": 120}}
+            })
+        );
+
+        send_ws_json(
+            &mut socket,
+            r#"{"type":"command","id":7,"command":"set co2_ppm 1500"}"#,
+        );
+        let ack = read_ws_json(&mut socket);
+        assert_eq!(ack["type"], "ack");
+        assert_eq!(ack["id"], 7);
+        assert_eq!(ack["control"]["sensors"]["co2_ppm"], 1500.0);
+        send_ws_json(
+            &mut socket,
+            r#"{"type":"command","id":8,"command":"servo 500"}"#,
+        );
+        let error = read_ws_json(&mut socket);
+        assert_eq!(error["type"], "error");
+        assert_eq!(error["id"], 8);
+        send_ws_json(&mut socket, r#"{"type":"subscribe","panels":["radar"]}"#);
+        assert_eq!(
+            read_ws_json(&mut socket)["message"],
+            "unknown panel `radar`"
+        );
+
+        socket
+            .send(&platform_pc_sim::websocket::Message::Close(Some(1000)))
+            .unwrap();
+        assert_eq!(
+            socket.read_message().unwrap(),
+            platform_pc_sim::websocket::Message::Close(Some(1000))
+        );
+        server.join().expect("server thread should exit");
+        assert_eq!(
+            ctx.default
+                .control
+                .lock()
+                .unwrap()
+                .active()
+                .sensors
+                .get(platform_pc_sim::sim_control::SensorChannel::Co2Ppm),
+            Some(1500.0)
+        );
+    }
+
+    #[test]
+    fn slow_websocket_client_is_resynced_instead_of_replaying_stale_deltas() {
+        const PUSHES: u64 = 40;
+        let ctx = ServerContext::new(BoardProfile::ORIGINAL_ESP32);
+        let mut rig = DeviceSimulationRig::new(BoardProfile::ORIGINAL_ESP32);
+        let mut tracker = PanelTracker::new();
+        let mut state = rig.step(&test_wiring_state());
+        let delta = tracker.update(&state);
+        ctx.default.push_panels(&tracker, delta);
+
+        let (mut socket, server) = connect_ws(&ctx);
+        assert_eq!(read_ws_json(&mut socket)["type"], "snapshot");
+
+        // Large panels fill the socket buffers while the client is not
+        // reading, so the writer blocks and the bounded queue overflows.
+        for n in 0..PUSHES {
+            state.tick += 10;
+            state.i2c.recent_operations = vec![format!("{n}:{}", "x".repeat(256 * 1024))];
+            let delta = tracker.update(&state);
+            ctx.default.push_panels(&tracker, delta);
+        }
+
+        let mut last_seq = 1;
+        let mut resynced = false;
+        while last_seq < PUSHES + 1 {
+            let message = read_ws_json(&mut socket);
+            let seq = message["seq"].as_u64().unwrap();
+            assert!(seq > last_seq, "stale message after seq {last_seq}: {seq}");
+            last_seq = seq;
+            if message["type"] == "snapshot" {
+                assert_eq!(message["resync"], true);
+                resynced = true;
+            }
+        }
+        assert!(resynced, "the lagging client should get a resync snapshot");
+
+        drop(socket);
+        ctx.default.ws_clients.lock().unwrap().clear();
+        server.join().expect("server thread should exit");
+    }
 }
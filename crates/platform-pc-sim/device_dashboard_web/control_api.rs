@@ -0,0 +1,174 @@
+use std::net::TcpStream;
+
+use hal_api::actuator::MotorCommand;
+use platform_pc_sim::sim_control::{
+    parse_direction, ControlCommand, ControlError, Scenario, SensorChannel,
+};
+use serde::Deserialize;
+
+use super::http_util::respond;
+use super::session::Session;
+
+#[derive(Deserialize)]
+struct SensorRequest {
+    sensor: String,
+    /// `null` / missing hands the sensor back to its mock.
+    value: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct ServoRequest {
+    /// `null` / missing returns the servo to distance tracking.
+    angle: Option<u16>,
+}
+
+#[derive(Deserialize)]
+struct MotorRequest {
+    direction: String,
+    duty_percent: u8,
+}
+
+#[derive(Deserialize)]
+struct MotorsRequest {
+    /// Both `null` / missing returns the driver to obstacle avoidance.
+    left: Option<MotorRequest>,
+    right: Option<MotorRequest>,
+}
+
+#[derive(Deserialize)]
+struct StepRequest {
+    ticks: Option<u32>,
+}
+
+/// Routes `/api/control/*`. Every mutating call answers with the updated
+/// control state (same JSON as `GET /api/control`) or `400` with the reason.
+pub(super) fn handle_control(
+    stream: &mut TcpStream,
+    method: &str,
+    path: &str,
+    body: &str,
+    session: &Session,
+) {
+    let result = match (method, path) {
+        (_, "/api/control") => Ok(None),
+        ("POST", "/api/control/sensor") => parse_body::<SensorRequest>(body).and_then(|req| {
+            let channel = SensorChannel::from_slug(&req.sensor)
+                .ok_or_else(|| ControlError::UnknownSensor(req.sensor.clone()).to_string())?;
+            Ok(Some(match req.value {
+                Some(value) => ControlCommand::Set(channel, value),
+                None => ControlCommand::Clear(channel),
+            }))
+        }),
+        ("POST", "/api/control/servo") => {
+            parse_body::<ServoRequest>(body).map(|req| Some(ControlCommand::Servo(req.angle)))
+        }
+        ("POST", "/api/control/motors") => parse_body::<MotorsRequest>(body).and_then(|req| {
+            Ok(Some(ControlCommand::Motors(match (req.left, req.right) {
+                (None, None) => None,
+                (Some(left), Some(right)) => Some((motor_command(left)?, motor_command(right)?)),
+                _ => return Err("set both `left` and `right`, or neither".to_string()),
+            })))
+        }),
+        ("POST", "/api/control/reset") => Ok(Some(ControlCommand::Reset)),
+        ("POST", "/api/control/pause") => {
+            session.control.lock().unwrap().pause();
+            Ok(None)
+        }
+        ("POST", "/api/control/resume") => {
+            session.control.lock().unwrap().resume();
+            Ok(None)
+        }
+        ("POST", "/api/control/step") => {
+            let ticks = if body.trim().is_empty() {
+                Ok(1)
+            } else {
+                parse_body::<StepRequest>(body).map(|req| req.ticks.unwrap_or(1))
+            };
+            ticks.map(|ticks| {
+                session.control.lock().unwrap().step(ticks);
+                None
+            })
+        }
+        ("POST", "/api/control/record/start") => {
+            session.control.lock().unwrap().start_recording();
+            Ok(None)
+        }
+        ("POST", "/api/control/record/stop") => {
+            let scenario = session.control.lock().unwrap().stop_recording();
+            respond(
+                stream,
+                "200 OK",
+                "text/plain; charset=utf-8",
+                &scenario.to_string(),
+            );
+            return;
+        }
+        ("POST", "/api/control/scenario") => match Scenario::parse(body) {
+            Ok(scenario) => {
+                session.control.lock().unwrap().play(scenario);
+                Ok(None)
+            }
+            Err(e) => Err(e.to_string()),
+        },
+        ("POST", "/api/control/scenario/stop") => {
+            session.control.lock().unwrap().stop_playback();
+            Ok(None)
+        }
+        (_, "/api/control/scenario") => {
+            match session.control.lock().unwrap().recorded() {
+                Some(scenario) => respond(
+                    stream,
+                    "200 OK",
+                    "text/plain; charset=utf-8",
+                    &scenario.to_string(),
+                ),
+                None => respond(
+                    stream,
+                    "409 Conflict",
+                    "text/plain; charset=utf-8",
+                    "not recording",
+                ),
+            }
+            return;
+        }
+        _ => {
+            respond(
+                stream,
+                "404 Not Found",
+                "text/plain; charset=utf-8",
+                "not found",
+            );
+            return;
+        }
+    };
+
+    let mut control = session.control.lock().unwrap();
+    let result = result.and_then(|command| match command {
+        Some(command) => control.apply(command).map_err(|e| e.to_string()),
+        None => Ok(()),
+    });
+    match result {
+        Ok(()) => respond(
+            stream,
+            "200 OK",
+            "application/json; charset=utf-8",
+            &control.to_json(),
+        ),
+        Err(message) => respond(
+            stream,
+            "400 Bad Request",
+            "text/plain; charset=utf-8",
+            &message,
+        ),
+    }
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(body: &str) -> Result<T, String> {
+    serde_json::from_str(body).map_err(|e| format!("invalid request body: {e}"))
+}
+
+fn motor_command(req: MotorRequest) -> Result<MotorCommand, String> {
+    let direction = parse_direction(&req.direction)
+        .ok_or_else(|| format!("unknown direction `{}`", req.direction))?;
+    Ok(MotorCommand::new(direction, req.duty_percent))
+}
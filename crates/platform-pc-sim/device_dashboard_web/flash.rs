@@ -1,107 +1,59 @@
 use std::io::Write as _;
 use std::net::TcpStream;
 
-// ── Firmware Flash targets ──────────────────────────────────────────────────
-
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub(super) enum BoardKind {
-    Esp32,
-    M5StickC,
-    ArduinoNano,
-    RaspberryPiPico,
-}
+use platform_pc_sim::board_def::{FlashDef, FlashMethod};
+use platform_pc_sim::dashboard::BoardProfile;
 
-impl BoardKind {
-    pub(super) fn label(self) -> &'static str {
-        match self {
-            BoardKind::Esp32 => "ESP32",
-            BoardKind::M5StickC => "M5StickC",
-            BoardKind::ArduinoNano => "Arduino Nano",
-            BoardKind::RaspberryPiPico => "Raspberry Pi Pico",
-        }
-    }
-}
+// ── Firmware Flash targets ──────────────────────────────────────────────────
 
 pub(super) struct FlashTarget {
     pub id: &'static str,
     pub label: &'static str,
     pub firmware_dir: &'static str,
     pub binary_name: &'static str,
-    pub target_triple: &'static str,
-    pub board: BoardKind,
+    pub board: BoardProfile,
+    pub flash: &'static FlashDef,
+}
+
+/// ボード定義の `flash.firmware` を登録順に並べた書き込み対象一覧。
+pub(super) fn flash_targets() -> Vec<FlashTarget> {
+    BoardProfile::all()
+        .into_iter()
+        .filter_map(|board| Some((board, board.flash()?)))
+        .flat_map(|(board, flash)| {
+            flash.firmware.iter().map(move |firmware| FlashTarget {
+                id: &firmware.id,
+                label: &firmware.label,
+                firmware_dir: &firmware.dir,
+                binary_name: firmware.binary(),
+                board,
+                flash,
+            })
+        })
+        .collect()
 }
 
-pub(super) fn flash_targets() -> &'static [FlashTarget] {
-    &[
-        // ── ESP32 ──────────────────────────────────────────────────────────
-        FlashTarget {
-            id: "esp32-climate-display",
-            label: "BME280 + LCD1602 (climate display)",
-            firmware_dir: "firmware/original-esp32-climate-display",
-            binary_name: "original-esp32-climate-display",
-            target_triple: "xtensa-esp32-none-elf",
-            board: BoardKind::Esp32,
-        },
-        FlashTarget {
-            id: "esp32-robot-base",
-            label: "Robot base (servo + motors)",
-            firmware_dir: "firmware/original-esp32-robot-base",
-            binary_name: "original-esp32-robot-base",
-            target_triple: "xtensa-esp32-none-elf",
-            board: BoardKind::Esp32,
-        },
-        FlashTarget {
-            id: "esp32-bringup",
-            label: "Bringup (GPIO / I2C check)",
-            firmware_dir: "firmware/original-esp32-bringup",
-            binary_name: "original-esp32-bringup",
-            target_triple: "xtensa-esp32-none-elf",
-            board: BoardKind::Esp32,
-        },
-        // ── M5StickC ───────────────────────────────────────────────────────
-        FlashTarget {
-            id: "m5stickc-bringup",
-            label: "Bringup (GPIO / I2C check)",
-            firmware_dir: "firmware/m5stickc-bringup",
-            binary_name: "m5stickc-bringup",
-            target_triple: "xtensa-esp32-none-elf",
-            board: BoardKind::M5StickC,
-        },
-        // ── Arduino Nano ───────────────────────────────────────────────────
-        FlashTarget {
-            id: "arduino-nano-climate-display",
-            label: "BME280 + LCD1602 (climate display)",
-            firmware_dir: "firmware/arduino-nano-climate-display",
-            binary_name: "arduino-nano-climate-display",
-            target_triple: "avr-none",
-            board: BoardKind::ArduinoNano,
-        },
-        FlashTarget {
-            id: "arduino-nano-bringup",
-            label: "Bringup (GPIO / I2C check)",
-            firmware_dir: "firmware/arduino-nano-bringup",
-            binary_name: "arduino-nano-bringup",
-            target_triple: "avr-none",
-            board: BoardKind::ArduinoNano,
-        },
-        // ── Raspberry Pi Pico ──────────────────────────────────────────────
-        FlashTarget {
-            id: "raspi-pico-climate-display",
-            label: "BME280 + LCD1602 (climate display)",
-            firmware_dir: "firmware/raspi-pico-climate-display",
-            binary_name: "raspi-pico-climate-display",
-            target_triple: "thumbv6m-none-eabi",
-            board: BoardKind::RaspberryPiPico,
-        },
-        FlashTarget {
-            id: "raspi-pico-bringup",
-            label: "Bringup (GPIO / I2C check)",
-            firmware_dir: "firmware/raspi-pico-bringup",
-            binary_name: "raspi-pico-bringup",
-            target_triple: "thumbv6m-none-eabi",
-            board: BoardKind::RaspberryPiPico,
-        },
-    ]
+/// `/api/flash/targets` 用 JSON: `[{id,label,board,slug,needs_port}]`。
+pub(super) fn flash_targets_json() -> String {
+    #[derive(serde::Serialize)]
+    struct TargetView {
+        id: &'static str,
+        label: &'static str,
+        board: &'static str,
+        slug: &'static str,
+        needs_port: bool,
+    }
+    let targets: Vec<TargetView> = flash_targets()
+        .into_iter()
+        .map(|target| TargetView {
+            id: target.id,
+            label: target.label,
+            board: target.board.name(),
+            slug: target.board.slug(),
+            needs_port: target.flash.method.needs_port(),
+        })
+        .collect();
+    serde_json::to_string(&targets).unwrap_or_else(|_| "[]".to_string())
 }
 
 /// ~/.rustup/toolchains/esp/xtensa-esp-elf/<ver>/xtensa-esp-elf/bin を探す。
@@ -248,16 +200,6 @@ pub(super) fn list_serial_ports() -> Vec<String> {
     ports
 }
 
-/// Convert a board string (from query param) to a `BoardKind`.
-pub(super) fn board_kind_from_str(s: &str) -> BoardKind {
-    match s {
-        "m5stickc" => BoardKind::M5StickC,
-        "arduino-nano" => BoardKind::ArduinoNano,
-        "raspi-pico" => BoardKind::RaspberryPiPico,
-        _ => BoardKind::Esp32,
-    }
-}
-
 /// Read `.cargo/config.toml` in `dir` and extract the `target = "..."` line.
 pub(super) fn detect_build_target(dir: &std::path::Path) -> Option<String> {
     let content = std::fs::read_to_string(dir.join(".cargo").join("config.toml")).ok()?;
@@ -283,7 +225,7 @@ pub(super) fn detect_binary_name(dir: &std::path::Path) -> Option<String> {
 /// ターゲット指定 or 旧来の bin 指定でビルド＋書き込みを SSE ストリーム。
 ///
 /// Query params:
-///   target=<id>      (flash_targets() の id を指定)
+///   target=<id>      (ボード定義 `flash.firmware` の id を指定)
 ///   port=<device>
 ///   board=<board>    (custom_elf/custom_dir 時のボード指定)
 ///   custom_elf=<abs> (ビルド済み ELF を直接 flash)
@@ -315,7 +257,7 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
 
     // ── target= が指定されている場合: build + flash ──────────────────────────
     if !target_id.is_empty() {
-        let Some(target) = flash_targets().iter().find(|t| t.id == target_id) else {
+        let Some(target) = flash_targets().into_iter().find(|t| t.id == target_id) else {
             let _ = stream.write_all(
                 format!("data: [ERROR] Unknown target: {target_id}\n\ndata: [DONE] exit=1\n\n")
                     .as_bytes(),
@@ -323,7 +265,7 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
             return;
         };
 
-        if port.is_empty() && !matches!(target.board, BoardKind::RaspberryPiPico) {
+        if port.is_empty() && target.flash.method.needs_port() {
             let _ =
                 stream.write_all(b"data: [ERROR] No port specified.\n\ndata: [DONE] exit=1\n\n");
             return;
@@ -332,8 +274,8 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
         let workspace = std::env::current_dir().unwrap_or_default();
         let firmware_dir = workspace.join(target.firmware_dir);
 
-        match target.board {
-            BoardKind::Esp32 | BoardKind::M5StickC => {
+        match target.flash.method {
+            FlashMethod::Espflash => {
                 // Step 1: cargo build --release (Xtensa GCC を PATH に追加)
                 let _ = stream.write_all(
                     format!("data: [BUILD] Building {}...\n\n", target.label).as_bytes(),
@@ -377,7 +319,7 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
                 // Step 2: espflash flash --port <port> <elf>
                 let elf = firmware_dir
                     .join("target")
-                    .join(target.target_triple)
+                    .join(&target.flash.target_triple)
                     .join("release")
                     .join(target.binary_name);
 
@@ -393,7 +335,7 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
                 let _ = stream.write_all(format!("data: [DONE] exit={flash_code}\n\n").as_bytes());
             }
 
-            BoardKind::ArduinoNano => {
+            FlashMethod::Ravedude => {
                 // ravedude が存在するか確認
                 if Command::new("ravedude")
                     .arg("--version")
@@ -431,7 +373,7 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
                 let _ = stream.write_all(format!("data: [DONE] exit={exit_code}\n\n").as_bytes());
             }
 
-            BoardKind::RaspberryPiPico => {
+            FlashMethod::Uf2 => {
                 // elf2uf2-rs が存在するか確認
                 if Command::new("elf2uf2-rs")
                     .arg("--help")
@@ -471,7 +413,7 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
                 // Step 2: elf2uf2-rs -d <elf>  (-d = deploy, waits for BOOTSEL mode)
                 let elf = firmware_dir
                     .join("target")
-                    .join(target.target_triple)
+                    .join(&target.flash.target_triple)
                     .join("release")
                     .join(target.binary_name);
 
@@ -494,7 +436,18 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
 
     // ── custom_elf= : flash a pre-built ELF ─────────────────────────────────
     if !custom_elf.is_empty() {
-        let board = board_kind_from_str(&board_str);
+        let board = BoardProfile::from_arg(Some(&board_str));
+        let Some(flash) = board.flash() else {
+            let _ = stream.write_all(
+                format!(
+                    "data: [ERROR] {} has no flash method in its board definition\n\n\
+                     data: [DONE] exit=1\n\n",
+                    board.name()
+                )
+                .as_bytes(),
+            );
+            return;
+        };
         let elf_path = std::path::Path::new(&custom_elf);
         if !elf_path.is_absolute() {
             let _ = stream.write_all(
@@ -511,8 +464,8 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
             return;
         }
         let workspace = std::env::current_dir().unwrap_or_default();
-        match board {
-            BoardKind::Esp32 | BoardKind::M5StickC => {
+        match flash.method {
+            FlashMethod::Espflash => {
                 if port.is_empty() {
                     let _ = stream
                         .write_all(b"data: [ERROR] No port specified.\n\ndata: [DONE] exit=1\n\n");
@@ -542,20 +495,20 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
                 );
                 let _ = stream.write_all(format!("data: [DONE] exit={code}\n\n").as_bytes());
             }
-            BoardKind::ArduinoNano => {
+            FlashMethod::Ravedude => {
                 if port.is_empty() {
                     let _ = stream
                         .write_all(b"data: [ERROR] No port specified.\n\ndata: [DONE] exit=1\n\n");
                     return;
                 }
                 let _ = stream.write_all(b"data: [FLASH] Flashing via avrdude...\n\n");
+                let part = flash.avrdude_part.as_deref().unwrap_or("m328p");
                 let flash_arg = format!("flash:w:{custom_elf}:e");
                 let code = stream_command(
                     stream,
                     "avrdude",
                     &[
-                        "-p", "m328p", "-c", "arduino", "-P", &port, "-b", "115200", "-U",
-                        &flash_arg,
+                        "-p", part, "-c", "arduino", "-P", &port, "-b", "115200", "-U", &flash_arg,
                     ],
                     &workspace,
                     &[],
@@ -563,7 +516,7 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
                 );
                 let _ = stream.write_all(format!("data: [DONE] exit={code}\n\n").as_bytes());
             }
-            BoardKind::RaspberryPiPico => {
+            FlashMethod::Uf2 => {
                 if Command::new("elf2uf2-rs")
                     .arg("--help")
                     .stdout(Stdio::null())
@@ -596,7 +549,18 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
 
     // ── custom_dir= : cargo build + flash an external Rust project ───────────
     if !custom_dir.is_empty() {
-        let board = board_kind_from_str(&board_str);
+        let board = BoardProfile::from_arg(Some(&board_str));
+        let Some(flash) = board.flash() else {
+            let _ = stream.write_all(
+                format!(
+                    "data: [ERROR] {} has no flash method in its board definition\n\n\
+                     data: [DONE] exit=1\n\n",
+                    board.name()
+                )
+                .as_bytes(),
+            );
+            return;
+        };
         let dir_path = std::path::PathBuf::from(&custom_dir);
         if !dir_path.is_absolute() {
             let _ = stream.write_all(
@@ -615,8 +579,8 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
             return;
         }
         let workspace = std::env::current_dir().unwrap_or_default();
-        match board {
-            BoardKind::Esp32 | BoardKind::M5StickC => {
+        match flash.method {
+            FlashMethod::Espflash => {
                 if port.is_empty() {
                     let _ = stream
                         .write_all(b"data: [ERROR] No port specified.\n\ndata: [DONE] exit=1\n\n");
@@ -655,8 +619,8 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
                     );
                     return;
                 }
-                let target_triple = detect_build_target(&dir_path)
-                    .unwrap_or_else(|| "xtensa-esp32-none-elf".to_string());
+                let target_triple =
+                    detect_build_target(&dir_path).unwrap_or_else(|| flash.target_triple.clone());
                 let bin_name =
                     detect_binary_name(&dir_path).unwrap_or_else(|| "firmware".to_string());
                 let elf = dir_path
@@ -687,7 +651,7 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
                 );
                 let _ = stream.write_all(format!("data: [DONE] exit={code}\n\n").as_bytes());
             }
-            BoardKind::ArduinoNano => {
+            FlashMethod::Ravedude => {
                 if port.is_empty() {
                     let _ = stream
                         .write_all(b"data: [ERROR] No port specified.\n\ndata: [DONE] exit=1\n\n");
@@ -718,7 +682,7 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
                 );
                 let _ = stream.write_all(format!("data: [DONE] exit={code}\n\n").as_bytes());
             }
-            BoardKind::RaspberryPiPico => {
+            FlashMethod::Uf2 => {
                 if Command::new("elf2uf2-rs")
                     .arg("--help")
                     .stdout(Stdio::null())
@@ -748,8 +712,8 @@ pub(super) fn handle_flash_stream(stream: &mut TcpStream, query: &str) {
                     );
                     return;
                 }
-                let target_triple = detect_build_target(&dir_path)
-                    .unwrap_or_else(|| "thumbv6m-none-eabi".to_string());
+                let target_triple =
+                    detect_build_target(&dir_path).unwrap_or_else(|| flash.target_triple.clone());
                 let bin_name =
                     detect_binary_name(&dir_path).unwrap_or_else(|| "firmware".to_string());
                 let elf = dir_path
@@ -0,0 +1,162 @@
+//! Persistent history routes backed by `platform_pc_sim::history_store`.
+//!
+//! `GET /api/history?panel=imu&last=15m&points=300` returns one JSON array
+//! per column of the panel, downsampled to at most `points` buckets.
+//! `GET /api/history/export?format=parquet&from=..&to=..` downloads the same
+//! range as CSV (default) or Parquet; it keeps every row unless `points` is
+//! given. `from` / `to` are Unix milliseconds and `last` (`90s`, `30m`, `24h`,
+//! `7d`) is shorthand for `from = now - last`. `panel` is optional for both.
+//!
+//! Plain `/api/history` and `?sensor=` requests keep using the in-memory
+//! sparkline buffer; any of the parameters above selects the store.
+
+use std::io;
+use std::net::TcpStream;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use platform_pc_sim::history_store::{
+    downsample, history_json, is_known_panel, parse_duration_ms, write_csv, write_parquet,
+    HistoryRecord,
+};
+
+use super::http_util::{respond, respond_attachment};
+use super::query_param;
+use super::session::Session;
+
+/// Default bucket count for `/api/history` range queries.
+const DEFAULT_POINTS: usize = 500;
+
+pub(super) fn unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// True when `/api/history` should be answered from the persistent store.
+pub(super) fn is_store_query(query: &str) -> bool {
+    ["panel", "from", "to", "last", "points"]
+        .iter()
+        .any(|key| query_param(query, key).is_some())
+}
+
+struct HistoryQuery<'a> {
+    panel: Option<&'a str>,
+    from_ms: u64,
+    to_ms: u64,
+    points: Option<usize>,
+}
+
+impl<'a> HistoryQuery<'a> {
+    fn parse(query: &'a str) -> Result<Self, HistoryError> {
+        let number = |key: &str| -> Result<Option<u64>, HistoryError> {
+            query_param(query, key)
+                .map(|value| value.parse().map_err(|_| format!("invalid `{key}`").into()))
+                .transpose()
+        };
+        let panel = query_param(query, "panel");
+        if !is_known_panel(panel) {
+            return Err(format!("unknown panel `{}`", panel.unwrap_or_default()).into());
+        }
+        let to_ms = number("to")?.unwrap_or(u64::MAX);
+        let from_ms = match query_param(query, "last") {
+            Some(last) => {
+                let last = parse_duration_ms(last).ok_or_else(|| "invalid `last`".to_string())?;
+                unix_ms().min(to_ms).saturating_sub(last)
+            }
+            None => number("from")?.unwrap_or(0),
+        };
+        Ok(Self {
+            panel,
+            from_ms,
+            to_ms,
+            points: number("points")?.map(|points| points as usize),
+        })
+    }
+}
+
+/// `GET /api/history?panel=..` (see the module docs).
+pub(super) fn handle_history_query(stream: &mut TcpStream, query: &str, session: &Session) {
+    let result = HistoryQuery::parse(query).and_then(|request| {
+        let records = query_store(session, &request)?;
+        let records = downsample(&records, request.points.unwrap_or(DEFAULT_POINTS));
+        Ok(history_json(&records, request.panel).unwrap_or_default())
+    });
+    match result {
+        Ok(json) => respond(stream, "200 OK", "application/json; charset=utf-8", &json),
+        Err(error) => respond_error(stream, error),
+    }
+}
+
+/// `GET /api/history/export?format=csv|parquet` (see the module docs).
+pub(super) fn handle_history_export(stream: &mut TcpStream, query: &str, session: &Session) {
+    let format = query_param(query, "format").unwrap_or("csv");
+    let result = HistoryQuery::parse(query).and_then(|request| {
+        let records = query_store(session, &request)?;
+        let records = downsample(&records, request.points.unwrap_or(0));
+        let mut body = Vec::new();
+        let (content_type, extension) = match format {
+            "csv" => {
+                write_csv(&mut body, &records, request.panel).map_err(|e| e.to_string())?;
+                ("text/csv; charset=utf-8", "csv")
+            }
+            "parquet" => {
+                write_parquet(&mut body, &records, request.panel).map_err(|e| e.to_string())?;
+                ("application/vnd.apache.parquet", "parquet")
+            }
+            other => return Err(format!("unknown format `{other}` (csv, parquet)").into()),
+        };
+        let filename = format!(
+            "dashboard-history-{}.{extension}",
+            request.panel.unwrap_or("all")
+        );
+        Ok((content_type, filename, body))
+    });
+    match result {
+        Ok((content_type, filename, body)) => {
+            respond_attachment(stream, content_type, &filename, &body)
+        }
+        Err(error) => respond_error(stream, error),
+    }
+}
+
+fn query_store(
+    session: &Session,
+    request: &HistoryQuery,
+) -> Result<Vec<HistoryRecord>, HistoryError> {
+    // Read the segments without holding the lock so recording never waits
+    // on a long export; a segment pruned meanwhile just reads as empty.
+    let store = session.history_store.lock().unwrap().clone();
+    let store = store.ok_or(HistoryError::Disabled)?;
+    store
+        .query(request.from_ms, request.to_ms)
+        .map_err(HistoryError::Unreadable)
+}
+
+enum HistoryError {
+    BadRequest(String),
+    /// Started without a store (`HISTORY_DIR` could not be opened).
+    Disabled,
+    Unreadable(io::Error),
+}
+
+impl From<String> for HistoryError {
+    fn from(message: String) -> Self {
+        Self::BadRequest(message)
+    }
+}
+
+fn respond_error(stream: &mut TcpStream, error: HistoryError) {
+    let (status, message) = match error {
+        HistoryError::BadRequest(message) => ("400 Bad Request", message),
+        HistoryError::Disabled => (
+            "503 Service Unavailable",
+            "history store disabled".to_string(),
+        ),
+        HistoryError::Unreadable(e) => (
+            "500 Internal Server Error",
+            format!("history store unreadable: {e}"),
+        ),
+    };
+    respond(stream, status, "text/plain; charset=utf-8", &message);
+}
@@ -1,8 +1,40 @@
-use std::io::Write as _;
+use std::io::{Read as _, Write as _};
 use std::net::TcpStream;
 
+/// Keep reading until the body announced by `Content-Length` has arrived.
+///
+/// Most requests fit in the first `read()`, but scenario uploads can be
+/// split across segments. Stops at `max_len` bytes or on EOF / timeout.
+pub(super) fn read_remaining_body(stream: &mut TcpStream, request: &mut Vec<u8>, max_len: usize) {
+    let Some(header_end) = request.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return;
+    };
+    let content_length = String::from_utf8_lossy(&request[..header_end])
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse::<usize>().ok())?
+        })
+        .unwrap_or(0);
+    let total = (header_end + 4 + content_length).min(max_len);
+    let mut chunk = [0u8; 4096];
+    while request.len() < total {
+        match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => request.extend_from_slice(&chunk[..n]),
+        }
+    }
+}
+
 /// Send an HTTP response with the given status, content-type, and body.
 pub(super) fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) {
+    respond_bytes(stream, status, content_type, body.as_bytes());
+}
+
+/// Same as [`respond`] for binary bodies such as CBOR.
+pub(super) fn respond_bytes(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) {
     let header = format!(
         "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
         body.len()
@@ -12,7 +44,23 @@ pub(super) fn respond(stream: &mut TcpStream, status: &str, content_type: &str,
     if stream.write_all(header.as_bytes()).is_err() {
         return;
     }
-    let _ = stream.write_all(body.as_bytes());
+    let _ = stream.write_all(body);
+}
+
+/// Send a `200 OK` download that browsers save as `filename`.
+pub(super) fn respond_attachment(
+    stream: &mut TcpStream,
+    content_type: &str,
+    filename: &str,
+    body: &[u8],
+) {
+    let header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Disposition: attachment; filename=\"{filename}\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    if stream.write_all(header.as_bytes()).is_ok() {
+        let _ = stream.write_all(body);
+    }
 }
 
 /// Extract a string field value from a minimal JSON body.
@@ -0,0 +1,216 @@
+//! OpenMetrics exporter behind `GET /metrics`.
+//!
+//! The main loop captures a [`MetricsSnapshot`] on every push and the route
+//! renders it with `core_app::metrics`, so the simulator exposes the same
+//! metric names as the WiFi firmware (`mcu_temperature_celsius`, ...) plus
+//! simulator-only families: panel values the telemetry frame does not carry,
+//! per-address I2C counters, diagnostics counts and the tick rate.
+
+use std::time::Instant;
+
+use core_app::metrics::{MetricType, MetricsError, MetricsWriter, DEFAULT_NAMESPACE};
+use core_app::telemetry::FieldValue;
+use platform_pc_sim::virtual_i2c::I2cAddressStats;
+use platform_pc_sim::web_dashboard::{state_to_telemetry, DeviceDashboardState};
+
+/// Severity labels in `DeviceSimulationRig::diag_severity_counts` order.
+const DIAG_SEVERITIES: [&str; 3] = ["info", "warn", "error"];
+
+/// Everything `/metrics` reports, captured once per push.
+pub struct MetricsSnapshot {
+    pub state: DeviceDashboardState,
+    pub i2c: Vec<(u8, I2cAddressStats)>,
+    pub diag_counts: [u64; 3],
+    /// Simulation ticks per second in hundredths (0 while paused).
+    pub tick_rate_x100: u32,
+    pub paused: bool,
+}
+
+impl MetricsSnapshot {
+    /// Renders the snapshot of `session` as a complete OpenMetrics document.
+    pub fn render(&self, session: &str) -> Result<Vec<u8>, MetricsError> {
+        let mut buffer = vec![0u8; 4096];
+        loop {
+            match self.render_into(&mut buffer, session) {
+                Ok(len) => {
+                    buffer.truncate(len);
+                    return Ok(buffer);
+                }
+                Err(MetricsError::BufferTooSmall) => buffer.resize(buffer.len() * 2, 0),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn render_into(&self, buffer: &mut [u8], session: &str) -> Result<usize, MetricsError> {
+        let state = &self.state;
+        let mut m = MetricsWriter::new(buffer, DEFAULT_NAMESPACE);
+        state_to_telemetry(state).write_metrics(&mut m)?;
+
+        m.family("board_info", MetricType::Gauge, "Simulated board profile")?;
+        m.sample(
+            "board_info",
+            &[
+                ("device_id", state.board_name),
+                ("mcu", state.mcu_name),
+                ("session", session),
+            ],
+            FieldValue::Int(1),
+        )?;
+
+        m.family("sim_ticks", MetricType::Counter, "Simulation ticks run")?;
+        m.sample("sim_ticks", &[], FieldValue::Int(state.tick.into()))?;
+        m.family(
+            "sim_tick_rate_hz",
+            MetricType::Gauge,
+            "Simulation ticks per second",
+        )?;
+        m.sample(
+            "sim_tick_rate_hz",
+            &[],
+            FieldValue::Fixed {
+                value: self.tick_rate_x100.into(),
+                decimals: 2,
+            },
+        )?;
+        m.family(
+            "sim_paused",
+            MetricType::Gauge,
+            "1 while the simulation is paused",
+        )?;
+        m.sample("sim_paused", &[], FieldValue::Int(self.paused.into()))?;
+
+        m.family("servo_angle_degrees", MetricType::Gauge, "Servo angle")?;
+        m.sample(
+            "servo_angle_degrees",
+            &[],
+            FieldValue::Int(state.servo.angle_degrees.into()),
+        )?;
+        m.family("motor_duty_percent", MetricType::Gauge, "Motor PWM duty")?;
+        let motors = &state.motor_driver;
+        for (side, channel) in [("left", &motors.left), ("right", &motors.right)] {
+            m.sample(
+                "motor_duty_percent",
+                &[("side", side), ("direction", &channel.direction)],
+                FieldValue::Int(channel.duty_percent.into()),
+            )?;
+        }
+        if let Some(distance_mm) = state.tof.distance_mm {
+            m.family("tof_distance_mm", MetricType::Gauge, "VL53L0X distance")?;
+            m.sample("tof_distance_mm", &[], FieldValue::Int(distance_mm.into()))?;
+        }
+        m.family(
+            "camera_frames",
+            MetricType::Counter,
+            "Camera frames captured",
+        )?;
+        m.sample(
+            "camera_frames",
+            &[],
+            FieldValue::Int(state.camera.sequence.into()),
+        )?;
+
+        let wiring = &state.wiring;
+        m.family(
+            "wiring_attached_devices",
+            MetricType::Gauge,
+            "Devices attached to the I2C bus",
+        )?;
+        m.sample(
+            "wiring_attached_devices",
+            &[],
+            FieldValue::Int(wiring.attached_devices.len() as i64),
+        )?;
+        m.family(
+            "wiring_device_selected",
+            MetricType::Gauge,
+            "1 for every device selected in the wiring editor",
+        )?;
+        for device in &wiring.selected_devices {
+            m.sample(
+                "wiring_device_selected",
+                &[("device", device)],
+                FieldValue::Int(1),
+            )?;
+        }
+
+        m.family(
+            "i2c_operations",
+            MetricType::Counter,
+            "I2C transfers per address and kind",
+        )?;
+        let addresses: Vec<String> = self
+            .i2c
+            .iter()
+            .map(|(addr, _)| format!("0x{addr:02x}"))
+            .collect();
+        for (address, (_, stats)) in addresses.iter().zip(&self.i2c) {
+            for (kind, count) in [
+                ("write", stats.writes),
+                ("read", stats.reads),
+                ("write_read", stats.write_reads),
+            ] {
+                m.sample(
+                    "i2c_operations",
+                    &[("address", address), ("kind", kind)],
+                    FieldValue::Int(count as i64),
+                )?;
+            }
+        }
+        m.family("i2c_errors", MetricType::Counter, "Failed I2C transfers")?;
+        for (address, (_, stats)) in addresses.iter().zip(&self.i2c) {
+            m.sample(
+                "i2c_errors",
+                &[("address", address)],
+                FieldValue::Int(stats.errors as i64),
+            )?;
+        }
+
+        m.family(
+            "diag_events",
+            MetricType::Counter,
+            "Diagnostics events by severity",
+        )?;
+        for (severity, count) in DIAG_SEVERITIES.iter().zip(self.diag_counts) {
+            m.sample(
+                "diag_events",
+                &[("severity", severity)],
+                FieldValue::Int(count as i64),
+            )?;
+        }
+        m.family(
+            "active_alarms",
+            MetricType::Gauge,
+            "Currently raised alarms",
+        )?;
+        m.sample(
+            "active_alarms",
+            &[],
+            FieldValue::Int(state.diagnostics.active_alarms.len() as i64),
+        )?;
+
+        m.finish()
+    }
+}
+
+/// Measures simulation ticks per wall-clock second between pushes.
+#[derive(Default)]
+pub struct TickRate {
+    last: Option<(Instant, u32)>,
+}
+
+impl TickRate {
+    /// Records `tick` at `now` and returns the rate since the previous call
+    /// in hundredths of a hertz.
+    pub fn sample(&mut self, now: Instant, tick: u32) -> u32 {
+        let rate = match self.last {
+            Some((then, last_tick)) => {
+                let elapsed_ms = now.duration_since(then).as_millis().max(1) as u64;
+                u64::from(tick.wrapping_sub(last_tick)) * 100_000 / elapsed_ms
+            }
+            None => 0,
+        };
+        self.last = Some((now, tick));
+        rate.min(u64::from(u32::MAX)) as u32
+    }
+}
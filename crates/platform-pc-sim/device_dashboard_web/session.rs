@@ -0,0 +1,531 @@
+//! Named simulation sessions and the `/api/sessions` routes.
+//!
+//! Every session owns one `DeviceSimulationRig` with its own board, wiring,
+//! `/api/control` state (overrides, pause / step, scenario) and tick clock,
+//! and runs it on its own thread. The `default` session always exists and
+//! answers the unprefixed routes (`/api/state`, `/api/wiring`, `/metrics`,
+//! ...); every session answers the same routes under
+//! `/api/sessions/<name>/...` (`/api/sessions/nano/control/pause`,
+//! `/api/sessions/nano/metrics`).
+//!
+//! - `GET /api/sessions` lists the sessions.
+//! - `POST /api/sessions` with `{"name":"nano","board":"arduino-nano"}`
+//!   (optional `"sensor_profile"`) creates and starts one (`201`).
+//! - `GET /api/sessions/<name>` describes one session.
+//! - `DELETE /api/sessions/<name>` stops it and disconnects its SSE and
+//!   WebSocket clients; its persistent history stays on disk.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use core_app::telemetry::TelemetryFrame;
+use platform_pc_sim::dashboard::BoardProfile;
+use platform_pc_sim::history_store::{HistoryRecord, HistoryStore, RetentionPolicy};
+use platform_pc_sim::sim_control::SimControl;
+use platform_pc_sim::web_dashboard::{state_to_telemetry, PanelDelta, PanelTracker};
+use platform_pc_sim::wiring_config::{normalize_supported_device_selection, SensorProfile};
+use platform_pc_sim::wiring_project::{ProjectError, WiringProject};
+use serde::{Deserialize, Serialize};
+
+use super::history::unix_ms;
+use super::http_util::respond;
+use super::metrics::{MetricsSnapshot, TickRate};
+use super::sim_rig::DeviceSimulationRig;
+use super::ws::{close_all, fan_out, WsClient};
+use super::{
+    dashboard_wiring_config, SensorHistoryBuffer, ServerContext, WiringState, HISTORY_CAPACITY,
+    HISTORY_RECORD_INTERVAL_MS,
+};
+
+/// Name of the session behind the unprefixed routes; it cannot be deleted.
+pub(super) const DEFAULT_SESSION: &str = "default";
+/// Upper bound on concurrent sessions (each one is a simulation thread).
+const MAX_SESSIONS: usize = 32;
+const MAX_NAME_LEN: usize = 32;
+
+/// State of one simulation session, shared by its simulation thread and
+/// every connection-handler thread.
+pub(super) struct Session {
+    pub(super) name: String,
+    pub(super) latest_json: Mutex<Arc<str>>,
+    pub(super) sse_clients: Mutex<Vec<mpsc::SyncSender<Arc<str>>>>,
+    /// Atomic board + sensor profile state for wiring API reads. The
+    /// simulation thread rebuilds its rig when `board` changes.
+    pub(super) wiring_state: Mutex<WiringState>,
+    /// Last wiring-editor JSON submitted via POST /api/wiring/editor.
+    pub(super) editor_json: Mutex<String>,
+    /// `name` of the last loaded wiring project, written back on save.
+    project_name: Mutex<Option<String>>,
+    /// Snapshot of diagnostics ring from the last sim tick (for /api/diagnostics).
+    pub(super) latest_diagnostics: Mutex<String>,
+    /// Ring buffer of sensor readings for /api/history.
+    pub(super) history: Mutex<SensorHistoryBuffer>,
+    /// Latest readings in the firmware telemetry format (for /api/telemetry).
+    pub(super) latest_telemetry: Mutex<Option<TelemetryFrame<'static>>>,
+    /// Overrides, manual actuators, pause / step and scenario record / replay
+    /// driven by `/api/control/*`.
+    pub(super) control: Mutex<SimControl>,
+    /// Per-panel JSON of the last push; new `/api/ws` clients start from it.
+    pub(super) latest_panels: Mutex<PanelTracker>,
+    pub(super) ws_clients: Mutex<Vec<WsClient>>,
+    /// Persistent all-panel history (for /api/history range queries and
+    /// /api/history/export); `None` if the directory could not be opened.
+    pub(super) history_store: Mutex<Option<HistoryStore>>,
+    /// Counters and panel values from the last push (for /metrics).
+    pub(super) latest_metrics: Mutex<Option<MetricsSnapshot>>,
+    /// Cleared by `DELETE`; the simulation thread exits on its next tick.
+    running: AtomicBool,
+}
+
+impl Session {
+    pub(super) fn new(name: &str, board: BoardProfile, sensor_profile: SensorProfile) -> Self {
+        Self {
+            name: name.to_string(),
+            latest_json: Mutex::new(Arc::from("{}")),
+            sse_clients: Mutex::new(vec![]),
+            wiring_state: Mutex::new(WiringState {
+                board,
+                sensor_profile,
+                selected_devices: normalize_supported_device_selection(
+                    board,
+                    sensor_profile.device_kinds(),
+                ),
+                show_bus_labels: false,
+                layout: None,
+            }),
+            editor_json: Mutex::new("{}".into()),
+            project_name: Mutex::new(None),
+            latest_diagnostics: Mutex::new("[]".into()),
+            history: Mutex::new(SensorHistoryBuffer::new(HISTORY_CAPACITY)),
+            latest_telemetry: Mutex::new(None),
+            control: Mutex::new(SimControl::new()),
+            latest_panels: Mutex::new(PanelTracker::new()),
+            ws_clients: Mutex::new(vec![]),
+            history_store: Mutex::new(None),
+            latest_metrics: Mutex::new(None),
+            running: AtomicBool::new(true),
+        }
+    }
+
+    /// Attaches `store` and seeds the sparkline buffer from it.
+    pub(super) fn attach_history_store(&self, store: HistoryStore) {
+        match store.latest(HISTORY_CAPACITY) {
+            Ok(records) => self.history.lock().unwrap().seed(&records),
+            Err(e) => eprintln!(
+                "{}: history: cannot read {}: {e}",
+                self.name,
+                store.dir().display()
+            ),
+        }
+        *self.history_store.lock().unwrap() = Some(store);
+    }
+
+    /// Switches the session to `project`'s board, devices and layout. The
+    /// editor layout is replaced only when the project carries one.
+    pub(super) fn load_project(&self, project: &WiringProject) -> Result<(), ProjectError> {
+        let config = project.to_config()?;
+        *self.wiring_state.lock().unwrap() = WiringState {
+            board: config.board,
+            sensor_profile: config.sensor_profile,
+            selected_devices: config.devices.iter().map(|device| device.kind).collect(),
+            show_bus_labels: config.show_bus_labels,
+            layout: Some(config),
+        };
+        if let Some(editor) = &project.editor {
+            *self.editor_json.lock().unwrap() = editor.to_string();
+        }
+        *self.project_name.lock().unwrap() = project.name.clone();
+        Ok(())
+    }
+
+    /// The session's current wiring (and editor layout) as a project file.
+    pub(super) fn project(&self) -> WiringProject {
+        let config = dashboard_wiring_config(&self.wiring_state.lock().unwrap());
+        let mut project = WiringProject::from_config(&config);
+        project.name = self.project_name.lock().unwrap().clone();
+        project.editor =
+            serde_json::from_str::<serde_json::Value>(&self.editor_json.lock().unwrap())
+                .ok()
+                .filter(|editor| editor.as_object().is_some_and(|map| !map.is_empty()));
+        project
+    }
+
+    pub(super) fn push_state(&self, json: String, diag_json: String) {
+        // `Arc<str>` so fan-out to every SSE client bumps a refcount instead of
+        // allocating a fresh String copy per client (see #226).
+        let json: Arc<str> = Arc::from(json);
+        *self.latest_json.lock().unwrap() = Arc::clone(&json);
+        *self.latest_diagnostics.lock().unwrap() = diag_json;
+        self.sse_clients
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.try_send(Arc::clone(&json)).is_ok());
+    }
+
+    /// Fans the changed panels out to `/api/ws` clients.
+    pub(super) fn push_panels(&self, tracker: &PanelTracker, delta: PanelDelta) {
+        *self.latest_panels.lock().unwrap() = tracker.clone();
+        fan_out(&mut self.ws_clients.lock().unwrap(), delta);
+    }
+
+    fn info_json(&self) -> String {
+        serde_json::to_string(&self.info()).expect("session info serializes")
+    }
+
+    fn info(&self) -> SessionInfo<'_> {
+        let wiring = self.wiring_state.lock().unwrap().clone();
+        let tick = self
+            .latest_metrics
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, |metrics| metrics.state.tick);
+        SessionInfo {
+            name: &self.name,
+            board: wiring.board.name(),
+            mcu: wiring.board.mcu(),
+            sensor_profile: wiring.sensor_profile.slug(),
+            tick,
+            paused: self.control.lock().unwrap().is_paused(),
+            clients: self.sse_clients.lock().unwrap().len() + self.ws_clients.lock().unwrap().len(),
+        }
+    }
+
+    /// Starts the simulation thread.
+    pub(super) fn spawn(self: &Arc<Self>) {
+        let session = Arc::clone(self);
+        thread::Builder::new()
+            .name(format!("session-{}", self.name))
+            .spawn(move || session.run())
+            .expect("session thread should spawn");
+    }
+
+    fn stop(&self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+
+    fn run(&self) {
+        let mut rig = DeviceSimulationRig::new(self.wiring_state.lock().unwrap().board);
+        let mut panels = PanelTracker::new();
+        let mut push_ticker: u32 = 0;
+        let mut tick_rate = TickRate::default();
+        let mut last_history_ms: u64 = 0;
+
+        while self.running.load(Ordering::Relaxed) {
+            let wiring_state = self.wiring_state.lock().unwrap().clone();
+            // Apply a board change made through POST /api/wiring.
+            if wiring_state.board != rig.board {
+                rig = DeviceSimulationRig::new(wiring_state.board);
+                println!("{}: board changed to: {}", self.name, rig.board.name());
+            }
+
+            // Tick the simulation. `advance()` is the cheap phase (sensor mocks /
+            // internal counters only) and always runs; the expensive `snapshot()`
+            // formatting phase (wiring diagram, recent I2C ops, ...) only runs
+            // when the result will actually be pushed to SSE clients, since 9 of
+            // every 10 ticks previously built and discarded a full snapshot (#225).
+            //
+            // While paused (`/api/control/pause`) the rig only advances for
+            // requested single steps, but snapshots keep flowing so the page
+            // stays connected and shows stepped ticks.
+            let (advance, paused) = {
+                let mut control = self.control.lock().unwrap();
+                let advance = control.begin_tick();
+                rig.controls = *control.active();
+                (advance, control.is_paused())
+            };
+            if advance {
+                rig.advance(&wiring_state);
+            }
+            push_ticker = push_ticker.wrapping_add(1);
+
+            // Push JSON to SSE / WebSocket clients every 10 ticks (~100 ms).
+            // Each panel is serialized once; SSE gets the spliced document and
+            // WebSocket clients only the panels that changed.
+            if push_ticker % 10 == 0 {
+                let state = rig.snapshot(&wiring_state);
+                let delta = panels.update(&state);
+                let diag_json = panels
+                    .panel("diagnostics")
+                    .map(|json| json.to_string())
+                    .unwrap_or_default();
+                // Append to history ring buffers when sensors are active.
+                {
+                    let mut hist = self.history.lock().unwrap();
+                    let cli = &state.climate;
+                    if cli.temperature_c.is_some() || cli.humidity_percent.is_some() {
+                        let temp_cc = cli.temperature_c.map(|v| (v * 100.0) as i32).unwrap_or(0);
+                        let hum_cp = cli
+                            .humidity_percent
+                            .map(|v| (v * 100.0) as u32)
+                            .unwrap_or(0);
+                        hist.push_climate(temp_cc, hum_cp, cli.pressure_pa);
+                    }
+                    hist.push_distance(state.distance.distance_mm);
+                }
+                *self.latest_telemetry.lock().unwrap() = Some(state_to_telemetry(&state));
+                let now_ms = unix_ms();
+                if now_ms.saturating_sub(last_history_ms) >= HISTORY_RECORD_INTERVAL_MS {
+                    last_history_ms = now_ms;
+                    let mut store = self.history_store.lock().unwrap();
+                    let failed = store.as_mut().and_then(|store| {
+                        store
+                            .append(&HistoryRecord::from_state(&state, now_ms))
+                            .err()
+                    });
+                    if let Some(e) = failed {
+                        eprintln!(
+                            "{}: history: write failed: {e} (persistence disabled)",
+                            self.name
+                        );
+                        *store = None;
+                    }
+                }
+                self.push_state(panels.full_json(), diag_json);
+                self.push_panels(&panels, delta);
+                *self.latest_metrics.lock().unwrap() = Some(MetricsSnapshot {
+                    i2c: rig.bus.address_stats(),
+                    diag_counts: rig.diag_severity_counts,
+                    tick_rate_x100: tick_rate.sample(Instant::now(), rig.tick),
+                    paused,
+                    state,
+                });
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // Dropping the senders ends this session's SSE streams; WebSocket
+        // clients get a 1001 (going away) close frame.
+        self.sse_clients.lock().unwrap().clear();
+        close_all(&mut self.ws_clients.lock().unwrap());
+    }
+}
+
+#[derive(Serialize)]
+struct SessionInfo<'a> {
+    name: &'a str,
+    board: &'static str,
+    mcu: &'static str,
+    sensor_profile: &'static str,
+    /// Tick of the last push (`0` before the first one).
+    tick: u32,
+    paused: bool,
+    /// Connected SSE and WebSocket clients.
+    clients: usize,
+}
+
+/// Every session of the server, `default` included.
+pub(super) struct SessionRegistry {
+    sessions: Mutex<BTreeMap<String, Arc<Session>>>,
+    /// Persistent history root and retention; the default session writes to
+    /// the root and every other session to `<root>/<name>`.
+    history: Option<(PathBuf, RetentionPolicy)>,
+}
+
+impl SessionRegistry {
+    pub(super) fn new(default: Arc<Session>, history: Option<(PathBuf, RetentionPolicy)>) -> Self {
+        Self {
+            sessions: Mutex::new(BTreeMap::from([(default.name.clone(), default)])),
+            history,
+        }
+    }
+
+    pub(super) fn get(&self, name: &str) -> Option<Arc<Session>> {
+        self.sessions.lock().unwrap().get(name).cloned()
+    }
+
+    /// Creates, registers and starts a session.
+    pub(super) fn create(
+        &self,
+        name: &str,
+        board: BoardProfile,
+        sensor_profile: SensorProfile,
+    ) -> Result<Arc<Session>, SessionError> {
+        if !is_valid_name(name) {
+            return Err(SessionError::BadRequest(format!(
+                "invalid session name `{name}` (1-{MAX_NAME_LEN} of a-z, 0-9, `-`, `_`)"
+            )));
+        }
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.contains_key(name) {
+            return Err(SessionError::Conflict(format!(
+                "session `{name}` already exists"
+            )));
+        }
+        if sessions.len() >= MAX_SESSIONS {
+            return Err(SessionError::Conflict(format!(
+                "session limit ({MAX_SESSIONS}) reached"
+            )));
+        }
+        let session = Arc::new(Session::new(name, board, sensor_profile));
+        if let Some((root, retention)) = &self.history {
+            let dir = root.join(name);
+            match HistoryStore::open(&dir, *retention) {
+                Ok(store) => session.attach_history_store(store),
+                Err(e) => eprintln!(
+                    "{name}: history: cannot open {}: {e} (persistence disabled)",
+                    dir.display()
+                ),
+            }
+        }
+        sessions.insert(name.to_string(), Arc::clone(&session));
+        drop(sessions);
+        session.spawn();
+        println!("session {name} started: {}", board.name());
+        Ok(session)
+    }
+
+    /// Stops and unregisters a session.
+    pub(super) fn remove(&self, name: &str) -> Result<(), SessionError> {
+        if name == DEFAULT_SESSION {
+            return Err(SessionError::Conflict(format!(
+                "the `{DEFAULT_SESSION}` session cannot be deleted"
+            )));
+        }
+        let session = self
+            .sessions
+            .lock()
+            .unwrap()
+            .remove(name)
+            .ok_or_else(|| SessionError::NotFound(name.to_string()))?;
+        session.stop();
+        println!("session {name} stopped");
+        Ok(())
+    }
+
+    fn list(&self) -> Vec<Arc<Session>> {
+        self.sessions.lock().unwrap().values().cloned().collect()
+    }
+}
+
+/// Session names end up in URLs and history directory names.
+fn is_valid_name(name: &str) -> bool {
+    (1..=MAX_NAME_LEN).contains(&name.len())
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+}
+
+/// Parses `DASHBOARD_SESSIONS`-style specs: `nano=arduino-nano,node-2=esp32`.
+/// A bare name gets the ESP32 board.
+pub(super) fn parse_session_specs(specs: &str) -> Result<Vec<(String, BoardProfile)>, String> {
+    specs
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+        .map(|spec| {
+            let (name, board) = spec.split_once('=').unwrap_or((spec, "esp32"));
+            if !is_valid_name(name) {
+                return Err(format!("invalid session name `{name}`"));
+            }
+            Ok((name.to_string(), BoardProfile::from_arg(Some(board))))
+        })
+        .collect()
+}
+
+/// Splits `/api/sessions/<name>/<rest>` into the session name and the path
+/// its routes see (`/api/<rest>`, or `/metrics`). `None` for every other
+/// path, including the `/api/sessions[/<name>]` management routes.
+pub(super) fn split_session_path(path: &str) -> Option<(&str, String)> {
+    let (name, rest) = path.strip_prefix("/api/sessions/")?.split_once('/')?;
+    match rest {
+        "" => None,
+        "metrics" => Some((name, "/metrics".to_string())),
+        rest => Some((name, format!("/api/{rest}"))),
+    }
+}
+
+#[derive(Deserialize)]
+struct CreateRequest {
+    name: String,
+    /// `esp32` (default) or `arduino-nano`, as on the command line.
+    board: Option<String>,
+    sensor_profile: Option<String>,
+}
+
+/// Routes `/api/sessions` and `/api/sessions/<name>`.
+pub(super) fn handle_sessions(
+    stream: &mut TcpStream,
+    method: &str,
+    path: &str,
+    body: &str,
+    ctx: &ServerContext,
+) {
+    let name = path
+        .strip_prefix("/api/sessions")
+        .unwrap_or_default()
+        .trim_matches('/');
+    let result = match (method, name) {
+        ("POST", "") => {
+            create_session(body, ctx).map(|session| ("201 Created", session.info_json()))
+        }
+        (_, "") => {
+            let infos: Vec<String> = ctx.sessions.list().iter().map(|s| s.info_json()).collect();
+            Ok(("200 OK", format!("[{}]", infos.join(","))))
+        }
+        ("DELETE", name) => ctx
+            .sessions
+            .remove(name)
+            .map(|()| ("200 OK", r#"{"ok":true}"#.to_string())),
+        (_, name) => ctx
+            .sessions
+            .get(name)
+            .map(|session| ("200 OK", session.info_json()))
+            .ok_or_else(|| SessionError::NotFound(name.to_string())),
+    };
+    match result {
+        Ok((status, json)) => respond(stream, status, "application/json; charset=utf-8", &json),
+        Err(error) => respond_error(stream, error),
+    }
+}
+
+fn create_session(body: &str, ctx: &ServerContext) -> Result<Arc<Session>, SessionError> {
+    let request: CreateRequest = serde_json::from_str(body)
+        .map_err(|e| SessionError::BadRequest(format!("invalid request body: {e}")))?;
+    let sensor_profile = match request.sensor_profile.as_deref() {
+        Some(slug) => SensorProfile::from_slug(slug)
+            .ok_or_else(|| SessionError::BadRequest(format!("unknown sensor profile `{slug}`")))?,
+        None => SensorProfile::Full,
+    };
+    ctx.sessions.create(
+        &request.name,
+        BoardProfile::from_arg(request.board.as_deref()),
+        sensor_profile,
+    )
+}
+
+pub(super) enum SessionError {
+    BadRequest(String),
+    NotFound(String),
+    Conflict(String),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadRequest(message) | Self::Conflict(message) => f.write_str(message),
+            Self::NotFound(name) => write!(f, "unknown session `{name}`"),
+        }
+    }
+}
+
+pub(super) fn respond_error(stream: &mut TcpStream, error: SessionError) {
+    let status = match error {
+        SessionError::BadRequest(_) => "400 Bad Request",
+        SessionError::NotFound(_) => "404 Not Found",
+        SessionError::Conflict(_) => "409 Conflict",
+    };
+    respond(
+        stream,
+        status,
+        "text/plain; charset=utf-8",
+        &error.to_string(),
+    );
+}
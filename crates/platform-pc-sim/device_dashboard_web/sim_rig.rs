@@ -2,26 +2,33 @@ use core::str;
 use std::collections::VecDeque;
 use std::fmt::Write as FmtWrite;
 
+use core_app::alarm::{
+    metric, AlarmEngine, AlarmEventQueue, AlarmRule, AlarmSeverity, AlarmTransition, SensorSample,
+};
 use core_app::climate_display::{frame_from_reading, ClimateDisplayApp, ClimateDisplayConfig};
+use core_app::data_logger::datetime_from_unix;
 use embedded_hal::delay::DelayNs;
 use hal_api::actuator::{DualMotorDriver, MotorCommand, MotorDirection, ServoMotor};
 use hal_api::camera::CameraCapture;
-use hal_api::distance::DistanceSensor;
-use hal_api::gas::GasSensor;
+use hal_api::distance::{DistanceReading, DistanceSensor};
+use hal_api::gas::{GasReading, GasSensor};
 use hal_api::imu::ImuSensor;
 use hal_api::light::LightSensor;
 use hal_api::rtc::RtcSensor;
-use hal_api::sensor::EnvSensor;
-use platform_pc_sim::bme280_mock::{demo_raw_samples, MockBme280Device};
+use hal_api::sensor::{EnvReading, EnvSensor};
+use platform_pc_sim::bme280_mock::{
+    demo_raw_samples, raw_sample_for_reading, reading_for_raw_sample, MockBme280Device,
+};
 use platform_pc_sim::camera_mock::MockCamera;
 use platform_pc_sim::dashboard::BoardProfile;
-use platform_pc_sim::ds3231_mock::{demo_timestamps, MockDs3231Device};
+use platform_pc_sim::ds3231_mock::{demo_timestamps, MockDs3231Device, MockRtcTimestamp};
 use platform_pc_sim::hc_sr04_mock::{demo_echo_pulses_us, MockHcSr04Device};
 use platform_pc_sim::lcd1602_mock::MockLcd1602Device;
 use platform_pc_sim::mock_hal::MockPin;
 use platform_pc_sim::mpu6050_mock::{demo_raw_frames, MockMpu6050Device};
 use platform_pc_sim::pwm_mock::MockPwmOutput;
 use platform_pc_sim::sgp30_mock::MockSgp30Device;
+use platform_pc_sim::sim_control::{ActiveControls, SensorChannel};
 use platform_pc_sim::ssd1306_mock::MockSsd1306TextDisplay;
 use platform_pc_sim::virtual_i2c::{VirtualI2cBus, VirtualI2cOperation};
 use platform_pc_sim::vl53l0x_mock::MockVl53l0xDevice;
@@ -51,6 +58,98 @@ use super::{dashboard_wiring_config, WiringState};
 /// at this offset address to avoid collision; display helpers translate it back.
 pub(super) const DS3231_SIM_ADDRESS: u8 = DS3231_ADDRESS + 1;
 
+/// Simulated time per `advance()` call. The main loop sleeps 10 ms per tick,
+/// but alarm debounce uses tick-derived time so tests stay deterministic.
+const SIM_TICK_MS: u32 = 10;
+
+pub(super) const ALARM_CO2_HIGH: u16 = 1;
+pub(super) const ALARM_TOO_CLOSE: u16 = 2;
+pub(super) const ALARM_TEMPERATURE_BAND: u16 = 3;
+
+// ── BusAddresses ───────────────────────────────────────────────────────────
+
+/// Where the rig attaches each mock on the virtual I2C bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct BusAddresses {
+    pub bme280: u8,
+    pub lcd: u8,
+    pub mpu6050: u8,
+    pub bh1750: u8,
+    /// Bus address of the DS3231 mock; moved off any address another mock
+    /// uses (the MPU6050's `0x68` by default).
+    pub ds3231: u8,
+    /// DS3231 address as wired, shown in place of `ds3231`.
+    pub ds3231_wired: u8,
+    pub sgp30: u8,
+    pub vl53l0x: u8,
+}
+
+impl Default for BusAddresses {
+    fn default() -> Self {
+        Self {
+            bme280: BME280_ADDRESS_PRIMARY,
+            lcd: LCD1602_ADDRESS_PRIMARY,
+            mpu6050: MPU6050_ADDRESS_PRIMARY,
+            bh1750: BH1750_ADDRESS_LOW,
+            ds3231: DS3231_SIM_ADDRESS,
+            ds3231_wired: DS3231_ADDRESS,
+            sgp30: SGP30_ADDRESS,
+            vl53l0x: VL53L0X_ADDRESS,
+        }
+    }
+}
+
+impl BusAddresses {
+    /// Addresses set by the loaded wiring project, falling back to each
+    /// device's default.
+    pub fn for_wiring(wiring: &WiringState) -> Self {
+        let layout = wiring
+            .layout
+            .as_ref()
+            .filter(|layout| layout.board == wiring.board);
+        let address = |kind: DeviceKind| {
+            layout
+                .and_then(|layout| layout.devices.iter().find(|spec| spec.kind == kind))
+                .and_then(|spec| spec.address)
+                .or_else(|| kind.default_address())
+                .expect("I2C devices have a default address")
+        };
+        let taken = [
+            DeviceKind::Bme280,
+            DeviceKind::Lcd1602,
+            DeviceKind::Mpu6050,
+            DeviceKind::Bh1750,
+            DeviceKind::Sgp30,
+            DeviceKind::Vl53l0x,
+        ]
+        .map(address);
+        let ds3231_wired = address(DeviceKind::Ds3231);
+        let ds3231 = (ds3231_wired..=0x77)
+            .chain(0x08..ds3231_wired)
+            .find(|candidate| !taken.contains(candidate))
+            .expect("more free addresses than mocks");
+        Self {
+            bme280: taken[0],
+            lcd: taken[1],
+            mpu6050: taken[2],
+            bh1750: taken[3],
+            ds3231,
+            ds3231_wired,
+            sgp30: taken[4],
+            vl53l0x: taken[5],
+        }
+    }
+
+    /// Address shown on the dashboard for a bus address.
+    fn display(self, addr: u8) -> u8 {
+        if addr == self.ds3231 {
+            self.ds3231_wired
+        } else {
+            addr
+        }
+    }
+}
+
 // ── NoopDelay ──────────────────────────────────────────────────────────────
 
 #[derive(Default)]
@@ -67,18 +166,33 @@ impl DelayNs for NoopDelay {
 pub(super) type ServoRig = ServoDriver<MockPwmOutput>;
 pub(super) type MotorChannelRig = L298nChannel<MockPin, MockPin, MockPwmOutput>;
 pub(super) type MotorDriverRig = L298nDualDriver<MotorChannelRig, MotorChannelRig>;
+pub(super) type ClimateAppRig =
+    ClimateDisplayApp<Bme280Sensor<VirtualI2cBus>, Lcd1602Display<VirtualI2cBus, NoopDelay>>;
+
+fn climate_app(bus: &VirtualI2cBus, addresses: BusAddresses) -> ClimateAppRig {
+    ClimateDisplayApp::new_with_config(
+        Bme280Sensor::new_with_address(bus.clone(), addresses.bme280),
+        Lcd1602Display::new_with_address(bus.clone(), NoopDelay, addresses.lcd),
+        ClimateDisplayConfig {
+            refresh_period_ticks: 5,
+            refresh_on_first_tick: true,
+        },
+    )
+}
 
 // ── DeviceSimulationRig ────────────────────────────────────────────────────
 
 pub(super) struct DeviceSimulationRig {
     pub board: BoardProfile,
     pub bus: VirtualI2cBus,
+    /// Addresses the mocks and drivers currently use; follows the wiring
+    /// project so a moved device still answers its driver.
+    pub addresses: BusAddresses,
     pub bme280: MockBme280Device,
     pub lcd: MockLcd1602Device,
     pub mpu6050: MockMpu6050Device,
     pub climate_sensor: Bme280Sensor<VirtualI2cBus>,
-    pub app:
-        ClimateDisplayApp<Bme280Sensor<VirtualI2cBus>, Lcd1602Display<VirtualI2cBus, NoopDelay>>,
+    pub app: ClimateAppRig,
     pub bme280_samples: Vec<[u8; 8]>,
     pub bme280_sample_index: usize,
     pub distance_sensor: HcSr04Sensor<MockHcSr04Device>,
@@ -96,7 +210,7 @@ pub(super) struct DeviceSimulationRig {
     pub last_lux_x100: u32,
     pub last_camera_sequence: u32,
     pub ds3231_mock: MockDs3231Device,
-    pub ds3231_timestamps: Vec<platform_pc_sim::ds3231_mock::MockRtcTimestamp>,
+    pub ds3231_timestamps: Vec<MockRtcTimestamp>,
     pub ds3231_ts_index: usize,
     pub rtc_sensor: Ds3231Sensor<VirtualI2cBus>,
     pub sgp30_mock: MockSgp30Device,
@@ -115,10 +229,27 @@ pub(super) struct DeviceSimulationRig {
     pub diag_ring: VecDeque<DiagEvent>,
     /// Cumulative diagnostic event counter.
     pub diag_event_count: u32,
+    /// Cumulative diagnostic events per severity: info, warn, error.
+    pub diag_severity_counts: [u64; 3],
     /// Monotonic start time for elapsed-ms timestamps in diag events.
     pub start_instant: std::time::Instant,
+    /// Threshold alarms evaluated against every fresh sensor reading.
+    pub alarm_engine: AlarmEngine<SensorSample, 4>,
+    /// Alarm transitions waiting to be copied into the diagnostics ring.
+    pub alarm_events: AlarmEventQueue<8>,
     /// Device selection from the previous tick — used to detect toggle events.
     pub last_selected_devices: Vec<DeviceKind>,
+    /// Selected devices left detached because an earlier device already
+    /// holds their address; treated as disabled until the clash is fixed.
+    pub shadowed_devices: Vec<DeviceKind>,
+    /// Sensor overrides and manual actuator commands from `/api/control`,
+    /// copied in by the main loop before every `advance()`.
+    pub controls: ActiveControls,
+    /// Raw BME280 samples re-encoded for the current climate overrides,
+    /// keyed by the demo sample they replace (the demo sequence only has a
+    /// handful of entries, so this stays tiny).
+    bme280_override_cache: Vec<([u8; 8], [u8; 8])>,
+    bme280_override_key: [Option<f64>; 3],
     /// Cached wiring diagram lines keyed by the `WiringConfig` that produced
     /// them, so `snapshot()` only re-renders the diagram when the wiring
     /// actually changes instead of on every SSE push tick.
@@ -142,32 +273,26 @@ impl DeviceSimulationRig {
         let vl53l0x_mock = MockVl53l0xDevice::new();
         let ssd1306_display = MockSsd1306TextDisplay::new();
 
-        bus.attach_device(BME280_ADDRESS_PRIMARY, bme280.clone());
-        bus.attach_device(LCD1602_ADDRESS_PRIMARY, lcd.clone());
-        bus.attach_device(MPU6050_ADDRESS_PRIMARY, mpu6050.clone());
-        bus.attach_device(BH1750_ADDRESS_LOW, bh1750_mock.clone());
-        bus.attach_device(DS3231_SIM_ADDRESS, ds3231_mock.clone());
-        bus.attach_device(SGP30_ADDRESS, sgp30_mock.clone());
-        bus.attach_device(VL53L0X_ADDRESS, vl53l0x_mock.clone());
-
-        let app = ClimateDisplayApp::new_with_config(
-            Bme280Sensor::new(bus.clone()),
-            Lcd1602Display::new(bus.clone(), NoopDelay),
-            ClimateDisplayConfig {
-                refresh_period_ticks: 5,
-                refresh_on_first_tick: true,
-            },
-        );
-        let climate_sensor = Bme280Sensor::new(bus.clone());
+        let addresses = BusAddresses::default();
+        bus.attach_device(addresses.bme280, bme280.clone());
+        bus.attach_device(addresses.lcd, lcd.clone());
+        bus.attach_device(addresses.mpu6050, mpu6050.clone());
+        bus.attach_device(addresses.bh1750, bh1750_mock.clone());
+        bus.attach_device(addresses.ds3231, ds3231_mock.clone());
+        bus.attach_device(addresses.sgp30, sgp30_mock.clone());
+        bus.attach_device(addresses.vl53l0x, vl53l0x_mock.clone());
+
+        let app = climate_app(&bus, addresses);
+        let climate_sensor = Bme280Sensor::new_with_address(bus.clone(), addresses.bme280);
         let distance_sensor = HcSr04Sensor::new(MockHcSr04Device::looping(demo_echo_pulses_us()));
-        let imu_sensor = Mpu6050Sensor::new(bus.clone());
-        let light_sensor = Bh1750Sensor::new(bus.clone(), BH1750_ADDRESS_LOW)
+        let imu_sensor = Mpu6050Sensor::new_with_address(bus.clone(), addresses.mpu6050);
+        let light_sensor = Bh1750Sensor::new(bus.clone(), addresses.bh1750)
             .expect("BH1750 mock device should initialise");
         let camera = MockCamera::qvga_jpeg();
-        let rtc_sensor = Ds3231Sensor::new(bus.clone(), DS3231_SIM_ADDRESS);
+        let rtc_sensor = Ds3231Sensor::new(bus.clone(), addresses.ds3231);
         let sgp30_sensor =
-            Sgp30Sensor::new(bus.clone(), SGP30_ADDRESS).expect("SGP30 mock init should succeed");
-        let tof_sensor = Vl53l0xSensor::new(bus.clone(), VL53L0X_ADDRESS)
+            Sgp30Sensor::new(bus.clone(), addresses.sgp30).expect("SGP30 mock init should succeed");
+        let tof_sensor = Vl53l0xSensor::new(bus.clone(), addresses.vl53l0x)
             .expect("VL53L0X mock init should succeed");
 
         let servo = ServoDriver::new(MockPwmOutput::new());
@@ -179,6 +304,7 @@ impl DeviceSimulationRig {
         Self {
             board,
             bus,
+            addresses,
             bme280,
             lcd,
             mpu6050,
@@ -216,66 +342,108 @@ impl DeviceSimulationRig {
             last_climate: None,
             diag_ring: VecDeque::new(),
             diag_event_count: 0,
+            diag_severity_counts: [0; 3],
             start_instant: std::time::Instant::now(),
+            alarm_engine: dashboard_alarm_engine(),
+            alarm_events: AlarmEventQueue::new(),
             last_selected_devices: vec![],
+            shadowed_devices: vec![],
+            controls: ActiveControls::default(),
+            bme280_override_cache: Vec::new(),
+            bme280_override_key: [None; 3],
             wiring_diagram_cache: None,
         }
     }
 
+    /// Attach the selected mocks at their addresses. When two share an
+    /// address the first one keeps it; the other stays detached (and is not
+    /// polled) and an error goes to the diagnostics ring, since a real bus
+    /// would have both answering at once.
     pub fn sync_selected_devices(&mut self, selected_devices: &[DeviceKind]) {
-        if selected_devices.contains(&DeviceKind::Bme280) {
-            self.bus
-                .attach_device(BME280_ADDRESS_PRIMARY, self.bme280.clone());
-        } else {
-            self.bus.detach_device(BME280_ADDRESS_PRIMARY);
-        }
-
-        if selected_devices.contains(&DeviceKind::Lcd1602) {
-            self.bus
-                .attach_device(LCD1602_ADDRESS_PRIMARY, self.lcd.clone());
-        } else {
-            self.bus.detach_device(LCD1602_ADDRESS_PRIMARY);
+        for addr in self.bus.attached_addresses() {
+            self.bus.detach_device(addr);
         }
-
-        if selected_devices.contains(&DeviceKind::Mpu6050) {
-            self.bus
-                .attach_device(MPU6050_ADDRESS_PRIMARY, self.mpu6050.clone());
-        } else {
-            self.bus.detach_device(MPU6050_ADDRESS_PRIMARY);
+        let addresses = self.addresses;
+        let mocks = [
+            (DeviceKind::Bme280, addresses.bme280),
+            (DeviceKind::Lcd1602, addresses.lcd),
+            (DeviceKind::Mpu6050, addresses.mpu6050),
+            (DeviceKind::Bh1750, addresses.bh1750),
+            (DeviceKind::Ds3231, addresses.ds3231),
+            (DeviceKind::Sgp30, addresses.sgp30),
+            (DeviceKind::Vl53l0x, addresses.vl53l0x),
+        ];
+        let mut claimed: Vec<(u8, DeviceKind)> = Vec::new();
+        self.shadowed_devices.clear();
+        for (kind, addr) in mocks {
+            if !selected_devices.contains(&kind) {
+                continue;
+            }
+            if let Some(&(_, owner)) = claimed.iter().find(|(claimed, _)| *claimed == addr) {
+                self.shadowed_devices.push(kind);
+                self.push_diag(
+                    "error",
+                    format!(
+                        "[i2c] {} shares 0x{:02X} with {}; left detached",
+                        kind.label(),
+                        addresses.display(addr),
+                        owner.label()
+                    ),
+                );
+                continue;
+            }
+            claimed.push((addr, kind));
+            self.attach_mock(kind, addr);
         }
+    }
 
-        if selected_devices.contains(&DeviceKind::Bh1750) {
-            self.bus
-                .attach_device(BH1750_ADDRESS_LOW, self.bh1750_mock.clone());
-        } else {
-            self.bus.detach_device(BH1750_ADDRESS_LOW);
+    fn attach_mock(&self, kind: DeviceKind, addr: u8) {
+        match kind {
+            DeviceKind::Bme280 => self.bus.attach_device(addr, self.bme280.clone()),
+            DeviceKind::Lcd1602 => self.bus.attach_device(addr, self.lcd.clone()),
+            DeviceKind::Mpu6050 => self.bus.attach_device(addr, self.mpu6050.clone()),
+            DeviceKind::Bh1750 => self.bus.attach_device(addr, self.bh1750_mock.clone()),
+            DeviceKind::Ds3231 => self.bus.attach_device(addr, self.ds3231_mock.clone()),
+            DeviceKind::Sgp30 => self.bus.attach_device(addr, self.sgp30_mock.clone()),
+            DeviceKind::Vl53l0x => self.bus.attach_device(addr, self.vl53l0x_mock.clone()),
+            _ => {}
         }
+    }
 
-        if selected_devices.contains(&DeviceKind::Ds3231) {
-            self.bus
-                .attach_device(DS3231_SIM_ADDRESS, self.ds3231_mock.clone());
-        } else {
-            self.bus.detach_device(DS3231_SIM_ADDRESS);
-        }
+    /// Move the mocks to `addresses` and rebuild the drivers that talk to
+    /// them, then re-attach only `selected_devices`.
+    fn rebind_addresses(&mut self, addresses: BusAddresses, selected_devices: &[DeviceKind]) {
+        self.addresses = addresses;
+        // These constructors talk to their device, so give each its own mock
+        // first even if the project puts another device at the same address.
+        self.attach_mock(DeviceKind::Bh1750, addresses.bh1750);
+        self.light_sensor = Bh1750Sensor::new(self.bus.clone(), addresses.bh1750)
+            .expect("BH1750 mock device should initialise");
+        self.attach_mock(DeviceKind::Sgp30, addresses.sgp30);
+        self.sgp30_sensor = Sgp30Sensor::new(self.bus.clone(), addresses.sgp30)
+            .expect("SGP30 mock init should succeed");
+        self.attach_mock(DeviceKind::Vl53l0x, addresses.vl53l0x);
+        self.tof_sensor = Vl53l0xSensor::new(self.bus.clone(), addresses.vl53l0x)
+            .expect("VL53L0X mock init should succeed");
 
-        if selected_devices.contains(&DeviceKind::Sgp30) {
-            self.bus
-                .attach_device(SGP30_ADDRESS, self.sgp30_mock.clone());
-        } else {
-            self.bus.detach_device(SGP30_ADDRESS);
-        }
+        let bus = &self.bus;
+        self.app = climate_app(bus, addresses);
+        self.climate_sensor = Bme280Sensor::new_with_address(bus.clone(), addresses.bme280);
+        self.imu_sensor = Mpu6050Sensor::new_with_address(bus.clone(), addresses.mpu6050);
+        self.rtc_sensor = Ds3231Sensor::new(bus.clone(), addresses.ds3231);
 
-        if selected_devices.contains(&DeviceKind::Vl53l0x) {
-            self.bus
-                .attach_device(VL53L0X_ADDRESS, self.vl53l0x_mock.clone());
-        } else {
-            self.bus.detach_device(VL53L0X_ADDRESS);
-        }
+        self.sync_selected_devices(selected_devices);
     }
 
     pub fn push_diag(&mut self, severity: &str, msg: String) {
         const MAX_RING: usize = 20;
         self.diag_event_count = self.diag_event_count.saturating_add(1);
+        let slot = match severity {
+            "error" => 2,
+            "warn" => 1,
+            _ => 0,
+        };
+        self.diag_severity_counts[slot] += 1;
         if self.diag_ring.len() >= MAX_RING {
             self.diag_ring.pop_front();
         }
@@ -286,6 +454,50 @@ impl DeviceSimulationRig {
         });
     }
 
+    /// Feed one sensor reading through the alarm rules and log any
+    /// raised / cleared transitions to the diagnostics ring.
+    fn check_alarms(&mut self, sample: SensorSample) {
+        let now_ms = self.tick.wrapping_mul(SIM_TICK_MS);
+        let report = self.alarm_engine.evaluate(&sample, now_ms);
+        let Ok(()) = report.dispatch(&mut self.alarm_events, now_ms);
+        while let Some(event) = self.alarm_events.pop() {
+            let severity = match (event.transition, event.severity) {
+                (AlarmTransition::Raised, AlarmSeverity::Critical) => "error",
+                (AlarmTransition::Raised, AlarmSeverity::Warning) => "warn",
+                _ => "info",
+            };
+            self.push_diag(
+                severity,
+                format!(
+                    "[alarm] {} {} ({})",
+                    event.name,
+                    event.transition.as_str(),
+                    event.value
+                ),
+            );
+        }
+    }
+
+    /// Currently raised alarms, formatted for the diagnostics panel.
+    fn active_alarm_lines(&self) -> Vec<String> {
+        self.alarm_engine
+            .rules()
+            .filter(|rule| {
+                self.alarm_engine
+                    .state(rule.id)
+                    .is_some_and(|state| state.is_raised())
+            })
+            .map(|rule| {
+                format!(
+                    "{} {} = {}",
+                    rule.severity.as_str(),
+                    rule.name,
+                    self.alarm_engine.last_value(rule.id).unwrap_or_default()
+                )
+            })
+            .collect()
+    }
+
     /// Advance the simulation by one tick and build the full dashboard
     /// snapshot. Equivalent to `advance()` followed by `snapshot()`. The
     /// production main loop calls `advance()` / `snapshot()` separately
@@ -312,7 +524,10 @@ impl DeviceSimulationRig {
         // virtual bus (a fresh Box+Rc allocation per device, see
         // VirtualI2cBus::attach_device), which is pure churn when the
         // selection hasn't changed since the previous tick.
-        if selected_devices != self.last_selected_devices {
+        let addresses = BusAddresses::for_wiring(wiring_state);
+        if addresses != self.addresses {
+            self.rebind_addresses(addresses, &selected_devices);
+        } else if selected_devices != self.last_selected_devices {
             self.sync_selected_devices(&selected_devices);
         }
         self.bus.clear_operations();
@@ -336,7 +551,9 @@ impl DeviceSimulationRig {
         }
         self.last_selected_devices = selected_devices.clone();
 
-        let is_enabled = |kind: DeviceKind| selected_devices.contains(&kind);
+        let shadowed = self.shadowed_devices.clone();
+        let is_enabled =
+            |kind: DeviceKind| selected_devices.contains(&kind) && !shadowed.contains(&kind);
         let bme280_enabled = is_enabled(DeviceKind::Bme280);
         let lcd_enabled = is_enabled(DeviceKind::Lcd1602);
 
@@ -366,8 +583,9 @@ impl DeviceSimulationRig {
         }
 
         if bme280_enabled {
-            self.bme280
-                .set_raw_sample(self.bme280_samples[self.bme280_sample_index]);
+            let sample =
+                self.overridden_bme280_sample(self.bme280_samples[self.bme280_sample_index]);
+            self.bme280.set_raw_sample(sample);
             self.bme280_sample_index = (self.bme280_sample_index + 1) % self.bme280_samples.len();
         }
 
@@ -383,25 +601,44 @@ impl DeviceSimulationRig {
                 .expect("dashboard climate app should keep running");
         }
         if is_enabled(DeviceKind::HcSr04) && (tick == 1 || tick % 2 == 0) {
-            self.last_distance_mm = Some(
-                self.distance_sensor
-                    .read_distance()
-                    .expect("distance driver should read from host-side pulse device")
-                    .distance_mm,
-            );
+            let mut reading = self
+                .distance_sensor
+                .read_distance()
+                .expect("distance driver should read from host-side pulse device");
+            if let Some(mm) = self.controls.sensors.get(SensorChannel::DistanceMm) {
+                reading = DistanceReading::new(mm as u32);
+            }
+            self.last_distance_mm = Some(reading.distance_mm);
+            self.check_alarms(reading.into());
         }
 
         if is_enabled(DeviceKind::Mpu6050) && (tick == 1 || tick % 3 == 0) {
-            self.last_imu = Some(
-                self.imu_sensor
-                    .read_imu()
-                    .expect("imu driver should read from host-side mock device"),
-            );
+            let mut reading = self
+                .imu_sensor
+                .read_imu()
+                .expect("imu driver should read from host-side mock device");
+            let sensors = &self.controls.sensors;
+            for (axis, channel) in SensorChannel::ACCEL_MG.into_iter().enumerate() {
+                if let Some(mg) = sensors.get(channel) {
+                    reading.accel_mg[axis] = mg.round() as i16;
+                }
+            }
+            for (axis, channel) in SensorChannel::GYRO_DPS.into_iter().enumerate() {
+                if let Some(dps) = sensors.get(channel) {
+                    reading.gyro_mdps[axis] = (dps * 1000.0).round() as i32;
+                }
+            }
+            self.last_imu = Some(reading);
         }
 
         if is_enabled(DeviceKind::Bh1750) && (tick == 1 || tick % 5 == 0) {
             match self.light_sensor.read_lux() {
-                Ok(reading) => self.last_lux_x100 = reading.lux_x100,
+                Ok(reading) => {
+                    self.last_lux_x100 = match self.controls.sensors.get(SensorChannel::Lux) {
+                        Some(lux) => (lux * 100.0).round() as u32,
+                        None => reading.lux_x100,
+                    };
+                }
                 Err(_) => self.push_diag("error", "[bh1750] read_lux error".into()),
             }
         }
@@ -416,7 +653,19 @@ impl DeviceSimulationRig {
         // Poll SGP30 gas sensor every 11 ticks
         if is_enabled(DeviceKind::Sgp30) && (tick == 1 || tick % 11 == 0) {
             match self.sgp30_sensor.read_gas() {
-                Ok(reading) => self.last_gas = Some(reading),
+                Ok(reading) => {
+                    let sensors = &self.controls.sensors;
+                    let reading = GasReading::new(
+                        sensors
+                            .get(SensorChannel::Co2Ppm)
+                            .map_or(reading.co2_ppm, |ppm| ppm as u16),
+                        sensors
+                            .get(SensorChannel::VocPpb)
+                            .map_or(reading.voc_ppb, |ppb| ppb as u16),
+                    );
+                    self.last_gas = Some(reading);
+                    self.check_alarms(reading.into());
+                }
                 Err(_) => self.push_diag("error", "[sgp30] read_gas error".into()),
             }
         }
@@ -424,7 +673,10 @@ impl DeviceSimulationRig {
         // Poll DS3231 RTC every tick so /api/state recent_operations always reflects the
         // currently selected simulator address.
         if is_enabled(DeviceKind::Ds3231) {
-            let ts = self.ds3231_timestamps[self.ds3231_ts_index];
+            let ts = match self.controls.sensors.get(SensorChannel::RtcUnixS) {
+                Some(seconds) => rtc_timestamp_from_unix(seconds as u32),
+                None => self.ds3231_timestamps[self.ds3231_ts_index],
+            };
             self.ds3231_ts_index = (self.ds3231_ts_index + 1) % self.ds3231_timestamps.len();
             self.ds3231_mock.set_timestamp(ts);
             match self.rtc_sensor.read_datetime() {
@@ -449,13 +701,22 @@ impl DeviceSimulationRig {
         // Poll VL53L0X ToF sensor every 4 ticks
         if is_enabled(DeviceKind::Vl53l0x) && (tick == 1 || tick % 4 == 0) {
             match self.tof_sensor.read_distance() {
-                Ok(reading) => self.last_tof_mm = Some(reading.distance_mm),
+                Ok(reading) => {
+                    self.last_tof_mm = Some(
+                        self.controls
+                            .sensors
+                            .get(SensorChannel::TofMm)
+                            .map_or(reading.distance_mm, |mm| mm as u32),
+                    );
+                }
                 Err(_) => self.push_diag("error", "[vl53l0x] read_distance error".into()),
             }
         }
 
         if is_enabled(DeviceKind::Servo) {
-            let servo_angle = if is_enabled(DeviceKind::HcSr04) {
+            let servo_angle = if let Some(angle) = self.controls.servo_angle {
+                angle
+            } else if is_enabled(DeviceKind::HcSr04) {
                 distance_to_servo_angle(self.last_distance_mm.unwrap_or(180))
             } else {
                 0
@@ -470,8 +731,9 @@ impl DeviceSimulationRig {
         }
 
         if is_enabled(DeviceKind::L298n) {
-            let (left, right) = if is_enabled(DeviceKind::HcSr04) && is_enabled(DeviceKind::Mpu6050)
-            {
+            let (left, right) = if let Some(manual) = self.controls.motors {
+                manual
+            } else if is_enabled(DeviceKind::HcSr04) && is_enabled(DeviceKind::Mpu6050) {
                 motor_commands_from_state(self.last_distance_mm, self.last_imu)
             } else {
                 (
@@ -502,6 +764,10 @@ impl DeviceSimulationRig {
             None
         };
 
+        if let Some(reading) = self.last_climate {
+            self.check_alarms(reading.into());
+        }
+
         // Render climate frame to SSD1306 display every 5 ticks when both are enabled
         if is_enabled(DeviceKind::Ssd1306) && bme280_enabled && (tick == 1 || tick % 5 == 0) {
             if let Some(reading) = self.last_climate {
@@ -516,6 +782,46 @@ impl DeviceSimulationRig {
         }
     }
 
+    /// Re-encodes `demo_sample` so the BME280 driver reads the overridden
+    /// temperature / humidity / pressure. Channels without an override keep
+    /// the demo value, so e.g. pinning the temperature still lets humidity
+    /// move. Going through the raw registers (rather than patching the
+    /// reading afterwards) keeps the LCD / OLED frames and alarms consistent.
+    fn overridden_bme280_sample(&mut self, demo_sample: [u8; 8]) -> [u8; 8] {
+        let sensors = self.controls.sensors;
+        let key = [
+            sensors.get(SensorChannel::TemperatureC),
+            sensors.get(SensorChannel::HumidityPercent),
+            sensors.get(SensorChannel::PressurePa),
+        ];
+        if key == [None; 3] {
+            return demo_sample;
+        }
+        if key != self.bme280_override_key {
+            self.bme280_override_key = key;
+            self.bme280_override_cache.clear();
+        }
+        if let Some((_, raw)) = self
+            .bme280_override_cache
+            .iter()
+            .find(|(demo, _)| *demo == demo_sample)
+        {
+            return *raw;
+        }
+        let Some(demo) = reading_for_raw_sample(demo_sample) else {
+            return demo_sample;
+        };
+        let raw = raw_sample_for_reading(EnvReading::new(
+            key[0].map_or(demo.temperature_centi_celsius, |c| {
+                (c * 100.0).round() as i32
+            }),
+            key[1].map_or(demo.humidity_centi_percent, |p| (p * 100.0).round() as u32),
+            key[2].map(|pa| pa.round() as u32).or(demo.pressure_pascal),
+        ));
+        self.bme280_override_cache.push((demo_sample, raw));
+        raw
+    }
+
     /// Returns the formatted wiring diagram for `config`, reusing the cached
     /// result when the wiring hasn't changed since the last snapshot instead
     /// of re-running `build_wiring_diagram()`'s string formatting on every
@@ -542,38 +848,36 @@ impl DeviceSimulationRig {
             wiring_state.board,
             &wiring_state.selected_devices,
         );
-        let is_enabled = |kind: DeviceKind| selected_devices.contains(&kind);
+        let shadowed = self.shadowed_devices.clone();
+        let is_enabled =
+            |kind: DeviceKind| selected_devices.contains(&kind) && !shadowed.contains(&kind);
         let bme280_enabled = is_enabled(DeviceKind::Bme280);
         let lcd_enabled = is_enabled(DeviceKind::Lcd1602);
 
-        let wiring_config = dashboard_wiring_config(
-            wiring_state.board,
-            wiring_state.sensor_profile,
-            &selected_devices,
-            wiring_state.show_bus_labels,
-        );
+        let wiring_config = dashboard_wiring_config(wiring_state);
         let attached_devices = wiring_config
             .devices
             .iter()
             .map(|device| device.label.clone())
             .collect::<Vec<_>>();
+        let addresses = self.addresses;
         let attached_addresses = self
             .bus
             .attached_addresses()
             .into_iter()
-            .map(display_i2c_addr)
+            .map(|addr| addresses.display(addr))
             .collect::<Vec<_>>();
         let recent_operations = self
             .bus
             .operations()
             .iter()
             .rev()
-            .filter(|operation| attached_addresses.contains(&operation_addr(operation)))
+            .filter(|operation| attached_addresses.contains(&operation_addr(operation, addresses)))
             .take(10)
             .collect::<Vec<_>>()
             .into_iter()
             .rev()
-            .map(format_operation)
+            .map(|operation| format_operation(operation, addresses))
             .collect::<Vec<_>>();
         let physical_lcd_frame = frame_to_lines(self.lcd.frame());
         let climate = self.last_climate;
@@ -721,11 +1025,48 @@ impl DeviceSimulationRig {
             diagnostics: DiagnosticsPanelState {
                 recent_events: self.diag_ring.iter().rev().cloned().collect(),
                 event_count: self.diag_event_count,
+                active_alarms: self.active_alarm_lines(),
             },
         }
     }
 }
 
+// ── Alarm helpers ──────────────────────────────────────────────────────────
+
+/// Demo alarm rules: CO2 above 1000 ppm (SGP30), an obstacle closer than
+/// 150 mm (HC-SR04), and room temperature leaving 18–28 °C (BME280).
+pub(super) fn dashboard_alarm_engine() -> AlarmEngine<SensorSample, 4> {
+    let mut engine = AlarmEngine::new();
+    let rules = [
+        AlarmRule {
+            hysteresis: 100,
+            ..AlarmRule::rising(ALARM_CO2_HIGH, "co2-high", metric::co2_ppm, 1000)
+        },
+        AlarmRule {
+            hysteresis: 20,
+            severity: AlarmSeverity::Info,
+            ..AlarmRule::falling(ALARM_TOO_CLOSE, "too-close", metric::distance_mm, 150)
+        },
+        AlarmRule {
+            hysteresis: 50,
+            debounce_ms: 1_000,
+            ..AlarmRule::outside_band(
+                ALARM_TEMPERATURE_BAND,
+                "temp-band",
+                metric::temperature_centi_celsius,
+                1_800,
+                2_800,
+            )
+        },
+    ];
+    for rule in rules {
+        engine
+            .add_rule(rule)
+            .expect("dashboard alarm engine has room for the demo rules");
+    }
+    engine
+}
+
 // ── Display helpers ─────────────────────────────────────────────────────────
 
 pub(super) fn blank_lines() -> [&'static str; 2] {
@@ -815,23 +1156,23 @@ fn build_wiring_diagram(config: &WiringConfig) -> Vec<String> {
                     ));
                     lines.push(format!(
                         "{} IN1  --- Motor-A direction 1",
-                        config.board.motor_in1_pin()
+                        config.motor_in1_pin
                     ));
                     lines.push(format!(
                         "{} IN2  --- Motor-A direction 2",
-                        config.board.motor_in2_pin()
+                        config.motor_in2_pin
                     ));
                     lines.push(format!(
                         "{} ENB  --- Motor-B enable (PWM)",
-                        config.board.motor_enb_pin()
+                        config.motor_enb_pin
                     ));
                     lines.push(format!(
                         "{} IN3  --- Motor-B direction 1",
-                        config.board.motor_in3_pin()
+                        config.motor_in3_pin
                     ));
                     lines.push(format!(
                         "{} IN4  --- Motor-B direction 2",
-                        config.board.motor_in4_pin()
+                        config.motor_in4_pin
                     ));
                 }
                 _ => {}
@@ -899,36 +1240,45 @@ fn disabled_motor_command() -> MotorCommand {
     MotorCommand::new(MotorDirection::Coast, 0)
 }
 
-// ── I2C address display helpers ────────────────────────────────────────────
-
-fn display_i2c_addr(addr: u8) -> u8 {
-    if addr == DS3231_SIM_ADDRESS {
-        DS3231_ADDRESS
-    } else {
-        addr
-    }
+/// DS3231 register contents for an RTC override, so the driver decodes it
+/// like any other time.
+fn rtc_timestamp_from_unix(seconds: u32) -> MockRtcTimestamp {
+    let dt = datetime_from_unix(seconds);
+    // 1970-01-01 was a Thursday; DS3231 day-of-week runs 1 (Monday) ..= 7.
+    let dow = ((seconds / 86_400 + 3) % 7 + 1) as u8;
+    MockRtcTimestamp::from_decimal(
+        dt.second,
+        dt.minute,
+        dt.hour,
+        dow,
+        dt.day,
+        dt.month,
+        dt.year_offset,
+    )
 }
 
-fn operation_addr(operation: &VirtualI2cOperation) -> u8 {
+// ── I2C address display helpers ────────────────────────────────────────────
+
+fn operation_addr(operation: &VirtualI2cOperation, addresses: BusAddresses) -> u8 {
     match operation {
         VirtualI2cOperation::Write { addr, .. }
         | VirtualI2cOperation::Read { addr, .. }
-        | VirtualI2cOperation::WriteRead { addr, .. } => display_i2c_addr(*addr),
+        | VirtualI2cOperation::WriteRead { addr, .. } => addresses.display(*addr),
     }
 }
 
-fn format_operation(operation: &VirtualI2cOperation) -> String {
+fn format_operation(operation: &VirtualI2cOperation, addresses: BusAddresses) -> String {
     match operation {
         VirtualI2cOperation::Write { addr, bytes } => {
-            let addr = display_i2c_addr(*addr);
+            let addr = addresses.display(*addr);
             format!("WRITE addr=0x{addr:02X} bytes={bytes:02X?}")
         }
         VirtualI2cOperation::Read { addr, len } => {
-            let addr = display_i2c_addr(*addr);
+            let addr = addresses.display(*addr);
             format!("READ addr=0x{addr:02X} len={len}")
         }
         VirtualI2cOperation::WriteRead { addr, bytes, len } => {
-            let addr = display_i2c_addr(*addr);
+            let addr = addresses.display(*addr);
             format!("WRITE_READ addr=0x{addr:02X} bytes={bytes:02X?} len={len}")
         }
     }
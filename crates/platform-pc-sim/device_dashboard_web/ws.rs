@@ -0,0 +1,446 @@
+//! `GET /api/ws`: WebSocket transport for state deltas and control commands.
+//!
+//! All messages are JSON text frames with a `"type"` field.
+//!
+//! Server → client:
+//!
+//! - `{"type":"snapshot","seq":S,"tick":T,"resync":false,"panels":{...}}` —
+//!   every subscribed panel. Sent on connect, after `subscribe` /
+//!   `resync`, and with `"resync":true` when the client fell behind.
+//! - `{"type":"delta","seq":S,"tick":T,"panels":{...}}` — only the subscribed
+//!   panels that changed since the previous push. Pushes where nothing
+//!   subscribed changed are skipped, so `seq` may jump.
+//! - `{"type":"subscribed","panels":[...],"min_interval_ms":N}`
+//! - `{"type":"ack","id":I,"control":{...}}` — command applied; `control` is
+//!   the same JSON as `GET /api/control`.
+//! - `{"type":"error","id":I,"message":"..."}` — `id` is `null` for
+//!   malformed messages.
+//!
+//! Client → server:
+//!
+//! - `{"type":"subscribe","panels":["climate","servo"],"min_interval_ms":500}`
+//!   replaces the subscription. `panels` defaults to all of [`PANELS`],
+//!   `min_interval_ms` to 0 (every push, ~100 ms). With an interval, changes
+//!   are merged and only the latest value of each panel is sent.
+//! - `{"type":"unsubscribe","panels":["i2c"]}`
+//! - `{"type":"command","id":1,"command":"set temperature_c 31"}` — the
+//!   scenario syntax of `sim_control` plus `pause`, `resume` and `step N`.
+//! - `{"type":"resync"}` asks for a fresh snapshot.
+//!
+//! Backpressure: each client has a small bounded queue. When the simulation
+//! loop finds it full, the push is dropped for that client and it is marked
+//! lagging; its next message is a `"resync":true` snapshot and any older
+//! deltas still queued are discarded. Panel values are whole replacements,
+//! so a client never has to replay missed deltas.
+
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use platform_pc_sim::web_dashboard::{PanelDelta, PanelTracker, PANELS};
+use platform_pc_sim::websocket::{self, Message, WebSocket};
+use serde::Deserialize;
+
+use super::session::Session;
+
+/// Pushes queued per client before it is considered lagging (~0.8 s).
+const CLIENT_QUEUE_LEN: usize = 8;
+/// Largest client message accepted (commands and subscriptions are tiny).
+const MAX_CLIENT_MESSAGE_LEN: usize = 64 * 1024;
+const ALL_PANELS: u16 = (1 << PANELS.len()) - 1;
+
+/// One push from the simulation loop, shared by every client.
+pub(super) struct WsUpdate {
+    seq: u64,
+    tick: u32,
+    changed: Vec<(usize, Arc<str>)>,
+    /// The delta message for clients subscribed to every panel.
+    message: Arc<str>,
+}
+
+enum WsEvent {
+    Update(Arc<WsUpdate>),
+    Subscribe { mask: u16, min_interval: Duration },
+    Unsubscribe(u16),
+    Resync,
+    Reply(String),
+    Pong(Vec<u8>),
+    Close(Option<u16>),
+}
+
+/// Handle kept by [`Session`] for one connected client.
+pub(super) struct WsClient {
+    tx: SyncSender<WsEvent>,
+    lagging: Arc<AtomicBool>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Subscribe {
+        panels: Option<Vec<String>>,
+        min_interval_ms: Option<u64>,
+    },
+    Unsubscribe {
+        panels: Vec<String>,
+    },
+    Command {
+        id: Option<u64>,
+        command: String,
+    },
+    Resync,
+}
+
+/// Sends `delta` to every client, dropping disconnected ones and flagging
+/// the ones whose queue is full.
+pub(super) fn fan_out(clients: &mut Vec<WsClient>, delta: PanelDelta) {
+    if clients.is_empty() {
+        return;
+    }
+    let changed: Vec<(usize, Arc<str>)> = delta
+        .changed
+        .into_iter()
+        .filter_map(|(name, json)| Some((panel_index(name)?, json)))
+        .collect();
+    let message = panels_message(
+        "delta",
+        delta.seq,
+        delta.tick,
+        None,
+        changed.iter().map(|(i, json)| (*i, &**json)),
+    );
+    let update = Arc::new(WsUpdate {
+        seq: delta.seq,
+        tick: delta.tick,
+        changed,
+        message: Arc::from(message),
+    });
+    clients.retain(
+        |client| match client.tx.try_send(WsEvent::Update(Arc::clone(&update))) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                client.lagging.store(true, Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        },
+    );
+}
+
+/// Asks every client to close with 1001 (going away) and forgets them.
+pub(super) fn close_all(clients: &mut Vec<WsClient>) {
+    for client in clients.drain(..) {
+        let _ = client.tx.try_send(WsEvent::Close(Some(1001)));
+    }
+}
+
+/// Completes the upgrade and serves the client until it disconnects.
+pub(super) fn handle_websocket(mut stream: TcpStream, request_head: &str, session: Arc<Session>) {
+    if websocket::server_handshake(&mut stream, request_head).is_err() {
+        return;
+    }
+    // Clients may stay silent for as long as they like.
+    let _ = stream.set_read_timeout(None);
+    let Ok(read_half) = stream.try_clone() else {
+        return;
+    };
+
+    let (tx, rx) = mpsc::sync_channel(CLIENT_QUEUE_LEN);
+    let lagging = Arc::new(AtomicBool::new(false));
+    let reader_tx = tx.clone();
+    let reader_session = Arc::clone(&session);
+    thread::spawn(move || read_client(read_half, reader_tx, reader_session));
+    session.ws_clients.lock().unwrap().push(WsClient {
+        tx,
+        lagging: Arc::clone(&lagging),
+    });
+
+    let mut writer = ClientWriter {
+        socket: WebSocket::server(stream),
+        mask: ALL_PANELS,
+        min_interval: Duration::ZERO,
+        pending: std::array::from_fn(|_| None),
+        pending_seq_tick: None,
+        last_flush: Instant::now(),
+        last_seq: 0,
+    };
+    if writer.send_snapshot(&session, false).is_err() {
+        return;
+    }
+
+    loop {
+        let event = match writer.flush_deadline() {
+            Some(deadline) => {
+                match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            None => match rx.recv() {
+                Ok(event) => Some(event),
+                Err(_) => break,
+            },
+        };
+
+        if lagging.swap(false, Ordering::Relaxed) && writer.send_snapshot(&session, true).is_err() {
+            break;
+        }
+        let result = match event {
+            None => writer.flush(),
+            Some(WsEvent::Update(update)) => writer.push(&update),
+            Some(WsEvent::Subscribe { mask, min_interval }) => {
+                writer.mask = mask;
+                writer.min_interval = min_interval;
+                writer.pending = std::array::from_fn(|_| None);
+                writer.pending_seq_tick = None;
+                writer
+                    .send_text(subscribed_message(mask, min_interval))
+                    .and_then(|()| writer.send_snapshot(&session, false))
+            }
+            Some(WsEvent::Unsubscribe(mask)) => {
+                writer.mask &= !mask;
+                for (index, slot) in writer.pending.iter_mut().enumerate() {
+                    if mask & (1 << index) != 0 {
+                        *slot = None;
+                    }
+                }
+                writer.send_text(subscribed_message(writer.mask, writer.min_interval))
+            }
+            Some(WsEvent::Resync) => writer.send_snapshot(&session, false),
+            Some(WsEvent::Reply(text)) => writer.send_text(text),
+            Some(WsEvent::Pong(payload)) => writer.socket.send(&Message::Pong(payload)),
+            Some(WsEvent::Close(code)) => {
+                let _ = writer.socket.send(&Message::Close(code.or(Some(1000))));
+                break;
+            }
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+    let _ = writer.socket.get_ref().shutdown(Shutdown::Both);
+}
+
+struct ClientWriter {
+    socket: WebSocket<TcpStream>,
+    mask: u16,
+    min_interval: Duration,
+    /// Changes merged while `min_interval` has not elapsed yet.
+    pending: [Option<Arc<str>>; PANELS.len()],
+    pending_seq_tick: Option<(u64, u32)>,
+    last_flush: Instant,
+    /// Updates up to this sequence number are already covered.
+    last_seq: u64,
+}
+
+impl ClientWriter {
+    fn send_text(&mut self, text: String) -> std::io::Result<()> {
+        self.socket.send_text(&text)
+    }
+
+    fn send_snapshot(&mut self, session: &Session, resync: bool) -> std::io::Result<()> {
+        let tracker: PanelTracker = session.latest_panels.lock().unwrap().clone();
+        let mask = self.mask;
+        let message = panels_message(
+            "snapshot",
+            tracker.seq(),
+            tracker.tick(),
+            Some(resync),
+            tracker
+                .panels()
+                .enumerate()
+                .filter(|(index, _)| mask & (1 << index) != 0)
+                .map(|(index, (_, json))| (index, &**json)),
+        );
+        self.pending = std::array::from_fn(|_| None);
+        self.pending_seq_tick = None;
+        self.last_seq = tracker.seq();
+        self.last_flush = Instant::now();
+        self.send_text(message)
+    }
+
+    fn push(&mut self, update: &WsUpdate) -> std::io::Result<()> {
+        if update.seq <= self.last_seq {
+            return Ok(());
+        }
+        self.last_seq = update.seq;
+        if self.mask == ALL_PANELS && self.min_interval.is_zero() {
+            if update.changed.is_empty() {
+                return Ok(());
+            }
+            self.last_flush = Instant::now();
+            return self.socket.send_text(&update.message);
+        }
+        for (index, json) in &update.changed {
+            if self.mask & (1 << index) != 0 {
+                self.pending[*index] = Some(Arc::clone(json));
+                self.pending_seq_tick = Some((update.seq, update.tick));
+            }
+        }
+        match self.flush_deadline() {
+            Some(deadline) if deadline > Instant::now() => Ok(()),
+            _ => self.flush(),
+        }
+    }
+
+    /// When merged changes are due, if any are waiting.
+    fn flush_deadline(&self) -> Option<Instant> {
+        self.pending_seq_tick?;
+        Some(self.last_flush + self.min_interval)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let Some((seq, tick)) = self.pending_seq_tick.take() else {
+            return Ok(());
+        };
+        if self.pending.iter().all(Option::is_none) {
+            return Ok(());
+        }
+        let message = panels_message(
+            "delta",
+            seq,
+            tick,
+            None,
+            self.pending
+                .iter()
+                .enumerate()
+                .filter_map(|(index, json)| Some((index, &**json.as_ref()?))),
+        );
+        self.pending = std::array::from_fn(|_| None);
+        self.last_flush = Instant::now();
+        self.send_text(message)
+    }
+}
+
+/// Reads client frames, runs commands and forwards everything else to the
+/// writer so only one thread ever writes to the socket.
+fn read_client(stream: TcpStream, tx: SyncSender<WsEvent>, session: Arc<Session>) {
+    let mut socket = WebSocket::server(stream).with_max_message_len(MAX_CLIENT_MESSAGE_LEN);
+    loop {
+        let event = match socket.read_message() {
+            Ok(Message::Text(text)) => client_event(&text, &session),
+            Ok(Message::Binary(_)) => {
+                WsEvent::Reply(error_message(None, "expected a JSON text message"))
+            }
+            Ok(Message::Ping(payload)) => WsEvent::Pong(payload),
+            Ok(Message::Pong(_)) => continue,
+            Ok(Message::Close(code)) => WsEvent::Close(code),
+            // 1002: protocol error; the write fails too if the peer is gone.
+            Err(_) => WsEvent::Close(Some(1002)),
+        };
+        let closing = matches!(event, WsEvent::Close(_));
+        if tx.send(event).is_err() || closing {
+            return;
+        }
+    }
+}
+
+fn client_event(text: &str, session: &Session) -> WsEvent {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(e) => return WsEvent::Reply(error_message(None, &format!("invalid message: {e}"))),
+    };
+    match message {
+        ClientMessage::Subscribe {
+            panels,
+            min_interval_ms,
+        } => {
+            let mask = match panels {
+                Some(panels) => match panel_mask(&panels) {
+                    Ok(mask) => mask,
+                    Err(message) => return WsEvent::Reply(error_message(None, &message)),
+                },
+                None => ALL_PANELS,
+            };
+            WsEvent::Subscribe {
+                mask,
+                min_interval: Duration::from_millis(min_interval_ms.unwrap_or(0)),
+            }
+        }
+        ClientMessage::Unsubscribe { panels } => match panel_mask(&panels) {
+            Ok(mask) => WsEvent::Unsubscribe(mask),
+            Err(message) => WsEvent::Reply(error_message(None, &message)),
+        },
+        ClientMessage::Command { id, command } => {
+            let mut control = session.control.lock().unwrap();
+            WsEvent::Reply(match control.execute(&command) {
+                Ok(()) => format!(
+                    r#"{{"type":"ack","id":{},"control":{}}}"#,
+                    json_id(id),
+                    control.to_json()
+                ),
+                Err(e) => error_message(id, &e.to_string()),
+            })
+        }
+        ClientMessage::Resync => WsEvent::Resync,
+    }
+}
+
+fn panel_index(name: &str) -> Option<usize> {
+    PANELS.iter().position(|panel| *panel == name)
+}
+
+fn panel_mask(names: &[String]) -> Result<u16, String> {
+    names.iter().try_fold(0, |mask, name| {
+        panel_index(name)
+            .map(|index| mask | (1 << index))
+            .ok_or_else(|| format!("unknown panel `{name}`"))
+    })
+}
+
+/// `{"type":..,"seq":..,"tick":..,["resync":..,]"panels":{name:json,..}}`,
+/// concatenated from the pre-serialized panel fragments.
+fn panels_message<'a>(
+    kind: &str,
+    seq: u64,
+    tick: u32,
+    resync: Option<bool>,
+    panels: impl Iterator<Item = (usize, &'a str)>,
+) -> String {
+    let mut message = format!(r#"{{"type":"{kind}","seq":{seq},"tick":{tick}"#);
+    if let Some(resync) = resync {
+        message.push_str(&format!(r#","resync":{resync}"#));
+    }
+    message.push_str(r#","panels":{"#);
+    for (n, (index, json)) in panels.enumerate() {
+        if n > 0 {
+            message.push(',');
+        }
+        message.push('"');
+        message.push_str(PANELS[index]);
+        message.push_str("\":");
+        message.push_str(json);
+    }
+    message.push_str("}}");
+    message
+}
+
+fn subscribed_message(mask: u16, min_interval: Duration) -> String {
+    let panels: Vec<&str> = PANELS
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| mask & (1 << index) != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    format!(
+        r#"{{"type":"subscribed","panels":{},"min_interval_ms":{}}}"#,
+        serde_json::to_string(&panels).unwrap_or_default(),
+        min_interval.as_millis()
+    )
+}
+
+fn error_message(id: Option<u64>, message: &str) -> String {
+    format!(
+        r#"{{"type":"error","id":{},"message":{}}}"#,
+        json_id(id),
+        serde_json::to_string(message).unwrap_or_default()
+    )
+}
+
+fn json_id(id: Option<u64>) -> String {
+    id.map_or_else(|| "null".to_string(), |id| id.to_string())
+}
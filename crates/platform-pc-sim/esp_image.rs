@@ -0,0 +1,461 @@
+//! ESP-IDF アプリイメージ (`espflash save-image` の出力) の解析と検査。
+//!
+//! OTA で送る前に、ブートローダが起動時に行うのと同じ検査を host 側で済ませる。
+//!
+//! - 先頭ヘッダのマジック (`0xE9`)・chip ID・セグメント数
+//! - セグメント長のワード境界、セグメントデータの XOR チェックサム (初期値 `0xEF`)
+//! - `hash_appended` のときは末尾 32 バイトの SHA-256
+//! - 先頭セグメントの `esp_app_desc_t` (マジック `0xABCD5432`)。esp-hal の `no_std` アプリ
+//!   には無いことがあるので、無くてもエラーにはしない
+//!
+//! あわせて、書き込み後の照合に使う ROM 互換の MD5 / CRC-32 ([`crate::esp_rom`]) も計算する。
+//!
+//! # Examples
+//!
+//! ```
+//! use platform_pc_sim::esp_image::{build_image, AppDescriptor, EspChip, EspImage};
+//!
+//! let desc = AppDescriptor::new("climate", "1.2.0");
+//! let bytes = build_image(EspChip::Esp32, 0x4008_0000, &desc, &[(0x3FFB_0000, &[0u8; 64])]);
+//! let image = EspImage::parse(&bytes).unwrap();
+//! assert_eq!(image.chip, EspChip::Esp32);
+//! assert_eq!(image.app.unwrap().version, "1.2.0");
+//! ```
+
+use std::fmt;
+
+use ota_core::manifest::sha256;
+
+use crate::esp_rom::{crc32_le, md5};
+
+pub const IMAGE_MAGIC: u8 = 0xE9;
+pub const APP_DESC_MAGIC: u32 = 0xABCD_5432;
+pub const HEADER_LEN: usize = 24;
+pub const SEGMENT_HEADER_LEN: usize = 8;
+pub const APP_DESC_LEN: usize = 256;
+/// ブートローダが受け付けるセグメント数の上限 (`ESP_IMAGE_MAX_SEGMENTS`)。
+pub const MAX_SEGMENTS: usize = 16;
+const CHECKSUM_SEED: u8 = 0xEF;
+
+/// イメージヘッダの chip ID。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EspChip {
+    Esp32,
+    Esp32S2,
+    Esp32C3,
+    Esp32S3,
+    Esp32C2,
+    Esp32C6,
+    Esp32H2,
+    Unknown(u16),
+}
+
+impl EspChip {
+    pub fn from_id(id: u16) -> Self {
+        match id {
+            0x0000 => Self::Esp32,
+            0x0002 => Self::Esp32S2,
+            0x0005 => Self::Esp32C3,
+            0x0009 => Self::Esp32S3,
+            0x000C => Self::Esp32C2,
+            0x000D => Self::Esp32C6,
+            0x0010 => Self::Esp32H2,
+            other => Self::Unknown(other),
+        }
+    }
+
+    pub fn id(self) -> u16 {
+        match self {
+            Self::Esp32 => 0x0000,
+            Self::Esp32S2 => 0x0002,
+            Self::Esp32C3 => 0x0005,
+            Self::Esp32S3 => 0x0009,
+            Self::Esp32C2 => 0x000C,
+            Self::Esp32C6 => 0x000D,
+            Self::Esp32H2 => 0x0010,
+            Self::Unknown(id) => id,
+        }
+    }
+
+    /// `espflash --chip` と同じ名前。
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "esp32" => Self::Esp32,
+            "esp32s2" => Self::Esp32S2,
+            "esp32c3" => Self::Esp32C3,
+            "esp32s3" => Self::Esp32S3,
+            "esp32c2" => Self::Esp32C2,
+            "esp32c6" => Self::Esp32C6,
+            "esp32h2" => Self::Esp32H2,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Esp32 => "esp32",
+            Self::Esp32S2 => "esp32s2",
+            Self::Esp32C3 => "esp32c3",
+            Self::Esp32S3 => "esp32s3",
+            Self::Esp32C2 => "esp32c2",
+            Self::Esp32C6 => "esp32c6",
+            Self::Esp32H2 => "esp32h2",
+            Self::Unknown(_) => "unknown",
+        }
+    }
+}
+
+/// `esp_app_desc_t` のうち表示・検査に使うフィールド。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppDescriptor {
+    pub secure_version: u32,
+    pub version: String,
+    pub project_name: String,
+    pub time: String,
+    pub date: String,
+    pub idf_version: String,
+    pub elf_sha256: [u8; 32],
+}
+
+impl AppDescriptor {
+    pub fn new(project_name: &str, version: &str) -> Self {
+        Self {
+            secure_version: 0,
+            version: version.to_string(),
+            project_name: project_name.to_string(),
+            time: String::new(),
+            date: String::new(),
+            idf_version: String::new(),
+            elf_sha256: [0; 32],
+        }
+    }
+
+    /// 256 バイトの `esp_app_desc_t`。文字列は各フィールド長で切り詰める。
+    pub fn to_bytes(&self) -> [u8; APP_DESC_LEN] {
+        let mut bytes = [0u8; APP_DESC_LEN];
+        bytes[0..4].copy_from_slice(&APP_DESC_MAGIC.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.secure_version.to_le_bytes());
+        put_str(&mut bytes[16..48], &self.version);
+        put_str(&mut bytes[48..80], &self.project_name);
+        put_str(&mut bytes[80..96], &self.time);
+        put_str(&mut bytes[96..112], &self.date);
+        put_str(&mut bytes[112..144], &self.idf_version);
+        bytes[144..176].copy_from_slice(&self.elf_sha256);
+        bytes
+    }
+
+    /// マジックが一致しなければ `None`。
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < APP_DESC_LEN || read_u32(bytes, 0) != APP_DESC_MAGIC {
+            return None;
+        }
+        let mut elf_sha256 = [0u8; 32];
+        elf_sha256.copy_from_slice(&bytes[144..176]);
+        Some(Self {
+            secure_version: read_u32(bytes, 4),
+            version: get_str(&bytes[16..48]),
+            project_name: get_str(&bytes[48..80]),
+            time: get_str(&bytes[80..96]),
+            date: get_str(&bytes[96..112]),
+            idf_version: get_str(&bytes[112..144]),
+            elf_sha256,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub load_addr: u32,
+    /// ファイル内でのデータの開始位置
+    pub offset: usize,
+    pub len: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EspImageError {
+    /// ヘッダ・セグメント・チェックサム・ハッシュのいずれかの途中でファイルが終わる
+    Truncated,
+    BadMagic(u8),
+    TooManySegments(u8),
+    /// セグメント長が 4 の倍数でない
+    UnalignedSegment {
+        index: usize,
+        len: u32,
+    },
+    ChecksumMismatch {
+        stored: u8,
+        computed: u8,
+    },
+    HashMismatch,
+}
+
+impl fmt::Display for EspImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "image is truncated"),
+            Self::BadMagic(magic) => {
+                write!(
+                    f,
+                    "not an ESP app image (magic 0x{magic:02x}, expected 0xe9)"
+                )
+            }
+            Self::TooManySegments(count) => {
+                write!(
+                    f,
+                    "{count} segments exceed the bootloader limit of {MAX_SEGMENTS}"
+                )
+            }
+            Self::UnalignedSegment { index, len } => {
+                write!(f, "segment {index} length {len} is not word aligned")
+            }
+            Self::ChecksumMismatch { stored, computed } => write!(
+                f,
+                "segment checksum mismatch (stored 0x{stored:02x}, computed 0x{computed:02x})"
+            ),
+            Self::HashMismatch => write!(f, "appended SHA-256 does not match the image"),
+        }
+    }
+}
+
+impl std::error::Error for EspImageError {}
+
+/// 検査済みのアプリイメージ。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EspImage {
+    pub chip: EspChip,
+    pub entry: u32,
+    pub segments: Vec<Segment>,
+    pub hash_appended: bool,
+    pub app: Option<AppDescriptor>,
+    /// ヘッダからチェックサム (と SHA-256) までのバイト数。これより後ろはパディング
+    pub len: usize,
+    /// ファイル全体の MD5 (ROM の `SPI_FLASH_MD5` で読み戻した値と比べる)
+    pub md5: md5::Digest,
+    /// ファイル全体の CRC-32 (`crc32_le(0, ..)`)
+    pub crc32: u32,
+}
+
+impl EspImage {
+    pub fn parse(bytes: &[u8]) -> Result<Self, EspImageError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(EspImageError::Truncated);
+        }
+        if bytes[0] != IMAGE_MAGIC {
+            return Err(EspImageError::BadMagic(bytes[0]));
+        }
+        let segment_count = bytes[1];
+        if usize::from(segment_count) > MAX_SEGMENTS {
+            return Err(EspImageError::TooManySegments(segment_count));
+        }
+        let entry = read_u32(bytes, 4);
+        let chip = EspChip::from_id(u16::from_le_bytes([bytes[12], bytes[13]]));
+        let hash_appended = bytes[23] == 1;
+
+        let mut segments = Vec::with_capacity(usize::from(segment_count));
+        let mut checksum = CHECKSUM_SEED;
+        let mut offset = HEADER_LEN;
+        for index in 0..usize::from(segment_count) {
+            if bytes.len() < offset + SEGMENT_HEADER_LEN {
+                return Err(EspImageError::Truncated);
+            }
+            let load_addr = read_u32(bytes, offset);
+            let len = read_u32(bytes, offset + 4);
+            if len % 4 != 0 {
+                return Err(EspImageError::UnalignedSegment { index, len });
+            }
+            let data_start = offset + SEGMENT_HEADER_LEN;
+            let data = bytes
+                .get(data_start..data_start + len as usize)
+                .ok_or(EspImageError::Truncated)?;
+            checksum = data.iter().fold(checksum, |acc, byte| acc ^ byte);
+            segments.push(Segment {
+                load_addr,
+                offset: data_start,
+                len,
+            });
+            offset = data_start + len as usize;
+        }
+
+        // チェックサムは 16 バイト境界の最後の 1 バイトに置かれる
+        let checksum_at = offset | 0xF;
+        let stored = *bytes.get(checksum_at).ok_or(EspImageError::Truncated)?;
+        if stored != checksum {
+            return Err(EspImageError::ChecksumMismatch {
+                stored,
+                computed: checksum,
+            });
+        }
+        let mut len = checksum_at + 1;
+        if hash_appended {
+            let digest = bytes.get(len..len + 32).ok_or(EspImageError::Truncated)?;
+            if digest != sha256(&bytes[..len]) {
+                return Err(EspImageError::HashMismatch);
+            }
+            len += 32;
+        }
+
+        let app = segments
+            .first()
+            .and_then(|segment| AppDescriptor::from_bytes(&bytes[segment.offset..]));
+        Ok(Self {
+            chip,
+            entry,
+            segments,
+            hash_appended,
+            app,
+            len,
+            md5: md5::compute(bytes),
+            crc32: crc32_le(0, bytes),
+        })
+    }
+}
+
+/// 検査を通るイメージを組み立てる (テストと PC シミュレーション用)。
+///
+/// `desc` は先頭セグメント (`DROM`) の先頭に置き、SHA-256 を末尾に付ける。
+/// 各セグメントの長さは 4 の倍数にパディングする。
+pub fn build_image(
+    chip: EspChip,
+    entry: u32,
+    desc: &AppDescriptor,
+    segments: &[(u32, &[u8])],
+) -> Vec<u8> {
+    let mut first = desc.to_bytes().to_vec();
+    let mut all: Vec<(u32, Vec<u8>)> = Vec::with_capacity(segments.len() + 1);
+    let mut rest = segments.iter();
+    let drom_addr = match rest.next() {
+        Some((addr, data)) => {
+            first.extend_from_slice(data);
+            *addr
+        }
+        None => 0x3F40_0020,
+    };
+    all.push((drom_addr, first));
+    all.extend(rest.map(|(addr, data)| (*addr, data.to_vec())));
+
+    let mut bytes = vec![0u8; HEADER_LEN];
+    bytes[0] = IMAGE_MAGIC;
+    bytes[1] = all.len() as u8;
+    bytes[2] = 0x02; // DIO
+    bytes[3] = 0x20; // 4 MB, 40 MHz
+    bytes[4..8].copy_from_slice(&entry.to_le_bytes());
+    bytes[8] = 0xEE; // wp_pin 無効
+    bytes[12..14].copy_from_slice(&chip.id().to_le_bytes());
+    bytes[17..19].copy_from_slice(&0xFFFFu16.to_le_bytes()); // max_chip_rev_full
+    bytes[23] = 1;
+
+    let mut checksum = CHECKSUM_SEED;
+    for (addr, mut data) in all {
+        data.resize((data.len() + 3) & !3, 0);
+        checksum = data.iter().fold(checksum, |acc, byte| acc ^ byte);
+        bytes.extend_from_slice(&addr.to_le_bytes());
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&data);
+    }
+    bytes.resize(bytes.len() | 0xF, 0);
+    bytes.push(checksum);
+    let digest = sha256(&bytes);
+    bytes.extend_from_slice(&digest);
+    bytes
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn put_str(field: &mut [u8], value: &str) {
+    // 末尾の NUL を残す
+    let len = value.len().min(field.len() - 1);
+    field[..len].copy_from_slice(&value.as_bytes()[..len]);
+}
+
+fn get_str(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        let mut desc = AppDescriptor::new("wifi-climate", "0.3.1");
+        desc.idf_version = "v5.3".to_string();
+        build_image(
+            EspChip::Esp32,
+            0x4008_1234,
+            &desc,
+            &[(0x3F40_0020, &[1, 2, 3]), (0x4008_0000, &[0xAA; 40])],
+        )
+    }
+
+    #[test]
+    fn built_image_passes_the_bootloader_checks() {
+        let bytes = sample();
+        let image = EspImage::parse(&bytes).unwrap();
+        assert_eq!(image.chip, EspChip::Esp32);
+        assert_eq!(image.entry, 0x4008_1234);
+        assert_eq!(image.segments.len(), 2);
+        assert_eq!(image.segments[0].len, 260);
+        assert!(image.hash_appended);
+        assert_eq!(image.len, bytes.len());
+        assert_eq!(image.len % 16, 0);
+        let app = image.app.unwrap();
+        assert_eq!(app.project_name, "wifi-climate");
+        assert_eq!(app.version, "0.3.1");
+        assert_eq!(app.idf_version, "v5.3");
+        assert_eq!(image.md5, md5::compute(&bytes));
+
+        // フラッシュに書くときの 0xFF パディングは無視する
+        let mut padded = bytes.clone();
+        padded.resize(bytes.len() + 100, 0xFF);
+        assert_eq!(EspImage::parse(&padded).unwrap().len, bytes.len());
+    }
+
+    #[test]
+    fn corrupted_images_are_rejected() {
+        let bytes = sample();
+
+        let mut flipped = bytes.clone();
+        flipped[HEADER_LEN + SEGMENT_HEADER_LEN + APP_DESC_LEN] ^= 0x01;
+        assert!(matches!(
+            EspImage::parse(&flipped),
+            Err(EspImageError::ChecksumMismatch { .. })
+        ));
+
+        let mut header = bytes.clone();
+        header[2] = 0x03;
+        assert_eq!(EspImage::parse(&header), Err(EspImageError::HashMismatch));
+
+        let mut magic = bytes.clone();
+        magic[0] = 0x7F;
+        assert_eq!(EspImage::parse(&magic), Err(EspImageError::BadMagic(0x7F)));
+
+        assert_eq!(
+            EspImage::parse(&bytes[..bytes.len() - 1]),
+            Err(EspImageError::Truncated)
+        );
+
+        let mut unaligned = bytes;
+        unaligned[HEADER_LEN + 4] = 0x03;
+        assert!(matches!(
+            EspImage::parse(&unaligned),
+            Err(EspImageError::UnalignedSegment { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn missing_app_descriptor_is_not_an_error() {
+        let mut bytes = sample();
+        // magic を消すとチェックサムも変わるので、同じ XOR を別のバイトで打ち消す
+        let desc_at = HEADER_LEN + SEGMENT_HEADER_LEN;
+        bytes[desc_at] ^= 0xFF;
+        bytes[desc_at + 8] ^= 0xFF;
+        let hash_at = bytes.len() - 32;
+        let digest = sha256(&bytes[..hash_at]);
+        bytes[hash_at..].copy_from_slice(&digest);
+
+        let image = EspImage::parse(&bytes).unwrap();
+        assert_eq!(image.app, None);
+    }
+}
@@ -0,0 +1,233 @@
+//! ESP32 ROM のチェックサム関数の host 版。
+//!
+//! `vendor/esp-rom-sys` の `rom::crc` / `rom::md5` と同じ呼び出し規約・同じ結果になるように
+//! 移植したもの。vendored crate は chip feature と ROM のリンカスクリプトを前提にしていて
+//! host ではビルドできないため、ソフトウェア実装 (ROM に関数が無い chip 向けの
+//! フォールバックと同じ表引き CRC、RFC 1321 の MD5) を持つ。
+//!
+//! - [`crc32_le`] は ESP-IDF が otadata の `ota_seq` に付ける CRC (`esp_rom_crc32_le(UINT32_MAX, ..)`)
+//! - [`md5`] は ROM の `SPI_FLASH_MD5` / `espflash` が書き込み後の照合に使うダイジェスト
+
+/// 右シフト (反転入出力) の CRC-32、多項式 0x04c11db7。
+///
+/// `esp_rom_sys::rom::crc::crc32_le` と同じく、渡す初期値と返す値は 1 の補数になっている。
+/// 一般的な CRC-32 (ISO-HDLC) は `crc32_le(0, data)`。
+pub fn crc32_le(crc: u32, buf: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in buf {
+        crc = CRC32_LE_TABLE[(crc as usize ^ byte as usize) & 0xff] ^ (crc >> 8);
+    }
+    !crc
+}
+
+static CRC32_LE_TABLE: [u32; 256] = crc32_le_table();
+
+const fn crc32_le_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+}
+
+/// MD5 (`esp_rom_sys::rom::md5` と同じ API)。
+///
+/// 改ざん検出には使えない。フラッシュへの書き込みが化けていないかの照合用。
+pub mod md5 {
+    use std::fmt;
+
+    #[derive(Clone)]
+    pub struct Context {
+        state: [u32; 4],
+        buffer: [u8; 64],
+        buffered: usize,
+        length: u64,
+    }
+
+    impl Context {
+        pub fn new() -> Self {
+            Self {
+                state: [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476],
+                buffer: [0; 64],
+                buffered: 0,
+                length: 0,
+            }
+        }
+
+        pub fn consume<T: AsRef<[u8]>>(&mut self, data: T) {
+            let mut data = data.as_ref();
+            self.length = self.length.wrapping_add(data.len() as u64);
+            if self.buffered > 0 {
+                let take = (64 - self.buffered).min(data.len());
+                self.buffer[self.buffered..self.buffered + take].copy_from_slice(&data[..take]);
+                self.buffered += take;
+                data = &data[take..];
+                if self.buffered < 64 {
+                    return;
+                }
+                let block = self.buffer;
+                self.process(&block);
+                self.buffered = 0;
+            }
+            let mut blocks = data.chunks_exact(64);
+            for block in &mut blocks {
+                self.process(block);
+            }
+            let rest = blocks.remainder();
+            self.buffer[..rest.len()].copy_from_slice(rest);
+            self.buffered = rest.len();
+        }
+
+        pub fn compute(mut self) -> Digest {
+            let bit_length = self.length.wrapping_mul(8);
+            let padding_len = if self.buffered < 56 {
+                56 - self.buffered
+            } else {
+                120 - self.buffered
+            };
+            let mut padding = [0u8; 64];
+            padding[0] = 0x80;
+            self.consume(&padding[..padding_len]);
+            self.consume(bit_length.to_le_bytes());
+            let mut digest = [0u8; 16];
+            for (out, word) in digest.chunks_exact_mut(4).zip(self.state) {
+                out.copy_from_slice(&word.to_le_bytes());
+            }
+            Digest(digest)
+        }
+
+        fn process(&mut self, block: &[u8]) {
+            let mut words = [0u32; 16];
+            for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+                *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            }
+            let [mut a, mut b, mut c, mut d] = self.state;
+            for round in 0..64 {
+                let (f, g) = match round / 16 {
+                    0 => ((b & c) | (!b & d), round),
+                    1 => ((d & b) | (!d & c), (5 * round + 1) % 16),
+                    2 => (b ^ c ^ d, (3 * round + 5) % 16),
+                    _ => (c ^ (b | !d), (7 * round) % 16),
+                };
+                let rotated = a
+                    .wrapping_add(f)
+                    .wrapping_add(SINES[round])
+                    .wrapping_add(words[g])
+                    .rotate_left(SHIFTS[round]);
+                a = d;
+                d = c;
+                c = b;
+                b = b.wrapping_add(rotated);
+            }
+            for (state, value) in self.state.iter_mut().zip([a, b, c, d]) {
+                *state = state.wrapping_add(value);
+            }
+        }
+    }
+
+    impl Default for Context {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    pub fn compute<T: AsRef<[u8]>>(data: T) -> Digest {
+        let mut context = Context::new();
+        context.consume(data);
+        context.compute()
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Digest(pub [u8; 16]);
+
+    impl fmt::LowerHex for Digest {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for byte in self.0 {
+                write!(f, "{byte:02x}")?;
+            }
+            Ok(())
+        }
+    }
+
+    impl fmt::Debug for Digest {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::LowerHex::fmt(self, f)
+        }
+    }
+
+    impl fmt::Display for Digest {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::LowerHex::fmt(self, f)
+        }
+    }
+
+    const SHIFTS: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5,
+        9, 14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10,
+        15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+
+    /// `floor(abs(sin(i + 1)) * 2^32)`
+    const SINES: [u32; 64] = [
+        0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613,
+        0xfd469501, 0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193,
+        0xa679438e, 0x49b40821, 0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d,
+        0x02441453, 0xd8a1e681, 0xe7d3fbc8, 0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed,
+        0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a, 0xfffa3942, 0x8771f681, 0x6d9d6122,
+        0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70, 0x289b7ec6, 0xeaa127fa,
+        0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665, 0xf4292244,
+        0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+        0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb,
+        0xeb86d391,
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_le_follows_the_rom_complement_convention() {
+        // CRC-32/ISO-HDLC: init=0xffffffff xorout=0xffffffff
+        assert_eq!(crc32_le(!0xffff_ffff, b"123456789"), 0xCBF4_3926);
+        // 複数バッファにまたがる計算
+        let partial = crc32_le(0, b"12345");
+        assert_eq!(crc32_le(partial, b"6789"), 0xCBF4_3926);
+        // ESP-IDF の otadata: esp_rom_crc32_le(UINT32_MAX, &ota_seq, 4)。ota_seq=1 で 0x4743989a
+        assert_eq!(crc32_le(u32::MAX, &1u32.to_le_bytes()), 0x4743_989A);
+    }
+
+    #[test]
+    fn md5_matches_rfc_1321_vectors_across_buffer_boundaries() {
+        assert_eq!(
+            format!("{:x}", md5::compute(b"")),
+            "d41d8cd98f00b204e9800998ecf8427e"
+        );
+        assert_eq!(
+            format!("{:x}", md5::compute(b"abc")),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        let text =
+            b"12345678901234567890123456789012345678901234567890123456789012345678901234567890";
+        let mut context = md5::Context::new();
+        for chunk in text.chunks(7) {
+            context.consume(chunk);
+        }
+        assert_eq!(
+            format!("{:x}", context.compute()),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+}
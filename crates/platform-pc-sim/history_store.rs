@@ -0,0 +1,807 @@
+//! `device-dashboard-web` の全パネル履歴を保存するファイルストア。
+//!
+//! - [`HistoryRecord`] は 1 回分の記録。[`HISTORY_FIELDS`] の数値と RTC の表示文字列を持つ
+//! - [`HistoryStore`] はディレクトリ内の時間区切りの CSV セグメント
+//!   (`history-<開始 UNIX ミリ秒>.csv`) へ追記し、[`RetentionPolicy`] の保持期間 /
+//!   合計サイズを超えた古いセグメントを丸ごと削除する。再起動後も読み戻せる
+//! - [`downsample`] は範囲を等間隔のバケットに分けて平均する
+//! - [`history_json`] / [`write_csv`] / [`write_parquet`] は `/api/history` と
+//!   `/api/history/export` の出力。パネル名で列を絞れる
+//!
+//! セグメントの CSV は [`write_csv`] の出力と同じ形式で、列はヘッダ名で対応付けて読む。
+//! 列を足しても古いセグメントはそのまま読める (無い列は空になる)。
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::parquet_writer::{self, Column, ColumnData};
+use crate::web_dashboard::DeviceDashboardState;
+
+/// 記録する数値列と、それが属するパネル ([`crate::web_dashboard::PANELS`] の名前)。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryField {
+    pub panel: &'static str,
+    pub column: &'static str,
+}
+
+const fn field(panel: &'static str, column: &'static str) -> HistoryField {
+    HistoryField { panel, column }
+}
+
+/// [`HistoryRecord::values`] の並び。
+pub const HISTORY_FIELDS: [HistoryField; 22] = [
+    field("climate", "temperature_c"),
+    field("climate", "humidity_percent"),
+    field("climate", "pressure_pa"),
+    field("distance", "distance_mm"),
+    field("imu", "accel_x_mg"),
+    field("imu", "accel_y_mg"),
+    field("imu", "accel_z_mg"),
+    field("imu", "gyro_x_mdps"),
+    field("imu", "gyro_y_mdps"),
+    field("imu", "gyro_z_mdps"),
+    field("imu", "imu_temperature_c"),
+    field("servo", "servo_angle_degrees"),
+    field("motor_driver", "motor_left_duty_percent"),
+    field("motor_driver", "motor_right_duty_percent"),
+    field("i2c", "i2c_operation_count"),
+    field("light", "lux"),
+    field("camera", "camera_sequence"),
+    field("gas", "co2_ppm"),
+    field("gas", "voc_ppb"),
+    field("tof", "tof_distance_mm"),
+    field("diagnostics", "diag_event_count"),
+    field("diagnostics", "active_alarms"),
+];
+
+/// RTC パネルの表示文字列の列名 (数値ではないので [`HISTORY_FIELDS`] とは別)。
+pub const RTC_COLUMN: &str = "rtc_datetime";
+
+const SEGMENT_PREFIX: &str = "history-";
+const SEGMENT_SUFFIX: &str = ".csv";
+
+/// 1 回分の記録。
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryRecord {
+    /// 記録した時刻 (UNIX ミリ秒)
+    pub timestamp_ms: u64,
+    pub tick: u32,
+    /// [`HISTORY_FIELDS`] と同じ並び。センサーが未接続なら `None`
+    pub values: [Option<f64>; HISTORY_FIELDS.len()],
+    pub rtc: Option<String>,
+}
+
+impl HistoryRecord {
+    /// ダッシュボードの状態から記録を作る。配線で外れているセンサーの列は `None`。
+    pub fn from_state(state: &DeviceDashboardState, timestamp_ms: u64) -> Self {
+        let selected = |slug: &str| state.wiring.selected_devices.iter().any(|s| s == slug);
+        let centi = |value: f32| (f64::from(value) * 100.0).round() / 100.0;
+        let imu = selected("mpu6050").then_some(&state.imu);
+        let motors = &state.motor_driver;
+        let values = [
+            state.climate.temperature_c.map(centi),
+            state.climate.humidity_percent.map(centi),
+            state.climate.pressure_pa.map(f64::from),
+            state.distance.distance_mm.map(f64::from),
+            imu.map(|imu| imu.accel_mg[0].into()),
+            imu.map(|imu| imu.accel_mg[1].into()),
+            imu.map(|imu| imu.accel_mg[2].into()),
+            imu.map(|imu| imu.gyro_mdps[0].into()),
+            imu.map(|imu| imu.gyro_mdps[1].into()),
+            imu.map(|imu| imu.gyro_mdps[2].into()),
+            imu.and_then(|imu| imu.temperature_c).map(centi),
+            Some(state.servo.angle_degrees.into()),
+            Some(motors.left.duty_percent.into()),
+            Some(motors.right.duty_percent.into()),
+            Some(state.i2c.operation_count as f64),
+            selected("bh1750").then(|| f64::from(state.light.lux_x100) / 100.0),
+            selected("esp32_cam").then(|| state.camera.sequence.into()),
+            state.gas.co2_ppm.map(f64::from),
+            state.gas.voc_ppb.map(f64::from),
+            state.tof.distance_mm.map(f64::from),
+            Some(state.diagnostics.event_count.into()),
+            Some(state.diagnostics.active_alarms.len() as f64),
+        ];
+        let rtc = &state.rtc.datetime_str;
+        Self {
+            timestamp_ms,
+            tick: state.tick,
+            values,
+            rtc: (!rtc.is_empty()).then(|| rtc.clone()),
+        }
+    }
+
+    /// 列名で値を引く。
+    pub fn value(&self, column: &str) -> Option<f64> {
+        HISTORY_FIELDS
+            .iter()
+            .position(|field| field.column == column)
+            .and_then(|index| self.values[index])
+    }
+}
+
+/// 古いセグメントを消す条件。どちらも `None` なら消さない。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// これより古い記録だけのセグメントを消す
+    pub max_age_ms: Option<u64>,
+    /// 合計サイズがこれを超えたら古い順に消す (書き込み中のセグメントは残す)
+    pub max_bytes: Option<u64>,
+}
+
+impl Default for RetentionPolicy {
+    /// 24 時間 / 256 MiB。
+    fn default() -> Self {
+        Self {
+            max_age_ms: Some(24 * 3_600_000),
+            max_bytes: Some(256 * 1024 * 1024),
+        }
+    }
+}
+
+/// `90s` / `30m` / `24h` / `7d` 形式の期間をミリ秒にする。
+pub fn parse_duration_ms(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let unit_at = text.len().checked_sub(1)?;
+    let (number, unit) = text.split_at(unit_at);
+    let scale = match unit {
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(scale)
+}
+
+#[derive(Clone, Debug)]
+struct Segment {
+    start_ms: u64,
+    path: PathBuf,
+    bytes: u64,
+    /// 今の列構成と違うヘッダで書かれている (追記すると行が読めなくなる)。
+    stale_header: bool,
+}
+
+/// 時間区切りの CSV セグメントに履歴を保存するストア。
+///
+/// `Clone` はセグメント一覧の複製だけで、読み出しをロックの外で行うために使える。
+#[derive(Clone, Debug)]
+pub struct HistoryStore {
+    dir: PathBuf,
+    retention: RetentionPolicy,
+    segment_ms: u64,
+    /// 開始時刻の昇順
+    segments: Vec<Segment>,
+}
+
+impl HistoryStore {
+    /// セグメント 1 つの既定の長さ (1 時間)。
+    pub const DEFAULT_SEGMENT_MS: u64 = 3_600_000;
+
+    /// `dir` を (無ければ作って) 開き、既存のセグメントを拾う。
+    pub fn open(dir: impl AsRef<Path>, retention: RetentionPolicy) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut segments = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(start_ms) = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(SEGMENT_PREFIX))
+                .and_then(|name| name.strip_suffix(SEGMENT_SUFFIX))
+                .and_then(|start| start.parse().ok())
+            else {
+                continue;
+            };
+            segments.push(Segment {
+                start_ms,
+                path: entry.path(),
+                bytes: entry.metadata()?.len(),
+                stale_header: false,
+            });
+        }
+        segments.sort_unstable_by_key(|segment| segment.start_ms);
+        // 追記先になり得るのは最後のセグメントだけ
+        if let Some(last) = segments.last_mut() {
+            last.stale_header = last.bytes > 0 && read_header(&last.path)? != csv_header(None);
+        }
+        Ok(Self {
+            dir,
+            retention,
+            segment_ms: Self::DEFAULT_SEGMENT_MS,
+            segments,
+        })
+    }
+
+    /// セグメントの長さを変える (テストや長期保存向け)。
+    pub fn with_segment_ms(mut self, segment_ms: u64) -> Self {
+        self.segment_ms = segment_ms.max(1);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn retention(&self) -> RetentionPolicy {
+        self.retention
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// 保存済みの合計バイト数。
+    pub fn total_bytes(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.bytes).sum()
+    }
+
+    /// 記録を追記し、保持ポリシーを適用する。
+    pub fn append(&mut self, record: &HistoryRecord) -> io::Result<()> {
+        let start_ms = record.timestamp_ms - record.timestamp_ms % self.segment_ms;
+        let last = self.segments.last();
+        let current = last.filter(|segment| segment.start_ms >= start_ms && !segment.stale_header);
+        // 古い列構成のセグメントには足さず、その直後から新しいセグメントを始める
+        let start_ms = match last {
+            Some(segment) if segment.stale_header => record.timestamp_ms.max(segment.start_ms + 1),
+            _ => start_ms,
+        };
+        let index = match current {
+            Some(_) => self.segments.len() - 1,
+            None => {
+                let path = self
+                    .dir
+                    .join(format!("{SEGMENT_PREFIX}{start_ms}{SEGMENT_SUFFIX}"));
+                self.segments.push(Segment {
+                    start_ms,
+                    path,
+                    bytes: 0,
+                    stale_header: false,
+                });
+                self.segments.len() - 1
+            }
+        };
+
+        let segment = &mut self.segments[index];
+        let mut text = String::new();
+        if segment.bytes == 0 {
+            text.push_str(&csv_header(None));
+            text.push('\n');
+        }
+        text.push_str(&csv_row(record, None));
+        text.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&segment.path)?;
+        file.write_all(text.as_bytes())?;
+        segment.bytes += text.len() as u64;
+
+        self.prune(record.timestamp_ms)?;
+        Ok(())
+    }
+
+    /// 保持ポリシーを超えたセグメントを消し、消した数を返す。
+    pub fn prune(&mut self, now_ms: u64) -> io::Result<usize> {
+        let mut expired = 0;
+        if let Some(max_age_ms) = self.retention.max_age_ms {
+            let cutoff = now_ms.saturating_sub(max_age_ms);
+            // 次のセグメントの開始が cutoff 以前なら、その前の記録は全部古い
+            expired = self
+                .segments
+                .windows(2)
+                .take_while(|pair| pair[1].start_ms <= cutoff)
+                .count();
+        }
+        if let Some(max_bytes) = self.retention.max_bytes {
+            let mut total: u64 = self.segments[expired..].iter().map(|s| s.bytes).sum();
+            while total > max_bytes && expired + 1 < self.segments.len() {
+                total -= self.segments[expired].bytes;
+                expired += 1;
+            }
+        }
+        for segment in self.segments.drain(..expired) {
+            match fs::remove_file(&segment.path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(expired)
+    }
+
+    /// `from_ms..=to_ms` の記録を時刻順に返す。
+    pub fn query(&self, from_ms: u64, to_ms: u64) -> io::Result<Vec<HistoryRecord>> {
+        let mut records = Vec::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            let end_ms = self
+                .segments
+                .get(i + 1)
+                .map_or(u64::MAX, |next| next.start_ms);
+            if segment.start_ms > to_ms || end_ms <= from_ms {
+                continue;
+            }
+            records.extend(
+                read_segment(&segment.path)?
+                    .into_iter()
+                    .filter(|record| (from_ms..=to_ms).contains(&record.timestamp_ms)),
+            );
+        }
+        Ok(records)
+    }
+
+    /// 新しい方から最大 `limit` 件を時刻順に返す。
+    pub fn latest(&self, limit: usize) -> io::Result<Vec<HistoryRecord>> {
+        let mut records = Vec::new();
+        for segment in self.segments.iter().rev() {
+            if records.len() >= limit {
+                break;
+            }
+            let mut older = read_segment(&segment.path)?;
+            older.append(&mut records);
+            records = older;
+        }
+        let skip = records.len().saturating_sub(limit);
+        records.drain(..skip);
+        Ok(records)
+    }
+}
+
+fn read_header(path: &Path) -> io::Result<String> {
+    let mut header = String::new();
+    BufReader::new(fs::File::open(path)?).read_line(&mut header)?;
+    Ok(header.trim_end().to_string())
+}
+
+fn read_segment(path: &Path) -> io::Result<Vec<HistoryRecord>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut lines = BufReader::new(file).lines();
+    let Some(header) = lines.next().transpose()? else {
+        return Ok(Vec::new());
+    };
+    let layout = CsvLayout::from_header(&header);
+    let mut records = Vec::new();
+    for line in lines {
+        // 電源断などで途中までしか書かれなかった行は読み飛ばす
+        if let Some(record) = layout.parse_row(&line?) {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+/// セグメントのヘッダから求めた、CSV の列と記録のフィールドの対応。
+struct CsvLayout {
+    columns: Vec<CsvColumn>,
+}
+
+enum CsvColumn {
+    Timestamp,
+    Tick,
+    Field(usize),
+    Rtc,
+    Unknown,
+}
+
+impl CsvLayout {
+    fn from_header(header: &str) -> Self {
+        let columns = header
+            .split(',')
+            .map(|name| match name.trim() {
+                "timestamp_ms" => CsvColumn::Timestamp,
+                "tick" => CsvColumn::Tick,
+                RTC_COLUMN => CsvColumn::Rtc,
+                name => HISTORY_FIELDS
+                    .iter()
+                    .position(|field| field.column == name)
+                    .map_or(CsvColumn::Unknown, CsvColumn::Field),
+            })
+            .collect();
+        Self { columns }
+    }
+
+    fn parse_row(&self, line: &str) -> Option<HistoryRecord> {
+        let cells: Vec<&str> = line.split(',').collect();
+        if cells.len() != self.columns.len() {
+            return None;
+        }
+        let mut record = HistoryRecord {
+            timestamp_ms: 0,
+            tick: 0,
+            values: [None; HISTORY_FIELDS.len()],
+            rtc: None,
+        };
+        let mut has_timestamp = false;
+        for (column, cell) in self.columns.iter().zip(cells) {
+            match column {
+                CsvColumn::Timestamp => {
+                    record.timestamp_ms = cell.parse().ok()?;
+                    has_timestamp = true;
+                }
+                CsvColumn::Tick => record.tick = cell.parse().ok()?,
+                CsvColumn::Field(index) if !cell.is_empty() => {
+                    record.values[*index] = Some(cell.parse().ok()?);
+                }
+                CsvColumn::Rtc if !cell.is_empty() => record.rtc = Some(cell.to_string()),
+                _ => {}
+            }
+        }
+        has_timestamp.then_some(record)
+    }
+}
+
+/// `panel` で絞った列 ([`HISTORY_FIELDS`] の添字) と、RTC 列を含めるか。
+/// `panel` が未知なら `None`。
+fn selected_columns(panel: Option<&str>) -> Option<(Vec<usize>, bool)> {
+    let fields: Vec<usize> = (0..HISTORY_FIELDS.len())
+        .filter(|&i| panel.map_or(true, |panel| HISTORY_FIELDS[i].panel == panel))
+        .collect();
+    let rtc = panel.map_or(true, |panel| panel == "rtc");
+    (!fields.is_empty() || rtc).then_some((fields, rtc))
+}
+
+/// `panel` が記録されているパネル名 (か `None`) なら true。
+pub fn is_known_panel(panel: Option<&str>) -> bool {
+    selected_columns(panel).is_some()
+}
+
+fn csv_header(panel: Option<&str>) -> String {
+    let (fields, rtc) = selected_columns(panel).unwrap_or_default();
+    let mut header = String::from("timestamp_ms,tick");
+    for index in fields {
+        header.push(',');
+        header.push_str(HISTORY_FIELDS[index].column);
+    }
+    if rtc {
+        header.push(',');
+        header.push_str(RTC_COLUMN);
+    }
+    header
+}
+
+fn csv_row(record: &HistoryRecord, panel: Option<&str>) -> String {
+    let (fields, rtc) = selected_columns(panel).unwrap_or_default();
+    let mut row = format!("{},{}", record.timestamp_ms, record.tick);
+    for index in fields {
+        row.push(',');
+        if let Some(value) = record.values[index] {
+            row.push_str(&value.to_string());
+        }
+    }
+    if rtc {
+        row.push(',');
+        if let Some(text) = &record.rtc {
+            // 区切り文字と改行は CSV を壊すので落とす
+            row.extend(text.chars().filter(|c| !matches!(c, ',' | '\n' | '\r')));
+        }
+    }
+    row
+}
+
+/// 記録の時刻範囲を `points` 個の等間隔バケットに分け、バケットごとに平均する。
+///
+/// 件数が `points` 以下 (または `points == 0`) ならそのまま返す。時刻は各バケットの
+/// 平均、`tick` と RTC 表示はバケット内の最後の値。
+pub fn downsample(records: &[HistoryRecord], points: usize) -> Vec<HistoryRecord> {
+    if points == 0 || records.len() <= points {
+        return records.to_vec();
+    }
+    let first = records[0].timestamp_ms;
+    let span = records[records.len() - 1].timestamp_ms - first + 1;
+    let bucket_of = |record: &HistoryRecord| {
+        ((u128::from(record.timestamp_ms - first) * points as u128) / u128::from(span)) as usize
+    };
+
+    let mut out = Vec::with_capacity(points);
+    let mut start = 0;
+    while start < records.len() {
+        let bucket = bucket_of(&records[start]);
+        let len = records[start..]
+            .iter()
+            .take_while(|record| bucket_of(record) == bucket)
+            .count();
+        let group = &records[start..start + len];
+        let mean_ms = group
+            .iter()
+            .map(|record| u128::from(record.timestamp_ms))
+            .sum::<u128>()
+            / len as u128;
+        let mut values = [None; HISTORY_FIELDS.len()];
+        for (index, value) in values.iter_mut().enumerate() {
+            let (sum, count) = group
+                .iter()
+                .filter_map(|record| record.values[index])
+                .fold((0.0, 0usize), |(sum, count), v| (sum + v, count + 1));
+            *value = (count > 0).then(|| sum / count as f64);
+        }
+        let last = &group[len - 1];
+        out.push(HistoryRecord {
+            timestamp_ms: mean_ms as u64,
+            tick: last.tick,
+            values,
+            rtc: group.iter().rev().find_map(|record| record.rtc.clone()),
+        });
+        start += len;
+    }
+    out
+}
+
+/// `/api/history?panel=...` の JSON。列ごとの配列で、`panel` が未知なら `None`。
+///
+/// `{"panel":"climate","timestamp_ms":[..],"tick":[..],"temperature_c":[..],...}`
+pub fn history_json(records: &[HistoryRecord], panel: Option<&str>) -> Option<String> {
+    let (fields, rtc) = selected_columns(panel)?;
+    let mut json = String::from("{\"panel\":");
+    json.push_str(&to_json(&panel));
+    json.push_str(",\"timestamp_ms\":");
+    let timestamps: Vec<u64> = records.iter().map(|record| record.timestamp_ms).collect();
+    json.push_str(&to_json(&timestamps));
+    json.push_str(",\"tick\":");
+    let ticks: Vec<u32> = records.iter().map(|record| record.tick).collect();
+    json.push_str(&to_json(&ticks));
+    for index in fields {
+        json.push_str(",\"");
+        json.push_str(HISTORY_FIELDS[index].column);
+        json.push_str("\":");
+        let values: Vec<Option<f64>> = records.iter().map(|record| record.values[index]).collect();
+        json.push_str(&to_json(&values));
+    }
+    if rtc {
+        json.push_str(",\"");
+        json.push_str(RTC_COLUMN);
+        json.push_str("\":");
+        let values: Vec<Option<&str>> =
+            records.iter().map(|record| record.rtc.as_deref()).collect();
+        json.push_str(&to_json(&values));
+    }
+    json.push('}');
+    Some(json)
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "null".to_string())
+}
+
+/// 記録を CSV (ヘッダ付き、セグメントと同じ形式) で書き出す。`panel` が未知なら `InvalidInput`。
+pub fn write_csv(
+    out: &mut impl Write,
+    records: &[HistoryRecord],
+    panel: Option<&str>,
+) -> io::Result<()> {
+    if !is_known_panel(panel) {
+        return Err(unknown_panel());
+    }
+    let mut text = csv_header(panel);
+    text.push('\n');
+    for record in records {
+        text.push_str(&csv_row(record, panel));
+        text.push('\n');
+    }
+    out.write_all(text.as_bytes())
+}
+
+/// 記録を Parquet で書き出す。`panel` が未知なら `InvalidInput`。
+pub fn write_parquet(
+    out: &mut impl Write,
+    records: &[HistoryRecord],
+    panel: Option<&str>,
+) -> io::Result<()> {
+    let (fields, rtc) = selected_columns(panel).ok_or_else(unknown_panel)?;
+    let mut columns = vec![
+        Column::new(
+            "timestamp_ms",
+            ColumnData::TimestampMillis(
+                records
+                    .iter()
+                    .map(|record| record.timestamp_ms as i64)
+                    .collect(),
+            ),
+        ),
+        Column::new(
+            "tick",
+            ColumnData::Int64(records.iter().map(|record| record.tick.into()).collect()),
+        ),
+    ];
+    for index in fields {
+        columns.push(Column::new(
+            HISTORY_FIELDS[index].column,
+            ColumnData::OptionalDouble(records.iter().map(|record| record.values[index]).collect()),
+        ));
+    }
+    if rtc {
+        columns.push(Column::new(
+            RTC_COLUMN,
+            ColumnData::OptionalText(records.iter().map(|record| record.rtc.clone()).collect()),
+        ));
+    }
+    parquet_writer::write_parquet(out, &columns)
+}
+
+fn unknown_panel() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "unknown history panel")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp_ms: u64, temperature_c: f64) -> HistoryRecord {
+        let mut values = [None; HISTORY_FIELDS.len()];
+        values[0] = Some(temperature_c);
+        values[15] = Some(120.5);
+        HistoryRecord {
+            timestamp_ms,
+            tick: (timestamp_ms / 100) as u32,
+            values,
+            rtc: Some(format!("2025-01-01 00:00:{:02}", timestamp_ms / 1000 % 60)),
+        }
+    }
+
+    #[test]
+    fn parse_duration_accepts_suffixes() {
+        assert_eq!(parse_duration_ms("90s"), Some(90_000));
+        assert_eq!(parse_duration_ms("30m"), Some(1_800_000));
+        assert_eq!(parse_duration_ms(" 24h"), Some(86_400_000));
+        assert_eq!(parse_duration_ms("7d"), Some(604_800_000));
+        assert_eq!(parse_duration_ms("7"), None);
+        assert_eq!(parse_duration_ms("h"), None);
+        assert_eq!(parse_duration_ms(""), None);
+    }
+
+    #[test]
+    fn store_persists_segments_and_queries_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut store = HistoryStore::open(dir.path(), RetentionPolicy::default())
+                .unwrap()
+                .with_segment_ms(10_000);
+            for i in 0..25u64 {
+                store
+                    .append(&record(1_000_000 + i * 1000, 20.0 + i as f64))
+                    .unwrap();
+            }
+            assert_eq!(store.segment_count(), 3);
+        }
+        let store = HistoryStore::open(dir.path(), RetentionPolicy::default()).unwrap();
+        assert_eq!(store.segment_count(), 3);
+
+        let range = store.query(1_008_000, 1_012_000).unwrap();
+        let temps: Vec<_> = range.iter().map(|r| r.value("temperature_c")).collect();
+        assert_eq!(
+            temps,
+            [Some(28.0), Some(29.0), Some(30.0), Some(31.0), Some(32.0)]
+        );
+        assert_eq!(range[0], record(1_008_000, 28.0));
+
+        let latest = store.latest(3).unwrap();
+        assert_eq!(
+            latest.iter().map(|r| r.timestamp_ms).collect::<Vec<_>>(),
+            [1_022_000, 1_023_000, 1_024_000]
+        );
+        assert_eq!(store.query(0, 999_999).unwrap(), []);
+    }
+
+    #[test]
+    fn retention_drops_whole_old_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let retention = RetentionPolicy {
+            max_age_ms: Some(15_000),
+            max_bytes: None,
+        };
+        let mut store = HistoryStore::open(dir.path(), retention)
+            .unwrap()
+            .with_segment_ms(10_000);
+        for i in 0..40u64 {
+            store.append(&record(i * 1000, 20.0)).unwrap();
+        }
+        // 39 s 時点で 24 s より前しか含まないのは 0..10 s と 10..20 s
+        assert_eq!(store.segment_count(), 2);
+        assert_eq!(store.query(0, u64::MAX).unwrap()[0].timestamp_ms, 20_000);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        let bytes_per_segment = store.total_bytes() / 2;
+        store.retention = RetentionPolicy {
+            max_age_ms: None,
+            max_bytes: Some(bytes_per_segment),
+        };
+        assert_eq!(store.prune(40_000).unwrap(), 1);
+        // 書き込み中のセグメントは上限を超えていても消さない
+        store.retention.max_bytes = Some(1);
+        assert_eq!(store.prune(40_000).unwrap(), 0);
+        assert_eq!(store.segment_count(), 1);
+    }
+
+    #[test]
+    fn segments_written_with_fewer_columns_still_load() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("history-0.csv"),
+            "timestamp_ms,tick,temperature_c,retired_column\n1000,10,21.5,7\n2000,20,,7\n3000,30,22",
+        )
+        .unwrap();
+        let store = HistoryStore::open(dir.path(), RetentionPolicy::default()).unwrap();
+        let records = store.query(0, u64::MAX).unwrap();
+        // 途中で切れた最終行は読み飛ばす
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].value("temperature_c"), Some(21.5));
+        assert_eq!(records[1].value("temperature_c"), None);
+        assert_eq!(records[1].value("lux"), None);
+    }
+
+    #[test]
+    fn appends_after_reopening_an_old_column_set_start_a_new_segment() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("history-0.csv"),
+            "timestamp_ms,tick,temperature_c\n1000,10,21.5\n",
+        )
+        .unwrap();
+        let mut store = HistoryStore::open(dir.path(), RetentionPolicy::default()).unwrap();
+        store.append(&record(2000, 22.0)).unwrap();
+        assert_eq!(store.segment_count(), 2);
+        assert!(dir.path().join("history-2000.csv").exists());
+
+        let store = HistoryStore::open(dir.path(), RetentionPolicy::default()).unwrap();
+        assert_eq!(store.query(500, 1500).unwrap().len(), 1);
+        let records = store.query(0, u64::MAX).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].timestamp_ms, 2000);
+    }
+
+    #[test]
+    fn downsample_averages_time_buckets() {
+        let records: Vec<_> = (0..10u64).map(|i| record(i * 1000, i as f64)).collect();
+        let down = downsample(&records, 5);
+        assert_eq!(down.len(), 5);
+        assert_eq!(down[0].timestamp_ms, 500);
+        assert_eq!(down[0].value("temperature_c"), Some(0.5));
+        assert_eq!(down[4].value("temperature_c"), Some(8.5));
+        assert_eq!(down[4].tick, records[9].tick);
+        assert_eq!(down[4].rtc, records[9].rtc);
+        assert_eq!(downsample(&records, 20), records);
+    }
+
+    #[test]
+    fn exports_filter_columns_by_panel() {
+        let records = [record(1000, 21.5), record(2000, 22.0)];
+        let json = history_json(&records, Some("climate")).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["panel"], "climate");
+        assert_eq!(parsed["timestamp_ms"], serde_json::json!([1000, 2000]));
+        assert_eq!(parsed["temperature_c"], serde_json::json!([21.5, 22.0]));
+        assert_eq!(parsed["pressure_pa"], serde_json::json!([null, null]));
+        assert!(parsed.get("lux").is_none());
+        assert!(history_json(&records, Some("wiring")).is_none());
+        let all: serde_json::Value =
+            serde_json::from_str(&history_json(&records, None).unwrap()).unwrap();
+        assert_eq!(all["lux"], serde_json::json!([120.5, 120.5]));
+        assert_eq!(all["rtc_datetime"][1], "2025-01-01 00:00:02");
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &records, Some("light")).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "timestamp_ms,tick,lux\n1000,10,120.5\n2000,20,120.5\n"
+        );
+
+        let mut parquet = Vec::new();
+        write_parquet(&mut parquet, &records, Some("rtc")).unwrap();
+        assert!(parquet.starts_with(b"PAR1"));
+        assert!(parquet.windows(12).any(|w| w == b"rtc_datetime"));
+        assert_eq!(
+            write_parquet(&mut Vec::new(), &records, Some("oled"))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
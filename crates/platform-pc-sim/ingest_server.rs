@@ -0,0 +1,348 @@
+//! Local stand-in for the Raspberry Pi IoT ingest server.
+//!
+//! Accepts the `POST /api/sensors/reading` requests the wifi-climate firmware
+//! sends to `RASPI_IP:8000`, validates them with
+//! [`platform_pc_sim::ingest_store::parse_reading`] and appends them to a CSV
+//! store. Stored readings are served back as JSON:
+//!
+//! - `GET /api/sensors/readings?device_id=..&limit=..` — raw readings
+//! - `GET /api/history?device_id=..&limit=..` — same shape as the web
+//!   dashboard's `/api/history?sensor=bme280`
+//!
+//! Usage: `ingest-server [port] [store.csv]` (defaults: 8000, `ingest_readings.csv`).
+//! Port `0` picks a free port; the bound address is printed on startup.
+
+use std::env;
+use std::io::{Read as _, Write as _};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use platform_pc_sim::ingest_store::{parse_reading, CsvReadingStore};
+
+const DEFAULT_PORT: u16 = 8000;
+const DEFAULT_STORE: &str = "ingest_readings.csv";
+/// Readings returned when the request has no `limit` (matches the dashboard's
+/// history ring buffer).
+const DEFAULT_LIMIT: usize = 300;
+const MAX_HEADER_LEN: usize = 8 * 1024;
+const MAX_BODY_LEN: usize = 16 * 1024;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let port = args
+        .next()
+        .map(|value| value.parse::<u16>().expect("port must be a number"))
+        .unwrap_or(DEFAULT_PORT);
+    let store_path = args.next().unwrap_or_else(|| DEFAULT_STORE.to_string());
+
+    let store = CsvReadingStore::open(&store_path).expect("ingest store should open");
+    println!(
+        "ingest store: {} ({} readings)",
+        store.path().display(),
+        store.len()
+    );
+    let store = Arc::new(Mutex::new(store));
+
+    // The firmware posts from the LAN, so listen on every interface.
+    let listener = TcpListener::bind(("0.0.0.0", port)).expect("server should bind");
+    println!(
+        "ingest server listening on {}",
+        listener.local_addr().expect("bound address")
+    );
+
+    for stream in listener.incoming().flatten() {
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        let store = Arc::clone(&store);
+        thread::spawn(move || handle_connection(stream, &store));
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    body: Vec<u8>,
+}
+
+enum RequestError {
+    Io,
+    Malformed,
+    TooLarge,
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<Request, RequestError> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    let header_len = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_HEADER_LEN {
+            return Err(RequestError::TooLarge);
+        }
+        match stream.read(&mut chunk) {
+            Ok(0) => return Err(RequestError::Malformed),
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            Err(_) => return Err(RequestError::Io),
+        }
+    };
+
+    let head = std::str::from_utf8(&buf[..header_len]).map_err(|_| RequestError::Malformed)?;
+    let mut lines = head.lines();
+    let mut parts = lines.next().unwrap_or("").split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(RequestError::Malformed);
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.trim().parse::<usize>())
+        .transpose()
+        .map_err(|_| RequestError::Malformed)?
+        .unwrap_or(0);
+    if content_length > MAX_BODY_LEN {
+        return Err(RequestError::TooLarge);
+    }
+    let (method, path, query) = (method.to_string(), path.to_string(), query.to_string());
+
+    let mut body = buf.split_off(header_len);
+    while body.len() < content_length {
+        match stream.read(&mut chunk) {
+            Ok(0) => return Err(RequestError::Malformed),
+            Ok(n) => body.extend_from_slice(&chunk[..n]),
+            Err(_) => return Err(RequestError::Io),
+        }
+    }
+    body.truncate(content_length);
+    Ok(Request {
+        method,
+        path,
+        query,
+        body,
+    })
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn respond_json(stream: &mut TcpStream, status: &str, json: &str) {
+    respond(stream, status, "application/json; charset=utf-8", json);
+}
+
+fn error_json(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+fn query_param<'a>(query: &'a str, key: &str) -> Option<&'a str> {
+    query.split('&').find_map(|part| {
+        let (k, v) = part.split_once('=')?;
+        (k == key).then_some(v)
+    })
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn handle_connection(mut stream: TcpStream, store: &Mutex<CsvReadingStore>) {
+    let request = match read_request(&mut stream) {
+        Ok(request) => request,
+        Err(RequestError::Io) => return,
+        Err(RequestError::Malformed) => {
+            respond_json(
+                &mut stream,
+                "400 Bad Request",
+                &error_json("malformed request"),
+            );
+            return;
+        }
+        Err(RequestError::TooLarge) => {
+            respond_json(
+                &mut stream,
+                "413 Payload Too Large",
+                &error_json("request too large"),
+            );
+            return;
+        }
+    };
+
+    let device_id = query_param(&request.query, "device_id");
+    let limit = query_param(&request.query, "limit")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_LIMIT);
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/api/sensors/reading") => {
+            let reading = match parse_reading(&request.body, unix_now()) {
+                Ok(reading) => reading,
+                Err(e) => {
+                    respond_json(&mut stream, "400 Bad Request", &error_json(&e.to_string()));
+                    return;
+                }
+            };
+            let summary = format!(
+                "{} {:.2}°C {:.2}%",
+                reading.device_id, reading.temperature, reading.humidity
+            );
+            let appended = store.lock().unwrap().append(reading);
+            match appended {
+                Ok(id) => {
+                    println!("[ingest] #{id} {summary}");
+                    respond_json(&mut stream, "201 Created", &format!("{{\"id\":{id}}}"));
+                }
+                Err(e) => respond_json(
+                    &mut stream,
+                    "500 Internal Server Error",
+                    &error_json(&format!("store write failed: {e}")),
+                ),
+            }
+        }
+        ("GET", "/api/sensors/readings") => {
+            let json = store.lock().unwrap().readings_json(device_id, limit);
+            respond_json(&mut stream, "200 OK", &json);
+        }
+        ("GET", "/api/history") => {
+            let json = store.lock().unwrap().history_json(device_id, limit);
+            respond_json(&mut stream, "200 OK", &json);
+        }
+        (_, "/api/sensors/reading" | "/api/sensors/readings" | "/api/history") => respond_json(
+            &mut stream,
+            "405 Method Not Allowed",
+            &error_json("method not allowed"),
+        ),
+        _ => respond_json(&mut stream, "404 Not Found", &error_json("not found")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    fn spawn_server(store: CsvReadingStore) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let store = Arc::new(Mutex::new(store));
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let store = Arc::clone(&store);
+                thread::spawn(move || handle_connection(stream, &store));
+            }
+        });
+        addr
+    }
+
+    fn send(addr: SocketAddr, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn post(addr: SocketAddr, body: &str) -> String {
+        send(
+            addr,
+            format!(
+                "POST /api/sensors/reading HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .as_bytes(),
+        )
+    }
+
+    #[test]
+    fn post_then_read_back_readings_and_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let addr = spawn_server(CsvReadingStore::open(dir.path().join("r.csv")).unwrap());
+
+        let created = post(
+            addr,
+            r#"{"device_id":"esp32-01","temperature":23.50,"humidity":41.25}"#,
+        );
+        assert!(created.starts_with("HTTP/1.1 201 Created"), "{created}");
+        assert!(created.ends_with(r#"{"id":1}"#), "{created}");
+        post(
+            addr,
+            r#"{"device_id":"other","temperature":10.00,"humidity":50.00}"#,
+        );
+
+        let readings = send(
+            addr,
+            b"GET /api/sensors/readings?device_id=esp32-01 HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        );
+        let body = readings.split("\r\n\r\n").nth(1).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 1);
+        assert_eq!(parsed[0]["device_id"], "esp32-01");
+        assert_eq!(parsed[0]["temperature"], 23.5);
+
+        let history = send(addr, b"GET /api/history?limit=1 HTTP/1.1\r\n\r\n");
+        assert!(
+            history.ends_with(r#"{"temperature":[10.0],"humidity":[50.0],"pressure":[null]}"#),
+            "{history}"
+        );
+    }
+
+    #[test]
+    fn body_split_across_packets_is_reassembled() {
+        let dir = tempfile::tempdir().unwrap();
+        let addr = spawn_server(CsvReadingStore::open(dir.path().join("r.csv")).unwrap());
+        let body = r#"{"device_id":"slow","temperature":1.00,"humidity":2.00}"#;
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST /api/sensors/reading HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .unwrap();
+        stream.flush().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        stream.write_all(body.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 201"), "{response}");
+    }
+
+    #[test]
+    fn invalid_requests_are_rejected_without_storing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("r.csv");
+        let addr = spawn_server(CsvReadingStore::open(&path).unwrap());
+
+        let bad = post(addr, r#"{"device_id":"esp32-01","humidity":41.25}"#);
+        assert!(bad.starts_with("HTTP/1.1 400"), "{bad}");
+        assert!(bad.contains("missing field `temperature`"), "{bad}");
+
+        let too_large = send(
+            addr,
+            b"POST /api/sensors/reading HTTP/1.1\r\nContent-Length: 999999\r\n\r\n",
+        );
+        assert!(too_large.starts_with("HTTP/1.1 413"), "{too_large}");
+
+        let wrong_method = send(addr, b"GET /api/sensors/reading HTTP/1.1\r\n\r\n");
+        assert!(wrong_method.starts_with("HTTP/1.1 405"), "{wrong_method}");
+        let missing = send(addr, b"GET /nope HTTP/1.1\r\n\r\n");
+        assert!(missing.starts_with("HTTP/1.1 404"), "{missing}");
+
+        assert_eq!(CsvReadingStore::open(&path).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn query_param_finds_value() {
+        assert_eq!(query_param("device_id=a&limit=3", "limit"), Some("3"));
+        assert_eq!(query_param("device_id=a", "limit"), None);
+    }
+}
@@ -0,0 +1,434 @@
+//! Raspberry Pi IoT サーバーの代わりに測定値を受け取る ingest ストア。
+//!
+//! - [`parse_reading`] で wifi-climate ファームウェアの POST 本文
+//!   (`core_app::telemetry::TelemetryFrame::encode_json` の出力) を検証する
+//! - [`CsvReadingStore`] は 1 行 1 測定値の CSV に追記し、再起動後も読み戻せる
+//! - [`CsvReadingStore::history_json`] は `device-dashboard-web` の
+//!   `/api/history` と同じ形の JSON を返す
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// CSV のヘッダ行。
+pub const CSV_HEADER: &str = "id,received_at,device_id,timestamp,temperature,humidity,pressure_pa";
+
+/// `device_id` の最大長。
+pub const MAX_DEVICE_ID_LEN: usize = 64;
+
+/// BME280 の測定範囲 (データシート 1.1)。
+const TEMPERATURE_RANGE: (f64, f64) = (-40.0, 85.0);
+const HUMIDITY_RANGE: (f64, f64) = (0.0, 100.0);
+const PRESSURE_RANGE_PA: (u64, u64) = (30_000, 110_000);
+
+/// 受け取った 1 件の測定値。
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct IngestReading {
+    /// ストア内の通し番号 (1 始まり)
+    pub id: u64,
+    /// サーバーが受け取った時刻 (Unix 秒)
+    pub received_at: u64,
+    pub device_id: String,
+    /// デバイス側の時刻 (RTC を持つ場合のみ)
+    pub timestamp: Option<u32>,
+    /// ℃
+    pub temperature: f64,
+    /// %RH
+    pub humidity: f64,
+    pub pressure_pa: Option<u32>,
+}
+
+/// POST 本文がスキーマに合わない理由。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IngestError {
+    /// JSON として読めない、またはオブジェクトでない
+    InvalidJson,
+    MissingField(&'static str),
+    /// 型が違う
+    InvalidField(&'static str),
+    /// 型は正しいがセンサーの測定範囲外
+    OutOfRange(&'static str),
+}
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidJson => write!(f, "body is not a JSON object"),
+            Self::MissingField(name) => write!(f, "missing field `{name}`"),
+            Self::InvalidField(name) => write!(f, "field `{name}` has the wrong type"),
+            Self::OutOfRange(name) => write!(f, "field `{name}` is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for IngestError {}
+
+/// POST 本文を検証する。`id` は 0 のまま返し、[`CsvReadingStore::append`] で振る。
+///
+/// 必須は `device_id` / `temperature` / `humidity`。`timestamp` と `pressure_pa` は任意で、
+/// それ以外のフィールド (`co2_ppm` など) は読み捨てる。
+pub fn parse_reading(body: &[u8], received_at: u64) -> Result<IngestReading, IngestError> {
+    let value: Value = serde_json::from_slice(body).map_err(|_| IngestError::InvalidJson)?;
+    let object = value.as_object().ok_or(IngestError::InvalidJson)?;
+
+    let device_id = required(object, "device_id")?
+        .as_str()
+        .ok_or(IngestError::InvalidField("device_id"))?;
+    if device_id.is_empty()
+        || device_id.len() > MAX_DEVICE_ID_LEN
+        || !device_id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+    {
+        return Err(IngestError::InvalidField("device_id"));
+    }
+
+    let timestamp = optional(object, "timestamp")
+        .map(|v| {
+            v.as_u64()
+                .and_then(|t| u32::try_from(t).ok())
+                .ok_or(IngestError::InvalidField("timestamp"))
+        })
+        .transpose()?;
+    let temperature = number_in(object, "temperature", TEMPERATURE_RANGE)?;
+    let humidity = number_in(object, "humidity", HUMIDITY_RANGE)?;
+    let pressure_pa = optional(object, "pressure_pa")
+        .map(|v| {
+            let pa = v.as_u64().ok_or(IngestError::InvalidField("pressure_pa"))?;
+            if !(PRESSURE_RANGE_PA.0..=PRESSURE_RANGE_PA.1).contains(&pa) {
+                return Err(IngestError::OutOfRange("pressure_pa"));
+            }
+            Ok(pa as u32)
+        })
+        .transpose()?;
+
+    Ok(IngestReading {
+        id: 0,
+        received_at,
+        device_id: device_id.to_string(),
+        timestamp,
+        temperature,
+        humidity,
+        pressure_pa,
+    })
+}
+
+fn required<'a>(
+    object: &'a Map<String, Value>,
+    name: &'static str,
+) -> Result<&'a Value, IngestError> {
+    optional(object, name).ok_or(IngestError::MissingField(name))
+}
+
+/// `null` は省略と同じ扱いにする。
+fn optional<'a>(object: &'a Map<String, Value>, name: &str) -> Option<&'a Value> {
+    object.get(name).filter(|v| !v.is_null())
+}
+
+fn number_in(
+    object: &Map<String, Value>,
+    name: &'static str,
+    (min, max): (f64, f64),
+) -> Result<f64, IngestError> {
+    let value = required(object, name)?
+        .as_f64()
+        .ok_or(IngestError::InvalidField(name))?;
+    if !(min..=max).contains(&value) {
+        return Err(IngestError::OutOfRange(name));
+    }
+    Ok(value)
+}
+
+/// 測定値を CSV ファイルに追記するストア。
+///
+/// 起動時に既存の行をすべて読み込み、以後の読み出しはメモリから返す。
+/// 追記のたびに flush するので、プロセスを落としても受理済みの測定値は残る。
+#[derive(Debug)]
+pub struct CsvReadingStore {
+    path: PathBuf,
+    file: File,
+    readings: Vec<IngestReading>,
+}
+
+impl CsvReadingStore {
+    /// ファイルを開く。無ければヘッダ行だけのファイルを作る。
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let readings = match File::open(&path) {
+            Ok(file) => read_csv(BufReader::new(file))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{CSV_HEADER}")?;
+            file.flush()?;
+        }
+        Ok(Self {
+            path,
+            file,
+            readings,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.readings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readings.is_empty()
+    }
+
+    pub fn readings(&self) -> &[IngestReading] {
+        &self.readings
+    }
+
+    /// 通し番号を振って追記し、その番号を返す。
+    pub fn append(&mut self, mut reading: IngestReading) -> io::Result<u64> {
+        reading.id = self.readings.last().map_or(1, |last| last.id + 1);
+        writeln!(self.file, "{}", csv_row(&reading))?;
+        self.file.flush()?;
+        let id = reading.id;
+        self.readings.push(reading);
+        Ok(id)
+    }
+
+    /// `device_id` で絞り込んだ最新 `limit` 件 (古い順)。
+    pub fn latest(&self, device_id: Option<&str>, limit: usize) -> Vec<&IngestReading> {
+        let mut matching: Vec<&IngestReading> = self
+            .readings
+            .iter()
+            .rev()
+            .filter(|r| device_id.map_or(true, |id| r.device_id == id))
+            .take(limit)
+            .collect();
+        matching.reverse();
+        matching
+    }
+
+    /// [`CsvReadingStore::latest`] の JSON 配列。
+    pub fn readings_json(&self, device_id: Option<&str>, limit: usize) -> String {
+        serde_json::to_string(&self.latest(device_id, limit)).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// `device-dashboard-web` の `/api/history?sensor=bme280` と同じ形の JSON。
+    pub fn history_json(&self, device_id: Option<&str>, limit: usize) -> String {
+        #[derive(Serialize)]
+        struct ClimateHistory {
+            temperature: Vec<f64>,
+            humidity: Vec<f64>,
+            pressure: Vec<Option<u32>>,
+        }
+        let latest = self.latest(device_id, limit);
+        let history = ClimateHistory {
+            temperature: latest.iter().map(|r| r.temperature).collect(),
+            humidity: latest.iter().map(|r| r.humidity).collect(),
+            pressure: latest.iter().map(|r| r.pressure_pa).collect(),
+        };
+        serde_json::to_string(&history).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+/// `device_id` は英数字と `-_.` に限っているので、CSV のクォートは不要。
+fn csv_row(reading: &IngestReading) -> String {
+    fn opt<T: ToString>(value: Option<T>) -> String {
+        value.map(|v| v.to_string()).unwrap_or_default()
+    }
+    format!(
+        "{},{},{},{},{},{},{}",
+        reading.id,
+        reading.received_at,
+        reading.device_id,
+        opt(reading.timestamp),
+        reading.temperature,
+        reading.humidity,
+        opt(reading.pressure_pa),
+    )
+}
+
+fn read_csv(reader: impl BufRead) -> io::Result<Vec<IngestReading>> {
+    let mut readings = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if index == 0 && line == CSV_HEADER || line.is_empty() {
+            continue;
+        }
+        let reading = parse_csv_row(&line).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("malformed ingest CSV row at line {}", index + 1),
+            )
+        })?;
+        readings.push(reading);
+    }
+    Ok(readings)
+}
+
+fn parse_csv_row(line: &str) -> Option<IngestReading> {
+    fn opt<T: std::str::FromStr>(field: &str) -> Option<Option<T>> {
+        if field.is_empty() {
+            Some(None)
+        } else {
+            field.parse().ok().map(Some)
+        }
+    }
+    let mut fields = line.split(',');
+    let reading = IngestReading {
+        id: fields.next()?.parse().ok()?,
+        received_at: fields.next()?.parse().ok()?,
+        device_id: fields.next()?.to_string(),
+        timestamp: opt(fields.next()?)?,
+        temperature: fields.next()?.parse().ok()?,
+        humidity: fields.next()?.parse().ok()?,
+        pressure_pa: opt(fields.next()?)?,
+    };
+    fields.next().is_none().then_some(reading)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(device_id: &str, temperature: f64) -> IngestReading {
+        IngestReading {
+            id: 0,
+            received_at: 1_700_000_000,
+            device_id: device_id.to_string(),
+            timestamp: None,
+            temperature,
+            humidity: 40.05,
+            pressure_pa: Some(101_325),
+        }
+    }
+
+    #[test]
+    fn parse_reading_accepts_firmware_payload() {
+        let parsed = parse_reading(
+            br#"{"device_id":"esp32-01","temperature":-3.05,"humidity":40.05}"#,
+            42,
+        )
+        .unwrap();
+        assert_eq!(parsed.device_id, "esp32-01");
+        assert_eq!(parsed.temperature, -3.05);
+        assert_eq!(parsed.humidity, 40.05);
+        assert_eq!(
+            (parsed.received_at, parsed.timestamp, parsed.pressure_pa),
+            (42, None, None)
+        );
+
+        let full = parse_reading(
+            br#"{"device_id":"rig-1","timestamp":1736164800,"temperature":21.00,"humidity":50.00,"pressure_pa":101325,"co2_ppm":1200}"#,
+            0,
+        )
+        .unwrap();
+        assert_eq!(full.timestamp, Some(1_736_164_800));
+        assert_eq!(full.pressure_pa, Some(101_325));
+    }
+
+    #[test]
+    fn parse_reading_reports_schema_violations() {
+        let cases: [(&[u8], IngestError); 8] = [
+            (b"not json", IngestError::InvalidJson),
+            (b"[1,2]", IngestError::InvalidJson),
+            (
+                br#"{"temperature":1,"humidity":1}"#,
+                IngestError::MissingField("device_id"),
+            ),
+            (
+                br#"{"device_id":"a","humidity":1}"#,
+                IngestError::MissingField("temperature"),
+            ),
+            (
+                br#"{"device_id":"a b","temperature":1,"humidity":1}"#,
+                IngestError::InvalidField("device_id"),
+            ),
+            (
+                br#"{"device_id":"a","temperature":"20","humidity":1}"#,
+                IngestError::InvalidField("temperature"),
+            ),
+            (
+                br#"{"device_id":"a","temperature":20,"humidity":101}"#,
+                IngestError::OutOfRange("humidity"),
+            ),
+            (
+                br#"{"device_id":"a","temperature":20,"humidity":1,"pressure_pa":5}"#,
+                IngestError::OutOfRange("pressure_pa"),
+            ),
+        ];
+        for (body, expected) in cases {
+            assert_eq!(
+                parse_reading(body, 0),
+                Err(expected),
+                "{}",
+                String::from_utf8_lossy(body)
+            );
+        }
+    }
+
+    #[test]
+    fn csv_store_persists_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("readings.csv");
+        {
+            let mut store = CsvReadingStore::open(&path).unwrap();
+            assert_eq!(store.append(reading("a", 21.5)).unwrap(), 1);
+            let mut no_pressure = reading("b", -0.25);
+            no_pressure.pressure_pa = None;
+            no_pressure.timestamp = Some(7);
+            assert_eq!(store.append(no_pressure).unwrap(), 2);
+        }
+        let mut store = CsvReadingStore::open(&path).unwrap();
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.readings()[1].timestamp, Some(7));
+        assert_eq!(store.readings()[1].pressure_pa, None);
+        assert_eq!(store.append(reading("a", 22.0)).unwrap(), 3);
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            csv,
+            format!(
+                "{CSV_HEADER}\n1,1700000000,a,,21.5,40.05,101325\n2,1700000000,b,7,-0.25,40.05,\n3,1700000000,a,,22,40.05,101325\n"
+            )
+        );
+    }
+
+    #[test]
+    fn csv_store_rejects_corrupt_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("readings.csv");
+        std::fs::write(&path, format!("{CSV_HEADER}\n1,0,a,,20,40\n")).unwrap();
+        let err = CsvReadingStore::open(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn latest_filters_by_device_and_keeps_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = CsvReadingStore::open(dir.path().join("r.csv")).unwrap();
+        for (device, t) in [("a", 1.0), ("b", 2.0), ("a", 3.0), ("a", 4.0)] {
+            store.append(reading(device, t)).unwrap();
+        }
+        let temps = |device, limit| -> Vec<f64> {
+            store
+                .latest(device, limit)
+                .iter()
+                .map(|r| r.temperature)
+                .collect()
+        };
+        assert_eq!(temps(Some("a"), 2), vec![3.0, 4.0]);
+        assert_eq!(temps(None, 10), vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(temps(Some("c"), 10), Vec::<f64>::new());
+        assert_eq!(
+            store.history_json(Some("b"), 10),
+            r#"{"temperature":[2.0],"humidity":[40.05],"pressure":[101325]}"#
+        );
+    }
+}
@@ -7,23 +7,43 @@
 
 pub mod bh1750_mock;
 pub mod bme280_mock;
+pub mod board_def;
 pub mod camera_mock;
+pub mod cli_args;
 pub mod climate_sim;
 pub mod component_sim;
 pub mod dashboard;
 pub mod dht22_mock;
 pub mod ds3231_mock;
+pub mod esp_image;
+pub mod esp_rom;
 pub mod hc_sr04_mock;
+pub mod history_store;
+pub mod ingest_store;
 pub mod l298n_mock;
 pub mod lcd1602_mock;
 pub mod mock_hal;
+pub mod motor_sim;
 pub mod mpu6050_mock;
+pub mod mqtt_broker;
+pub mod ota_client;
+pub mod ota_updater_mock;
+pub mod parquet_writer;
 pub mod pwm_mock;
 pub mod servo_mock;
 pub mod sgp30_mock;
+pub mod sim_control;
 pub mod ssd1306_mock;
+pub mod std_socket;
+pub mod storage_mock;
+pub mod thermal_sim;
 pub mod virtual_i2c;
 pub mod vl53l0x_mock;
 pub mod web_dashboard;
+pub mod websocket;
+pub mod wiring_codegen;
 pub mod wiring_config;
+pub mod wiring_export;
+pub mod wiring_project;
+pub mod wiring_rules;
 pub mod wiring_svg;
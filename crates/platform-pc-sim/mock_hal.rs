@@ -8,12 +8,13 @@
 //! ## 提供する型
 //!
 //! - [`MockPin`][]: GPIO出力ピンのモック実装
+//! - [`MockButton`][]: 押しボタン (GPIO入力ピン) のモック実装
 //! - [`MockI2c`][]: I2Cバスのモック実装
 
 use hal_api::error::{GpioError, I2cError};
-use hal_api::gpio::OutputPin;
+use hal_api::gpio::{InputPin, OutputPin};
 use hal_api::i2c::I2cBus;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::vec::Vec;
 
@@ -33,7 +34,7 @@ struct MockPinState {
 ///
 /// ```
 /// use platform_pc_sim::mock_hal::MockPin;
-/// use hal_api::gpio::OutputPin;
+/// use hal_api::gpio::{InputPin, OutputPin};
 ///
 /// let mut pin = MockPin::new(13);
 /// pin.set_high().unwrap();
@@ -104,6 +105,65 @@ impl OutputPin for MockPin {
     }
 }
 
+/// 押しボタンのモック実装
+///
+/// プルアップ配線 (押下で LOW) を想定し、[`MockButton::press`] / [`MockButton::release`]
+/// でテストやシミュレータから押下状態を切り替える。クローン間で状態を共有する。
+///
+/// # Examples
+///
+/// ```
+/// use platform_pc_sim::mock_hal::MockButton;
+/// use hal_api::gpio::InputPin;
+///
+/// let button = MockButton::new(4);
+/// assert!(button.is_high().unwrap());
+/// button.press();
+/// assert!(button.is_low().unwrap());
+/// ```
+#[derive(Clone, Debug)]
+pub struct MockButton {
+    pin_number: u8,
+    pressed: Rc<Cell<bool>>,
+}
+
+impl MockButton {
+    pub fn new(pin_number: u8) -> Self {
+        Self {
+            pin_number,
+            pressed: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn pin_number(&self) -> u8 {
+        self.pin_number
+    }
+
+    pub fn press(&self) {
+        self.pressed.set(true);
+    }
+
+    pub fn release(&self) {
+        self.pressed.set(false);
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed.get()
+    }
+}
+
+impl InputPin for MockButton {
+    type Error = GpioError;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(!self.pressed.get())
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(self.pressed.get())
+    }
+}
+
 #[derive(Debug, Default)]
 struct MockI2cState {
     read_count: usize,
@@ -0,0 +1,377 @@
+//! Host-side DC motor plant with inertia for closed-loop speed control tuning.
+//!
+//! [`SimulatedDcMotor`] implements [`DriveMotor`] (the input side), and both
+//! [`SpeedFeedback`] and [`WheelEncoder`] (the output side), so it can stand in for
+//! a real motor + encoder pair behind `core_app::speed_control::SpeedControlledMotor`.
+//!
+//! The plant is a first-order lag: the shaft speed approaches a steady-state rpm
+//! determined by the duty cycle, a static-friction dead band and an external load.
+//! Call [`SimulatedDcMotor::step`] once per control tick to advance physics time.
+//!
+//! Cloned instances share the same internal state.
+//!
+//! # Examples
+//!
+//! ```
+//! use core_app::speed_control::SpeedControlledMotor;
+//! use platform_pc_sim::motor_sim::SimulatedDcMotor;
+//!
+//! let plant = SimulatedDcMotor::new();
+//! let mut motor = SpeedControlledMotor::new(plant.clone(), plant.clone());
+//! motor.set_target_rpm(150);
+//! for _ in 0..300 {
+//!     plant.step(10);
+//!     motor.tick().unwrap();
+//! }
+//! assert!((plant.rpm() - 150.0).abs() < 10.0);
+//! ```
+
+use core_app::pid::PidConfig;
+use core_app::speed_control::{SpeedControlConfig, SpeedControlledMotor};
+use hal_api::actuator::{DriveMotor, MotorCommand, MotorDirection};
+use hal_api::encoder::{SpeedFeedback, SpeedReading, WheelEncoder};
+use hal_api::error::{ActuatorError, SensorError};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::vec::Vec;
+
+/// Physical parameters of the simulated motor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DcMotorModel {
+    /// No-load speed at 100 % duty.
+    pub max_rpm: f32,
+    /// Speed time constant while driven (rotor + wheel inertia).
+    pub time_constant_ms: f32,
+    /// Speed time constant while coasting (friction only).
+    pub coast_time_constant_ms: f32,
+    /// Speed time constant while braking (shorted windings).
+    pub brake_time_constant_ms: f32,
+    /// Duty below which static friction keeps the shaft from turning.
+    pub dead_band_percent: u8,
+    /// Encoder resolution exposed through [`WheelEncoder`].
+    pub counts_per_revolution: u32,
+}
+
+impl Default for DcMotorModel {
+    fn default() -> Self {
+        Self {
+            max_rpm: 330.0,
+            time_constant_ms: 120.0,
+            coast_time_constant_ms: 800.0,
+            brake_time_constant_ms: 40.0,
+            dead_band_percent: 8,
+            counts_per_revolution: 20,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SimulatedDcMotorState {
+    model: DcMotorModel,
+    command: MotorCommand,
+    /// External load as a fraction of drive torque (0–100 %).
+    load_percent: u8,
+    rpm: f32,
+    /// Shaft position in revolutions since construction.
+    revolutions: f64,
+    elapsed_ms: u64,
+    history: Vec<MotorCommand>,
+}
+
+/// Simulated DC motor with inertia and an attached wheel encoder.
+#[derive(Clone, Debug)]
+pub struct SimulatedDcMotor {
+    state: Rc<RefCell<SimulatedDcMotorState>>,
+}
+
+impl Default for SimulatedDcMotor {
+    fn default() -> Self {
+        Self::with_model(DcMotorModel::default())
+    }
+}
+
+impl SimulatedDcMotor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_model(model: DcMotorModel) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(SimulatedDcMotorState {
+                model,
+                command: MotorCommand::new(MotorDirection::Coast, 0),
+                load_percent: 0,
+                rpm: 0.0,
+                revolutions: 0.0,
+                elapsed_ms: 0,
+                history: Vec::new(),
+            })),
+        }
+    }
+
+    /// Advances the plant by `dt_ms` milliseconds.
+    pub fn step(&self, dt_ms: u32) {
+        let mut state = self.state.borrow_mut();
+        let (target, tau) = steady_state(&state);
+        let alpha = 1.0 - (-(dt_ms as f32) / tau.max(1.0)).exp();
+        let previous = state.rpm;
+        state.rpm += (target - state.rpm) * alpha;
+        // Trapezoidal integration keeps encoder counts consistent with rpm.
+        let mean_rpm = (previous + state.rpm) as f64 / 2.0;
+        state.revolutions += mean_rpm * dt_ms as f64 / 60_000.0;
+        state.elapsed_ms += dt_ms as u64;
+    }
+
+    /// Sets an external load that reduces the steady-state speed (0–100 %).
+    pub fn set_load_percent(&self, load_percent: u8) {
+        self.state.borrow_mut().load_percent = load_percent.min(100);
+    }
+
+    /// Current shaft speed (positive = forward).
+    pub fn rpm(&self) -> f32 {
+        self.state.borrow().rpm
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.state.borrow().elapsed_ms
+    }
+
+    pub fn model(&self) -> DcMotorModel {
+        self.state.borrow().model
+    }
+
+    /// Most recent command applied.
+    pub fn current_command(&self) -> MotorCommand {
+        self.state.borrow().command
+    }
+
+    /// Full history of commands applied since construction.
+    pub fn history(&self) -> Vec<MotorCommand> {
+        self.state.borrow().history.clone()
+    }
+}
+
+fn steady_state(state: &SimulatedDcMotorState) -> (f32, f32) {
+    let model = &state.model;
+    let sign = match state.command.direction {
+        MotorDirection::Forward => 1.0,
+        MotorDirection::Reverse => -1.0,
+        MotorDirection::Brake => return (0.0, model.brake_time_constant_ms),
+        MotorDirection::Coast => return (0.0, model.coast_time_constant_ms),
+    };
+    let dead_band = model.dead_band_percent.min(99) as f32;
+    let duty = state.command.duty_percent.min(100) as f32;
+    if duty <= dead_band {
+        // Static friction holds the shaft; it spins down like a coasting motor.
+        return (0.0, model.coast_time_constant_ms);
+    }
+    let effective = (duty - dead_band) / (100.0 - dead_band);
+    let load = 1.0 - state.load_percent as f32 / 100.0;
+    (
+        sign * model.max_rpm * effective * load,
+        model.time_constant_ms,
+    )
+}
+
+impl DriveMotor for SimulatedDcMotor {
+    type Error = ActuatorError;
+
+    fn apply(&mut self, command: MotorCommand) -> Result<(), Self::Error> {
+        if command.duty_percent > 100 {
+            return Err(ActuatorError::InvalidCommand);
+        }
+        let mut state = self.state.borrow_mut();
+        state.command = command;
+        state.history.push(command);
+        Ok(())
+    }
+}
+
+impl SpeedFeedback for SimulatedDcMotor {
+    type Error = SensorError;
+
+    fn read_speed(&mut self) -> Result<SpeedReading, Self::Error> {
+        Ok(SpeedReading::new(self.rpm().round() as i32))
+    }
+}
+
+impl WheelEncoder for SimulatedDcMotor {
+    type Error = SensorError;
+
+    fn read_count(&mut self) -> Result<i32, Self::Error> {
+        let state = self.state.borrow();
+        let counts = state.revolutions * state.model.counts_per_revolution as f64;
+        Ok(counts.floor() as i64 as i32)
+    }
+}
+
+/// One row of a closed-loop step response.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedSample {
+    pub elapsed_ms: u64,
+    pub target_rpm: i32,
+    pub rpm: f32,
+    pub command: MotorCommand,
+}
+
+/// Runs `SpeedControlledMotor` against a fresh [`SimulatedDcMotor`] and records every tick.
+///
+/// Intended for tuning `pid` on the host: plot `rpm` against `target_rpm` and adjust gains.
+pub fn run_step_response(
+    model: DcMotorModel,
+    config: SpeedControlConfig,
+    target_rpm: i32,
+    tick_ms: u32,
+    ticks: usize,
+) -> Vec<SpeedSample> {
+    let plant = SimulatedDcMotor::with_model(model);
+    let mut motor = SpeedControlledMotor::new_with_config(plant.clone(), plant.clone(), config);
+    motor.set_target_rpm(target_rpm);
+
+    let mut samples = Vec::with_capacity(ticks);
+    for _ in 0..ticks {
+        plant.step(tick_ms);
+        // The simulated plant never fails, so errors here indicate a bug.
+        motor.tick().expect("simulated motor is infallible");
+        samples.push(SpeedSample {
+            elapsed_ms: plant.elapsed_ms(),
+            target_rpm,
+            rpm: plant.rpm(),
+            command: plant.current_command(),
+        });
+    }
+    samples
+}
+
+/// Default PID gains tuned against [`DcMotorModel::default`] at a 10 ms tick.
+pub fn demo_speed_control_config() -> SpeedControlConfig {
+    SpeedControlConfig {
+        max_rpm: 300,
+        control_period_ticks: 1,
+        pid: PidConfig {
+            kp_milli: 150,
+            ki_milli: 40,
+            kd_milli: 0,
+            output_min: -100,
+            output_max: 100,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_loop_speed_approaches_steady_state() {
+        let mut motor = SimulatedDcMotor::new();
+        motor
+            .apply(MotorCommand::new(MotorDirection::Forward, 100))
+            .unwrap();
+        motor.step(10);
+        let early = motor.rpm();
+        for _ in 0..200 {
+            motor.step(10);
+        }
+        assert!(early > 0.0 && early < 100.0, "inertia should slow spin-up");
+        assert!((motor.rpm() - 330.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn dead_band_keeps_shaft_still() {
+        let mut motor = SimulatedDcMotor::new();
+        motor
+            .apply(MotorCommand::new(MotorDirection::Forward, 5))
+            .unwrap();
+        for _ in 0..100 {
+            motor.step(10);
+        }
+        assert_eq!(motor.rpm(), 0.0);
+    }
+
+    #[test]
+    fn brake_stops_faster_than_coast() {
+        let spin_up = |motor: &mut SimulatedDcMotor| {
+            motor
+                .apply(MotorCommand::new(MotorDirection::Forward, 100))
+                .unwrap();
+            for _ in 0..200 {
+                motor.step(10);
+            }
+        };
+        let mut braked = SimulatedDcMotor::new();
+        let mut coasted = SimulatedDcMotor::new();
+        spin_up(&mut braked);
+        spin_up(&mut coasted);
+        braked
+            .apply(MotorCommand::new(MotorDirection::Brake, 0))
+            .unwrap();
+        coasted
+            .apply(MotorCommand::new(MotorDirection::Coast, 0))
+            .unwrap();
+        for _ in 0..10 {
+            braked.step(10);
+            coasted.step(10);
+        }
+        assert!(braked.rpm() < coasted.rpm());
+    }
+
+    #[test]
+    fn encoder_counts_follow_rotation() {
+        let mut motor = SimulatedDcMotor::new();
+        motor
+            .apply(MotorCommand::new(MotorDirection::Reverse, 100))
+            .unwrap();
+        for _ in 0..500 {
+            motor.step(10);
+        }
+        // Roughly 330 rpm for ~5 s in reverse → about -27 revolutions.
+        let counts = motor.read_count().unwrap();
+        assert!(counts < -400 && counts > -600, "counts = {counts}");
+    }
+
+    #[test]
+    fn closed_loop_reaches_target_despite_dead_band() {
+        let samples = run_step_response(
+            DcMotorModel::default(),
+            demo_speed_control_config(),
+            120,
+            10,
+            400,
+        );
+        let last = samples.last().unwrap();
+        assert!((last.rpm - 120.0).abs() < 3.0, "rpm = {}", last.rpm);
+    }
+
+    #[test]
+    fn closed_loop_rejects_load_disturbance() {
+        let plant = SimulatedDcMotor::new();
+        let mut motor = SpeedControlledMotor::new_with_config(
+            plant.clone(),
+            plant.clone(),
+            demo_speed_control_config(),
+        );
+        motor.set_target_rpm(150);
+        for _ in 0..300 {
+            plant.step(10);
+            motor.tick().unwrap();
+        }
+        plant.set_load_percent(30);
+        for _ in 0..400 {
+            plant.step(10);
+            motor.tick().unwrap();
+        }
+        assert!((plant.rpm() - 150.0).abs() < 3.0, "rpm = {}", plant.rpm());
+    }
+
+    #[test]
+    fn clones_share_state() {
+        let motor = SimulatedDcMotor::new();
+        let mut handle = motor.clone();
+        handle
+            .apply(MotorCommand::new(MotorDirection::Brake, 0))
+            .unwrap();
+        assert_eq!(motor.current_command().direction, MotorDirection::Brake);
+        assert_eq!(motor.history().len(), 1);
+    }
+}
@@ -0,0 +1,63 @@
+//! Step-response printer for tuning `SpeedControlledMotor` PID gains on the host.
+//!
+//! Usage: `motor-speed-sim [target_rpm] [kp_milli] [ki_milli] [kd_milli] [load_percent]`
+//!
+//! Output is CSV (`ms,target_rpm,rpm,direction,duty`) so it can be piped into a plotter.
+
+use core_app::pid::PidConfig;
+use core_app::speed_control::SpeedControlledMotor;
+use platform_pc_sim::motor_sim::{demo_speed_control_config, SimulatedDcMotor};
+
+const TICK_MS: u32 = 10;
+const TICKS: usize = 600;
+
+fn arg_or<T: std::str::FromStr>(args: &[String], index: usize, default: T) -> T {
+    args.get(index)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let defaults = demo_speed_control_config();
+    let target_rpm: i32 = arg_or(&args, 0, 150);
+    let pid = PidConfig {
+        kp_milli: arg_or(&args, 1, defaults.pid.kp_milli),
+        ki_milli: arg_or(&args, 2, defaults.pid.ki_milli),
+        kd_milli: arg_or(&args, 3, defaults.pid.kd_milli),
+        ..defaults.pid
+    };
+    let load_percent: u8 = arg_or(&args, 4, 0);
+
+    let plant = SimulatedDcMotor::new();
+    let mut config = defaults;
+    config.pid = pid;
+    let mut motor = SpeedControlledMotor::new_with_config(plant.clone(), plant.clone(), config);
+    motor.set_target_rpm(target_rpm);
+
+    eprintln!(
+        "target={}rpm kp={} ki={} kd={} (milli) load={}%",
+        target_rpm, pid.kp_milli, pid.ki_milli, pid.kd_milli, load_percent
+    );
+    println!("ms,target_rpm,rpm,direction,duty");
+    for tick in 0..TICKS {
+        // 後半で負荷を掛けて外乱応答も見られるようにする
+        if tick == TICKS / 2 {
+            plant.set_load_percent(load_percent);
+        }
+        plant.step(TICK_MS);
+        if let Err(error) = motor.tick() {
+            eprintln!("motor speed sim failed: {:?}", error);
+            break;
+        }
+        let command = plant.current_command();
+        println!(
+            "{},{},{:.1},{:?},{}",
+            plant.elapsed_ms(),
+            target_rpm,
+            plant.rpm(),
+            command.direction,
+            command.duty_percent
+        );
+    }
+}
@@ -0,0 +1,518 @@
+//! テスト用の小さな MQTT 3.1.1 ブローカー。
+//!
+//! `core_app::mqtt::MqttClient` を host 上で本物の TCP 越しに動かすための代役。
+//! パケットの読み書きには `core_app::mqtt::Packet` をそのまま使う。
+//!
+//! - CONNECT / SUBSCRIBE (`+` / `#` ワイルドカード) / PUBLISH (QoS 0 / 1) / PINGREQ /
+//!   DISCONNECT を受け付ける
+//! - retain されたメッセージは購読時に配送する
+//! - DISCONNECT 無しで切れたクライアント、および keepalive の 1.5 倍の間黙っていた
+//!   クライアントの last-will を publish する
+//! - [`MqttBroker::drop_client`] / [`MqttBroker::withhold_pubacks`] で通信断や
+//!   PUBACK の欠落を再現できる
+//!
+//! セッションは常に clean session として扱い、購読者への QoS 1 配送は再送しない。
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use core_app::mqtt::QoS;
+//! use platform_pc_sim::mqtt_broker::MqttBroker;
+//!
+//! let broker = MqttBroker::start().unwrap();
+//! broker.publish("lab/rover-1/status", b"online", QoS::AtMostOnce, true);
+//! let message = broker
+//!     .wait_for_message("lab/+/status", Duration::from_secs(1))
+//!     .unwrap();
+//! assert_eq!(message.payload, b"online");
+//! assert!(message.retain);
+//! ```
+
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use core_app::mqtt::{topic_matches, Packet, Publish, QoS, SUBACK_FAILURE};
+
+/// ブローカーを通ったメッセージ。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BrokerMessage {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: QoS,
+    pub retain: bool,
+    /// クライアントが再送として送ってきた (DUP=1)
+    pub dup: bool,
+    /// 送信元のクライアント ID。[`MqttBroker::publish`] や last-will なら `None`
+    pub from: Option<String>,
+}
+
+#[derive(Default)]
+struct Shared {
+    messages: Vec<BrokerMessage>,
+    clients: Vec<String>,
+    injected: Vec<BrokerMessage>,
+    drop_requests: Vec<String>,
+    withheld_pubacks: usize,
+}
+
+/// バックグラウンドスレッドで動くブローカー。drop すると停止する。
+pub struct MqttBroker {
+    addr: SocketAddr,
+    shared: Arc<Mutex<Shared>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MqttBroker {
+    /// `127.0.0.1` の空きポートで起動する。
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let shared = Arc::clone(&shared);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || BrokerLoop::new(listener, shared).run(&stop))
+        };
+        Ok(Self {
+            addr,
+            shared,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// これまでに受け取った (または注入した) メッセージ。
+    pub fn messages(&self) -> Vec<BrokerMessage> {
+        self.lock().messages.clone()
+    }
+
+    /// CONNECT 済みで接続中のクライアント ID。
+    pub fn connected_clients(&self) -> Vec<String> {
+        self.lock().clients.clone()
+    }
+
+    /// ブローカー自身からメッセージを publish する。
+    pub fn publish(&self, topic: &str, payload: &[u8], qos: QoS, retain: bool) {
+        self.lock().injected.push(BrokerMessage {
+            topic: topic.to_string(),
+            payload: payload.to_vec(),
+            qos,
+            retain,
+            dup: false,
+            from: None,
+        });
+    }
+
+    /// `client_id` の TCP 接続を DISCONNECT 無しで切る (last-will が publish される)。
+    pub fn drop_client(&self, client_id: &str) {
+        self.lock().drop_requests.push(client_id.to_string());
+    }
+
+    /// 次に受け取る QoS 1 の PUBLISH `count` 件に PUBACK を返さない。
+    pub fn withhold_pubacks(&self, count: usize) {
+        self.lock().withheld_pubacks = count;
+    }
+
+    /// `filter` に一致するメッセージが届くまで待つ (既に届いていればすぐ返す)。
+    pub fn wait_for_message(&self, filter: &str, timeout: Duration) -> Option<BrokerMessage> {
+        self.wait_for(timeout, |shared| {
+            shared
+                .messages
+                .iter()
+                .find(|m| topic_matches(filter, &m.topic))
+                .cloned()
+        })
+    }
+
+    /// `client_id` が接続するまで待つ。
+    pub fn wait_for_client(&self, client_id: &str, timeout: Duration) -> bool {
+        self.wait_for(timeout, |shared| {
+            shared.clients.iter().any(|c| c == client_id).then_some(())
+        })
+        .is_some()
+    }
+
+    fn wait_for<T>(
+        &self,
+        timeout: Duration,
+        mut check: impl FnMut(&Shared) -> Option<T>,
+    ) -> Option<T> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(found) = check(&self.lock()) {
+                return Some(found);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MqttBroker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Session {
+    stream: TcpStream,
+    rx: Vec<u8>,
+    client_id: Option<String>,
+    will: Option<BrokerMessage>,
+    subscriptions: Vec<(String, QoS)>,
+    next_packet_id: u16,
+    keep_alive: Option<Duration>,
+    last_seen: Instant,
+    closed: bool,
+    /// 正常に DISCONNECT した (last-will を出さない)
+    graceful: bool,
+}
+
+impl Session {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            rx: Vec::new(),
+            client_id: None,
+            will: None,
+            subscriptions: Vec::new(),
+            next_packet_id: 1,
+            keep_alive: None,
+            last_seen: Instant::now(),
+            closed: false,
+            graceful: false,
+        }
+    }
+
+    fn send(&mut self, packet: &Packet<'_>) {
+        let mut buffer = vec![0u8; 16 * 1024];
+        let Ok(len) = packet.encode(&mut buffer) else {
+            return;
+        };
+        let mut written = 0;
+        while written < len && !self.closed {
+            match self.stream.write(&buffer[written..len]) {
+                Ok(0) => self.closed = true,
+                Ok(n) => written += n,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(1))
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => self.closed = true,
+            }
+        }
+    }
+
+    fn deliver(&mut self, message: &BrokerMessage, retained: bool) {
+        let granted = self
+            .subscriptions
+            .iter()
+            .filter(|(filter, _)| topic_matches(filter, &message.topic))
+            .map(|(_, qos)| *qos)
+            .max();
+        let Some(granted) = granted else {
+            return;
+        };
+        let qos = granted.min(message.qos);
+        let packet_id = match qos {
+            QoS::AtMostOnce => None,
+            QoS::AtLeastOnce => {
+                let id = self.next_packet_id;
+                self.next_packet_id = self.next_packet_id.checked_add(1).unwrap_or(1);
+                Some(id)
+            }
+        };
+        self.send(&Packet::Publish(Publish {
+            topic: &message.topic,
+            payload: &message.payload,
+            qos,
+            retain: retained,
+            dup: false,
+            packet_id,
+        }));
+    }
+}
+
+struct BrokerLoop {
+    listener: TcpListener,
+    shared: Arc<Mutex<Shared>>,
+    sessions: Vec<Session>,
+    retained: BTreeMap<String, BrokerMessage>,
+}
+
+impl BrokerLoop {
+    fn new(listener: TcpListener, shared: Arc<Mutex<Shared>>) -> Self {
+        Self {
+            listener,
+            shared,
+            sessions: Vec::new(),
+            retained: BTreeMap::new(),
+        }
+    }
+
+    fn run(mut self, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            self.accept();
+            let (injected, drop_requests) = {
+                let mut shared = self.lock();
+                (
+                    std::mem::take(&mut shared.injected),
+                    std::mem::take(&mut shared.drop_requests),
+                )
+            };
+            for message in injected {
+                self.route(message);
+            }
+            for client_id in drop_requests {
+                for session in &mut self.sessions {
+                    if session.client_id.as_deref() == Some(client_id.as_str()) {
+                        session.closed = true;
+                    }
+                }
+            }
+            for index in 0..self.sessions.len() {
+                let outgoing = self.service(index);
+                for message in outgoing {
+                    self.route(message);
+                }
+            }
+            self.reap();
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn accept(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                let _ = stream.set_nodelay(true);
+                self.sessions.push(Session::new(stream));
+            }
+        }
+    }
+
+    /// 1 セッション分の受信を処理し、配送すべきメッセージを返す。
+    fn service(&mut self, index: usize) -> Vec<BrokerMessage> {
+        let mut outgoing = Vec::new();
+        let mut chunk = [0u8; 1024];
+        let session = &mut self.sessions[index];
+        // EOF の前に届いていたパケット (DISCONNECT など) は処理してから閉じる
+        let mut eof = false;
+        while !session.closed && !eof {
+            match session.stream.read(&mut chunk) {
+                Ok(0) => eof = true,
+                Ok(n) => {
+                    session.rx.extend_from_slice(&chunk[..n]);
+                    session.last_seen = Instant::now();
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => session.closed = true,
+            }
+        }
+
+        // 受信バッファを取り出しておけば、パケットを借用したまま self を変更できる
+        let mut rx = std::mem::take(&mut self.sessions[index].rx);
+        let mut consumed = 0;
+        let mut new_subscriptions = Vec::new();
+        while !self.sessions[index].closed {
+            match Packet::decode(&rx[consumed..]) {
+                Ok(Some((packet, used))) => {
+                    consumed += used;
+                    self.handle(index, packet, &mut outgoing, &mut new_subscriptions);
+                }
+                Ok(None) => break,
+                Err(_) => self.sessions[index].closed = true,
+            }
+        }
+        rx.drain(..consumed);
+        self.sessions[index].rx = rx;
+        if eof {
+            self.sessions[index].closed = true;
+        }
+
+        for filter in new_subscriptions {
+            let matching: Vec<BrokerMessage> = self
+                .retained
+                .values()
+                .filter(|m| topic_matches(&filter, &m.topic))
+                .cloned()
+                .collect();
+            for message in matching {
+                self.sessions[index].deliver(&message, true);
+            }
+        }
+
+        let session = &mut self.sessions[index];
+        if let Some(keep_alive) = session.keep_alive {
+            if session.last_seen.elapsed() > keep_alive + keep_alive / 2 {
+                session.closed = true;
+            }
+        }
+        outgoing
+    }
+
+    fn handle(
+        &mut self,
+        index: usize,
+        packet: Packet<'_>,
+        outgoing: &mut Vec<BrokerMessage>,
+        new_subscriptions: &mut Vec<String>,
+    ) {
+        let connected = self.sessions[index].client_id.is_some();
+        match packet {
+            Packet::Connect(connect) if !connected => {
+                let client_id = connect.client_id.to_string();
+                // 同じ ID の古い接続は切る (MQTT 3.1.1 3.1.4)
+                for (other, session) in self.sessions.iter_mut().enumerate() {
+                    if other != index && session.client_id.as_deref() == Some(&client_id) {
+                        session.closed = true;
+                    }
+                }
+                let session = &mut self.sessions[index];
+                session.keep_alive = (connect.keep_alive_secs > 0)
+                    .then(|| Duration::from_secs(u64::from(connect.keep_alive_secs)));
+                session.will = connect.will.map(|will| BrokerMessage {
+                    topic: will.topic.to_string(),
+                    payload: will.payload.to_vec(),
+                    qos: will.qos,
+                    retain: will.retain,
+                    dup: false,
+                    from: None,
+                });
+                session.client_id = Some(client_id.clone());
+                session.send(&Packet::ConnAck {
+                    session_present: false,
+                    return_code: 0,
+                });
+                self.lock().clients.push(client_id);
+            }
+            Packet::Publish(publish) if connected => {
+                let session = &mut self.sessions[index];
+                outgoing.push(BrokerMessage {
+                    topic: publish.topic.to_string(),
+                    payload: publish.payload.to_vec(),
+                    qos: publish.qos,
+                    retain: publish.retain,
+                    dup: publish.dup,
+                    from: session.client_id.clone(),
+                });
+                if let Some(packet_id) = publish.packet_id {
+                    let withhold = {
+                        let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+                        let withhold = shared.withheld_pubacks > 0;
+                        shared.withheld_pubacks = shared.withheld_pubacks.saturating_sub(1);
+                        withhold
+                    };
+                    if !withhold {
+                        session.send(&Packet::PubAck { packet_id });
+                    }
+                }
+            }
+            Packet::Subscribe { packet_id, filters } if connected => {
+                let session = &mut self.sessions[index];
+                let mut return_codes = Vec::new();
+                for (filter, requested) in filters.iter() {
+                    if filter.is_empty() {
+                        return_codes.push(SUBACK_FAILURE);
+                        continue;
+                    }
+                    let qos = if requested == 0 {
+                        QoS::AtMostOnce
+                    } else {
+                        QoS::AtLeastOnce
+                    };
+                    session.subscriptions.retain(|(f, _)| f != filter);
+                    session.subscriptions.push((filter.to_string(), qos));
+                    new_subscriptions.push(filter.to_string());
+                    return_codes.push(qos as u8);
+                }
+                session.send(&Packet::SubAck {
+                    packet_id,
+                    return_codes: &return_codes,
+                });
+            }
+            Packet::PubAck { .. } if connected => {}
+            Packet::PingReq if connected => self.sessions[index].send(&Packet::PingResp),
+            Packet::Disconnect if connected => {
+                let session = &mut self.sessions[index];
+                session.graceful = true;
+                session.closed = true;
+            }
+            // CONNECT 前のパケット、2 回目の CONNECT、クライアントが送らないはずのパケット
+            _ => self.sessions[index].closed = true,
+        }
+    }
+
+    fn route(&mut self, message: BrokerMessage) {
+        if message.retain {
+            if message.payload.is_empty() {
+                self.retained.remove(&message.topic);
+            } else {
+                self.retained.insert(message.topic.clone(), message.clone());
+            }
+        }
+        for session in &mut self.sessions {
+            if session.client_id.is_some() && !session.closed {
+                session.deliver(&message, false);
+            }
+        }
+        self.lock().messages.push(message);
+    }
+
+    /// 閉じたセッションを片付け、必要なら last-will を publish する。
+    fn reap(&mut self) {
+        let mut wills = Vec::new();
+        let mut index = 0;
+        while index < self.sessions.len() {
+            if !self.sessions[index].closed {
+                index += 1;
+                continue;
+            }
+            let session = self.sessions.remove(index);
+            let _ = session.stream.shutdown(std::net::Shutdown::Both);
+            if let Some(client_id) = &session.client_id {
+                let mut shared = self.lock();
+                if let Some(position) = shared.clients.iter().position(|c| c == client_id) {
+                    shared.clients.remove(position);
+                }
+            }
+            if !session.graceful {
+                wills.extend(session.will);
+            }
+        }
+        for will in wills {
+            self.route(will);
+        }
+    }
+}
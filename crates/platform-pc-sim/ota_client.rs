@@ -0,0 +1,430 @@
+//! OTA updater (`firmware/original-esp32-ota-bringup`) へイメージを送る HTTP クライアント。
+//!
+//! `scripts/flash-esp32.sh --ota` が `curl` で組み立てていたリクエストと同じものを送る。
+//!
+//! - `POST /switch`: 稼働中ファーム (wifi-climate の管理ポート) に updater への切り替えを頼む
+//! - `POST /ota`: アプリイメージ本体。`X-OTA-Token` で認可し、署名付きマニフェストがあれば
+//!   `X-OTA-Manifest` ヘッダ (108 バイトの 16 進表記) で一緒に送る
+//!
+//! 本文はチャンクごとに [`OtaEvent::Progress`] を通知しながら送る。接続できない・途中で
+//! 切れた・5xx が返ったときは [`RetryPolicy`] に従って最初から送り直す (切り替え直後の
+//! 再起動待ちもこの再試行で吸収する)。4xx は設定の誤りなので再試行しない。
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use platform_pc_sim::ota_client::{OtaClient, OtaTarget, RetryPolicy};
+//! use platform_pc_sim::ota_updater_mock::{MockUpdater, MockUpdaterConfig};
+//!
+//! let updater = MockUpdater::start(MockUpdaterConfig::new("secret")).unwrap();
+//! let target = OtaTarget::new("127.0.0.1", updater.port());
+//! let client = OtaClient::new("secret").with_retry(RetryPolicy::immediate(2));
+//!
+//! let response = client.upload(&target, b"\xE9firmware", None, |_| {}).unwrap();
+//! assert_eq!(response.status, 200);
+//! assert_eq!(updater.uploads()[0].image, b"\xE9firmware");
+//! ```
+
+use std::fmt;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+use ota_core::manifest::ImageManifest;
+
+/// updater と管理ポートの既定ポート。
+pub const DEFAULT_OTA_PORT: u16 = 8080;
+pub const TOKEN_HEADER: &str = "X-OTA-Token";
+pub const MANIFEST_HEADER: &str = "X-OTA-Manifest";
+
+/// 本文を書き込む単位 (進捗通知の粒度)。
+const CHUNK_SIZE: usize = 4096;
+const MAX_RESPONSE_LEN: usize = 4096;
+
+/// 送信先 (`<host>` または `<host>:<port>`)。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OtaTarget {
+    pub host: String,
+    pub port: u16,
+}
+
+impl OtaTarget {
+    pub fn new(host: &str, port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    /// ポートを省略すると [`DEFAULT_OTA_PORT`]。
+    pub fn parse(text: &str) -> Option<Self> {
+        let (host, port) = match text.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (text, DEFAULT_OTA_PORT),
+        };
+        if host.is_empty() || host.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(Self::new(host, port))
+    }
+}
+
+impl fmt::Display for OtaTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+/// 再試行の回数と間隔 (指数バックオフ)。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// 最初の試行を含む回数
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// 待たずに再試行する (テスト用)。
+    pub fn immediate(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            initial_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        }
+    }
+
+    /// `attempt` 回目 (1 始まり) が失敗した後の待ち時間。
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.saturating_sub(1).min(16);
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+impl Default for RetryPolicy {
+    /// ESP32 の再起動と WiFi 再接続 (数秒) を待てる長さ。
+    fn default() -> Self {
+        Self {
+            max_attempts: 6,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(8),
+        }
+    }
+}
+
+/// 送信中の出来事。CLI の進捗表示に使う。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OtaEvent {
+    Connecting {
+        attempt: u32,
+    },
+    Progress {
+        sent: usize,
+        total: usize,
+    },
+    /// `delay` 待ってから `attempt` 回目を始める
+    Retrying {
+        attempt: u32,
+        delay: Duration,
+        reason: String,
+    },
+}
+
+/// 2xx の応答。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OtaResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub enum OtaClientError {
+    Io(io::Error),
+    /// 2xx 以外の応答
+    Status {
+        status: u16,
+        body: String,
+    },
+    /// HTTP の応答として読めない
+    InvalidResponse,
+}
+
+impl OtaClientError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::Io(_) | Self::InvalidResponse => true,
+            Self::Status { status, .. } => *status >= 500,
+        }
+    }
+}
+
+impl From<io::Error> for OtaClientError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl fmt::Display for OtaClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "connection error: {error}"),
+            Self::Status { status, body } => write!(f, "HTTP {status}: {}", body.trim()),
+            Self::InvalidResponse => write!(f, "malformed HTTP response"),
+        }
+    }
+}
+
+impl std::error::Error for OtaClientError {}
+
+#[derive(Clone, Debug)]
+pub struct OtaClient {
+    token: String,
+    retry: RetryPolicy,
+    timeout: Duration,
+}
+
+impl OtaClient {
+    pub fn new(token: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            retry: RetryPolicy::default(),
+            // updater はフラッシュ消去の間ソケットを読まないことがある
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 接続・読み書きそれぞれのタイムアウト。
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 稼働中ファームに `POST /switch` を送り、updater へ切り替えさせる。
+    pub fn switch_to_updater(
+        &self,
+        target: &OtaTarget,
+        mut on_event: impl FnMut(OtaEvent),
+    ) -> Result<OtaResponse, OtaClientError> {
+        self.with_retries(&mut on_event, |client, _| {
+            client.send(target, "/switch", &[], &[], &mut |_| {})
+        })
+    }
+
+    /// `POST /ota` でイメージを送る。`manifest` は `X-OTA-Manifest` ヘッダになる。
+    pub fn upload(
+        &self,
+        target: &OtaTarget,
+        image: &[u8],
+        manifest: Option<&ImageManifest>,
+        mut on_event: impl FnMut(OtaEvent),
+    ) -> Result<OtaResponse, OtaClientError> {
+        let manifest_header = manifest.map(|m| hex_encode(&m.to_bytes()));
+        let extra: Vec<(&str, &str)> = manifest_header
+            .as_deref()
+            .map(|value| (MANIFEST_HEADER, value))
+            .into_iter()
+            .collect();
+        self.with_retries(&mut on_event, |client, on_event| {
+            client.send(target, "/ota", &extra, image, on_event)
+        })
+    }
+
+    fn with_retries(
+        &self,
+        on_event: &mut dyn FnMut(OtaEvent),
+        mut attempt_once: impl FnMut(
+            &Self,
+            &mut dyn FnMut(OtaEvent),
+        ) -> Result<OtaResponse, OtaClientError>,
+    ) -> Result<OtaResponse, OtaClientError> {
+        let mut attempt = 1;
+        loop {
+            on_event(OtaEvent::Connecting { attempt });
+            match attempt_once(self, on_event) {
+                Err(error) if error.is_retryable() && attempt < self.retry.max_attempts => {
+                    let delay = self.retry.delay_after(attempt);
+                    attempt += 1;
+                    on_event(OtaEvent::Retrying {
+                        attempt,
+                        delay,
+                        reason: error.to_string(),
+                    });
+                    thread::sleep(delay);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn send(
+        &self,
+        target: &OtaTarget,
+        path: &str,
+        extra_headers: &[(&str, &str)],
+        body: &[u8],
+        on_event: &mut dyn FnMut(OtaEvent),
+    ) -> Result<OtaResponse, OtaClientError> {
+        let mut stream = self.connect(target)?;
+        let mut request = format!(
+            "POST {path} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/octet-stream\r\n\
+             {TOKEN_HEADER}: {}\r\nContent-Length: {}\r\n",
+            target.host,
+            self.token,
+            body.len()
+        );
+        for (name, value) in extra_headers {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+        request.push_str("\r\n");
+
+        let sent = stream.write_all(request.as_bytes()).and_then(|()| {
+            let mut sent = 0;
+            for chunk in body.chunks(CHUNK_SIZE) {
+                stream.write_all(chunk)?;
+                sent += chunk.len();
+                on_event(OtaEvent::Progress {
+                    sent,
+                    total: body.len(),
+                });
+            }
+            stream.flush()
+        });
+        // updater は本文を読み終える前に 401 / 413 を返して切ることがあるので、
+        // 書き込みに失敗しても応答が届いていればそちらを優先する
+        let response = read_response(&mut stream);
+        match (sent, response) {
+            (_, Ok(response)) if (200..300).contains(&response.status) => Ok(response),
+            (_, Ok(response)) => Err(OtaClientError::Status {
+                status: response.status,
+                body: response.body,
+            }),
+            (Err(error), Err(_)) => Err(OtaClientError::Io(error)),
+            (Ok(()), Err(error)) => Err(error),
+        }
+    }
+
+    fn connect(&self, target: &OtaTarget) -> io::Result<TcpStream> {
+        let mut last_error = io::Error::new(ErrorKind::NotFound, "host did not resolve");
+        for addr in (target.host.as_str(), target.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.timeout))?;
+                    stream.set_write_timeout(Some(self.timeout))?;
+                    return Ok(stream);
+                }
+                Err(error) => last_error = error,
+            }
+        }
+        Err(last_error)
+    }
+}
+
+fn read_response(stream: &mut TcpStream) -> Result<OtaResponse, OtaClientError> {
+    let mut raw = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                raw.extend_from_slice(&buf[..n]);
+                if raw.len() > MAX_RESPONSE_LEN {
+                    return Err(OtaClientError::InvalidResponse);
+                }
+            }
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) if raw.is_empty() => return Err(OtaClientError::Io(error)),
+            // 応答を書いた直後の RST。届いた分で判断する
+            Err(_) => break,
+        }
+    }
+    if raw.is_empty() {
+        return Err(OtaClientError::Io(io::Error::new(
+            ErrorKind::UnexpectedEof,
+            "connection closed without a response",
+        )));
+    }
+    parse_response(&raw).ok_or(OtaClientError::InvalidResponse)
+}
+
+fn parse_response(raw: &[u8]) -> Option<OtaResponse> {
+    let text = String::from_utf8_lossy(raw);
+    let (head, body) = text.split_once("\r\n\r\n")?;
+    let mut status_line = head.lines().next()?.split_ascii_whitespace();
+    if !status_line.next()?.starts_with("HTTP/1.") {
+        return None;
+    }
+    let status = status_line.next()?.parse().ok()?;
+    Some(OtaResponse {
+        status,
+        body: body.to_string(),
+    })
+}
+
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// 16 進文字列 (大文字小文字どちらも可) をバイト列にする。
+pub fn hex_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(text.get(at..at + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_default_to_the_updater_port() {
+        assert_eq!(
+            OtaTarget::parse("192.168.1.42"),
+            Some(OtaTarget::new("192.168.1.42", 8080))
+        );
+        assert_eq!(
+            OtaTarget::parse("esp32.local:9000"),
+            Some(OtaTarget::new("esp32.local", 9000))
+        );
+        assert_eq!(OtaTarget::parse("192.168.1.42:http"), None);
+        assert_eq!(OtaTarget::parse(":8080"), None);
+    }
+
+    #[test]
+    fn retry_delay_backs_off_up_to_the_cap() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay_after(1), Duration::from_secs(1));
+        assert_eq!(policy.delay_after(3), Duration::from_secs(4));
+        assert_eq!(policy.delay_after(10), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn responses_and_hex_round_trip() {
+        let response = parse_response(
+            b"HTTP/1.0 401 Unauthorized\r\nContent-Length: 13\r\n\r\nUnauthorized\n",
+        )
+        .unwrap();
+        assert_eq!(response.status, 401);
+        assert_eq!(response.body, "Unauthorized\n");
+        assert_eq!(parse_response(b"SSH-2.0-OpenSSH\r\n\r\n"), None);
+
+        assert_eq!(hex_encode(&[0x00, 0xAB, 0x7F]), "00ab7f");
+        assert_eq!(hex_decode("00AB7f"), Some(vec![0x00, 0xAB, 0x7F]));
+        assert_eq!(hex_decode("abc"), None);
+        assert_eq!(hex_decode("zz"), None);
+    }
+}
@@ -0,0 +1,435 @@
+//! テスト用の OTA updater。
+//!
+//! `firmware/original-esp32-ota-bringup` の代役。`POST /ota` のヘッダ検査には実機と同じ
+//! `ota_http::read_ota_request` を使い、応答も実機と同じ文面を返す。wifi-climate の管理ポートの
+//! `POST /switch` も受け付けるので、切り替えから書き込みまでを 1 つのポートで再現できる。
+//!
+//! [`MockUpdaterConfig::with_public_key`] を指定すると `X-OTA-Manifest` を必須にし、
+//! `ota_core::boot::BootControl` と `ota_core::sim::SimFlash` に書き込む。署名・サイズ・
+//! バージョン (anti-rollback)・SHA-256 を検査し、受け入れたイメージは起動・確定まで進める。
+//!
+//! [`MockUpdater::drop_next_uploads`] / [`MockUpdater::fail_next_uploads`] で転送途中の切断や
+//! 書き込み失敗を再現できる。
+
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use ota_core::boot::{BootControl, Slot};
+use ota_core::manifest::{ImageManifest, PublicKey};
+use ota_core::partition::PartitionTable;
+use ota_core::sim::SimFlash;
+use ota_core::UploadError;
+use ota_http::{read_ota_request, OtaRequestError, RequestLimits};
+
+use crate::ota_client::{hex_decode, MANIFEST_HEADER, TOKEN_HEADER};
+
+/// 実機と同じ読み込み単位。
+const OTA_CHUNK_SIZE: usize = 512;
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct MockUpdaterConfig {
+    token: String,
+    limits: RequestLimits,
+    verify: Option<(PublicKey, u32)>,
+}
+
+impl MockUpdaterConfig {
+    pub fn new(token: &str) -> Self {
+        Self {
+            token: token.to_string(),
+            limits: RequestLimits::default(),
+            verify: None,
+        }
+    }
+
+    /// マニフェストの署名を `key` で検証する。`current_version` は稼働中のバージョン。
+    pub fn with_public_key(mut self, key: PublicKey, current_version: u32) -> Self {
+        self.verify = Some((key, current_version));
+        self
+    }
+
+    pub fn with_max_payload(mut self, max_payload_size: usize) -> Self {
+        self.limits.max_payload_size = max_payload_size;
+        self
+    }
+}
+
+/// 受け入れたイメージ。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReceivedUpload {
+    pub image: Vec<u8>,
+    pub manifest: Option<ImageManifest>,
+}
+
+#[derive(Default)]
+struct Shared {
+    uploads: Vec<ReceivedUpload>,
+    switch_requests: usize,
+    statuses: Vec<u16>,
+    /// (残り回数, 切断するまでに読むバイト数)
+    drop_uploads: (usize, usize),
+    fail_uploads: usize,
+}
+
+/// バックグラウンドスレッドで動く updater。drop すると停止する。
+pub struct MockUpdater {
+    addr: SocketAddr,
+    shared: Arc<Mutex<Shared>>,
+    boot: Arc<Mutex<Option<BootControl<SimFlash>>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockUpdater {
+    /// `127.0.0.1` の空きポートで起動する。
+    pub fn start(config: MockUpdaterConfig) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let boot = config.verify.map(|(_, current_version)| {
+            BootControl::open(
+                SimFlash::new(4096, 1024),
+                PartitionTable::ESP32_4MB_OTA,
+                Slot::A,
+                current_version,
+            )
+            .expect("4 MB simulated flash fits the ESP32 OTA layout")
+        });
+        let boot = Arc::new(Mutex::new(boot));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let shared = Arc::clone(&shared);
+            let boot = Arc::clone(&boot);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let updater = Connection {
+                                config: &config,
+                                shared: &shared,
+                                boot: &boot,
+                            };
+                            updater.handle(stream);
+                        }
+                        Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                            thread::sleep(Duration::from_millis(2));
+                        }
+                        Err(_) => break,
+                    }
+                }
+            })
+        };
+        Ok(Self {
+            addr,
+            shared,
+            boot,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// 受け入れたイメージ (古い順)。
+    pub fn uploads(&self) -> Vec<ReceivedUpload> {
+        self.lock().uploads.clone()
+    }
+
+    /// 受け付けた `POST /switch` の数 (トークン不一致は数えない)。
+    pub fn switch_requests(&self) -> usize {
+        self.lock().switch_requests
+    }
+
+    /// 返した HTTP ステータス (古い順)。途中で切断した接続は含まない。
+    pub fn statuses(&self) -> Vec<u16> {
+        self.lock().statuses.clone()
+    }
+
+    /// 署名検証ありのとき、稼働中のファームウェアのバージョン。
+    pub fn active_version(&self) -> Option<u32> {
+        let boot = self.boot.lock().unwrap_or_else(|e| e.into_inner());
+        boot.as_ref()
+            .map(|control| control.record().active_version())
+    }
+
+    /// 次の `count` 回の `POST /ota` で、本文を `after_bytes` バイト読んだところで切断する。
+    pub fn drop_next_uploads(&self, count: usize, after_bytes: usize) {
+        self.lock().drop_uploads = (count, after_bytes);
+    }
+
+    /// 次の `count` 回の `POST /ota` を、本文を読み終えてから 500 で失敗させる。
+    pub fn fail_next_uploads(&self, count: usize) {
+        self.lock().fail_uploads = count;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockUpdater {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// 実機 (`original-esp32-ota-bringup`) と同じ応答。
+fn error_response(error: &OtaRequestError) -> (u16, &'static [u8]) {
+    match error {
+        OtaRequestError::Unauthorized => (
+            401,
+            b"HTTP/1.0 401 Unauthorized\r\nContent-Length: 13\r\n\r\nUnauthorized\n",
+        ),
+        OtaRequestError::HeaderTooLarge => (
+            431,
+            b"HTTP/1.0 431 Request Header Fields Too Large\r\nContent-Length: 17\r\n\r\nHeader Too Large\n",
+        ),
+        OtaRequestError::LengthRequired => (
+            411,
+            b"HTTP/1.0 411 Length Required\r\nContent-Length: 16\r\n\r\nLength Required\n",
+        ),
+        OtaRequestError::PayloadTooLarge(_) => (
+            413,
+            b"HTTP/1.0 413 Payload Too Large\r\nContent-Length: 18\r\n\r\nPayload Too Large\n",
+        ),
+        OtaRequestError::BadRequest | OtaRequestError::Io(_) => (
+            400,
+            b"HTTP/1.0 400 Bad Request\r\nContent-Length: 12\r\n\r\nBad Request\n",
+        ),
+    }
+}
+
+const OTA_OK: &[u8] = b"HTTP/1.0 200 OK\r\nContent-Length: 7\r\n\r\nOTA OK\n";
+const OTA_FAILED: &[u8] =
+    b"HTTP/1.0 500 Internal Server Error\r\nContent-Length: 11\r\n\r\nOTA FAILED\n";
+const MANIFEST_REJECTED: &[u8] =
+    b"HTTP/1.0 403 Forbidden\r\nContent-Length: 18\r\n\r\nManifest Rejected\n";
+const NOT_NEWER: &[u8] = b"HTTP/1.0 409 Conflict\r\nContent-Length: 18\r\n\r\nVersion Not Newer\n";
+/// wifi-climate の管理ポート (`climate_http::AdminResponse`) と同じ応答。
+const SWITCHING: &[u8] = b"HTTP/1.0 200 OK\r\nContent-Length: 20\r\n\r\nswitching to updater";
+const SWITCH_UNAUTHORIZED: &[u8] =
+    b"HTTP/1.0 401 Unauthorized\r\nContent-Length: 12\r\n\r\nunauthorized";
+
+struct Connection<'a> {
+    config: &'a MockUpdaterConfig,
+    shared: &'a Mutex<Shared>,
+    boot: &'a Mutex<Option<BootControl<SimFlash>>>,
+}
+
+impl Connection<'_> {
+    fn handle(&self, stream: TcpStream) {
+        let _ = stream.set_nonblocking(false);
+        let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+        let Ok(writer) = stream.try_clone() else {
+            return;
+        };
+        let mut reader = BufReader::new(stream);
+        let is_switch = reader
+            .fill_buf()
+            .map(|buf| buf.starts_with(b"POST /switch "))
+            .unwrap_or(false);
+        let response = if is_switch {
+            self.switch(&mut reader)
+        } else {
+            self.ota(&mut reader)
+        };
+        if let Some((status, bytes)) = response {
+            self.lock().statuses.push(status);
+            respond(writer, &mut reader, bytes);
+        }
+    }
+
+    fn switch(&self, reader: &mut BufReader<TcpStream>) -> Option<(u16, &'static [u8])> {
+        let mut authorized = false;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            let trimmed = line.trim_end();
+            if trimmed.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = trimmed.split_once(':') {
+                if name.trim().eq_ignore_ascii_case(TOKEN_HEADER)
+                    && value.trim() == self.config.token
+                {
+                    authorized = true;
+                }
+            }
+        }
+        if authorized {
+            self.lock().switch_requests += 1;
+            Some((200, SWITCHING))
+        } else {
+            Some((401, SWITCH_UNAUTHORIZED))
+        }
+    }
+
+    fn ota(&self, reader: &mut BufReader<TcpStream>) -> Option<(u16, &'static [u8])> {
+        let mut recorder = HeaderRecorder {
+            inner: reader,
+            header: Vec::new(),
+        };
+        let content_len =
+            match read_ota_request(&mut recorder, &self.config.token, self.config.limits) {
+                Ok(len) => len,
+                Err(error) => return Some(error_response(&error)),
+            };
+        let manifest = match manifest_header(&recorder.header) {
+            Ok(manifest) => manifest,
+            Err(()) => return Some((403, MANIFEST_REJECTED)),
+        };
+
+        let drop_after = {
+            let mut shared = self.lock();
+            let (count, after) = shared.drop_uploads;
+            (count > 0).then(|| {
+                shared.drop_uploads.0 -= 1;
+                after
+            })
+        };
+
+        let mut boot = self.boot.lock().unwrap_or_else(|e| e.into_inner());
+        let mut session = match (boot.as_mut(), manifest) {
+            (Some(control), Some(manifest)) => {
+                let (key, _) = self.config.verify.expect("boot control implies a key");
+                match control.begin_update(manifest, &key) {
+                    Ok(session) => Some(session),
+                    Err(UploadError::Downgrade { .. }) => return Some((409, NOT_NEWER)),
+                    Err(_) => return Some((403, MANIFEST_REJECTED)),
+                }
+            }
+            (Some(_), None) => return Some((403, MANIFEST_REJECTED)),
+            (None, _) => None,
+        };
+        if session
+            .as_ref()
+            .is_some_and(|s| s.manifest().size as usize != content_len)
+        {
+            return Some((403, MANIFEST_REJECTED));
+        }
+
+        let mut image = Vec::with_capacity(content_len);
+        let mut chunk = [0u8; OTA_CHUNK_SIZE];
+        while image.len() < content_len {
+            if drop_after.is_some_and(|after| image.len() >= after) {
+                // 応答を返さずに切る (WiFi 切断や再起動の再現)
+                return None;
+            }
+            let want = (content_len - image.len()).min(OTA_CHUNK_SIZE);
+            let read = match reader.read(&mut chunk[..want]) {
+                Ok(0) | Err(_) => return None,
+                Ok(read) => read,
+            };
+            if let (Some(session), Some(control)) = (session.as_mut(), boot.as_mut()) {
+                let offset = image.len() as u32;
+                if session
+                    .write_chunk(control.storage_mut(), offset, &chunk[..read])
+                    .is_err()
+                {
+                    return Some((500, OTA_FAILED));
+                }
+            }
+            image.extend_from_slice(&chunk[..read]);
+        }
+
+        {
+            let mut shared = self.lock();
+            if shared.fail_uploads > 0 {
+                shared.fail_uploads -= 1;
+                return Some((500, OTA_FAILED));
+            }
+        }
+        if let (Some(session), Some(control)) = (session.as_ref(), boot.as_mut()) {
+            // 実機なら再起動して新しいスロットで試行起動し、動作確認後に確定する
+            let Ok(verified) = control.finish_update(session) else {
+                return Some((500, OTA_FAILED));
+            };
+            if control.stage(&verified).is_err()
+                || control.boot(3).is_err()
+                || control.mark_valid().is_err()
+            {
+                return Some((500, OTA_FAILED));
+            }
+        }
+        self.lock().uploads.push(ReceivedUpload { image, manifest });
+        Some((200, OTA_OK))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Shared> {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// `X-OTA-Manifest` を読む。ヘッダが無ければ `Ok(None)`、読めなければ `Err`。
+fn manifest_header(header: &[u8]) -> Result<Option<ImageManifest>, ()> {
+    let text = String::from_utf8_lossy(header);
+    let Some(value) = text.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case(MANIFEST_HEADER)
+            .then(|| value.trim().to_string())
+    }) else {
+        return Ok(None);
+    };
+    let bytes = hex_decode(&value).ok_or(())?;
+    ImageManifest::from_bytes(&bytes).map(Some).map_err(|_| ())
+}
+
+/// 応答を書いて送信側を閉じ、残りの本文を読み捨てる。
+///
+/// 未読のまま close すると RST になり、クライアントが応答を読む前に接続が壊れるため。
+fn respond(mut writer: TcpStream, reader: &mut BufReader<TcpStream>, bytes: &[u8]) {
+    let _ = writer.write_all(bytes);
+    let _ = writer.shutdown(Shutdown::Write);
+    let _ = reader
+        .get_ref()
+        .set_read_timeout(Some(Duration::from_millis(200)));
+    let _ = io::copy(reader, &mut io::sink());
+}
+
+/// `read_ota_request` が消費したヘッダのバイト列を記録する `BufRead`。
+struct HeaderRecorder<'a, R> {
+    inner: &'a mut R,
+    header: Vec<u8>,
+}
+
+impl<R: BufRead> Read for HeaderRecorder<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.header.extend_from_slice(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for HeaderRecorder<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        if let Ok(buf) = self.inner.fill_buf() {
+            let amount = amount.min(buf.len());
+            self.header.extend_from_slice(&buf[..amount]);
+        }
+        self.inner.consume(amount);
+    }
+}
@@ -0,0 +1,349 @@
+//! Build, sign and upload OTA images to the ESP32 updater.
+//!
+//! Replaces the hand-written `curl` calls of `scripts/flash-esp32.sh --ota`:
+//!
+//! - `ota-upload keygen <secret.key>` — create an Ed25519 signing key (hex) and
+//!   print the public key to embed in the updater
+//! - `ota-upload inspect <image.bin>` — run the bootloader image checks and
+//!   print the app descriptor, MD5 and CRC-32
+//! - `ota-upload package <firmware-dir|image.bin> --version <n> --key <secret.key>
+//!   [--chip esp32] [--out <image.bin>]` — build the firmware with
+//!   `cargo build --release` + `espflash save-image` (or take an existing
+//!   `.bin`), check it and write `<out>` plus a signed `<out>.manifest`
+//! - `ota-upload send <host[:port]> <image.bin> [--manifest <file>]
+//!   [--switch <host[:port]>] [--retries <n>]` — optionally `POST /switch` to
+//!   the running firmware, then stream the image to `POST /ota` with progress
+//!   and retries. `<image.bin>.manifest` is sent automatically when present.
+//!
+//! The shared token is read from `OTA_AUTH_TOKEN`, like the flash script.
+
+use std::env;
+use std::fs;
+use std::io::{self, Read as _, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use ota_core::manifest::{self, ImageManifest, SecretKey, MANIFEST_LEN};
+use ota_core::partition::PartitionTable;
+use platform_pc_sim::cli_args::Args;
+use platform_pc_sim::esp_image::{EspChip, EspImage};
+use platform_pc_sim::ota_client::{
+    hex_decode, hex_encode, OtaClient, OtaEvent, OtaTarget, RetryPolicy,
+};
+
+const TOKEN_ENV: &str = "OTA_AUTH_TOKEN";
+const USAGE: &str = "usage:
+  ota-upload keygen <secret.key>
+  ota-upload inspect <image.bin> [--chip <chip>]
+  ota-upload package <firmware-dir|image.bin> --version <n> --key <secret.key> [--chip <chip>] [--out <image.bin>]
+  ota-upload send <host[:port]> <image.bin> [--manifest <file>] [--switch <host[:port]>] [--retries <n>]";
+
+type CliResult<T = ()> = Result<T, String>;
+
+fn main() {
+    let mut raw = env::args().skip(1);
+    let command = raw.next();
+    let (run, options): (fn(&Args) -> CliResult, &[&str]) = match command.as_deref() {
+        Some("keygen") => (keygen, &[]),
+        Some("inspect") => (inspect, &["chip"]),
+        Some("package") => (package, &["version", "key", "chip", "out"]),
+        Some("send") => (send, &["manifest", "switch", "retries"]),
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(1);
+        }
+    };
+    if let Err(message) = Args::parse(raw, options, USAGE).and_then(|args| run(&args)) {
+        eprintln!("{message}");
+        process::exit(1);
+    }
+}
+
+fn positional<'a>(args: &'a Args, index: usize, what: &str) -> CliResult<&'a str> {
+    args.positional(index)
+        .ok_or(format!("missing {what}\n{USAGE}"))
+}
+
+fn chip(args: &Args) -> CliResult<EspChip> {
+    let name = args.option("chip").unwrap_or("esp32");
+    EspChip::from_name(name).ok_or(format!("unknown chip {name:?}"))
+}
+
+fn keygen(args: &Args) -> CliResult {
+    let path = Path::new(positional(args, 0, "key file")?);
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+    let mut secret: SecretKey = [0; 32];
+    fs::File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(&mut secret))
+        .map_err(|e| format!("cannot read /dev/urandom: {e}"))?;
+    write_secret(path, &secret).map_err(|e| format!("cannot write {}: {e}", path.display()))?;
+    println!("secret key: {} (keep it off the device)", path.display());
+    println!("public key: {}", hex_encode(&manifest::public_key(&secret)));
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_secret(path: &Path, secret: &SecretKey) -> io::Result<()> {
+    use std::os::unix::fs::OpenOptionsExt as _;
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    writeln!(file, "{}", hex_encode(secret))
+}
+
+#[cfg(not(unix))]
+fn write_secret(path: &Path, secret: &SecretKey) -> io::Result<()> {
+    fs::write(path, format!("{}\n", hex_encode(secret)))
+}
+
+/// Accepts 32 raw bytes or 64 hex characters.
+fn read_secret(path: &Path) -> CliResult<SecretKey> {
+    let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let raw = if bytes.len() == 32 {
+        bytes
+    } else {
+        hex_decode(&String::from_utf8_lossy(&bytes)).unwrap_or_default()
+    };
+    raw.try_into()
+        .map_err(|_| format!("{} is not a 32-byte Ed25519 secret key", path.display()))
+}
+
+fn inspect(args: &Args) -> CliResult {
+    let path = Path::new(positional(args, 0, "image path")?);
+    let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    check_image(&bytes, chip(args)?)?;
+    Ok(())
+}
+
+/// Runs the bootloader checks plus chip / slot size checks and prints a summary.
+fn check_image(bytes: &[u8], chip: EspChip) -> CliResult<EspImage> {
+    let image = EspImage::parse(bytes).map_err(|e| format!("image check failed: {e}"))?;
+    if image.chip != chip {
+        return Err(format!(
+            "image targets {} (chip id {}), expected {}",
+            image.chip.name(),
+            image.chip.id(),
+            chip.name()
+        ));
+    }
+    let slot_size = PartitionTable::ESP32_4MB_OTA.slots[0].size as usize;
+    if bytes.len() > slot_size {
+        return Err(format!(
+            "image is {} bytes but an OTA slot holds {slot_size}",
+            bytes.len()
+        ));
+    }
+    println!("chip:      {}", image.chip.name());
+    println!("entry:     0x{:08x}", image.entry);
+    println!(
+        "segments:  {} ({})",
+        image.segments.len(),
+        image
+            .segments
+            .iter()
+            .map(|s| format!("0x{:08x}+{}", s.load_addr, s.len))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    match &image.app {
+        Some(app) => {
+            println!("project:   {} {}", app.project_name, app.version);
+            if !app.idf_version.is_empty() {
+                println!("esp-idf:   {}", app.idf_version);
+            }
+        }
+        None => println!("project:   (no esp_app_desc_t)"),
+    }
+    println!(
+        "size:      {} bytes ({:.1}% of slot)",
+        bytes.len(),
+        bytes.len() as f64 * 100.0 / slot_size as f64
+    );
+    println!("md5:       {:x}", image.md5);
+    println!("crc32:     0x{:08x}", image.crc32);
+    Ok(image)
+}
+
+fn package(args: &Args) -> CliResult {
+    let input = PathBuf::from(positional(args, 0, "firmware directory or image")?);
+    let version: u32 = args
+        .option("version")
+        .ok_or("--version is required (monotonic firmware version)")?
+        .parse()
+        .map_err(|_| "--version must be a non-negative integer".to_string())?;
+    let secret = read_secret(Path::new(args.option("key").ok_or("--key is required")?))?;
+    let chip = chip(args)?;
+
+    let bytes = if input.is_dir() {
+        build_firmware(&input, chip)?
+    } else {
+        fs::read(&input).map_err(|e| format!("cannot read {}: {e}", input.display()))?
+    };
+    check_image(&bytes, chip)?;
+
+    let out = match args.option("out") {
+        Some(out) => PathBuf::from(out),
+        None if input.is_dir() => input.join("ota-image.bin"),
+        None => input.with_extension("ota.bin"),
+    };
+    let manifest = ImageManifest::sign(&bytes, version, &secret);
+    let manifest_path = manifest_path(&out);
+    if out != input {
+        fs::write(&out, &bytes).map_err(|e| format!("cannot write {}: {e}", out.display()))?;
+    }
+    fs::write(&manifest_path, manifest.to_bytes())
+        .map_err(|e| format!("cannot write {}: {e}", manifest_path.display()))?;
+    println!("version:   {version}");
+    println!("sha256:    {}", hex_encode(&manifest.sha256));
+    println!("image:     {}", out.display());
+    println!("manifest:  {}", manifest_path.display());
+    Ok(())
+}
+
+/// `cargo build --release` followed by `espflash save-image`, as the flash
+/// script does. Falls back to `--ignore-app-descriptor` for esp-hal apps.
+fn build_firmware(dir: &Path, chip: EspChip) -> CliResult<Vec<u8>> {
+    println!("building {} ...", dir.display());
+    run(Command::new("cargo")
+        .args(["build", "--release"])
+        .current_dir(dir))?;
+
+    let manifest = fs::read_to_string(dir.join("Cargo.toml"))
+        .map_err(|e| format!("cannot read {}/Cargo.toml: {e}", dir.display()))?;
+    let name = toml_string(&manifest, "name").ok_or("Cargo.toml has no package name")?;
+    let target = fs::read_to_string(dir.join(".cargo/config.toml"))
+        .ok()
+        .and_then(|config| toml_string(&config, "target"));
+    let mut elf = dir.join("target");
+    if let Some(target) = target {
+        elf.push(target);
+    }
+    elf.push("release");
+    elf.push(name);
+
+    let bin = env::temp_dir().join(format!("ota-upload-{}.bin", process::id()));
+    let save = |extra: &[&str]| {
+        run(Command::new("espflash")
+            .args(["save-image", "--chip", chip.name()])
+            .args(extra)
+            .arg(&elf)
+            .arg(&bin))
+    };
+    save(&[]).or_else(|_| save(&["--ignore-app-descriptor"]))?;
+    let bytes = fs::read(&bin).map_err(|e| format!("cannot read {}: {e}", bin.display()));
+    let _ = fs::remove_file(&bin);
+    bytes
+}
+
+fn run(command: &mut Command) -> CliResult {
+    let status = command
+        .status()
+        .map_err(|e| format!("cannot run {:?}: {e}", command.get_program()))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{:?} failed ({status})", command.get_program()))
+    }
+}
+
+/// First `key = "value"` line of a TOML file (enough for `name` / `target`).
+fn toml_string(text: &str, key: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let (name, value) = line.split_once('=')?;
+        (name.trim() == key).then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn manifest_path(image: &Path) -> PathBuf {
+    let mut path = image.as_os_str().to_owned();
+    path.push(".manifest");
+    PathBuf::from(path)
+}
+
+fn send(args: &Args) -> CliResult {
+    let target = parse_target(positional(args, 0, "updater address")?)?;
+    let path = Path::new(positional(args, 1, "image path")?);
+    let token = env::var(TOKEN_ENV).map_err(|_| format!("{TOKEN_ENV} is not set"))?;
+    if token.contains(['\r', '\n']) {
+        return Err(format!("{TOKEN_ENV} must not contain newlines"));
+    }
+    let image = fs::read(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+
+    let manifest = match args.option("manifest") {
+        Some(file) => Some(read_manifest(Path::new(file))?),
+        None if manifest_path(path).exists() => Some(read_manifest(&manifest_path(path))?),
+        None => None,
+    };
+    if let Some(manifest) = &manifest {
+        if manifest.sha256 != manifest::sha256(&image) {
+            return Err("manifest does not match the image (re-run package)".to_string());
+        }
+    }
+
+    let mut retry = RetryPolicy::default();
+    if let Some(retries) = args.option("retries") {
+        retry.max_attempts = retries
+            .parse::<u32>()
+            .map_err(|_| "--retries must be a number".to_string())?
+            + 1;
+    }
+    let client = OtaClient::new(&token).with_retry(retry);
+
+    if let Some(admin) = args.option("switch") {
+        let admin = parse_target(admin)?;
+        println!("[ota] switching {admin} to the updater");
+        let response = client
+            .switch_to_updater(&admin, print_event)
+            .map_err(|e| format!("switch failed: {e}"))?;
+        println!("[ota] {}", response.body.trim());
+    }
+
+    match &manifest {
+        Some(m) => println!(
+            "[ota] sending {} bytes (signed, version {})",
+            image.len(),
+            m.version
+        ),
+        None => println!("[ota] sending {} bytes (unsigned)", image.len()),
+    }
+    let response = client
+        .upload(&target, &image, manifest.as_ref(), print_event)
+        .map_err(|e| format!("\n[ota] upload failed: {e}"))?;
+    println!("\n[ota] {} — the device is rebooting", response.body.trim());
+    Ok(())
+}
+
+fn parse_target(text: &str) -> CliResult<OtaTarget> {
+    OtaTarget::parse(text).ok_or(format!("invalid address {text:?} (expected host[:port])"))
+}
+
+fn read_manifest(path: &Path) -> CliResult<ImageManifest> {
+    let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    if bytes.len() != MANIFEST_LEN {
+        return Err(format!("{} is not an image manifest", path.display()));
+    }
+    ImageManifest::from_bytes(&bytes).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn print_event(event: OtaEvent) {
+    match event {
+        OtaEvent::Connecting { attempt } if attempt > 1 => {
+            println!("[ota] attempt {attempt}");
+        }
+        OtaEvent::Connecting { .. } => {}
+        OtaEvent::Progress { sent, total } => {
+            print!(
+                "\r[ota] {:3}% ({sent}/{total} bytes)",
+                sent * 100 / total.max(1)
+            );
+            let _ = io::stdout().flush();
+        }
+        OtaEvent::Retrying { delay, reason, .. } => {
+            println!("\n[ota] {reason}; retrying in {:.1}s", delay.as_secs_f32());
+        }
+    }
+}
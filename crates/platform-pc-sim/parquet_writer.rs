@@ -0,0 +1,394 @@
+//! 最小限の Apache Parquet 書き出し。
+//!
+//! 外部 crate 無しで、シミュレーション履歴を pandas / DuckDB / Polars などで開ける
+//! ファイルにするためのもの。対応しているのは次の組み合わせだけ:
+//!
+//! - 行グループ 1 つ、列ごとに DATA_PAGE (v1) 1 枚、PLAIN エンコーディング、無圧縮
+//! - 列の型は [`ColumnData`] の 4 種類 (INT64 / TIMESTAMP_MILLIS / 省略可能な DOUBLE /
+//!   省略可能な UTF-8 文字列)
+//! - メタデータは Thrift compact protocol で書く。統計情報は付けない
+//!
+//! # Examples
+//!
+//! ```
+//! use platform_pc_sim::parquet_writer::{write_parquet, Column, ColumnData};
+//!
+//! let columns = [
+//!     Column::new("timestamp_ms", ColumnData::TimestampMillis(vec![1_000, 2_000])),
+//!     Column::new("temperature_c", ColumnData::OptionalDouble(vec![Some(24.5), None])),
+//! ];
+//! let mut file = Vec::new();
+//! write_parquet(&mut file, &columns).unwrap();
+//! assert!(file.starts_with(b"PAR1") && file.ends_with(b"PAR1"));
+//! ```
+
+use std::io::{self, Write};
+
+const MAGIC: &[u8; 4] = b"PAR1";
+
+// parquet.thrift の列挙値
+const TYPE_INT64: i32 = 2;
+const TYPE_DOUBLE: i32 = 5;
+const TYPE_BYTE_ARRAY: i32 = 6;
+const REPETITION_REQUIRED: i32 = 0;
+const REPETITION_OPTIONAL: i32 = 1;
+const CONVERTED_UTF8: i32 = 0;
+const CONVERTED_TIMESTAMP_MILLIS: i32 = 9;
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+const CODEC_UNCOMPRESSED: i32 = 0;
+const PAGE_DATA: i32 = 0;
+
+/// 1 列分の値。全列の長さ (= 行数) は揃っている必要がある。
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnData {
+    Int64(Vec<i64>),
+    /// UNIX ミリ秒。`TIMESTAMP_MILLIS` として書く
+    TimestampMillis(Vec<i64>),
+    OptionalDouble(Vec<Option<f64>>),
+    OptionalText(Vec<Option<String>>),
+}
+
+impl ColumnData {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Int64(values) | Self::TimestampMillis(values) => values.len(),
+            Self::OptionalDouble(values) => values.len(),
+            Self::OptionalText(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn physical_type(&self) -> i32 {
+        match self {
+            Self::Int64(_) | Self::TimestampMillis(_) => TYPE_INT64,
+            Self::OptionalDouble(_) => TYPE_DOUBLE,
+            Self::OptionalText(_) => TYPE_BYTE_ARRAY,
+        }
+    }
+
+    fn converted_type(&self) -> Option<i32> {
+        match self {
+            Self::TimestampMillis(_) => Some(CONVERTED_TIMESTAMP_MILLIS),
+            Self::OptionalText(_) => Some(CONVERTED_UTF8),
+            _ => None,
+        }
+    }
+
+    fn is_optional(&self) -> bool {
+        matches!(self, Self::OptionalDouble(_) | Self::OptionalText(_))
+    }
+
+    /// 定義レベル (省略可能な列のみ) と PLAIN エンコードした値を並べたページ本体。
+    fn page_body(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        match self {
+            Self::Int64(values) | Self::TimestampMillis(values) => {
+                for value in values {
+                    body.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            Self::OptionalDouble(values) => {
+                write_definition_levels(&mut body, values.iter().map(Option::is_some));
+                for value in values.iter().flatten() {
+                    body.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            Self::OptionalText(values) => {
+                write_definition_levels(&mut body, values.iter().map(Option::is_some));
+                for value in values.iter().flatten() {
+                    body.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    body.extend_from_slice(value.as_bytes());
+                }
+            }
+        }
+        body
+    }
+}
+
+/// 名前付きの列。
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub data: ColumnData,
+}
+
+impl Column {
+    pub fn new(name: impl Into<String>, data: ColumnData) -> Self {
+        Self {
+            name: name.into(),
+            data,
+        }
+    }
+}
+
+/// 列の並びを 1 つの Parquet ファイルとして書き出す。
+///
+/// 列の長さが揃っていない場合は `InvalidInput` を返す。
+pub fn write_parquet(out: &mut impl Write, columns: &[Column]) -> io::Result<()> {
+    let num_rows = columns.first().map_or(0, |column| column.data.len());
+    if columns.iter().any(|column| column.data.len() != num_rows) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "parquet columns must have the same length",
+        ));
+    }
+
+    let mut file = MAGIC.to_vec();
+    let mut chunks = Vec::with_capacity(columns.len());
+    for column in columns {
+        let body = column.data.page_body();
+        let mut header = CompactWriter::new();
+        header.i32_field(1, PAGE_DATA);
+        header.i32_field(2, body.len() as i32);
+        header.i32_field(3, body.len() as i32);
+        header.struct_begin(5);
+        header.i32_field(1, num_rows as i32);
+        header.i32_field(2, ENCODING_PLAIN);
+        header.i32_field(3, ENCODING_RLE);
+        header.i32_field(4, ENCODING_RLE);
+        header.struct_end();
+        header.stop();
+
+        let offset = file.len() as i64;
+        file.extend_from_slice(&header.bytes);
+        file.extend_from_slice(&body);
+        chunks.push((offset, (header.bytes.len() + body.len()) as i64));
+    }
+
+    let mut meta = CompactWriter::new();
+    meta.i32_field(1, 1);
+    meta.list_begin(2, CompactType::Struct, columns.len() + 1);
+    meta.list_struct_begin();
+    meta.binary_field(4, b"schema");
+    meta.i32_field(5, columns.len() as i32);
+    meta.struct_end();
+    for column in columns {
+        meta.list_struct_begin();
+        meta.i32_field(1, column.data.physical_type());
+        meta.i32_field(
+            3,
+            if column.data.is_optional() {
+                REPETITION_OPTIONAL
+            } else {
+                REPETITION_REQUIRED
+            },
+        );
+        meta.binary_field(4, column.name.as_bytes());
+        if let Some(converted) = column.data.converted_type() {
+            meta.i32_field(6, converted);
+        }
+        meta.struct_end();
+    }
+    meta.i64_field(3, num_rows as i64);
+    meta.list_begin(4, CompactType::Struct, 1);
+    meta.list_struct_begin();
+    meta.list_begin(1, CompactType::Struct, columns.len());
+    for (column, (offset, size)) in columns.iter().zip(&chunks) {
+        meta.list_struct_begin();
+        meta.i64_field(2, *offset);
+        meta.struct_begin(3);
+        meta.i32_field(1, column.data.physical_type());
+        meta.list_begin(2, CompactType::I32, 2);
+        meta.varint(zigzag(ENCODING_PLAIN.into()));
+        meta.varint(zigzag(ENCODING_RLE.into()));
+        meta.list_begin(3, CompactType::Binary, 1);
+        meta.binary(column.name.as_bytes());
+        meta.i32_field(4, CODEC_UNCOMPRESSED);
+        meta.i64_field(5, num_rows as i64);
+        meta.i64_field(6, *size);
+        meta.i64_field(7, *size);
+        meta.i64_field(9, *offset);
+        meta.struct_end();
+        meta.struct_end();
+    }
+    let total: i64 = chunks.iter().map(|(_, size)| size).sum();
+    meta.i64_field(2, total);
+    meta.i64_field(3, num_rows as i64);
+    meta.struct_end();
+    meta.binary_field(6, b"platform-pc-sim parquet_writer");
+    meta.stop();
+
+    file.extend_from_slice(&meta.bytes);
+    file.extend_from_slice(&(meta.bytes.len() as u32).to_le_bytes());
+    file.extend_from_slice(MAGIC);
+    out.write_all(&file)
+}
+
+/// ビット幅 1 の RLE / bit-packed hybrid。データページ v1 なので長さを前置する。
+fn write_definition_levels(out: &mut Vec<u8>, present: impl ExactSizeIterator<Item = bool>) {
+    let groups = (present.len() + 7) / 8;
+    let mut levels = Vec::with_capacity(groups + 5);
+    push_varint(&mut levels, ((groups as u64) << 1) | 1);
+    let start = levels.len();
+    levels.resize(start + groups, 0);
+    for (i, is_present) in present.enumerate() {
+        if is_present {
+            levels[start + i / 8] |= 1 << (i % 8);
+        }
+    }
+    out.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+    out.extend_from_slice(&levels);
+}
+
+#[derive(Clone, Copy)]
+enum CompactType {
+    I32 = 5,
+    I64 = 6,
+    Binary = 8,
+    List = 9,
+    Struct = 12,
+}
+
+/// Thrift compact protocol の書き込み (使う型だけ)。
+struct CompactWriter {
+    bytes: Vec<u8>,
+    last_field: Vec<i16>,
+}
+
+impl CompactWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            last_field: vec![0],
+        }
+    }
+
+    fn field_header(&mut self, id: i16, kind: CompactType) {
+        let last = self.last_field.last_mut().expect("inside a struct");
+        let delta = id - *last;
+        if (1..=15).contains(&delta) {
+            self.bytes.push(((delta as u8) << 4) | kind as u8);
+        } else {
+            self.bytes.push(kind as u8);
+            push_varint(&mut self.bytes, zigzag(id.into()));
+        }
+        *last = id;
+    }
+
+    fn i32_field(&mut self, id: i16, value: i32) {
+        self.field_header(id, CompactType::I32);
+        self.varint(zigzag(value.into()));
+    }
+
+    fn i64_field(&mut self, id: i16, value: i64) {
+        self.field_header(id, CompactType::I64);
+        self.varint(zigzag(value));
+    }
+
+    fn binary_field(&mut self, id: i16, value: &[u8]) {
+        self.field_header(id, CompactType::Binary);
+        self.binary(value);
+    }
+
+    fn binary(&mut self, value: &[u8]) {
+        self.varint(value.len() as u64);
+        self.bytes.extend_from_slice(value);
+    }
+
+    fn list_begin(&mut self, id: i16, element: CompactType, len: usize) {
+        self.field_header(id, CompactType::List);
+        if len < 15 {
+            self.bytes.push(((len as u8) << 4) | element as u8);
+        } else {
+            self.bytes.push(0xF0 | element as u8);
+            self.varint(len as u64);
+        }
+    }
+
+    fn struct_begin(&mut self, id: i16) {
+        self.field_header(id, CompactType::Struct);
+        self.last_field.push(0);
+    }
+
+    /// リストの要素としての struct (フィールドヘッダ無し)。
+    fn list_struct_begin(&mut self) {
+        self.last_field.push(0);
+    }
+
+    fn struct_end(&mut self) {
+        self.stop();
+        self.last_field.pop();
+    }
+
+    fn stop(&mut self) {
+        self.bytes.push(0);
+    }
+
+    fn varint(&mut self, value: u64) {
+        push_varint(&mut self.bytes, value);
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn push_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn definition_levels_are_bit_packed_with_length_prefix() {
+        let mut out = Vec::new();
+        write_definition_levels(
+            &mut out,
+            [true, false, true, true, false, false, false, false, true].into_iter(),
+        );
+        // 長さ 3 = ヘッダ 1 + 2 グループ。ヘッダは (2 << 1) | 1
+        assert_eq!(out, [3, 0, 0, 0, 0b101, 0b0000_1101, 0b1]);
+    }
+
+    #[test]
+    fn compact_writer_uses_long_form_for_large_field_deltas() {
+        let mut writer = CompactWriter::new();
+        writer.i32_field(1, -1);
+        writer.i64_field(20, 300);
+        writer.stop();
+        assert_eq!(writer.bytes, [0x15, 0x01, 0x06, 0x28, 0xD8, 0x04, 0x00]);
+    }
+
+    #[test]
+    fn footer_length_points_at_file_metadata() {
+        let columns = [
+            Column::new("tick", ColumnData::Int64(vec![1, 2, 3])),
+            Column::new(
+                "rtc",
+                ColumnData::OptionalText(vec![Some("12:00".into()), None, Some("12:02".into())]),
+            ),
+        ];
+        let mut file = Vec::new();
+        write_parquet(&mut file, &columns).unwrap();
+
+        let footer_len =
+            u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap());
+        let meta_start = file.len() - 8 - footer_len as usize;
+        // FileMetaData は version = 1 (フィールド 1, i32) から始まる
+        assert_eq!(&file[meta_start..meta_start + 2], &[0x15, 0x02]);
+        // 1 列目のページは magic の直後
+        assert_eq!(file[4], 0x15);
+        assert!(file.windows(5).any(|w| w == b"12:02"));
+
+        let mismatched = [
+            Column::new("a", ColumnData::Int64(vec![1])),
+            Column::new("b", ColumnData::Int64(vec![])),
+        ];
+        assert_eq!(
+            write_parquet(&mut Vec::new(), &mismatched)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
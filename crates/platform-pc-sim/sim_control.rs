@@ -0,0 +1,731 @@
+//! Live control of the device dashboard rig.
+//!
+//! [`SimControl`] is shared between the HTTP handlers and the simulation
+//! loop of `device-dashboard-web`. It holds sensor overrides, manual servo /
+//! motor commands, the pause / single-step state and an optional scenario
+//! recorder or player. The rig reads [`ActiveControls`] once per tick.
+//!
+//! Scenarios are plain text, one command per line, prefixed with the tick
+//! offset from the start of the recording:
+//!
+//! ```text
+//! # device-dashboard-web scenario
+//! 0 set temperature_c 31.5
+//! 40 servo 90
+//! 60 motors forward 60 reverse 30
+//! 120 clear temperature_c
+//! 150 motors auto
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use hal_api::actuator::{MotorCommand, MotorDirection};
+use serde::Serialize;
+
+const SCENARIO_HEADER: &str = "# device-dashboard-web scenario";
+
+/// A sensor value that can be overridden from the dashboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SensorChannel {
+    TemperatureC,
+    HumidityPercent,
+    PressurePa,
+    DistanceMm,
+    Lux,
+    Co2Ppm,
+    VocPpb,
+    TofMm,
+    AccelXMg,
+    AccelYMg,
+    AccelZMg,
+    GyroXDps,
+    GyroYDps,
+    GyroZDps,
+    /// DS3231 time as UNIX seconds.
+    RtcUnixS,
+}
+
+impl SensorChannel {
+    pub const ALL: [SensorChannel; 15] = [
+        SensorChannel::TemperatureC,
+        SensorChannel::HumidityPercent,
+        SensorChannel::PressurePa,
+        SensorChannel::DistanceMm,
+        SensorChannel::Lux,
+        SensorChannel::Co2Ppm,
+        SensorChannel::VocPpb,
+        SensorChannel::TofMm,
+        SensorChannel::AccelXMg,
+        SensorChannel::AccelYMg,
+        SensorChannel::AccelZMg,
+        SensorChannel::GyroXDps,
+        SensorChannel::GyroYDps,
+        SensorChannel::GyroZDps,
+        SensorChannel::RtcUnixS,
+    ];
+    /// MPU6050 accelerometer axes (x, y, z).
+    pub const ACCEL_MG: [SensorChannel; 3] = [
+        SensorChannel::AccelXMg,
+        SensorChannel::AccelYMg,
+        SensorChannel::AccelZMg,
+    ];
+    /// MPU6050 gyroscope axes (x, y, z).
+    pub const GYRO_DPS: [SensorChannel; 3] = [
+        SensorChannel::GyroXDps,
+        SensorChannel::GyroYDps,
+        SensorChannel::GyroZDps,
+    ];
+
+    pub fn slug(self) -> &'static str {
+        match self {
+            SensorChannel::TemperatureC => "temperature_c",
+            SensorChannel::HumidityPercent => "humidity_percent",
+            SensorChannel::PressurePa => "pressure_pa",
+            SensorChannel::DistanceMm => "distance_mm",
+            SensorChannel::Lux => "lux",
+            SensorChannel::Co2Ppm => "co2_ppm",
+            SensorChannel::VocPpb => "voc_ppb",
+            SensorChannel::TofMm => "tof_mm",
+            SensorChannel::AccelXMg => "accel_x_mg",
+            SensorChannel::AccelYMg => "accel_y_mg",
+            SensorChannel::AccelZMg => "accel_z_mg",
+            SensorChannel::GyroXDps => "gyro_x_dps",
+            SensorChannel::GyroYDps => "gyro_y_dps",
+            SensorChannel::GyroZDps => "gyro_z_dps",
+            SensorChannel::RtcUnixS => "rtc_unix_s",
+        }
+    }
+
+    pub fn from_slug(slug: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|channel| channel.slug() == slug)
+    }
+
+    /// Inclusive range accepted for overrides, taken from the sensors'
+    /// datasheet operating ranges.
+    pub fn range(self) -> (f64, f64) {
+        match self {
+            SensorChannel::TemperatureC => (-40.0, 85.0),
+            SensorChannel::HumidityPercent => (0.0, 100.0),
+            SensorChannel::PressurePa => (30_000.0, 110_000.0),
+            SensorChannel::DistanceMm => (20.0, 4_000.0),
+            SensorChannel::Lux => (0.0, 65_535.0),
+            SensorChannel::Co2Ppm => (400.0, 60_000.0),
+            SensorChannel::VocPpb => (0.0, 60_000.0),
+            SensorChannel::TofMm => (0.0, 8_190.0),
+            SensorChannel::AccelXMg | SensorChannel::AccelYMg | SensorChannel::AccelZMg => {
+                (-16_000.0, 16_000.0)
+            }
+            SensorChannel::GyroXDps | SensorChannel::GyroYDps | SensorChannel::GyroZDps => {
+                (-2_000.0, 2_000.0)
+            }
+            // 2000-01-01T00:00:00Z ..= 2099-12-31T23:59:59Z, the DS3231 calendar.
+            SensorChannel::RtcUnixS => (946_684_800.0, 4_102_444_799.0),
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Per-channel override values; `None` leaves the mock's own value.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SensorOverrides {
+    values: [Option<f64>; SensorChannel::ALL.len()],
+}
+
+impl SensorOverrides {
+    pub fn get(&self, channel: SensorChannel) -> Option<f64> {
+        self.values[channel.index()]
+    }
+
+    pub fn set(&mut self, channel: SensorChannel, value: f64) {
+        self.values[channel.index()] = Some(value);
+    }
+
+    pub fn clear(&mut self, channel: SensorChannel) {
+        self.values[channel.index()] = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.iter().all(Option::is_none)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (SensorChannel, f64)> + '_ {
+        SensorChannel::ALL
+            .into_iter()
+            .filter_map(|channel| self.get(channel).map(|value| (channel, value)))
+    }
+}
+
+/// One change requested from the dashboard or replayed from a scenario.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ControlCommand {
+    /// Override a sensor value.
+    Set(SensorChannel, f64),
+    /// Hand a sensor back to its mock.
+    Clear(SensorChannel),
+    /// Drop every sensor override and manual actuator command.
+    Reset,
+    /// Fixed servo angle, or `None` to follow the distance sensor again.
+    Servo(Option<u16>),
+    /// Fixed (left, right) motor commands, or `None` for obstacle avoidance.
+    Motors(Option<(MotorCommand, MotorCommand)>),
+}
+
+impl ControlCommand {
+    pub fn validate(&self) -> Result<(), ControlError> {
+        match *self {
+            ControlCommand::Set(channel, value) => {
+                let (min, max) = channel.range();
+                if !(min..=max).contains(&value) {
+                    return Err(ControlError::OutOfRange { channel, value });
+                }
+            }
+            ControlCommand::Servo(Some(angle)) if angle > 180 => {
+                return Err(ControlError::InvalidServoAngle(angle));
+            }
+            ControlCommand::Motors(Some((left, right))) => {
+                for command in [left, right] {
+                    if command.duty_percent > 100 {
+                        return Err(ControlError::InvalidDuty(command.duty_percent));
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Parses one scenario command (without the tick prefix).
+    pub fn parse(text: &str) -> Result<Self, ControlError> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let command = match words.as_slice() {
+            ["set", channel, value] => ControlCommand::Set(
+                parse_channel(channel)?,
+                value
+                    .parse()
+                    .map_err(|_| ControlError::syntax(format!("invalid value `{value}`")))?,
+            ),
+            ["clear", channel] => ControlCommand::Clear(parse_channel(channel)?),
+            ["reset"] => ControlCommand::Reset,
+            ["servo", "auto"] => ControlCommand::Servo(None),
+            ["servo", angle] => ControlCommand::Servo(Some(
+                angle
+                    .parse()
+                    .map_err(|_| ControlError::syntax(format!("invalid angle `{angle}`")))?,
+            )),
+            ["motors", "auto"] => ControlCommand::Motors(None),
+            ["motors", left_dir, left_duty, right_dir, right_duty] => {
+                ControlCommand::Motors(Some((
+                    parse_motor_command(left_dir, left_duty)?,
+                    parse_motor_command(right_dir, right_duty)?,
+                )))
+            }
+            _ => return Err(ControlError::syntax(format!("unknown command `{text}`"))),
+        };
+        command.validate()?;
+        Ok(command)
+    }
+}
+
+impl fmt::Display for ControlCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlCommand::Set(channel, value) => write!(f, "set {} {value}", channel.slug()),
+            ControlCommand::Clear(channel) => write!(f, "clear {}", channel.slug()),
+            ControlCommand::Reset => f.write_str("reset"),
+            ControlCommand::Servo(None) => f.write_str("servo auto"),
+            ControlCommand::Servo(Some(angle)) => write!(f, "servo {angle}"),
+            ControlCommand::Motors(None) => f.write_str("motors auto"),
+            ControlCommand::Motors(Some((left, right))) => write!(
+                f,
+                "motors {} {} {} {}",
+                direction_slug(left.direction),
+                left.duty_percent,
+                direction_slug(right.direction),
+                right.duty_percent
+            ),
+        }
+    }
+}
+
+pub fn direction_slug(direction: MotorDirection) -> &'static str {
+    match direction {
+        MotorDirection::Forward => "forward",
+        MotorDirection::Reverse => "reverse",
+        MotorDirection::Brake => "brake",
+        MotorDirection::Coast => "coast",
+    }
+}
+
+pub fn parse_direction(slug: &str) -> Option<MotorDirection> {
+    match slug {
+        "forward" => Some(MotorDirection::Forward),
+        "reverse" => Some(MotorDirection::Reverse),
+        "brake" => Some(MotorDirection::Brake),
+        "coast" => Some(MotorDirection::Coast),
+        _ => None,
+    }
+}
+
+fn parse_channel(slug: &str) -> Result<SensorChannel, ControlError> {
+    SensorChannel::from_slug(slug).ok_or_else(|| ControlError::UnknownSensor(slug.to_string()))
+}
+
+fn parse_motor_command(direction: &str, duty: &str) -> Result<MotorCommand, ControlError> {
+    let direction = parse_direction(direction)
+        .ok_or_else(|| ControlError::syntax(format!("unknown direction `{direction}`")))?;
+    let duty = duty
+        .parse()
+        .map_err(|_| ControlError::syntax(format!("invalid duty `{duty}`")))?;
+    Ok(MotorCommand::new(direction, duty))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlError {
+    UnknownSensor(String),
+    OutOfRange { channel: SensorChannel, value: f64 },
+    InvalidServoAngle(u16),
+    InvalidDuty(u8),
+    Syntax { line: usize, message: String },
+}
+
+impl ControlError {
+    fn syntax(message: String) -> Self {
+        ControlError::Syntax { line: 0, message }
+    }
+}
+
+impl fmt::Display for ControlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlError::UnknownSensor(slug) => write!(f, "unknown sensor `{slug}`"),
+            ControlError::OutOfRange { channel, value } => {
+                let (min, max) = channel.range();
+                write!(f, "{} = {value} is outside {min}..={max}", channel.slug())
+            }
+            ControlError::InvalidServoAngle(angle) => {
+                write!(f, "servo angle {angle} is outside 0..=180")
+            }
+            ControlError::InvalidDuty(duty) => write!(f, "motor duty {duty} is outside 0..=100"),
+            ControlError::Syntax { line: 0, message } => f.write_str(message),
+            ControlError::Syntax { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ControlError {}
+
+/// What the rig applies on the current tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ActiveControls {
+    pub sensors: SensorOverrides,
+    pub servo_angle: Option<u16>,
+    pub motors: Option<(MotorCommand, MotorCommand)>,
+}
+
+impl ActiveControls {
+    fn apply(&mut self, command: ControlCommand) {
+        match command {
+            ControlCommand::Set(channel, value) => self.sensors.set(channel, value),
+            ControlCommand::Clear(channel) => self.sensors.clear(channel),
+            ControlCommand::Reset => *self = Self::default(),
+            ControlCommand::Servo(angle) => self.servo_angle = angle,
+            ControlCommand::Motors(motors) => self.motors = motors,
+        }
+    }
+}
+
+/// A command scheduled `tick` ticks after the scenario starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScenarioEntry {
+    pub tick: u32,
+    pub command: ControlCommand,
+}
+
+/// A recorded (or hand-written) sequence of control commands.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scenario {
+    pub entries: Vec<ScenarioEntry>,
+}
+
+impl Scenario {
+    /// Parses the text format described in the module docs. Blank lines and
+    /// `#` comments are ignored; ticks must not go backwards.
+    pub fn parse(text: &str) -> Result<Self, ControlError> {
+        let mut entries = Vec::new();
+        let mut last_tick = 0;
+        for (index, line) in text.lines().enumerate() {
+            let line_no = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let at_line = |error: ControlError| match error {
+                ControlError::Syntax { message, .. } => ControlError::Syntax {
+                    line: line_no,
+                    message,
+                },
+                other => ControlError::Syntax {
+                    line: line_no,
+                    message: other.to_string(),
+                },
+            };
+            let (tick, command) = line.split_once(char::is_whitespace).ok_or_else(|| {
+                at_line(ControlError::syntax("expected `<tick> <command>`".into()))
+            })?;
+            let tick: u32 = tick
+                .parse()
+                .map_err(|_| at_line(ControlError::syntax(format!("invalid tick `{tick}`"))))?;
+            if tick < last_tick {
+                return Err(at_line(ControlError::syntax(format!(
+                    "tick {tick} is before the previous entry ({last_tick})"
+                ))));
+            }
+            last_tick = tick;
+            entries.push(ScenarioEntry {
+                tick,
+                command: ControlCommand::parse(command).map_err(at_line)?,
+            });
+        }
+        Ok(Self { entries })
+    }
+}
+
+impl fmt::Display for Scenario {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{SCENARIO_HEADER}")?;
+        for entry in &self.entries {
+            writeln!(f, "{} {}", entry.tick, entry.command)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shared control state; see the module docs.
+#[derive(Debug, Default)]
+pub struct SimControl {
+    active: ActiveControls,
+    paused: bool,
+    pending_steps: u32,
+    /// Ticks advanced since the server started (independent of the rig,
+    /// which restarts from 0 on every board change).
+    tick: u32,
+    recording: Option<(u32, Vec<ScenarioEntry>)>,
+    playback: Option<(u32, VecDeque<ScenarioEntry>)>,
+}
+
+impl SimControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn active(&self) -> &ActiveControls {
+        &self.active
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Validates and applies `command`, appending it to the recording.
+    pub fn apply(&mut self, command: ControlCommand) -> Result<(), ControlError> {
+        command.validate()?;
+        self.active.apply(command);
+        if let Some((start, entries)) = &mut self.recording {
+            entries.push(ScenarioEntry {
+                tick: self.tick - *start,
+                command,
+            });
+        }
+        Ok(())
+    }
+
+    /// Runs one text command: anything [`ControlCommand::parse`] accepts, plus
+    /// `pause`, `resume` and `step [ticks]` (used by the WebSocket endpoint).
+    pub fn execute(&mut self, text: &str) -> Result<(), ControlError> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words.as_slice() {
+            ["pause"] => self.pause(),
+            ["resume"] => self.resume(),
+            ["step"] => self.step(1),
+            ["step", ticks] => self.step(
+                ticks
+                    .parse()
+                    .map_err(|_| ControlError::syntax(format!("invalid tick count `{ticks}`")))?,
+            ),
+            _ => return self.apply(ControlCommand::parse(text)?),
+        }
+        Ok(())
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_steps = 0;
+    }
+
+    /// Advances `ticks` more ticks while paused (pauses if running).
+    pub fn step(&mut self, ticks: u32) {
+        self.paused = true;
+        self.pending_steps = self.pending_steps.saturating_add(ticks);
+    }
+
+    /// Called by the simulation loop once per iteration. Returns whether the
+    /// rig should advance; if so, due scenario commands are applied first.
+    pub fn begin_tick(&mut self) -> bool {
+        if self.paused {
+            if self.pending_steps == 0 {
+                return false;
+            }
+            self.pending_steps -= 1;
+        }
+        self.tick = self.tick.wrapping_add(1);
+
+        if let Some((start, queue)) = &mut self.playback {
+            let elapsed = self.tick - *start;
+            while queue.front().is_some_and(|entry| entry.tick < elapsed) {
+                if let Some(entry) = queue.pop_front() {
+                    self.active.apply(entry.command);
+                }
+            }
+            if queue.is_empty() {
+                self.playback = None;
+            }
+        }
+        true
+    }
+
+    pub fn start_recording(&mut self) {
+        self.recording = Some((self.tick, Vec::new()));
+    }
+
+    /// Stops recording and returns what was captured (empty if not recording).
+    pub fn stop_recording(&mut self) -> Scenario {
+        Scenario {
+            entries: self
+                .recording
+                .take()
+                .map(|(_, entries)| entries)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The recording so far, without stopping it.
+    pub fn recorded(&self) -> Option<Scenario> {
+        self.recording.as_ref().map(|(_, entries)| Scenario {
+            entries: entries.clone(),
+        })
+    }
+
+    /// Replays `scenario` from the next tick. Entries at tick 0 apply on the
+    /// very next advance; replayed commands are not recorded again.
+    pub fn play(&mut self, scenario: Scenario) {
+        self.playback = Some((self.tick, scenario.entries.into()));
+    }
+
+    pub fn stop_playback(&mut self) {
+        self.playback = None;
+    }
+
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct MotorView {
+            direction: &'static str,
+            duty_percent: u8,
+        }
+        #[derive(Serialize)]
+        struct ControlView {
+            paused: bool,
+            tick: u32,
+            pending_steps: u32,
+            recording: bool,
+            recorded_entries: usize,
+            playback_remaining: Option<usize>,
+            sensors: serde_json::Map<String, serde_json::Value>,
+            servo_angle: Option<u16>,
+            motors: Option<[MotorView; 2]>,
+        }
+        let motor = |command: MotorCommand| MotorView {
+            direction: direction_slug(command.direction),
+            duty_percent: command.duty_percent,
+        };
+        let view = ControlView {
+            paused: self.paused,
+            tick: self.tick,
+            pending_steps: self.pending_steps,
+            recording: self.recording.is_some(),
+            recorded_entries: self.recording.as_ref().map_or(0, |(_, e)| e.len()),
+            playback_remaining: self.playback.as_ref().map(|(_, queue)| queue.len()),
+            sensors: self
+                .active
+                .sensors
+                .iter()
+                .map(|(channel, value)| (channel.slug().to_string(), value.into()))
+                .collect(),
+            servo_angle: self.active.servo_angle,
+            motors: self
+                .active
+                .motors
+                .map(|(left, right)| [motor(left), motor(right)]),
+        };
+        serde_json::to_string(&view).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_validate_ranges_and_round_trip_through_text() {
+        let commands = [
+            ControlCommand::Set(SensorChannel::TemperatureC, 31.5),
+            ControlCommand::Clear(SensorChannel::Co2Ppm),
+            ControlCommand::Reset,
+            ControlCommand::Servo(Some(90)),
+            ControlCommand::Servo(None),
+            ControlCommand::Motors(Some((
+                MotorCommand::new(MotorDirection::Forward, 60),
+                MotorCommand::new(MotorDirection::Brake, 0),
+            ))),
+            ControlCommand::Motors(None),
+        ];
+        for command in commands {
+            assert_eq!(ControlCommand::parse(&command.to_string()), Ok(command));
+        }
+
+        assert_eq!(
+            ControlCommand::parse("set humidity_percent 120"),
+            Err(ControlError::OutOfRange {
+                channel: SensorChannel::HumidityPercent,
+                value: 120.0
+            })
+        );
+        assert_eq!(
+            ControlCommand::parse("servo 200"),
+            Err(ControlError::InvalidServoAngle(200))
+        );
+        assert_eq!(
+            ControlCommand::parse("motors forward 101 coast 0"),
+            Err(ControlError::InvalidDuty(101))
+        );
+        assert_eq!(
+            ControlCommand::parse("set wind 3"),
+            Err(ControlError::UnknownSensor("wind".into()))
+        );
+    }
+
+    #[test]
+    fn imu_and_rtc_channels_parse_and_record() {
+        assert_eq!(
+            ControlCommand::parse("set rtc_unix_s 1760875200"),
+            Ok(ControlCommand::Set(
+                SensorChannel::RtcUnixS,
+                1_760_875_200.0
+            ))
+        );
+        assert_eq!(
+            ControlCommand::Set(SensorChannel::RtcUnixS, 1_760_875_201.0).to_string(),
+            "set rtc_unix_s 1760875201"
+        );
+        assert_eq!(
+            ControlCommand::parse("set gyro_z_dps 2500"),
+            Err(ControlError::OutOfRange {
+                channel: SensorChannel::GyroZDps,
+                value: 2500.0
+            })
+        );
+        assert!(ControlCommand::parse("set rtc_unix_s 0").is_err());
+
+        let mut control = SimControl::new();
+        control.start_recording();
+        control.execute("set accel_z_mg -1000").unwrap();
+        control.begin_tick();
+        control.execute("set gyro_x_dps 12.5").unwrap();
+        control.execute("set rtc_unix_s 1760875200").unwrap();
+        assert_eq!(
+            control.stop_recording().to_string(),
+            "# device-dashboard-web scenario\n0 set accel_z_mg -1000\n\
+             1 set gyro_x_dps 12.5\n1 set rtc_unix_s 1760875200\n"
+        );
+    }
+
+    #[test]
+    fn scenario_parse_reports_line_numbers() {
+        let scenario = Scenario::parse("# comment\n\n0 set lux 120\n15 servo 45\n").unwrap();
+        assert_eq!(scenario.entries.len(), 2);
+        assert_eq!(scenario.entries[1].tick, 15);
+
+        let error = Scenario::parse("0 set lux 120\n10 jump\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: unknown command `jump`");
+        let error = Scenario::parse("10 reset\n5 reset\n").unwrap_err();
+        assert!(error.to_string().starts_with("line 2: tick 5"));
+    }
+
+    #[test]
+    fn pause_and_step_gate_the_simulation_loop() {
+        let mut control = SimControl::new();
+        assert!(control.begin_tick());
+
+        control.pause();
+        assert!(!control.begin_tick());
+
+        control.step(2);
+        assert!(control.begin_tick());
+        assert!(control.begin_tick());
+        assert!(!control.begin_tick());
+        assert!(control.is_paused());
+
+        control.resume();
+        assert!(control.begin_tick());
+
+        control.execute("step 1").unwrap();
+        assert!(control.begin_tick());
+        assert!(!control.begin_tick());
+        control.execute("resume").unwrap();
+        control.execute("servo 45").unwrap();
+        assert_eq!(control.active().servo_angle, Some(45));
+        assert!(control.execute("step many").is_err());
+    }
+
+    #[test]
+    fn recorded_scenario_replays_at_the_same_offsets() {
+        let mut control = SimControl::new();
+        control.begin_tick();
+        control.start_recording();
+        control
+            .apply(ControlCommand::Set(SensorChannel::DistanceMm, 120.0))
+            .unwrap();
+        for _ in 0..5 {
+            control.begin_tick();
+        }
+        control.apply(ControlCommand::Servo(Some(30))).unwrap();
+        let scenario = control.stop_recording();
+        assert_eq!(
+            scenario.to_string(),
+            "# device-dashboard-web scenario\n0 set distance_mm 120\n5 servo 30\n"
+        );
+
+        let mut replay = SimControl::new();
+        replay.play(Scenario::parse(&scenario.to_string()).unwrap());
+        replay.begin_tick();
+        assert_eq!(
+            replay.active().sensors.get(SensorChannel::DistanceMm),
+            Some(120.0)
+        );
+        assert_eq!(replay.active().servo_angle, None);
+        for _ in 0..4 {
+            replay.begin_tick();
+        }
+        assert_eq!(replay.active().servo_angle, None);
+        replay.begin_tick();
+        assert_eq!(replay.active().servo_angle, Some(30));
+        assert!(replay.to_json().contains(r#""playback_remaining":null"#));
+    }
+}
@@ -0,0 +1,194 @@
+//! `std::net::TcpStream` を [`TcpClientSocket`] として使うアダプタ。
+//!
+//! `core_app::mqtt::MqttClient` などのソケット利用側を、実機のネットワークスタック
+//! 無しで host 上の本物の TCP 接続に載せて動かすためのもの。
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::Write;
+//! use std::net::TcpListener;
+//!
+//! use hal_api::net::TcpClientSocket;
+//! use platform_pc_sim::std_socket::StdTcpSocket;
+//!
+//! let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//! let port = listener.local_addr().unwrap().port();
+//!
+//! let mut socket = StdTcpSocket::new();
+//! socket.connect("127.0.0.1", port).unwrap();
+//! assert!(socket.is_connected());
+//!
+//! let (mut peer, _) = listener.accept().unwrap();
+//! peer.write_all(b"hello").unwrap();
+//!
+//! let mut buffer = [0u8; 8];
+//! let mut received = 0;
+//! while received == 0 {
+//!     received = socket.receive(&mut buffer).unwrap();
+//! }
+//! assert_eq!(&buffer[..received], b"hello");
+//! ```
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use hal_api::error::NetError;
+use hal_api::net::TcpClientSocket;
+
+/// [`StdTcpSocket::connect`] の既定のタイムアウト。
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// ノンブロッキングの `TcpStream` で [`TcpClientSocket`] を実装する。
+///
+/// `connect` だけは確立までブロックする (最大 [`DEFAULT_CONNECT_TIMEOUT`])。
+/// 送受信は待たずに戻り、相手からの切断を検出すると自動で閉じる。
+#[derive(Debug)]
+pub struct StdTcpSocket {
+    stream: Option<TcpStream>,
+    connect_timeout: Duration,
+}
+
+impl Default for StdTcpSocket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StdTcpSocket {
+    pub fn new() -> Self {
+        Self {
+            stream: None,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+        }
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    fn fail(&mut self, error: io::Error) -> NetError {
+        self.close();
+        map_io_error(&error)
+    }
+}
+
+fn map_io_error(error: &io::Error) -> NetError {
+    match error.kind() {
+        ErrorKind::ConnectionRefused | ErrorKind::NotFound | ErrorKind::AddrNotAvailable => {
+            NetError::ConnectionRefused
+        }
+        ErrorKind::ConnectionReset
+        | ErrorKind::ConnectionAborted
+        | ErrorKind::BrokenPipe
+        | ErrorKind::UnexpectedEof => NetError::ConnectionClosed,
+        ErrorKind::TimedOut => NetError::Timeout,
+        _ => NetError::Io,
+    }
+}
+
+impl TcpClientSocket for StdTcpSocket {
+    type Error = NetError;
+
+    fn connect(&mut self, host: &str, port: u16) -> Result<(), Self::Error> {
+        self.close();
+        let addrs = (host, port)
+            .to_socket_addrs()
+            .map_err(|_| NetError::ConnectionRefused)?;
+        let mut last_error = NetError::ConnectionRefused;
+        for addr in addrs {
+            match TcpStream::connect_timeout(&addr, self.connect_timeout) {
+                Ok(stream) => {
+                    stream.set_nonblocking(true).map_err(|e| map_io_error(&e))?;
+                    let _ = stream.set_nodelay(true);
+                    self.stream = Some(stream);
+                    return Ok(());
+                }
+                Err(e) => last_error = map_io_error(&e),
+            }
+        }
+        Err(last_error)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    fn send(&mut self, data: &[u8]) -> Result<usize, Self::Error> {
+        let stream = self.stream.as_mut().ok_or(NetError::NotConnected)?;
+        match stream.write(data) {
+            Ok(n) => Ok(n),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(0),
+            Err(e) if e.kind() == ErrorKind::Interrupted => Ok(0),
+            Err(e) => Err(self.fail(e)),
+        }
+    }
+
+    fn receive(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        let stream = self.stream.as_mut().ok_or(NetError::NotConnected)?;
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+        match stream.read(buffer) {
+            Ok(0) => {
+                self.close();
+                Err(NetError::ConnectionClosed)
+            }
+            Ok(n) => Ok(n),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(0),
+            Err(e) if e.kind() == ErrorKind::Interrupted => Ok(0),
+            Err(e) => Err(self.fail(e)),
+        }
+    }
+
+    fn close(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn refused_connection_maps_to_connection_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let mut socket = StdTcpSocket::new();
+        assert_eq!(
+            socket.connect("127.0.0.1", port),
+            Err(NetError::ConnectionRefused)
+        );
+        assert!(!socket.is_connected());
+        assert_eq!(socket.send(b"x"), Err(NetError::NotConnected));
+    }
+
+    #[test]
+    fn peer_close_is_reported_and_closes_the_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut socket = StdTcpSocket::new();
+        socket.connect("localhost", port).unwrap();
+        let (peer, _) = listener.accept().unwrap();
+
+        let mut buffer = [0u8; 4];
+        assert_eq!(socket.receive(&mut buffer), Ok(0));
+        drop(peer);
+        let error = loop {
+            match socket.receive(&mut buffer) {
+                Ok(0) => std::thread::sleep(Duration::from_millis(1)),
+                Ok(n) => panic!("unexpected {n} bytes"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(error, NetError::ConnectionClosed);
+        assert!(!socket.is_connected());
+    }
+}
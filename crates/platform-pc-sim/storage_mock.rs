@@ -0,0 +1,241 @@
+//! File-backed block storage mock.
+
+use core_app::settings::FlashKvStore;
+use hal_api::error::StorageError;
+use hal_api::storage::BlockStorage;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// ファイルをフラッシュに見立てた `BlockStorage` 実装。
+///
+/// - 新規ファイルは `0xFF` (消去済み) で埋める
+/// - 書き込みは NOR フラッシュと同様に既存値との AND を取る (1 → 0 のみ)
+/// - 書き込みごとにファイルへ反映するので、インスタンスを捨てて
+///   [`FileBlockStorage::open`] し直すと電源断後の再起動を再現できる
+/// - [`FileBlockStorage::tear_next_write`] で次の書き込みを途中で打ち切れる
+#[derive(Debug)]
+pub struct FileBlockStorage {
+    file: File,
+    path: PathBuf,
+    block_size: u32,
+    block_count: u32,
+    tear_next_write: Option<usize>,
+    write_count: u32,
+    erase_count: u32,
+}
+
+impl FileBlockStorage {
+    /// ファイルを開く。無ければ作成し、足りない分を `0xFF` で埋める。
+    pub fn open(path: impl AsRef<Path>, block_size: u32, block_count: u32) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let capacity = u64::from(block_size) * u64::from(block_count);
+        let current = file.metadata()?.len();
+        if current < capacity {
+            file.seek(SeekFrom::Start(current))?;
+            file.write_all(&vec![0xFF; (capacity - current) as usize])?;
+            file.flush()?;
+        }
+        Ok(Self {
+            file,
+            path,
+            block_size,
+            block_count,
+            tear_next_write: None,
+            write_count: 0,
+            erase_count: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 次の書き込みを先頭 `bytes` バイトだけ反映して `WriteFailed` で失敗させる。
+    pub fn tear_next_write(&mut self, bytes: usize) {
+        self.tear_next_write = Some(bytes);
+    }
+
+    /// 成功した書き込み回数。
+    pub fn write_count(&self) -> u32 {
+        self.write_count
+    }
+
+    /// 消去回数 (摩耗の目安)。
+    pub fn erase_count(&self) -> u32 {
+        self.erase_count
+    }
+
+    fn check_range(&self, offset: u32, len: usize) -> Result<(), StorageError> {
+        let end = u64::from(offset) + len as u64;
+        if end > u64::from(self.capacity()) {
+            return Err(StorageError::OutOfBounds);
+        }
+        Ok(())
+    }
+
+    fn read_at(&mut self, offset: u32, buffer: &mut [u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(u64::from(offset)))?;
+        self.file.read_exact(buffer)
+    }
+
+    fn write_at(&mut self, offset: u32, data: &[u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(u64::from(offset)))?;
+        self.file.write_all(data)?;
+        self.file.flush()
+    }
+}
+
+impl BlockStorage for FileBlockStorage {
+    type Error = StorageError;
+
+    fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u32 {
+        self.block_count
+    }
+
+    fn read(&mut self, offset: u32, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.check_range(offset, buffer.len())?;
+        self.read_at(offset, buffer)
+            .map_err(|_| StorageError::ReadFailed)
+    }
+
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
+        self.check_range(offset, data.len())?;
+        let mut cells = vec![0u8; data.len()];
+        self.read_at(offset, &mut cells)
+            .map_err(|_| StorageError::ReadFailed)?;
+        for (cell, byte) in cells.iter_mut().zip(data) {
+            *cell &= *byte;
+        }
+        let torn = self.tear_next_write.take();
+        let written = torn.unwrap_or(cells.len()).min(cells.len());
+        self.write_at(offset, &cells[..written])
+            .map_err(|_| StorageError::WriteFailed)?;
+        if torn.is_some() {
+            return Err(StorageError::WriteFailed);
+        }
+        self.write_count += 1;
+        Ok(())
+    }
+
+    fn erase_block(&mut self, block: u32) -> Result<(), Self::Error> {
+        if block >= self.block_count {
+            return Err(StorageError::OutOfBounds);
+        }
+        let erased = vec![0xFF; self.block_size as usize];
+        self.write_at(block * self.block_size, &erased)
+            .map_err(|_| StorageError::EraseFailed)?;
+        self.erase_count += 1;
+        Ok(())
+    }
+}
+
+/// [`FileKvStore`] のレコード 1 件分のバイト数。
+pub const FILE_KV_SLOT_SIZE: usize = 512;
+/// [`open_kv_store`] が作るファイルのブロックサイズ (ESP32 のフラッシュセクタと同じ)。
+pub const FILE_KV_BLOCK_SIZE: u32 = 4096;
+/// [`open_kv_store`] が作るファイルのブロック数。
+pub const FILE_KV_BLOCK_COUNT: u32 = 4;
+
+/// ファイルに永続化するキーバリューストア (host 側の NVS 代わり)。
+pub type FileKvStore<const N: usize> = FlashKvStore<FileBlockStorage, N, FILE_KV_SLOT_SIZE>;
+
+/// `path` のファイルをフラッシュに見立てて `FileKvStore` をマウントする。
+pub fn open_kv_store<const N: usize>(path: impl AsRef<Path>) -> io::Result<FileKvStore<N>> {
+    let storage = FileBlockStorage::open(path, FILE_KV_BLOCK_SIZE, FILE_KV_BLOCK_COUNT)?;
+    FlashKvStore::mount(storage)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{err:?}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_file_is_erased_and_writes_and_bits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flash.bin");
+        let mut storage = FileBlockStorage::open(&path, 32, 2).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 64);
+
+        storage.write(4, &[0b1010_1010]).unwrap();
+        storage.write(4, &[0b1100_1100]).unwrap();
+        let mut byte = [0u8];
+        storage.read(4, &mut byte).unwrap();
+        assert_eq!(byte, [0b1000_1000]);
+
+        storage.erase_block(0).unwrap();
+        storage.read(4, &mut byte).unwrap();
+        assert_eq!(byte, [0xFF]);
+        assert_eq!(storage.erase_count(), 1);
+    }
+
+    #[test]
+    fn contents_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flash.bin");
+        {
+            let mut storage = FileBlockStorage::open(&path, 32, 2).unwrap();
+            storage.write(40, &[1, 2, 3]).unwrap();
+        }
+        let mut storage = FileBlockStorage::open(&path, 32, 2).unwrap();
+        let mut buffer = [0u8; 3];
+        storage.read(40, &mut buffer).unwrap();
+        assert_eq!(buffer, [1, 2, 3]);
+    }
+
+    #[test]
+    fn torn_write_keeps_prefix_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = FileBlockStorage::open(dir.path().join("flash.bin"), 32, 2).unwrap();
+        storage.tear_next_write(2);
+        assert_eq!(
+            storage.write(0, &[0, 0, 0, 0]),
+            Err(StorageError::WriteFailed)
+        );
+        let mut buffer = [0u8; 4];
+        storage.read(0, &mut buffer).unwrap();
+        assert_eq!(buffer, [0, 0, 0xFF, 0xFF]);
+        assert_eq!(storage.write_count(), 0);
+    }
+
+    #[test]
+    fn kv_store_survives_reopen() {
+        use hal_api::kv::KeyValueStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nvs.bin");
+        {
+            let mut store = open_kv_store::<8>(&path).unwrap();
+            store.set("wifi_ssid", b"lab-net").unwrap();
+            store.set_i32("post_ms", 30_000).unwrap();
+        }
+        let mut store = open_kv_store::<8>(&path).unwrap();
+        let mut buffer = [0u8; 16];
+        let len = store.get("wifi_ssid", &mut buffer).unwrap().unwrap();
+        assert_eq!(&buffer[..len], b"lab-net");
+        assert_eq!(store.get_i32("post_ms").unwrap(), Some(30_000));
+    }
+
+    #[test]
+    fn out_of_range_access_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = FileBlockStorage::open(dir.path().join("flash.bin"), 32, 2).unwrap();
+        let mut buffer = [0u8; 4];
+        assert_eq!(
+            storage.read(62, &mut buffer),
+            Err(StorageError::OutOfBounds)
+        );
+        assert_eq!(storage.erase_block(2), Err(StorageError::OutOfBounds));
+    }
+}
@@ -0,0 +1,144 @@
+//! Data logger on a file-backed flash: `DataLoggerApp` + `SimulatedRoom` + `SimulatedRtc`,
+//! including a torn write and a simulated reboot in the middle of logging.
+
+use core_app::data_logger::{
+    DataLog, DataLogError, DataLoggerApp, DataLoggerConfig, DataLoggerError, LogTimestamp,
+    RtcClock, SensorSource, CSV_HEADER,
+};
+use hal_api::error::{SensorError, StorageError};
+use hal_api::rtc::RtcDateTime;
+use hal_api::sensor::{EnvReading, EnvSensor};
+use platform_pc_sim::mock_hal::MockPin;
+use platform_pc_sim::storage_mock::FileBlockStorage;
+use platform_pc_sim::thermal_sim::{HeaterDrive, RoomThermalModel, SimulatedRoom, SimulatedRtc};
+
+const TICK_MS: u32 = 1_000;
+// 4 KiB セクタ × 4 = 680 レコード
+const BLOCK_SIZE: u32 = 4_096;
+const BLOCK_COUNT: u32 = 4;
+
+type RoomSource =
+    SensorSource<SimulatedRoom, fn(&mut SimulatedRoom) -> Result<EnvReading, SensorError>>;
+type RoomLogger = DataLoggerApp<RoomSource, FileBlockStorage, RtcClock<SimulatedRtc>>;
+
+fn logger(storage: FileBlockStorage, room: &SimulatedRoom, rtc: &SimulatedRtc) -> RoomLogger {
+    DataLoggerApp::new_with_config(
+        SensorSource::new(room.clone(), EnvSensor::read as _),
+        DataLog::mount(storage).expect("file flash should mount"),
+        RtcClock::new(rtc.clone()),
+        DataLoggerConfig {
+            sample_period_ticks: 60,
+        },
+    )
+}
+
+#[test]
+fn logger_recovers_after_power_loss_and_exports_csv() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("datalog.bin");
+    let room = SimulatedRoom::new(
+        RoomThermalModel::default(),
+        HeaterDrive::Relay(MockPin::new(5)),
+    );
+    let rtc = SimulatedRtc::new(RtcDateTime::new(25, 3, 1, 0, 0, 0));
+
+    // 1 時間分 (60 レコード) 記録
+    let mut app = logger(
+        FileBlockStorage::open(&path, BLOCK_SIZE, BLOCK_COUNT).unwrap(),
+        &room,
+        &rtc,
+    );
+    for _ in 0..3_600 {
+        room.step(TICK_MS);
+        rtc.step(TICK_MS);
+        app.tick().unwrap();
+    }
+    assert_eq!(app.log().len(), 60);
+
+    // 61 件目の書き込み中に電源断
+    let (_, log, _) = app.into_parts();
+    let mut storage = log.into_inner();
+    storage.tear_next_write(7);
+    let mut app = logger(storage, &room, &rtc);
+    let mut failure = None;
+    for _ in 0..60 {
+        room.step(TICK_MS);
+        rtc.step(TICK_MS);
+        if let Err(err) = app.tick() {
+            failure = Some(err);
+        }
+    }
+    assert_eq!(
+        failure,
+        Some(DataLoggerError::Log(DataLogError::Storage(
+            StorageError::WriteFailed
+        )))
+    );
+    drop(app);
+
+    // 再起動: ファイルを開き直して続きから記録する
+    let mut app = logger(
+        FileBlockStorage::open(&path, BLOCK_SIZE, BLOCK_COUNT).unwrap(),
+        &room,
+        &rtc,
+    );
+    assert_eq!(app.log().len(), 60);
+    assert_eq!(app.log().next_sequence(), 60);
+    for _ in 0..600 {
+        room.step(TICK_MS);
+        rtc.step(TICK_MS);
+        app.tick().unwrap();
+    }
+    assert_eq!(app.log().len(), 70);
+
+    let mut sequences = Vec::new();
+    let mut timestamps = Vec::new();
+    app.log_mut()
+        .for_each(|record| {
+            sequences.push(record.sequence);
+            timestamps.push(record.timestamp);
+        })
+        .unwrap();
+    assert_eq!(sequences, (0..70).collect::<Vec<_>>());
+    assert!(timestamps.windows(2).all(
+        |pair| matches!(pair, [LogTimestamp::UnixSeconds(a), LogTimestamp::UnixSeconds(b)] if a < b)
+    ));
+
+    let mut csv = String::new();
+    assert_eq!(app.log_mut().export_csv(&mut csv).unwrap(), 70);
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some(CSV_HEADER.trim_end()));
+    let first = lines.next().unwrap();
+    assert!(first.starts_with("0,2025-03-01T00:01:00,env,"), "{first}");
+    assert_eq!(csv.lines().count(), 71);
+}
+
+#[test]
+fn logger_wraps_on_small_flash() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("small.bin");
+    let room = SimulatedRoom::new(
+        RoomThermalModel::default(),
+        HeaterDrive::Relay(MockPin::new(5)),
+    );
+    let rtc = SimulatedRtc::new(RtcDateTime::new(25, 3, 1, 0, 0, 0));
+    // 240 バイト × 3 ブロック = 30 スロット
+    let mut app = logger(FileBlockStorage::open(&path, 240, 3).unwrap(), &room, &rtc);
+    for _ in 0..60 * 45 {
+        rtc.step(TICK_MS);
+        app.tick().unwrap();
+    }
+
+    let log = app.log();
+    assert_eq!(log.next_sequence(), 45);
+    assert!(log.len() >= 20 && log.len() <= 30, "len = {}", log.len());
+    assert!(log.storage().erase_count() >= 4);
+
+    let mut first = None;
+    app.log_mut()
+        .for_each(|record| {
+            first.get_or_insert(record.sequence);
+        })
+        .unwrap();
+    assert_eq!(first, Some(45 - app.log().len()));
+}
@@ -0,0 +1,137 @@
+//! End-to-end: a simulated wifi-climate device publishes to the `ingest-server`
+//! binary with the firmware's telemetry encoder, then the readings are read back
+//! as JSON — including after a server restart on the same CSV store.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+use core_app::telemetry::TelemetryFrame;
+use hal_api::sensor::EnvSensor;
+use platform_pc_sim::climate_sim::{demo_sensor_readings, SimulatedEnvSensor};
+
+const DEVICE_ID: &str = "sim-climate-01";
+
+struct IngestServer {
+    child: Child,
+    addr: SocketAddr,
+}
+
+impl IngestServer {
+    fn start(store: &Path) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_ingest-server"))
+            .arg("0")
+            .arg(store)
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("ingest-server should start");
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut line = String::new();
+        let port = loop {
+            line.clear();
+            assert!(
+                stdout.read_line(&mut line).unwrap() > 0,
+                "server exited early"
+            );
+            if let Some(addr) = line.trim().strip_prefix("ingest server listening on ") {
+                break addr.parse::<SocketAddr>().unwrap().port();
+            }
+        };
+        // Keep draining stdout so per-reading log lines never block the server.
+        std::thread::spawn(move || std::io::copy(&mut stdout, &mut std::io::sink()));
+        Self {
+            child,
+            addr: SocketAddr::from(([127, 0, 0, 1], port)),
+        }
+    }
+
+    fn request(&self, request: &[u8]) -> (u16, String) {
+        let mut stream = TcpStream::connect(self.addr).unwrap();
+        stream.write_all(request).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+        (status, body)
+    }
+
+    /// Same request layout the firmware builds with `climate_http::build_post`.
+    fn post_reading(&self, json: &[u8]) -> (u16, String) {
+        let mut request = format!(
+            "POST /api/sensors/reading HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.addr,
+            json.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(json);
+        self.request(&request)
+    }
+
+    fn get_json(&self, path: &str) -> serde_json::Value {
+        let (status, body) =
+            self.request(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes());
+        assert_eq!(status, 200, "{body}");
+        serde_json::from_str(&body).unwrap()
+    }
+}
+
+impl Drop for IngestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn simulated_device_publishes_readings_that_survive_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = dir.path().join("readings.csv");
+    let expected = demo_sensor_readings();
+
+    {
+        let server = IngestServer::start(&store);
+        let mut sensor = SimulatedEnvSensor::new(expected.clone());
+        for id in 1..=expected.len() {
+            let reading = sensor.read().unwrap();
+            let mut json = [0u8; 128];
+            let len = TelemetryFrame::new(DEVICE_ID)
+                .with_env(reading)
+                .encode_json(&mut json)
+                .unwrap();
+            assert_eq!(
+                server.post_reading(&json[..len]),
+                (201, format!("{{\"id\":{id}}}"))
+            );
+        }
+
+        let (status, body) = server.post_reading(br#"{"device_id":"sim-climate-01"}"#);
+        assert_eq!(status, 400, "{body}");
+    }
+
+    let server = IngestServer::start(&store);
+    let readings = server.get_json(&format!("/api/sensors/readings?device_id={DEVICE_ID}"));
+    let readings = readings.as_array().unwrap();
+    assert_eq!(readings.len(), expected.len());
+    for (stored, sent) in readings.iter().zip(&expected) {
+        assert_eq!(stored["device_id"], DEVICE_ID);
+        assert_eq!(
+            stored["temperature"].as_f64().unwrap(),
+            f64::from(sent.temperature_centi_celsius) / 100.0
+        );
+        assert_eq!(
+            stored["humidity"].as_f64().unwrap(),
+            f64::from(sent.humidity_centi_percent) / 100.0
+        );
+    }
+
+    let history = server.get_json(&format!("/api/history?device_id={DEVICE_ID}&limit=2"));
+    assert_eq!(
+        history,
+        serde_json::json!({
+            "temperature": [25.7, 26.2],
+            "humidity": [44.6, 45.2],
+            "pressure": [101_240, 101_210],
+        })
+    );
+}
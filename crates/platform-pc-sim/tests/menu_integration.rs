@@ -0,0 +1,222 @@
+//! Settings menu driven by scripted button presses: `ButtonInput<MockButton>` →
+//! `MenuApp` → `Lcd1602Display` over `VirtualI2cBus` → `MockLcd1602Device`.
+
+use core_app::climate_display::{ClimateDisplayApp, ClimateDisplayConfig};
+use core_app::menu::{
+    ButtonConfig, ButtonInput, ChoiceSetting, MemorySettings, Menu, MenuApp, MenuEvent, MenuItem,
+    NumberSetting, SettingsStore,
+};
+use embedded_hal::delay::DelayNs;
+use platform_pc_sim::climate_sim::{demo_sensor_readings, SimulatedEnvSensor, TerminalDisplay16x2};
+use platform_pc_sim::lcd1602_mock::MockLcd1602Device;
+use platform_pc_sim::mock_hal::MockButton;
+use platform_pc_sim::virtual_i2c::VirtualI2cBus;
+use reference_drivers::lcd1602::{Lcd1602Display, LCD1602_ADDRESS_PRIMARY};
+
+const KEY_REFRESH_SECONDS: u16 = 1;
+const KEY_BACKLIGHT: u16 = 2;
+const ACTION_FACTORY_RESET: u16 = 1;
+const TICKS_PER_SECOND: u32 = 100;
+
+static ON_OFF: [&str; 2] = ["off", "on"];
+static DISPLAY_ITEMS: [MenuItem; 2] = [MenuItem::Number(REFRESH), MenuItem::Choice(BACKLIGHT)];
+static ROOT: [MenuItem; 2] = [
+    MenuItem::submenu("Display", &DISPLAY_ITEMS),
+    MenuItem::confirmed_action("Factory reset", ACTION_FACTORY_RESET, "Erase settings?"),
+];
+const REFRESH: NumberSetting =
+    NumberSetting::new("Refresh", KEY_REFRESH_SECONDS, 1, 60, 1).unit("s");
+const BACKLIGHT: ChoiceSetting =
+    ChoiceSetting::new("Backlight", KEY_BACKLIGHT, &ON_OFF).default_index(1);
+
+struct NoopDelay;
+
+impl DelayNs for NoopDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+type Buttons = ButtonInput<MockButton, MockButton, MockButton>;
+type Lcd = Lcd1602Display<VirtualI2cBus, NoopDelay>;
+type SettingsMenu = MenuApp<Buttons, Lcd, MemorySettings<8>, 2>;
+
+struct Rig {
+    up: MockButton,
+    down: MockButton,
+    select: MockButton,
+    lcd: MockLcd1602Device,
+    app: SettingsMenu,
+    events: Vec<MenuEvent>,
+}
+
+impl Rig {
+    fn new() -> Self {
+        let bus = VirtualI2cBus::new();
+        let lcd = MockLcd1602Device::new();
+        bus.attach_device(LCD1602_ADDRESS_PRIMARY, lcd.clone());
+        let (up, down, select) = (MockButton::new(2), MockButton::new(3), MockButton::new(4));
+        let input = ButtonInput::new_with_config(
+            up.clone(),
+            down.clone(),
+            select.clone(),
+            ButtonConfig {
+                long_press_polls: 50,
+                ..ButtonConfig::default()
+            },
+        );
+        let mut app = MenuApp::new(
+            input,
+            Lcd1602Display::new(bus, NoopDelay),
+            MemorySettings::new(),
+            Menu::new("Settings", &ROOT),
+        );
+        app.refresh().unwrap();
+        Self {
+            up,
+            down,
+            select,
+            lcd,
+            app,
+            events: Vec::new(),
+        }
+    }
+
+    fn run(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            if let Some(event) = self.app.tick().unwrap() {
+                self.events.push(event);
+            }
+        }
+    }
+
+    /// `button` を `hold` tick 押して離し、離した後も数 tick 回す。
+    fn click(&mut self, button: Button, hold: u32) {
+        let pin = match button {
+            Button::Up => self.up.clone(),
+            Button::Down => self.down.clone(),
+            Button::Select => self.select.clone(),
+        };
+        pin.press();
+        self.run(hold);
+        pin.release();
+        self.run(3);
+    }
+
+    fn script(&mut self, buttons: &[Button]) {
+        for button in buttons {
+            self.click(*button, 3);
+        }
+    }
+
+    fn lcd_lines(&self) -> (String, String) {
+        let frame = self.lcd.frame();
+        let line = |row| {
+            String::from_utf8_lossy(frame.line(row))
+                .trim_end()
+                .to_string()
+        };
+        (line(0), line(1))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Button {
+    Up,
+    Down,
+    Select,
+}
+
+#[test]
+fn scripted_presses_edit_refresh_period_on_the_lcd() {
+    let mut rig = Rig::new();
+    assert_eq!(
+        rig.lcd_lines(),
+        (">Display       >".into(), " Factory reset".into())
+    );
+
+    rig.script(&[Button::Select]);
+    assert_eq!(
+        rig.lcd_lines(),
+        (">Refresh      1s".into(), " Backlight    on".into())
+    );
+
+    rig.script(&[
+        Button::Select,
+        Button::Up,
+        Button::Up,
+        Button::Up,
+        Button::Up,
+    ]);
+    assert_eq!(rig.lcd_lines(), ("Refresh".into(), "= 5s".into()));
+    rig.script(&[Button::Select]);
+    assert_eq!(
+        rig.events,
+        [MenuEvent::Changed {
+            key: KEY_REFRESH_SECONDS,
+            value: 5
+        }]
+    );
+
+    // 保存された値から ClimateDisplayApp の設定を組み立て直す
+    let display = TerminalDisplay16x2::new();
+    let mut climate = ClimateDisplayApp::new(
+        SimulatedEnvSensor::looping(demo_sensor_readings()),
+        display.clone(),
+    );
+    let seconds = REFRESH.load(rig.app.store_mut()).unwrap() as u32;
+    climate.set_config(ClimateDisplayConfig {
+        refresh_period_ticks: seconds * TICKS_PER_SECOND,
+        ..climate.config()
+    });
+    for _ in 0..1_000 {
+        climate.tick().unwrap();
+    }
+    // 初回 + 500 tick ごと
+    assert_eq!(display.render_count(), 3);
+}
+
+#[test]
+fn choice_and_long_press_back_navigation() {
+    let mut rig = Rig::new();
+    rig.script(&[
+        Button::Select,
+        Button::Down,
+        Button::Select,
+        Button::Down,
+        Button::Select,
+    ]);
+    assert_eq!(
+        rig.events,
+        [MenuEvent::Changed {
+            key: KEY_BACKLIGHT,
+            value: 0
+        }]
+    );
+    assert_eq!(rig.lcd_lines().0, ">Backlight   off");
+    assert_eq!(BACKLIGHT.option(rig.app.store_mut()).unwrap(), "off");
+
+    // Select 長押しで親メニューへ戻る。離しても Select にはならない
+    rig.click(Button::Select, 60);
+    assert_eq!(rig.app.menu().depth(), 0);
+    assert_eq!(rig.lcd_lines().0, ">Display       >");
+
+    rig.click(Button::Select, 60);
+    assert_eq!(rig.events.last(), Some(&MenuEvent::Exit));
+}
+
+#[test]
+fn factory_reset_requires_confirmation() {
+    let mut rig = Rig::new();
+    rig.app.store_mut().store(KEY_REFRESH_SECONDS, 30).unwrap();
+
+    rig.script(&[Button::Down, Button::Select]);
+    assert_eq!(
+        rig.lcd_lines(),
+        ("Erase settings?".into(), ">No   Yes".into())
+    );
+    rig.script(&[Button::Select]);
+    assert!(rig.events.is_empty());
+
+    rig.script(&[Button::Select, Button::Up, Button::Select]);
+    assert_eq!(rig.events, [MenuEvent::Action(ACTION_FACTORY_RESET)]);
+    assert_eq!(rig.lcd_lines(), (">Factory reset".into(), " < Back".into()));
+}
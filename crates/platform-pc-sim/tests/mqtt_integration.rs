@@ -0,0 +1,243 @@
+//! `core_app::mqtt::MqttClient` を `StdTcpSocket` 越しにブローカー代役へ接続し、
+//! テレメトリの publish・コマンドによるアクチュエータ操作・再接続・last-will・
+//! QoS 1 の再送を end-to-end で確認する。
+
+use std::time::{Duration, Instant};
+
+use core_app::mqtt::{
+    Command, CommandDispatcher, LastWill, MqttClient, MqttConfig, MqttEvent, MqttState, QoS,
+    TopicScheme,
+};
+use core_app::telemetry::{TelemetryFormat, TelemetryFrame};
+use hal_api::actuator::{MotorCommand, MotorDirection};
+use hal_api::sensor::EnvReading;
+use platform_pc_sim::l298n_mock::MockL298nDevice;
+use platform_pc_sim::mqtt_broker::MqttBroker;
+use platform_pc_sim::servo_mock::MockServoDevice;
+use platform_pc_sim::std_socket::StdTcpSocket;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const TOPICS: TopicScheme<'static> = TopicScheme::new("lab", "rover-1");
+
+type Client = MqttClient<'static, StdTcpSocket, 512>;
+
+/// コマンドを受けてアクチュエータを動かすデバイス側。
+struct Device {
+    client: Client,
+    dispatcher: CommandDispatcher<MockServoDevice, MockL298nDevice>,
+    commands: Vec<Command>,
+    events: Vec<String>,
+    started: Instant,
+}
+
+impl Device {
+    fn new(port: u16) -> Self {
+        let config = MqttConfig::new("127.0.0.1", "rover-1")
+            .with_port(port)
+            .with_keep_alive(30)
+            .with_will(LastWill {
+                topic: "lab/rover-1/status",
+                payload: b"offline",
+                qos: QoS::AtLeastOnce,
+                retain: true,
+            });
+        let config = MqttConfig {
+            reconnect_delay_ms: 50,
+            ack_timeout_ms: 200,
+            ..config
+        };
+        let mut client = MqttClient::new(StdTcpSocket::new(), config);
+        let filter = TOPICS.command_filter().unwrap();
+        client.subscribe(&filter, QoS::AtLeastOnce, 0).unwrap();
+        Self {
+            client,
+            dispatcher: CommandDispatcher::new(MockServoDevice::new(), MockL298nDevice::new()),
+            commands: Vec::new(),
+            events: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+
+    fn now_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn poll(&mut self) {
+        let now_ms = self.now_ms();
+        let dispatcher = &mut self.dispatcher;
+        let commands = &mut self.commands;
+        let events = &mut self.events;
+        let mut came_online = false;
+        self.client.poll(now_ms, |event| match event {
+            MqttEvent::Message(message) => {
+                if let Some(result) = dispatcher.handle(&TOPICS, &message) {
+                    commands.push(result.expect("command should apply"));
+                }
+            }
+            MqttEvent::Connected { .. } => {
+                came_online = true;
+                events.push("connected".to_string());
+            }
+            other => events.push(format!("{other:?}")),
+        });
+        if came_online {
+            let status = TOPICS.status().unwrap();
+            self.client
+                .publish(&status, b"online", QoS::AtLeastOnce, true, now_ms)
+                .unwrap();
+        }
+    }
+
+    /// `done` が真になるまで poll し続ける。
+    fn poll_until(&mut self, what: &str, mut done: impl FnMut(&Self) -> bool) {
+        let deadline = Instant::now() + TIMEOUT;
+        while !done(self) {
+            assert!(Instant::now() < deadline, "timed out waiting for {what}");
+            self.poll();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+fn status_payloads(broker: &MqttBroker) -> Vec<Vec<u8>> {
+    broker
+        .messages()
+        .into_iter()
+        .filter(|m| m.topic == "lab/rover-1/status")
+        .map(|m| m.payload)
+        .collect()
+}
+
+#[test]
+fn telemetry_is_published_and_commands_drive_the_actuators() {
+    let broker = MqttBroker::start().unwrap();
+    let mut device = Device::new(broker.port());
+    device.poll_until("connection", |d| d.client.is_connected());
+    device.poll_until("online status", |_| {
+        status_payloads(&broker) == [b"online".to_vec()]
+    });
+
+    let frame = TelemetryFrame::new("rover-1")
+        .with_unix_timestamp(1_700_000_000)
+        .with_env(EnvReading::new(2_345, 5_120, Some(101_325)));
+    let topic = TOPICS.telemetry().unwrap();
+    let now_ms = device.now_ms();
+    device
+        .client
+        .publish_telemetry(
+            &topic,
+            &frame,
+            TelemetryFormat::Json,
+            QoS::AtLeastOnce,
+            now_ms,
+        )
+        .unwrap();
+    device.poll_until("PUBACK", |d| d.client.in_flight() == 0);
+
+    let telemetry = broker
+        .wait_for_message("lab/+/telemetry", TIMEOUT)
+        .expect("telemetry should reach the broker");
+    assert_eq!(telemetry.from.as_deref(), Some("rover-1"));
+    assert_eq!(telemetry.qos, QoS::AtLeastOnce);
+    let json: serde_json::Value = serde_json::from_slice(&telemetry.payload).unwrap();
+    assert_eq!(json["device_id"], "rover-1");
+    assert_eq!(json["temperature"], 23.45);
+    assert_eq!(json["pressure_pa"], 101_325);
+
+    broker.publish("lab/rover-1/cmd/servo", b"135", QoS::AtLeastOnce, false);
+    broker.publish(
+        "lab/rover-1/cmd/motors",
+        b"forward:60,reverse:40",
+        QoS::AtMostOnce,
+        false,
+    );
+    // 別デバイス宛てのコマンドは無視される
+    broker.publish("lab/rover-2/cmd/servo", b"10", QoS::AtMostOnce, false);
+    broker.publish("lab/rover-1/cmd/stop", b"", QoS::AtLeastOnce, false);
+    device.poll_until("three commands", |d| d.commands.len() == 3);
+
+    let servo = device.dispatcher.servo();
+    assert_eq!(servo.history(), vec![135]);
+    let motors = device.dispatcher.motors();
+    let brake = MotorCommand::new(MotorDirection::Brake, 0);
+    assert_eq!(
+        motors.left.history(),
+        vec![MotorCommand::new(MotorDirection::Forward, 60), brake]
+    );
+    assert_eq!(
+        motors.right.history(),
+        vec![MotorCommand::new(MotorDirection::Reverse, 40), brake]
+    );
+
+    device.client.disconnect(device.now_ms());
+    assert_eq!(device.client.state(), MqttState::Stopped);
+    let deadline = Instant::now() + TIMEOUT;
+    while !broker.connected_clients().is_empty() {
+        assert!(
+            Instant::now() < deadline,
+            "broker should see the disconnect"
+        );
+        std::thread::sleep(Duration::from_millis(2));
+    }
+    // DISCONNECT で抜けたので last-will は出ない
+    assert_eq!(status_payloads(&broker), [b"online".to_vec()]);
+}
+
+#[test]
+fn dropped_connection_publishes_will_then_reconnects_and_resubscribes() {
+    let broker = MqttBroker::start().unwrap();
+    let mut device = Device::new(broker.port());
+    // "online" の PUBACK 前に切ると、再接続後に DUP 付きでもう一度届いてしまう
+    device.poll_until("online status acked", |d| {
+        d.client.is_connected() && d.client.in_flight() == 0
+    });
+    assert!(broker.wait_for_client("rover-1", TIMEOUT));
+
+    broker.drop_client("rover-1");
+    device.poll_until("disconnect event", |d| {
+        d.events.iter().any(|e| e.starts_with("Disconnected"))
+    });
+    device.poll_until("second connection", |d| {
+        d.events.iter().filter(|e| *e == "connected").count() == 2
+    });
+    device.poll_until("online again", |_| {
+        status_payloads(&broker) == [b"online".to_vec(), b"offline".to_vec(), b"online".to_vec()]
+    });
+
+    // 再接続後も購読が生きている
+    broker.publish("lab/rover-1/cmd/servo", b"45", QoS::AtLeastOnce, false);
+    device.poll_until("servo command", |d| !d.commands.is_empty());
+    assert_eq!(device.commands, [Command::Servo { angle_degrees: 45 }]);
+    assert_eq!(device.dispatcher.servo().current_angle(), 45);
+}
+
+#[test]
+fn unacknowledged_qos1_publish_is_resent_with_dup_flag() {
+    let broker = MqttBroker::start().unwrap();
+    let mut device = Device::new(broker.port());
+    device.poll_until("online status acked", |d| {
+        d.client.is_connected() && d.client.in_flight() == 0
+    });
+
+    broker.withhold_pubacks(1);
+    let now_ms = device.now_ms();
+    let packet_id = device
+        .client
+        .publish("lab/rover-1/log", b"boot", QoS::AtLeastOnce, false, now_ms)
+        .unwrap()
+        .unwrap();
+    device.poll_until("PUBACK after retransmit", |d| d.client.in_flight() == 0);
+    assert!(device
+        .events
+        .contains(&format!("Published {{ packet_id: {packet_id} }}")));
+
+    let copies: Vec<_> = broker
+        .messages()
+        .into_iter()
+        .filter(|m| m.topic == "lab/rover-1/log")
+        .collect();
+    assert_eq!(copies.len(), 2, "{copies:?}");
+    assert!(!copies[0].dup);
+    assert!(copies[1].dup);
+    assert_eq!(copies[1].payload, b"boot");
+}
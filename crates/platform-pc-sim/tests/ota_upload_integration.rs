@@ -0,0 +1,242 @@
+//! End-to-end: `ota-upload` packages and signs an ESP app image, switches the
+//! running firmware to the updater and streams the image to a mock updater that
+//! parses requests with the firmware's own `ota_http::read_ota_request`.
+//! Dropped connections, 5xx and rejected manifests are exercised through
+//! `OtaClient` directly.
+
+use std::fs;
+use std::process::{Command, Output};
+
+use ota_core::manifest::{self, ImageManifest};
+use platform_pc_sim::esp_image::{build_image, AppDescriptor, EspChip};
+use platform_pc_sim::ota_client::{
+    hex_decode, OtaClient, OtaClientError, OtaEvent, OtaTarget, RetryPolicy,
+};
+use platform_pc_sim::ota_updater_mock::{MockUpdater, MockUpdaterConfig};
+
+const TOKEN: &str = "test-token";
+const SECRET: [u8; 32] = [0x5A; 32];
+
+fn firmware_image(version: &str) -> Vec<u8> {
+    let code = vec![0x36; 20_000];
+    build_image(
+        EspChip::Esp32,
+        0x4008_0400,
+        &AppDescriptor::new("wifi-climate", version),
+        &[(0x3F40_0020, &[0x11; 1024]), (0x4008_0000, &code)],
+    )
+}
+
+fn ota_upload(args: &[&str], token: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ota-upload"))
+        .args(args)
+        .env("OTA_AUTH_TOKEN", token)
+        .output()
+        .expect("ota-upload should run")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn client() -> OtaClient {
+    OtaClient::new(TOKEN).with_retry(RetryPolicy::immediate(3))
+}
+
+#[test]
+fn cli_packages_signs_switches_and_uploads() {
+    let dir = tempfile::tempdir().unwrap();
+    let key = dir.path().join("signing.key");
+    let image_path = dir.path().join("app.bin");
+    let image = firmware_image("0.4.0");
+    fs::write(&image_path, &image).unwrap();
+
+    let keygen = ota_upload(&["keygen", key.to_str().unwrap()], TOKEN);
+    assert!(keygen.status.success(), "{keygen:?}");
+    let public_key: [u8; 32] = stdout(&keygen)
+        .lines()
+        .find_map(|line| line.strip_prefix("public key: "))
+        .and_then(hex_decode)
+        .unwrap()
+        .try_into()
+        .unwrap();
+
+    let package = ota_upload(
+        &[
+            "package",
+            image_path.to_str().unwrap(),
+            "--version",
+            "4",
+            "--key",
+            key.to_str().unwrap(),
+        ],
+        TOKEN,
+    );
+    assert!(package.status.success(), "{package:?}");
+    let report = stdout(&package);
+    assert!(report.contains("project:   wifi-climate 0.4.0"), "{report}");
+    assert!(report.contains(&format!(
+        "md5:       {:x}",
+        platform_pc_sim::esp_rom::md5::compute(&image)
+    )));
+    let packaged = dir.path().join("app.ota.bin");
+    assert_eq!(fs::read(&packaged).unwrap(), image);
+
+    let updater =
+        MockUpdater::start(MockUpdaterConfig::new(TOKEN).with_public_key(public_key, 3)).unwrap();
+    let address = format!("127.0.0.1:{}", updater.port());
+    let send = ota_upload(
+        &[
+            "send",
+            &address,
+            packaged.to_str().unwrap(),
+            "--switch",
+            &address,
+        ],
+        TOKEN,
+    );
+    assert!(send.status.success(), "{send:?}");
+    let log = stdout(&send);
+    assert!(log.contains("switching to updater"), "{log}");
+    assert!(log.contains("100%"), "{log}");
+    assert!(log.contains("OTA OK"), "{log}");
+
+    assert_eq!(updater.switch_requests(), 1);
+    let uploads = updater.uploads();
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(uploads[0].image, image);
+    assert_eq!(uploads[0].manifest.unwrap().version, 4);
+    assert_eq!(updater.active_version(), Some(4));
+}
+
+#[test]
+fn cli_rejects_broken_images_and_wrong_tokens() {
+    let dir = tempfile::tempdir().unwrap();
+    let image_path = dir.path().join("app.bin");
+    let mut image = firmware_image("0.4.0");
+    let last = image.len() - 1;
+    image[last] ^= 0xFF;
+    fs::write(&image_path, &image).unwrap();
+
+    let inspect = ota_upload(&["inspect", image_path.to_str().unwrap()], TOKEN);
+    assert!(!inspect.status.success());
+    assert!(String::from_utf8_lossy(&inspect.stderr).contains("SHA-256"));
+
+    let updater = MockUpdater::start(MockUpdaterConfig::new(TOKEN)).unwrap();
+    let address = format!("127.0.0.1:{}", updater.port());
+    let send = ota_upload(&["send", &address, image_path.to_str().unwrap()], "wrong");
+    assert!(!send.status.success());
+    assert!(String::from_utf8_lossy(&send.stderr).contains("HTTP 401"));
+    // 4xx is a configuration error and is not retried
+    assert_eq!(updater.statuses(), vec![401]);
+    assert!(updater.uploads().is_empty());
+}
+
+#[test]
+fn cli_rejects_unknown_and_misspelled_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let image_path = dir.path().join("app.bin");
+    fs::write(&image_path, firmware_image("0.4.0")).unwrap();
+    let image = image_path.to_str().unwrap();
+
+    let updater = MockUpdater::start(MockUpdaterConfig::new(TOKEN)).unwrap();
+    let address = format!("127.0.0.1:{}", updater.port());
+    for (args, option) in [
+        (vec!["send", &address, image, "--retry", "5"], "--retry"),
+        (
+            vec!["package", image, "--verison", "7", "--key", "k"],
+            "--verison",
+        ),
+        // Valid for `package`, but not for `inspect`
+        (vec!["inspect", image, "--out", "x.bin"], "--out"),
+    ] {
+        let output = ota_upload(&args, TOKEN);
+        assert!(!output.status.success(), "{args:?}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.starts_with(&format!("unknown option {option}\nusage:")),
+            "{stderr}"
+        );
+    }
+    assert!(updater.statuses().is_empty());
+}
+
+#[test]
+fn dropped_connections_and_server_errors_are_retried_from_the_start() {
+    let updater = MockUpdater::start(MockUpdaterConfig::new(TOKEN)).unwrap();
+    let target = OtaTarget::new("127.0.0.1", updater.port());
+    let image = firmware_image("0.5.0");
+
+    updater.drop_next_uploads(1, 4096);
+    let mut events = Vec::new();
+    let response = client()
+        .upload(&target, &image, None, |event| events.push(event))
+        .unwrap();
+    assert_eq!(response.body, "OTA OK\n");
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, OtaEvent::Retrying { attempt: 2, .. })));
+    assert_eq!(
+        events.last(),
+        Some(&OtaEvent::Progress {
+            sent: image.len(),
+            total: image.len()
+        })
+    );
+
+    updater.fail_next_uploads(1);
+    client().upload(&target, &image, None, |_| {}).unwrap();
+    assert_eq!(updater.statuses(), vec![200, 500, 200]);
+    assert_eq!(updater.uploads().len(), 2);
+    assert!(updater.uploads().iter().all(|u| u.image == image));
+
+    updater.fail_next_uploads(3);
+    let error = client().upload(&target, &image, None, |_| {}).unwrap_err();
+    assert!(matches!(error, OtaClientError::Status { status: 500, .. }));
+}
+
+#[test]
+fn verifying_updater_rejects_unsigned_tampered_and_downgraded_images() {
+    let key = manifest::public_key(&SECRET);
+    let updater =
+        MockUpdater::start(MockUpdaterConfig::new(TOKEN).with_public_key(key, 5)).unwrap();
+    let target = OtaTarget::new("127.0.0.1", updater.port());
+    let image = firmware_image("0.6.0");
+
+    let unsigned = client().upload(&target, &image, None, |_| {}).unwrap_err();
+    assert!(matches!(
+        unsigned,
+        OtaClientError::Status { status: 403, .. }
+    ));
+
+    let old = ImageManifest::sign(&image, 5, &SECRET);
+    let downgrade = client()
+        .upload(&target, &image, Some(&old), |_| {})
+        .unwrap_err();
+    assert!(matches!(
+        downgrade,
+        OtaClientError::Status { status: 409, .. }
+    ));
+
+    let forged = ImageManifest::sign(&image, 6, &[0x01; 32]);
+    let forged = client()
+        .upload(&target, &image, Some(&forged), |_| {})
+        .unwrap_err();
+    assert!(matches!(forged, OtaClientError::Status { status: 403, .. }));
+
+    // Signed manifest, but the body differs: caught by the read-back hash
+    let manifest = ImageManifest::sign(&image, 6, &SECRET);
+    let mut tampered = image.clone();
+    tampered[100] ^= 0x01;
+    let error = client()
+        .upload(&target, &tampered, Some(&manifest), |_| {})
+        .unwrap_err();
+    assert!(matches!(error, OtaClientError::Status { status: 500, .. }));
+    assert!(updater.uploads().is_empty());
+    assert_eq!(updater.active_version(), Some(5));
+
+    client()
+        .upload(&target, &image, Some(&manifest), |_| {})
+        .unwrap();
+    assert_eq!(updater.active_version(), Some(6));
+}
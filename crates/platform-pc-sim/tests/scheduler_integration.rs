@@ -0,0 +1,136 @@
+//! `Scheduler` composing several core-app apps on the pc-sim mocks:
+//! blink `App` + `ClimateDisplayApp` + `SpeedControlledMotor` (+ plant step) on one 10 ms tick.
+
+use core_app::climate_display::{ClimateDisplayApp, ClimateDisplayConfig};
+use core_app::scheduler::{NoClock, Scheduler, TaskConfig, TaskError, TaskState};
+use core_app::speed_control::SpeedControlledMotor;
+use core_app::App;
+use hal_api::actuator::{DriveMotor, MotorCommand, MotorDirection};
+use hal_api::encoder::EncoderSpeedSensor;
+use platform_pc_sim::climate_sim::{demo_sensor_readings, SimulatedEnvSensor, TerminalDisplay16x2};
+use platform_pc_sim::mock_hal::{MockI2c, MockPin};
+use platform_pc_sim::motor_sim::{demo_speed_control_config, DcMotorModel, SimulatedDcMotor};
+
+const TICK_MS: u32 = 10;
+
+#[test]
+fn scheduler_runs_multiple_apps_side_by_side() {
+    let pin = MockPin::new(13);
+    let i2c = MockI2c::new();
+    let mut blink = App::new(pin.clone(), i2c.clone());
+
+    let display = TerminalDisplay16x2::new();
+    let sensor = SimulatedEnvSensor::looping(demo_sensor_readings());
+    let mut climate = ClimateDisplayApp::new_with_config(
+        sensor.clone(),
+        display.clone(),
+        ClimateDisplayConfig {
+            refresh_period_ticks: 1,
+            refresh_on_first_tick: true,
+        },
+    );
+
+    let plant = SimulatedDcMotor::new();
+    let model = DcMotorModel::default();
+    let control_period_ticks = 10;
+    let tach = EncoderSpeedSensor::new(
+        plant.clone(),
+        model.counts_per_revolution,
+        TICK_MS * control_period_ticks,
+    );
+    let mut speed_config = demo_speed_control_config();
+    speed_config.control_period_ticks = 1;
+    let mut motor = SpeedControlledMotor::new_with_config(plant.clone(), tach, speed_config);
+    motor
+        .apply(MotorCommand::new(MotorDirection::Forward, 50))
+        .unwrap();
+    let plant_step = plant.clone();
+    let mut step_plant = move || -> Result<(), TaskError> {
+        plant_step.step(TICK_MS);
+        Ok(())
+    };
+
+    let mut scheduler: Scheduler<'_, NoClock, 4> = Scheduler::new(NoClock);
+    scheduler
+        .add_task(&mut step_plant, TaskConfig::every("plant", 1))
+        .unwrap();
+    scheduler
+        .add_task(&mut blink, TaskConfig::every("blink", 1))
+        .unwrap();
+    let climate_id = scheduler
+        .add_task(
+            &mut climate,
+            TaskConfig {
+                offset_ticks: 50,
+                ..TaskConfig::every("climate", 100)
+            },
+        )
+        .unwrap();
+    let motor_id = scheduler
+        .add_task(
+            &mut motor,
+            TaskConfig {
+                offset_ticks: 5,
+                ..TaskConfig::every("motor", control_period_ticks)
+            },
+        )
+        .unwrap();
+
+    for _ in 0..3_000 {
+        let report = scheduler.tick();
+        assert_eq!(report.failed, 0);
+    }
+
+    assert_eq!(scheduler.stats(climate_id).unwrap().runs, 30);
+    assert_eq!(scheduler.stats(motor_id).unwrap().runs, 300);
+    drop(scheduler);
+
+    // blink App: 100 tick ごとにトグル、500 tick ごとに I2C 読み出し
+    assert_eq!(pin.history().len(), 30);
+    assert_eq!(i2c.read_count(), 6);
+    assert_eq!(sensor.read_count(), 30);
+    assert_eq!(display.render_count(), 30);
+    assert!((plant.rpm() - 150.0).abs() < 15.0, "rpm = {}", plant.rpm());
+}
+
+#[test]
+fn failing_app_is_suspended_without_stalling_the_others() {
+    let pin = MockPin::new(13);
+    let i2c = MockI2c::new();
+    let mut blink = App::new(pin.clone(), i2c);
+
+    // 読み値の無いセンサは NotInitialized を返し続ける
+    let mut climate = ClimateDisplayApp::new_with_config(
+        SimulatedEnvSensor::new(Vec::new()),
+        TerminalDisplay16x2::new(),
+        ClimateDisplayConfig {
+            refresh_period_ticks: 1,
+            refresh_on_first_tick: true,
+        },
+    );
+
+    let mut scheduler: Scheduler<'_, NoClock, 2> = Scheduler::new(NoClock);
+    let climate_id = scheduler
+        .add_task(
+            &mut climate,
+            TaskConfig {
+                max_consecutive_errors: 3,
+                ..TaskConfig::every("climate", 10)
+            },
+        )
+        .unwrap();
+    scheduler
+        .add_task(&mut blink, TaskConfig::every("blink", 1))
+        .unwrap();
+
+    for _ in 0..1_000 {
+        scheduler.tick();
+    }
+
+    let stats = *scheduler.stats(climate_id).unwrap();
+    assert_eq!(stats.state, TaskState::Suspended);
+    assert_eq!(stats.runs, 3);
+    assert_eq!(stats.last_error, Some(TaskError::Sensor));
+    drop(scheduler);
+    assert_eq!(pin.history().len(), 10);
+}
@@ -0,0 +1,186 @@
+//! Persistent settings on a file-backed flash: schema migration across a "firmware update",
+//! a torn write during save, and menu edits surviving a reboot through `FileKvStore`.
+
+use core_app::menu::{Menu, MenuInput, MenuItem, NumberSetting, SettingsStore};
+use core_app::settings::{PersistentSettings, SettingsSchema, SettingsSource};
+use hal_api::kv::KeyValueStore;
+use platform_pc_sim::storage_mock::{open_kv_store, FileBlockStorage};
+
+const BLOCK_SIZE: u32 = 4_096;
+const BLOCK_COUNT: u32 = 2;
+const SLOT: usize = 64;
+
+/// 旧ファームウェアの設定: 送信周期 (秒) のみ。
+struct ConfigV1 {
+    post_interval_s: u16,
+}
+
+impl SettingsSchema for ConfigV1 {
+    const VERSION: u16 = 1;
+
+    fn encode(&self, out: &mut [u8]) -> Option<usize> {
+        out.get_mut(..2)?
+            .copy_from_slice(&self.post_interval_s.to_le_bytes());
+        Some(2)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            post_interval_s: u16::from_le_bytes(bytes.try_into().ok()?),
+        })
+    }
+}
+
+/// 新ファームウェアの設定: 周期をミリ秒に変え、LCD のバックライト設定を追加した。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ConfigV2 {
+    post_interval_ms: u32,
+    backlight: bool,
+}
+
+impl Default for ConfigV2 {
+    fn default() -> Self {
+        Self {
+            post_interval_ms: 30_000,
+            backlight: true,
+        }
+    }
+}
+
+impl SettingsSchema for ConfigV2 {
+    const VERSION: u16 = 2;
+
+    fn encode(&self, out: &mut [u8]) -> Option<usize> {
+        let out = out.get_mut(..5)?;
+        out[..4].copy_from_slice(&self.post_interval_ms.to_le_bytes());
+        out[4] = u8::from(self.backlight);
+        Some(5)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let bytes: [u8; 5] = bytes.try_into().ok()?;
+        Some(Self {
+            post_interval_ms: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            backlight: bytes[4] != 0,
+        })
+    }
+
+    fn migrate(from_version: u16, bytes: &[u8]) -> Option<Self> {
+        match from_version {
+            1 => Some(Self {
+                post_interval_ms: u32::from(ConfigV1::decode(bytes)?.post_interval_s) * 1_000,
+                ..Self::default()
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn firmware_update_migrates_settings_and_survives_torn_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("settings.bin");
+    let open = || FileBlockStorage::open(&path, BLOCK_SIZE, BLOCK_COUNT).unwrap();
+
+    // 旧ファームウェアが保存
+    let mut old: PersistentSettings<_, SLOT> = PersistentSettings::mount(open()).unwrap();
+    old.save(&ConfigV1 {
+        post_interval_s: 60,
+    })
+    .unwrap();
+    drop(old);
+
+    // 新ファームウェアの初回起動で変換して保存し直す
+    let mut settings: PersistentSettings<_, SLOT> = PersistentSettings::mount(open()).unwrap();
+    let (config, source) = settings.load::<ConfigV2>().unwrap();
+    assert_eq!(source, SettingsSource::Migrated { from: 1 });
+    assert_eq!(
+        config,
+        ConfigV2 {
+            post_interval_ms: 60_000,
+            backlight: true
+        }
+    );
+    drop(settings);
+
+    // 保存中に電源断
+    let mut storage = open();
+    storage.tear_next_write(7);
+    let mut settings: PersistentSettings<_, SLOT> = PersistentSettings::mount(storage).unwrap();
+    assert_eq!(
+        settings.load::<ConfigV2>().unwrap().1,
+        SettingsSource::Stored
+    );
+    assert!(settings
+        .save(&ConfigV2 {
+            post_interval_ms: 5_000,
+            backlight: false
+        })
+        .is_err());
+    drop(settings);
+
+    // 再起動後は直前の値に戻り、次の保存は問題なく行える
+    let mut settings: PersistentSettings<_, SLOT> = PersistentSettings::mount(open()).unwrap();
+    assert_eq!(
+        settings.load::<ConfigV2>().unwrap().0.post_interval_ms,
+        60_000
+    );
+    settings
+        .save(&ConfigV2 {
+            post_interval_ms: 5_000,
+            backlight: false,
+        })
+        .unwrap();
+    drop(settings);
+
+    let mut settings: PersistentSettings<_, SLOT> = PersistentSettings::mount(open()).unwrap();
+    assert_eq!(
+        settings.load::<ConfigV2>().unwrap(),
+        (
+            ConfigV2 {
+                post_interval_ms: 5_000,
+                backlight: false
+            },
+            SettingsSource::Stored
+        )
+    );
+}
+
+static ITEMS: [MenuItem; 1] = [MenuItem::Number(
+    NumberSetting::new("Post every", 1, 5, 600, 30)
+        .step(5)
+        .unit("s"),
+)];
+
+#[test]
+fn menu_edits_and_credentials_persist_in_file_kv_store() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nvs.bin");
+
+    {
+        let mut store = open_kv_store::<8>(&path).unwrap();
+        store.set("wifi_ssid", b"workshop").unwrap();
+        store.set("wifi_psk", b"correct horse").unwrap();
+
+        let mut menu: Menu<1> = Menu::new("Settings", &ITEMS);
+        for input in [
+            MenuInput::Select,
+            MenuInput::Up,
+            MenuInput::Up,
+            MenuInput::Select,
+        ] {
+            menu.handle(input, &mut store).unwrap();
+        }
+    }
+
+    let mut store = open_kv_store::<8>(&path).unwrap();
+    assert_eq!(SettingsStore::load(&mut store, 1).unwrap(), Some(40));
+    let mut buffer = [0u8; 64];
+    let len = store.get("wifi_psk", &mut buffer).unwrap().unwrap();
+    assert_eq!(&buffer[..len], b"correct horse");
+    assert_eq!(store.record().len(), 3);
+
+    let menu: Menu<1> = Menu::new("Settings", &ITEMS);
+    let frame = menu.render(&mut store).unwrap();
+    assert_eq!(frame.line(0), b">Post every  40s");
+}
@@ -0,0 +1,150 @@
+//! End-to-end thermostat simulation: `ThermostatApp` + `SimulatedRoom` thermal model +
+//! `SimulatedRtc` schedule clock + `TerminalDisplay16x2`, one tick per simulated second.
+
+use core_app::thermostat::{
+    PwmDemandOutput, RelayOutput, ThermostatApp, ThermostatConfig, ThermostatStatus,
+};
+use hal_api::rtc::RtcDateTime;
+use platform_pc_sim::climate_sim::TerminalDisplay16x2;
+use platform_pc_sim::mock_hal::MockPin;
+use platform_pc_sim::pwm_mock::MockPwmOutput;
+use platform_pc_sim::thermal_sim::{
+    demo_schedule, HeaterDrive, RoomThermalModel, SimulatedRoom, SimulatedRtc,
+};
+
+const TICK_MS: u32 = 1_000;
+
+fn config() -> ThermostatConfig {
+    ThermostatConfig {
+        sample_period_ticks: 10,
+        min_on_ticks: 120,
+        min_off_ticks: 120,
+        min_cycle_ticks: 300,
+        ..ThermostatConfig::default()
+    }
+}
+
+#[test]
+fn relay_thermostat_follows_daily_schedule() {
+    let relay = MockPin::new(5);
+    let room = SimulatedRoom::new(
+        RoomThermalModel::default(),
+        HeaterDrive::Relay(relay.clone()),
+    );
+    let rtc = SimulatedRtc::new(RtcDateTime::new(25, 1, 6, 0, 0, 0));
+    let display = TerminalDisplay16x2::new();
+    let mut app = ThermostatApp::new_with_config(
+        room.clone(),
+        rtc.clone(),
+        RelayOutput::new(relay.clone()),
+        display.clone(),
+        config(),
+        demo_schedule(),
+    );
+
+    let mut last_level = false;
+    let mut last_change_tick = 0u32;
+    let mut last_start_tick: Option<u32> = None;
+    let mut daytime_samples = Vec::new();
+    for tick in 1..=24 * 3_600u32 {
+        room.step(TICK_MS);
+        rtc.step(TICK_MS);
+        app.tick().unwrap();
+
+        let level = relay.level();
+        if level != last_level {
+            let held = tick - last_change_tick;
+            if last_level {
+                assert!(held >= 120, "min on time violated at tick {tick}");
+            } else if last_change_tick != 0 {
+                assert!(held >= 120, "min off time violated at tick {tick}");
+            }
+            if level {
+                if let Some(start) = last_start_tick {
+                    assert!(tick - start >= 300, "short cycle at tick {tick}");
+                }
+                last_start_tick = Some(tick);
+            }
+            last_level = level;
+            last_change_tick = tick;
+        }
+
+        // 12:00–17:00 は 18℃ 設定で安定しているはず
+        let now = rtc.now();
+        if (12..17).contains(&now.hour) {
+            daytime_samples.push(room.temperature_celsius());
+        }
+    }
+
+    let mean = daytime_samples.iter().sum::<f32>() / daytime_samples.len() as f32;
+    assert!((mean - 18.0).abs() < 0.5, "daytime mean = {mean}");
+    assert!(daytime_samples.iter().all(|t| (t - 18.0).abs() < 1.5));
+    // 24h 経過後は 00:00 → 夜間設定
+    assert_eq!(app.setpoint_centi_celsius(), 1700);
+    assert!(display.last_ascii().unwrap().contains("SP 17.0C"));
+    assert!(room.duty_cycle() > 0.2 && room.duty_cycle() < 0.8);
+}
+
+#[test]
+fn pwm_heater_is_driven_at_configured_duty() {
+    let pwm = MockPwmOutput::new();
+    let room = SimulatedRoom::new(RoomThermalModel::default(), HeaterDrive::Pwm(pwm.clone()));
+    let rtc = SimulatedRtc::new(RtcDateTime::new(25, 1, 6, 7, 0, 0));
+    let mut app = ThermostatApp::new_with_config(
+        room.clone(),
+        rtc.clone(),
+        PwmDemandOutput::new(pwm.clone(), 60),
+        TerminalDisplay16x2::new(),
+        config(),
+        demo_schedule(),
+    );
+
+    for _ in 0..10 {
+        room.step(TICK_MS);
+        rtc.step(TICK_MS);
+        app.tick().unwrap();
+    }
+    assert_eq!(app.status(), ThermostatStatus::On);
+    assert_eq!(pwm.current_duty(), 60);
+}
+
+#[test]
+fn sensor_fault_turns_heater_off_and_recovers() {
+    let relay = MockPin::new(5);
+    let room = SimulatedRoom::new(
+        RoomThermalModel::default(),
+        HeaterDrive::Relay(relay.clone()),
+    );
+    let rtc = SimulatedRtc::new(RtcDateTime::new(25, 1, 6, 7, 0, 0));
+    let display = TerminalDisplay16x2::new();
+    let mut app = ThermostatApp::new_with_config(
+        room.clone(),
+        rtc.clone(),
+        RelayOutput::new(relay.clone()),
+        display.clone(),
+        config(),
+        demo_schedule(),
+    );
+    for _ in 0..10 {
+        app.tick().unwrap();
+    }
+    assert!(relay.level());
+
+    room.set_sensor_fault(true);
+    let mut errors = 0;
+    for _ in 0..10 {
+        if app.tick().is_err() {
+            errors += 1;
+        }
+    }
+    assert_eq!(errors, 1);
+    assert!(!relay.level());
+    assert!(display.last_ascii().unwrap().contains("FAULT"));
+
+    // 最小 OFF 時間と起動間隔が明けたら再起動する
+    room.set_sensor_fault(false);
+    for _ in 0..300 {
+        app.tick().unwrap();
+    }
+    assert!(relay.level());
+}
@@ -0,0 +1,31 @@
+//! `wiring-check` exit statuses as a CI gate sees them.
+
+use std::process::{Command, Output};
+
+fn wiring_check(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_wiring-check"))
+        .args(args)
+        .env_remove("BOARD_DEFS_DIR")
+        .output()
+        .expect("wiring-check should run")
+}
+
+#[test]
+fn unknown_options_fail_instead_of_falling_back_to_defaults() {
+    for args in [
+        &["--board", "m5stickc"][..],
+        &["pico", "--fail_on", "warning"][..],
+    ] {
+        let output = wiring_check(args);
+        assert_eq!(output.status.code(), Some(2), "{args:?}: {output:?}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.starts_with(&format!("unknown option {}\nusage:", args[args.len() - 2])),
+            "{stderr}"
+        );
+    }
+
+    // The correctly spelled option still gates on its findings.
+    let output = wiring_check(&["pico", "--profile", "minimal", "--fail-on", "warning"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+}
@@ -0,0 +1,374 @@
+//! サーモスタット用の熱モデル / 時計シミュレータ。
+//!
+//! - [`SimulatedRoom`]: 1 次遅れの部屋の熱モデル。ヒーター (またはクーラー) の出力を
+//!   [`MockPin`] / [`MockPwmOutput`] から読み取り、`step()` ごとに温度を進める。
+//!   `EnvSensor` として現在温度を返す
+//! - [`SimulatedRtc`]: `step()` で進む `RtcSensor`。1 日分のスケジュールを数秒で回せる
+//!
+//! どちらもクローン間で内部状態を共有します。
+//!
+//! # Examples
+//!
+//! ```
+//! use hal_api::sensor::EnvSensor;
+//! use platform_pc_sim::mock_hal::MockPin;
+//! use platform_pc_sim::thermal_sim::{HeaterDrive, RoomThermalModel, SimulatedRoom};
+//! use hal_api::gpio::OutputPin;
+//!
+//! let mut relay = MockPin::new(5);
+//! let mut room = SimulatedRoom::new(RoomThermalModel::default(), HeaterDrive::Relay(relay.clone()));
+//! let before = room.read().unwrap().temperature_centi_celsius;
+//! relay.set_high().unwrap();
+//! room.step(60_000);
+//! assert!(room.read().unwrap().temperature_centi_celsius > before);
+//! ```
+
+use crate::mock_hal::MockPin;
+use crate::pwm_mock::MockPwmOutput;
+use core_app::thermostat::{ScheduleEntry, SetpointSchedule};
+use hal_api::error::SensorError;
+use hal_api::pwm::PwmOutput;
+use hal_api::rtc::{RtcDateTime, RtcSensor};
+use hal_api::sensor::{EnvReading, EnvSensor};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// 部屋の熱モデルのパラメータ。
+///
+/// `dT/dt = (ambient + power * actuator_gain - T) / time_constant`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoomThermalModel {
+    /// 外気温 (℃)。
+    pub ambient_celsius: f32,
+    /// 初期室温 (℃)。
+    pub initial_celsius: f32,
+    /// 室温の時定数 (秒)。断熱が良いほど大きい。
+    pub time_constant_s: f32,
+    /// 出力 100 % を続けたときの外気温からの平衡温度差 (℃)。冷房なら負。
+    pub actuator_gain_celsius: f32,
+    /// `EnvSensor` が返す相対湿度 (1/100 %)。
+    pub humidity_centi_percent: u32,
+}
+
+impl Default for RoomThermalModel {
+    fn default() -> Self {
+        Self {
+            ambient_celsius: 8.0,
+            initial_celsius: 16.0,
+            time_constant_s: 1_800.0,
+            actuator_gain_celsius: 20.0,
+            humidity_centi_percent: 4_500,
+        }
+    }
+}
+
+/// 部屋を温める / 冷やす出力の観測元。
+#[derive(Clone, Debug)]
+pub enum HeaterDrive {
+    /// リレー: HIGH で 100 %。
+    Relay(MockPin),
+    /// PWM: duty がそのまま出力割合。
+    Pwm(MockPwmOutput),
+}
+
+impl HeaterDrive {
+    fn power(&self) -> f32 {
+        match self {
+            Self::Relay(pin) => {
+                if pin.level() {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Pwm(pwm) => pwm.duty_percent() as f32 / 100.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SimulatedRoomState {
+    model: RoomThermalModel,
+    temperature_celsius: f32,
+    elapsed_ms: u64,
+    /// 出力が入っていた累積時間 (出力割合で重み付け)。
+    actuator_on_ms: f64,
+    fail_reads: bool,
+}
+
+/// 1 次遅れの部屋の熱モデル。`EnvSensor` を実装する。
+#[derive(Clone, Debug)]
+pub struct SimulatedRoom {
+    drive: HeaterDrive,
+    state: Rc<RefCell<SimulatedRoomState>>,
+}
+
+impl SimulatedRoom {
+    pub fn new(model: RoomThermalModel, drive: HeaterDrive) -> Self {
+        Self {
+            drive,
+            state: Rc::new(RefCell::new(SimulatedRoomState {
+                model,
+                temperature_celsius: model.initial_celsius,
+                elapsed_ms: 0,
+                actuator_on_ms: 0.0,
+                fail_reads: false,
+            })),
+        }
+    }
+
+    /// `dt_ms` だけ時間を進める。出力はこの区間中一定とみなす。
+    pub fn step(&self, dt_ms: u32) {
+        let power = self.drive.power();
+        let mut state = self.state.borrow_mut();
+        let model = state.model;
+        let equilibrium = model.ambient_celsius + power * model.actuator_gain_celsius;
+        let alpha = 1.0 - (-(dt_ms as f32 / 1000.0) / model.time_constant_s.max(1.0)).exp();
+        state.temperature_celsius += (equilibrium - state.temperature_celsius) * alpha;
+        state.elapsed_ms += dt_ms as u64;
+        state.actuator_on_ms += power as f64 * dt_ms as f64;
+    }
+
+    pub fn temperature_celsius(&self) -> f32 {
+        self.state.borrow().temperature_celsius
+    }
+
+    /// 外気温を変える (昼夜の変化や窓の開放を模擬する)。
+    pub fn set_ambient_celsius(&self, ambient_celsius: f32) {
+        self.state.borrow_mut().model.ambient_celsius = ambient_celsius;
+    }
+
+    /// `true` の間は `read()` が `SensorError::BusError` を返す。
+    pub fn set_sensor_fault(&self, fail: bool) {
+        self.state.borrow_mut().fail_reads = fail;
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.state.borrow().elapsed_ms
+    }
+
+    /// 経過時間に占める出力 ON の割合 (0.0–1.0)。
+    pub fn duty_cycle(&self) -> f32 {
+        let state = self.state.borrow();
+        if state.elapsed_ms == 0 {
+            return 0.0;
+        }
+        (state.actuator_on_ms / state.elapsed_ms as f64) as f32
+    }
+}
+
+impl EnvSensor for SimulatedRoom {
+    type Error = SensorError;
+
+    fn read(&mut self) -> Result<EnvReading, Self::Error> {
+        let state = self.state.borrow();
+        if state.fail_reads {
+            return Err(SensorError::BusError);
+        }
+        Ok(EnvReading::new(
+            (state.temperature_celsius * 100.0).round() as i32,
+            state.model.humidity_centi_percent,
+            None,
+        ))
+    }
+}
+
+#[derive(Debug)]
+struct SimulatedRtcState {
+    now: RtcDateTime,
+    sub_second_ms: u32,
+}
+
+/// `step()` で進むシミュレーション時計。
+#[derive(Clone, Debug)]
+pub struct SimulatedRtc {
+    state: Rc<RefCell<SimulatedRtcState>>,
+}
+
+impl SimulatedRtc {
+    pub fn new(start: RtcDateTime) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(SimulatedRtcState {
+                now: start,
+                sub_second_ms: 0,
+            })),
+        }
+    }
+
+    pub fn now(&self) -> RtcDateTime {
+        self.state.borrow().now
+    }
+
+    /// `dt_ms` だけ時計を進める。日・月・年の繰り上がりも扱う。
+    pub fn step(&self, dt_ms: u32) {
+        let mut state = self.state.borrow_mut();
+        let total_ms = state.sub_second_ms as u64 + dt_ms as u64;
+        state.sub_second_ms = (total_ms % 1000) as u32;
+        let mut seconds = total_ms / 1000;
+        while seconds > 0 {
+            let now = &mut state.now;
+            let step = seconds.min(60 - now.second as u64);
+            seconds -= step;
+            let second = now.second as u64 + step;
+            if second < 60 {
+                now.second = second as u8;
+                continue;
+            }
+            now.second = 0;
+            advance_minute(now);
+        }
+    }
+}
+
+fn advance_minute(now: &mut RtcDateTime) {
+    now.minute += 1;
+    if now.minute < 60 {
+        return;
+    }
+    now.minute = 0;
+    now.hour += 1;
+    if now.hour < 24 {
+        return;
+    }
+    now.hour = 0;
+    now.day += 1;
+    if now.day <= days_in_month(now.year(), now.month) {
+        return;
+    }
+    now.day = 1;
+    now.month += 1;
+    if now.month <= 12 {
+        return;
+    }
+    now.month = 1;
+    now.year_offset = (now.year_offset + 1) % 100;
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl RtcSensor for SimulatedRtc {
+    type Error = SensorError;
+
+    fn read_datetime(&mut self) -> Result<RtcDateTime, Self::Error> {
+        Ok(self.now())
+    }
+
+    fn set_datetime(&mut self, dt: &RtcDateTime) -> Result<(), Self::Error> {
+        let mut state = self.state.borrow_mut();
+        state.now = *dt;
+        state.sub_second_ms = 0;
+        Ok(())
+    }
+}
+
+/// 平日を想定したデモ用スケジュール: 朝 21℃ / 日中 18℃ / 夕方 21.5℃ / 夜 17℃。
+pub fn demo_schedule() -> SetpointSchedule {
+    let mut schedule = SetpointSchedule::new();
+    for entry in [
+        ScheduleEntry::new(6, 30, 2100),
+        ScheduleEntry::new(9, 0, 1800),
+        ScheduleEntry::new(17, 30, 2150),
+        ScheduleEntry::new(22, 30, 1700),
+    ] {
+        schedule
+            .insert(entry)
+            .expect("demo schedule fits in capacity");
+    }
+    schedule
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hal_api::gpio::OutputPin;
+
+    #[test]
+    fn room_drifts_toward_ambient_when_off() {
+        let room = SimulatedRoom::new(
+            RoomThermalModel::default(),
+            HeaterDrive::Relay(MockPin::new(5)),
+        );
+        for _ in 0..(6 * 60) {
+            room.step(60_000);
+        }
+        assert!((room.temperature_celsius() - 8.0).abs() < 0.1);
+        assert_eq!(room.duty_cycle(), 0.0);
+    }
+
+    #[test]
+    fn pwm_drive_scales_equilibrium() {
+        let mut pwm = MockPwmOutput::new();
+        pwm.set_duty_percent(50).unwrap();
+        let room = SimulatedRoom::new(RoomThermalModel::default(), HeaterDrive::Pwm(pwm));
+        for _ in 0..(6 * 60) {
+            room.step(60_000);
+        }
+        // 8℃ + 0.5 * 20℃
+        assert!((room.temperature_celsius() - 18.0).abs() < 0.1);
+        assert!((room.duty_cycle() - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cooling_gain_lowers_temperature() {
+        let mut relay = MockPin::new(6);
+        relay.set_high().unwrap();
+        let room = SimulatedRoom::new(
+            RoomThermalModel {
+                ambient_celsius: 30.0,
+                initial_celsius: 30.0,
+                actuator_gain_celsius: -10.0,
+                ..RoomThermalModel::default()
+            },
+            HeaterDrive::Relay(relay),
+        );
+        room.step(600_000);
+        assert!(room.temperature_celsius() < 30.0);
+    }
+
+    #[test]
+    fn sensor_fault_is_reported() {
+        let mut room = SimulatedRoom::new(
+            RoomThermalModel::default(),
+            HeaterDrive::Relay(MockPin::new(5)),
+        );
+        room.set_sensor_fault(true);
+        assert_eq!(room.read(), Err(SensorError::BusError));
+        room.set_sensor_fault(false);
+        assert_eq!(room.read().unwrap().temperature_centi_celsius, 1600);
+    }
+
+    #[test]
+    fn rtc_rolls_over_day_month_and_year() {
+        let rtc = SimulatedRtc::new(RtcDateTime::new(24, 12, 31, 23, 59, 30));
+        rtc.step(45_000);
+        assert_eq!(rtc.now(), RtcDateTime::new(25, 1, 1, 0, 0, 15));
+    }
+
+    #[test]
+    fn rtc_handles_leap_day_and_sub_second_steps() {
+        let rtc = SimulatedRtc::new(RtcDateTime::new(24, 2, 28, 23, 59, 59));
+        for _ in 0..4 {
+            rtc.step(250);
+        }
+        assert_eq!(rtc.now(), RtcDateTime::new(24, 2, 29, 0, 0, 0));
+        rtc.step(24 * 3_600_000);
+        assert_eq!(rtc.now(), RtcDateTime::new(24, 3, 1, 0, 0, 0));
+    }
+
+    #[test]
+    fn demo_schedule_is_sorted() {
+        let schedule = demo_schedule();
+        assert_eq!(schedule.entries().len(), 4);
+        assert_eq!(
+            schedule.setpoint_at(&RtcDateTime::new(25, 1, 1, 3, 0, 0)),
+            Some(1700)
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! Terminal demo for `ThermostatApp` against the `thermal_sim` room model.
+//!
+//! 1 tick = シミュレーション上の 1 秒。20 ms ごとに 60 tick 進めるので、1 日が約 30 秒で回る。
+
+use core_app::thermostat::{RelayOutput, ThermostatApp, ThermostatConfig};
+use hal_api::rtc::RtcDateTime;
+use platform_pc_sim::climate_sim::TerminalDisplay16x2;
+use platform_pc_sim::mock_hal::MockPin;
+use platform_pc_sim::thermal_sim::{
+    demo_schedule, HeaterDrive, RoomThermalModel, SimulatedRoom, SimulatedRtc,
+};
+use std::thread;
+use std::time::Duration;
+
+const TICK_MS: u32 = 1_000;
+const TICKS_PER_FRAME: u32 = 60;
+
+fn main() {
+    println!("=== Thermostat Sim ===");
+    println!("Ctrl+C で終了します");
+
+    let relay = MockPin::new(5);
+    let room = SimulatedRoom::new(
+        RoomThermalModel::default(),
+        HeaterDrive::Relay(relay.clone()),
+    );
+    let rtc = SimulatedRtc::new(RtcDateTime::new(25, 1, 6, 5, 0, 0));
+    let mut app = ThermostatApp::new_with_config(
+        room.clone(),
+        rtc.clone(),
+        RelayOutput::new(relay),
+        TerminalDisplay16x2::with_stdout(),
+        ThermostatConfig {
+            sample_period_ticks: TICKS_PER_FRAME,
+            min_on_ticks: 120,
+            min_off_ticks: 120,
+            min_cycle_ticks: 300,
+            ..ThermostatConfig::default()
+        },
+        demo_schedule(),
+    );
+
+    loop {
+        for _ in 0..TICKS_PER_FRAME {
+            room.step(TICK_MS);
+            rtc.step(TICK_MS);
+            if let Err(error) = app.tick() {
+                eprintln!("thermostat sim failed: {:?}", error);
+                return;
+            }
+        }
+        println!(
+            "room {:.2}C  heater duty {:.0}%",
+            room.temperature_celsius(),
+            room.duty_cycle() * 100.0
+        );
+        thread::sleep(Duration::from_millis(20));
+    }
+}
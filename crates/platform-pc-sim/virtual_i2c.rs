@@ -37,12 +37,26 @@ pub enum VirtualI2cOperation {
     },
 }
 
+/// Cumulative transfer counts for one address.
+///
+/// Kept even when operation recording is disabled, and never trimmed like
+/// the operation log, so exporters can report them as counters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct I2cAddressStats {
+    pub writes: u64,
+    pub reads: u64,
+    pub write_reads: u64,
+    /// Transfers that returned an error (including NACKs on empty addresses).
+    pub errors: u64,
+}
+
 type SharedVirtualDevice = Rc<RefCell<Box<dyn VirtualI2cDevice>>>;
 
 struct VirtualI2cBusState {
     devices: Vec<(u8, SharedVirtualDevice)>,
     operations: VecDeque<VirtualI2cOperation>,
     recording_enabled: bool,
+    stats: Vec<(u8, I2cAddressStats)>,
 }
 
 impl Default for VirtualI2cBusState {
@@ -51,6 +65,7 @@ impl Default for VirtualI2cBusState {
             devices: Vec::new(),
             operations: VecDeque::new(),
             recording_enabled: true,
+            stats: Vec::new(),
         }
     }
 }
@@ -120,6 +135,35 @@ impl VirtualI2cBus {
         addresses
     }
 
+    /// Per-address transfer counters, sorted by address.
+    pub fn address_stats(&self) -> Vec<(u8, I2cAddressStats)> {
+        let mut stats = self.state.borrow().stats.clone();
+        stats.sort_unstable_by_key(|(addr, _)| *addr);
+        stats
+    }
+
+    fn count<T>(
+        &self,
+        addr: u8,
+        result: Result<T, I2cError>,
+        field: fn(&mut I2cAddressStats) -> &mut u64,
+    ) -> Result<T, I2cError> {
+        let mut state = self.state.borrow_mut();
+        let index = match state.stats.iter().position(|(a, _)| *a == addr) {
+            Some(index) => index,
+            None => {
+                state.stats.push((addr, I2cAddressStats::default()));
+                state.stats.len() - 1
+            }
+        };
+        let stats = &mut state.stats[index].1;
+        *field(stats) += 1;
+        if result.is_err() {
+            stats.errors += 1;
+        }
+        result
+    }
+
     fn with_device<T>(
         &self,
         addr: u8,
@@ -160,7 +204,8 @@ impl I2cBus for VirtualI2cBus {
                 bytes: bytes.to_vec(),
             }
         });
-        self.with_device(addr, |device| device.write(bytes))
+        let result = self.with_device(addr, |device| device.write(bytes));
+        self.count(addr, result, |stats| &mut stats.writes)
     }
 
     fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
@@ -168,7 +213,8 @@ impl I2cBus for VirtualI2cBus {
             addr,
             len: buffer.len(),
         });
-        self.with_device(addr, |device| device.read(buffer))
+        let result = self.with_device(addr, |device| device.read(buffer));
+        self.count(addr, result, |stats| &mut stats.reads)
     }
 
     fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
@@ -179,7 +225,8 @@ impl I2cBus for VirtualI2cBus {
                 len: buffer.len(),
             }
         });
-        self.with_device(addr, |device| device.write_read(bytes, buffer))
+        let result = self.with_device(addr, |device| device.write_read(bytes, buffer));
+        self.count(addr, result, |stats| &mut stats.write_reads)
     }
 }
 
@@ -245,6 +292,17 @@ mod tests {
         let mut buffer = [0u8; 1];
 
         assert_eq!(bus.read(0x42, &mut buffer), Err(I2cError::InvalidAddress));
+        assert_eq!(
+            bus.address_stats(),
+            vec![(
+                0x42,
+                I2cAddressStats {
+                    reads: 1,
+                    errors: 1,
+                    ..I2cAddressStats::default()
+                }
+            )]
+        );
     }
 
     #[test]
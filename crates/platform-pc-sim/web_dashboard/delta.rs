@@ -0,0 +1,169 @@
+//! Per-panel change tracking for push transports.
+//!
+//! Each panel of [`DeviceDashboardState`] is serialized once per push and
+//! compared with the previous push, so the WebSocket endpoint only ships the
+//! panels that changed and every client shares the same `Arc<str>` fragments.
+//! [`PanelTracker::full_json`] splices the fragments back into the document
+//! [`state_to_json`](super::state_to_json) would produce, so the SSE stream
+//! reuses them instead of serializing the state a second time.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use super::DeviceDashboardState;
+
+/// Panel names in document order. `board` carries `board_name` / `mcu_name`;
+/// `tick` is sent alongside every message rather than as a panel.
+pub const PANELS: [&str; 15] = [
+    "board",
+    "climate",
+    "distance",
+    "imu",
+    "servo",
+    "motor_driver",
+    "wiring",
+    "i2c",
+    "light",
+    "camera",
+    "gas",
+    "rtc",
+    "tof",
+    "oled",
+    "diagnostics",
+];
+
+/// Panels that changed in one [`PanelTracker::update`] call.
+#[derive(Debug, Clone)]
+pub struct PanelDelta {
+    /// Increments on every update, starting at 1.
+    pub seq: u64,
+    pub tick: u32,
+    pub changed: Vec<(&'static str, Arc<str>)>,
+}
+
+/// Remembers the last serialized form of every panel.
+#[derive(Debug, Clone)]
+pub struct PanelTracker {
+    seq: u64,
+    tick: u32,
+    board_name: Arc<str>,
+    mcu_name: Arc<str>,
+    panels: [Arc<str>; PANELS.len()],
+}
+
+impl Default for PanelTracker {
+    fn default() -> Self {
+        let null: Arc<str> = Arc::from("null");
+        Self {
+            seq: 0,
+            tick: 0,
+            board_name: Arc::clone(&null),
+            mcu_name: Arc::clone(&null),
+            panels: std::array::from_fn(|_| Arc::clone(&null)),
+        }
+    }
+}
+
+impl PanelTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serializes every panel of `state` and returns the ones that differ
+    /// from the previous call (all of them on the first call).
+    pub fn update(&mut self, state: &DeviceDashboardState) -> PanelDelta {
+        #[derive(Serialize)]
+        struct BoardPanel {
+            board_name: &'static str,
+            mcu_name: &'static str,
+        }
+
+        let board = BoardPanel {
+            board_name: state.board_name,
+            mcu_name: state.mcu_name,
+        };
+        let fresh: [String; PANELS.len()] = [
+            to_json(&board),
+            to_json(&state.climate),
+            to_json(&state.distance),
+            to_json(&state.imu),
+            to_json(&state.servo),
+            to_json(&state.motor_driver),
+            to_json(&state.wiring),
+            to_json(&state.i2c),
+            to_json(&state.light),
+            to_json(&state.camera),
+            to_json(&state.gas),
+            to_json(&state.rtc),
+            to_json(&state.tof),
+            to_json(&state.oled),
+            to_json(&state.diagnostics),
+        ];
+
+        self.seq += 1;
+        self.tick = state.tick;
+        let mut changed = Vec::new();
+        for ((name, slot), json) in PANELS.iter().zip(&mut self.panels).zip(fresh) {
+            if **slot != *json {
+                *slot = Arc::from(json);
+                changed.push((*name, Arc::clone(slot)));
+            }
+        }
+        if changed.first().is_some_and(|(name, _)| *name == "board") {
+            self.board_name = Arc::from(to_json(&state.board_name));
+            self.mcu_name = Arc::from(to_json(&state.mcu_name));
+        }
+        PanelDelta {
+            seq: self.seq,
+            tick: self.tick,
+            changed,
+        }
+    }
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Latest serialized form of `name`, if it is one of [`PANELS`].
+    pub fn panel(&self, name: &str) -> Option<&Arc<str>> {
+        PANELS
+            .iter()
+            .position(|panel| *panel == name)
+            .map(|index| &self.panels[index])
+    }
+
+    /// Every panel with its latest serialized form, in [`PANELS`] order.
+    pub fn panels(&self) -> impl Iterator<Item = (&'static str, &Arc<str>)> + '_ {
+        PANELS.iter().copied().zip(&self.panels)
+    }
+
+    /// The whole state as one JSON document, field for field what
+    /// `state_to_json` returns for the last updated state.
+    pub fn full_json(&self) -> String {
+        let len = self.panels.iter().map(|p| p.len() + 16).sum::<usize>() + 64;
+        let mut json = String::with_capacity(len);
+        json.push_str("{\"board_name\":");
+        json.push_str(&self.board_name);
+        json.push_str(",\"mcu_name\":");
+        json.push_str(&self.mcu_name);
+        json.push_str(",\"tick\":");
+        json.push_str(&self.tick.to_string());
+        for (name, panel) in self.panels().skip(1) {
+            json.push_str(",\"");
+            json.push_str(name);
+            json.push_str("\":");
+            json.push_str(panel);
+        }
+        json.push('}');
+        json
+    }
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "null".to_string())
+}
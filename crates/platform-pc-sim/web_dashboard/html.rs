@@ -213,13 +213,26 @@ pub fn dashboard_html() -> &'static str {
     /* ── Wiring Editor ── */
     .we-chip { display:block; padding:5px 8px; border:1px solid; border-radius:6px; font-size:11px; font-weight:600; cursor:grab; text-align:center; user-select:none; transition:background .15s,opacity .15s; }
     .we-chip:hover { opacity:.8; background:rgba(15,124,107,.08); }
-    .we-node { position:absolute; background:var(--paper); border:2px solid; border-radius:8px; min-width:110px; user-select:none; z-index:10; box-shadow:var(--shadow); }
-    .we-node-hdr { padding:3px 8px; border-radius:5px 5px 0 0; font-size:11px; font-weight:700; cursor:move; color:#fff; }
-    .we-port { display:flex; align-items:center; gap:5px; padding:2px 8px; font-size:10px; cursor:pointer; transition:background .12s; }
-    .we-port:hover { background:rgba(127,127,127,.12); }
-    .we-port.pend .we-dot { box-shadow:0 0 7px 2px #ff9800 !important; }
-    .we-dot { width:9px; height:9px; border-radius:50%; border:1.5px solid rgba(255,255,255,.45); flex-shrink:0; }
+    .we-chip.off { opacity:.35; cursor:not-allowed; }
     #we-canvas { background-image:radial-gradient(circle,rgba(127,127,127,.2) 1px,transparent 1px); background-size:20px 20px; }
+    #we-svg { display:block; user-select:none; }
+    #we-svg text { font:10px system-ui,sans-serif; fill:var(--ink); pointer-events:none; }
+    #we-svg .we-box { fill:var(--paper); stroke-width:2; }
+    #we-svg .we-box.selected { stroke-width:3.5; }
+    #we-svg .we-box.warning { stroke:#d90; }
+    #we-svg .we-box.error { stroke:#e55; }
+    #we-svg .we-hdr { cursor:move; }
+    #we-svg .we-hdr text { fill:#fff; font-size:11px; font-weight:700; }
+    #we-svg .we-row { cursor:crosshair; }
+    #we-svg .we-row:hover .we-hit { fill:rgba(127,127,127,.14); }
+    #we-svg .we-row.reserved text, #we-svg .we-caps { fill:var(--muted); }
+    #we-svg .we-caps { font-size:9px; }
+    #we-svg .we-dot { fill:var(--paper); stroke:rgba(127,127,127,.7); stroke-width:1.5; }
+    #we-svg .we-dot.used { fill:#7bc47b; }
+    #we-svg .we-dot.warning { stroke:#d90; stroke-width:3; }
+    #we-svg .we-dot.error { stroke:#e55; stroke-width:3; }
+    #we-svg .we-wire { fill:none; stroke-width:2.5; pointer-events:none; }
+    #we-svg .we-wire.warning, #we-svg .we-wire.error { stroke-dasharray:6 3; }
   </style>
 </head>
 <body>
@@ -231,6 +244,10 @@ pub fn dashboard_html() -> &'static str {
       <span class="stext" id="stext">connecting&#x2026;</span>
       <span class="serr"  id="serr"></span>
       <div class="ctrls">
+        <label for="session-select" style="color:var(--muted)">Session</label>
+        <select id="session-select" onchange="sessionOpen(this.value)"></select>
+        <button class="btn" onclick="sessionCreate()" title="New session with the board selected below">&#xFF0B; Session</button>
+        <button class="btn" id="session-delete" onclick="sessionDelete()" title="Stop this session">&#x2715;</button>
         <label for="isel" style="color:var(--muted)">Refresh</label>
         <select id="isel">
           <option value="250">250 ms</option>
@@ -595,8 +612,10 @@ pub fn dashboard_html() -> &'static str {
         <h2 style="display:flex;align-items:center;gap:12px">
           Wiring Diagram
           <select id="board-select" style="font-size:12px;background:#1a2a1a;color:#7bc47b;border:1px solid #3d7a3d;border-radius:4px;padding:2px 6px;cursor:pointer">
-            <option value="original-esp32">Original ESP32</option>
-            <option value="arduino-nano">Arduino Nano</option>
+            <option value="esp32">Original ESP32</option>
+            <option value="nano">Arduino Nano</option>
+            <option value="pico">Raspberry Pi Pico</option>
+            <option value="m5stickc">M5StickC</option>
           </select>
           <select id="sensor-profile-select" style="font-size:12px;background:#1a2a1a;color:#7bc47b;border:1px solid #3d7a3d;border-radius:4px;padding:2px 6px;cursor:pointer">
           </select>
@@ -606,10 +625,25 @@ pub fn dashboard_html() -> &'static str {
           <input id="show-bus-labels-toggle" type="checkbox" />
           <span>Show bus labels</span>
         </label>
+        <button class="btn" onclick="wiringProjectSave()" title="Download board, devices, pins and editor layout">&#x2B07; Save project</button>
+        <label class="btn" style="cursor:pointer" title="Load a wiring project file">&#x1F4C2; Load project
+          <input type="file" id="wiring-project-file" accept=".json" style="display:none" onchange="wiringProjectLoad(this)">
+        </label>
+        <button class="btn" onclick="wiringExport('kicad')" title="KiCad netlist for Pcbnew">&#x2B07; KiCad</button>
+        <button class="btn" onclick="wiringExport('breadboard')" title="Parts and wire list with Fritzing wire colours">&#x2B07; Breadboard</button>
+        <button class="btn" onclick="wiringExport('bom')" title="Bill of materials (CSV)">&#x2B07; BOM</button>
+        <button class="btn" onclick="wiringExport('board-setup')" title="Firmware board-setup module (pins, I2C bus, drivers, PWM)">&#x2B07; Board setup</button>
         <div id="wiring-svg-wrap" style="width:100%;overflow-x:auto;min-height:180px"></div>
         <div class="footer" style="margin-top:6px">
           Attached: <span id="wiring-devices" style="font-family:monospace">--</span>
         </div>
+        <div class="footer" style="margin-top:6px">
+          Onboard: <span id="wiring-onboard" style="font-family:monospace">--</span>
+        </div>
+        <div class="footer" style="margin-top:8px">
+          Wiring check: <span id="wiring-check-summary" style="font-family:monospace">--</span>
+        </div>
+        <ul class="ops" id="wiring-check-list" style="max-height:180px;overflow-y:auto"></ul>
       </article>
 
       <!-- I2C Activity -->
@@ -625,9 +659,77 @@ pub fn dashboard_html() -> &'static str {
           <span style="font-size:11px;color:var(--muted)">Total events:</span>
           <span id="diag-event-count" style="font-weight:600;font-variant-numeric:tabular-nums">0</span>
         </div>
+        <div id="diag-active-alarms" style="display:flex;flex-wrap:wrap;gap:6px;margin-bottom:8px;font-size:11px;font-family:'IBM Plex Mono',monospace"></div>
         <ul id="diag-events" style="margin:0;padding:0;list-style:none;font-size:12px;font-family:'IBM Plex Mono',monospace;max-height:160px;overflow-y:auto"></ul>
       </article>
 
+      <!-- Simulation Control -->
+      <article class="panel card span-12" id="control-panel">
+        <h2>&#x1F39B;&#xFE0F; Simulation Control</h2>
+        <div style="display:flex;align-items:center;gap:8px;flex-wrap:wrap;margin-bottom:10px">
+          <button class="btn" id="ctl-pause-btn" onclick="ctlTogglePause()">&#x23F8; Pause sim</button>
+          <button class="btn" onclick="ctlStep()">&#x23ED; Step</button>
+          <input type="number" id="ctl-step-ticks" value="1" min="1" style="width:64px">
+          <span style="font-size:12px;color:var(--muted)">ticks</span>
+          <button class="btn" onclick="ctlPost('/api/control/reset')">&#x21BA; Reset overrides</button>
+          <span style="margin-left:auto"></span>
+          <button class="btn" id="ctl-record-btn" onclick="ctlToggleRecord()">&#x23FA; Record</button>
+          <label class="btn" style="cursor:pointer">&#x1F4C2; Play scenario
+            <input type="file" id="ctl-scenario-file" accept=".txt,.scenario" style="display:none" onchange="ctlPlayScenario(this)">
+          </label>
+        </div>
+        <div id="ctl-sensors" class="toggle-grid" style="margin-bottom:10px"></div>
+        <div style="display:flex;align-items:center;gap:8px;flex-wrap:wrap;margin-bottom:6px">
+          <span style="min-width:70px">Servo</span>
+          <input type="range" id="ctl-servo-angle" min="0" max="180" value="90"
+                 oninput="$('ctl-servo-label').textContent = this.value + ' deg'">
+          <span id="ctl-servo-label" style="min-width:56px">90 deg</span>
+          <button class="btn" onclick="ctlPost('/api/control/servo', { angle: +$('ctl-servo-angle').value })">Set</button>
+          <button class="btn" onclick="ctlPost('/api/control/servo', { angle: null })">Auto</button>
+        </div>
+        <div style="display:flex;align-items:center;gap:8px;flex-wrap:wrap">
+          <span style="min-width:70px">Motors</span>
+          <select id="ctl-motor-left-dir"></select>
+          <input type="number" id="ctl-motor-left-duty" value="50" min="0" max="100" style="width:64px">
+          <select id="ctl-motor-right-dir"></select>
+          <input type="number" id="ctl-motor-right-duty" value="50" min="0" max="100" style="width:64px">
+          <button class="btn" onclick="ctlSetMotors()">Set</button>
+          <button class="btn" onclick="ctlPost('/api/control/motors', {})">Auto</button>
+        </div>
+        <div id="ctl-status" style="margin-top:8px;font-size:11px;color:var(--muted);font-family:'IBM Plex Mono',monospace">--</div>
+      </article>
+
+      <!-- History Export -->
+      <article class="panel card span-12" id="history-panel">
+        <h2>&#x1F4BE; History</h2>
+        <div style="display:flex;align-items:center;gap:8px;flex-wrap:wrap">
+          <select id="hist-panel">
+            <option value="">All panels</option>
+            <option value="climate">climate</option>
+            <option value="distance">distance</option>
+            <option value="imu">imu</option>
+            <option value="servo">servo</option>
+            <option value="motor_driver">motor_driver</option>
+            <option value="i2c">i2c</option>
+            <option value="light">light</option>
+            <option value="camera">camera</option>
+            <option value="gas">gas</option>
+            <option value="rtc">rtc</option>
+            <option value="tof">tof</option>
+            <option value="diagnostics">diagnostics</option>
+          </select>
+          <select id="hist-range">
+            <option value="15m">Last 15 min</option>
+            <option value="1h" selected>Last hour</option>
+            <option value="24h">Last 24 h</option>
+            <option value="">Everything kept</option>
+          </select>
+          <button class="btn" onclick="histExport('csv')">&#x2B07; CSV</button>
+          <button class="btn" onclick="histExport('parquet')">&#x2B07; Parquet</button>
+          <span style="font-size:11px;color:var(--muted)">Recorded once per second to the server's history store</span>
+        </div>
+      </article>
+
       <!-- E2E Test Runner -->
       <article class="panel card span-12">
         <h2>&#x1F9EA; E2E Test Runner</h2>
@@ -639,24 +741,25 @@ pub fn dashboard_html() -> &'static str {
       <article class="panel card span-12" style="padding:0;overflow:hidden">
         <div style="padding:13px 20px;border-bottom:1px solid var(--line);display:flex;align-items:center;gap:10px;flex-wrap:wrap">
           <h2 style="margin:0">&#x1F50C; Wiring Editor</h2>
-          <span style="font-size:12px;color:var(--muted)">Drag devices &#x2192; canvas &bull; click ports to wire &bull; click wire to delete</span>
+          <span style="font-size:12px;color:var(--muted)">Drag devices &#x2192; canvas &bull; drag from a device pin to a board pin to wire it &bull; click a device to edit it &bull; changes apply to the simulator immediately</span>
           <div style="margin-left:auto;display:flex;gap:6px">
-            <button class="btn" onclick="weExport()">&#x1F4BE; Export</button>
-            <button class="btn" onclick="weImport()">&#x1F4C2; Import</button>
-            <button class="btn" onclick="weClear()">&#x1F5D1; Clear</button>
+            <button class="btn" onclick="weTidy()">&#x1F5C2; Tidy layout</button>
+            <button class="btn" onclick="wiringProjectSave()" title="Download board, devices, pins and editor layout">&#x1F4BE; Save project</button>
           </div>
         </div>
-        <div style="display:flex;height:400px">
-          <div id="we-sidebar" style="width:132px;flex-shrink:0;border-right:1px solid var(--line);padding:8px;overflow-y:auto;display:flex;flex-direction:column;gap:5px">
-            <div style="font-size:10px;color:var(--muted);text-transform:uppercase;letter-spacing:.07em;margin-bottom:2px">Library</div>
+        <div style="display:flex;height:520px">
+          <div id="we-sidebar" style="width:150px;flex-shrink:0;border-right:1px solid var(--line);padding:8px;overflow-y:auto;display:flex;flex-direction:column;gap:5px">
+            <div style="font-size:10px;color:var(--muted);text-transform:uppercase;letter-spacing:.07em;margin-bottom:2px">Devices</div>
+            <div id="we-palette" style="display:flex;flex-direction:column;gap:5px"></div>
+            <div id="we-inspector" style="display:none;margin-top:auto;padding-top:8px;border-top:1px solid var(--line);font-size:11px"></div>
           </div>
-          <div id="we-canvas" style="flex:1;position:relative;overflow:hidden"
+          <div id="we-canvas" style="flex:1;position:relative;overflow:auto"
                ondragover="event.preventDefault()"
                ondrop="weOnDrop(event)">
-            <svg id="we-svg" style="position:absolute;inset:0;width:100%;height:100%;overflow:visible"></svg>
+            <svg id="we-svg" xmlns="http://www.w3.org/2000/svg"></svg>
           </div>
         </div>
-        <div id="we-status" style="padding:5px 14px;border-top:1px solid var(--line);font-size:11px;color:var(--muted)">Ready &#x2014; drag a device chip to the canvas to get started.</div>
+        <div id="we-status" style="padding:5px 14px;border-top:1px solid var(--line);font-size:11px;color:var(--muted)">Loading wiring&#x2026;</div>
       </article>
 
       <!-- Firmware Flash -->
@@ -777,6 +880,14 @@ pub fn dashboard_html() -> &'static str {
 
     // ── DOM helpers ──
     const $ = id => document.getElementById(id);
+
+    // ── Sessions (/api/sessions) ──
+    // The page shows one session (`?session=name`); its routes live under
+    // /api/sessions/<name>/..., the default session keeps the plain /api/...
+    const SESSION = new URLSearchParams(location.search).get("session") || "default";
+    function api(path) {
+      return SESSION === "default" ? path : "/api/sessions/" + encodeURIComponent(SESSION) + path.slice(4);
+    }
     const fmt = (v, sfx) => v == null ? "--" : v + sfx;
     const LCD_BLANK = "                ";
     const lcdLines = ["lcd-line-1","lcd-line-2"].map(id => $(id));
@@ -926,7 +1037,7 @@ pub fn dashboard_html() -> &'static str {
     }
     async function loadWiringDiagram() {
       try {
-        const svg = await fetchTextOrThrow("/api/wiring/svg", "load wiring diagram");
+        const svg = await fetchTextOrThrow(api("/api/wiring/svg"), "load wiring diagram");
         const wrap = $("wiring-svg-wrap");
         if (wrap) {
           wrap.innerHTML = svg;
@@ -936,9 +1047,77 @@ pub fn dashboard_html() -> &'static str {
         setErr(wiringErrorMessage("Wiring diagram", err));
       }
     }
+    let wiringReport = null;
+    async function loadWiringCheck() {
+      const summary = $("wiring-check-summary");
+      const list = $("wiring-check-list");
+      if (!summary || !list) return;
+      try {
+        const report = await fetchJsonOrThrow(api("/api/wiring/check"), "check wiring");
+        wiringReport = report;
+        summary.textContent = report.errors + " error(s), " + report.warnings + " warning(s), " + report.infos + " info";
+        summary.style.color = report.errors ? "#e55" : report.warnings ? "#d90" : "var(--ink)";
+        list.innerHTML = "";
+        for (const finding of (report.findings || [])) {
+          const li = document.createElement("li");
+          li.style.cssText = "display:flex;gap:6px;align-items:baseline;padding:3px 0;font-size:11px";
+          const sev = finding.severity || "info";
+          const badge = document.createElement("span");
+          badge.textContent = sev.toUpperCase();
+          badge.title = finding.rule || "";
+          badge.style.cssText = "flex-shrink:0;padding:1px 4px;border-radius:3px;font-size:9px;font-weight:700;color:#fff;background:" + (sev === "error" ? "#e55" : sev === "warning" ? "#d90" : "#58a");
+          const msg = document.createElement("span");
+          msg.textContent = finding.message || "";
+          msg.style.cssText = "flex:1;word-break:break-word";
+          li.appendChild(badge);
+          li.appendChild(msg);
+          list.appendChild(li);
+        }
+      } catch(err) {
+        setErr(wiringErrorMessage("Wiring check", err));
+      }
+    }
+    async function wiringProjectSave() {
+      try {
+        const text = await fetchTextOrThrow(api("/api/wiring/project"), "save wiring project");
+        const link = document.createElement("a");
+        link.href = URL.createObjectURL(new Blob([text], { type: "application/json" }));
+        link.download = "wiring-" + SESSION + ".json";
+        link.click();
+        URL.revokeObjectURL(link.href);
+      } catch(err) {
+        setErr(wiringErrorMessage("Wiring project", err));
+      }
+    }
+    function wiringExport(format) {
+      window.location.href = api("/api/wiring/export?") + new URLSearchParams({ format });
+    }
+    function wiringProjectLoad(input) {
+      const file = input.files[0];
+      input.value = "";
+      if (!file) return;
+      return queueWiringUpdate(async () => {
+        try {
+          const response = await fetch(api("/api/wiring/project"), {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: await file.text(),
+          });
+          if (!response.ok) {
+            throw new Error(await response.text());
+          }
+          await refreshWiringUi();
+          clearErr();
+        } catch(err) {
+          setErr(wiringErrorMessage("Wiring project", err));
+        }
+      });
+    }
     async function refreshWiringUi() {
       await loadWiringConfig();
       await loadWiringDiagram();
+      await loadWiringCheck();
+      await weSync();
     }
     let wiringUpdatePromise = Promise.resolve();
     function queueWiringUpdate(task) {
@@ -957,7 +1136,7 @@ pub fn dashboard_html() -> &'static str {
       if (showBusLabelsToggle) body.show_bus_labels = showBusLabelsToggle.checked;
       return queueWiringUpdate(async () => {
         try {
-          const response = await fetch("/api/wiring", {
+          const response = await fetch(api("/api/wiring"), {
             method: "POST",
             headers: { "Content-Type": "application/json" },
             body: JSON.stringify(body),
@@ -984,7 +1163,7 @@ pub fn dashboard_html() -> &'static str {
       if (showBusLabelsToggle) body.show_bus_labels = showBusLabelsToggle.checked;
       return queueWiringUpdate(async () => {
         try {
-          const response = await fetch("/api/wiring", {
+          const response = await fetch(api("/api/wiring"), {
             method: "POST",
             headers: { "Content-Type": "application/json" },
             body: JSON.stringify(body),
@@ -1006,7 +1185,7 @@ pub fn dashboard_html() -> &'static str {
       };
       return queueWiringUpdate(async () => {
         try {
-          const response = await fetch("/api/wiring", {
+          const response = await fetch(api("/api/wiring"), {
             method: "POST",
             headers: { "Content-Type": "application/json" },
             body: JSON.stringify(body),
@@ -1023,19 +1202,53 @@ pub fn dashboard_html() -> &'static str {
     }
     async function loadWiringConfig() {
       try {
-        const data = await fetchJsonOrThrow("/api/wiring", "load wiring config");
+        const data = await fetchJsonOrThrow(api("/api/wiring"), "load wiring config");
         const boardSel = $("board-select");
         const profileSel = $("sensor-profile-select");
         const showBusLabelsToggle = $("show-bus-labels-toggle");
-        if (boardSel) boardSel.value = data.board === "nano" ? "arduino-nano" : "original-esp32";
+        if (boardSel) boardSel.value = data.board;
+        const onboard = data.onboard_devices || [];
+        $("wiring-onboard").textContent = onboard.length
+          ? `${data.mcu}; ` + onboard.map(p => p.address ? `${p.name} ${p.address}` : p.name).join(", ")
+          : (data.mcu || "--");
         if (profileSel) profileSel.value = data.sensor_profile;
         if (showBusLabelsToggle) showBusLabelsToggle.checked = !!data.show_bus_labels;
+        wiringAvailable = (data.available_devices || []).map(d => d.kind);
         renderDeviceToggles(data.available_devices || []);
         applyDeviceSelection(data.selected_devices || []);
       } catch(err) {
         setErr(wiringErrorMessage("Wiring config", err));
       }
     }
+    // Boards come from the server's board definitions, including ones loaded
+    // from BOARD_DEFS_DIR; the static options are the fallback.
+    let boardInfo = {};
+    let wiringAvailable = [];
+    async function initBoardSelects() {
+      try {
+        const boards = await fetchJsonOrThrow("/api/boards", "load boards");
+        boardInfo = Object.fromEntries(boards.map(b => [b.slug, b]));
+        const boardSel = $("board-select");
+        if (boardSel) {
+          const current = boardSel.value;
+          boardSel.innerHTML = "";
+          boards.forEach(b => boardSel.add(new Option(b.name, b.slug)));
+          boardSel.value = current;
+        }
+        const extSel = $("ext-flash-board");
+        if (extSel) {
+          extSel.innerHTML = "";
+          boards.filter(b => b.flash).forEach(b => extSel.add(new Option(b.name, b.slug)));
+          extBoardChange();
+        }
+      } catch(err) {
+        setErr(wiringErrorMessage("Board list", err));
+      }
+    }
+    function boardNeedsPort(slug) {
+      const info = boardInfo[slug];
+      return info ? info.needs_port : slug !== "raspi-pico";
+    }
     async function initProfileSelect() {
       try {
         const data = await fetchJsonOrThrow("/api/wiring/profiles", "load wiring profiles");
@@ -1055,7 +1268,9 @@ pub fn dashboard_html() -> &'static str {
     const showBusLabelsToggle = $("show-bus-labels-toggle");
     if (showBusLabelsToggle) showBusLabelsToggle.addEventListener("change", changeBusLabelToggle);
     queueWiringUpdate(async () => {
+      await initBoardSelects();
       await initProfileSelect();
+      await weLoadDevices();
       await refreshWiringUi();
     });
     setInterval(() => {
@@ -1241,6 +1456,16 @@ pub fn dashboard_html() -> &'static str {
       if (diagCount && s.diagnostics) {
         diagCount.textContent = s.diagnostics.event_count;
       }
+      const diagAlarms = $("diag-active-alarms");
+      if (diagAlarms && s.diagnostics) {
+        diagAlarms.innerHTML = "";
+        for (const alarm of (s.diagnostics.active_alarms || [])) {
+          const chip = document.createElement("span");
+          chip.textContent = "\u26A0 " + alarm;
+          chip.style.cssText = "padding:2px 6px;border-radius:3px;background:#d90;color:#fff;font-weight:600";
+          diagAlarms.appendChild(chip);
+        }
+      }
       if (diagList && s.diagnostics) {
         diagList.innerHTML = "";
         for (const ev of (s.diagnostics.recent_events || [])) {
@@ -1334,7 +1559,7 @@ pub fn dashboard_html() -> &'static str {
     let lastRenderMs = 0;
     $("isel").addEventListener("change", e => { renderIntervalMs = +e.target.value; });
 
-    const evtSrc = new EventSource('/api/events');
+    const evtSrc = new EventSource(api('/api/events'));
 
     evtSrc.onopen = () => { setOk(); };
 
@@ -1351,7 +1576,7 @@ pub fn dashboard_html() -> &'static str {
 
     // ── Seed sparklines from server history on page load ──
     function fetchHistory(sensor) {
-      fetch('/api/history?sensor=' + sensor)
+      fetch(api('/api/history?sensor=' + sensor))
         .then(r => r.json())
         .then(d => {
           if (sensor === 'distance') {
@@ -1394,218 +1619,387 @@ pub fn dashboard_html() -> &'static str {
 
     // ── Boot ──
     // ── Wiring Editor ────────────────────────────────────────────────────────
-    const WE_DEFS = {
-      'ESP32':        { color:'#2196F3', ports:['3V3','GND','GPIO21(SDA)','GPIO22(SCL)','GPIO5(TRIG)','GPIO18(ECHO)','GPIO13(Servo)','GPIO25','GPIO26','GPIO27','GPIO32'] },
-      'Arduino Nano': { color:'#1565C0', ports:['5V','3.3V','GND','A4(SDA)','A5(SCL)','D2(TRIG)','D3(ECHO)','D9(Servo)','D5','D6','D7','D10'] },
-      'BME280':   { color:'#4CAF50', ports:['VCC','GND','SDA','SCL'] },
-      'MPU6050':  { color:'#9C27B0', ports:['VCC','GND','SDA','SCL'] },
-      'HC-SR04':  { color:'#FF5722', ports:['VCC','GND','TRIG','ECHO'] },
-      'LCD1602':  { color:'#00BCD4', ports:['VCC','GND','SDA','SCL'] },
-      'Servo':    { color:'#FF9800', ports:['VCC','GND','PWM'] },
-      'L298N':    { color:'#795548', ports:['12V','GND','IN1','IN2','ENA','IN3','IN4','ENB'] },
-      'BH1750':   { color:'#FDD835', ports:['VCC','GND','SDA','SCL','ADDR'] },
-      'DHT22':    { color:'#26C6DA', ports:['VCC','GND','DATA'] },
-      'SSD1306':  { color:'#78909C', ports:['VCC','GND','SDA','SCL'] },
-      'ESP32-CAM':{ color:'#E91E63', ports:['5V','GND','U0TXD','U0RXD','GPIO0','GPIO4(FLASH)','VCC(3V3)'] },
+    // Edits the session's wiring project. Board pins come from /api/boards and
+    // the pins each device needs from /api/wiring/devices; a wire sets the
+    // project pin behind the device pin, so every SDA wire moves together.
+    // Wiring changes are posted as a project, which reconfigures the
+    // simulator, and the editor then redraws from the server's project and
+    // wiring check. Moving nodes only saves the layout.
+    const WE_SVG = "http://www.w3.org/2000/svg";
+    const WE_ROW = 16, WE_HDR = 22, WE_BOARD_W = 170, WE_DEV_W = 140;
+    const WE_WIRE_COLORS = { VCC:"#e53935", GND:"#546e7a", SDA:"#1e88e5", SCL:"#f9a825" };
+    const WE_COLORS = {
+      bme280:"#4CAF50", mpu6050:"#9C27B0", lcd1602:"#00BCD4", bh1750:"#C0A000",
+      ds3231:"#5C6BC0", sgp30:"#8D6E63", vl53l0x:"#00897B", ssd1306:"#78909C",
+      servo:"#FF9800", l298n:"#795548", hc_sr04:"#FF5722", esp32_cam:"#E91E63",
     };
-    const weS = { nodes:{}, edges:[], seq:1, pending:null };
-
-    (function weInit() {
-      const sb = document.getElementById('we-sidebar');
-      Object.entries(WE_DEFS).forEach(function([type, def]) {
-        const chip = document.createElement('div');
-        chip.className = 'we-chip';
-        chip.style.borderColor = def.color;
-        chip.style.color = def.color;
-        chip.textContent = type;
-        chip.draggable = true;
-        chip.addEventListener('dragstart', function(e) {
-          e.dataTransfer.setData('we-type', type);
-        });
-        sb.appendChild(chip);
-      });
-    })();
+    const weS = { devices:{}, project:null, layout:{ board:null, nodes:{} }, drag:null, wire:null, selected:null };
 
-    function weOnDrop(e) {
-      const type = e.dataTransfer.getData('we-type');
-      if (!type) return;
-      const rect = document.getElementById('we-canvas').getBoundingClientRect();
-      weAddNode(type, Math.max(0, e.clientX - rect.left - 55), Math.max(0, e.clientY - rect.top - 20));
-    }
-
-    function weBuildNodeEl(id, type, x, y) {
-      const def = WE_DEFS[type];
-      const el = document.createElement('div');
-      el.className = 'we-node';
-      el.id = 'we-node-' + id;
-      el.style.cssText = 'left:' + x + 'px;top:' + y + 'px;border-color:' + def.color;
-      const hdr = document.createElement('div');
-      hdr.className = 'we-node-hdr';
-      hdr.style.background = def.color;
-      hdr.textContent = type;
-      el.appendChild(hdr);
-      def.ports.forEach(function(port) {
-        const row = document.createElement('div');
-        row.className = 'we-port';
-        row.dataset.port = port;
-        row.onclick = function() { weClickPort(id, port, row); };
-        const dot = document.createElement('span');
-        dot.className = 'we-dot';
-        dot.style.background = def.color;
-        row.appendChild(dot);
-        row.appendChild(document.createTextNode('\u00a0' + port));
-        el.appendChild(row);
-      });
-      let ox, oy;
-      hdr.addEventListener('mousedown', function(e) {
-        e.preventDefault();
-        const nd = weS.nodes[id];
-        ox = e.clientX - nd.x; oy = e.clientY - nd.y;
-        function onMove(ev) {
-          nd.x = ev.clientX - ox; nd.y = ev.clientY - oy;
-          el.style.left = nd.x + 'px'; el.style.top = nd.y + 'px';
-          weRenderEdges();
-        }
-        function onUp() {
-          document.removeEventListener('mousemove', onMove);
-          document.removeEventListener('mouseup', onUp);
-        }
-        document.addEventListener('mousemove', onMove);
-        document.addEventListener('mouseup', onUp);
-      });
+    function weStatus(text) { $("we-status").textContent = text; }
+    function weColor(kind) { return WE_COLORS[kind] || "#607D8B"; }
+    function weDevice(kind) {
+      return weS.project ? weS.project.devices.find(d => d.kind === kind) : undefined;
+    }
+    function weEl(tag, attrs, parent) {
+      const el = document.createElementNS(WE_SVG, tag);
+      for (const [key, value] of Object.entries(attrs)) el.setAttribute(key, value);
+      if (parent) parent.appendChild(el);
       return el;
     }
+    function weCurve(a, b) {
+      const mx = (a.x + b.x) / 2;
+      return "M" + a.x + "," + a.y + " C" + mx + "," + a.y + " " + mx + "," + b.y + " " + b.x + "," + b.y;
+    }
+    function weSvgPoint(e) {
+      const rect = $("we-svg").getBoundingClientRect();
+      return { x: e.clientX - rect.left, y: e.clientY - rect.top };
+    }
 
-    function weAddNode(type, x, y) {
-      if (!WE_DEFS[type]) return;
-      const id = 'n' + weS.seq++;
-      weS.nodes[id] = { id, type, x, y };
-      document.getElementById('we-canvas').appendChild(weBuildNodeEl(id, type, x, y));
-      weRenderEdges();
-      document.getElementById('we-status').textContent = 'Added ' + type + '.';
-    }
-
-    function weClickPort(nodeId, port, rowEl) {
-      if (!weS.pending) {
-        weS.pending = { nodeId, port, rowEl };
-        rowEl.classList.add('pend');
-        document.getElementById('we-status').textContent =
-          port + ' selected \u2014 click another port to connect, or click again to cancel.';
-      } else if (weS.pending.nodeId === nodeId && weS.pending.port === port) {
-        weS.pending.rowEl.classList.remove('pend');
-        weS.pending = null;
-        document.getElementById('we-status').textContent = 'Cancelled.';
-      } else {
-        const fn = weS.pending.nodeId, fp = weS.pending.port, fEl = weS.pending.rowEl;
-        fEl.classList.remove('pend');
-        weS.pending = null;
-        const dup = weS.edges.some(function(e) {
-          return (e.from===fn&&e.fromPort===fp&&e.to===nodeId&&e.toPort===port) ||
-                 (e.from===nodeId&&e.fromPort===port&&e.to===fn&&e.toPort===fp);
-        });
-        if (!dup) {
-          weS.edges.push({ id:'e'+weS.seq++, from:fn, fromPort:fp, to:nodeId, toPort:port });
-        }
-        weRenderEdges();
-        document.getElementById('we-status').textContent =
-          'Connected ' + fp + ' \u2192 ' + port + '. Click a wire to delete it.';
+    async function weLoadDevices() {
+      try {
+        const devices = await fetchJsonOrThrow("/api/wiring/devices", "load device pins");
+        weS.devices = Object.fromEntries(devices.map(d => [d.kind, d]));
+      } catch(err) {
+        setErr(wiringErrorMessage("Wiring editor", err));
+      }
+    }
+
+    // Reloads the project; refreshWiringUi() runs it after the wiring check.
+    async function weSync() {
+      try {
+        const project = await fetchJsonOrThrow(api("/api/wiring/project"), "load wiring project");
+        const editor = project.editor || {};
+        weS.project = project;
+        weS.layout = {
+          board: editor.board || null,
+          nodes: Object.fromEntries((editor.nodes || [])
+            .filter(n => n.kind && isFinite(n.x) && isFinite(n.y))
+            .map(n => [n.kind, { x:n.x, y:n.y }])),
+        };
+        if (!weDevice(weS.selected)) weS.selected = null;
+        weRender();
+        weRenderPalette();
+        weRenderInspector();
+        weStatus(weSummary());
+      } catch(err) {
+        weStatus(wiringErrorMessage("Wiring editor", err));
       }
     }
 
-    function wePortPos(nodeId, portName) {
-      const nodeEl = document.getElementById('we-node-' + nodeId);
-      if (!nodeEl) return { x:0, y:0 };
-      const row = nodeEl.querySelector('[data-port="' + portName + '"]');
-      if (!row) return { x:0, y:0 };
-      const dot = row.querySelector('.we-dot');
-      const cr = document.getElementById('we-canvas').getBoundingClientRect();
-      const r = dot.getBoundingClientRect();
-      return { x: r.left + r.width/2 - cr.left, y: r.top + r.height/2 - cr.top };
+    function weSummary() {
+      if (!wiringReport) return "Wiring check unavailable.";
+      const findings = wiringReport.findings || [];
+      const first = findings.find(f => f.severity === "error") || findings.find(f => f.severity === "warning");
+      return wiringReport.errors + " error(s), " + wiringReport.warnings + " warning(s) \u2014 " +
+        (first ? first.message : "wiring OK");
     }
 
-    function weRenderEdges() {
-      const svg = document.getElementById('we-svg');
-      while (svg.firstChild) svg.removeChild(svg.firstChild);
-      weS.edges.forEach(function(edge) {
-        const p1 = wePortPos(edge.from, edge.fromPort);
-        const p2 = wePortPos(edge.to, edge.toPort);
-        const mx = (p1.x + p2.x) / 2;
-        const d = 'M'+p1.x+','+p1.y+' C'+mx+','+p1.y+' '+mx+','+p2.y+' '+p2.x+','+p2.y;
-        const path = document.createElementNS('http://www.w3.org/2000/svg', 'path');
-        path.setAttribute('d', d);
-        path.setAttribute('stroke', '#7bc47b');
-        path.setAttribute('stroke-width', '2');
-        path.setAttribute('fill', 'none');
-        svg.appendChild(path);
-        const hit = document.createElementNS('http://www.w3.org/2000/svg', 'path');
-        hit.setAttribute('d', d);
-        hit.setAttribute('stroke', 'transparent');
-        hit.setAttribute('stroke-width', '10');
-        hit.setAttribute('fill', 'none');
-        hit.setAttribute('pointer-events', 'stroke');
-        hit.style.cursor = 'pointer';
-        const eid = edge.id;
-        hit.addEventListener('click', function() {
-          weS.edges = weS.edges.filter(function(e) { return e.id !== eid; });
-          weRenderEdges();
-          document.getElementById('we-status').textContent = 'Wire deleted.';
-        });
-        svg.appendChild(hit);
+    // Worst error/warning finding that `match` accepts.
+    function weIssue(match) {
+      let worst = null;
+      for (const finding of (wiringReport && wiringReport.findings) || []) {
+        if (finding.severity === "info" || !match(finding)) continue;
+        if (finding.severity === "error") return finding;
+        worst = worst || finding;
+      }
+      return worst;
+    }
+
+    function weBoardRows() {
+      const info = boardInfo[weS.project.board] || {};
+      const rails = (info.rails || []).map(name => ({ name, caps:"power" }));
+      const pins = (info.pins || []).map(p => ({
+        name: p.name,
+        caps: p.caps.join(" ") + (p.input_only ? " in" : ""),
+        reserved: p.reserved,
+      }));
+      return rails.concat([{ name:"GND", caps:"ground" }], pins);
+    }
+
+    function weBoardPos() {
+      return weS.layout.board || (weS.layout.board = { x:20, y:20 });
+    }
+    function weNodePos(kind, index) {
+      return weS.layout.nodes[kind] || (weS.layout.nodes[kind] = {
+        x: WE_BOARD_W + 200 + (index % 2) * (WE_DEV_W + 40),
+        y: 20 + Math.floor(index / 2) * 110,
       });
     }
 
-    async function weExport() {
-      const data = JSON.stringify({ nodes: Object.values(weS.nodes), edges: weS.edges });
-      try {
-        await fetch('/api/wiring/editor', {
-          method: 'POST',
-          headers: { 'Content-Type': 'application/json' },
-          body: data,
+    function weNodeBox(parent, pos, width, rows, color, title, node, issue) {
+      const classes = "we-box" + (issue ? " " + issue.severity : "") + (node === weS.selected ? " selected" : "");
+      weEl("rect", { class:classes, x:pos.x, y:pos.y, width, height:WE_HDR + rows * WE_ROW + 4, rx:6, stroke:color }, parent);
+      const hdr = weEl("g", { class:"we-hdr", "data-node":node }, parent);
+      weEl("rect", { x:pos.x, y:pos.y, width, height:WE_HDR - 2, rx:6, fill:color }, hdr);
+      weEl("text", { x:pos.x + 8, y:pos.y + 14 }, hdr).textContent = title;
+      if (issue) weEl("title", {}, hdr).textContent = issue.message;
+    }
+
+    function weRender() {
+      const svg = $("we-svg");
+      while (svg.firstChild) svg.removeChild(svg.firstChild);
+      const project = weS.project;
+      if (!project) return;
+      const wires = weEl("g", {}, svg);
+      const anchors = {};
+      let right = 0, bottom = 0;
+
+      const bp = weBoardPos();
+      const rows = weBoardRows();
+      const board = weEl("g", {}, svg);
+      const used = new Set(Object.values(project.pins));
+      weNodeBox(board, bp, WE_BOARD_W, rows.length, "#2e7d32", (boardInfo[project.board] || {}).name || project.board, "board", null);
+      rows.forEach((row, i) => {
+        const y = bp.y + WE_HDR + i * WE_ROW;
+        const issue = weIssue(f => (f.pins || []).includes(row.name));
+        const g = weEl("g", { class:"we-row" + (row.reserved ? " reserved" : ""), "data-pin":row.name }, board);
+        weEl("rect", { class:"we-hit", x:bp.x, y, width:WE_BOARD_W, height:WE_ROW, fill:"transparent" }, g);
+        weEl("text", { x:bp.x + 8, y:y + 12 }, g).textContent = row.name;
+        weEl("text", { class:"we-caps", x:bp.x + WE_BOARD_W - 12, y:y + 12, "text-anchor":"end" }, g).textContent = row.caps;
+        const dotClass = "we-dot" + (used.has(row.name) ? " used" : "") + (issue ? " " + issue.severity : "");
+        weEl("circle", { class:dotClass, cx:bp.x + WE_BOARD_W, cy:y + WE_ROW / 2, r:5 }, g);
+        if (issue) weEl("title", {}, g).textContent = issue.message;
+        anchors[row.name] = { x:bp.x + WE_BOARD_W, y:y + WE_ROW / 2 };
+      });
+      right = bp.x + WE_BOARD_W;
+      bottom = bp.y + WE_HDR + rows.length * WE_ROW;
+
+      project.devices.forEach((device, index) => {
+        const info = weS.devices[device.kind];
+        if (!info) return;
+        const pos = weNodePos(device.kind, index);
+        const g = weEl("g", {}, svg);
+        const title = info.label + (device.address ? " @ " + device.address : "");
+        const issue = weIssue(f => (f.devices || []).includes(device.kind));
+        weNodeBox(g, pos, WE_DEV_W, info.signals.length, weColor(device.kind), title, device.kind, issue);
+        info.signals.forEach((sig, i) => {
+          const y = pos.y + WE_HDR + i * WE_ROW;
+          const target = project.pins[sig.pin];
+          const anchor = anchors[target];
+          const row = weEl("g", { class:"we-row", "data-kind":device.kind, "data-signal":sig.signal, "data-role":sig.pin }, g);
+          weEl("rect", { class:"we-hit", x:pos.x, y, width:WE_DEV_W, height:WE_ROW, fill:"transparent" }, row);
+          weEl("text", { x:pos.x + 12, y:y + 12 }, row).textContent = sig.signal;
+          weEl("text", { class:"we-caps", x:pos.x + WE_DEV_W - 8, y:y + 12, "text-anchor":"end" }, row).textContent =
+            anchor ? target : "not wired";
+          weEl("circle", { class:"we-dot" + (anchor ? " used" : " error"), cx:pos.x, cy:y + WE_ROW / 2, r:5 }, row);
+          if (anchor) {
+            const pinIssue = weIssue(f => (f.pins || []).includes(target));
+            weEl("path", {
+              class: "we-wire" + (pinIssue ? " " + pinIssue.severity : ""),
+              d: weCurve(anchor, { x:pos.x, y:y + WE_ROW / 2 }),
+              stroke: WE_WIRE_COLORS[sig.signal] || "#43a047",
+            }, wires);
+          }
         });
-        document.getElementById('we-status').textContent = 'Saved to server.';
-      } catch(err) {
-        document.getElementById('we-status').textContent = 'Export failed: ' + err.message;
+        right = Math.max(right, pos.x + WE_DEV_W);
+        bottom = Math.max(bottom, pos.y + WE_HDR + info.signals.length * WE_ROW);
+      });
+
+      const canvas = $("we-canvas");
+      svg.setAttribute("width", Math.max(canvas.clientWidth, right + 40));
+      svg.setAttribute("height", Math.max(canvas.clientHeight, bottom + 40));
+    }
+
+    function weRenderPalette() {
+      const palette = $("we-palette");
+      palette.innerHTML = "";
+      for (const info of Object.values(weS.devices)) {
+        const placed = !!weDevice(info.kind);
+        const supported = wiringAvailable.includes(info.kind);
+        const chip = document.createElement("div");
+        chip.className = "we-chip" + (placed || !supported ? " off" : "");
+        chip.style.borderColor = chip.style.color = weColor(info.kind);
+        chip.textContent = info.label;
+        chip.title = placed ? "Already on the canvas"
+          : !supported ? "Not supported on this board"
+          : info.address ? "I2C, default address " + info.address
+          : info.signals.map(s => s.signal).join(", ");
+        chip.draggable = !placed && supported;
+        chip.addEventListener("dragstart", e => e.dataTransfer.setData("we-kind", info.kind));
+        palette.appendChild(chip);
       }
     }
 
-    async function weImport() {
-      try {
-        const r = await fetch('/api/wiring/editor');
-        if (!r.ok) { document.getElementById('we-status').textContent = 'No saved data.'; return; }
-        weLoadData(await r.json());
-        document.getElementById('we-status').textContent = 'Imported from server.';
-      } catch(err) {
-        document.getElementById('we-status').textContent = 'Import failed: ' + err.message;
+    function weRenderInspector() {
+      const box = $("we-inspector");
+      const device = weDevice(weS.selected);
+      const info = device && weS.devices[device.kind];
+      box.innerHTML = "";
+      box.style.display = info ? "block" : "none";
+      if (!info) return;
+      const title = document.createElement("div");
+      title.style.fontWeight = "700";
+      title.textContent = info.label;
+      box.appendChild(title);
+      if (info.address) {
+        const field = document.createElement("label");
+        field.style.cssText = "display:block;margin:6px 0;color:var(--muted)";
+        field.textContent = "I2C address";
+        const input = document.createElement("input");
+        input.id = "we-address";
+        input.value = device.address || info.address;
+        input.style.cssText = "display:block;width:100%;box-sizing:border-box;font-family:monospace;font-size:11px";
+        input.addEventListener("change", () => weSetAddress(device.kind, input.value.trim()));
+        field.appendChild(input);
+        box.appendChild(field);
       }
+      const remove = document.createElement("button");
+      remove.className = "btn";
+      remove.textContent = "Remove";
+      remove.onclick = () => weRemove(device.kind);
+      box.appendChild(remove);
+    }
+
+    function weLayoutJson() {
+      const round = p => ({ x:Math.round(p.x), y:Math.round(p.y) });
+      return {
+        board: round(weBoardPos()),
+        nodes: Object.entries(weS.layout.nodes)
+          .filter(([kind]) => weDevice(kind))
+          .map(([kind, p]) => Object.assign({ kind }, round(p))),
+      };
     }
 
-    function weLoadData(data) {
-      weClear();
-      (data.nodes || []).forEach(function(n) {
-        if (!WE_DEFS[n.type]) return;
-        weS.nodes[n.id] = n;
-        const num = parseInt(n.id.replace('n', ''), 10);
-        if (!isNaN(num) && num >= weS.seq) weS.seq = num + 1;
-        document.getElementById('we-canvas').appendChild(weBuildNodeEl(n.id, n.type, n.x, n.y));
-      });
-      weS.edges = (data.edges || []).map(function(e) {
-        const num = parseInt(e.id.replace('e', ''), 10);
-        if (!isNaN(num) && num >= weS.seq) weS.seq = num + 1;
-        return e;
+    // Posts the edited project; the server rejects wiring it cannot represent
+    // (bad address, unsupported device) and the editor then reloads.
+    function weApply(action) {
+      const project = Object.assign({}, weS.project, { editor: weLayoutJson() });
+      weStatus(action + " \u2014 applying\u2026");
+      return queueWiringUpdate(async () => {
+        try {
+          const response = await fetch(api("/api/wiring/project"), {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify(project),
+          });
+          if (!response.ok) {
+            throw new Error(await response.text());
+          }
+          await refreshWiringUi();
+          clearErr();
+          weStatus(action + ". " + weSummary());
+        } catch(err) {
+          await weSync();
+          weStatus(wiringErrorMessage(action, err));
+        }
       });
-      weRenderEdges();
     }
 
-    function weClear() {
-      Object.keys(weS.nodes).forEach(function(id) {
-        const el = document.getElementById('we-node-' + id);
-        if (el) el.parentNode.removeChild(el);
+    function weSaveLayout() {
+      return queueWiringUpdate(async () => {
+        try {
+          const response = await fetch(api("/api/wiring/editor"), {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify(weLayoutJson()),
+          });
+          if (!response.ok) throw new Error(await response.text());
+        } catch(err) {
+          weStatus(wiringErrorMessage("Saving the layout", err));
+        }
       });
-      weS.nodes = {}; weS.edges = []; weS.seq = 1; weS.pending = null;
-      const svg = document.getElementById('we-svg');
-      while (svg.firstChild) svg.removeChild(svg.firstChild);
-      document.getElementById('we-status').textContent = 'Canvas cleared.';
     }
+
+    function weTidy() {
+      weS.layout = { board:null, nodes:{} };
+      weRender();
+      weSaveLayout();
+    }
+
+    function weConnect(end, pin) {
+      const pins = weS.project.pins;
+      if (pins[end.role] === pin) return;
+      pins[end.role] = pin;
+      const sharing = weS.project.devices.filter(d =>
+        (weS.devices[d.kind] || { signals:[] }).signals.some(s => s.pin === end.role)).length;
+      weApply(end.signal + " \u2192 " + pin + (sharing > 1 ? " (shared by " + sharing + " devices)" : ""));
+    }
+
+    function weSetAddress(kind, address) {
+      const device = weDevice(kind);
+      const info = weS.devices[kind];
+      if (!device || !address || address === device.address) return;
+      // Derived labels name the address, so let the server derive a new one.
+      if (device.label === info.label + " (" + device.address + ")") delete device.label;
+      device.address = address;
+      weApply(info.label + " address \u2192 " + address);
+    }
+
+    function weRemove(kind) {
+      weS.project.devices = weS.project.devices.filter(d => d.kind !== kind);
+      delete weS.layout.nodes[kind];
+      weS.selected = null;
+      weApply("Removed " + weS.devices[kind].label);
+    }
+
+    function weOnDrop(e) {
+      e.preventDefault();
+      const kind = e.dataTransfer.getData("we-kind");
+      if (!kind || !weS.project || !weS.devices[kind] || weDevice(kind)) return;
+      const p = weSvgPoint(e);
+      weS.layout.nodes[kind] = { x:Math.max(0, p.x - WE_DEV_W / 2), y:Math.max(0, p.y - WE_HDR / 2) };
+      weS.project.devices.push({ kind });
+      weS.selected = kind;
+      weApply("Added " + weS.devices[kind].label);
+    }
+
+    $("we-svg").addEventListener("mousedown", e => {
+      if (!weS.project || e.button !== 0) return;
+      const p = weSvgPoint(e);
+      const hdr = e.target.closest("[data-node]");
+      const row = e.target.closest("[data-pin],[data-signal]");
+      if (hdr) {
+        const node = hdr.dataset.node;
+        const pos = node === "board" ? weBoardPos() : weS.layout.nodes[node];
+        weS.drag = { node, pos, dx:p.x - pos.x, dy:p.y - pos.y, moved:false };
+      } else if (row) {
+        weS.wire = { from:Object.assign({}, row.dataset), start:p };
+      } else {
+        return;
+      }
+      e.preventDefault();
+    });
+    document.addEventListener("mousemove", e => {
+      if (weS.drag) {
+        const p = weSvgPoint(e);
+        weS.drag.pos.x = Math.max(0, p.x - weS.drag.dx);
+        weS.drag.pos.y = Math.max(0, p.y - weS.drag.dy);
+        weS.drag.moved = true;
+        weRender();
+      } else if (weS.wire) {
+        const temp = $("we-temp-wire") || weEl("path", { id:"we-temp-wire", class:"we-wire", stroke:"#ff9800" }, $("we-svg"));
+        temp.setAttribute("d", weCurve(weS.wire.start, weSvgPoint(e)));
+      }
+    });
+    document.addEventListener("mouseup", e => {
+      if (weS.drag) {
+        const drag = weS.drag;
+        weS.drag = null;
+        if (drag.moved) {
+          weSaveLayout();
+        } else if (drag.node !== "board") {
+          weS.selected = drag.node;
+          weRender();
+          weRenderInspector();
+        }
+      } else if (weS.wire) {
+        const from = weS.wire.from;
+        weS.wire = null;
+        const temp = $("we-temp-wire");
+        if (temp) temp.remove();
+        const hit = document.elementFromPoint(e.clientX, e.clientY);
+        const to = hit && hit.closest("#we-svg [data-pin], #we-svg [data-signal]");
+        if (!to || JSON.stringify(Object.assign({}, to.dataset)) === JSON.stringify(from)) return;
+        const ends = [from, to.dataset];
+        const device = ends.find(end => end.signal);
+        const board = ends.find(end => end.pin);
+        if (device && board) {
+          weConnect(device, board.pin);
+        } else {
+          weStatus("Wires run from a device pin to a board pin.");
+        }
+      }
+    });
     // ── Boot ──
     // ── Firmware Flash ────────────────────────────────────────────────────────
     let flashAllTargets = [];
@@ -1636,7 +2030,7 @@ pub fn dashboard_html() -> &'static str {
     function flashFilterTargets(board) {
       const tSel = document.getElementById('flash-target');
       tSel.innerHTML = '<option value="">-- select firmware --</option>';
-      const isPico = board === 'Raspberry Pi Pico';
+      const isPico = flashAllTargets.some(t => t.board === board && !t.needs_port);
       document.getElementById('flash-port-row').style.display = isPico ? 'none' : '';
       document.getElementById('flash-pico-hint').style.display = isPico ? 'block' : 'none';
       if (!board) return;
@@ -1670,7 +2064,7 @@ pub fn dashboard_html() -> &'static str {
     function flashStart() {
       const board  = document.getElementById('flash-board').value;
       const target = document.getElementById('flash-target').value;
-      const isPico = board === 'Raspberry Pi Pico';
+      const isPico = flashAllTargets.some(t => t.board === board && !t.needs_port);
       const port   = isPico ? '' : document.getElementById('flash-port').value;
       const out    = document.getElementById('flash-output');
       const btn    = document.getElementById('flash-btn');
@@ -1721,7 +2115,7 @@ pub fn dashboard_html() -> &'static str {
 
     function extBoardChange() {
       const board = document.getElementById('ext-flash-board').value;
-      const isPico = board === 'raspi-pico';
+      const isPico = !boardNeedsPort(board);
       document.getElementById('ext-port-row').style.display = isPico ? 'none' : '';
       document.getElementById('ext-pico-hint').style.display = isPico ? 'block' : 'none';
     }
@@ -1746,7 +2140,7 @@ pub fn dashboard_html() -> &'static str {
       const mode  = document.querySelector('input[name="ext-mode"]:checked').value;
       const path  = document.getElementById('ext-flash-path').value.trim();
       const board = document.getElementById('ext-flash-board').value;
-      const isPico = board === 'raspi-pico';
+      const isPico = !boardNeedsPort(board);
       const port  = isPico ? '' : document.getElementById('ext-flash-port').value;
       const out   = document.getElementById('ext-flash-output');
       const btn   = document.getElementById('ext-flash-btn');
@@ -1783,6 +2177,162 @@ pub fn dashboard_html() -> &'static str {
         document.getElementById('ext-flash-status').textContent = 'Connection error.';
       };
     }
+
+    // ── Simulation control (/api/control) ──
+    const CTL_SENSORS = [
+      ["temperature_c", "Temp \u00B0C", 0.1],
+      ["humidity_percent", "Humidity %", 0.1],
+      ["pressure_pa", "Pressure Pa", 1],
+      ["distance_mm", "HC-SR04 mm", 1],
+      ["lux", "Light lx", 1],
+      ["co2_ppm", "CO\u2082 ppm", 1],
+      ["voc_ppb", "TVOC ppb", 1],
+      ["tof_mm", "ToF mm", 1],
+      ["accel_x_mg", "Accel X mg", 1],
+      ["accel_y_mg", "Accel Y mg", 1],
+      ["accel_z_mg", "Accel Z mg", 1],
+      ["gyro_x_dps", "Gyro X \u00B0/s", 0.1],
+      ["gyro_y_dps", "Gyro Y \u00B0/s", 0.1],
+      ["gyro_z_dps", "Gyro Z \u00B0/s", 0.1],
+      ["rtc_unix_s", "RTC UNIX s", 1],
+    ];
+    let ctlState = null;
+    function ctlRender(state) {
+      ctlState = state;
+      $("ctl-pause-btn").innerHTML = state.paused ? "&#x25B6; Resume sim" : "&#x23F8; Pause sim";
+      $("ctl-record-btn").innerHTML = state.recording
+        ? "&#x23F9; Stop &amp; save (" + state.recorded_entries + ")"
+        : "&#x23FA; Record";
+      for (const [slug] of CTL_SENSORS) {
+        const active = state.sensors[slug] != null;
+        $("ctl-" + slug).closest("label").style.fontWeight = active ? "600" : "";
+      }
+      const parts = [state.paused ? "paused" : "running", "tick=" + state.tick];
+      const overrides = Object.entries(state.sensors).map(([k, v]) => k + "=" + v);
+      if (overrides.length) parts.push("overrides: " + overrides.join(", "));
+      if (state.servo_angle != null) parts.push("servo=" + state.servo_angle);
+      if (state.motors) parts.push("motors=" + state.motors.map(m => m.direction + "/" + m.duty_percent).join(" "));
+      if (state.playback_remaining != null) parts.push("scenario: " + state.playback_remaining + " left");
+      $("ctl-status").textContent = parts.join("  \u2502  ");
+    }
+    // ── History export ──
+    function histExport(format) {
+      const params = new URLSearchParams({ format });
+      const panel = $('hist-panel').value, range = $('hist-range').value;
+      if (panel) params.set('panel', panel);
+      if (range) params.set('last', range);
+      window.location.href = api('/api/history/export?') + params;
+    }
+
+    async function ctlPost(url, body) {
+      try {
+        const response = await fetch(api(url), {
+          method: "POST",
+          headers: { "Content-Type": "application/json" },
+          body: body === undefined ? "" : (typeof body === "string" ? body : JSON.stringify(body)),
+        });
+        const text = await response.text();
+        if (!response.ok) throw new Error(text || `HTTP ${response.status}`);
+        clearErr();
+        return text;
+      } catch(err) {
+        setErr("Control: " + err.message);
+        return null;
+      }
+    }
+    async function ctlPostJson(url, body) {
+      const text = await ctlPost(url, body);
+      if (text) ctlRender(JSON.parse(text));
+    }
+    function ctlTogglePause() {
+      ctlPostJson(ctlState && ctlState.paused ? "/api/control/resume" : "/api/control/pause");
+    }
+    function ctlStep() {
+      ctlPostJson("/api/control/step", { ticks: Math.max(1, +$("ctl-step-ticks").value || 1) });
+    }
+    function ctlSetSensor(slug, clear) {
+      const input = $("ctl-" + slug);
+      ctlPostJson("/api/control/sensor", { sensor: slug, value: clear ? null : +input.value });
+    }
+    function ctlSetMotors() {
+      const side = s => ({ direction: $("ctl-motor-" + s + "-dir").value, duty_percent: +$("ctl-motor-" + s + "-duty").value });
+      ctlPostJson("/api/control/motors", { left: side("left"), right: side("right") });
+    }
+    async function ctlToggleRecord() {
+      if (!(ctlState && ctlState.recording)) {
+        return ctlPostJson("/api/control/record/start");
+      }
+      const scenario = await ctlPost("/api/control/record/stop");
+      if (scenario == null) return;
+      const link = document.createElement("a");
+      link.href = URL.createObjectURL(new Blob([scenario], { type: "text/plain" }));
+      link.download = "dashboard-scenario.txt";
+      link.click();
+      URL.revokeObjectURL(link.href);
+      ctlPostJson("/api/control");
+    }
+    async function ctlPlayScenario(input) {
+      const file = input.files[0];
+      input.value = "";
+      if (file) ctlPostJson("/api/control/scenario", await file.text());
+    }
+    // ── Session selector ──
+    function sessionOpen(name) {
+      location.search = name === "default" ? "" : "?session=" + encodeURIComponent(name);
+    }
+    async function sessionsLoad() {
+      try {
+        const sessions = await fetchJsonOrThrow("/api/sessions", "load sessions");
+        const sel = $("session-select");
+        sel.innerHTML = "";
+        for (const s of sessions) {
+          sel.add(new Option(s.name + " \u00B7 " + s.board, s.name, false, s.name === SESSION));
+        }
+        if (!sessions.some(s => s.name === SESSION)) setErr(`session "${SESSION}" not found`);
+        $("session-delete").disabled = SESSION === "default";
+      } catch(err) {
+        setErr(err.message);
+      }
+    }
+    async function sessionCreate() {
+      const name = prompt("New session name (a-z, 0-9, - and _):");
+      if (!name) return;
+      const response = await fetch("/api/sessions", {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ name, board: $("board-select").value }),
+      });
+      if (response.ok) sessionOpen(name);
+      else setErr("Session: " + await response.text());
+    }
+    async function sessionDelete() {
+      if (SESSION === "default" || !confirm(`Stop session "${SESSION}"?`)) return;
+      const response = await fetch("/api/sessions/" + encodeURIComponent(SESSION), { method: "DELETE" });
+      if (response.ok) sessionOpen("default");
+      else setErr("Session: " + await response.text());
+    }
+    sessionsLoad();
+
+    (function ctlInit() {
+      const list = $("ctl-sensors");
+      for (const [slug, label, step] of CTL_SENSORS) {
+        const row = document.createElement("label");
+        row.className = "device-toggle";
+        row.innerHTML = `<span style="min-width:92px">${label}</span>`
+          + `<input type="number" id="ctl-${slug}" step="${step}" style="width:84px">`
+          + `<button class="btn" onclick="ctlSetSensor('${slug}', false)">Set</button>`
+          + `<button class="btn" onclick="ctlSetSensor('${slug}', true)">&#x2715;</button>`;
+        list.appendChild(row);
+      }
+      for (const side of ["left", "right"]) {
+        const sel = $("ctl-motor-" + side + "-dir");
+        for (const dir of ["forward", "reverse", "brake", "coast"]) {
+          sel.add(new Option(side + ": " + dir, dir));
+        }
+      }
+      ctlPostJson("/api/control");
+      setInterval(() => { if (!document.hidden) ctlPostJson("/api/control"); }, 2000);
+    })();
   </script>
 </body>
 </html>
@@ -1839,9 +2389,9 @@ mod tests {
         assert!(html.contains("/api/wiring/editor"));
         assert!(html.contains("we-canvas"));
         assert!(html.contains("we-sidebar"));
-        assert!(html.contains("weExport"));
-        assert!(html.contains("weImport"));
-        assert!(html.contains("weClear"));
+        assert!(html.contains("weSaveLayout"));
+        assert!(html.contains("weSync"));
+        assert!(html.contains("weTidy"));
         // Firmware flash panel
         assert!(html.contains("/api/flash/devices"));
         assert!(html.contains("/api/flash/targets"));
@@ -1874,6 +2424,8 @@ mod tests {
         // History fetch from server
         assert!(html.contains("fetchHistory"));
         assert!(html.contains("/api/history"));
+        assert!(html.contains("/api/history/export?"));
+        assert!(html.contains("histExport('parquet')"));
     }
 
     #[test]
@@ -2015,6 +2567,7 @@ mod tests {
                         message: "[bh1750] read_lux error".to_string(),
                     },
                 ],
+                active_alarms: vec!["warn co2-high = 1100".to_string()],
             },
         });
 
@@ -2107,6 +2660,10 @@ mod tests {
             json.contains("\"ts\":5000"),
             "diagnostics.recent_events[0] ts missing in JSON"
         );
+        assert!(
+            json.contains("\"active_alarms\":[\"warn co2-high = 1100\"]"),
+            "diagnostics.active_alarms missing in JSON"
+        );
         assert!(
             json.contains("\"diagnostics\""),
             "diagnostics key missing in JSON"
@@ -2138,10 +2695,98 @@ mod tests {
             "diag-event-count element missing"
         );
         assert!(html.contains("diag-events"), "diag-events list missing");
+        assert!(
+            html.contains("diag-active-alarms"),
+            "diag-active-alarms element missing"
+        );
         assert!(html.contains("Diagnostics"), "Diagnostics heading missing");
         assert!(
             html.contains("s.diagnostics"),
             "diagnostics renderState handler missing"
         );
     }
+    #[test]
+    fn html_contains_simulation_control_panel() {
+        let html = dashboard_html();
+        assert!(html.contains(r#"id="control-panel""#));
+        assert!(html.contains("/api/control/sensor"));
+        assert!(html.contains("/api/control/step"));
+        assert!(html.contains("/api/control/servo"));
+        assert!(html.contains("/api/control/motors"));
+        assert!(html.contains("/api/control/record/stop"));
+        assert!(html.contains("/api/control/scenario"));
+        for slug in crate::sim_control::SensorChannel::ALL.map(|c| c.slug()) {
+            assert!(html.contains(&format!(r#"["{slug}""#)), "{slug} missing");
+        }
+    }
+    #[test]
+    fn html_contains_session_selector() {
+        let html = dashboard_html();
+        assert!(html.contains(r#"id="session-select""#));
+        assert!(html.contains(r#"fetchJsonOrThrow("/api/sessions""#));
+        // Session-scoped calls go through `api()` so a selected session
+        // reaches /api/sessions/<name>/...
+        assert!(html.contains("new EventSource(api('/api/events'))"));
+        assert!(html.contains("fetch(api(url)"));
+        assert!(html.contains(r#"fetchTextOrThrow(api("/api/wiring/svg")"#));
+    }
+
+    #[test]
+    fn html_contains_wiring_check_findings() {
+        let html = dashboard_html();
+        assert!(html.contains(r#"id="wiring-check-list""#));
+        assert!(html.contains(r#"fetchJsonOrThrow(api("/api/wiring/check")"#));
+        assert!(html.contains("await loadWiringCheck();"));
+    }
+
+    #[test]
+    fn html_offers_all_board_profiles_with_onboard_devices() {
+        let html = dashboard_html();
+        assert!(html.contains(r#"<option value="raspi-pico">Raspberry Pi Pico</option>"#));
+        assert!(html.contains(r#"<option value="m5stickc">M5StickC</option>"#));
+        assert!(html.contains(r#"id="wiring-onboard""#));
+        assert!(html.contains("data.onboard_devices"));
+    }
+
+    #[test]
+    fn html_builds_board_selects_from_board_definitions() {
+        let html = dashboard_html();
+        assert!(html.contains(r#"<option value="pico">Raspberry Pi Pico</option>"#));
+        assert!(html.contains(r#"fetchJsonOrThrow("/api/boards", "load boards")"#));
+        assert!(html.contains("boardSel.value = data.board;"));
+        assert!(html.contains("const isPico = !boardNeedsPort(board);"));
+        assert!(!html.contains("board === 'Raspberry Pi Pico'"));
+    }
+
+    #[test]
+    fn html_wiring_editor_edits_the_project() {
+        let html = dashboard_html();
+        assert!(html.contains(r#"<svg id="we-svg""#));
+        assert!(html.contains(r#"fetchJsonOrThrow("/api/wiring/devices", "load device pins")"#));
+        assert!(
+            html.contains(r#"fetchJsonOrThrow(api("/api/wiring/project"), "load wiring project")"#)
+        );
+        assert!(html.contains("pins[end.role] = pin;"));
+        assert!(html.contains("weSetAddress(device.kind, input.value.trim())"));
+        assert!(html.contains("await weSync();"));
+        // The hardcoded part library is gone; devices and pins come from the server.
+        assert!(!html.contains("WE_DEFS"));
+    }
+
+    #[test]
+    fn html_contains_wiring_project_load_and_save() {
+        let html = dashboard_html();
+        assert!(html.contains(r#"id="wiring-project-file""#));
+        assert!(html.contains(r#"fetchTextOrThrow(api("/api/wiring/project")"#));
+        assert!(html.contains(r#"fetch(api("/api/wiring/project"), {"#));
+    }
+
+    #[test]
+    fn html_contains_wiring_export_downloads() {
+        let html = dashboard_html();
+        assert!(html.contains(r#"api("/api/wiring/export?")"#));
+        for format in ["kicad", "breadboard", "bom", "board-setup"] {
+            assert!(html.contains(&format!("wiringExport('{format}')")));
+        }
+    }
 }
@@ -1,5 +1,11 @@
 //! Browser dashboard state and HTML renderer.
 
+use core_app::telemetry::TelemetryFrame;
+use hal_api::distance::DistanceReading;
+use hal_api::gas::GasReading;
+use hal_api::imu::ImuReading;
+use hal_api::light::LightReading;
+use hal_api::sensor::EnvReading;
 use serde::Serialize;
 
 #[derive(Debug, Clone, Serialize)]
@@ -142,15 +148,57 @@ pub struct DiagEvent {
 /// `recent_events` holds up to 20 entries most-recent-first.
 /// `event_count` is a monotonically increasing counter of all events ever
 /// logged (useful for detecting new activity without diffing the list).
+/// `active_alarms` lists the threshold alarms currently raised, e.g.
+/// `"warn co2-high = 1100"`.
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct DiagnosticsPanelState {
     pub recent_events: Vec<DiagEvent>,
     pub event_count: u32,
+    pub active_alarms: Vec<String>,
 }
 
 pub fn state_to_json(state: &DeviceDashboardState) -> String {
     serde_json::to_string(state).unwrap_or_default()
 }
 
+/// Converts the dashboard snapshot into the same telemetry frame the firmware
+/// posts, so `/api/telemetry` emits byte-identical payloads.
+///
+/// Panels without a reading (or devices not selected in the wiring panel) are
+/// left out. The board name doubles as the device id.
+pub fn state_to_telemetry(state: &DeviceDashboardState) -> TelemetryFrame<'static> {
+    let selected = |slug: &str| state.wiring.selected_devices.iter().any(|s| s == slug);
+    let centi = |value: f32| (value * 100.0).round() as i32;
+    let mut frame = TelemetryFrame::new(state.board_name);
+
+    let climate = &state.climate;
+    if let (Some(temperature), Some(humidity)) = (climate.temperature_c, climate.humidity_percent) {
+        frame = frame.with_env(EnvReading::new(
+            centi(temperature),
+            centi(humidity).max(0) as u32,
+            climate.pressure_pa,
+        ));
+    }
+    if let (Some(co2_ppm), Some(voc_ppb)) = (state.gas.co2_ppm, state.gas.voc_ppb) {
+        frame = frame.with_gas(GasReading::new(co2_ppm, voc_ppb));
+    }
+    if selected("bh1750") {
+        frame = frame.with_light(LightReading::new(state.light.lux_x100));
+    }
+    if selected("mpu6050") {
+        frame = frame.with_imu(ImuReading::new(
+            state.imu.accel_mg,
+            state.imu.gyro_mdps,
+            state.imu.temperature_c.map(|value| centi(value) as i16),
+        ));
+    }
+    if let Some(distance_mm) = state.distance.distance_mm {
+        frame = frame.with_distance(DistanceReading::new(distance_mm));
+    }
+    frame
+}
+
+mod delta;
 mod html;
+pub use delta::{PanelDelta, PanelTracker, PANELS};
 pub use html::dashboard_html;
@@ -0,0 +1,512 @@
+//! 最小限の WebSocket (RFC 6455) 実装。
+//!
+//! `device-dashboard-web` の手書き HTTP サーバーに `/api/ws` を載せるためのもの。
+//! 拡張 (permessage-deflate 等) とサブプロトコルは扱わない。
+//!
+//! - [`server_handshake`] / [`client_handshake`] で HTTP Upgrade を行う
+//! - [`WebSocket`] はフレームの読み書きを行う。分割 (continuation) フレームは
+//!   1 つの [`Message`] に組み立てて返す
+//! - ping への pong 応答や close の折り返しは呼び出し側が行う。読み出しと書き込みを
+//!   別スレッドに分けたとき、フレームが混ざらないようにするため
+//!
+//! クライアント側 ([`WebSocket::client`]) はテストやツールから dashboard に繋ぐためのもので、
+//! マスク鍵は暗号論的な乱数ではない。
+//!
+//! # Examples
+//!
+//! ```
+//! use std::net::{TcpListener, TcpStream};
+//! use std::thread;
+//!
+//! use platform_pc_sim::websocket::{self, Message, WebSocket};
+//!
+//! let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//! let addr = listener.local_addr().unwrap();
+//! let server = thread::spawn(move || {
+//!     let (mut stream, _) = listener.accept().unwrap();
+//!     let head = websocket::read_request_head(&mut stream).unwrap();
+//!     websocket::server_handshake(&mut stream, &head).unwrap();
+//!     let mut socket = WebSocket::server(stream);
+//!     let message = socket.read_message().unwrap();
+//!     socket.send(&message).unwrap();
+//! });
+//!
+//! let mut stream = TcpStream::connect(addr).unwrap();
+//! websocket::client_handshake(&mut stream, "localhost", "/echo").unwrap();
+//! let mut socket = WebSocket::client(stream);
+//! socket.send(&Message::Text("hello".into())).unwrap();
+//! assert_eq!(socket.read_message().unwrap(), Message::Text("hello".into()));
+//! server.join().unwrap();
+//! ```
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `Sec-WebSocket-Accept` の計算に使う固定 GUID。
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// HTTP リクエストヘッダの上限。
+const MAX_HEAD_LEN: usize = 8 * 1024;
+/// 既定のメッセージ長上限。dashboard の JSON には十分な大きさ。
+pub const DEFAULT_MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// 受信・送信するメッセージ。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    /// ステータスコード付きの close。本文の理由文字列は捨てる
+    Close(Option<u16>),
+}
+
+/// `Sec-WebSocket-Key` に対する `Sec-WebSocket-Accept` の値。
+pub fn accept_key(client_key: &str) -> String {
+    let mut sha1 = sha1_smol::Sha1::new();
+    sha1.update(client_key.trim().as_bytes());
+    sha1.update(ACCEPT_GUID.as_bytes());
+    base64_encode(&sha1.digest().bytes())
+}
+
+/// ヘッダ名を大文字小文字を区別せずに探す。
+pub fn header_value<'a>(request_head: &'a str, name: &str) -> Option<&'a str> {
+    request_head.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// `Upgrade: websocket` を要求する HTTP リクエストか。
+pub fn is_upgrade_request(request_head: &str) -> bool {
+    header_value(request_head, "Upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+        && header_value(request_head, "Connection").is_some_and(|v| {
+            v.split(',')
+                .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+        })
+}
+
+/// 空行までの HTTP リクエストヘッダを 1 バイトずつ読む。
+///
+/// ヘッダ直後に届いた WebSocket フレームを読み過ぎないよう、バッファリングしない。
+pub fn read_request_head(stream: &mut impl Read) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_HEAD_LEN {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "request head too long",
+            ));
+        }
+        stream.read_exact(&mut byte)?;
+        head.push(byte[0]);
+    }
+    String::from_utf8(head).map_err(|_| io::Error::new(ErrorKind::InvalidData, "non-UTF-8 head"))
+}
+
+/// サーバー側のハンドシェイク。`request_head` は空行までのリクエスト。
+///
+/// Upgrade 要求として不正なら `400` を返して `InvalidData` エラーにする。
+pub fn server_handshake(stream: &mut impl Write, request_head: &str) -> io::Result<()> {
+    let key = header_value(request_head, "Sec-WebSocket-Key");
+    let version_ok = header_value(request_head, "Sec-WebSocket-Version") == Some("13");
+    let (Some(key), true, true) = (key, version_ok, is_upgrade_request(request_head)) else {
+        let body = "expected a WebSocket upgrade (version 13)";
+        let response = format!(
+            "HTTP/1.1 400 Bad Request\r\nSec-WebSocket-Version: 13\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes())?;
+        return Err(io::Error::new(ErrorKind::InvalidData, body));
+    };
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    );
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+/// クライアント側のハンドシェイク。`101` 以外や accept 値の不一致はエラー。
+pub fn client_handshake<S: Read + Write>(stream: &mut S, host: &str, path: &str) -> io::Result<()> {
+    let mut nonce = [0u8; 16];
+    fill_pseudo_random(&mut nonce);
+    let key = base64_encode(&nonce);
+    // 1 回の write で送る。サーバーが最初の read でヘッダ全体を受け取れるように
+    let request = format!(
+        "GET {path} HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\r\n"
+    );
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+
+    let head = read_request_head(stream)?;
+    let status_ok = head
+        .lines()
+        .next()
+        .is_some_and(|line| line.split_whitespace().nth(1) == Some("101"));
+    if !status_ok || header_value(&head, "Sec-WebSocket-Accept") != Some(accept_key(&key).as_str())
+    {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "WebSocket handshake rejected: {}",
+                head.lines().next().unwrap_or("")
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Role {
+    Server,
+    Client,
+}
+
+/// ハンドシェイク済みのストリーム上でフレームを読み書きする。
+pub struct WebSocket<S> {
+    stream: S,
+    role: Role,
+    max_message_len: usize,
+    /// 組み立て途中の分割メッセージ (最初の opcode, 受信済みの本文)
+    fragments: Option<(u8, Vec<u8>)>,
+}
+
+impl<S> WebSocket<S> {
+    /// サーバー側。受信フレームにはマスクを要求し、送信はマスクしない。
+    pub fn server(stream: S) -> Self {
+        Self {
+            stream,
+            role: Role::Server,
+            max_message_len: DEFAULT_MAX_MESSAGE_LEN,
+            fragments: None,
+        }
+    }
+
+    /// クライアント側。送信フレームをマスクする。
+    pub fn client(stream: S) -> Self {
+        Self {
+            stream,
+            role: Role::Client,
+            max_message_len: DEFAULT_MAX_MESSAGE_LEN,
+            fragments: None,
+        }
+    }
+
+    /// 受信メッセージ長の上限を変える。超えたら `InvalidData`。
+    pub fn with_max_message_len(mut self, max_message_len: usize) -> Self {
+        self.max_message_len = max_message_len;
+        self
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: Read> WebSocket<S> {
+    /// 次のメッセージを読む。制御フレームは分割メッセージの途中でもそのまま返す。
+    pub fn read_message(&mut self) -> io::Result<Message> {
+        loop {
+            let (fin, opcode, payload) = self.read_frame()?;
+            match opcode {
+                OP_CLOSE => {
+                    let code =
+                        (payload.len() >= 2).then(|| u16::from_be_bytes([payload[0], payload[1]]));
+                    return Ok(Message::Close(code));
+                }
+                OP_PING => return Ok(Message::Ping(payload)),
+                OP_PONG => return Ok(Message::Pong(payload)),
+                OP_TEXT | OP_BINARY if self.fragments.is_none() => {
+                    if fin {
+                        return message_from(opcode, payload);
+                    }
+                    self.fragments = Some((opcode, payload));
+                }
+                OP_CONTINUATION if self.fragments.is_some() => {
+                    let Some((first_opcode, mut data)) = self.fragments.take() else {
+                        unreachable!("checked by the match guard");
+                    };
+                    if data.len() + payload.len() > self.max_message_len {
+                        return Err(invalid("message too long"));
+                    }
+                    data.extend_from_slice(&payload);
+                    if fin {
+                        return message_from(first_opcode, data);
+                    }
+                    self.fragments = Some((first_opcode, data));
+                }
+                _ => return Err(invalid("unexpected opcode")),
+            }
+        }
+    }
+
+    fn read_frame(&mut self) -> io::Result<(bool, u8, Vec<u8>)> {
+        let mut header = [0u8; 2];
+        self.stream.read_exact(&mut header)?;
+        let fin = header[0] & 0x80 != 0;
+        if header[0] & 0x70 != 0 {
+            return Err(invalid("reserved bits set without an extension"));
+        }
+        let opcode = header[0] & 0x0F;
+        let masked = header[1] & 0x80 != 0;
+        if masked != (self.role == Role::Server) {
+            return Err(invalid(if masked {
+                "server frames must not be masked"
+            } else {
+                "client frames must be masked"
+            }));
+        }
+        let len = match header[1] & 0x7F {
+            126 => {
+                let mut ext = [0u8; 2];
+                self.stream.read_exact(&mut ext)?;
+                u64::from(u16::from_be_bytes(ext))
+            }
+            127 => {
+                let mut ext = [0u8; 8];
+                self.stream.read_exact(&mut ext)?;
+                u64::from_be_bytes(ext)
+            }
+            len => u64::from(len),
+        };
+        if opcode >= OP_CLOSE && (len > 125 || !fin) {
+            return Err(invalid("control frames must be short and unfragmented"));
+        }
+        if len > self.max_message_len as u64 {
+            return Err(invalid("message too long"));
+        }
+        let mut mask = [0u8; 4];
+        if masked {
+            self.stream.read_exact(&mut mask)?;
+        }
+        let mut payload = vec![0u8; len as usize];
+        self.stream.read_exact(&mut payload)?;
+        if masked {
+            apply_mask(&mut payload, mask);
+        }
+        Ok((fin, opcode, payload))
+    }
+}
+
+impl<S: Write> WebSocket<S> {
+    /// 1 フレームで送る (分割しない)。
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        let close_payload;
+        let (opcode, payload): (u8, &[u8]) = match message {
+            Message::Text(text) => (OP_TEXT, text.as_bytes()),
+            Message::Binary(data) => (OP_BINARY, data),
+            Message::Ping(data) => (OP_PING, data),
+            Message::Pong(data) => (OP_PONG, data),
+            Message::Close(code) => {
+                close_payload = code.map(u16::to_be_bytes);
+                (OP_CLOSE, close_payload.as_ref().map_or(&[][..], |c| &c[..]))
+            }
+        };
+        self.send_frame(opcode, payload)
+    }
+
+    /// テキストを `String` に複製せずに送る。共有している JSON をそのまま流す用。
+    pub fn send_text(&mut self, text: &str) -> io::Result<()> {
+        self.send_frame(OP_TEXT, text.as_bytes())
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mut header = Vec::with_capacity(14);
+        header.push(0x80 | opcode);
+        let mask_bit = if self.role == Role::Client { 0x80 } else { 0 };
+        match payload.len() {
+            len @ 0..=125 => header.push(mask_bit | len as u8),
+            len @ 126..=0xFFFF => {
+                header.push(mask_bit | 126);
+                header.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                header.push(mask_bit | 127);
+                header.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        if self.role == Role::Client {
+            let mut mask = [0u8; 4];
+            fill_pseudo_random(&mut mask);
+            header.extend_from_slice(&mask);
+            let mut masked = payload.to_vec();
+            apply_mask(&mut masked, mask);
+            header.extend_from_slice(&masked);
+            self.stream.write_all(&header)?;
+        } else {
+            // サーバー側は本文をコピーせずヘッダの後ろにそのまま書く
+            self.stream.write_all(&header)?;
+            self.stream.write_all(payload)?;
+        }
+        self.stream.flush()
+    }
+}
+
+fn message_from(opcode: u8, payload: Vec<u8>) -> io::Result<Message> {
+    if opcode == OP_TEXT {
+        String::from_utf8(payload)
+            .map(Message::Text)
+            .map_err(|_| invalid("text message is not UTF-8"))
+    } else {
+        Ok(Message::Binary(payload))
+    }
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+fn apply_mask(data: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+/// xorshift。マスク鍵とハンドシェイクの nonce 用で、予測可能でも困らない。
+fn fill_pseudo_random(out: &mut [u8]) {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0x9E37_79B9, |d| d.as_nanos() as u64);
+    let mut state = seed | 1;
+    for byte in out {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        *byte = state as u8;
+    }
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn accept_key_matches_rfc6455_example() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn server_reads_masked_fragmented_frames_from_rfc6455() {
+        // RFC 6455 5.7: masked "Hello", then an unmasked-text split into
+        // "Hel" + "lo" (masked here, since clients must mask).
+        let mut bytes = vec![
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let mut client = WebSocket::client(Vec::new());
+        client
+            .stream
+            .extend_from_slice(&[0x01, 0x83, 0, 0, 0, 0, b'H', b'e', b'l']);
+        client.send(&Message::Ping(b"p".to_vec())).unwrap();
+        client
+            .stream
+            .extend_from_slice(&[0x80, 0x82, 0, 0, 0, 0, b'l', b'o']);
+        bytes.extend_from_slice(&client.into_inner());
+
+        let mut server = WebSocket::server(Cursor::new(bytes));
+        assert_eq!(
+            server.read_message().unwrap(),
+            Message::Text("Hello".into())
+        );
+        assert_eq!(server.read_message().unwrap(), Message::Ping(b"p".to_vec()));
+        assert_eq!(
+            server.read_message().unwrap(),
+            Message::Text("Hello".into())
+        );
+    }
+
+    #[test]
+    fn frames_round_trip_with_extended_lengths_and_close_codes() {
+        let long = "x".repeat(70_000);
+        let mut server = WebSocket::server(Vec::new());
+        server.send(&Message::Text("hi".into())).unwrap();
+        server.send(&Message::Text(long.clone())).unwrap();
+        server.send(&Message::Binary(vec![7; 300])).unwrap();
+        server.send(&Message::Close(Some(1000))).unwrap();
+        let wire = server.into_inner();
+        assert_eq!(&wire[..4], &[0x81, 0x02, b'h', b'i']);
+
+        let mut client = WebSocket::client(Cursor::new(wire));
+        assert_eq!(client.read_message().unwrap(), Message::Text("hi".into()));
+        assert_eq!(client.read_message().unwrap(), Message::Text(long));
+        assert_eq!(
+            client.read_message().unwrap(),
+            Message::Binary(vec![7; 300])
+        );
+        assert_eq!(client.read_message().unwrap(), Message::Close(Some(1000)));
+    }
+
+    #[test]
+    fn rejects_unmasked_client_frames_and_oversized_messages() {
+        let mut server = WebSocket::server(Cursor::new(vec![0x81, 0x01, b'a']));
+        assert_eq!(
+            server.read_message().unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+
+        let mut client = WebSocket::client(Vec::new());
+        client.send(&Message::Text("0123456789".into())).unwrap();
+        let mut server =
+            WebSocket::server(Cursor::new(client.into_inner())).with_max_message_len(4);
+        assert_eq!(
+            server.read_message().unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn handshake_rejects_plain_http_requests() {
+        let head = "GET /api/ws HTTP/1.1\r\nHost: x\r\n\r\n";
+        let mut out = Vec::new();
+        assert!(server_handshake(&mut out, head).is_err());
+        assert!(String::from_utf8(out).unwrap().starts_with("HTTP/1.1 400"));
+
+        let head = "GET /api/ws HTTP/1.1\r\nHost: x\r\nupgrade: WebSocket\r\nConnection: keep-alive, Upgrade\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n";
+        let mut out = Vec::new();
+        server_handshake(&mut out, head).unwrap();
+        let response = String::from_utf8(out).unwrap();
+        assert!(response.starts_with("HTTP/1.1 101"));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+    }
+}
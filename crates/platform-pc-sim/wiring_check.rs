@@ -0,0 +1,171 @@
+//! Check a board wiring for address/pin conflicts and electrical problems.
+//!
+//! `wiring-check [esp32|nano|pico|m5stickc] [--profile <slug>] [--devices <a,b,...>]
+//! [--wiring <project.json>] [--pin <name>=<pin> ...] [--format text|json]
+//! [--fail-on error|warning]`
+//!
+//! Builds the same [`WiringConfig`] as the dashboard (board + sensor profile,
+//! optionally narrowed to `--devices`) or loads it from a wiring project file
+//! (`--wiring`), applies `--pin` overrides such as
+//! `--pin servo=GPIO12` and prints the findings of
+//! [`platform_pc_sim::wiring_rules::check`]. Exits with status 1 when a
+//! finding reaches the `--fail-on` severity (default `error`), so it can gate CI;
+//! unknown options and other usage errors exit with status 2.
+//!
+//! `--export kicad|breadboard|bom|board-setup` also writes the wiring through
+//! [`platform_pc_sim::wiring_export`] to `--out <file>` (or stdout, with the
+//! findings moved to stderr). `board-setup` is the firmware's generated
+//! pin/bus module and fails with status 2 on wiring it cannot build.
+//!
+//! Boards defined in `$BOARD_DEFS_DIR/*.json` are accepted by slug next to
+//! the built-in ones (see [`platform_pc_sim::board_def`]).
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use platform_pc_sim::board_def;
+use platform_pc_sim::cli_args::Args;
+use platform_pc_sim::dashboard::BoardProfile;
+use platform_pc_sim::wiring_config::{DeviceKind, SensorProfile, WiringConfig};
+use platform_pc_sim::wiring_export::{self, ExportFormat};
+use platform_pc_sim::wiring_project::WiringProject;
+use platform_pc_sim::wiring_rules::{self, Severity};
+
+const USAGE: &str = "usage:
+  wiring-check [esp32|nano|pico|m5stickc|<board slug>] [--profile <full|climate|robot|minimal>] [--devices <a,b,...>]
+  wiring-check --wiring <project.json>
+      [--pin <sda|scl|power|trig|echo|cam|servo|motor|in1|in2|enb|in3|in4>=<pin> ...]
+      [--format text|json] [--fail-on error|warning]
+      [--export kicad|breadboard|bom|board-setup [--out <file>]]";
+
+type CliResult<T = ()> = Result<T, String>;
+
+/// Options `run` reads; anything else is rejected.
+const OPTIONS: &[&str] = &[
+    "profile", "devices", "wiring", "pin", "format", "fail-on", "export", "out",
+];
+
+fn main() {
+    let result = board_def::load_from_env()
+        .map_err(|e| format!("{}: {e}", board_def::BOARD_DEFS_ENV))
+        .and_then(|_| Args::parse(env::args().skip(1), OPTIONS, USAGE))
+        .and_then(|args| run(&args));
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(message) => {
+            eprintln!("{message}");
+            process::exit(2);
+        }
+    }
+}
+
+/// Returns `false` when a finding reaches the `--fail-on` severity.
+fn run(args: &Args) -> CliResult<bool> {
+    let config = build_config(args)?;
+    let fail_on = match args.option("fail-on").unwrap_or("error") {
+        "error" => Severity::Error,
+        "warning" => Severity::Warning,
+        other => return Err(format!("unknown --fail-on {other:?}\n{USAGE}")),
+    };
+    let export = match args.option("export") {
+        Some(slug) => Some(
+            ExportFormat::from_slug(slug).ok_or(format!("unknown --export {slug:?}\n{USAGE}"))?,
+        ),
+        None => None,
+    };
+    let report = wiring_rules::check(&config);
+    let text = match args.option("format").unwrap_or("text") {
+        "text" => format!(
+            "{} / {} ({} devices)\n{report}",
+            config.board.name(),
+            config.sensor_profile.slug(),
+            config.devices.len()
+        ),
+        "json" => report.to_json(),
+        other => return Err(format!("unknown --format {other:?}\n{USAGE}")),
+    };
+    match (export, args.option("out")) {
+        (None, None) => println!("{text}"),
+        (None, Some(_)) => return Err(format!("--out needs --export\n{USAGE}")),
+        (Some(format), out) => {
+            let body = wiring_export::export(&config, format)
+                .map_err(|e| format!("{text}\ncannot export {}: {e}", format.slug()))?;
+            match out {
+                Some(path) => {
+                    fs::write(path, body).map_err(|e| format!("{path}: {e}"))?;
+                    println!("{text}");
+                    println!("wrote {} export to {path}", format.slug());
+                }
+                None => {
+                    eprintln!("{text}");
+                    print!("{body}");
+                }
+            }
+        }
+    }
+    Ok(report
+        .findings
+        .iter()
+        .all(|finding| finding.severity < fail_on))
+}
+
+fn build_config(args: &Args) -> CliResult<WiringConfig> {
+    let mut config = match args.option("wiring") {
+        Some(path) => WiringProject::load(Path::new(path))
+            .and_then(|project| project.to_config())
+            .map_err(|e| format!("{path}: {e}"))?,
+        None => config_from_args(args)?,
+    };
+    for assignment in args.all("pin") {
+        let (name, pin) = assignment
+            .split_once('=')
+            .ok_or(format!("--pin expects <name>=<pin>, got {assignment:?}"))?;
+        let field = match name {
+            "sda" => &mut config.sda_pin,
+            "scl" => &mut config.scl_pin,
+            "power" => &mut config.power_pin,
+            "trig" => &mut config.trig_pin,
+            "echo" => &mut config.echo_pin,
+            "cam" => &mut config.cam_pin,
+            "servo" => &mut config.servo_pin,
+            "motor" | "ena" => &mut config.motor_pin,
+            "in1" => &mut config.motor_in1_pin,
+            "in2" => &mut config.motor_in2_pin,
+            "enb" => &mut config.motor_enb_pin,
+            "in3" => &mut config.motor_in3_pin,
+            "in4" => &mut config.motor_in4_pin,
+            other => return Err(format!("unknown pin name {other:?}\n{USAGE}")),
+        };
+        *field = pin.to_string();
+    }
+    Ok(config)
+}
+
+fn config_from_args(args: &Args) -> CliResult<WiringConfig> {
+    let board = match args.positional(0) {
+        None => BoardProfile::ORIGINAL_ESP32,
+        Some(slug) => {
+            BoardProfile::from_slug(slug).ok_or(format!("unknown board {slug:?}\n{USAGE}"))?
+        }
+    };
+    let profile = match args.option("profile") {
+        Some(slug) => {
+            SensorProfile::from_slug(slug).ok_or(format!("unknown profile {slug:?}\n{USAGE}"))?
+        }
+        None => SensorProfile::Full,
+    };
+    Ok(match args.option("devices") {
+        Some(list) => {
+            let kinds = list
+                .split(',')
+                .filter(|slug| !slug.is_empty())
+                .map(|slug| DeviceKind::from_slug(slug).ok_or(format!("unknown device {slug:?}")))
+                .collect::<CliResult<Vec<_>>>()?;
+            WiringConfig::from_board_with_selected_devices(board, profile, &kinds)
+        }
+        None => WiringConfig::from_board_with_sensors(board, profile),
+    })
+}
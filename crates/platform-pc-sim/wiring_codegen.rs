@@ -0,0 +1,829 @@
+//! Generate the firmware's board-setup module from a [`WiringConfig`].
+//!
+//! Pin numbers used to live twice: in `BoardProfile` for the simulator and as
+//! constants in each firmware. [`board_setup`] turns the wiring the simulator
+//! runs into a Rust module for the firmware crate, so both come from one
+//! place:
+//!
+//! - **original ESP32** (esp-hal 1.0): pin and address constants, the I2C
+//!   bus in a `RefCell` shared through `SharedI2cBus`, every I2C driver, LEDC
+//!   timers and channels for the servo and L298N, and GPIO for the rest.
+//!   `run(peripherals, app)` builds them on its own stack frame and hands a
+//!   `Board` to a closure that never returns, so the drivers can borrow the
+//!   bus and timers without `'static` storage. The M5StickC gets the same
+//!   module for its Grove/HAT pins.
+//! - **Arduino Nano** (arduino-hal): constants (Arduino digital pin numbers,
+//!   A0 = 14), the I2C bus on A4/A5 and the I2C drivers. arduino-hal types
+//!   each pin separately and `platform-avr` has no PWM adapter, so GPIO and
+//!   PWM devices get constants only.
+//!
+//! The Raspberry Pi Pico is not covered yet and is refused as unsupported.
+//!
+//! Wiring that cannot become working code — an I2C address or a pin used
+//! twice, a reserved pin — is refused with the `wiring_rules` findings.
+
+use std::fmt;
+use std::fmt::Write as _;
+
+use crate::board_def::{McuFamily, PinDef};
+use crate::dashboard::BoardProfile;
+use crate::wiring_config::{ConnectionType, DeviceKind, DeviceSpec, WiringConfig};
+use crate::wiring_rules::{self, PinDirection, Rule, Severity};
+
+/// Why [`board_setup`] refused a wiring.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodegenError {
+    /// Findings that would turn into broken code.
+    Wiring(Vec<String>),
+    /// A pin or bus the target cannot express.
+    Unsupported(String),
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodegenError::Wiring(messages) => {
+                write!(f, "fix the wiring first: {}", messages.join("; "))
+            }
+            CodegenError::Unsupported(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for CodegenError {}
+
+const SERVO_PWM_HZ: u32 = 50;
+const MOTOR_PWM_HZ: u32 = 1_000;
+const NANO_I2C_FREQUENCY_HZ: u32 = 100_000;
+const MAX_WIDTH: usize = 100;
+
+/// A board pin the generated module names.
+struct PinConst {
+    /// Constant and field stem, e.g. `HC_SR04_TRIG`.
+    stem: String,
+    pin: String,
+    number: u8,
+    owner: String,
+    signal: &'static str,
+    direction: PinDirection,
+    device: Option<DeviceKind>,
+}
+
+impl PinConst {
+    fn field(&self) -> String {
+        self.stem.to_lowercase()
+    }
+}
+
+/// Driver type and constructor of an I2C device.
+struct I2cDriver {
+    module: &'static str,
+    ty: &'static str,
+    ctor: &'static str,
+    /// Error type when the constructor talks to the device and can fail.
+    error: Option<&'static str>,
+    needs_delay: bool,
+}
+
+fn i2c_driver(kind: DeviceKind) -> Option<I2cDriver> {
+    let (module, ty, ctor, error) = match kind {
+        DeviceKind::Bme280 => ("bme280", "Bme280Sensor", "new_with_address", None),
+        DeviceKind::Mpu6050 => ("mpu6050", "Mpu6050Sensor", "new_with_address", None),
+        DeviceKind::Lcd1602 => ("lcd1602", "Lcd1602Display", "new_with_address", None),
+        DeviceKind::Bh1750 => ("bh1750", "Bh1750Sensor", "new", Some("SensorError")),
+        DeviceKind::Ds3231 => ("ds3231", "Ds3231Sensor", "new", None),
+        DeviceKind::Sgp30 => ("sgp30", "Sgp30Sensor", "new", Some("SensorError")),
+        DeviceKind::Vl53l0x => ("vl53l0x", "Vl53l0xSensor", "new", Some("SensorError")),
+        DeviceKind::Ssd1306 => ("ssd1306", "Ssd1306Display", "new", Some("DisplayError")),
+        DeviceKind::HcSr04 | DeviceKind::Servo | DeviceKind::L298n | DeviceKind::Esp32Cam => {
+            return None
+        }
+    };
+    Some(I2cDriver {
+        module,
+        ty,
+        ctor,
+        error,
+        needs_delay: kind == DeviceKind::Lcd1602,
+    })
+}
+
+/// Generate the board-setup module for `config`.
+pub fn board_setup(config: &WiringConfig) -> Result<String, CodegenError> {
+    let blocking: Vec<String> = wiring_rules::check(config)
+        .findings
+        .into_iter()
+        .filter(|finding| {
+            finding.severity == Severity::Error
+                && matches!(
+                    finding.rule,
+                    Rule::AddressConflict | Rule::PinConflict | Rule::ReservedPin
+                )
+        })
+        .map(|finding| finding.message)
+        .collect();
+    if !blocking.is_empty() {
+        return Err(CodegenError::Wiring(blocking));
+    }
+    if config.devices.is_empty() {
+        return Err(CodegenError::Unsupported(
+            "the wiring has no devices to set up".to_string(),
+        ));
+    }
+    let family = config.board.family();
+    if family == McuFamily::Rp2040 {
+        return Err(CodegenError::Unsupported(format!(
+            "board-setup generation does not cover the {} yet",
+            config.board.name()
+        )));
+    }
+    let pins = pin_consts(config)?;
+    let i2c = i2c_devices(config)?;
+    let mut out = String::with_capacity(8192);
+    header(&mut out, config, &pins, &i2c);
+    match family {
+        McuFamily::Esp32 => esp32(&mut out, config, &pins, &i2c),
+        McuFamily::Avr => nano(&mut out, config, &pins, &i2c)?,
+        McuFamily::Rp2040 => unreachable!("refused above"),
+    }
+    Ok(out)
+}
+
+fn pin_consts(config: &WiringConfig) -> Result<Vec<PinConst>, CodegenError> {
+    wiring_rules::pin_uses(config)
+        .into_iter()
+        .map(|pin_use| {
+            let prefix = match pin_use.device {
+                None => "I2C".to_string(),
+                Some(DeviceKind::L298n) => "MOTOR".to_string(),
+                Some(kind) => kind.slug().to_uppercase(),
+            };
+            Ok(PinConst {
+                stem: format!("{prefix}_{}", pin_use.signal),
+                number: pin_number(config.board, &pin_use.pin)?,
+                owner: pin_use
+                    .device
+                    .map_or("I2C bus".to_string(), |kind| kind.label().to_string()),
+                pin: pin_use.pin,
+                signal: pin_use.signal,
+                direction: pin_use.direction,
+                device: pin_use.device,
+            })
+        })
+        .collect()
+}
+
+fn pin_number(board: BoardProfile, pin: &str) -> Result<u8, CodegenError> {
+    board
+        .pin(pin)
+        .and_then(PinDef::pin_number)
+        .ok_or_else(|| CodegenError::Unsupported(format!("{pin} is not a {} pin", board.name())))
+}
+
+fn i2c_devices(config: &WiringConfig) -> Result<Vec<(&DeviceSpec, I2cDriver)>, CodegenError> {
+    config
+        .devices
+        .iter()
+        .filter_map(|device| i2c_driver(device.kind).map(|driver| (device, driver)))
+        .map(|(device, driver)| match device.address {
+            Some(_) => Ok((device, driver)),
+            None => Err(CodegenError::Unsupported(format!(
+                "{} has no I2C address",
+                device.kind.label()
+            ))),
+        })
+        .collect()
+}
+
+fn address_const(kind: DeviceKind) -> String {
+    format!("{}_ADDRESS", kind.slug().to_uppercase())
+}
+
+fn has(config: &WiringConfig, kind: DeviceKind) -> bool {
+    config.devices.iter().any(|device| device.kind == kind)
+}
+
+fn header(
+    out: &mut String,
+    config: &WiringConfig,
+    pins: &[PinConst],
+    i2c: &[(&DeviceSpec, I2cDriver)],
+) {
+    let _ = writeln!(
+        out,
+        "//! Board setup for the {} ({} profile).\n//!\n\
+         //! Generated by `wiring-check --export board-setup` from the wiring the\n\
+         //! simulator runs. Change the wiring project and regenerate instead of\n\
+         //! editing this file.\n//!\n\
+         //! | Signal | Pin | Device |\n//! |--------|-----|--------|",
+        config.board.name(),
+        config.sensor_profile.slug()
+    );
+    for pin in pins {
+        let _ = writeln!(out, "//! | {} | {} | {} |", pin.signal, pin.pin, pin.owner);
+    }
+    if !i2c.is_empty() {
+        let addresses: Vec<String> = i2c
+            .iter()
+            .map(|(device, _)| {
+                format!(
+                    "{} 0x{:02X}",
+                    device.kind.label(),
+                    device.address.unwrap_or_default()
+                )
+            })
+            .collect();
+        let _ = writeln!(out, "//!\n//! I2C: {}.", addresses.join(", "));
+    }
+    out.push_str("#![allow(dead_code)]\n\n");
+}
+
+fn constants(out: &mut String, pins: &[PinConst], i2c: &[(&DeviceSpec, I2cDriver)], suffix: &str) {
+    for pin in pins {
+        let _ = writeln!(
+            out,
+            "/// {} — {} {}.\npub const {}_{suffix}: u8 = {};",
+            pin.pin, pin.owner, pin.signal, pin.stem, pin.number
+        );
+    }
+    for (device, _) in i2c {
+        let _ = writeln!(
+            out,
+            "/// {} I2C address.\npub const {}: u8 = 0x{:02X};",
+            device.kind.label(),
+            address_const(device.kind),
+            device.address.unwrap_or_default()
+        );
+    }
+}
+
+/// `let name = callee(args);`, wrapped the way rustfmt does.
+fn let_call(out: &mut String, name: &str, callee: &str, args: &[String]) {
+    let joined = args.join(", ");
+    let call = format!("{callee}({joined});");
+    if joined.len() <= 60 {
+        if 4 + format!("let {name} = ").len() + call.len() <= MAX_WIDTH {
+            let _ = writeln!(out, "    let {name} = {call}");
+            return;
+        }
+        if 8 + call.len() <= MAX_WIDTH {
+            let _ = writeln!(out, "    let {name} =\n        {call}");
+            return;
+        }
+    }
+    let _ = writeln!(out, "    let {name} = {callee}(");
+    for arg in args {
+        let _ = writeln!(out, "        {arg},");
+    }
+    out.push_str("    );\n");
+}
+
+/// `app(Board { fields })` and the closing brace of `run`; rustfmt keeps
+/// struct literals of up to 18 columns on one line.
+fn call_app(out: &mut String, fields: &[String]) {
+    let inline = fields.join(", ");
+    if inline.len() <= 18 {
+        let _ = writeln!(out, "    app(Board {{ {inline} }})\n}}");
+        return;
+    }
+    out.push_str("    app(Board {\n");
+    for field in fields {
+        let _ = writeln!(out, "        {field},");
+    }
+    out.push_str("    })\n}\n");
+}
+
+/// Board fields and constructor statements for the I2C drivers.
+fn i2c_fields(
+    out: &mut String,
+    i2c: &[(&DeviceSpec, I2cDriver)],
+    delay: &str,
+    fields: &mut Vec<String>,
+) {
+    for (device, driver) in i2c {
+        let mut args = vec!["BoardBus::new(&bus)".to_string()];
+        if driver.needs_delay {
+            args.push(delay.to_string());
+        }
+        args.push(address_const(device.kind));
+        let field = device.kind.slug().to_string();
+        let_call(
+            out,
+            &field,
+            &format!("{}::{}", driver.ty, driver.ctor),
+            &args,
+        );
+        fields.push(field);
+    }
+}
+
+fn struct_field_types(out: &mut String, i2c: &[(&DeviceSpec, I2cDriver)], delay_ty: &str) {
+    for (device, driver) in i2c {
+        let ty = if driver.needs_delay {
+            format!("{}<BoardBus<'a>, {delay_ty}>", driver.ty)
+        } else {
+            format!("{}<BoardBus<'a>>", driver.ty)
+        };
+        let ty = match driver.error {
+            Some(error) => format!("Result<{ty}, {error}>"),
+            None => ty,
+        };
+        let _ = writeln!(out, "    pub {}: {ty},", device.kind.slug());
+    }
+}
+
+fn error_imports(out: &mut String, i2c: &[(&DeviceSpec, I2cDriver)]) {
+    let mut errors: Vec<&str> = i2c.iter().filter_map(|(_, driver)| driver.error).collect();
+    errors.sort_unstable();
+    errors.dedup();
+    match errors.as_slice() {
+        [] => {}
+        [one] => {
+            let _ = writeln!(out, "use hal_api::error::{one};");
+        }
+        many => {
+            let _ = writeln!(out, "use hal_api::error::{{{}}};", many.join(", "));
+        }
+    }
+}
+
+fn esp32(
+    out: &mut String,
+    config: &WiringConfig,
+    pins: &[PinConst],
+    i2c: &[(&DeviceSpec, I2cDriver)],
+) {
+    let servo = has(config, DeviceKind::Servo);
+    let motors = has(config, DeviceKind::L298n);
+    let pwm = servo || motors;
+    let gpio: Vec<&PinConst> = pins
+        .iter()
+        .filter(|pin| {
+            pin.device.is_some()
+                && !matches!(pin.device, Some(DeviceKind::Servo | DeviceKind::L298n))
+        })
+        .collect();
+    let outputs = motors || gpio.iter().any(|pin| pin.direction == PinDirection::Output);
+    let inputs = gpio.iter().any(|pin| pin.direction != PinDirection::Output);
+    let lcd = i2c.iter().any(|(_, driver)| driver.needs_delay);
+    let borrows = !i2c.is_empty() || pwm;
+    let pin = |stem: &str| -> &PinConst {
+        pins.iter()
+            .find(|pin| pin.stem == stem)
+            .expect("pin_uses lists every wired signal")
+    };
+
+    // Imports, in rustfmt order.
+    if !i2c.is_empty() {
+        out.push_str("use core::cell::RefCell;\n\n");
+    }
+    if lcd {
+        out.push_str("use esp_hal::delay::Delay;\n");
+    }
+    let mut gpio_names = Vec::new();
+    if inputs {
+        gpio_names.extend(["Input", "InputConfig"]);
+    }
+    if outputs {
+        gpio_names.extend(["Level", "Output", "OutputConfig"]);
+    }
+    if !gpio_names.is_empty() {
+        let _ = writeln!(out, "use esp_hal::gpio::{{{}}};", gpio_names.join(", "));
+    }
+    if !i2c.is_empty() {
+        out.push_str("use esp_hal::i2c::master::{Config as I2cConfig, I2c};\n");
+    }
+    if pwm {
+        out.push_str(
+            "use esp_hal::ledc::channel::{self, ChannelConfig, ChannelIFace};\n\
+             use esp_hal::ledc::timer::{self, TimerConfig, TimerIFace};\n\
+             use esp_hal::ledc::{LSGlobalClkSource, Ledc, LowSpeed};\n",
+        );
+    }
+    out.push_str("use esp_hal::peripherals::Peripherals;\n");
+    if pwm {
+        out.push_str("use esp_hal::time::Rate;\n");
+    }
+    if !i2c.is_empty() {
+        out.push_str("use esp_hal::Blocking;\n");
+    }
+    error_imports(out, i2c);
+    let mut platform: Vec<String> = i2c
+        .iter()
+        .map(|(_, driver)| format!("use platform_esp32::{}::{};", driver.module, driver.ty))
+        .collect();
+    if motors {
+        platform.push("use platform_esp32::gpio::Esp32OutputPin;".to_string());
+    }
+    if !i2c.is_empty() {
+        platform.push("use platform_esp32::i2c::Esp32I2c;".to_string());
+        platform.push("use platform_esp32::shared_i2c::SharedI2cBus;".to_string());
+    }
+    if pwm {
+        platform.push("use platform_esp32::pwm::Esp32PwmOutput;".to_string());
+    }
+    match (servo, motors) {
+        (true, true) => platform.push(
+            "use platform_esp32::types::{Esp32L298nChannel, Esp32L298nDualDriverSimple, Esp32ServoDriver};"
+                .to_string(),
+        ),
+        (true, false) => platform.push("use platform_esp32::types::Esp32ServoDriver;".to_string()),
+        (false, true) => platform.push(
+            "use platform_esp32::types::{Esp32L298nChannel, Esp32L298nDualDriverSimple};".to_string(),
+        ),
+        (false, false) => {}
+    }
+    platform.sort();
+    for line in platform {
+        if line.len() > MAX_WIDTH {
+            // Only the three-name `types` import gets this long.
+            let (path, names) = line
+                .trim_end_matches("};")
+                .split_once('{')
+                .expect("long imports are brace lists");
+            let _ = writeln!(out, "{path}{{");
+            for name in names.split(", ") {
+                let _ = writeln!(out, "    {name},");
+            }
+            out.push_str("};\n");
+        } else {
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    out.push('\n');
+
+    constants(out, pins, i2c, "GPIO");
+    if servo {
+        let _ = writeln!(
+            out,
+            "/// Servo PWM frequency (LEDC timer 0, 14-bit duty).\npub const SERVO_PWM_HZ: u32 = {SERVO_PWM_HZ};"
+        );
+    }
+    if motors {
+        let _ = writeln!(
+            out,
+            "/// L298N enable PWM frequency (LEDC timer 1, 8-bit duty).\npub const MOTOR_PWM_HZ: u32 = {};",
+            group_digits(MOTOR_PWM_HZ)
+        );
+    }
+    out.push('\n');
+
+    if !i2c.is_empty() {
+        out.push_str(
+            "/// The I2C bus every I2C driver shares.\n\
+             pub type BoardI2c = Esp32I2c<I2c<'static, Blocking>>;\n\
+             /// Handle to [`BoardI2c`] held by each driver.\n\
+             pub type BoardBus<'a> = SharedI2cBus<'a, BoardI2c>;\n",
+        );
+    }
+    if pwm {
+        out.push_str(
+            "/// LEDC channel borrowing its timer from [`run`].\n\
+             pub type BoardPwm<'a> = channel::Channel<'a, LowSpeed>;\n",
+        );
+    }
+    if borrows {
+        out.push('\n');
+    }
+
+    let board = if borrows { "Board<'a>" } else { "Board" };
+    let _ = writeln!(
+        out,
+        "/// Every device on the wiring, ready to use.\npub struct {board} {{"
+    );
+    struct_field_types(out, i2c, "Delay");
+    if servo {
+        out.push_str("    pub servo: Esp32ServoDriver<BoardPwm<'a>>,\n");
+    }
+    if motors {
+        out.push_str(
+            "    pub motors: Esp32L298nDualDriverSimple<Output<'static>, BoardPwm<'a>>,\n",
+        );
+    }
+    for pin in &gpio {
+        let ty = if pin.direction == PinDirection::Output {
+            "Output<'static>"
+        } else {
+            "Input<'static>"
+        };
+        let _ = writeln!(out, "    pub {}: {ty},", pin.field());
+    }
+    out.push_str("}\n\n");
+
+    let app_board = if borrows { "Board<'_>" } else { "Board" };
+    out.push_str("/// Take the peripherals, set up every device and hand them to `app`.\n");
+    let lenders = match (!i2c.is_empty(), pwm) {
+        (true, true) => Some("The I2C bus and LEDC timers live"),
+        (true, false) => Some("The I2C bus lives"),
+        (false, true) => Some("The LEDC timers live"),
+        (false, false) => None,
+    };
+    if let Some(lenders) = lenders {
+        let _ = writeln!(
+            out,
+            "///\n/// {lenders} on this stack frame, which never returns,\n\
+             /// so the drivers in [`Board`] can borrow them."
+        );
+    }
+    let _ = writeln!(
+        out,
+        "pub fn run(peripherals: Peripherals, app: impl FnOnce({app_board}) -> !) -> ! {{"
+    );
+    let mut fields = Vec::new();
+    if !i2c.is_empty() {
+        let _ = writeln!(
+            out,
+            "    let i2c = I2c::new(peripherals.I2C0, I2cConfig::default())\n        .unwrap()\n        \
+             .with_sda(peripherals.GPIO{})\n        .with_scl(peripherals.GPIO{});\n    \
+             let bus = RefCell::new(Esp32I2c::new(i2c));",
+            pin("I2C_SDA").number,
+            pin("I2C_SCL").number
+        );
+        i2c_fields(out, i2c, "Delay::new()", &mut fields);
+    }
+    if pwm {
+        out.push_str(
+            "    let mut ledc = Ledc::new(peripherals.LEDC);\n    \
+             ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);\n",
+        );
+    }
+    let timer = |out: &mut String, name: &str, number: u8, duty: &str, hz: &str| {
+        let _ = writeln!(
+            out,
+            "    let mut {name} = ledc.timer::<LowSpeed>(timer::Number::Timer{number});\n    \
+             {name}\n        .configure(TimerConfig {{\n            \
+             duty: timer::config::Duty::{duty},\n            \
+             clock_source: timer::LSClockSource::APBClk,\n            \
+             frequency: Rate::from_hz({hz}),\n        }})\n        .unwrap();"
+        );
+    };
+    let channel = |out: &mut String, name: &str, number: u8, gpio: u8, timer: &str| {
+        let _ = writeln!(
+            out,
+            "    let mut {name} = ledc.channel(channel::Number::Channel{number}, peripherals.GPIO{gpio});\n    \
+             {name}\n        .configure(ChannelConfig {{\n            \
+             timer: &{timer},\n            duty_pct: 0,\n            \
+             pin_config: channel::config::PinConfig::PushPull,\n        }})\n        .unwrap();"
+        );
+    };
+    if servo {
+        timer(out, "servo_timer", 0, "Duty14Bit", "SERVO_PWM_HZ");
+        channel(out, "servo_pwm", 0, pin("SERVO_PWM").number, "servo_timer");
+        out.push_str("    let servo = Esp32ServoDriver::new(Esp32PwmOutput::new(servo_pwm));\n");
+        fields.push("servo".to_string());
+    }
+    if motors {
+        timer(out, "motor_timer", 1, "Duty8Bit", "MOTOR_PWM_HZ");
+        channel(out, "motor_ena", 1, pin("MOTOR_ENA").number, "motor_timer");
+        channel(out, "motor_enb", 2, pin("MOTOR_ENB").number, "motor_timer");
+        for stem in ["MOTOR_IN1", "MOTOR_IN2", "MOTOR_IN3", "MOTOR_IN4"] {
+            let pin = pin(stem);
+            let_call(
+                out,
+                &pin.field(),
+                "Output::new",
+                &[
+                    format!("peripherals.GPIO{}", pin.number),
+                    "Level::Low".to_string(),
+                    "OutputConfig::default()".to_string(),
+                ],
+            );
+        }
+        out.push_str(
+            "    let motors = Esp32L298nDualDriverSimple::new(\n        \
+             Esp32L298nChannel::new(\n            Esp32OutputPin::new(motor_in1),\n            \
+             Esp32OutputPin::new(motor_in2),\n            Esp32PwmOutput::new(motor_ena),\n        ),\n        \
+             Esp32L298nChannel::new(\n            Esp32OutputPin::new(motor_in3),\n            \
+             Esp32OutputPin::new(motor_in4),\n            Esp32PwmOutput::new(motor_enb),\n        ),\n    );\n",
+        );
+        fields.push("motors".to_string());
+    }
+    for pin in &gpio {
+        let gpio = format!("peripherals.GPIO{}", pin.number);
+        let (callee, args) = if pin.direction == PinDirection::Output {
+            (
+                "Output::new",
+                vec![
+                    gpio,
+                    "Level::Low".to_string(),
+                    "OutputConfig::default()".to_string(),
+                ],
+            )
+        } else {
+            (
+                "Input::new",
+                vec![gpio, "InputConfig::default()".to_string()],
+            )
+        };
+        let_call(out, &pin.field(), callee, &args);
+        fields.push(pin.field());
+    }
+    call_app(out, &fields);
+}
+
+fn nano(
+    out: &mut String,
+    config: &WiringConfig,
+    pins: &[PinConst],
+    i2c: &[(&DeviceSpec, I2cDriver)],
+) -> Result<(), CodegenError> {
+    if !i2c.is_empty() && (config.sda_pin != "A4" || config.scl_pin != "A5") {
+        return Err(CodegenError::Unsupported(format!(
+            "the Nano's I2C is fixed to SDA=A4, SCL=A5, not SDA={} SCL={}",
+            config.sda_pin, config.scl_pin
+        )));
+    }
+    let lcd = i2c.iter().any(|(_, driver)| driver.needs_delay);
+    if !i2c.is_empty() {
+        out.push_str("use core::cell::RefCell;\n\n");
+        error_imports(out, i2c);
+        let mut platform: Vec<String> = i2c
+            .iter()
+            .map(|(_, driver)| format!("use platform_avr::{}::{};", driver.module, driver.ty))
+            .collect();
+        platform.push("use platform_avr::i2c::AvrI2c;".to_string());
+        platform.push("use platform_avr::shared_i2c::SharedI2cBus;".to_string());
+        platform.sort();
+        for line in platform {
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+    constants(out, pins, i2c, "PIN");
+    if !i2c.is_empty() {
+        let _ = writeln!(
+            out,
+            "/// I2C clock for `arduino_hal::I2c::new`.\npub const I2C_FREQUENCY_HZ: u32 = {};",
+            group_digits(NANO_I2C_FREQUENCY_HZ)
+        );
+    }
+    let unbuilt: Vec<&str> = config
+        .devices
+        .iter()
+        .filter(|device| device.kind.connection_type() != ConnectionType::I2c)
+        .map(|device| device.kind.label())
+        .collect();
+    if i2c.is_empty() {
+        return Ok(());
+    }
+    out.push_str(
+        "\n/// The I2C bus every I2C driver shares.\n\
+         pub type BoardI2c = AvrI2c<arduino_hal::I2c>;\n\
+         /// Handle to [`BoardI2c`] held by each driver.\n\
+         pub type BoardBus<'a> = SharedI2cBus<'a, BoardI2c>;\n\n",
+    );
+    if lcd {
+        out.push_str(
+            "/// `DelayNs` for the LCD1602 driver on top of `arduino_hal::delay_us`.\n\
+             pub struct BoardDelay;\n\n\
+             impl embedded_hal::delay::DelayNs for BoardDelay {\n    \
+             fn delay_ns(&mut self, ns: u32) {\n        \
+             arduino_hal::delay_us(ns.div_ceil(1_000));\n    }\n}\n\n",
+        );
+    }
+    out.push_str("/// Every I2C device on the wiring, ready to use.\n");
+    if !unbuilt.is_empty() {
+        let _ = writeln!(
+            out,
+            "///\n/// Not built here: {}. arduino-hal types each pin separately and\n\
+             /// platform-avr has no PWM adapter, so take the `pins.*` named by the\n\
+             /// `*_PIN` constants in the firmware.",
+            unbuilt.join(", ")
+        );
+    }
+    out.push_str("pub struct Board<'a> {\n");
+    struct_field_types(out, i2c, "BoardDelay");
+    out.push_str(
+        "}\n\n\
+         /// Share `i2c` (SDA = A4, SCL = A5 at [`I2C_FREQUENCY_HZ`]), build every\n\
+         /// I2C driver and hand them to `app`.\n\
+         pub fn run(i2c: arduino_hal::I2c, app: impl FnOnce(Board<'_>) -> !) -> ! {\n    \
+         let bus = RefCell::new(AvrI2c::new(i2c));\n",
+    );
+    let mut fields = Vec::new();
+    i2c_fields(out, i2c, "BoardDelay", &mut fields);
+    call_app(out, &fields);
+    Ok(())
+}
+
+/// `1000` → `1_000`, as rustfmt-clean literals.
+fn group_digits(value: u32) -> String {
+    let digits = value.to_string();
+    let mut out = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index) % 3 == 0 {
+            out.push('_');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wiring_config::SensorProfile;
+    use crate::wiring_project::WiringProject;
+
+    fn config(board: BoardProfile, devices: &[DeviceKind]) -> WiringConfig {
+        WiringConfig::from_board_with_selected_devices(board, SensorProfile::Full, devices)
+    }
+
+    #[test]
+    fn robot_base_firmware_matches_its_wiring_project() {
+        let project =
+            WiringProject::parse(include_str!("../../examples/wiring/esp32-robot-base.json"))
+                .unwrap();
+        let generated = board_setup(&project.to_config().unwrap()).unwrap();
+        assert!(
+            generated
+                == include_str!("../../firmware/original-esp32-robot-base/src/board_setup.rs"),
+            "firmware/original-esp32-robot-base/src/board_setup.rs is stale; regenerate it with\n\
+             cargo run -p platform-pc-sim --bin wiring-check -- \
+             --wiring examples/wiring/esp32-robot-base.json --export board-setup \
+             --out firmware/original-esp32-robot-base/src/board_setup.rs"
+        );
+    }
+
+    #[test]
+    fn esp32_setup_shares_the_i2c_bus_and_builds_gpio_devices() {
+        let cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::Bme280, DeviceKind::Bh1750, DeviceKind::HcSr04],
+        );
+        let code = board_setup(&cfg).unwrap();
+        for expected in [
+            "pub const I2C_SDA_GPIO: u8 = 21;",
+            "pub const HC_SR04_ECHO_GPIO: u8 = 18;",
+            "pub const BH1750_ADDRESS: u8 = 0x23;",
+            "use platform_esp32::bh1750::Bh1750Sensor;",
+            "use hal_api::error::SensorError;",
+            "    pub bh1750: Result<Bh1750Sensor<BoardBus<'a>>, SensorError>,",
+            "        .with_sda(peripherals.GPIO21)\n        .with_scl(peripherals.GPIO22);",
+            "    let bme280 = Bme280Sensor::new_with_address(BoardBus::new(&bus), BME280_ADDRESS);",
+            "    let hc_sr04_echo = Input::new(peripherals.GPIO18, InputConfig::default());",
+        ] {
+            assert!(code.contains(expected), "missing {expected:?} in\n{code}");
+        }
+        // Only what the wiring uses is imported.
+        assert!(!code.contains("ledc"), "{code}");
+        assert!(!code.contains("Delay"), "{code}");
+    }
+
+    #[test]
+    fn nano_setup_builds_i2c_drivers_and_names_other_pins() {
+        let cfg = config(
+            BoardProfile::ARDUINO_NANO,
+            &[DeviceKind::Lcd1602, DeviceKind::Servo],
+        );
+        let code = board_setup(&cfg).unwrap();
+        for expected in [
+            "pub const I2C_SDA_PIN: u8 = 18;",
+            "pub const SERVO_PWM_PIN: u8 = 9;",
+            "pub const I2C_FREQUENCY_HZ: u32 = 100_000;",
+            "pub type BoardI2c = AvrI2c<arduino_hal::I2c>;",
+            "impl embedded_hal::delay::DelayNs for BoardDelay {",
+            "    pub lcd1602: Lcd1602Display<BoardBus<'a>, BoardDelay>,",
+            "/// Not built here: Servo.",
+            "    app(Board { lcd1602 })",
+        ] {
+            assert!(code.contains(expected), "missing {expected:?} in\n{code}");
+        }
+    }
+
+    #[test]
+    fn nano_i2c_must_stay_on_a4_a5() {
+        let mut cfg = config(BoardProfile::ARDUINO_NANO, &[DeviceKind::Bme280]);
+        cfg.sda_pin = "D4".to_string();
+        assert!(matches!(
+            board_setup(&cfg),
+            Err(CodegenError::Unsupported(message)) if message.contains("SDA=D4")
+        ));
+    }
+
+    #[test]
+    fn conflicting_wiring_is_refused() {
+        let cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::Mpu6050, DeviceKind::Ds3231],
+        );
+        let Err(CodegenError::Wiring(messages)) = board_setup(&cfg) else {
+            panic!("address conflict should be refused");
+        };
+        assert!(messages[0].contains("0x68"), "{messages:?}");
+
+        let mut cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::Servo, DeviceKind::HcSr04],
+        );
+        cfg.servo_pin = cfg.trig_pin.clone();
+        assert!(matches!(board_setup(&cfg), Err(CodegenError::Wiring(_))));
+
+        assert!(matches!(
+            board_setup(&config(BoardProfile::ORIGINAL_ESP32, &[])),
+            Err(CodegenError::Unsupported(_))
+        ));
+    }
+}
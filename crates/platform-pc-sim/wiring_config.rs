@@ -217,7 +217,7 @@ impl DeviceKind {
         ]
     }
 
-    pub fn label(&self) -> &str {
+    pub fn label(&self) -> &'static str {
         match self {
             DeviceKind::Bme280 => "BME280",
             DeviceKind::Mpu6050 => "MPU6050",
@@ -242,14 +242,54 @@ impl DeviceKind {
         }
     }
 
+    /// Whether the board can host this device: its definition has default
+    /// pins for every signal the device needs (the camera boot pin, the
+    /// HC-SR04 trigger/echo pair, the servo PWM or all six L298N lines).
     pub fn supported_on(self, board: BoardProfile) -> bool {
-        !matches!(
-            (board, self),
-            (BoardProfile::ArduinoNano, DeviceKind::Esp32Cam)
-        )
+        let defaults = &board.def().defaults;
+        match self {
+            DeviceKind::Esp32Cam => defaults.cam.is_some(),
+            DeviceKind::HcSr04 => defaults.trig.is_some() && defaults.echo.is_some(),
+            DeviceKind::Servo => defaults.servo.is_some(),
+            DeviceKind::L298n => defaults.motor.is_some(),
+            _ => true,
+        }
     }
 
-    fn default_address(self) -> Option<u8> {
+    /// Device pins the wiring editor draws, each with the project pin
+    /// (`sda`, `power`, ...) that picks its board pin. Devices on the same bus
+    /// or rail share that project pin; the fixed L298N IN/ENB lines are left
+    /// out.
+    pub fn signals(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            DeviceKind::HcSr04 => &[
+                ("VCC", "power"),
+                ("GND", "ground"),
+                ("TRIG", "trig"),
+                ("ECHO", "echo"),
+            ],
+            DeviceKind::Servo => &[("VCC", "power"), ("GND", "ground"), ("PWM", "servo")],
+            DeviceKind::L298n => &[
+                ("GND", "ground"),
+                ("ENA", "motor"),
+                ("IN1", "in1"),
+                ("IN2", "in2"),
+                ("ENB", "enb"),
+                ("IN3", "in3"),
+                ("IN4", "in4"),
+            ],
+            DeviceKind::Esp32Cam => &[("VCC", "power"), ("GND", "ground"), ("IO0", "cam")],
+            _ => &[
+                ("VCC", "power"),
+                ("GND", "ground"),
+                ("SDA", "sda"),
+                ("SCL", "scl"),
+            ],
+        }
+    }
+
+    /// Address used when a project does not set one; `None` off the I2C bus.
+    pub fn default_address(self) -> Option<u8> {
         match self {
             DeviceKind::Bme280 => Some(0x77),
             DeviceKind::Mpu6050 => Some(0x68),
@@ -329,6 +369,12 @@ pub struct WiringConfig {
     pub servo_pin: String,
     /// L298N motor driver enable-A pin (ENA).
     pub motor_pin: String,
+    /// L298N direction and enable-B pins; the board definition supplies the defaults.
+    pub motor_in1_pin: String,
+    pub motor_in2_pin: String,
+    pub motor_enb_pin: String,
+    pub motor_in3_pin: String,
+    pub motor_in4_pin: String,
     pub devices: Vec<DeviceSpec>,
 }
 
@@ -342,7 +388,7 @@ impl WiringConfig {
     /// use platform_pc_sim::wiring_config::{SensorProfile, WiringConfig};
     ///
     /// let cfg = WiringConfig::from_board_with_sensors(
-    ///     BoardProfile::OriginalEsp32,
+    ///     BoardProfile::ORIGINAL_ESP32,
     ///     SensorProfile::Minimal,
     /// );
     /// assert_eq!(cfg.devices.len(), 2);
@@ -368,6 +414,11 @@ impl WiringConfig {
             cam_pin: board.cam_pin().to_string(),
             servo_pin: board.servo_pwm_pin().to_string(),
             motor_pin: board.motor_ena_pin().to_string(),
+            motor_in1_pin: board.motor_in1_pin().to_string(),
+            motor_in2_pin: board.motor_in2_pin().to_string(),
+            motor_enb_pin: board.motor_enb_pin().to_string(),
+            motor_in3_pin: board.motor_in3_pin().to_string(),
+            motor_in4_pin: board.motor_in4_pin().to_string(),
             devices: selected_devices
                 .into_iter()
                 .map(device_spec_from_kind)
@@ -382,6 +433,38 @@ impl WiringConfig {
         self
     }
 
+    /// Take pin assignments and per-device addresses/labels from `layout`
+    /// (typically loaded from a [`crate::wiring_project::WiringProject`]) while
+    /// keeping this config's device selection.
+    ///
+    /// Ignored when `layout` is for another board, since its pins would not
+    /// exist there.
+    pub fn with_layout(mut self, layout: &WiringConfig) -> Self {
+        if layout.board != self.board {
+            return self;
+        }
+        self.sda_pin = layout.sda_pin.clone();
+        self.scl_pin = layout.scl_pin.clone();
+        self.power_pin = layout.power_pin.clone();
+        self.ground_pin = layout.ground_pin.clone();
+        self.trig_pin = layout.trig_pin.clone();
+        self.echo_pin = layout.echo_pin.clone();
+        self.cam_pin = layout.cam_pin.clone();
+        self.servo_pin = layout.servo_pin.clone();
+        self.motor_pin = layout.motor_pin.clone();
+        self.motor_in1_pin = layout.motor_in1_pin.clone();
+        self.motor_in2_pin = layout.motor_in2_pin.clone();
+        self.motor_enb_pin = layout.motor_enb_pin.clone();
+        self.motor_in3_pin = layout.motor_in3_pin.clone();
+        self.motor_in4_pin = layout.motor_in4_pin.clone();
+        for device in &mut self.devices {
+            if let Some(spec) = layout.devices.iter().find(|spec| spec.kind == device.kind) {
+                *device = spec.clone();
+            }
+        }
+        self
+    }
+
     /// Build the standard wiring config for a board profile with all devices.
     ///
     /// Equivalent to `from_board_with_sensors(board, SensorProfile::Full)`.
@@ -394,16 +477,14 @@ impl WiringConfig {
     /// The simulator attaches DS3231 internally at `0x69` to avoid colliding
     /// with MPU6050 on the virtual bus, but dashboard-facing wiring/state
     /// surfaces translate it back to the logical hardware address `0x68`.
+    /// [`crate::wiring_rules::check`] reports the collision for real hardware.
     pub fn from_board(board: BoardProfile) -> Self {
         Self::from_board_with_sensors(board, SensorProfile::Full)
     }
 
     /// Serialise to a simple JSON string.
     pub fn to_json(&self) -> String {
-        let board_str = match self.board {
-            BoardProfile::OriginalEsp32 => "esp32",
-            BoardProfile::ArduinoNano => "nano",
-        };
+        let board_str = self.board.slug();
         let selected_devices: Vec<String> = self
             .devices
             .iter()
@@ -438,18 +519,41 @@ impl WiringConfig {
                 ),
             })
             .collect();
+        let onboard_devices: Vec<String> = self
+            .board
+            .onboard_devices()
+            .iter()
+            .map(|part| {
+                let address = part
+                    .address
+                    .map(|a| format!(r#""address":"0x{a:02X}","#))
+                    .unwrap_or_default();
+                let pins: Vec<String> = part
+                    .pins
+                    .iter()
+                    .map(|pin| format!(r#""{}""#, json_escape(pin)))
+                    .collect();
+                format!(
+                    r#"{{"name":"{}","function":"{}",{address}"pins":[{}]}}"#,
+                    json_escape(&part.name),
+                    json_escape(&part.function),
+                    pins.join(",")
+                )
+            })
+            .collect();
         format!(
             concat!(
-                r#"{{"board":"{board}","sensor_profile":"{sp}","#,
+                r#"{{"board":"{board}","mcu":"{mcu}","sensor_profile":"{sp}","#,
                 r#""show_bus_labels":{show_bus_labels},"#,
                 r#""selected_devices":[{selected}],"available_devices":[{available}],"#,
                 r#""sda_pin":"{sda}","scl_pin":"{scl}","#,
                 r#""power_pin":"{vcc}","ground_pin":"{gnd}","#,
                 r#""trig_pin":"{trig}","echo_pin":"{echo}","cam_pin":"{cam}","#,
                 r#""servo_pin":"{sv}","motor_pin":"{mot}","#,
-                r#""devices":[{devs}]}}"#
+                r#""devices":[{devs}],"onboard_devices":[{onboard}]}}"#
             ),
             board = board_str,
+            mcu = json_escape(self.board.mcu()),
             sp = self.sensor_profile.slug(),
             show_bus_labels = self.show_bus_labels,
             selected = selected_devices.join(","),
@@ -464,6 +568,7 @@ impl WiringConfig {
             sv = json_escape(&self.servo_pin),
             mot = json_escape(&self.motor_pin),
             devs = devices.join(","),
+            onboard = onboard_devices.join(","),
         )
     }
 }
@@ -522,7 +627,7 @@ mod tests {
 
     #[test]
     fn wiring_config_esp32_has_expected_pins() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         assert_eq!(cfg.sda_pin, "GPIO21");
         assert_eq!(cfg.scl_pin, "GPIO22");
         assert_eq!(cfg.power_pin, "3V3");
@@ -533,9 +638,33 @@ mod tests {
         assert_eq!(cfg.cam_pin, "GPIO0");
     }
 
+    #[test]
+    fn with_layout_keeps_selection_but_takes_pins_and_addresses() {
+        let mut layout = WiringConfig::from_board_with_sensors(
+            BoardProfile::ORIGINAL_ESP32,
+            SensorProfile::RobotBase,
+        );
+        layout.servo_pin = "GPIO4".to_string();
+        layout.devices[0] = DeviceSpec::i2c(DeviceKind::Mpu6050, 0x69);
+
+        let cfg = WiringConfig::from_board_with_selected_devices(
+            BoardProfile::ORIGINAL_ESP32,
+            SensorProfile::RobotBase,
+            &[DeviceKind::Bme280, DeviceKind::Mpu6050],
+        )
+        .with_layout(&layout);
+        assert_eq!(cfg.servo_pin, "GPIO4");
+        assert_eq!(cfg.devices.len(), 2);
+        assert_eq!(cfg.devices[0].address, Some(0x77));
+        assert_eq!(cfg.devices[1].label, "MPU6050 (0x69)");
+
+        let nano = WiringConfig::from_board(BoardProfile::ARDUINO_NANO).with_layout(&layout);
+        assert_eq!(nano.servo_pin, "D9");
+    }
+
     #[test]
     fn wiring_config_nano_has_expected_pins() {
-        let cfg = WiringConfig::from_board(BoardProfile::ArduinoNano);
+        let cfg = WiringConfig::from_board(BoardProfile::ARDUINO_NANO);
         assert_eq!(cfg.sda_pin, "A4");
         assert_eq!(cfg.scl_pin, "A5");
         assert_eq!(cfg.power_pin, "5V");
@@ -546,9 +675,62 @@ mod tests {
         assert_eq!(cfg.cam_pin, "N/A");
     }
 
+    #[test]
+    fn wiring_config_pico_and_m5stickc_have_expected_pins() {
+        let pico = WiringConfig::from_board(BoardProfile::RASPBERRY_PI_PICO);
+        assert_eq!(
+            (pico.sda_pin.as_str(), pico.scl_pin.as_str()),
+            ("GP4", "GP5")
+        );
+        assert_eq!(pico.servo_pin, "GP15");
+        assert_eq!(pico.motor_pin, "GP16");
+        assert_eq!(pico.cam_pin, "N/A");
+
+        let stick = WiringConfig::from_board(BoardProfile::M5STICKC);
+        assert_eq!(
+            (stick.sda_pin.as_str(), stick.scl_pin.as_str()),
+            ("GPIO32", "GPIO33")
+        );
+        assert_eq!(stick.trig_pin, "GPIO0");
+        assert_eq!(stick.echo_pin, "GPIO36");
+        assert_eq!(stick.servo_pin, "GPIO26");
+        assert_eq!(stick.motor_pin, "N/A");
+    }
+
+    #[test]
+    fn supported_devices_follow_board_io() {
+        for board in BoardProfile::all() {
+            assert!(DeviceKind::Bme280.supported_on(board), "{board:?}");
+        }
+        assert!(DeviceKind::Esp32Cam.supported_on(BoardProfile::ORIGINAL_ESP32));
+        assert!(!DeviceKind::Esp32Cam.supported_on(BoardProfile::RASPBERRY_PI_PICO));
+        assert!(DeviceKind::L298n.supported_on(BoardProfile::RASPBERRY_PI_PICO));
+        assert!(!DeviceKind::L298n.supported_on(BoardProfile::M5STICKC));
+
+        let stick =
+            WiringConfig::from_board_with_sensors(BoardProfile::M5STICKC, SensorProfile::RobotBase);
+        assert!(stick.devices.iter().all(|d| d.kind != DeviceKind::L298n));
+    }
+
+    #[test]
+    fn wiring_config_json_lists_onboard_devices() {
+        let json = WiringConfig::from_board(BoardProfile::M5STICKC).to_json();
+        assert!(
+            json.contains(r#""board":"m5stickc","mcu":"ESP32-PICO-D4""#),
+            "{json}"
+        );
+        assert!(json.contains(
+            r#"{"name":"MPU6886","function":"6-axis IMU","address":"0x68","pins":["GPIO21","GPIO22"]}"#
+        ));
+        assert!(!json.contains(r#""kind":"l298n""#));
+
+        let esp = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32).to_json();
+        assert!(esp.contains(r#""onboard_devices":[]"#));
+    }
+
     #[test]
     fn wiring_config_has_eleven_devices() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         assert_eq!(cfg.devices.len(), 12);
         // I2C devices: [0-6] and [11]
         assert_eq!(cfg.devices[0].kind, DeviceKind::Bme280);
@@ -570,7 +752,7 @@ mod tests {
 
     #[test]
     fn wiring_config_devices_have_correct_connection_types() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         let expected = [
             ConnectionType::I2c,  // [0] BME280
             ConnectionType::I2c,  // [1] MPU6050
@@ -596,7 +778,7 @@ mod tests {
 
     #[test]
     fn wiring_config_to_json_contains_board_and_devices() {
-        let json = WiringConfig::from_board(BoardProfile::OriginalEsp32).to_json();
+        let json = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32).to_json();
         assert!(json.contains(r#""board":"esp32""#));
         assert!(json.contains(r#""sensor_profile":"full""#));
         assert!(json.contains(r#""sda_pin":"GPIO21""#));
@@ -618,7 +800,7 @@ mod tests {
     #[test]
     fn sensor_profile_minimal_has_two_devices() {
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::Minimal,
         );
         assert_eq!(cfg.devices.len(), 2);
@@ -630,7 +812,7 @@ mod tests {
     #[test]
     fn sensor_profile_climate_station_has_five_devices() {
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::ClimateStation,
         );
         assert_eq!(cfg.devices.len(), 5);
@@ -645,7 +827,7 @@ mod tests {
     #[test]
     fn sensor_profile_robot_base_has_five_devices() {
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::RobotBase,
         );
         assert_eq!(cfg.devices.len(), 5);
@@ -659,9 +841,11 @@ mod tests {
 
     #[test]
     fn sensor_profile_full_is_default_and_has_eleven_devices() {
-        let full =
-            WiringConfig::from_board_with_sensors(BoardProfile::OriginalEsp32, SensorProfile::Full);
-        let default = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let full = WiringConfig::from_board_with_sensors(
+            BoardProfile::ORIGINAL_ESP32,
+            SensorProfile::Full,
+        );
+        let default = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         assert_eq!(full.devices.len(), 12);
         assert_eq!(default.devices.len(), 12);
         assert_eq!(full.sensor_profile, SensorProfile::Full);
@@ -670,7 +854,7 @@ mod tests {
 
     #[test]
     fn wiring_config_nano_full_omits_unsupported_camera_device() {
-        let cfg = WiringConfig::from_board(BoardProfile::ArduinoNano);
+        let cfg = WiringConfig::from_board(BoardProfile::ARDUINO_NANO);
         let kinds: Vec<_> = cfg.devices.iter().map(|d| d.kind).collect();
 
         assert_eq!(cfg.devices.len(), 11);
@@ -726,7 +910,7 @@ mod tests {
     #[test]
     fn wiring_config_from_selected_devices_allows_custom_selection() {
         let cfg = WiringConfig::from_board_with_selected_devices(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::Minimal,
             &[DeviceKind::Servo, DeviceKind::Bme280, DeviceKind::Servo],
         );
@@ -738,7 +922,7 @@ mod tests {
     #[test]
     fn wiring_config_uses_simulated_ds3231_address() {
         let cfg = WiringConfig::from_board_with_selected_devices(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::ClimateStation,
             &[DeviceKind::Ds3231],
         );
@@ -752,7 +936,7 @@ mod tests {
     #[test]
     fn wiring_config_json_includes_sensor_profile_slug() {
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::ClimateStation,
         );
         let json = cfg.to_json();
@@ -767,7 +951,7 @@ mod tests {
     #[test]
     fn wiring_config_json_includes_selected_and_available_devices() {
         let cfg = WiringConfig::from_board_with_selected_devices(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::Minimal,
             &[DeviceKind::Bme280, DeviceKind::Servo],
         );
@@ -780,10 +964,10 @@ mod tests {
 
     #[test]
     fn wiring_config_json_tracks_bus_label_visibility() {
-        let compact_json = WiringConfig::from_board(BoardProfile::OriginalEsp32).to_json();
+        let compact_json = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32).to_json();
         assert!(compact_json.contains(r#""show_bus_labels":false"#));
 
-        let detailed_json = WiringConfig::from_board(BoardProfile::OriginalEsp32)
+        let detailed_json = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32)
             .with_bus_labels(true)
             .to_json();
         assert!(detailed_json.contains(r#""show_bus_labels":true"#));
@@ -791,7 +975,7 @@ mod tests {
 
     #[test]
     fn wiring_config_json_omits_unsupported_camera_from_nano_dashboard_payload() {
-        let json = WiringConfig::from_board(BoardProfile::ArduinoNano).to_json();
+        let json = WiringConfig::from_board(BoardProfile::ARDUINO_NANO).to_json();
 
         assert!(!json.contains(r#""kind":"esp32_cam""#));
         assert!(!json.contains(r#""label":"ESP32-CAM""#));
@@ -800,11 +984,11 @@ mod tests {
     #[test]
     fn wiring_config_pins_are_board_specific_regardless_of_sensor_profile() {
         let esp = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::Minimal,
         );
         let nano = WiringConfig::from_board_with_sensors(
-            BoardProfile::ArduinoNano,
+            BoardProfile::ARDUINO_NANO,
             SensorProfile::Minimal,
         );
         assert_eq!(esp.sda_pin, "GPIO21");
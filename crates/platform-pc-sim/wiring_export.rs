@@ -0,0 +1,791 @@
+//! Export a [`WiringConfig`] to EDA tools and build documents.
+//!
+//! The simulated wiring is the source of truth for the physical build:
+//!
+//! - [`kicad_netlist`] — KiCad S-expression netlist (`.net`, version `E`)
+//!   for Pcbnew's *Import Netlist*. Modules get pin-header footprints
+//!   numbered in silkscreen order; board pins are named by their header
+//!   label (`GPIO21`, `A4`), so pick a board footprint whose pads use the
+//!   same names.
+//! - [`breadboard`] — parts and wire list in Fritzing's wire colours, to
+//!   follow when drawing the breadboard view or plugging the real wires.
+//! - [`bom_csv`] — bill of materials with part, address and quantity.
+//! - [`wiring_codegen::board_setup`] — the firmware's board-setup module,
+//!   the one format that can refuse a wiring.
+//!
+//! Devices are wired the way [`crate::wiring_rules::check`] suggests: VCC
+//! goes to the other rail when a module cannot run from `power_pin`, and
+//! 4.7 kΩ pull-ups are added when no module on the I2C bus has them.
+
+use std::fmt::Write as _;
+
+use crate::dashboard::BoardProfile;
+use crate::wiring_codegen::{self, CodegenError};
+use crate::wiring_config::{ConnectionType, DeviceKind, WiringConfig};
+use crate::wiring_rules::{self, BoardElectrical, DeviceElectrical};
+
+/// Reference designator of the MCU board.
+pub const BOARD_REF: &str = "U1";
+
+/// Output format of [`export`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// KiCad netlist (`.net`).
+    Kicad,
+    /// Breadboard parts and wire list (`.txt`).
+    Breadboard,
+    /// Bill of materials (`.csv`).
+    Bom,
+    /// Firmware board-setup module (`.rs`).
+    BoardSetup,
+}
+
+impl ExportFormat {
+    pub fn all() -> &'static [ExportFormat] {
+        &[
+            ExportFormat::Kicad,
+            ExportFormat::Breadboard,
+            ExportFormat::Bom,
+            ExportFormat::BoardSetup,
+        ]
+    }
+
+    pub fn slug(self) -> &'static str {
+        match self {
+            ExportFormat::Kicad => "kicad",
+            ExportFormat::Breadboard => "breadboard",
+            ExportFormat::Bom => "bom",
+            ExportFormat::BoardSetup => "board-setup",
+        }
+    }
+
+    pub fn from_slug(s: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|format| format.slug() == s)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Kicad => "net",
+            ExportFormat::Breadboard => "txt",
+            ExportFormat::Bom => "csv",
+            ExportFormat::BoardSetup => "rs",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Kicad | ExportFormat::Breadboard | ExportFormat::BoardSetup => {
+                "text/plain; charset=utf-8"
+            }
+            ExportFormat::Bom => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+/// Render `config` in `format`.
+pub fn export(config: &WiringConfig, format: ExportFormat) -> Result<String, CodegenError> {
+    match format {
+        ExportFormat::Kicad => Ok(kicad_netlist(config)),
+        ExportFormat::Breadboard => Ok(breadboard(config)),
+        ExportFormat::Bom => Ok(bom_csv(config)),
+        ExportFormat::BoardSetup => wiring_codegen::board_setup(config),
+    }
+}
+
+/// One part of the build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    pub reference: String,
+    /// Part name as sold, e.g. `GY-521`.
+    pub value: &'static str,
+    pub description: String,
+    /// KiCad footprint; empty for the board.
+    pub footprint: &'static str,
+    pub address: Option<u8>,
+    pub device: Option<DeviceKind>,
+}
+
+/// A component pin on a net.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub reference: String,
+    /// Pin number (1-based header position) or board pin label.
+    pub pin: String,
+    /// Signal name printed next to the pin, e.g. `SDA`.
+    pub function: &'static str,
+}
+
+/// What a net carries; decides the breadboard wire colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetRole {
+    Ground,
+    /// The rail named by `WiringConfig::power_pin`.
+    Supply,
+    /// The board's other rail.
+    AltSupply,
+    I2c,
+    Signal,
+}
+
+/// Board pin and everything wired to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Net {
+    pub name: String,
+    pub board_pin: String,
+    pub role: NetRole,
+    /// Board node first, then module pins in device order.
+    pub nodes: Vec<Node>,
+}
+
+/// Components and nets of a wiring.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Netlist {
+    pub components: Vec<Component>,
+    pub nets: Vec<Net>,
+}
+
+impl Netlist {
+    /// Add `node` to the net on `board_pin`, creating the net (with the
+    /// board's own node) on first use. A pin assigned twice yields one net.
+    fn connect(&mut self, board_pin: &str, name: String, role: NetRole, node: Node) {
+        let index = match self.nets.iter().position(|net| net.board_pin == board_pin) {
+            Some(index) => index,
+            None => {
+                self.nets.push(Net {
+                    name,
+                    board_pin: board_pin.to_string(),
+                    role,
+                    nodes: vec![Node {
+                        reference: BOARD_REF.to_string(),
+                        pin: board_pin.to_string(),
+                        function: "",
+                    }],
+                });
+                self.nets.len() - 1
+            }
+        };
+        self.nets[index].nodes.push(node);
+    }
+
+    fn component(&self, reference: &str) -> Option<&Component> {
+        self.components
+            .iter()
+            .find(|component| component.reference == reference)
+    }
+}
+
+/// Breakout module as bought: part name and header pinout.
+struct Module {
+    value: &'static str,
+    description: &'static str,
+    /// Header pins in silkscreen order.
+    pins: &'static [&'static str],
+    /// Header pin taking the supply.
+    supply: &'static str,
+}
+
+impl Module {
+    fn for_device(kind: DeviceKind) -> Self {
+        match kind {
+            DeviceKind::Bme280 => Module {
+                value: "GY-BME280",
+                description: "BME280 temperature/humidity/pressure breakout",
+                pins: &["VIN", "GND", "SCL", "SDA"],
+                supply: "VIN",
+            },
+            DeviceKind::Mpu6050 => Module {
+                value: "GY-521",
+                description: "MPU6050 accelerometer/gyroscope breakout",
+                pins: &["VCC", "GND", "SCL", "SDA", "XDA", "XCL", "AD0", "INT"],
+                supply: "VCC",
+            },
+            DeviceKind::Lcd1602 => Module {
+                value: "LCD1602 I2C",
+                description: "16x2 character LCD with PCF8574 backpack",
+                pins: &["GND", "VCC", "SDA", "SCL"],
+                supply: "VCC",
+            },
+            DeviceKind::HcSr04 => Module {
+                value: "HC-SR04",
+                description: "ultrasonic distance sensor",
+                pins: &["VCC", "TRIG", "ECHO", "GND"],
+                supply: "VCC",
+            },
+            DeviceKind::Bh1750 => Module {
+                value: "GY-302",
+                description: "BH1750 ambient light sensor breakout",
+                pins: &["VCC", "GND", "SCL", "SDA", "ADDR"],
+                supply: "VCC",
+            },
+            DeviceKind::Servo => Module {
+                value: "SG90",
+                description: "micro servo (brown GND, red V+, orange PWM)",
+                pins: &["GND", "V+", "PWM"],
+                supply: "V+",
+            },
+            DeviceKind::L298n => Module {
+                value: "L298N module",
+                description: "dual H-bridge motor driver, logic header",
+                pins: &[
+                    "ENA", "IN1", "IN2", "IN3", "IN4", "ENB", "+12V", "GND", "+5V",
+                ],
+                supply: "+5V",
+            },
+            DeviceKind::Esp32Cam => Module {
+                value: "ESP32-CAM",
+                description: "ESP32 camera module with OV2640",
+                pins: &[
+                    "5V", "GND", "IO12", "IO13", "IO15", "IO14", "IO2", "IO4", "3V3", "IO16",
+                    "IO0", "GND", "VCC", "U0R", "U0T", "GND",
+                ],
+                supply: "5V",
+            },
+            DeviceKind::Ds3231 => Module {
+                value: "ZS-042",
+                description: "DS3231 real-time clock with AT24C32 EEPROM",
+                pins: &["32K", "SQW", "SCL", "SDA", "VCC", "GND"],
+                supply: "VCC",
+            },
+            DeviceKind::Sgp30 => Module {
+                value: "SGP30 breakout",
+                description: "SGP30 eCO2/TVOC gas sensor",
+                pins: &["VIN", "GND", "SCL", "SDA"],
+                supply: "VIN",
+            },
+            DeviceKind::Vl53l0x => Module {
+                value: "GY-VL53L0XV2",
+                description: "VL53L0X time-of-flight distance sensor",
+                pins: &["VIN", "GND", "SCL", "SDA", "GPIO1", "XSHUT"],
+                supply: "VIN",
+            },
+            DeviceKind::Ssd1306 => Module {
+                value: "SSD1306 OLED",
+                description: "0.96 inch 128x64 I2C OLED display",
+                pins: &["GND", "VCC", "SCL", "SDA"],
+                supply: "VCC",
+            },
+        }
+    }
+
+    fn footprint(&self) -> &'static str {
+        match self.pins.len() {
+            3 => "Connector_PinHeader_2.54mm:PinHeader_1x03_P2.54mm_Vertical",
+            4 => "Connector_PinHeader_2.54mm:PinHeader_1x04_P2.54mm_Vertical",
+            5 => "Connector_PinHeader_2.54mm:PinHeader_1x05_P2.54mm_Vertical",
+            6 => "Connector_PinHeader_2.54mm:PinHeader_1x06_P2.54mm_Vertical",
+            8 => "Connector_PinHeader_2.54mm:PinHeader_1x08_P2.54mm_Vertical",
+            9 => "Connector_PinHeader_2.54mm:PinHeader_1x09_P2.54mm_Vertical",
+            _ => "Connector_PinHeader_2.54mm:PinHeader_2x08_P2.54mm_Vertical",
+        }
+    }
+
+    /// Node for the first header pin called `function`.
+    fn node(&self, reference: &str, function: &'static str) -> Node {
+        let position = self
+            .pins
+            .iter()
+            .position(|&pin| pin == function)
+            .expect("module pinout lists every wired signal");
+        Node {
+            reference: reference.to_string(),
+            pin: (position + 1).to_string(),
+            function,
+        }
+    }
+}
+
+const PULLUP_VALUE: &str = "4.7k";
+const PULLUP_FOOTPRINT: &str = "Resistor_THT:R_Axial_DIN0207_L6.3mm_D2.5mm_P7.62mm_Horizontal";
+
+fn board_value(board: BoardProfile) -> &'static str {
+    let def = board.def();
+    def.part.as_deref().unwrap_or(&def.name)
+}
+
+/// True when the I2C bus has devices but none of them brings pull-ups.
+fn needs_pullups(config: &WiringConfig) -> bool {
+    let mut i2c = config
+        .devices
+        .iter()
+        .filter(|device| device.kind.connection_type() == ConnectionType::I2c)
+        .peekable();
+    i2c.peek().is_some()
+        && i2c.all(|device| {
+            DeviceElectrical::for_device(device.kind)
+                .pullup_ohms
+                .is_none()
+        })
+}
+
+/// Build the components and nets of `config`.
+pub fn netlist(config: &WiringConfig) -> Netlist {
+    let electrical = BoardElectrical::for_board(config.board);
+    let uses = wiring_rules::pin_uses(config);
+    let mut list = Netlist {
+        components: vec![Component {
+            reference: BOARD_REF.to_string(),
+            value: board_value(config.board),
+            description: format!("{} development board", config.board.name()),
+            footprint: "",
+            address: None,
+            device: None,
+        }],
+        nets: Vec::new(),
+    };
+    for (index, device) in config.devices.iter().enumerate() {
+        let reference = format!("M{}", index + 1);
+        let module = Module::for_device(device.kind);
+        list.components.push(Component {
+            reference: reference.clone(),
+            value: module.value,
+            description: module.description.to_string(),
+            footprint: module.footprint(),
+            address: device.address,
+            device: Some(device.kind),
+        });
+        list.connect(
+            &config.ground_pin,
+            "GND".to_string(),
+            NetRole::Ground,
+            module.node(&reference, "GND"),
+        );
+        let rail = electrical.rail_for(device.kind);
+        let (rail_pin, role) = if rail == electrical.main_rail {
+            (config.power_pin.as_str(), NetRole::Supply)
+        } else {
+            (rail.pin(), NetRole::AltSupply)
+        };
+        list.connect(
+            rail_pin,
+            rail_pin.to_string(),
+            role,
+            module.node(&reference, module.supply),
+        );
+        let is_i2c = device.kind.connection_type() == ConnectionType::I2c;
+        for pin_use in &uses {
+            match pin_use.device {
+                None if is_i2c => list.connect(
+                    &pin_use.pin,
+                    pin_use.signal.to_string(),
+                    NetRole::I2c,
+                    module.node(&reference, pin_use.signal),
+                ),
+                Some(kind) if kind == device.kind => list.connect(
+                    &pin_use.pin,
+                    format!("{}_{}", kind.label(), pin_use.signal),
+                    NetRole::Signal,
+                    module.node(&reference, pin_use.signal),
+                ),
+                _ => {}
+            }
+        }
+    }
+    if needs_pullups(config) {
+        for (index, pin) in [&config.sda_pin, &config.scl_pin].into_iter().enumerate() {
+            let reference = format!("R{}", index + 1);
+            list.components.push(Component {
+                reference: reference.clone(),
+                value: PULLUP_VALUE,
+                description: format!("I2C pull-up to {}", config.power_pin),
+                footprint: PULLUP_FOOTPRINT,
+                address: None,
+                device: None,
+            });
+            for (lead, board_pin, role) in [
+                ("1", pin.as_str(), NetRole::I2c),
+                ("2", config.power_pin.as_str(), NetRole::Supply),
+            ] {
+                list.connect(
+                    board_pin,
+                    board_pin.to_string(),
+                    role,
+                    Node {
+                        reference: reference.clone(),
+                        pin: lead.to_string(),
+                        function: "",
+                    },
+                );
+            }
+        }
+    }
+    list
+}
+
+/// KiCad S-expression netlist of `config`.
+pub fn kicad_netlist(config: &WiringConfig) -> String {
+    let list = netlist(config);
+    let mut out = String::with_capacity(4096);
+    out.push_str("(export (version \"E\")\n");
+    let _ = writeln!(
+        out,
+        "  (design\n    (source {})\n    (tool \"mcu-hal-sim wiring_export\"))",
+        quote(&format!(
+            "{} / {}",
+            config.board.name(),
+            config.sensor_profile.slug()
+        ))
+    );
+    out.push_str("  (components");
+    for component in &list.components {
+        let _ = write!(
+            out,
+            "\n    (comp (ref {})\n      (value {})\n      (footprint {})\n      (libsource (lib \"mcu-hal-sim\") (part {}) (description {}))",
+            quote(&component.reference),
+            quote(component.value),
+            quote(component.footprint),
+            quote(component.value),
+            quote(&component.description)
+        );
+        if let Some(address) = component.address {
+            let _ = write!(
+                out,
+                "\n      (property (name \"I2C address\") (value \"0x{address:02X}\"))"
+            );
+        }
+        out.push(')');
+    }
+    out.push_str(")\n  (nets");
+    for (code, net) in list.nets.iter().enumerate() {
+        let _ = write!(
+            out,
+            "\n    (net (code \"{}\") (name {})",
+            code + 1,
+            quote(&net.name)
+        );
+        for node in &net.nodes {
+            let _ = write!(
+                out,
+                "\n      (node (ref {}) (pin {})",
+                quote(&node.reference),
+                quote(&node.pin)
+            );
+            if !node.function.is_empty() {
+                let _ = write!(out, " (pinfunction {})", quote(node.function));
+            }
+            out.push(')');
+        }
+        out.push(')');
+    }
+    out.push_str("))\n");
+    out
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn wire_colour(net: &Net) -> &'static str {
+    match net.role {
+        NetRole::Ground => "black",
+        NetRole::Supply => "red",
+        NetRole::AltSupply => "orange",
+        NetRole::I2c if net.name == "SCL" => "yellow",
+        NetRole::I2c => "blue",
+        NetRole::Signal => "green",
+    }
+}
+
+/// Parts and wire list for building `config` on a breadboard.
+pub fn breadboard(config: &WiringConfig) -> String {
+    let list = netlist(config);
+    let mut out = String::with_capacity(2048);
+    let _ = writeln!(
+        out,
+        "Breadboard wiring: {} / {} profile\n\nParts:",
+        config.board.name(),
+        config.sensor_profile.slug()
+    );
+    for component in &list.components {
+        let _ = write!(
+            out,
+            "  {:<4}{} - {}",
+            component.reference, component.value, component.description
+        );
+        if let Some(address) = component.address {
+            let _ = write!(out, " @ 0x{address:02X}");
+        }
+        out.push('\n');
+    }
+    let width = list
+        .nets
+        .iter()
+        .map(|net| net.board_pin.len())
+        .max()
+        .unwrap_or(0);
+    out.push_str("\nWires (board pin -> part pin):\n");
+    for net in &list.nets {
+        for node in net.nodes.iter().skip(1) {
+            let value = list
+                .component(&node.reference)
+                .map_or("", |component| component.value);
+            let pin = if node.function.is_empty() {
+                format!("lead {}", node.pin)
+            } else {
+                node.function.to_string()
+            };
+            let _ = writeln!(
+                out,
+                "  {:<7}{:<width$} -> {} {value} {pin}",
+                wire_colour(net),
+                net.board_pin,
+                node.reference
+            );
+        }
+    }
+    out
+}
+
+/// Bill of materials of `config` as CSV.
+///
+/// Identical parts at the same address share a row; the jumper-wire count is
+/// one wire per part pin in [`breadboard`].
+pub fn bom_csv(config: &WiringConfig) -> String {
+    let list = netlist(config);
+    // (first component, references) in netlist order.
+    let mut rows: Vec<(&Component, Vec<&str>)> = Vec::new();
+    for component in &list.components {
+        match rows
+            .iter_mut()
+            .find(|(first, _)| first.value == component.value && first.address == component.address)
+        {
+            Some((_, references)) => references.push(&component.reference),
+            None => rows.push((component, vec![&component.reference])),
+        }
+    }
+    let wires: usize = list.nets.iter().map(|net| net.nodes.len() - 1).sum();
+    let mut out = String::from("part,description,address,quantity,references\n");
+    for (component, references) in &rows {
+        let address = component
+            .address
+            .map(|a| format!("0x{a:02X}"))
+            .unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "{},{},{address},{},{}",
+            csv_field(component.value),
+            csv_field(&component.description),
+            references.len(),
+            csv_field(&references.join(" "))
+        );
+    }
+    let _ = writeln!(out, "Dupont jumper wire,20 cm,,{wires},");
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wiring_config::SensorProfile;
+
+    fn config(board: BoardProfile, devices: &[DeviceKind]) -> WiringConfig {
+        WiringConfig::from_board_with_selected_devices(board, SensorProfile::Full, devices)
+    }
+
+    fn net<'a>(list: &'a Netlist, name: &str) -> &'a Net {
+        list.nets
+            .iter()
+            .find(|net| net.name == name)
+            .unwrap_or_else(|| panic!("no net {name} in {:?}", list.nets))
+    }
+
+    fn pins(net: &Net) -> Vec<(&str, &str)> {
+        net.nodes
+            .iter()
+            .map(|node| (node.reference.as_str(), node.pin.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn netlist_connects_i2c_bus_supply_and_ground() {
+        let cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::Bme280, DeviceKind::Mpu6050],
+        );
+        let list = netlist(&cfg);
+        let refs: Vec<(&str, &str)> = list
+            .components
+            .iter()
+            .map(|c| (c.reference.as_str(), c.value))
+            .collect();
+        assert_eq!(
+            refs,
+            [
+                ("U1", "ESP32-DevKitC"),
+                ("M1", "GY-BME280"),
+                ("M2", "GY-521")
+            ]
+        );
+        assert_eq!(
+            pins(net(&list, "GND")),
+            [("U1", "GND"), ("M1", "2"), ("M2", "2")]
+        );
+        assert_eq!(
+            pins(net(&list, "3V3")),
+            [("U1", "3V3"), ("M1", "1"), ("M2", "1")]
+        );
+        let sda = net(&list, "SDA");
+        assert_eq!(sda.board_pin, "GPIO21");
+        assert_eq!(pins(sda), [("U1", "GPIO21"), ("M1", "4"), ("M2", "4")]);
+        assert_eq!(
+            pins(net(&list, "SCL")),
+            [("U1", "GPIO22"), ("M1", "3"), ("M2", "3")]
+        );
+    }
+
+    #[test]
+    fn five_volt_modules_on_esp32_are_wired_to_vin() {
+        let cfg = config(BoardProfile::ORIGINAL_ESP32, &[DeviceKind::HcSr04]);
+        let list = netlist(&cfg);
+        let vin = net(&list, "VIN");
+        assert_eq!(vin.role, NetRole::AltSupply);
+        assert_eq!(pins(vin), [("U1", "VIN"), ("M1", "1")]);
+        assert_eq!(
+            pins(net(&list, "HC-SR04_TRIG")),
+            [("U1", cfg.trig_pin.as_str()), ("M1", "2")]
+        );
+        assert!(list.nets.iter().all(|net| net.name != "3V3"));
+    }
+
+    #[test]
+    fn pullups_are_added_only_when_no_module_has_them() {
+        let bare = config(BoardProfile::ARDUINO_NANO, &[DeviceKind::Ssd1306]);
+        let list = netlist(&bare);
+        let resistors: Vec<&str> = list
+            .components
+            .iter()
+            .filter(|c| c.value == PULLUP_VALUE)
+            .map(|c| c.reference.as_str())
+            .collect();
+        assert_eq!(resistors, ["R1", "R2"]);
+        assert!(pins(net(&list, "SDA")).contains(&("R1", "1")));
+        assert!(pins(net(&list, "5V")).contains(&("R1", "2")));
+        assert!(pins(net(&list, "SCL")).contains(&("R2", "1")));
+
+        let pulled = config(
+            BoardProfile::ARDUINO_NANO,
+            &[DeviceKind::Ssd1306, DeviceKind::Bme280],
+        );
+        assert!(netlist(&pulled)
+            .components
+            .iter()
+            .all(|c| c.value != PULLUP_VALUE));
+    }
+
+    #[test]
+    fn every_device_pinout_covers_its_wiring() {
+        for &board in &[BoardProfile::ORIGINAL_ESP32, BoardProfile::ARDUINO_NANO] {
+            let cfg = WiringConfig::from_board_with_sensors(board, SensorProfile::Full);
+            let list = netlist(&cfg);
+            for component in list.components.iter().filter(|c| c.device.is_some()) {
+                let wired = list
+                    .nets
+                    .iter()
+                    .flat_map(|net| &net.nodes)
+                    .filter(|node| node.reference == component.reference)
+                    .count();
+                assert!(wired >= 2, "{} has {wired} wires", component.value);
+            }
+        }
+        let cfg = config(BoardProfile::ORIGINAL_ESP32, &[DeviceKind::L298n]);
+        let motor = netlist(&cfg);
+        assert_eq!(motor.nets.len(), 8, "{:?}", motor.nets);
+    }
+
+    #[test]
+    fn kicad_netlist_is_balanced_and_quotes_values() {
+        let cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::Bme280, DeviceKind::Servo],
+        );
+        let text = kicad_netlist(&cfg);
+        assert!(text.starts_with("(export (version \"E\")"), "{text}");
+        assert!(
+            text.contains("(comp (ref \"M1\")\n      (value \"GY-BME280\")"),
+            "{text}"
+        );
+        assert!(
+            text.contains("(property (name \"I2C address\") (value \"0x77\"))"),
+            "{text}"
+        );
+        assert!(
+            text.contains("(node (ref \"M2\") (pin \"3\") (pinfunction \"PWM\"))"),
+            "{text}"
+        );
+        let depth = text.chars().try_fold(0i32, |depth, c| {
+            let depth = depth + (c == '(') as i32 - (c == ')') as i32;
+            (depth >= 0).then_some(depth)
+        });
+        assert_eq!(depth, Some(0));
+        assert_eq!(quote(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+    }
+
+    #[test]
+    fn breadboard_lists_parts_and_coloured_wires() {
+        let cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::Bme280, DeviceKind::HcSr04],
+        );
+        let text = breadboard(&cfg);
+        assert!(
+            text.contains("  M1  GY-BME280 - BME280 temperature/humidity/pressure breakout @ 0x77"),
+            "{text}"
+        );
+        assert!(
+            text.contains("  black  GND    -> M1 GY-BME280 GND"),
+            "{text}"
+        );
+        assert!(
+            text.contains("  red    3V3    -> M1 GY-BME280 VIN"),
+            "{text}"
+        );
+        assert!(
+            text.contains("  blue   GPIO21 -> M1 GY-BME280 SDA"),
+            "{text}"
+        );
+        assert!(
+            text.contains("  yellow GPIO22 -> M1 GY-BME280 SCL"),
+            "{text}"
+        );
+        assert!(text.contains("  orange VIN    -> M2 HC-SR04 VCC"), "{text}");
+    }
+
+    #[test]
+    fn bom_groups_parts_and_counts_wires() {
+        let cfg = config(
+            BoardProfile::ARDUINO_NANO,
+            &[DeviceKind::Ssd1306, DeviceKind::Servo],
+        );
+        let csv = bom_csv(&cfg);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "part,description,address,quantity,references",
+                "Arduino Nano,Arduino Nano development board,,1,U1",
+                "SG90,\"micro servo (brown GND, red V+, orange PWM)\",,1,M1",
+                "SSD1306 OLED,0.96 inch 128x64 I2C OLED display,0x3C,1,M2",
+                "4.7k,I2C pull-up to 5V,,2,R1 R2",
+                "Dupont jumper wire,20 cm,,11,",
+            ]
+        );
+    }
+
+    #[test]
+    fn export_format_slugs_round_trip() {
+        for &format in ExportFormat::all() {
+            assert_eq!(ExportFormat::from_slug(format.slug()), Some(format));
+        }
+        assert_eq!(ExportFormat::from_slug("fritzing"), None);
+    }
+}
@@ -0,0 +1,493 @@
+//! Versioned wiring project files.
+//!
+//! A project file is the checked-in hardware description that
+//! `device-dashboard-web`, the terminal sims and `wiring-check` start from:
+//!
+//! ```json
+//! {
+//!   "format": "mcu-hal-sim-wiring",
+//!   "version": 1,
+//!   "name": "robot bench",
+//!   "board": "esp32",
+//!   "sensor_profile": "robot",
+//!   "show_bus_labels": false,
+//!   "pins": { "sda": "GPIO21", "scl": "GPIO22", "servo": "GPIO13" },
+//!   "devices": [
+//!     { "kind": "mpu6050", "address": "0x69", "label": "IMU (AD0 high)" },
+//!     { "kind": "servo" }
+//!   ]
+//! }
+//! ```
+//!
+//! Omitted pins use the board defaults, omitted addresses the device default
+//! and omitted labels are derived from the device and address. `editor` keeps
+//! the free-form wiring editor layout so it travels with the project.
+//!
+//! `version` is checked before the rest of the file is read, so a file written
+//! by a newer release fails with [`ProjectError::UnsupportedVersion`] instead of
+//! an unknown-field error.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dashboard::BoardProfile;
+use crate::wiring_config::{ConnectionType, DeviceKind, DeviceSpec, SensorProfile, WiringConfig};
+
+/// Value of the `format` field.
+pub const PROJECT_FORMAT: &str = "mcu-hal-sim-wiring";
+/// Newest `version` this build reads and the one it writes.
+pub const PROJECT_VERSION: u32 = 1;
+
+/// Error from loading or converting a project file.
+#[derive(Debug)]
+pub enum ProjectError {
+    Io(io::Error),
+    /// Not JSON, or a field has the wrong type.
+    Json(String),
+    /// `format` is missing or names another file type.
+    NotAProject,
+    /// Written by a newer release.
+    UnsupportedVersion(u32),
+    /// Well-formed but describes wiring this build cannot represent.
+    Invalid(String),
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectError::Io(e) => write!(f, "{e}"),
+            ProjectError::Json(e) => write!(f, "invalid project JSON: {e}"),
+            ProjectError::NotAProject => {
+                write!(
+                    f,
+                    "not a wiring project (expected \"format\": \"{PROJECT_FORMAT}\")"
+                )
+            }
+            ProjectError::UnsupportedVersion(v) => write!(
+                f,
+                "project version {v} is newer than the supported version {PROJECT_VERSION}"
+            ),
+            ProjectError::Invalid(e) => write!(f, "invalid project: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ProjectError {}
+
+impl From<io::Error> for ProjectError {
+    fn from(e: io::Error) -> Self {
+        ProjectError::Io(e)
+    }
+}
+
+/// Board pin assignments. `None` keeps the board default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectPins {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sda: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scl: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ground: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trig: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub echo: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cam: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub servo: Option<String>,
+    /// L298N ENA; `ena` is accepted as well.
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "ena")]
+    pub motor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in2: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enb: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in3: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in4: Option<String>,
+}
+
+/// One attached device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectDevice {
+    /// [`DeviceKind::slug`].
+    pub kind: String,
+    /// I2C address as `"0x68"` (or decimal).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// A wiring project file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WiringProject {
+    pub format: String,
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// `esp32`, `nano`, `pico` or `m5stickc` (`original-esp32` /
+    /// `arduino-nano` / `raspi-pico` are accepted).
+    pub board: String,
+    #[serde(default = "default_sensor_profile")]
+    pub sensor_profile: String,
+    #[serde(default)]
+    pub show_bus_labels: bool,
+    #[serde(default)]
+    pub pins: ProjectPins,
+    pub devices: Vec<ProjectDevice>,
+    /// Wiring editor layout (`{"nodes":[...],"edges":[...]}`), kept verbatim.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub editor: Option<serde_json::Value>,
+}
+
+fn default_sensor_profile() -> String {
+    SensorProfile::Full.slug().to_string()
+}
+
+impl WiringProject {
+    /// Describe `config` with every pin, address and label spelled out.
+    pub fn from_config(config: &WiringConfig) -> Self {
+        Self {
+            format: PROJECT_FORMAT.to_string(),
+            version: PROJECT_VERSION,
+            name: None,
+            board: config.board.slug().to_string(),
+            sensor_profile: config.sensor_profile.slug().to_string(),
+            show_bus_labels: config.show_bus_labels,
+            pins: ProjectPins {
+                sda: Some(config.sda_pin.clone()),
+                scl: Some(config.scl_pin.clone()),
+                power: Some(config.power_pin.clone()),
+                ground: Some(config.ground_pin.clone()),
+                trig: Some(config.trig_pin.clone()),
+                echo: Some(config.echo_pin.clone()),
+                cam: Some(config.cam_pin.clone()),
+                servo: Some(config.servo_pin.clone()),
+                motor: Some(config.motor_pin.clone()),
+                in1: Some(config.motor_in1_pin.clone()),
+                in2: Some(config.motor_in2_pin.clone()),
+                enb: Some(config.motor_enb_pin.clone()),
+                in3: Some(config.motor_in3_pin.clone()),
+                in4: Some(config.motor_in4_pin.clone()),
+            },
+            devices: config
+                .devices
+                .iter()
+                .map(|device| ProjectDevice {
+                    kind: device.kind.slug().to_string(),
+                    address: device.address.map(|address| format!("0x{address:02X}")),
+                    label: Some(device.label.clone()),
+                })
+                .collect(),
+            editor: None,
+        }
+    }
+
+    /// Parse a project file, checking `format` and `version` first.
+    pub fn parse(text: &str) -> Result<Self, ProjectError> {
+        let value: serde_json::Value =
+            serde_json::from_str(text).map_err(|e| ProjectError::Json(e.to_string()))?;
+        if value.get("format").and_then(|v| v.as_str()) != Some(PROJECT_FORMAT) {
+            return Err(ProjectError::NotAProject);
+        }
+        match value.get("version").and_then(|v| v.as_u64()) {
+            Some(v) if v > u64::from(PROJECT_VERSION) => {
+                return Err(ProjectError::UnsupportedVersion(
+                    u32::try_from(v).unwrap_or(u32::MAX),
+                ))
+            }
+            Some(0) | None => return Err(ProjectError::Json("missing version".to_string())),
+            Some(_) => {}
+        }
+        serde_json::from_value(value).map_err(|e| ProjectError::Json(e.to_string()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ProjectError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Pretty-printed JSON with a trailing newline, ready to check in.
+    pub fn to_json_pretty(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".to_string());
+        json.push('\n');
+        json
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ProjectError> {
+        fs::write(path, self.to_json_pretty())?;
+        Ok(())
+    }
+
+    /// Build the [`WiringConfig`] this project describes.
+    pub fn to_config(&self) -> Result<WiringConfig, ProjectError> {
+        let board = BoardProfile::from_slug(&self.board)
+            .ok_or_else(|| invalid(format!("unknown board {:?}", self.board)))?;
+        let sensor_profile = SensorProfile::from_slug(&self.sensor_profile)
+            .ok_or_else(|| invalid(format!("unknown sensor_profile {:?}", self.sensor_profile)))?;
+
+        let mut kinds = Vec::with_capacity(self.devices.len());
+        for device in &self.devices {
+            let kind = DeviceKind::from_slug(&device.kind)
+                .ok_or_else(|| invalid(format!("unknown device {:?}", device.kind)))?;
+            if kinds.contains(&kind) {
+                return Err(invalid(format!("{} is listed twice", device.kind)));
+            }
+            if !kind.supported_on(board) {
+                return Err(invalid(format!(
+                    "{} is not supported on {}",
+                    kind.label(),
+                    board.name()
+                )));
+            }
+            kinds.push(kind);
+        }
+        let mut config =
+            WiringConfig::from_board_with_selected_devices(board, sensor_profile, &kinds)
+                .with_bus_labels(self.show_bus_labels);
+        let pins = &self.pins;
+        for (pin, field) in [
+            (&pins.sda, &mut config.sda_pin),
+            (&pins.scl, &mut config.scl_pin),
+            (&pins.power, &mut config.power_pin),
+            (&pins.ground, &mut config.ground_pin),
+            (&pins.trig, &mut config.trig_pin),
+            (&pins.echo, &mut config.echo_pin),
+            (&pins.cam, &mut config.cam_pin),
+            (&pins.servo, &mut config.servo_pin),
+            (&pins.motor, &mut config.motor_pin),
+            (&pins.in1, &mut config.motor_in1_pin),
+            (&pins.in2, &mut config.motor_in2_pin),
+            (&pins.enb, &mut config.motor_enb_pin),
+            (&pins.in3, &mut config.motor_in3_pin),
+            (&pins.in4, &mut config.motor_in4_pin),
+        ] {
+            if let Some(pin) = pin {
+                *field = pin.clone();
+            }
+        }
+
+        for device in &self.devices {
+            let kind = DeviceKind::from_slug(&device.kind).expect("validated above");
+            let spec = config
+                .devices
+                .iter_mut()
+                .find(|spec| spec.kind == kind)
+                .expect("selected devices are all in the config");
+            if let Some(address) = &device.address {
+                if kind.connection_type() != ConnectionType::I2c {
+                    return Err(invalid(format!(
+                        "{} is not an I2C device and has no address",
+                        kind.label()
+                    )));
+                }
+                let address = parse_address(address)
+                    .ok_or_else(|| invalid(format!("bad I2C address {address:?}")))?;
+                *spec = DeviceSpec::i2c(kind, address);
+            }
+            if let Some(label) = &device.label {
+                spec.label = label.clone();
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn invalid(message: String) -> ProjectError {
+    ProjectError::Invalid(message)
+}
+
+/// 7-bit I2C address, excluding the reserved `0x00-0x07` and `0x78-0x7F`.
+fn parse_address(text: &str) -> Option<u8> {
+    let address = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16).ok()?,
+        None => text.parse().ok()?,
+    };
+    (0x08..=0x77).contains(&address).then_some(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_config_through_project_file() {
+        let mut config = WiringConfig::from_board_with_sensors(
+            BoardProfile::ORIGINAL_ESP32,
+            SensorProfile::RobotBase,
+        )
+        .with_bus_labels(true);
+        config.servo_pin = "GPIO4".to_string();
+        config.devices[0] = DeviceSpec::i2c(DeviceKind::Mpu6050, 0x69);
+        config.devices[0].label = "IMU".to_string();
+
+        let mut project = WiringProject::from_config(&config);
+        project.name = Some("robot bench".to_string());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wiring.json");
+        project.save(&path).unwrap();
+        let loaded = WiringProject::load(&path).unwrap();
+        assert_eq!(loaded, project);
+        assert_eq!(loaded.to_config().unwrap(), config);
+    }
+
+    #[test]
+    fn every_board_round_trips_by_slug() {
+        for board in BoardProfile::all() {
+            let config =
+                WiringConfig::from_board_with_sensors(board, SensorProfile::ClimateStation);
+            let text = WiringProject::from_config(&config).to_json_pretty();
+            assert!(
+                text.contains(&format!(r#""board": "{}""#, board.slug())),
+                "{text}"
+            );
+            let loaded = WiringProject::parse(&text).unwrap().to_config().unwrap();
+            assert_eq!(loaded, config);
+        }
+        assert_eq!(
+            BoardProfile::from_slug("raspi-pico"),
+            Some(BoardProfile::RASPBERRY_PI_PICO)
+        );
+    }
+
+    #[test]
+    fn minimal_project_uses_board_defaults() {
+        let project = WiringProject::parse(
+            r#"{"format":"mcu-hal-sim-wiring","version":1,"board":"nano",
+                "devices":[{"kind":"bme280","address":"0x76"},{"kind":"servo"}]}"#,
+        )
+        .unwrap();
+        let config = project.to_config().unwrap();
+        assert_eq!(config.board, BoardProfile::ARDUINO_NANO);
+        assert_eq!(config.sensor_profile, SensorProfile::Full);
+        assert_eq!(config.servo_pin, "D9");
+        assert_eq!(config.devices.len(), 2);
+        assert_eq!(config.devices[0].address, Some(0x76));
+        assert_eq!(config.devices[0].label, "BME280 (0x76)");
+        assert_eq!(config.devices[1].label, "Servo");
+    }
+
+    #[test]
+    fn l298n_direction_pins_override_the_board_defaults() {
+        let project =
+            WiringProject::parse(include_str!("../../examples/wiring/esp32-robot-base.json"))
+                .unwrap();
+        let config = project.to_config().unwrap();
+        assert_eq!(config.servo_pin, "GPIO18");
+        assert_eq!(
+            [
+                config.motor_pin.as_str(),
+                &config.motor_in1_pin,
+                &config.motor_in2_pin,
+                &config.motor_enb_pin,
+                &config.motor_in3_pin,
+                &config.motor_in4_pin,
+            ],
+            ["GPIO27", "GPIO25", "GPIO26", "GPIO14", "GPIO32", "GPIO33"]
+        );
+        let uses = crate::wiring_rules::pin_uses(&config);
+        assert!(uses
+            .iter()
+            .any(|pin_use| pin_use.signal == "ENB" && pin_use.pin == "GPIO14"));
+        assert_eq!(
+            WiringProject::from_config(&config).to_config().unwrap(),
+            config
+        );
+
+        let alias = WiringProject::parse(
+            r#"{"format":"mcu-hal-sim-wiring","version":1,"board":"esp32",
+                "pins":{"ena":"GPIO4"},"devices":[{"kind":"l298n"}]}"#,
+        )
+        .unwrap();
+        let config = alias.to_config().unwrap();
+        assert_eq!(config.motor_pin, "GPIO4");
+        assert_eq!(
+            config.motor_in1_pin,
+            BoardProfile::ORIGINAL_ESP32.motor_in1_pin()
+        );
+    }
+
+    #[test]
+    fn example_project_loads_and_passes_the_wiring_check() {
+        let project =
+            WiringProject::parse(include_str!("../../examples/wiring/esp32-bench.json")).unwrap();
+        let config = project.to_config().unwrap();
+        assert_eq!(config.devices.len(), 5);
+        assert_eq!(config.devices[1].address, Some(0x69));
+        assert_eq!(config.devices[4].label, "SSD1306 (0x3C)");
+        let report = crate::wiring_rules::check(&config);
+        assert!(!report.has_errors(), "{report}");
+    }
+
+    #[test]
+    fn rejects_foreign_newer_and_malformed_files() {
+        assert!(matches!(
+            WiringProject::parse(r#"{"board":"esp32","devices":[]}"#),
+            Err(ProjectError::NotAProject)
+        ));
+        assert!(matches!(
+            WiringProject::parse(
+                r#"{"format":"mcu-hal-sim-wiring","version":2,"board":"esp32","devices":[],"bus":"spi"}"#
+            ),
+            Err(ProjectError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            WiringProject::parse(
+                r#"{"format":"mcu-hal-sim-wiring","version":1,"board":"esp32","devices":[],"pinz":{}}"#
+            ),
+            Err(ProjectError::Json(_))
+        ));
+        assert!(matches!(
+            WiringProject::parse("nope"),
+            Err(ProjectError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_wiring_the_config_cannot_represent() {
+        let check = |devices: &str, board: &str| {
+            let text = format!(
+                r#"{{"format":"mcu-hal-sim-wiring","version":1,"board":"{board}","devices":[{devices}]}}"#
+            );
+            WiringProject::parse(&text)
+                .unwrap()
+                .to_config()
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(check(r#"{"kind":"lidar"}"#, "esp32").contains("unknown device"));
+        assert!(check(r#"{"kind":"servo"},{"kind":"servo"}"#, "esp32").contains("twice"));
+        assert!(check(r#"{"kind":"esp32_cam"}"#, "nano").contains("not supported"));
+        assert!(check(r#"{"kind":"servo","address":"0x40"}"#, "esp32").contains("not an I2C"));
+        assert!(check(r#"{"kind":"bme280","address":"0x90"}"#, "esp32").contains("address"));
+        assert!(check("", "uno").contains("unknown board"));
+    }
+
+    #[test]
+    fn every_device_signal_names_a_project_pin() {
+        let pins = serde_json::to_value(WiringProject::from_config(&WiringConfig::from_board(
+            BoardProfile::ORIGINAL_ESP32,
+        )))
+        .unwrap()["pins"]
+            .clone();
+        for &kind in DeviceKind::all() {
+            for (signal, pin) in kind.signals() {
+                assert!(pins.get(pin).is_some(), "{kind:?} {signal} -> {pin}");
+            }
+        }
+    }
+}
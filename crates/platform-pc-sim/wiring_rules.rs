@@ -0,0 +1,1400 @@
+//! Wiring validation: address conflicts, pin conflicts and electrical rules.
+//!
+//! [`check`] inspects a [`WiringConfig`] the way a reviewer would look at a
+//! breadboard before plugging in USB:
+//!
+//! - **I2C addresses** — two devices answering at the same address (the
+//!   MPU6050/DS3231 `0x68` clash that the simulator hides by attaching the
+//!   DS3231 at `0x69`), with a hint for an alternate address when one exists.
+//!   Devices on the M5StickC's internal bus also clash with its onboard chips.
+//! - **Pin conflicts** — one GPIO assigned to two signals. SDA/SCL are a
+//!   shared bus and only count once.
+//! - **Strapping and reserved pins** — whatever the board definition marks:
+//!   ESP32 boot-strapping pins (GPIO0/2/5/12/15), SPI-flash pins (GPIO6–11),
+//!   input-only pins (GPIO34–39), UART0 and the Nano's serial/analog-only
+//!   pins; pins that are not on the header (Pico, M5StickC) or are wired to
+//!   an onboard part such as the Pico LED or the M5StickC IMU/PMU/RTC bus.
+//! - **Pin capabilities** — SDA/SCL on a pin without I2C, or a servo or
+//!   motor-enable signal on a pin without PWM.
+//! - **Supply voltage and logic levels** — modules that need 5 V on a 3.3 V
+//!   rail, 5 V outputs or pull-ups into 3.3 V GPIOs, 5 V logic into 3.3 V
+//!   parts and 3.3 V logic below a 5 V input threshold.
+//! - **I2C pull-ups** — none on the bus, or so many module pull-ups in
+//!   parallel that the bus exceeds the 3 mA sink current of the I2C spec.
+//! - **Current budget** — typical draw per rail against the USB supply.
+//!
+//! Electrical figures are typical values for the common breakout modules
+//! (GY-521, ZS-042, PCF8574 backpack, ...), not for the bare chips.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::board_def::{PinCap, RailDef};
+use crate::dashboard::BoardProfile;
+use crate::wiring_config::{ConnectionType, DeviceKind, WiringConfig};
+
+/// Maximum low-level sink current allowed on a standard/fast-mode I2C bus.
+const I2C_SINK_LIMIT_UA: u32 = 3_000;
+/// Output low voltage assumed for the sink current calculation.
+const I2C_VOL_MV: u32 = 400;
+/// Fraction of the budget (in percent) above which the supply is reported as tight.
+const BUDGET_WARN_PERCENT: u32 = 80;
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth knowing, but the wiring works as drawn.
+    Info,
+    /// Likely to misbehave or to stress a part.
+    Warning,
+    /// Will not work or can damage hardware.
+    Error,
+}
+
+impl Severity {
+    pub fn slug(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Which rule produced a finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    AddressConflict,
+    PinConflict,
+    StrappingPin,
+    ReservedPin,
+    PinCapability,
+    SupplyVoltage,
+    LogicLevel,
+    PullUps,
+    CurrentBudget,
+}
+
+impl Rule {
+    pub fn slug(self) -> &'static str {
+        match self {
+            Rule::AddressConflict => "i2c-address",
+            Rule::PinConflict => "pin-conflict",
+            Rule::StrappingPin => "strapping-pin",
+            Rule::ReservedPin => "reserved-pin",
+            Rule::PinCapability => "pin-capability",
+            Rule::SupplyVoltage => "supply-voltage",
+            Rule::LogicLevel => "logic-level",
+            Rule::PullUps => "i2c-pullups",
+            Rule::CurrentBudget => "current-budget",
+        }
+    }
+}
+
+/// One problem found in a wiring config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub rule: Rule,
+    pub message: String,
+    /// Devices involved, in config order.
+    pub devices: Vec<DeviceKind>,
+    /// Board pins involved (e.g. `GPIO0`).
+    pub pins: Vec<String>,
+}
+
+impl Finding {
+    fn new(severity: Severity, rule: Rule, message: String) -> Self {
+        Self {
+            severity,
+            rule,
+            message,
+            devices: Vec::new(),
+            pins: Vec::new(),
+        }
+    }
+
+    fn devices(mut self, devices: &[DeviceKind]) -> Self {
+        self.devices = devices.to_vec();
+        self
+    }
+
+    fn pins<S: AsRef<str>>(mut self, pins: &[S]) -> Self {
+        self.pins = pins.iter().map(|pin| pin.as_ref().to_string()).collect();
+        self
+    }
+}
+
+/// All findings for one wiring config, most severe first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WiringReport {
+    pub findings: Vec<Finding>,
+}
+
+impl WiringReport {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+
+    /// Findings produced by `rule`.
+    pub fn by_rule(&self, rule: Rule) -> impl Iterator<Item = &Finding> {
+        self.findings
+            .iter()
+            .filter(move |finding| finding.rule == rule)
+    }
+
+    /// Serialise as `{"errors":n,"warnings":n,"infos":n,"findings":[...]}`.
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct FindingView<'a> {
+            severity: &'static str,
+            rule: &'static str,
+            message: &'a str,
+            devices: Vec<&'static str>,
+            pins: &'a [String],
+        }
+        #[derive(Serialize)]
+        struct ReportView<'a> {
+            errors: usize,
+            warnings: usize,
+            infos: usize,
+            findings: Vec<FindingView<'a>>,
+        }
+        let view = ReportView {
+            errors: self.count(Severity::Error),
+            warnings: self.count(Severity::Warning),
+            infos: self.count(Severity::Info),
+            findings: self
+                .findings
+                .iter()
+                .map(|finding| FindingView {
+                    severity: finding.severity.slug(),
+                    rule: finding.rule.slug(),
+                    message: &finding.message,
+                    devices: finding.devices.iter().map(|kind| kind.slug()).collect(),
+                    pins: &finding.pins,
+                })
+                .collect(),
+        };
+        serde_json::to_string(&view).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+impl fmt::Display for WiringReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for finding in &self.findings {
+            writeln!(
+                f,
+                "{:<7} [{}] {}",
+                finding.severity.slug(),
+                finding.rule.slug(),
+                finding.message
+            )?;
+        }
+        write!(
+            f,
+            "{} error(s), {} warning(s), {} info",
+            self.count(Severity::Error),
+            self.count(Severity::Warning),
+            self.count(Severity::Info)
+        )
+    }
+}
+
+/// A supply rail on the board header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rail {
+    pub name: &'static str,
+    pub millivolts: u32,
+    /// Current the rail can source for peripherals.
+    pub max_ma: u32,
+}
+
+impl Rail {
+    /// Header pin label, e.g. `VIN` for the `VIN (5V)` rail.
+    pub fn pin(self) -> &'static str {
+        self.name.split(' ').next().unwrap_or(self.name)
+    }
+}
+
+/// Electrical limits of a board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardElectrical {
+    /// GPIO output high level.
+    pub logic_mv: u32,
+    /// Highest voltage a GPIO input tolerates.
+    pub io_max_mv: u32,
+    /// Rail named by `WiringConfig::power_pin`.
+    pub main_rail: Rail,
+    /// The other rail on the header.
+    pub alt_rail: Rail,
+    /// USB current left for peripherals after the MCU's own draw.
+    pub peripheral_budget_ma: u32,
+}
+
+impl BoardElectrical {
+    pub fn for_board(board: BoardProfile) -> Self {
+        let electrical = &board.def().electrical;
+        let rail = |rail: &'static RailDef| Rail {
+            name: &rail.name,
+            millivolts: rail.millivolts,
+            max_ma: rail.max_ma,
+        };
+        Self {
+            logic_mv: electrical.logic_mv,
+            io_max_mv: electrical.io_max_mv,
+            main_rail: rail(&electrical.main_rail),
+            alt_rail: rail(&electrical.alt_rail),
+            peripheral_budget_ma: electrical.peripheral_budget_ma,
+        }
+    }
+
+    /// Rail a device's VCC is wired to: the main rail when it is in the
+    /// module's supply range, otherwise the other rail if that one is.
+    pub fn rail_for(&self, kind: DeviceKind) -> Rail {
+        let spec = DeviceElectrical::for_device(kind);
+        if !spec.accepts(self.main_rail.millivolts) && spec.accepts(self.alt_rail.millivolts) {
+            self.alt_rail
+        } else {
+            self.main_rail
+        }
+    }
+}
+
+/// Electrical characteristics of a breakout module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceElectrical {
+    /// Accepted VCC range in millivolts.
+    pub supply_mv: (u32, u32),
+    /// Fixed I/O level when the module regulates it; `None` follows VCC.
+    pub io_mv: Option<u32>,
+    /// Highest voltage its signal pins tolerate; `None` is VCC + 0.3 V.
+    pub io_max_mv: Option<u32>,
+    /// Minimum input-high voltage; `None` is 70% of the I/O level (CMOS).
+    pub vih_mv: Option<u32>,
+    /// Onboard SDA/SCL pull-up resistance.
+    pub pullup_ohms: Option<u32>,
+    /// Typical supply current.
+    pub current_ma: u32,
+    /// Other I2C addresses the module can be strapped to, with how.
+    pub alt_addresses: &'static [(u8, &'static str)],
+}
+
+impl DeviceElectrical {
+    pub fn for_device(kind: DeviceKind) -> Self {
+        let base = Self {
+            supply_mv: (3_000, 5_500),
+            io_mv: None,
+            io_max_mv: None,
+            vih_mv: None,
+            pullup_ohms: None,
+            current_ma: 1,
+            alt_addresses: &[],
+        };
+        match kind {
+            // Bare-sensor breakout without a regulator.
+            DeviceKind::Bme280 => Self {
+                supply_mv: (1_710, 3_600),
+                io_max_mv: Some(3_600),
+                pullup_ohms: Some(10_000),
+                alt_addresses: &[(0x76, "SDO to GND")],
+                ..base
+            },
+            // GY-521: LDO plus pull-ups to its 3.3 V output, no level shifter.
+            DeviceKind::Mpu6050 => Self {
+                io_mv: Some(3_300),
+                io_max_mv: Some(3_600),
+                pullup_ohms: Some(4_700),
+                current_ma: 4,
+                alt_addresses: &[(0x69, "AD0 high")],
+                ..base
+            },
+            // PCF8574 backpack, backlight on.
+            DeviceKind::Lcd1602 => Self {
+                supply_mv: (4_500, 5_500),
+                pullup_ohms: Some(4_700),
+                current_ma: 30,
+                alt_addresses: &[
+                    (0x26, "A0 jumper bridged"),
+                    (0x25, "A1 jumper bridged"),
+                    (0x24, "A0+A1 jumpers bridged"),
+                ],
+                ..base
+            },
+            // GY-302.
+            DeviceKind::Bh1750 => Self {
+                io_mv: Some(3_300),
+                io_max_mv: Some(4_500),
+                pullup_ohms: Some(4_700),
+                alt_addresses: &[(0x5C, "ADDR high")],
+                ..base
+            },
+            // ZS-042.
+            DeviceKind::Ds3231 => Self {
+                supply_mv: (2_300, 5_500),
+                pullup_ohms: Some(4_700),
+                ..base
+            },
+            // Breakout with LDO and level shifter, measuring.
+            DeviceKind::Sgp30 => Self {
+                pullup_ohms: Some(10_000),
+                current_ma: 48,
+                ..base
+            },
+            // GY-VL53L0XV2: 2.8 V LDO with pull-ups to 2.8 V.
+            DeviceKind::Vl53l0x => Self {
+                supply_mv: (2_600, 5_500),
+                io_mv: Some(2_800),
+                io_max_mv: Some(3_600),
+                pullup_ohms: Some(10_000),
+                current_ma: 19,
+                alt_addresses: &[(0x30, "set in software after an XSHUT reset")],
+                ..base
+            },
+            // 4-pin 0.96" module: onboard LDO, no pull-ups fitted.
+            DeviceKind::Ssd1306 => Self {
+                io_mv: Some(3_300),
+                io_max_mv: Some(5_500),
+                current_ma: 20,
+                alt_addresses: &[(0x3D, "address resistor moved")],
+                ..base
+            },
+            DeviceKind::HcSr04 => Self {
+                supply_mv: (4_500, 5_500),
+                vih_mv: Some(2_000),
+                current_ma: 15,
+                ..base
+            },
+            // SG90 while moving.
+            DeviceKind::Servo => Self {
+                supply_mv: (4_800, 6_000),
+                vih_mv: Some(2_000),
+                current_ma: 250,
+                ..base
+            },
+            // Logic side of the module (5 V from the onboard 78M05).
+            DeviceKind::L298n => Self {
+                supply_mv: (4_500, 7_000),
+                vih_mv: Some(2_300),
+                current_ma: 36,
+                ..base
+            },
+            // ESP32-CAM module fed with 5 V, 3.3 V I/O, camera streaming.
+            DeviceKind::Esp32Cam => Self {
+                supply_mv: (4_750, 5_250),
+                io_mv: Some(3_300),
+                io_max_mv: Some(3_600),
+                current_ma: 180,
+                ..base
+            },
+        }
+    }
+
+    fn accepts(&self, millivolts: u32) -> bool {
+        (self.supply_mv.0..=self.supply_mv.1).contains(&millivolts)
+    }
+}
+
+/// Who drives a pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinDirection {
+    /// The MCU drives the device input.
+    Output,
+    /// The device drives the MCU input.
+    Input,
+    /// Shared open-drain bus (I2C).
+    OpenDrain,
+}
+
+/// One signal wired to a board pin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinUse {
+    pub pin: String,
+    /// `None` for the shared I2C bus.
+    pub device: Option<DeviceKind>,
+    pub signal: &'static str,
+    pub direction: PinDirection,
+}
+
+impl PinUse {
+    fn owner(&self) -> String {
+        match self.device {
+            Some(kind) => format!("{} {}", kind.label(), self.signal),
+            None => format!("I2C {}", self.signal),
+        }
+    }
+}
+
+/// Every board pin the config uses, in config order. Unassigned (`N/A`) pins
+/// are skipped.
+pub fn pin_uses(config: &WiringConfig) -> Vec<PinUse> {
+    let mut uses = Vec::new();
+    let mut push = |pin: &str, device, signal, direction| {
+        if !pin.is_empty() && pin != "N/A" {
+            uses.push(PinUse {
+                pin: pin.to_string(),
+                device,
+                signal,
+                direction,
+            });
+        }
+    };
+    if config
+        .devices
+        .iter()
+        .any(|device| device.kind.connection_type() == ConnectionType::I2c)
+    {
+        push(&config.sda_pin, None, "SDA", PinDirection::OpenDrain);
+        push(&config.scl_pin, None, "SCL", PinDirection::OpenDrain);
+    }
+    for device in &config.devices {
+        let kind = Some(device.kind);
+        match device.kind {
+            DeviceKind::HcSr04 => {
+                push(&config.trig_pin, kind, "TRIG", PinDirection::Output);
+                push(&config.echo_pin, kind, "ECHO", PinDirection::Input);
+            }
+            DeviceKind::Servo => push(&config.servo_pin, kind, "PWM", PinDirection::Output),
+            DeviceKind::L298n => {
+                push(&config.motor_pin, kind, "ENA", PinDirection::Output);
+                push(&config.motor_in1_pin, kind, "IN1", PinDirection::Output);
+                push(&config.motor_in2_pin, kind, "IN2", PinDirection::Output);
+                push(&config.motor_enb_pin, kind, "ENB", PinDirection::Output);
+                push(&config.motor_in3_pin, kind, "IN3", PinDirection::Output);
+                push(&config.motor_in4_pin, kind, "IN4", PinDirection::Output);
+            }
+            DeviceKind::Esp32Cam => push(&config.cam_pin, kind, "IO0", PinDirection::Input),
+            _ => {}
+        }
+    }
+    uses
+}
+
+/// Run every rule against `config`.
+pub fn check(config: &WiringConfig) -> WiringReport {
+    let electrical = BoardElectrical::for_board(config.board);
+    let uses = pin_uses(config);
+    let mut findings = Vec::new();
+    check_addresses(config, &mut findings);
+    check_pin_conflicts(&uses, &mut findings);
+    check_reserved_pins(config.board, &uses, &mut findings);
+    check_pin_capabilities(config.board, &uses, &mut findings);
+    let rails = check_supply(config, &electrical, &mut findings);
+    check_logic_levels(config, &electrical, &uses, &rails, &mut findings);
+    check_pullups(config, &electrical, &rails, &mut findings);
+    check_current(config, &electrical, &rails, &mut findings);
+    // Stable sort keeps rule order within one severity.
+    findings.sort_by_key(|finding| Reverse(finding.severity));
+    WiringReport { findings }
+}
+
+fn check_addresses(config: &WiringConfig, findings: &mut Vec<Finding>) {
+    let mut by_address: BTreeMap<u8, Vec<DeviceKind>> = BTreeMap::new();
+    for device in &config.devices {
+        if let Some(address) = device.address {
+            by_address.entry(address).or_default().push(device.kind);
+        }
+    }
+    // Devices on the board's internal bus also share it with the onboard chips.
+    let onboard: Vec<_> = config
+        .board
+        .onboard_devices()
+        .iter()
+        .filter(|part| part.pins.contains(&config.sda_pin))
+        .filter_map(|part| Some((part.address?, part.name.as_str())))
+        .collect();
+    let taken = |address: u8| {
+        by_address.contains_key(&address) || onboard.iter().any(|&(used, _)| used == address)
+    };
+    for (&address, kinds) in &by_address {
+        let onboard_part = onboard
+            .iter()
+            .find(|&&(used, _)| used == address)
+            .map(|&(_, name)| format!("the onboard {name}"));
+        if kinds.len() + usize::from(onboard_part.is_some()) < 2 {
+            continue;
+        }
+        let mut names: Vec<String> = kinds.iter().map(|kind| kind.label().to_string()).collect();
+        names.extend(onboard_part);
+        let hint = kinds
+            .iter()
+            .find_map(|&kind| {
+                DeviceElectrical::for_device(kind)
+                    .alt_addresses
+                    .iter()
+                    .find(|&&(alt, _)| !taken(alt))
+                    .map(|(alt, how)| format!("move {} to 0x{alt:02X} ({how})", kind.label()))
+            })
+            .unwrap_or_else(|| {
+                "none of them has a free alternate address; use a second bus or an I2C multiplexer"
+                    .to_string()
+            });
+        findings.push(
+            Finding::new(
+                Severity::Error,
+                Rule::AddressConflict,
+                format!(
+                    "I2C address 0x{address:02X} is used by {}; {hint}",
+                    names.join(" and ")
+                ),
+            )
+            .devices(kinds)
+            .pins(&[&config.sda_pin, &config.scl_pin]),
+        );
+    }
+}
+
+fn check_pin_conflicts(uses: &[PinUse], findings: &mut Vec<Finding>) {
+    let mut by_pin: BTreeMap<&str, Vec<&PinUse>> = BTreeMap::new();
+    for pin_use in uses {
+        by_pin.entry(&pin_use.pin).or_default().push(pin_use);
+    }
+    for (pin, users) in by_pin {
+        if users.len() < 2 {
+            continue;
+        }
+        let owners: Vec<String> = users.iter().map(|pin_use| pin_use.owner()).collect();
+        let mut devices: Vec<DeviceKind> = Vec::new();
+        for kind in users.iter().filter_map(|pin_use| pin_use.device) {
+            if !devices.contains(&kind) {
+                devices.push(kind);
+            }
+        }
+        findings.push(
+            Finding::new(
+                Severity::Error,
+                Rule::PinConflict,
+                format!("{pin} is assigned to {}", owners.join(", ")),
+            )
+            .devices(&devices)
+            .pins(&[pin]),
+        );
+    }
+}
+
+fn check_reserved_pins(board: BoardProfile, uses: &[PinUse], findings: &mut Vec<Finding>) {
+    for pin_use in uses {
+        let finding = match onboard_pin_rule(board, pin_use) {
+            Some((severity, why)) => Some((severity, Rule::ReservedPin, why)),
+            None => header_pin_rule(board, pin_use),
+        };
+        if let Some((severity, rule, why)) = finding {
+            findings.push(
+                Finding::new(
+                    severity,
+                    rule,
+                    format!("{} ({}) {why}", pin_use.pin, pin_use.owner()),
+                )
+                .devices(&pin_use.device.into_iter().collect::<Vec<_>>())
+                .pins(&[&pin_use.pin]),
+            );
+        }
+    }
+}
+
+/// Pins hard-wired to a part on the board. Using the internal I2C bus for
+/// more I2C devices is fine; anything else fights the onboard part.
+fn onboard_pin_rule(board: BoardProfile, pin_use: &PinUse) -> Option<(Severity, String)> {
+    let onboard = board.onboard_devices();
+    let part = onboard
+        .iter()
+        .find(|part| part.pins.contains(&pin_use.pin))?;
+    if part.address.is_some() && pin_use.direction == PinDirection::OpenDrain {
+        let names: Vec<&str> = onboard
+            .iter()
+            .filter(|other| other.address.is_some())
+            .map(|other| other.name.as_str())
+            .collect();
+        return Some((
+            Severity::Info,
+            format!("is the internal I2C bus shared with {}", names.join(", ")),
+        ));
+    }
+    Some((
+        Severity::Error,
+        format!("is wired to the onboard {} ({})", part.name, part.function),
+    ))
+}
+
+/// Header pins the board definition marks as reserved, strapping or
+/// input-only, and pins that are not on the header at all.
+fn header_pin_rule(board: BoardProfile, pin_use: &PinUse) -> Option<(Severity, Rule, String)> {
+    let def = board.def();
+    let Some(pin) = board.pin(&pin_use.pin) else {
+        let why = def
+            .off_header
+            .clone()
+            .unwrap_or_else(|| format!("is not on the {} header", def.name));
+        return Some((Severity::Error, Rule::ReservedPin, why));
+    };
+    if let Some(note) = &pin.reserved {
+        let severity = note.severity.unwrap_or(Severity::Error);
+        return Some((severity, Rule::ReservedPin, note.why.clone()));
+    }
+    if pin.input_only && pin_use.direction != PinDirection::Input {
+        return Some((
+            Severity::Error,
+            Rule::ReservedPin,
+            "is input-only and has no output driver or pull-up".to_string(),
+        ));
+    }
+    let note = pin.strapping.as_ref()?;
+    // A device that can pull a strapping pin at reset changes the boot mode;
+    // an MCU output only glitches during boot.
+    let severity = note
+        .severity
+        .unwrap_or(if pin_use.direction != PinDirection::Output {
+            Severity::Warning
+        } else {
+            Severity::Info
+        });
+    Some((severity, Rule::StrappingPin, note.why.clone()))
+}
+
+/// Signals that need a pin function: the I2C bus and PWM outputs.
+fn check_pin_capabilities(board: BoardProfile, uses: &[PinUse], findings: &mut Vec<Finding>) {
+    for pin_use in uses {
+        let (cap, what) = match pin_use.signal {
+            "SDA" | "SCL" => (PinCap::I2c, "I2C"),
+            "PWM" | "ENA" | "ENB" => (PinCap::Pwm, "PWM"),
+            _ => continue,
+        };
+        let Some(pin) = board.pin(&pin_use.pin) else {
+            continue;
+        };
+        if pin.has(cap) || pin.reserved.is_some() {
+            continue;
+        }
+        findings.push(
+            Finding::new(
+                Severity::Error,
+                Rule::PinCapability,
+                format!(
+                    "{} ({}) has no {what} function on the {}",
+                    pin_use.pin,
+                    pin_use.owner(),
+                    board.name()
+                ),
+            )
+            .devices(&pin_use.device.into_iter().collect::<Vec<_>>())
+            .pins(&[&pin_use.pin]),
+        );
+    }
+}
+
+/// Rail each device is (or should be) powered from, by device index.
+fn check_supply(
+    config: &WiringConfig,
+    electrical: &BoardElectrical,
+    findings: &mut Vec<Finding>,
+) -> Vec<Rail> {
+    let main = electrical.main_rail;
+    let alt = electrical.alt_rail;
+    config
+        .devices
+        .iter()
+        .map(|device| {
+            let spec = DeviceElectrical::for_device(device.kind);
+            if spec.accepts(main.millivolts) {
+                return main;
+            }
+            let (min, max) = spec.supply_mv;
+            let fix = if spec.accepts(alt.millivolts) {
+                format!("wire VCC to {}", alt.name)
+            } else {
+                "it needs a separate supply".to_string()
+            };
+            findings.push(
+                Finding::new(
+                    Severity::Warning,
+                    Rule::SupplyVoltage,
+                    format!(
+                        "{} needs {}-{} V but the diagram powers it from {}; {fix}",
+                        device.kind.label(),
+                        volts(min),
+                        volts(max),
+                        config.power_pin
+                    ),
+                )
+                .devices(&[device.kind])
+                .pins(&[&config.power_pin]),
+            );
+            electrical.rail_for(device.kind)
+        })
+        .collect()
+}
+
+/// I/O level of a device powered from `rail`.
+fn io_level(spec: &DeviceElectrical, rail: Rail) -> u32 {
+    spec.io_mv.unwrap_or(rail.millivolts)
+}
+
+/// High level of the I2C bus: the highest pull-up voltage on it, or the MCU
+/// logic level (internal pull-ups) when no module has pull-ups.
+fn i2c_high_level(config: &WiringConfig, electrical: &BoardElectrical, rails: &[Rail]) -> u32 {
+    config
+        .devices
+        .iter()
+        .zip(rails)
+        .filter_map(|(device, &rail)| {
+            let spec = DeviceElectrical::for_device(device.kind);
+            spec.pullup_ohms.map(|_| io_level(&spec, rail))
+        })
+        .max()
+        .unwrap_or(electrical.logic_mv)
+}
+
+fn check_logic_levels(
+    config: &WiringConfig,
+    electrical: &BoardElectrical,
+    uses: &[PinUse],
+    rails: &[Rail],
+    findings: &mut Vec<Finding>,
+) {
+    let board_name = config.board.name();
+    let i2c_high = i2c_high_level(config, electrical, rails);
+    let i2c_pins = [config.sda_pin.as_str(), config.scl_pin.as_str()];
+
+    let pulling: Vec<DeviceKind> = config
+        .devices
+        .iter()
+        .filter(|device| {
+            DeviceElectrical::for_device(device.kind)
+                .pullup_ohms
+                .is_some()
+        })
+        .map(|device| device.kind)
+        .collect();
+    let pulling_names: Vec<&str> = pulling.iter().map(|kind| kind.label()).collect();
+    let board_vih = electrical.logic_mv * 7 / 10;
+    if !pulling.is_empty() && i2c_high > electrical.io_max_mv {
+        let over: Vec<DeviceKind> = config
+            .devices
+            .iter()
+            .zip(rails)
+            .filter(|(device, &rail)| {
+                let spec = DeviceElectrical::for_device(device.kind);
+                spec.pullup_ohms.is_some() && io_level(&spec, rail) > electrical.io_max_mv
+            })
+            .map(|(device, _)| device.kind)
+            .collect();
+        let over_names: Vec<&str> = over.iter().map(|kind| kind.label()).collect();
+        findings.push(
+            Finding::new(
+                Severity::Error,
+                Rule::LogicLevel,
+                format!(
+                    "{} pulls SDA/SCL up to {} V but {board_name} pins are rated to {} V; add an I2C level shifter",
+                    over_names.join(", "),
+                    volts(i2c_high),
+                    volts(electrical.io_max_mv)
+                ),
+            )
+            .devices(&over)
+            .pins(&i2c_pins),
+        );
+    } else if !pulling.is_empty() && i2c_high < board_vih {
+        findings.push(
+            Finding::new(
+                Severity::Warning,
+                Rule::LogicLevel,
+                format!(
+                    "{} pulls SDA/SCL up to only {} V, below the {} V input-high threshold of the {}; add a level shifter",
+                    pulling_names.join(", "),
+                    volts(i2c_high),
+                    volts(board_vih),
+                    config.board.mcu()
+                ),
+            )
+            .devices(&pulling)
+            .pins(&i2c_pins),
+        );
+    }
+
+    for (device, &rail) in config.devices.iter().zip(rails) {
+        let spec = DeviceElectrical::for_device(device.kind);
+        let io = io_level(&spec, rail);
+        let io_max = spec.io_max_mv.unwrap_or(rail.millivolts + 300);
+        let vih = spec.vih_mv.unwrap_or(io * 7 / 10);
+        let label = device.kind.label();
+        let (high, pins): (u32, Vec<&str>) = if device.kind.connection_type() == ConnectionType::I2c
+        {
+            (i2c_high, i2c_pins.to_vec())
+        } else {
+            let pins = uses
+                .iter()
+                .filter(|pin_use| pin_use.device == Some(device.kind))
+                .map(|pin_use| pin_use.pin.as_str())
+                .collect();
+            (electrical.logic_mv, pins)
+        };
+        if pins.is_empty() {
+            continue;
+        }
+
+        let device_outputs: Vec<&str> = uses
+            .iter()
+            .filter(|pin_use| {
+                pin_use.device == Some(device.kind) && pin_use.direction == PinDirection::Input
+            })
+            .map(|pin_use| pin_use.pin.as_str())
+            .collect();
+        if !device_outputs.is_empty() && io > electrical.io_max_mv {
+            findings.push(
+                Finding::new(
+                    Severity::Error,
+                    Rule::LogicLevel,
+                    format!(
+                        "{label} drives {} with {} V but {board_name} pins are rated to {} V; add a divider or level shifter",
+                        device_outputs.join(", "),
+                        volts(io),
+                        volts(electrical.io_max_mv)
+                    ),
+                )
+                .devices(&[device.kind])
+                .pins(&device_outputs),
+            );
+        }
+        if high > io_max {
+            findings.push(
+                Finding::new(
+                    Severity::Warning,
+                    Rule::LogicLevel,
+                    format!(
+                        "{label} signal pins see {} V but are rated to {} V; use a level shifter",
+                        volts(high),
+                        volts(io_max)
+                    ),
+                )
+                .devices(&[device.kind])
+                .pins(&pins),
+            );
+        } else if high < vih {
+            findings.push(
+                Finding::new(
+                    Severity::Warning,
+                    Rule::LogicLevel,
+                    format!(
+                        "{} V logic is below the {} V input-high threshold of {label}; it may not register",
+                        volts(high),
+                        volts(vih)
+                    ),
+                )
+                .devices(&[device.kind])
+                .pins(&pins),
+            );
+        }
+    }
+}
+
+fn check_pullups(
+    config: &WiringConfig,
+    electrical: &BoardElectrical,
+    rails: &[Rail],
+    findings: &mut Vec<Finding>,
+) {
+    let i2c: Vec<DeviceKind> = config
+        .devices
+        .iter()
+        .filter(|device| device.kind.connection_type() == ConnectionType::I2c)
+        .map(|device| device.kind)
+        .collect();
+    if i2c.is_empty() {
+        return;
+    }
+    let pins = [&config.sda_pin, &config.scl_pin];
+    // Parallel resistance as a sum of conductances in microsiemens.
+    let pulling: Vec<DeviceKind> = i2c
+        .iter()
+        .copied()
+        .filter(|&kind| DeviceElectrical::for_device(kind).pullup_ohms.is_some())
+        .collect();
+    let conductance_us: u32 = pulling
+        .iter()
+        .filter_map(|&kind| DeviceElectrical::for_device(kind).pullup_ohms)
+        .map(|ohms| 1_000_000 / ohms)
+        .sum();
+    if conductance_us == 0 {
+        findings.push(
+            Finding::new(
+                Severity::Warning,
+                Rule::PullUps,
+                format!(
+                    "no module on the I2C bus has pull-ups; fit 4.7 kΩ from SDA and SCL to {}",
+                    config.power_pin
+                ),
+            )
+            .devices(&i2c)
+            .pins(&pins),
+        );
+        return;
+    }
+    let high = i2c_high_level(config, electrical, rails);
+    let sink_ua = high.saturating_sub(I2C_VOL_MV) * conductance_us / 1_000;
+    if sink_ua > I2C_SINK_LIMIT_UA {
+        findings.push(
+            Finding::new(
+                Severity::Warning,
+                Rule::PullUps,
+                format!(
+                    "{} module pull-ups in parallel give {} Ω ({}.{} mA sink, I2C allows 3 mA); remove pull-up resistors from some modules",
+                    pulling.len(),
+                    1_000_000 / conductance_us,
+                    sink_ua / 1_000,
+                    sink_ua % 1_000 / 100
+                ),
+            )
+            .devices(&pulling)
+            .pins(&pins),
+        );
+    }
+}
+
+fn check_current(
+    config: &WiringConfig,
+    electrical: &BoardElectrical,
+    rails: &[Rail],
+    findings: &mut Vec<Finding>,
+) {
+    let budget = electrical.peripheral_budget_ma;
+    let mut total = 0;
+    for rail in [electrical.main_rail, electrical.alt_rail] {
+        let on_rail: Vec<(DeviceKind, u32)> = config
+            .devices
+            .iter()
+            .zip(rails)
+            .filter(|(_, &device_rail)| device_rail == rail)
+            .map(|(device, _)| {
+                (
+                    device.kind,
+                    DeviceElectrical::for_device(device.kind).current_ma,
+                )
+            })
+            .collect();
+        let draw: u32 = on_rail.iter().map(|(_, ma)| ma).sum();
+        total += draw;
+        // Rails that can source more than the USB budget are covered by the
+        // total below; only a weaker rail (the Nano's 3V3 pin) is checked here.
+        if rail.max_ma < budget && draw > rail.max_ma {
+            let kinds: Vec<DeviceKind> = on_rail.iter().map(|(kind, _)| *kind).collect();
+            findings.push(
+                Finding::new(
+                    Severity::Error,
+                    Rule::CurrentBudget,
+                    format!(
+                        "devices on {} draw about {draw} mA but the rail supplies {} mA",
+                        rail.name, rail.max_ma
+                    ),
+                )
+                .devices(&kinds),
+            );
+        }
+    }
+    let severity = if total > budget {
+        Severity::Error
+    } else if total * 100 > budget * BUDGET_WARN_PERCENT {
+        Severity::Warning
+    } else {
+        return;
+    };
+    let mut heavy: Vec<(DeviceKind, u32)> = config
+        .devices
+        .iter()
+        .map(|device| {
+            (
+                device.kind,
+                DeviceElectrical::for_device(device.kind).current_ma,
+            )
+        })
+        .collect();
+    heavy.sort_by_key(|(_, ma)| Reverse(*ma));
+    heavy.truncate(2);
+    let heavy_names: Vec<String> = heavy
+        .iter()
+        .map(|(kind, ma)| format!("{} {ma} mA", kind.label()))
+        .collect();
+    findings.push(
+        Finding::new(
+            severity,
+            Rule::CurrentBudget,
+            format!(
+                "peripherals draw about {total} mA of the ~{budget} mA USB leaves after the {}; largest: {}; power them from an external supply",
+                config.board.mcu(),
+                heavy_names.join(", ")
+            ),
+        )
+        .devices(&heavy.iter().map(|(kind, _)| *kind).collect::<Vec<_>>()),
+    );
+}
+
+/// Millivolts as volts (`3300` → `3.3`, `4750` → `4.75`).
+fn volts(millivolts: u32) -> String {
+    let (whole, frac) = (millivolts / 1_000, millivolts % 1_000);
+    if frac % 100 == 0 {
+        format!("{whole}.{}", frac / 100)
+    } else {
+        format!("{whole}.{:02}", frac / 10)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wiring_config::SensorProfile;
+
+    fn config(board: BoardProfile, devices: &[DeviceKind]) -> WiringConfig {
+        WiringConfig::from_board_with_selected_devices(board, SensorProfile::Full, devices)
+    }
+
+    #[test]
+    fn reports_mpu6050_ds3231_address_collision_with_hint() {
+        let cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::Mpu6050, DeviceKind::Ds3231],
+        );
+        let report = check(&cfg);
+        let finding = report
+            .by_rule(Rule::AddressConflict)
+            .next()
+            .expect("0x68 collision");
+        assert_eq!(finding.severity, Severity::Error);
+        assert_eq!(
+            finding.devices,
+            vec![DeviceKind::Mpu6050, DeviceKind::Ds3231]
+        );
+        assert!(finding.message.contains("0x68"), "{}", finding.message);
+        assert!(
+            finding.message.contains("MPU6050 to 0x69"),
+            "{}",
+            finding.message
+        );
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn address_hint_skips_alternates_already_in_use() {
+        let mut cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::Bme280, DeviceKind::Lcd1602],
+        );
+        cfg.devices[1].address = Some(0x77);
+        let report = check(&cfg);
+        let finding = report.by_rule(Rule::AddressConflict).next().unwrap();
+        assert!(
+            finding.message.contains("BME280 to 0x76"),
+            "{}",
+            finding.message
+        );
+
+        let cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::Ds3231, DeviceKind::Sgp30],
+        );
+        let mut cfg = cfg;
+        cfg.devices[1].address = Some(0x68);
+        let finding = check(&cfg).by_rule(Rule::AddressConflict).next().cloned();
+        assert!(finding.unwrap().message.contains("multiplexer"));
+    }
+
+    #[test]
+    fn reports_pin_double_assignment_but_not_shared_i2c_bus() {
+        let mut cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::Bme280, DeviceKind::Lcd1602, DeviceKind::Servo],
+        );
+        assert_eq!(check(&cfg).by_rule(Rule::PinConflict).count(), 0);
+
+        cfg.servo_pin = cfg.sda_pin.clone();
+        let report = check(&cfg);
+        let finding = report.by_rule(Rule::PinConflict).next().unwrap();
+        assert_eq!(finding.pins, vec!["GPIO21".to_string()]);
+        assert!(finding.message.contains("I2C SDA"), "{}", finding.message);
+        assert!(finding.message.contains("Servo PWM"), "{}", finding.message);
+    }
+
+    #[test]
+    fn flags_esp32_strapping_flash_and_input_only_pins() {
+        let mut cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::HcSr04, DeviceKind::Servo, DeviceKind::Esp32Cam],
+        );
+        let report = check(&cfg);
+        let cam = report
+            .by_rule(Rule::StrappingPin)
+            .find(|finding| finding.pins == ["GPIO0"])
+            .expect("GPIO0 strapping finding");
+        assert_eq!(cam.severity, Severity::Warning);
+        let trig = report
+            .by_rule(Rule::StrappingPin)
+            .find(|finding| finding.pins == ["GPIO5"])
+            .expect("GPIO5 strapping finding");
+        assert_eq!(trig.severity, Severity::Info);
+
+        cfg.servo_pin = "GPIO35".to_string();
+        cfg.trig_pin = "GPIO7".to_string();
+        cfg.echo_pin = "GPIO34".to_string();
+        let report = check(&cfg);
+        let reserved: Vec<&Finding> = report.by_rule(Rule::ReservedPin).collect();
+        assert_eq!(reserved.len(), 2, "{reserved:?}");
+        assert!(reserved.iter().all(|f| f.severity == Severity::Error));
+        assert!(reserved.iter().any(|f| f.pins == ["GPIO35"]));
+        assert!(reserved.iter().any(|f| f.pins == ["GPIO7"]));
+    }
+
+    #[test]
+    fn flags_nano_serial_and_analog_only_pins() {
+        let mut cfg = config(BoardProfile::ARDUINO_NANO, &[DeviceKind::HcSr04]);
+        cfg.trig_pin = "D1".to_string();
+        cfg.echo_pin = "A7".to_string();
+        let report = check(&cfg);
+        let severities: Vec<Severity> = report
+            .by_rule(Rule::ReservedPin)
+            .map(|finding| finding.severity)
+            .collect();
+        assert_eq!(severities, vec![Severity::Error, Severity::Warning]);
+    }
+
+    #[test]
+    fn flags_i2c_and_pwm_signals_on_pins_without_the_function() {
+        let mut cfg = config(
+            BoardProfile::ARDUINO_NANO,
+            &[DeviceKind::Bme280, DeviceKind::Servo],
+        );
+        assert_eq!(check(&cfg).by_rule(Rule::PinCapability).count(), 0);
+
+        cfg.sda_pin = "D2".to_string();
+        cfg.servo_pin = "D4".to_string();
+        let report = check(&cfg);
+        let messages: Vec<&str> = report
+            .by_rule(Rule::PinCapability)
+            .map(|finding| finding.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "D2 (I2C SDA) has no I2C function on the Arduino Nano",
+                "D4 (Servo PWM) has no PWM function on the Arduino Nano",
+            ]
+        );
+        assert!(report.to_json().contains(r#""rule":"pin-capability""#));
+    }
+
+    #[test]
+    fn flags_5v_echo_into_esp32_and_5v_parts_on_3v3_rail() {
+        let cfg = config(BoardProfile::ORIGINAL_ESP32, &[DeviceKind::HcSr04]);
+        let report = check(&cfg);
+        let supply = report.by_rule(Rule::SupplyVoltage).next().unwrap();
+        assert!(supply.message.contains("VIN"), "{}", supply.message);
+        let logic = report.by_rule(Rule::LogicLevel).next().unwrap();
+        assert_eq!(logic.severity, Severity::Error);
+        assert_eq!(logic.pins, vec!["GPIO18".to_string()]);
+    }
+
+    #[test]
+    fn flags_5v_pullups_on_esp32_and_5v_logic_into_3v3_parts_on_nano() {
+        let cfg = config(
+            BoardProfile::ORIGINAL_ESP32,
+            &[DeviceKind::Bme280, DeviceKind::Lcd1602],
+        );
+        let report = check(&cfg);
+        assert!(report.by_rule(Rule::LogicLevel).any(|finding| {
+            finding.severity == Severity::Error && finding.devices == [DeviceKind::Lcd1602]
+        }));
+        // LCD at 5 V also overdrives the 3.6 V BME280.
+        assert!(report
+            .by_rule(Rule::LogicLevel)
+            .any(|finding| finding.devices == [DeviceKind::Bme280]));
+
+        let cfg = config(BoardProfile::ARDUINO_NANO, &[DeviceKind::Mpu6050]);
+        let report = check(&cfg);
+        let finding = report.by_rule(Rule::LogicLevel).next().unwrap();
+        assert_eq!(finding.severity, Severity::Warning);
+        assert_eq!(finding.pins, vec!["A4".to_string(), "A5".to_string()]);
+    }
+
+    #[test]
+    fn clean_configs_report_no_errors() {
+        let cfg = WiringConfig::from_board_with_sensors(
+            BoardProfile::ARDUINO_NANO,
+            SensorProfile::Minimal,
+        );
+        let mut cfg = cfg;
+        cfg.devices
+            .retain(|device| device.kind == DeviceKind::Lcd1602);
+        let report = check(&cfg);
+        assert!(report.findings.is_empty(), "{report}");
+    }
+
+    #[test]
+    fn flags_m5stickc_pins_that_are_onboard_or_not_broken_out() {
+        let mut cfg = config(
+            BoardProfile::M5STICKC,
+            &[DeviceKind::HcSr04, DeviceKind::Servo],
+        );
+        let report = check(&cfg);
+        assert!(
+            report
+                .by_rule(Rule::ReservedPin)
+                .all(|finding| finding.severity != Severity::Error),
+            "{report}"
+        );
+        // A servo input can hold its signal low at reset; keep it off GPIO0.
+        assert!(
+            report
+                .by_rule(Rule::StrappingPin)
+                .all(|finding| !finding.message.contains("Servo")),
+            "{report}"
+        );
+
+        cfg.servo_pin = "GPIO10".to_string();
+        cfg.trig_pin = "GPIO4".to_string();
+        let report = check(&cfg);
+        let messages: Vec<&str> = report
+            .by_rule(Rule::ReservedPin)
+            .filter(|finding| finding.severity == Severity::Error)
+            .map(|finding| finding.message.as_str())
+            .collect();
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.starts_with("GPIO10") && m.contains("onboard LED")),
+            "{report}"
+        );
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.starts_with("GPIO4") && m.contains("not broken out")),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn m5stickc_internal_bus_collides_with_onboard_imu() {
+        let mut cfg = config(BoardProfile::M5STICKC, &[DeviceKind::Mpu6050]);
+        cfg.sda_pin = "GPIO21".to_string();
+        cfg.scl_pin = "GPIO22".to_string();
+        let report = check(&cfg);
+        let shared = report
+            .by_rule(Rule::ReservedPin)
+            .find(|finding| finding.pins == ["GPIO21"])
+            .expect("internal bus note");
+        assert_eq!(shared.severity, Severity::Info);
+        assert!(shared.message.contains("MPU6886"), "{}", shared.message);
+        let clash = report
+            .by_rule(Rule::AddressConflict)
+            .next()
+            .expect("0x68 clash with the onboard MPU6886");
+        assert!(
+            clash.message.contains("MPU6050 and the onboard MPU6886"),
+            "{}",
+            clash.message
+        );
+        assert!(
+            clash.message.contains("MPU6050 to 0x69"),
+            "{}",
+            clash.message
+        );
+
+        // On the Grove port the onboard chips are on another bus.
+        let grove = config(BoardProfile::M5STICKC, &[DeviceKind::Mpu6050]);
+        assert_eq!(check(&grove).by_rule(Rule::AddressConflict).count(), 0);
+    }
+
+    #[test]
+    fn flags_pico_onboard_and_missing_header_pins() {
+        let mut cfg = config(BoardProfile::RASPBERRY_PI_PICO, &[DeviceKind::Servo]);
+        assert_eq!(check(&cfg).by_rule(Rule::ReservedPin).count(), 0);
+
+        cfg.servo_pin = "GP25".to_string();
+        let report = check(&cfg);
+        let finding = report.by_rule(Rule::ReservedPin).next().expect("LED pin");
+        assert_eq!(finding.severity, Severity::Error);
+        assert!(
+            finding.message.contains("onboard LED"),
+            "{}",
+            finding.message
+        );
+
+        cfg.servo_pin = "GP30".to_string();
+        let report = check(&cfg);
+        let finding = report.by_rule(Rule::ReservedPin).next().expect("no GP30");
+        assert!(finding
+            .message
+            .contains("not on the Raspberry Pi Pico header"));
+    }
+
+    #[test]
+    fn pullup_rules_cover_missing_and_too_strong() {
+        let cfg = config(BoardProfile::ORIGINAL_ESP32, &[DeviceKind::Ssd1306]);
+        let report = check(&cfg);
+        assert_eq!(report.by_rule(Rule::PullUps).count(), 1);
+        assert!(report.findings[0].message.contains("no module"));
+
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
+        let finding = check(&cfg).by_rule(Rule::PullUps).next().cloned().unwrap();
+        assert!(finding.message.contains("sink"), "{}", finding.message);
+        assert!(!finding.devices.contains(&DeviceKind::Ssd1306));
+    }
+
+    #[test]
+    fn current_budget_counts_servo_against_usb() {
+        let cfg = WiringConfig::from_board_with_sensors(
+            BoardProfile::ORIGINAL_ESP32,
+            SensorProfile::RobotBase,
+        );
+        let report = check(&cfg);
+        let finding = report.by_rule(Rule::CurrentBudget).next().unwrap();
+        assert_eq!(finding.severity, Severity::Error);
+        assert_eq!(finding.devices[0], DeviceKind::Servo);
+
+        let cfg = WiringConfig::from_board_with_sensors(
+            BoardProfile::ARDUINO_NANO,
+            SensorProfile::ClimateStation,
+        );
+        assert_eq!(check(&cfg).by_rule(Rule::CurrentBudget).count(), 0);
+    }
+
+    #[test]
+    fn current_budget_checks_the_weak_nano_3v3_rail() {
+        // 3.3 V-only parts move to the Nano's 3V3 pin, which the USB-serial
+        // chip can only load with ~50 mA.
+        let mut cfg = config(BoardProfile::ARDUINO_NANO, &[DeviceKind::Bme280]);
+        let bme = cfg.devices[0].clone();
+        cfg.devices = vec![bme; 60];
+        let report = check(&cfg);
+        let finding = report
+            .by_rule(Rule::CurrentBudget)
+            .find(|finding| finding.message.contains("3V3"))
+            .expect("3V3 rail finding");
+        assert_eq!(finding.severity, Severity::Error);
+        assert_eq!(volts(4_750), "4.75");
+        assert_eq!(volts(3_300), "3.3");
+    }
+
+    #[test]
+    fn report_sorts_by_severity_and_serialises() {
+        let report = check(&WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32));
+        let severities: Vec<Severity> = report.findings.iter().map(|f| f.severity).collect();
+        let mut sorted = severities.clone();
+        sorted.sort_by(|a, b| b.cmp(a));
+        assert_eq!(severities, sorted);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["errors"], report.count(Severity::Error));
+        assert_eq!(json["findings"][0]["severity"], "error");
+        assert_eq!(json["findings"][0]["rule"], "i2c-address");
+        assert_eq!(json["findings"][0]["devices"][0], "mpu6050");
+        assert!(report.to_string().ends_with("info"));
+    }
+}
@@ -6,6 +6,8 @@
 
 use std::fmt::Write as _;
 
+use crate::board_def::PartKind;
+use crate::dashboard::BoardProfile;
 use crate::wiring_config::{ConnectionType, DeviceKind, WiringConfig};
 
 /// Generate a self-contained SVG string for the given wiring config.
@@ -50,6 +52,9 @@ const GND_RAIL_X: i32 = 240;
 const SDA_RAIL_X: i32 = 280;
 const SCL_RAIL_X: i32 = 306;
 
+/// Wrap width of the onboard peripheral list under the board.
+const ONBOARD_LINE_CHARS: usize = 64;
+
 #[allow(clippy::write_with_newline)]
 fn render(out: &mut String, config: &WiringConfig) {
     let device_rows = config.devices.len().max(1) as i32;
@@ -94,23 +99,49 @@ fn render(out: &mut String, config: &WiringConfig) {
 @keyframes wiring-flow{{from{{stroke-dashoffset:24}}to{{stroke-dashoffset:0}}}}
 .dot-sda{{fill:#4488ff}}.dot-scl{{fill:#ffdd44}}.dot-vcc{{fill:#e55}}.dot-gnd{{fill:#556}}.dot-gpio{{fill:#ff9944}}.dot-pwm{{fill:#bb88ff}}
 .leg{{fill:#888;font:9px monospace}}
+.pcb-part{{fill:#222;stroke:#777;stroke-width:1}}
+.pcb-usb{{fill:#aab;stroke:#667;stroke-width:1}}
+.pcb-lcd{{fill:#0b1630;stroke:#4a6a9a;stroke-width:1.5}}
+.pcb-part-lbl{{fill:#bbb;font:8px monospace}}
 </style></defs>
 "#
     );
 
     // Board PCB
-    let board_label = config.board.name();
-    let mcu_label = config.board.mcu();
+    let board_label = xml_escape(config.board.name());
+    let mcu_label = xml_escape(config.board.mcu());
+    let mcu_specs = xml_escape(config.board.mcu_specs());
+    // Board colours come from the footprint; without them the green DevKit look.
+    let footprint = &config.board.def().footprint;
+    let board_style: Vec<String> = [("fill", &footprint.fill), ("stroke", &footprint.stroke)]
+        .into_iter()
+        .filter_map(|(property, colour)| Some(format!("{property}:{}", colour.as_ref()?)))
+        .collect();
+    let board_style = if board_style.is_empty() {
+        String::new()
+    } else {
+        format!(r#" style="{}""#, board_style.join(";"))
+    };
+    // Long names are squeezed to the board width instead of spilling over.
+    let label_fit = if board_label.len() > 14 {
+        format!(
+            r#" textLength="{}" lengthAdjust="spacingAndGlyphs""#,
+            BOARD_W - 8
+        )
+    } else {
+        String::new()
+    };
     let cx = BOARD_X + BOARD_W / 2;
     let _ = write!(
         out,
-        r#"<rect x="{BOARD_X}" y="{BOARD_Y}" width="{BOARD_W}" height="{BOARD_H}" rx="6" class="pcb-board"/>
-<text x="{cx}" y="{}" class="pcb-lbl" text-anchor="middle">{board_label}</text>
+        r#"<rect x="{BOARD_X}" y="{BOARD_Y}" width="{BOARD_W}" height="{BOARD_H}" rx="6" class="pcb-board"{board_style}><title>{mcu_label}: {mcu_specs}</title></rect>
+<text x="{cx}" y="{}" class="pcb-lbl" text-anchor="middle"{label_fit}>{board_label}</text>
 <text x="{cx}" y="{}" class="pcb-sub" text-anchor="middle">{mcu_label}</text>
 "#,
         BOARD_Y + 20,
         BOARD_Y + 33,
     );
+    render_board_art(out, config.board);
 
     // SensorProfile label (top-right corner)
     let profile_name = config.sensor_profile.display_name();
@@ -424,6 +455,38 @@ fn render(out: &mut String, config: &WiringConfig) {
         }
     }
 
+    // Onboard peripherals under the board
+    let onboard: Vec<String> = config
+        .board
+        .onboard_devices()
+        .iter()
+        .map(|part| match part.address {
+            Some(address) => format!("{} 0x{address:02X}", xml_escape(&part.name)),
+            None => xml_escape(&part.name),
+        })
+        .collect();
+    if !onboard.is_empty() {
+        let mut lines = vec![String::from("Onboard:")];
+        for item in onboard {
+            let line = lines.last_mut().expect("at least one line");
+            if line.len() + item.len() + 2 > ONBOARD_LINE_CHARS {
+                line.push(',');
+                lines.push(format!("  {item}"));
+            } else {
+                line.push_str(if line.ends_with(':') { " " } else { ", " });
+                line.push_str(&item);
+            }
+        }
+        for (i, line) in lines.iter().enumerate() {
+            let _ = write!(
+                out,
+                r#"<text x="{BOARD_X}" y="{}" class="pcb-sub onboard-lbl">{line}</text>
+"#,
+                BOARD_Y + BOARD_H + 16 + i as i32 * 12,
+            );
+        }
+    }
+
     // Legend at bottom
     let leg_y = svg_h - 10;
     let _ = write!(
@@ -436,6 +499,41 @@ fn render(out: &mut String, config: &WiringConfig) {
     out.push_str("</svg>");
 }
 
+/// Decorations from the board footprint (connector, main part, display),
+/// placed relative to the board outline.
+fn render_board_art(out: &mut String, board: BoardProfile) {
+    for part in &board.def().footprint.parts {
+        let class = match part.kind {
+            PartKind::Usb => "pcb-usb",
+            PartKind::Chip => "pcb-part",
+            PartKind::Lcd => "pcb-lcd",
+        };
+        let (x, y) = (BOARD_X + part.x, BOARD_Y + part.y);
+        let _ = writeln!(
+            out,
+            r#"<rect x="{x}" y="{y}" width="{}" height="{}" rx="2" class="{class}"/>"#,
+            part.w, part.h,
+        );
+        if let Some(label) = &part.label {
+            let _ = writeln!(
+                out,
+                r#"<text x="{}" y="{}" class="pcb-part-lbl" text-anchor="middle">{}</text>"#,
+                x + part.w / 2,
+                y + part.h / 2 + 3,
+                xml_escape(label),
+            );
+        }
+    }
+}
+
+/// Escape text from board definitions for SVG text and attributes.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -444,7 +542,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_contains_board_label() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         let svg = wiring_svg(&cfg);
         assert!(svg.contains("original ESP32"), "missing board label");
         assert!(svg.contains("ESP32"), "missing MCU label");
@@ -452,7 +550,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_contains_device_labels() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         let svg = wiring_svg(&cfg);
         assert!(svg.contains("BME280"));
         assert!(svg.contains("MPU6050"));
@@ -466,7 +564,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_contains_pin_labels() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         let svg = wiring_svg(&cfg);
         assert!(svg.contains("GPIO21"), "missing SDA pin");
         assert!(svg.contains("GPIO22"), "missing SCL pin");
@@ -478,7 +576,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_contains_animation_css() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         let svg = wiring_svg(&cfg);
         assert!(svg.contains("wiring-flow"), "missing CSS animation name");
         assert!(svg.contains("stroke-dasharray"), "missing dash array");
@@ -489,7 +587,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_is_valid_svg_element() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         let svg = wiring_svg(&cfg);
         assert!(svg.starts_with("<svg "));
         assert!(svg.ends_with("</svg>"));
@@ -498,8 +596,10 @@ mod tests {
     #[test]
     fn wiring_svg_contains_profile_label() {
         use crate::wiring_config::SensorProfile;
-        let cfg =
-            WiringConfig::from_board_with_sensors(BoardProfile::OriginalEsp32, SensorProfile::Full);
+        let cfg = WiringConfig::from_board_with_sensors(
+            BoardProfile::ORIGINAL_ESP32,
+            SensorProfile::Full,
+        );
         let svg = wiring_svg(&cfg);
         assert!(svg.contains("Profile:"), "missing profile indicator");
         assert!(svg.contains("Full"), "missing profile name");
@@ -508,10 +608,12 @@ mod tests {
     #[test]
     fn wiring_svg_profile_label_changes_with_profile() {
         use crate::wiring_config::SensorProfile;
-        let full =
-            WiringConfig::from_board_with_sensors(BoardProfile::OriginalEsp32, SensorProfile::Full);
+        let full = WiringConfig::from_board_with_sensors(
+            BoardProfile::ORIGINAL_ESP32,
+            SensorProfile::Full,
+        );
         let climate = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::ClimateStation,
         );
         let svg_full = wiring_svg(&full);
@@ -522,7 +624,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_omits_redundant_device_pin_labels() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         let svg = wiring_svg(&cfg);
         assert!(
             !svg.contains(r#"class="dev-pin""#),
@@ -535,7 +637,7 @@ mod tests {
         use crate::wiring_config::SensorProfile;
 
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::Minimal,
         );
         let svg = wiring_svg(&cfg);
@@ -555,7 +657,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_renders_device_pin_labels_when_enabled() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32).with_bus_labels(true);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32).with_bus_labels(true);
         let svg = wiring_svg(&cfg);
         assert!(svg.contains(r#"class="dev-pin""#));
         assert!(svg.contains(">VCC<"));
@@ -566,7 +668,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_places_bus_labels_left_of_device_boxes_when_enabled() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32).with_bus_labels(true);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32).with_bus_labels(true);
         let svg = wiring_svg(&cfg);
         assert!(
             svg.contains(r#"text-anchor="end" class="dev-pin""#),
@@ -580,7 +682,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_contains_vcc_gnd_dots() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         let svg = wiring_svg(&cfg);
         assert!(svg.contains("dot-vcc"), "missing VCC dots");
         assert!(svg.contains("dot-gnd"), "missing GND dots");
@@ -588,7 +690,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_uses_shared_bus_trunks_for_dense_layout() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         let svg = wiring_svg(&cfg);
         assert_eq!(svg.matches(r#"class="w-vcc w-bus-trunk""#).count(), 1);
         assert_eq!(svg.matches(r#"class="w-gnd w-bus-trunk""#).count(), 1);
@@ -601,7 +703,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_keeps_pwm_and_gpio_pin_labels_single_sourced() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         let svg = wiring_svg(&cfg);
         assert_eq!(svg.matches("SRV/GPIO13").count(), 1);
         assert_eq!(svg.matches("MOT/GPIO25").count(), 1);
@@ -613,7 +715,7 @@ mod tests {
 
     #[test]
     fn wiring_svg_expands_height_for_large_device_sets() {
-        let cfg = WiringConfig::from_board(BoardProfile::OriginalEsp32);
+        let cfg = WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32);
         let svg = wiring_svg(&cfg);
         assert!(
             svg.contains(r#"viewBox="0 0 580 714""#),
@@ -626,7 +728,7 @@ mod tests {
         use crate::wiring_config::SensorProfile;
 
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::Minimal,
         );
         let svg = wiring_svg(&cfg);
@@ -641,7 +743,7 @@ mod tests {
         use crate::wiring_config::SensorProfile;
 
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::Minimal,
         );
         let svg = wiring_svg(&cfg);
@@ -659,7 +761,7 @@ mod tests {
         use crate::wiring_config::SensorProfile;
 
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::RobotBase,
         );
         let svg = wiring_svg(&cfg);
@@ -669,9 +771,28 @@ mod tests {
         assert!(!svg.contains("CAM/GPIO0"));
     }
 
+    #[test]
+    fn wiring_svg_draws_board_artwork_and_onboard_parts() {
+        let pico = wiring_svg(&WiringConfig::from_board(BoardProfile::RASPBERRY_PI_PICO));
+        assert!(pico.contains(r#"class="pcb-board" style="fill:#1b5e2b;stroke:#5fb86a""#));
+        assert!(pico.contains(r#"lengthAdjust="spacingAndGlyphs">Raspberry Pi Pico</text>"#));
+        assert!(pico.contains(">RP2040</text>"));
+        assert!(pico.contains("Onboard: LED, RT6150"));
+
+        let stick = wiring_svg(&WiringConfig::from_board(BoardProfile::M5STICKC));
+        assert!(stick.contains(r#"class="pcb-board" style="fill:#3a2414;stroke:#e07a2e""#));
+        assert!(stick.contains(r#"class="pcb-lcd""#));
+        assert!(stick.contains("Onboard: MPU6886 0x68, AXP192 0x34, BM8563 0x51"));
+        assert!(stick.contains("SDA/GPIO32"));
+
+        let esp = wiring_svg(&WiringConfig::from_board(BoardProfile::ORIGINAL_ESP32));
+        assert!(esp.contains(r#"class="pcb-board"><title>ESP32: dual Xtensa LX6"#));
+        assert!(!esp.contains("onboard-lbl"));
+    }
+
     #[test]
     fn wiring_svg_hides_nano_camera_placeholder_when_unsupported() {
-        let cfg = WiringConfig::from_board(BoardProfile::ArduinoNano);
+        let cfg = WiringConfig::from_board(BoardProfile::ARDUINO_NANO);
         let svg = wiring_svg(&cfg);
 
         assert!(!svg.contains("CAM/N/A"));
@@ -683,7 +804,7 @@ mod tests {
         use crate::wiring_config::SensorProfile;
 
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::Minimal,
         );
         let svg = wiring_svg(&cfg);
@@ -698,7 +819,7 @@ mod tests {
         use crate::wiring_config::SensorProfile;
 
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::Minimal,
         );
         let svg = wiring_svg(&cfg);
@@ -714,7 +835,7 @@ mod tests {
         use crate::wiring_config::SensorProfile;
 
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::RobotBase,
         );
         let svg = wiring_svg(&cfg);
@@ -728,7 +849,7 @@ mod tests {
         use crate::wiring_config::SensorProfile;
 
         let cfg = WiringConfig::from_board_with_sensors(
-            BoardProfile::OriginalEsp32,
+            BoardProfile::ORIGINAL_ESP32,
             SensorProfile::RobotBase,
         );
         let svg = wiring_svg(&cfg);
@@ -0,0 +1,121 @@
+//! Board setup for the original ESP32 (robot profile).
+//!
+//! Generated by `wiring-check --export board-setup` from the wiring the
+//! simulator runs. Change the wiring project and regenerate instead of
+//! editing this file.
+//!
+//! | Signal | Pin | Device |
+//! |--------|-----|--------|
+//! | PWM | GPIO18 | Servo |
+//! | ENA | GPIO27 | L298N |
+//! | IN1 | GPIO25 | L298N |
+//! | IN2 | GPIO26 | L298N |
+//! | ENB | GPIO14 | L298N |
+//! | IN3 | GPIO32 | L298N |
+//! | IN4 | GPIO33 | L298N |
+#![allow(dead_code)]
+
+use esp_hal::gpio::{Level, Output, OutputConfig};
+use esp_hal::ledc::channel::{self, ChannelConfig, ChannelIFace};
+use esp_hal::ledc::timer::{self, TimerConfig, TimerIFace};
+use esp_hal::ledc::{LSGlobalClkSource, Ledc, LowSpeed};
+use esp_hal::peripherals::Peripherals;
+use esp_hal::time::Rate;
+use platform_esp32::gpio::Esp32OutputPin;
+use platform_esp32::pwm::Esp32PwmOutput;
+use platform_esp32::types::{Esp32L298nChannel, Esp32L298nDualDriverSimple, Esp32ServoDriver};
+
+/// GPIO18 — Servo PWM.
+pub const SERVO_PWM_GPIO: u8 = 18;
+/// GPIO27 — L298N ENA.
+pub const MOTOR_ENA_GPIO: u8 = 27;
+/// GPIO25 — L298N IN1.
+pub const MOTOR_IN1_GPIO: u8 = 25;
+/// GPIO26 — L298N IN2.
+pub const MOTOR_IN2_GPIO: u8 = 26;
+/// GPIO14 — L298N ENB.
+pub const MOTOR_ENB_GPIO: u8 = 14;
+/// GPIO32 — L298N IN3.
+pub const MOTOR_IN3_GPIO: u8 = 32;
+/// GPIO33 — L298N IN4.
+pub const MOTOR_IN4_GPIO: u8 = 33;
+/// Servo PWM frequency (LEDC timer 0, 14-bit duty).
+pub const SERVO_PWM_HZ: u32 = 50;
+/// L298N enable PWM frequency (LEDC timer 1, 8-bit duty).
+pub const MOTOR_PWM_HZ: u32 = 1_000;
+
+/// LEDC channel borrowing its timer from [`run`].
+pub type BoardPwm<'a> = channel::Channel<'a, LowSpeed>;
+
+/// Every device on the wiring, ready to use.
+pub struct Board<'a> {
+    pub servo: Esp32ServoDriver<BoardPwm<'a>>,
+    pub motors: Esp32L298nDualDriverSimple<Output<'static>, BoardPwm<'a>>,
+}
+
+/// Take the peripherals, set up every device and hand them to `app`.
+///
+/// The LEDC timers live on this stack frame, which never returns,
+/// so the drivers in [`Board`] can borrow them.
+pub fn run(peripherals: Peripherals, app: impl FnOnce(Board<'_>) -> !) -> ! {
+    let mut ledc = Ledc::new(peripherals.LEDC);
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+    let mut servo_timer = ledc.timer::<LowSpeed>(timer::Number::Timer0);
+    servo_timer
+        .configure(TimerConfig {
+            duty: timer::config::Duty::Duty14Bit,
+            clock_source: timer::LSClockSource::APBClk,
+            frequency: Rate::from_hz(SERVO_PWM_HZ),
+        })
+        .unwrap();
+    let mut servo_pwm = ledc.channel(channel::Number::Channel0, peripherals.GPIO18);
+    servo_pwm
+        .configure(ChannelConfig {
+            timer: &servo_timer,
+            duty_pct: 0,
+            pin_config: channel::config::PinConfig::PushPull,
+        })
+        .unwrap();
+    let servo = Esp32ServoDriver::new(Esp32PwmOutput::new(servo_pwm));
+    let mut motor_timer = ledc.timer::<LowSpeed>(timer::Number::Timer1);
+    motor_timer
+        .configure(TimerConfig {
+            duty: timer::config::Duty::Duty8Bit,
+            clock_source: timer::LSClockSource::APBClk,
+            frequency: Rate::from_hz(MOTOR_PWM_HZ),
+        })
+        .unwrap();
+    let mut motor_ena = ledc.channel(channel::Number::Channel1, peripherals.GPIO27);
+    motor_ena
+        .configure(ChannelConfig {
+            timer: &motor_timer,
+            duty_pct: 0,
+            pin_config: channel::config::PinConfig::PushPull,
+        })
+        .unwrap();
+    let mut motor_enb = ledc.channel(channel::Number::Channel2, peripherals.GPIO14);
+    motor_enb
+        .configure(ChannelConfig {
+            timer: &motor_timer,
+            duty_pct: 0,
+            pin_config: channel::config::PinConfig::PushPull,
+        })
+        .unwrap();
+    let motor_in1 = Output::new(peripherals.GPIO25, Level::Low, OutputConfig::default());
+    let motor_in2 = Output::new(peripherals.GPIO26, Level::Low, OutputConfig::default());
+    let motor_in3 = Output::new(peripherals.GPIO32, Level::Low, OutputConfig::default());
+    let motor_in4 = Output::new(peripherals.GPIO33, Level::Low, OutputConfig::default());
+    let motors = Esp32L298nDualDriverSimple::new(
+        Esp32L298nChannel::new(
+            Esp32OutputPin::new(motor_in1),
+            Esp32OutputPin::new(motor_in2),
+            Esp32PwmOutput::new(motor_ena),
+        ),
+        Esp32L298nChannel::new(
+            Esp32OutputPin::new(motor_in3),
+            Esp32OutputPin::new(motor_in4),
+            Esp32PwmOutput::new(motor_enb),
+        ),
+    );
+    app(Board { servo, motors })
+}
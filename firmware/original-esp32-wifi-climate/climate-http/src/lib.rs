@@ -0,0 +1,837 @@
+//! wifi-climate ファームウェアの HTTP 部分 (ソケット非依存)。
+//!
+//! - [`PostClient`] は測定値 POST の状態機械。接続・送受信は呼び出し側 (smoltcp や
+//!   `std::net`) が行い、結果をこの型へ通知する。リトライと指数バックオフもここで持つ
+//! - [`ResponseParser`] は `Content-Length` / `Transfer-Encoding: chunked` / 切断までの
+//!   本文を扱う逐次パーサ。本文は読み捨てる
+//! - [`route_admin_request`] は管理ポート (`POST /switch`) のリクエスト判定
+
+#![no_std]
+
+use core::fmt;
+use core::str;
+
+/// 応答行・ヘッダ行・チャンクサイズ行を保持する長さ。超えた分は読み捨てる。
+const MAX_LINE_LEN: usize = 96;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HttpError {
+    /// リクエストがバッファに収まらない
+    BufferTooSmall,
+    /// 応答が HTTP として解釈できない
+    Malformed,
+    /// 応答の途中で切断された
+    Incomplete,
+    /// 接続・送受信の失敗
+    Transport,
+    /// 2xx 以外のステータス
+    Status(u16),
+}
+
+impl HttpError {
+    /// 再送して成功する見込みがあるか。5xx / 408 / 429 と通信断のみ再送する。
+    pub fn is_retryable(self) -> bool {
+        match self {
+            Self::Transport | Self::Incomplete => true,
+            Self::Status(code) => code >= 500 || code == 408 || code == 429,
+            Self::BufferTooSmall | Self::Malformed => false,
+        }
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BufferTooSmall => write!(f, "request does not fit in buffer"),
+            Self::Malformed => write!(f, "malformed HTTP response"),
+            Self::Incomplete => write!(f, "connection closed mid-response"),
+            Self::Transport => write!(f, "transport error"),
+            Self::Status(code) => write!(f, "unexpected status {code}"),
+        }
+    }
+}
+
+// ─── リクエスト構築 ────────────────────────────────────────────────────────────
+
+struct Cursor<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl Cursor<'_> {
+    fn push(&mut self, bytes: &[u8]) -> Result<(), HttpError> {
+        let end = self.pos + bytes.len();
+        let target = self
+            .buf
+            .get_mut(self.pos..end)
+            .ok_or(HttpError::BufferTooSmall)?;
+        target.copy_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+}
+
+impl fmt::Write for Cursor<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+/// `POST <path>` リクエストを `buf` に書き出し、長さを返す。
+///
+/// 1 リクエスト 1 接続 (`Connection: close`) で送る。
+pub fn build_post(
+    buf: &mut [u8],
+    host: &str,
+    path: &str,
+    content_type: &str,
+    body: &[u8],
+) -> Result<usize, HttpError> {
+    use fmt::Write as _;
+
+    let mut cursor = Cursor { buf, pos: 0 };
+    write!(
+        cursor,
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )
+    .map_err(|_| HttpError::BufferTooSmall)?;
+    cursor.push(body)?;
+    Ok(cursor.pos)
+}
+
+/// ヘッダ終端 (`\r\n\r\n`) の直後の位置。
+pub fn header_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n").map(|p| p + 4)
+}
+
+// ─── 応答パーサ ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ParseState {
+    StatusLine,
+    Headers,
+    Body { remaining: usize },
+    ChunkSize,
+    ChunkData { remaining: usize },
+    ChunkDataEnd,
+    Trailers,
+    UntilClose,
+    Complete,
+}
+
+/// [`ResponseParser::feed`] の結果。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseStatus {
+    /// まだ続きがある
+    Partial,
+    /// 応答を最後まで読んだ。値はステータスコード
+    Complete(u16),
+}
+
+/// HTTP/1.x 応答の逐次パーサ。任意の位置で分割されたバイト列を受け付ける。
+#[derive(Clone, Debug)]
+pub struct ResponseParser {
+    state: ParseState,
+    line: [u8; MAX_LINE_LEN],
+    line_len: usize,
+    status: u16,
+    content_length: Option<usize>,
+    chunked: bool,
+}
+
+impl Default for ResponseParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseParser {
+    pub const fn new() -> Self {
+        Self {
+            state: ParseState::StatusLine,
+            line: [0; MAX_LINE_LEN],
+            line_len: 0,
+            status: 0,
+            content_length: None,
+            chunked: false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// 受信済みのステータスコード (応答行を読む前は `None`)。
+    pub fn status(&self) -> Option<u16> {
+        (self.status != 0).then_some(self.status)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.state == ParseState::Complete
+    }
+
+    pub fn feed(&mut self, mut data: &[u8]) -> Result<ParseStatus, HttpError> {
+        while !data.is_empty() && self.state != ParseState::Complete {
+            match self.state {
+                ParseState::Body { remaining } | ParseState::ChunkData { remaining } => {
+                    let take = remaining.min(data.len());
+                    data = &data[take..];
+                    let remaining = remaining - take;
+                    self.state = match (self.state, remaining) {
+                        (ParseState::Body { .. }, 0) => ParseState::Complete,
+                        (ParseState::Body { .. }, _) => ParseState::Body { remaining },
+                        (_, 0) => ParseState::ChunkDataEnd,
+                        _ => ParseState::ChunkData { remaining },
+                    };
+                }
+                ParseState::UntilClose => data = &[],
+                _ => {
+                    let newline = data.iter().position(|&b| b == b'\n');
+                    let take = newline.map_or(data.len(), |p| p + 1);
+                    let room = MAX_LINE_LEN - self.line_len;
+                    let copy = take.min(room);
+                    self.line[self.line_len..self.line_len + copy].copy_from_slice(&data[..copy]);
+                    self.line_len += copy;
+                    data = &data[take..];
+                    if newline.is_some() {
+                        let len = self.line_len;
+                        self.line_len = 0;
+                        let line = self.line;
+                        self.on_line(trim_line(&line[..len]))?;
+                    }
+                }
+            }
+        }
+        Ok(match self.state {
+            ParseState::Complete => ParseStatus::Complete(self.status),
+            _ => ParseStatus::Partial,
+        })
+    }
+
+    /// 相手が切断したときに呼ぶ。本文が切断で終わる応答ならここで完了する。
+    pub fn finish(&mut self) -> Result<u16, HttpError> {
+        match self.state {
+            ParseState::Complete => Ok(self.status),
+            ParseState::UntilClose => {
+                self.state = ParseState::Complete;
+                Ok(self.status)
+            }
+            _ => Err(HttpError::Incomplete),
+        }
+    }
+
+    fn on_line(&mut self, line: &[u8]) -> Result<(), HttpError> {
+        match self.state {
+            ParseState::StatusLine => {
+                self.status = parse_status_line(line).ok_or(HttpError::Malformed)?;
+                self.content_length = None;
+                self.chunked = false;
+                self.state = ParseState::Headers;
+            }
+            ParseState::Headers if line.is_empty() => self.start_body(),
+            ParseState::Headers => {
+                // 長い行は途中で切り詰めるため、値を見ないヘッダは UTF-8 として検証しない
+                let colon = line
+                    .iter()
+                    .position(|&b| b == b':')
+                    .ok_or(HttpError::Malformed)?;
+                let (name, value) = (&line[..colon], &line[colon + 1..]);
+                let is_content_length = name.eq_ignore_ascii_case(b"content-length");
+                let is_transfer_encoding = name.eq_ignore_ascii_case(b"transfer-encoding");
+                if !is_content_length && !is_transfer_encoding {
+                    return Ok(());
+                }
+                let value = str::from_utf8(value)
+                    .map_err(|_| HttpError::Malformed)?
+                    .trim();
+                if is_content_length {
+                    let length = value.parse().map_err(|_| HttpError::Malformed)?;
+                    if self.content_length.is_some_and(|seen| seen != length) {
+                        return Err(HttpError::Malformed);
+                    }
+                    self.content_length = Some(length);
+                } else {
+                    self.chunked = value
+                        .rsplit(',')
+                        .next()
+                        .is_some_and(|last| last.trim().eq_ignore_ascii_case("chunked"));
+                }
+            }
+            ParseState::ChunkSize => {
+                let size = line.split(|&b| b == b';').next().unwrap_or_default();
+                let size = str::from_utf8(size)
+                    .ok()
+                    .and_then(|s| usize::from_str_radix(s.trim(), 16).ok())
+                    .ok_or(HttpError::Malformed)?;
+                self.state = if size == 0 {
+                    ParseState::Trailers
+                } else {
+                    ParseState::ChunkData { remaining: size }
+                };
+            }
+            ParseState::ChunkDataEnd if line.is_empty() => self.state = ParseState::ChunkSize,
+            ParseState::ChunkDataEnd => return Err(HttpError::Malformed),
+            ParseState::Trailers if line.is_empty() => self.state = ParseState::Complete,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn start_body(&mut self) {
+        self.state = if (100..200).contains(&self.status) {
+            // 100 Continue などの中間応答の後に本来の応答が続く
+            ParseState::StatusLine
+        } else if self.status == 204 || self.status == 304 {
+            ParseState::Complete
+        } else if self.chunked {
+            ParseState::ChunkSize
+        } else {
+            match self.content_length {
+                Some(0) => ParseState::Complete,
+                Some(remaining) => ParseState::Body { remaining },
+                None => ParseState::UntilClose,
+            }
+        };
+    }
+}
+
+fn trim_line(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+fn parse_status_line(line: &[u8]) -> Option<u16> {
+    let text = str::from_utf8(line).ok()?;
+    let mut parts = text.splitn(3, ' ');
+    if !parts.next()?.starts_with("HTTP/1.") {
+        return None;
+    }
+    let code = parts.next()?;
+    if code.len() != 3 {
+        return None;
+    }
+    code.parse().ok().filter(|code| (100..600).contains(code))
+}
+
+// ─── POST 状態機械 ─────────────────────────────────────────────────────────────
+
+/// 再送の回数と間隔。`attempt` 回目の失敗後は `base_delay_ms * 2^(attempt-1)` 待つ。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// 初回を含む最大試行回数
+    pub max_attempts: u8,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 1_000,
+            max_delay_ms: 8_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn delay_after(&self, attempt: u8) -> u64 {
+        let shift = u32::from(attempt.saturating_sub(1)).min(32);
+        self.base_delay_ms
+            .saturating_mul(1u64 << shift)
+            .min(self.max_delay_ms)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HttpState {
+    Idle,
+    /// `retry_at_ms` を過ぎたら接続を開始する
+    Connecting {
+        retry_at_ms: u64,
+    },
+    Sending,
+    Receiving,
+    Done,
+}
+
+/// 1 件の POST の結果。成功時は 2xx のステータスコード。
+pub type PostOutcome = Result<u16, HttpError>;
+
+/// リクエストを `N` バイトのバッファに保持し、再送時も作り直さずに送る。
+pub struct PostClient<const N: usize> {
+    state: HttpState,
+    policy: RetryPolicy,
+    request: [u8; N],
+    request_len: usize,
+    sent: usize,
+    attempt: u8,
+    parser: ResponseParser,
+    outcome: Option<PostOutcome>,
+}
+
+impl<const N: usize> Default for PostClient<N> {
+    fn default() -> Self {
+        Self::new(RetryPolicy::default())
+    }
+}
+
+impl<const N: usize> PostClient<N> {
+    pub const fn new(policy: RetryPolicy) -> Self {
+        Self {
+            state: HttpState::Idle,
+            policy,
+            request: [0; N],
+            request_len: 0,
+            sent: 0,
+            attempt: 0,
+            parser: ResponseParser::new(),
+            outcome: None,
+        }
+    }
+
+    pub fn state(&self) -> HttpState {
+        self.state
+    }
+
+    /// 現在の試行回数 (1 始まり)。
+    pub fn attempt(&self) -> u8 {
+        self.attempt
+    }
+
+    /// 新しい POST を始める。実行中なら何もせず `false` を返す。
+    pub fn start(
+        &mut self,
+        host: &str,
+        path: &str,
+        content_type: &str,
+        body: &[u8],
+        now_ms: u64,
+    ) -> Result<bool, HttpError> {
+        if !matches!(self.state, HttpState::Idle) {
+            return Ok(false);
+        }
+        self.request_len = build_post(&mut self.request, host, path, content_type, body)?;
+        self.attempt = 1;
+        self.outcome = None;
+        self.begin_attempt(now_ms);
+        Ok(true)
+    }
+
+    /// 接続を開始すべきタイミングか。
+    pub fn should_connect(&self, now_ms: u64) -> bool {
+        matches!(self.state, HttpState::Connecting { retry_at_ms } if now_ms >= retry_at_ms)
+    }
+
+    /// 接続を開始した (smoltcp では `connect()` が `Ok` を返した) ことを通知する。
+    pub fn on_connected(&mut self) {
+        if matches!(self.state, HttpState::Connecting { .. }) {
+            self.state = HttpState::Sending;
+        }
+    }
+
+    /// まだ送っていないリクエストの残り。
+    pub fn pending(&self) -> &[u8] {
+        match self.state {
+            HttpState::Sending => &self.request[self.sent..self.request_len],
+            _ => &[],
+        }
+    }
+
+    pub fn on_sent(&mut self, n: usize) {
+        if self.state == HttpState::Sending {
+            self.sent = (self.sent + n).min(self.request_len);
+            if self.sent == self.request_len {
+                self.state = HttpState::Receiving;
+            }
+        }
+    }
+
+    pub fn on_received(&mut self, data: &[u8], now_ms: u64) {
+        if self.state != HttpState::Receiving {
+            return;
+        }
+        match self.parser.feed(data) {
+            Ok(ParseStatus::Complete(status)) => self.on_status(status, now_ms),
+            Ok(ParseStatus::Partial) => {}
+            Err(e) => self.fail(e, now_ms),
+        }
+    }
+
+    /// 相手が切断した、またはソケットが閉じたことを通知する。
+    pub fn on_closed(&mut self, now_ms: u64) {
+        match self.state {
+            HttpState::Sending => self.fail(HttpError::Transport, now_ms),
+            HttpState::Receiving => match self.parser.finish() {
+                Ok(status) => self.on_status(status, now_ms),
+                Err(e) => self.fail(e, now_ms),
+            },
+            _ => {}
+        }
+    }
+
+    /// 接続・送受信でエラーが起きたことを通知する。
+    pub fn on_transport_error(&mut self, now_ms: u64) {
+        if matches!(
+            self.state,
+            HttpState::Connecting { .. } | HttpState::Sending | HttpState::Receiving
+        ) {
+            self.fail(HttpError::Transport, now_ms);
+        }
+    }
+
+    /// 完了した POST の結果を取り出し、`Idle` へ戻す。
+    pub fn take_outcome(&mut self) -> Option<PostOutcome> {
+        if self.state != HttpState::Done {
+            return None;
+        }
+        self.state = HttpState::Idle;
+        self.outcome.take()
+    }
+
+    fn begin_attempt(&mut self, retry_at_ms: u64) {
+        self.sent = 0;
+        self.parser.reset();
+        self.state = HttpState::Connecting { retry_at_ms };
+    }
+
+    fn on_status(&mut self, status: u16, now_ms: u64) {
+        if (200..300).contains(&status) {
+            self.outcome = Some(Ok(status));
+            self.state = HttpState::Done;
+        } else {
+            self.fail(HttpError::Status(status), now_ms);
+        }
+    }
+
+    fn fail(&mut self, error: HttpError, now_ms: u64) {
+        if error.is_retryable() && self.attempt < self.policy.max_attempts {
+            let delay = self.policy.delay_after(self.attempt);
+            self.attempt += 1;
+            self.begin_attempt(now_ms.saturating_add(delay));
+        } else {
+            self.outcome = Some(Err(error));
+            self.state = HttpState::Done;
+        }
+    }
+}
+
+// ─── 管理ポート ────────────────────────────────────────────────────────────────
+
+/// 管理ポートへのリクエストに対する応答。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdminResponse {
+    /// 正しいトークン付きの `POST /switch`。updater へ切り替える
+    Switch,
+    Unauthorized,
+    /// `POST /ota` は updater 側で受ける
+    Conflict,
+    BadRequest,
+    /// `GET /metrics`。トークン不要。`as_bytes` はヘッダだけで、本文は呼び出し側が
+    /// 書いてから接続を閉じる (HTTP/1.0 なので close が本文の終端)
+    Metrics,
+}
+
+impl AdminResponse {
+    pub fn as_bytes(self) -> &'static [u8] {
+        match self {
+            Self::Switch => {
+                b"HTTP/1.0 200 OK\r\nContent-Length: 20\r\n\r\nswitching to updater"
+            }
+            Self::Unauthorized => {
+                b"HTTP/1.0 401 Unauthorized\r\nContent-Length: 12\r\n\r\nunauthorized"
+            }
+            Self::Conflict => {
+                b"HTTP/1.0 409 Conflict\r\nContent-Length: 33\r\n\r\nPOST /switch first, then use /ota"
+            }
+            Self::BadRequest => b"HTTP/1.0 400 Bad Request\r\nContent-Length: 0\r\n\r\n",
+            Self::Metrics => {
+                b"HTTP/1.0 200 OK\r\nContent-Type: application/openmetrics-text; version=1.0.0; charset=utf-8\r\n\r\n"
+            }
+        }
+    }
+}
+
+fn request_line_is(header: &[u8], method: &str, path: &str) -> bool {
+    let Ok(text) = str::from_utf8(header) else {
+        return false;
+    };
+    let mut parts = text.lines().next().unwrap_or("").split(' ');
+    parts.next() == Some(method)
+        && parts.next() == Some(path)
+        && parts
+            .next()
+            .is_some_and(|version| version.starts_with("HTTP/1."))
+}
+
+pub fn is_ota_request(header: &[u8]) -> bool {
+    request_line_is(header, "POST", "/ota")
+}
+
+pub fn is_switch_request(header: &[u8]) -> bool {
+    request_line_is(header, "POST", "/switch")
+}
+
+pub fn is_metrics_request(header: &[u8]) -> bool {
+    request_line_is(header, "GET", "/metrics")
+}
+
+/// リクエストヘッダに `X-OTA-Token: <token>` が含まれるか検証する。
+/// updater (original-esp32-ota-bringup) の認可方式と揃えている。
+/// `token` が空の場合は fail-closed で常に false。
+pub fn header_has_valid_token(header: &[u8], token: &str) -> bool {
+    if token.is_empty() {
+        return false;
+    }
+    let Ok(text) = str::from_utf8(header) else {
+        return false;
+    };
+    text.lines().any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("x-ota-token") && value.trim() == token
+        })
+    })
+}
+
+/// ヘッダ全体 (終端の空行まで) を受け取り、返す応答を決める。
+pub fn route_admin_request(header: &[u8], token: &str) -> AdminResponse {
+    if is_switch_request(header) {
+        if header_has_valid_token(header, token) {
+            AdminResponse::Switch
+        } else {
+            AdminResponse::Unauthorized
+        }
+    } else if is_ota_request(header) {
+        AdminResponse::Conflict
+    } else if is_metrics_request(header) {
+        AdminResponse::Metrics
+    } else {
+        AdminResponse::BadRequest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_split(response: &[u8], chunk: usize) -> Result<ParseStatus, HttpError> {
+        let mut parser = ResponseParser::new();
+        let mut status = ParseStatus::Partial;
+        for part in response.chunks(chunk) {
+            status = parser.feed(part)?;
+        }
+        Ok(status)
+    }
+
+    #[test]
+    fn build_post_writes_headers_and_body() {
+        let mut buf = [0u8; 256];
+        let len = build_post(
+            &mut buf,
+            "192.168.1.10",
+            "/api/sensors/reading",
+            "application/json",
+            br#"{"t":1}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            &buf[..len],
+            b"POST /api/sensors/reading HTTP/1.1\r\nHost: 192.168.1.10\r\nContent-Type: application/json\r\nContent-Length: 7\r\nConnection: close\r\n\r\n{\"t\":1}"
+        );
+        assert_eq!(
+            build_post(&mut buf[..40], "h", "/", "text/plain", b"x"),
+            Err(HttpError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn parses_content_length_body_split_at_every_byte() {
+        let response = b"HTTP/1.1 201 Created\r\nContent-Length: 5\r\n\r\nhello";
+        for chunk in 1..response.len() {
+            assert_eq!(
+                parse_split(response, chunk),
+                Ok(ParseStatus::Complete(201)),
+                "chunk size {chunk}"
+            );
+        }
+        assert_eq!(
+            parse_split(&response[..response.len() - 1], 64),
+            Ok(ParseStatus::Partial)
+        );
+    }
+
+    #[test]
+    fn parses_chunked_body_with_extensions_and_trailers() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4;ext=1\r\nWiki\r\n5\r\npedia\r\n0\r\nX-Trailer: 1\r\n\r\n";
+        for chunk in 1..response.len() {
+            assert_eq!(parse_split(response, chunk), Ok(ParseStatus::Complete(200)));
+        }
+        let mut parser = ResponseParser::new();
+        parser.feed(&response[..response.len() - 2]).unwrap();
+        assert_eq!(parser.finish(), Err(HttpError::Incomplete));
+    }
+
+    #[test]
+    fn skips_interim_responses_and_bodyless_statuses() {
+        assert_eq!(
+            parse_split(
+                b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 204 No Content\r\n\r\n",
+                7
+            ),
+            Ok(ParseStatus::Complete(204))
+        );
+    }
+
+    #[test]
+    fn body_without_length_ends_at_close() {
+        let mut parser = ResponseParser::new();
+        assert_eq!(
+            parser.feed(b"HTTP/1.0 200 OK\r\n\r\nok"),
+            Ok(ParseStatus::Partial)
+        );
+        assert_eq!(parser.finish(), Ok(200));
+    }
+
+    #[test]
+    fn rejects_malformed_responses() {
+        for response in [
+            &b"SMTP 220 ready\r\n"[..],
+            b"HTTP/1.1 20 OK\r\n",
+            b"HTTP/1.1 200 OK\r\nno-colon\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: x\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\nContent-Length: 2\r\n",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n1\r\nab\r\n",
+        ] {
+            assert_eq!(
+                parse_split(response, 64),
+                Err(HttpError::Malformed),
+                "{:?}",
+                str::from_utf8(response)
+            );
+        }
+    }
+
+    #[test]
+    fn long_header_lines_are_truncated_not_rejected() {
+        let mut parser = ResponseParser::new();
+        parser.feed(b"HTTP/1.1 200 OK\r\nSet-Cookie: ").unwrap();
+        for _ in 0..20 {
+            parser.feed("0123456789温度".as_bytes()).unwrap();
+        }
+        assert_eq!(
+            parser.feed(b"\r\nContent-Length: 0\r\n\r\n"),
+            Ok(ParseStatus::Complete(200))
+        );
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_cap() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay_after(1), 1_000);
+        assert_eq!(policy.delay_after(2), 2_000);
+        assert_eq!(policy.delay_after(4), 8_000);
+        assert_eq!(policy.delay_after(200), 8_000);
+    }
+
+    #[test]
+    fn client_retries_server_errors_then_succeeds() {
+        let mut client: PostClient<256> = PostClient::default();
+        assert!(client.start("h", "/r", "text/plain", b"x", 0).unwrap());
+        assert!(!client.start("h", "/r", "text/plain", b"y", 0).unwrap());
+
+        assert!(client.should_connect(0));
+        client.on_connected();
+        let len = client.pending().len();
+        client.on_sent(len - 1);
+        assert_eq!(client.state(), HttpState::Sending);
+        client.on_sent(1);
+        client.on_received(b"HTTP/1.1 503 Busy\r\nContent-Length: 0\r\n\r\n", 100);
+        assert_eq!(client.state(), HttpState::Connecting { retry_at_ms: 1_100 });
+        assert!(!client.should_connect(1_099));
+        assert_eq!(client.take_outcome(), None);
+
+        client.on_connected();
+        client.on_transport_error(1_200);
+        assert_eq!(client.attempt(), 3);
+
+        client.on_connected();
+        assert_eq!(client.pending().len(), len);
+        client.on_sent(len);
+        client.on_received(b"HTTP/1.1 201 Created\r\n\r\n", 5_000);
+        client.on_closed(5_000);
+        assert_eq!(client.take_outcome(), Some(Ok(201)));
+        assert_eq!(client.state(), HttpState::Idle);
+    }
+
+    #[test]
+    fn client_gives_up_on_client_errors_and_after_max_attempts() {
+        let mut client: PostClient<256> = PostClient::default();
+        client.start("h", "/r", "text/plain", b"x", 0).unwrap();
+        client.on_connected();
+        client.on_sent(usize::MAX);
+        client.on_received(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n", 0);
+        assert_eq!(client.take_outcome(), Some(Err(HttpError::Status(400))));
+
+        client.start("h", "/r", "text/plain", b"x", 0).unwrap();
+        for _ in 0..3 {
+            client.on_connected();
+            client.on_closed(0);
+        }
+        assert_eq!(client.take_outcome(), Some(Err(HttpError::Transport)));
+    }
+
+    #[test]
+    fn admin_routing_requires_token_and_exact_path() {
+        let with_token = b"POST /switch HTTP/1.1\r\nx-ota-token:  secret \r\n\r\n";
+        let without = b"POST /switch HTTP/1.1\r\n\r\n";
+        assert_eq!(
+            route_admin_request(with_token, "secret"),
+            AdminResponse::Switch
+        );
+        assert_eq!(
+            route_admin_request(with_token, "other"),
+            AdminResponse::Unauthorized
+        );
+        assert_eq!(
+            route_admin_request(with_token, ""),
+            AdminResponse::Unauthorized
+        );
+        assert_eq!(
+            route_admin_request(without, "secret"),
+            AdminResponse::Unauthorized
+        );
+        assert_eq!(
+            route_admin_request(b"POST /ota HTTP/1.0\r\n\r\n", "secret"),
+            AdminResponse::Conflict
+        );
+        assert_eq!(
+            route_admin_request(b"POST /switchx HTTP/1.1\r\n\r\n", "secret"),
+            AdminResponse::BadRequest
+        );
+        assert_eq!(
+            route_admin_request(b"GET / HTTP/1.1\r\n\r\n", "secret"),
+            AdminResponse::BadRequest
+        );
+    }
+
+    #[test]
+    fn metrics_route_needs_no_token() {
+        let request = b"GET /metrics HTTP/1.1\r\nAccept: application/openmetrics-text\r\n\r\n";
+        assert_eq!(route_admin_request(request, ""), AdminResponse::Metrics);
+        assert_eq!(
+            route_admin_request(b"POST /metrics HTTP/1.1\r\n\r\n", "secret"),
+            AdminResponse::BadRequest
+        );
+        let head = AdminResponse::Metrics.as_bytes();
+        assert!(head.starts_with(b"HTTP/1.0 200 OK\r\n"));
+        assert!(head.ends_with(b"\r\n\r\n"));
+    }
+
+    #[test]
+    fn header_end_finds_body_start() {
+        assert_eq!(header_end(b"GET / HTTP/1.1\r\n\r\nbody"), Some(18));
+        assert_eq!(header_end(b"GET / HTTP/1.1\r\n"), None);
+    }
+}
@@ -0,0 +1,211 @@
+//! 127.0.0.1 上の代替 HTTP サーバに対して `PostClient` を `std::net` で動かす。
+//! ファームウェアの smoltcp ループと同じ順序でイベントを通知する。
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use climate_http::{HttpError, HttpState, PostClient, PostOutcome, RetryPolicy};
+
+const BODY: &[u8] = br#"{"device_id":"esp32-climate-01","temperature":23.5}"#;
+
+/// 接続ごとに `responses` を順に返し、受け取ったリクエストを送り返すサーバ。
+/// 応答は `split` バイトずつ送り、パーサが分割に耐えることも確かめる。
+fn spawn_server(
+    responses: Vec<&'static [u8]>,
+    split: usize,
+) -> (u16, mpsc::Receiver<Vec<u8>>, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            tx.send(read_request(&mut stream)).unwrap();
+            for part in response.chunks(split) {
+                stream.write_all(part).unwrap();
+                stream.flush().unwrap();
+                thread::sleep(Duration::from_millis(1));
+            }
+            let _ = stream.shutdown(Shutdown::Write);
+        }
+    });
+    (port, rx, handle)
+}
+
+fn read_request(stream: &mut TcpStream) -> Vec<u8> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 64];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        assert!(n > 0, "client closed before sending the full request");
+        request.extend_from_slice(&buf[..n]);
+        if let Some(end) = climate_http::header_end(&request) {
+            let head = String::from_utf8_lossy(&request[..end]).to_ascii_lowercase();
+            let length: usize = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .map_or(0, |v| v.trim().parse().unwrap());
+            if request.len() >= end + length {
+                return request;
+            }
+        }
+    }
+}
+
+/// ファームウェアのメインループ相当。再送待ちも実時間で進める。
+fn drive(client: &mut PostClient<512>, port: u16) -> PostOutcome {
+    let started = Instant::now();
+    let now_ms = || started.elapsed().as_millis() as u64;
+    let mut stream: Option<TcpStream> = None;
+    loop {
+        assert!(started.elapsed() < Duration::from_secs(10), "client hung");
+        match client.state() {
+            HttpState::Connecting { .. } => {
+                if client.should_connect(now_ms()) {
+                    match TcpStream::connect(("127.0.0.1", port)) {
+                        Ok(s) => {
+                            s.set_read_timeout(Some(Duration::from_millis(20))).unwrap();
+                            stream = Some(s);
+                            client.on_connected();
+                        }
+                        Err(_) => client.on_transport_error(now_ms()),
+                    }
+                } else {
+                    thread::sleep(Duration::from_millis(1));
+                }
+            }
+            HttpState::Sending => {
+                let s = stream.as_mut().unwrap();
+                // 1 回に 16 バイトまで: smoltcp の送信バッファが埋まる状況を真似る
+                let pending = client.pending();
+                let n = pending.len().min(16);
+                match s.write(&pending[..n]) {
+                    Ok(n) => client.on_sent(n),
+                    Err(_) => client.on_transport_error(now_ms()),
+                }
+            }
+            HttpState::Receiving => {
+                let s = stream.as_mut().unwrap();
+                let mut buf = [0u8; 16];
+                match s.read(&mut buf) {
+                    Ok(0) => {
+                        client.on_closed(now_ms());
+                        stream = None;
+                    }
+                    Ok(n) => client.on_received(&buf[..n], now_ms()),
+                    Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                    Err(_) => client.on_transport_error(now_ms()),
+                }
+                if !matches!(client.state(), HttpState::Receiving) {
+                    stream = None;
+                }
+            }
+            HttpState::Done => return client.take_outcome().unwrap(),
+            HttpState::Idle => unreachable!("client went idle without an outcome"),
+        }
+    }
+}
+
+fn fast_policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 3,
+        base_delay_ms: 10,
+        max_delay_ms: 40,
+    }
+}
+
+fn start(client: &mut PostClient<512>, port: u16) {
+    let host = format!("127.0.0.1:{port}");
+    assert!(client
+        .start(&host, "/api/sensors/reading", "application/json", BODY, 0)
+        .unwrap());
+}
+
+#[test]
+fn retries_after_server_error_and_reads_chunked_success() {
+    let (port, requests, server) = spawn_server(
+        vec![
+            b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 4\r\n\r\nbusy",
+            b"HTTP/1.1 201 Created\r\nTransfer-Encoding: chunked\r\nContent-Type: application/json\r\n\r\n7\r\n{\"id\":1\r\n1\r\n}\r\n0\r\n\r\n",
+        ],
+        5,
+    );
+    let mut client = PostClient::new(fast_policy());
+    start(&mut client, port);
+
+    assert_eq!(drive(&mut client, port), Ok(201));
+    assert_eq!(client.attempt(), 2);
+    server.join().unwrap();
+
+    let expected = [
+        format!(
+            "POST /api/sensors/reading HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            BODY.len()
+        )
+        .into_bytes(),
+        BODY.to_vec(),
+    ]
+    .concat();
+    let received: Vec<_> = requests.iter().collect();
+    assert_eq!(received, vec![expected.clone(), expected]);
+}
+
+#[test]
+fn body_delimited_by_close_completes_on_eof() {
+    let (port, _requests, server) = spawn_server(vec![b"HTTP/1.0 200 OK\r\n\r\nok"], 3);
+    let mut client = PostClient::new(fast_policy());
+    start(&mut client, port);
+
+    assert_eq!(drive(&mut client, port), Ok(200));
+    server.join().unwrap();
+}
+
+#[test]
+fn client_error_is_reported_without_retry() {
+    let (port, requests, server) = spawn_server(
+        vec![b"HTTP/1.1 422 Unprocessable Entity\r\nContent-Length: 0\r\n\r\n"],
+        64,
+    );
+    let mut client = PostClient::new(fast_policy());
+    start(&mut client, port);
+
+    assert_eq!(drive(&mut client, port), Err(HttpError::Status(422)));
+    server.join().unwrap();
+    assert_eq!(requests.iter().count(), 1);
+}
+
+#[test]
+fn gives_up_after_max_attempts_of_truncated_responses() {
+    let (port, requests, server) = spawn_server(
+        vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort",
+        ],
+        64,
+    );
+    let mut client = PostClient::new(fast_policy());
+    start(&mut client, port);
+
+    assert_eq!(drive(&mut client, port), Err(HttpError::Incomplete));
+    server.join().unwrap();
+    assert_eq!(requests.iter().count(), 3);
+}
+
+#[test]
+fn refused_connection_is_retried_as_transport_error() {
+    // bind してすぐ閉じたポートへは接続が拒否される
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut client = PostClient::new(fast_policy());
+    start(&mut client, port);
+
+    assert_eq!(drive(&mut client, port), Err(HttpError::Transport));
+    assert_eq!(client.attempt(), 3);
+}
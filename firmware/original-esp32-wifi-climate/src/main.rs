@@ -24,6 +24,10 @@
 //! OTAアップデート(2回目以降): 稼働中ファームに `POST /switch`
 //!   (ヘッダ `X-OTA-Token: <OTA_AUTH_TOKEN>` が必須) を送ると updater へ退避し、
 //!   updater の `POST /ota` で新ファームを受け取ります。
+//!
+//! 同じポートの `GET /metrics` (認証なし) は最後の読み取り値・稼働時間・POST の
+//! 成否を OpenMetrics 形式で返す (`core_app::metrics`)。応答全体が送信バッファ
+//! (`ota_tx`) に収まる前提で、送ったらすぐ接続を閉じる。
 
 #![no_std]
 #![no_main]
@@ -35,7 +39,6 @@ use alloc::vec::Vec;
 use core::cell::RefCell;
 use core::mem::MaybeUninit;
 use core::fmt::Write as FmtWrite;
-use core::str;
 
 use embedded_hal::delay::DelayNs;
 use esp_backtrace as _;
@@ -52,7 +55,13 @@ use esp_wifi::{
     init,
     wifi::{ClientConfiguration, Configuration, WifiDevice},
 };
+use climate_http::{
+    header_end, route_admin_request, AdminResponse, HttpState, PostClient, RetryPolicy,
+};
+use core_app::metrics::{MetricType, MetricsError, MetricsWriter, DEFAULT_NAMESPACE};
+use core_app::telemetry::{FieldValue, TelemetryFrame};
 use hal_api::display::{TextDisplay16x2, TextFrame16x2};
+use hal_api::sensor::EnvReading;
 use heapless::String as HString;
 use platform_esp32::{
     bme280::{BME280_ADDRESS_PRIMARY, BME280_ADDRESS_SECONDARY, Bme280Config, Bme280Sensor},
@@ -80,6 +89,7 @@ const WIFI_SSID: &str = env!("WIFI_SSID");
 const WIFI_PSK: &str = env!("WIFI_PSK");
 const RASPI_IP: &str = env!("RASPI_IP");
 const RASPI_PORT: u16 = 8000;
+const READING_PATH: &str = "/api/sensors/reading";
 const DEVICE_ID: &str = env!("DEVICE_ID");
 /// `POST /switch` を認可するための共有シークレット。updater 側の `OTA_AUTH_TOKEN`
 /// と一致させる。空文字の場合は fail-closed で switch を常に拒否する。
@@ -169,38 +179,6 @@ fn parse_ipv4(s: &str) -> Option<Ipv4Address> {
     Some(Ipv4Address::new(octets[0], octets[1], octets[2], octets[3]))
 }
 
-fn is_valid_ota_request(header: &[u8]) -> bool {
-    str::from_utf8(header)
-        .map(|s| s.lines().next().unwrap_or("").starts_with("POST /ota HTTP/1."))
-        .unwrap_or(false)
-}
-
-fn is_switch_request(header: &[u8]) -> bool {
-    str::from_utf8(header)
-        .map(|s| s.lines().next().unwrap_or("").starts_with("POST /switch HTTP/1."))
-        .unwrap_or(false)
-}
-
-/// リクエストヘッダに `X-OTA-Token: <OTA_AUTH_TOKEN>` が含まれるか検証する。
-/// updater (original-esp32-ota-bringup) の認可方式と揃えている。
-/// `OTA_AUTH_TOKEN` が空の場合は fail-closed で常に false。
-fn header_has_valid_token(header: &[u8]) -> bool {
-    if OTA_AUTH_TOKEN.is_empty() {
-        return false;
-    }
-    let Ok(text) = str::from_utf8(header) else {
-        return false;
-    };
-    for line in text.lines() {
-        if let Some((name, value)) = line.split_once(':') {
-            if name.trim().eq_ignore_ascii_case("x-ota-token") && value.trim() == OTA_AUTH_TOKEN {
-                return true;
-            }
-        }
-    }
-    false
-}
-
 // ─── OTA スロット切替 (otadata 直接書き換え) ──────────────────────────────────
 // esp-ota-nostd の ota_reject は accept 済み (Valid) スロットを拒否できない
 // ため、otadata に seq-1 の Valid エントリを直接書いて前スロット (app0 =
@@ -243,6 +221,37 @@ const BOOT_FAIL_MAGIC_VALUE: u32 = 0x424F_4F54; // "BOOT"
 /// この回数 POST 成功前の再起動が続いたら updater へ退避
 const MAX_UNHEALTHY_BOOTS: u32 = 3;
 
+/// `GET /metrics` の本文を書き出す。読み取り前は読み取り値の gauge を省く。
+fn encode_metrics(
+    out: &mut [u8],
+    reading: Option<EnvReading>,
+    uptime_ms: u64,
+    posts_ok: u32,
+    posts_failed: u32,
+) -> Result<usize, MetricsError> {
+    let mut metrics = MetricsWriter::new(out, DEFAULT_NAMESPACE);
+    let mut frame = TelemetryFrame::new(DEVICE_ID);
+    if let Some(reading) = reading {
+        frame = frame.with_env(reading);
+    }
+    frame.write_metrics(&mut metrics)?;
+    metrics.family("uptime_seconds", MetricType::Gauge, "Seconds since boot")?;
+    metrics.sample(
+        "uptime_seconds",
+        &[("device_id", DEVICE_ID)],
+        FieldValue::Int((uptime_ms / 1000) as i64),
+    )?;
+    metrics.family("http_posts", MetricType::Counter, "Reading POSTs by result")?;
+    for (result, count) in [("ok", posts_ok), ("error", posts_failed)] {
+        metrics.sample(
+            "http_posts",
+            &[("device_id", DEVICE_ID), ("result", result)],
+            FieldValue::Int(count.into()),
+        )?;
+    }
+    metrics.finish()
+}
+
 fn read_boot_fail_count() -> u32 {
     unsafe {
         if core::ptr::read_volatile(core::ptr::addr_of!(BOOT_FAIL_MAGIC)) == BOOT_FAIL_MAGIC_VALUE {
@@ -320,84 +329,6 @@ fn switch_boot_slot(flash: &mut FlashStorage) -> Result<u32, ()> {
     Ok(new_seq)
 }
 
-fn header_end(buf: &[u8]) -> Option<usize> {
-    buf.windows(4)
-        .position(|w| w == b"\r\n\r\n")
-        .map(|p| p + 4)
-}
-
-/// JSONボディを整数演算で構築
-fn build_json(buf: &mut [u8], device_id: &str, temp_cc: i32, hum_cp: u32) -> usize {
-    let mut pos = 0usize;
-
-    fn push(buf: &mut [u8], pos: &mut usize, s: &[u8]) {
-        for &b in s {
-            if *pos < buf.len() { buf[*pos] = b; *pos += 1; }
-        }
-    }
-
-    fn push_u32(buf: &mut [u8], pos: &mut usize, mut n: u32) {
-        let mut tmp = [0u8; 10];
-        let mut len = 0;
-        if n == 0 { push(buf, pos, b"0"); return; }
-        while n > 0 { tmp[len] = b'0' + (n % 10) as u8; n /= 10; len += 1; }
-        tmp[..len].reverse();
-        push(buf, pos, &tmp[..len]);
-    }
-
-    fn push_frac2(buf: &mut [u8], pos: &mut usize, frac: u32) {
-        let d1 = b'0' + (frac / 10) as u8;
-        let d2 = b'0' + (frac % 10) as u8;
-        if *pos < buf.len() { buf[*pos] = d1; *pos += 1; }
-        if *pos < buf.len() { buf[*pos] = d2; *pos += 1; }
-    }
-
-    let t_abs = abs_i32(temp_cc);
-    push(buf, &mut pos, b"{\"device_id\":\"");
-    push(buf, &mut pos, device_id.as_bytes());
-    push(buf, &mut pos, b"\",\"temperature\":");
-    if temp_cc < 0 { push(buf, &mut pos, b"-"); }
-    push_u32(buf, &mut pos, t_abs / 100);
-    push(buf, &mut pos, b".");
-    push_frac2(buf, &mut pos, t_abs % 100);
-    push(buf, &mut pos, b",\"humidity\":");
-    push_u32(buf, &mut pos, hum_cp / 100);
-    push(buf, &mut pos, b".");
-    push_frac2(buf, &mut pos, hum_cp % 100);
-    push(buf, &mut pos, b"}");
-
-    pos
-}
-
-/// HTTP POSTリクエストを構築
-fn build_http_post(buf: &mut [u8], host: &str, json: &[u8]) -> usize {
-    let mut pos = 0usize;
-
-    fn push(buf: &mut [u8], pos: &mut usize, s: &[u8]) {
-        for &b in s {
-            if *pos < buf.len() { buf[*pos] = b; *pos += 1; }
-        }
-    }
-
-    fn push_usize(buf: &mut [u8], pos: &mut usize, mut n: usize) {
-        let mut tmp = [0u8; 10];
-        let mut len = 0;
-        if n == 0 { push(buf, pos, b"0"); return; }
-        while n > 0 { tmp[len] = b'0' + (n % 10) as u8; n /= 10; len += 1; }
-        tmp[..len].reverse();
-        push(buf, pos, &tmp[..len]);
-    }
-
-    push(buf, &mut pos, b"POST /api/sensors/reading HTTP/1.0\r\nHost: ");
-    push(buf, &mut pos, host.as_bytes());
-    push(buf, &mut pos, b"\r\nContent-Type: application/json\r\nContent-Length: ");
-    push_usize(buf, &mut pos, json.len());
-    push(buf, &mut pos, b"\r\nConnection: close\r\n\r\n");
-    push(buf, &mut pos, json);
-
-    pos
-}
-
 /// 温湿度をLCDフレームに描画
 fn make_lcd_frame(temp_cc: i32, hum_cp: u32) -> TextFrame16x2 {
     let t_abs = abs_i32(temp_cc);
@@ -449,16 +380,6 @@ fn wait_for_dhcp(
     }
 }
 
-// ─── HTTP state machine ───────────────────────────────────────────────────────
-#[derive(Clone, Copy)]
-enum HttpState {
-    Idle,
-    Connecting,
-    Sending,
-    Receiving,
-    Done,
-}
-
 // ─── main ──────────────────────────────────────────────────────────────────────
 #[main]
 fn main() -> ! {
@@ -585,7 +506,8 @@ fn main() -> ! {
 
     // ─── TCP + DHCP sockets ───────────────────────────────────────────────────
     let mut ota_rx = [0u8; 1536];
-    let mut ota_tx = [0u8; 512];
+    // GET /metrics の応答 (ヘッダ + 本文) が丸ごと収まる大きさ
+    let mut ota_tx = [0u8; 2048];
     let mut http_rx = [0u8; 512];
     let mut http_tx = [0u8; 1024];
     let ota_sock = TcpSocket::new(
@@ -641,13 +563,16 @@ fn main() -> ! {
 
     // ─── HTTP POST state ──────────────────────────────────────────────────────
     let mut last_post_ms: u64 = 0;
-    let mut http_state = HttpState::Idle;
-    let mut http_req_buf = [0u8; 512];
-    let mut http_req_len = 0usize;
-    let mut http_req_sent = 0usize;
+    // 5xx / 通信断は 1s, 2s 間隔で 3 回まで送り直す
+    let mut http: PostClient<512> = PostClient::new(RetryPolicy::default());
     // 初回 POST 成功で true → 自動フォールバックのカウンタをクリア
     let mut health_confirmed = false;
 
+    // ─── GET /metrics 用 ─────────────────────────────────────────────────────
+    let mut last_reading: Option<EnvReading> = None;
+    let mut posts_ok: u32 = 0;
+    let mut posts_failed: u32 = 0;
+
     println!("[main] ready — posting every {}s", POST_INTERVAL_MS / 1000);
 
     loop {
@@ -691,21 +616,26 @@ fn main() -> ! {
                             hdr_buf.extend_from_slice(&chunk[..n]);
                             if let Some(body_start) = header_end(&hdr_buf) {
                                 let head = &hdr_buf[..body_start];
-                                if is_switch_request(head) {
-                                    if header_has_valid_token(head) {
-                                        s.send_slice(b"HTTP/1.0 200 OK\r\nContent-Length: 20\r\n\r\nswitching to updater").ok();
-                                        switch_requested = true;
-                                    } else {
-                                        // 認可なしの /switch は拒否 (同一 LAN からの
-                                        // 無認証ファーム上書きを防ぐ)。
-                                        s.send_slice(b"HTTP/1.0 401 Unauthorized\r\nContent-Length: 12\r\n\r\nunauthorized").ok();
-                                        s.close();
+                                // 認可なしの /switch は 401 で拒否する (同一 LAN からの
+                                // 無認証ファーム上書きを防ぐ)
+                                let response = route_admin_request(head, OTA_AUTH_TOKEN);
+                                s.send_slice(response.as_bytes()).ok();
+                                if response == AdminResponse::Metrics {
+                                    let mut body = [0u8; 1024];
+                                    match encode_metrics(
+                                        &mut body,
+                                        last_reading,
+                                        now_ms,
+                                        posts_ok,
+                                        posts_failed,
+                                    ) {
+                                        Ok(len) => { s.send_slice(&body[..len]).ok(); }
+                                        Err(e) => println!("[metrics] encode error: {:?}", e),
                                     }
-                                } else if is_valid_ota_request(head) {
-                                    s.send_slice(b"HTTP/1.0 409 Conflict\r\nContent-Length: 33\r\n\r\nPOST /switch first, then use /ota").ok();
-                                    s.close();
+                                }
+                                if response == AdminResponse::Switch {
+                                    switch_requested = true;
                                 } else {
-                                    s.send_slice(b"HTTP/1.0 400 Bad Request\r\nContent-Length: 0\r\n\r\n").ok();
                                     s.close();
                                 }
                                 hdr_buf.clear();
@@ -754,11 +684,12 @@ fn main() -> ! {
         }
 
         // ── センサー読み取り & HTTP POST ─────────────────────────────────────
-        match http_state {
+        match http.state() {
             HttpState::Idle => {
                 if now_ms.saturating_sub(last_post_ms) >= POST_INTERVAL_MS {
                     match hal_api::sensor::EnvSensor::read(&mut sensor) {
                         Ok(reading) => {
+                            last_reading = Some(reading);
                             let temp_cc = reading.temperature_centi_celsius;
                             let hum_cp = reading.humidity_centi_percent;
                             let t_abs = abs_i32(temp_cc);
@@ -775,14 +706,23 @@ fn main() -> ! {
 
                             // HTTPリクエスト構築
                             let mut json_buf = [0u8; 128];
-                            let json_len = build_json(&mut json_buf, DEVICE_ID, temp_cc, hum_cp);
-                            http_req_len = build_http_post(
-                                &mut http_req_buf,
-                                RASPI_IP,
-                                &json_buf[..json_len],
-                            );
-                            http_req_sent = 0;
-                            http_state = HttpState::Connecting;
+                            let started = TelemetryFrame::new(DEVICE_ID)
+                                .with_env(reading)
+                                .encode_json(&mut json_buf)
+                                .map_err(|e| println!("[http] encode error: {:?}", e))
+                                .and_then(|json_len| {
+                                    http.start(
+                                        RASPI_IP,
+                                        READING_PATH,
+                                        "application/json",
+                                        &json_buf[..json_len],
+                                        now_ms,
+                                    )
+                                    .map_err(|e| println!("[http] request error: {}", e))
+                                });
+                            if started.is_err() {
+                                last_post_ms = now_ms;
+                            }
                         }
                         Err(e) => {
                             println!("[sensor] error: {:?}", e);
@@ -792,18 +732,21 @@ fn main() -> ! {
                 }
             }
 
-            HttpState::Connecting => {
+            HttpState::Connecting { .. } => {
                 let s = sockets.get_mut::<TcpSocket>(http_h);
-                if !s.is_open() {
+                if !s.is_open() && http.should_connect(now_ms) {
                     let local_port = 49152u16.wrapping_add((millis() % 16384) as u16);
                     match s.connect(iface.context(), (raspi_ip, RASPI_PORT), local_port) {
                         Ok(()) => {
-                            println!("[http] connecting {}:{}…", RASPI_IP, RASPI_PORT);
-                            http_state = HttpState::Sending;
+                            println!(
+                                "[http] connecting {}:{}… (attempt {})",
+                                RASPI_IP, RASPI_PORT, http.attempt()
+                            );
+                            http.on_connected();
                         }
                         Err(e) => {
                             println!("[http] connect err: {:?}", e);
-                            http_state = HttpState::Done;
+                            http.on_transport_error(now_ms);
                         }
                     }
                 }
@@ -811,55 +754,56 @@ fn main() -> ! {
 
             HttpState::Sending => {
                 let s = sockets.get_mut::<TcpSocket>(http_h);
-                if s.may_send() && http_req_sent < http_req_len {
-                    match s.send_slice(&http_req_buf[http_req_sent..http_req_len]) {
-                        Ok(n) => { http_req_sent += n; }
-                        Err(_) => { s.close(); http_state = HttpState::Done; }
+                if s.may_send() {
+                    match s.send_slice(http.pending()) {
+                        Ok(n) => http.on_sent(n),
+                        Err(_) => { s.abort(); http.on_transport_error(now_ms); }
                     }
-                } else if http_req_sent >= http_req_len {
-                    http_state = HttpState::Receiving;
+                } else if !s.is_open() || s.state() == TcpState::CloseWait {
+                    // 接続が確立しなかった (RST / タイムアウト)
+                    s.abort();
+                    http.on_closed(now_ms);
                 }
             }
 
             HttpState::Receiving => {
                 let s = sockets.get_mut::<TcpSocket>(http_h);
-                if s.may_recv() {
-                    let mut resp = [0u8; 64];
+                if s.can_recv() {
+                    let mut resp = [0u8; 128];
                     match s.recv_slice(&mut resp) {
-                        Ok(n) if n > 0 => {
-                            let status = str::from_utf8(&resp[..n]).unwrap_or("");
-                            if status.contains("201") || status.contains("200") {
-                                println!("[http] POST ok");
-                                if !health_confirmed {
-                                    // 初回 POST 成功 = このファームは健全。
-                                    // 自動フォールバックのカウンタをクリアする
-                                    health_confirmed = true;
-                                    write_boot_fail_count(0);
-                                    println!("[fallback] boot marked healthy");
-                                }
-                            } else {
-                                let end = status.len().min(40);
-                                println!("[http] resp: {}", &status[..end]);
-                            }
-                            s.close();
-                            http_state = HttpState::Done;
-                        }
-                        Ok(_) => {}
-                        Err(_) => { s.close(); http_state = HttpState::Done; }
+                        Ok(n) => http.on_received(&resp[..n], now_ms),
+                        Err(_) => { s.abort(); http.on_transport_error(now_ms); }
                     }
-                } else if s.state() == TcpState::CloseWait {
-                    // Server sent FIN with no (remaining) data: close or we
-                    // stay in Receiving forever and posting stops
+                } else if s.state() == TcpState::CloseWait || !s.is_open() {
+                    // Server sent FIN: body delimited by close, or a truncated
+                    // response that the client will retry
+                    http.on_closed(now_ms);
+                }
+                if http.state() != HttpState::Receiving {
                     s.close();
-                    http_state = HttpState::Done;
-                } else if !s.is_open() {
-                    http_state = HttpState::Done;
                 }
             }
 
             HttpState::Done => {
+                match http.take_outcome() {
+                    Some(Ok(status)) => {
+                        println!("[http] POST ok ({})", status);
+                        posts_ok = posts_ok.wrapping_add(1);
+                        if !health_confirmed {
+                            // 初回 POST 成功 = このファームは健全。
+                            // 自動フォールバックのカウンタをクリアする
+                            health_confirmed = true;
+                            write_boot_fail_count(0);
+                            println!("[fallback] boot marked healthy");
+                        }
+                    }
+                    Some(Err(e)) => {
+                        println!("[http] POST failed after {} attempt(s): {}", http.attempt(), e);
+                        posts_failed = posts_failed.wrapping_add(1);
+                    }
+                    None => {}
+                }
                 last_post_ms = now_ms;
-                http_state = HttpState::Idle;
             }
         }
 